@@ -29,7 +29,7 @@ use std::{
     convert::{From, TryFrom},
     error::Error,
     fmt::{self, Formatter},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -644,6 +644,7 @@ impl From<&mc_transaction_core::Block> for blockchain::Block {
         block.set_cumulative_txo_count(other.cumulative_txo_count);
         block.set_root_element((&other.root_element).into());
         block.set_contents_hash(blockchain::BlockContentsHash::from(&other.contents_hash));
+        block.set_timestamp(other.timestamp);
         block
     }
 }
@@ -667,6 +668,7 @@ impl TryFrom<&blockchain::Block> for mc_transaction_core::Block {
             cumulative_txo_count: value.cumulative_txo_count,
             root_element,
             contents_hash,
+            timestamp: value.timestamp,
         };
         Ok(block)
     }
@@ -714,6 +716,7 @@ impl From<&BlockSignature> for blockchain::BlockSignature {
         let mut dst = blockchain::BlockSignature::new();
         dst.set_signature(external::Ed25519Signature::from(src.signature()));
         dst.set_signer(external::Ed25519Public::from(src.signer()));
+        dst.set_signed_at(src.signed_at());
         dst
     }
 }
@@ -725,7 +728,11 @@ impl TryFrom<&blockchain::BlockSignature> for BlockSignature {
     fn try_from(source: &blockchain::BlockSignature) -> Result<Self, Self::Error> {
         let signature = Ed25519Signature::try_from(source.get_signature())?;
         let signer = Ed25519Public::try_from(source.get_signer())?;
-        Ok(BlockSignature::new(signature, signer))
+        Ok(BlockSignature::new(
+            signature,
+            signer,
+            source.get_signed_at(),
+        ))
     }
 }
 
@@ -740,6 +747,36 @@ pub fn block_num_to_s3block_path(block_index: mc_transaction_core::BlockIndex) -
     path
 }
 
+/// The block index of a path returned by `block_num_to_s3block_path`, or None if `path` is not
+/// such a path.
+pub fn s3block_path_to_block_num(path: &Path) -> Option<mc_transaction_core::BlockIndex> {
+    let filename = path.file_name()?.to_str()?;
+    if filename.len() != 19 || !filename.ends_with(".pb") {
+        return None;
+    }
+    let block_index = u64::from_str_radix(&filename[..16], 16).ok()?;
+    if block_num_to_s3block_path(block_index) == path {
+        Some(block_index)
+    } else {
+        None
+    }
+}
+
+/// Path of the manifest describing the available bundles of archived blocks.
+pub const S3BLOCK_BUNDLES_MANIFEST_PATH: &str = "bundles/manifest.pb";
+
+/// Helper method for getting the suggested path/filename for the gzip-compressed bundle of
+/// `bundle_size` blocks that starts at `first_block_index`.
+pub fn block_bundle_to_s3_path(
+    first_block_index: mc_transaction_core::BlockIndex,
+    bundle_size: u64,
+) -> PathBuf {
+    let mut path = PathBuf::from("bundles");
+    path.push(bundle_size.to_string());
+    path.push(block_num_to_s3block_path(first_block_index).with_extension("pb.gz"));
+    path
+}
+
 #[cfg(test)]
 mod conversion_tests {
     use super::*;
@@ -846,6 +883,7 @@ mod conversion_tests {
             },
             contents_hash: mc_transaction_core::BlockContentsHash::try_from(&[66u8; 32][..])
                 .unwrap(),
+            timestamp: 1_590_000_000,
         };
 
         let block = blockchain::Block::from(&source_block);
@@ -858,6 +896,7 @@ mod conversion_tests {
         assert_eq!(block.get_root_element().get_range().get_to(), 20);
         assert_eq!(block.get_root_element().get_hash().get_data(), &[12u8; 32]);
         assert_eq!(block.get_contents_hash().get_data(), [66u8; 32]);
+        assert_eq!(block.get_timestamp(), 1_590_000_000);
     }
 
     #[test]
@@ -884,6 +923,7 @@ mod conversion_tests {
         source_block.set_index(2);
         source_block.set_root_element(root_element);
         source_block.set_contents_hash(contents_hash);
+        source_block.set_timestamp(1_590_000_000);
 
         let block = mc_transaction_core::Block::try_from(&source_block).unwrap();
         assert_eq!(block.id.as_ref(), [10u8; 32]);
@@ -894,6 +934,7 @@ mod conversion_tests {
         assert_eq!(block.root_element.range.to, 20);
         assert_eq!(block.root_element.hash.as_ref(), &[13u8; 32]);
         assert_eq!(block.contents_hash.as_ref(), [66u8; 32]);
+        assert_eq!(block.timestamp, 1_590_000_000);
     }
 
     #[test]
@@ -913,6 +954,7 @@ mod conversion_tests {
             },
             contents_hash: mc_transaction_core::BlockContentsHash::try_from(&[66u8; 32][..])
                 .unwrap(),
+            timestamp: 1_590_000_000,
         };
 
         // Encode using `protobuf`, decode using `prost`.
@@ -961,6 +1003,7 @@ mod conversion_tests {
         let source_block_signature = mc_transaction_core::BlockSignature::new(
             Ed25519Signature::new([1; 64]),
             (&Ed25519Private::from_random(&mut rng)).into(),
+            31337,
         );
 
         let block_signature = blockchain::BlockSignature::from(&source_block_signature);
@@ -972,6 +1015,10 @@ mod conversion_tests {
             block_signature.get_signer().get_data(),
             source_block_signature.signer().to_bytes()
         );
+        assert_eq!(
+            block_signature.get_signed_at(),
+            source_block_signature.signed_at()
+        );
     }
 
     #[test]
@@ -982,6 +1029,7 @@ mod conversion_tests {
         let expected_block_signature = mc_transaction_core::BlockSignature::new(
             Ed25519Signature::new([1; 64]),
             (&Ed25519Private::from_random(&mut rng)).into(),
+            31337,
         );
 
         let mut source_block_signature = blockchain::BlockSignature::new();
@@ -994,6 +1042,8 @@ mod conversion_tests {
         signer.set_data(expected_block_signature.signer().to_bytes().to_vec());
         source_block_signature.set_signer(signer);
 
+        source_block_signature.set_signed_at(31337);
+
         let block_signature =
             mc_transaction_core::BlockSignature::try_from(&source_block_signature).unwrap();
         assert_eq!(block_signature, expected_block_signature);
@@ -1009,6 +1059,7 @@ mod conversion_tests {
         let source_block_signature = mc_transaction_core::BlockSignature::new(
             Ed25519Signature::new([1; 64]),
             (&Ed25519Private::from_random(&mut rng)).into(),
+            31337,
         );
 
         // Encode using `protobuf`, decode using `prost`.
@@ -1209,4 +1260,35 @@ mod conversion_tests {
             PathBuf::from("1a/2b/3c/4e/5a/6b/7c/1a2b3c4e5a6b7c8d.pb"),
         );
     }
+
+    #[test]
+    fn test_s3block_path_to_block_num() {
+        for block_index in &[0, 1, 0x1a2b_3c4e_5a6b_7c8d] {
+            assert_eq!(
+                s3block_path_to_block_num(&block_num_to_s3block_path(*block_index)),
+                Some(*block_index)
+            );
+        }
+
+        assert_eq!(
+            s3block_path_to_block_num(Path::new("00/00/00/00/00/00/01/0000000000000001.pb")),
+            None
+        );
+        assert_eq!(
+            s3block_path_to_block_num(&block_bundle_to_s3_path(0, 1000)),
+            None
+        );
+        assert_eq!(
+            s3block_path_to_block_num(Path::new("bundles/manifest.pb")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_block_bundle_to_s3_path() {
+        assert_eq!(
+            block_bundle_to_s3_path(0x3e8, 1000),
+            PathBuf::from("bundles/1000/00/00/00/00/00/00/00/00000000000003e8.pb.gz"),
+        );
+    }
 }
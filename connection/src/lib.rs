@@ -12,12 +12,13 @@ mod traits;
 pub use self::{
     error::{Error, Result, RetryError, RetryResult},
     grpcio_utils::{ConnectionUriGrpcioChannel, ConnectionUriGrpcioServer},
-    manager::ConnectionManager,
+    manager::{ConnectionChanges, ConnectionManager},
     sync::SyncConnection,
     thick::{ThickClient, ThickClientAttestationError},
     traits::{
-        AttestationError, AttestedConnection, BlockchainConnection, Connection,
-        RetryableBlockchainConnection, RetryableUserTxConnection, UserTxConnection,
+        AttestationError, AttestedConnection, BlockStream, BlockStreamConnection,
+        BlockchainConnection, Connection, RetryableBlockchainConnection, RetryableUserTxConnection,
+        UserTxConnection,
     },
 };
 
@@ -4,7 +4,7 @@
 
 use crate::{sync::SyncConnection, traits::Connection};
 use mc_common::{
-    logger::{o, Logger},
+    logger::{log, o, Logger},
     ResponderId,
 };
 use mc_util_uri::ConnectionUri;
@@ -22,41 +22,107 @@ struct ConnectionManagerInner<C: Connection> {
 /// A connection manager manages a list of peers it is connected to.
 pub struct ConnectionManager<C: Connection> {
     inner: Arc<RwLock<ConnectionManagerInner<C>>>,
+    logger: Logger,
 }
 
 impl<C: Connection> Clone for ConnectionManager<C> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            logger: self.logger.clone(),
         }
     }
 }
 
+/// Changes made by `ConnectionManager::update`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ConnectionChanges {
+    /// Peers that were not previously managed.
+    pub added: Vec<ResponderId>,
+
+    /// Peers that are no longer managed.
+    pub removed: Vec<ResponderId>,
+
+    /// Peers whose URI changed, and whose connection was replaced.
+    pub replaced: Vec<ResponderId>,
+}
+
+impl ConnectionChanges {
+    /// Returns true if no connection was added, removed or replaced.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.replaced.is_empty()
+    }
+}
+
 /// A collection of connections
 impl<C: Connection> ConnectionManager<C> {
     pub fn new(conns: Vec<C>, logger: Logger) -> Self {
         Self {
             inner: Arc::new(RwLock::new(ConnectionManagerInner {
-                id_to_conn: BTreeMap::from_iter(conns.into_iter().map(|conn| {
-                    let name = conn.to_string();
-                    let responder_id = conn.uri().responder_id().unwrap_or_else(|_| {
-                        panic!(
-                            "Could not create responder_id from {:?}",
-                            conn.uri().to_string()
-                        )
-                    });
-                    let sync_conn =
-                        SyncConnection::new(conn, logger.new(o!("mc.peers.peer_name" => name)));
-                    (responder_id, sync_conn)
-                })),
+                id_to_conn: BTreeMap::from_iter(
+                    conns
+                        .into_iter()
+                        .map(|conn| Self::sync_conn_with_responder_id(conn, &logger)),
+                ),
             })),
+            logger,
         }
     }
 
+    fn sync_conn_with_responder_id(conn: C, logger: &Logger) -> (ResponderId, SyncConnection<C>) {
+        let name = conn.to_string();
+        let responder_id = conn.uri().responder_id().unwrap_or_else(|_| {
+            panic!(
+                "Could not create responder_id from {:?}",
+                conn.uri().to_string()
+            )
+        });
+        let sync_conn = SyncConnection::new(conn, logger.new(o!("mc.peers.peer_name" => name)));
+        (responder_id, sync_conn)
+    }
+
     fn read(&self) -> RwLockReadGuard<ConnectionManagerInner<C>> {
         self.inner.read().expect("ConnectionManager lock poisoned")
     }
 
+    /// Replace the managed connections with `conns`.
+    ///
+    /// Existing connections whose URI did not change are kept, so that any state they hold (such
+    /// as an attested session) survives the update.
+    ///
+    /// # Arguments
+    /// * `conns` - The connections that should be managed from now on.
+    pub fn update(&self, conns: Vec<C>) -> ConnectionChanges {
+        let mut inner = self.inner.write().expect("ConnectionManager lock poisoned");
+        let mut changes = ConnectionChanges::default();
+
+        let mut id_to_conn = BTreeMap::new();
+        for conn in conns {
+            let (responder_id, sync_conn) = Self::sync_conn_with_responder_id(conn, &self.logger);
+            match inner.id_to_conn.remove(&responder_id) {
+                Some(existing) if existing.uri() == sync_conn.uri() => {
+                    id_to_conn.insert(responder_id, existing);
+                }
+                Some(_) => {
+                    changes.replaced.push(responder_id.clone());
+                    id_to_conn.insert(responder_id, sync_conn);
+                }
+                None => {
+                    changes.added.push(responder_id.clone());
+                    id_to_conn.insert(responder_id, sync_conn);
+                }
+            }
+        }
+        changes.removed = inner.id_to_conn.keys().cloned().collect();
+        inner.id_to_conn = id_to_conn;
+
+        if !changes.is_empty() {
+            log::info!(self.logger, "Updated connections: {:?}", changes);
+        }
+
+        changes
+    }
+
     /// Retrieve a vector of all the connection URLs owned by this manager.
     pub fn responder_ids(&self) -> Vec<ResponderId> {
         self.read().id_to_conn.keys().cloned().collect()
@@ -7,11 +7,13 @@ use crate::{
     error::{Error, Result},
     grpcio_utils::ConnectionUriGrpcioChannel,
     traits::{
-        AttestationError, AttestedConnection, BlockchainConnection, Connection, UserTxConnection,
+        AttestationError, AttestedConnection, BlockStream, BlockStreamConnection,
+        BlockchainConnection, Connection, UserTxConnection,
     },
 };
 use aes_gcm::Aes256Gcm;
 use failure::Fail;
+use futures::Stream;
 use grpcio::{ChannelBuilder, Environment, Error as GrpcError};
 use mc_attest_ake::{ClientInitiate, Error as AkeError, Ready, Start, Transition};
 use mc_attest_api::{attest::Message, attest_grpc::AttestedApiClient};
@@ -21,15 +23,16 @@ use mc_common::{
     trace_time,
 };
 use mc_consensus_api::{
+    blockchain::ArchiveBlock,
     consensus_client_grpc::ConsensusClientApiClient,
-    consensus_common::{BlocksRequest, ProposeTxResult},
+    consensus_common::{BlocksRequest, ProposeTxResult, SubscribeBlocksRequest},
     consensus_common_grpc::BlockchainApiClient,
     empty::Empty,
 };
 use mc_crypto_keys::X25519;
 use mc_crypto_noise::CipherError;
 use mc_crypto_rand::McRng;
-use mc_transaction_core::{tx::Tx, Block, BlockID, BlockIndex};
+use mc_transaction_core::{tx::Tx, Block, BlockContents, BlockID, BlockIndex, BlockSignature};
 use mc_util_serial::encode;
 use mc_util_uri::{ConnectionUri, ConsensusClientUri as ClientUri, UriConversionError};
 use secrecy::{ExposeSecret, SecretVec};
@@ -232,6 +235,42 @@ impl BlockchainConnection for ThickClient {
     }
 }
 
+impl BlockStreamConnection for ThickClient {
+    fn subscribe_blocks(&mut self, from_index: BlockIndex) -> Result<BlockStream> {
+        trace_time!(self.logger, "ThickClient::subscribe_blocks");
+
+        let mut request = SubscribeBlocksRequest::new();
+        request.set_from_index(from_index);
+
+        let receiver =
+            self.attested_call(|this| this.blockchain_api_client.subscribe_blocks(&request))?;
+
+        Ok(Box::new(receiver.map_err(Error::from).and_then(
+            |archive_block| from_archive_block(&archive_block),
+        )))
+    }
+}
+
+/// Converts a streamed block to its block, contents and signature.
+fn from_archive_block(
+    archive_block: &ArchiveBlock,
+) -> Result<(Block, BlockContents, Option<BlockSignature>)> {
+    if !archive_block.has_v1() {
+        return Err(Error::Other("Unsupported ArchiveBlock version".to_string()));
+    }
+    let archive_block_v1 = archive_block.get_v1();
+
+    let block = Block::try_from(archive_block_v1.get_block())?;
+    let block_contents = BlockContents::try_from(archive_block_v1.get_block_contents())?;
+    let signature = if archive_block_v1.has_signature() {
+        Some(BlockSignature::try_from(archive_block_v1.get_signature())?)
+    } else {
+        None
+    };
+
+    Ok((block, block_contents, signature))
+}
+
 impl UserTxConnection for ThickClient {
     fn propose_tx(&mut self, tx: &Tx) -> Result<BlockIndex> {
         trace_time!(self.logger, "ThickClient::propose_tx");
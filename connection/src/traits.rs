@@ -2,9 +2,10 @@
 
 //! Traits which connection implementations can implement.
 
-use crate::error::{Result, RetryResult};
+use crate::error::{Error, Result, RetryResult};
+use futures::Stream;
 use grpcio::{Error as GrpcError, RpcStatusCode};
-use mc_transaction_core::{tx::Tx, Block, BlockID, BlockIndex};
+use mc_transaction_core::{tx::Tx, Block, BlockContents, BlockID, BlockIndex, BlockSignature};
 use mc_util_uri::ConnectionUri;
 use std::{
     fmt::{Debug, Display},
@@ -65,6 +66,18 @@ pub trait BlockchainConnection: Connection {
     fn fetch_block_height(&mut self) -> Result<BlockIndex>;
 }
 
+/// A stream of blocks, with their contents and signatures. The stream is not ready until the next
+/// block is available, and ends when the connection is closed.
+pub type BlockStream =
+    Box<dyn Stream<Item = (Block, BlockContents, Option<BlockSignature>), Error = Error> + Send>;
+
+/// A connection trait providing a stream of the blocks appended to a consensus node's ledger.
+pub trait BlockStreamConnection: BlockchainConnection {
+    /// Subscribe to every block from `from_index` onwards, followed by each block as the node
+    /// appends it to its ledger.
+    fn subscribe_blocks(&mut self, from_index: BlockIndex) -> Result<BlockStream>;
+}
+
 /// A trait which supports supporting the submission of transactions to a node
 pub trait UserTxConnection: Connection {
     /// Propose a transaction over the encrypted channel.
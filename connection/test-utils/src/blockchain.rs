@@ -2,12 +2,13 @@
 
 //! Connection mock and test utilities
 
+use futures::stream;
 use mc_connection::{
-    BlockchainConnection, Connection, Error as ConnectionError, Result as ConnectionResult,
-    UserTxConnection,
+    BlockStream, BlockStreamConnection, BlockchainConnection, Connection, Error as ConnectionError,
+    Result as ConnectionResult, UserTxConnection,
 };
 use mc_ledger_db::Ledger;
-use mc_transaction_core::{tx::Tx, Block, BlockID, BlockIndex};
+use mc_transaction_core::{tx::Tx, Block, BlockContents, BlockID, BlockIndex, BlockSignature};
 use mc_util_uri::{ConnectionUri, ConsensusClientUri};
 use std::{
     cmp::{min, Ordering},
@@ -114,6 +115,28 @@ impl<L: Ledger + Sync> BlockchainConnection for MockBlockchainConnection<L> {
     }
 }
 
+impl<L: Ledger + Sync + 'static> BlockStreamConnection for MockBlockchainConnection<L> {
+    /// Streams the blocks currently in the mock ledger, and then ends the stream.
+    fn subscribe_blocks(&mut self, from_index: BlockIndex) -> ConnectionResult<BlockStream> {
+        thread::sleep(Duration::from_millis(self.latency_millis));
+
+        let ledger = self.ledger.clone();
+        let num_blocks = ledger.num_blocks().unwrap();
+        Ok(Box::new(stream::iter_result((from_index..num_blocks).map(
+            move |block_index| -> ConnectionResult<(Block, BlockContents, Option<BlockSignature>)> {
+                let block = ledger
+                    .get_block(block_index)
+                    .or(Err(ConnectionError::NotFound))?;
+                let block_contents = ledger
+                    .get_block_contents(block_index)
+                    .or(Err(ConnectionError::NotFound))?;
+                let signature = ledger.get_block_signature(block_index).ok();
+                Ok((block, block_contents, signature))
+            },
+        ))))
+    }
+}
+
 impl<L: Ledger + Sync> UserTxConnection for MockBlockchainConnection<L> {
     fn propose_tx(&mut self, tx: &Tx) -> ConnectionResult<BlockIndex> {
         self.proposed_txs.push(tx.clone());
@@ -125,6 +148,7 @@ impl<L: Ledger + Sync> UserTxConnection for MockBlockchainConnection<L> {
 mod tests {
     use super::*;
     use crate::test_client_uri;
+    use futures::Stream;
     use mc_ledger_db::test_utils::get_mock_ledger;
 
     #[test]
@@ -160,4 +184,19 @@ mod tests {
             assert_eq!(blocks.len(), 5)
         }
     }
+
+    #[test]
+    // Mock peer should stream the blocks from the requested index onwards.
+    fn subscribe_blocks() {
+        let mock_ledger = get_mock_ledger(25);
+        let mut mock_peer = MockBlockchainConnection::new(test_client_uri(123), mock_ledger, 0);
+
+        let block_indexes: Vec<BlockIndex> = mock_peer
+            .subscribe_blocks(20)
+            .unwrap()
+            .wait()
+            .map(|result| result.unwrap().0.index)
+            .collect();
+        assert_eq!(block_indexes, vec![20, 21, 22, 23, 24]);
+    }
 }
@@ -31,6 +31,7 @@ fn main() {
             "consensus_client.proto",
             "consensus_common.proto",
             "consensus_peer.proto",
+            "ledger_query.proto",
         ],
     );
 }
@@ -56,6 +56,7 @@ impl From<TransactionValidationError> for ProposeTxResult {
             }
             TransactionValidationError::TxFeeError => Self::TxFeeError,
             TransactionValidationError::KeyError => Self::KeyError,
+            TransactionValidationError::UnsortedOutputs => Self::UnsortedOutputs,
         }
     }
 }
@@ -110,6 +111,7 @@ impl TryInto<TransactionValidationError> for ProposeTxResult {
             }
             Self::TxFeeError => Ok(TransactionValidationError::TxFeeError),
             Self::KeyError => Ok(TransactionValidationError::KeyError),
+            Self::UnsortedOutputs => Ok(TransactionValidationError::UnsortedOutputs),
         }
     }
 }
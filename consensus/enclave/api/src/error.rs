@@ -54,6 +54,18 @@ pub enum Error {
     /// Signature error
     #[fail(display = "Signature error")]
     Signature,
+
+    /// The network parameters provided at initialization are unusable.
+    #[fail(display = "Invalid network parameters: {}", _0)]
+    InvalidNetworkParameters(String),
+
+    /// The block version schedule provided at initialization is unusable.
+    #[fail(display = "Invalid block version schedule: {}", _0)]
+    InvalidBlockVersionSchedule(String),
+
+    /// The origin block provided at initialization is not the one the enclave was built for.
+    #[fail(display = "Invalid origin block: {}", _0)]
+    InvalidOriginBlock(String),
 }
 
 impl From<MessageCipherError> for Error {
@@ -23,7 +23,7 @@ use mc_crypto_keys::{Ed25519Public, X25519Public};
 use mc_transaction_core::{
     ring_signature::KeyImage,
     tx::{Tx, TxHash, TxOutMembershipProof},
-    Block, BlockContents, BlockSignature,
+    Block, BlockContents, BlockSignature, BlockVersionSchedule, NetworkParameters,
 };
 use serde::{Deserialize, Serialize};
 
@@ -59,6 +59,23 @@ pub struct WellFormedTxContext {
 }
 
 impl WellFormedTxContext {
+    /// Create a new WellFormedTxContext.
+    pub fn new(
+        fee: u64,
+        tx_hash: TxHash,
+        tombstone_block: u64,
+        key_images: Vec<KeyImage>,
+        highest_indices: Vec<u64>,
+    ) -> Self {
+        Self {
+            tx_hash,
+            fee,
+            tombstone_block,
+            key_images,
+            highest_indices,
+        }
+    }
+
     pub fn tx_hash(&self) -> &TxHash {
         &self.tx_hash
     }
@@ -110,11 +127,18 @@ pub trait ConsensusEnclave {
     // UTILITY METHODS
 
     /// Perform one-time initialization upon enclave startup.
+    ///
+    /// `network_parameters` are the limits enforced when validating transactions and forming
+    /// blocks, and must be committed to by `origin_block`. `block_version_schedule` determines
+    /// the version of each block the enclave forms.
     fn enclave_init(
         &self,
         self_peer_id: &ResponderId,
         self_client_id: &ResponderId,
         sealed_key: &Option<SealedBlockSigningKey>,
+        origin_block: &Block,
+        network_parameters: &NetworkParameters,
+        block_version_schedule: &BlockVersionSchedule,
     ) -> Result<SealedBlockSigningKey>;
 
     /// Retrieve the public identity of the enclave.
@@ -216,11 +240,15 @@ pub trait ConsensusEnclave {
     ) -> Result<EnclaveMessage<PeerSession>>;
 
     /// Redact txs in order to form a new block.
-    /// Returns a block, the block contents, and a signature over the block's digest.
+    /// Returns a block, the block contents, and a signature over the block's digest and
+    /// `close_time`, the close time agreed on by consensus in seconds since the Unix epoch. Blocks
+    /// of versions that record it also contain the close time, which must not precede the
+    /// parent's.
     fn form_block(
         &self,
         parent_block: &Block,
         txs: &[(WellFormedEncryptedTx, Vec<TxOutMembershipProof>)],
+        close_time: u64,
     ) -> Result<(Block, BlockContents, BlockSignature)>;
 }
 
@@ -9,14 +9,23 @@ use mc_attest_enclave_api::{
     ClientAuthRequest, ClientSession, EnclaveMessage, PeerAuthRequest, PeerAuthResponse,
     PeerSession,
 };
-use mc_transaction_core::{tx::TxOutMembershipProof, Block};
+use mc_transaction_core::{
+    tx::TxOutMembershipProof, Block, BlockVersionSchedule, NetworkParameters,
+};
 use serde::{Deserialize, Serialize};
 
 /// An enumeration of API calls and their arguments for use across serialization boundaries.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum EnclaveCall {
     /// The [ConsensusEnclave::enclave_init()] method.
-    EnclaveInit(ResponderId, ResponderId, Option<SealedBlockSigningKey>),
+    EnclaveInit(
+        ResponderId,
+        ResponderId,
+        Option<SealedBlockSigningKey>,
+        Block,
+        NetworkParameters,
+        BlockVersionSchedule,
+    ),
 
     /// The [PeerableEnclave::peer_init()] method.
     ///
@@ -109,9 +118,10 @@ pub enum EnclaveCall {
     /// The [ConsensusEnclave::form_block()] method.
     ///
     /// Converts a list of well-formed, encrypted txs + proofs into a block, block contents (key
-    /// images + tx outs) and a signature.
+    /// images + tx outs) and a signature, at the given close time.
     FormBlock(
         Block,
         Vec<(WellFormedEncryptedTx, Vec<TxOutMembershipProof>)>,
+        u64,
     ),
 }
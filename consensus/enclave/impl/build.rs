@@ -0,0 +1,54 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Build script for the consensus enclave implementation.
+//!
+//! Builds the ID of the network's origin block into the enclave, so that it is covered by the
+//! enclave's measurement and the untrusted host can not choose which origin block, and therefore
+//! which network parameters and block version schedule, the enclave enforces.
+
+use std::{env::var, fs, path::PathBuf};
+
+/// Environment variable holding the hex-encoded ID of the origin block.
+const ORIGIN_BLOCK_ID_VAR: &str = "CONSENSUS_ENCLAVE_ORIGIN_BLOCK_ID";
+
+fn main() {
+    println!("cargo:rerun-if-env-changed={}", ORIGIN_BLOCK_ID_VAR);
+
+    let origin_block_id = match var(ORIGIN_BLOCK_ID_VAR) {
+        Ok(hex) => format!("Some({:?})", parse_block_id(&hex)),
+        Err(_) => "None".to_owned(),
+    };
+
+    let out_dir = PathBuf::from(var("OUT_DIR").expect("Could not read OUT_DIR"));
+    fs::write(
+        out_dir.join("origin_block_id.rs"),
+        format!(
+            "/// The ID of the origin block this enclave was built for, if any.\n\
+             const ORIGIN_BLOCK_ID: Option<[u8; 32]> = {};\n",
+            origin_block_id
+        ),
+    )
+    .expect("Could not write origin_block_id.rs");
+}
+
+/// Parse a hex-encoded 32 byte block ID.
+fn parse_block_id(hex: &str) -> [u8; 32] {
+    let hex = hex.trim();
+    if hex.len() != 64 || !hex.is_ascii() {
+        panic!(
+            "{} must be 64 hex digits, got {:?}",
+            ORIGIN_BLOCK_ID_VAR, hex
+        );
+    }
+
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap_or_else(|_| {
+            panic!(
+                "{} must be 64 hex digits, got {:?}",
+                ORIGIN_BLOCK_ID_VAR, hex
+            )
+        });
+    }
+    bytes
+}
@@ -44,11 +44,15 @@ use mc_transaction_core::{
     onetime_keys::{compute_shared_secret, compute_tx_pubkey, create_onetime_public_key},
     ring_signature::{KeyImage, Scalar},
     tx::{Tx, TxOut, TxOutMembershipProof},
-    Block, BlockContents, BlockSignature, BLOCK_VERSION,
+    Block, BlockContents, BlockID, BlockSignature, BlockVersionSchedule, Feature,
+    NetworkParameters,
 };
 use prost::Message;
 use rand_core::{CryptoRng, RngCore};
 
+// Defines ORIGIN_BLOCK_ID, from CONSENSUS_ENCLAVE_ORIGIN_BLOCK_ID at build time.
+include!(concat!(env!("OUT_DIR"), "/origin_block_id.rs"));
+
 /// A well-formed transaction.
 #[derive(Clone, Eq, PartialEq, Message)]
 pub struct WellFormedTx {
@@ -92,6 +96,16 @@ pub struct SgxConsensusEnclave {
 
     /// Cipher used to encrypt well-formed-encrypted transactions.
     well_formed_encrypted_tx_cipher: Mutex<AesMessageCipher>,
+
+    /// Limits enforced when validating transactions and forming blocks.
+    network_parameters: Mutex<NetworkParameters>,
+
+    /// Determines the version of each block formed by the enclave.
+    block_version_schedule: Mutex<BlockVersionSchedule>,
+
+    /// The origin block the network parameters and block version schedule must be committed to,
+    /// if the enclave was built for a specific network.
+    origin_block_id: Option<BlockID>,
 }
 
 impl core::default::Default for SgxConsensusEnclave {
@@ -102,6 +116,10 @@ impl core::default::Default for SgxConsensusEnclave {
             well_formed_encrypted_tx_cipher: Mutex::new(AesMessageCipher::new(
                 &mut McRng::default(),
             )),
+            network_parameters: Mutex::new(NetworkParameters::default()),
+            block_version_schedule: Mutex::new(BlockVersionSchedule::default()),
+            origin_block_id: ORIGIN_BLOCK_ID
+                .map(|id| BlockID::try_from(&id[..]).expect("Invalid origin block ID")),
         }
     }
 }
@@ -127,6 +145,46 @@ impl SgxConsensusEnclave {
         let well_formed_tx: WellFormedTx = mc_util_serial::decode(&plaintext)?;
         Ok(well_formed_tx)
     }
+
+    /// Stores the network parameters and block version schedule, once they are known to be
+    /// usable and to be the ones the network's origin block commits to. The host can not be
+    /// trusted to have checked either, nor to supply the network's actual origin block, which is
+    /// why the origin block must be the one the enclave was built for, when there is one.
+    fn set_network_config(
+        &self,
+        origin_block: &Block,
+        network_parameters: &NetworkParameters,
+        block_version_schedule: &BlockVersionSchedule,
+    ) -> Result<()> {
+        if origin_block.index != 0 || !origin_block.is_block_id_valid() {
+            return Err(Error::InvalidOriginBlock(format!(
+                "block {} with ID {:?} is not a valid origin block",
+                origin_block.index, origin_block.id
+            )));
+        }
+        if let Some(origin_block_id) = &self.origin_block_id {
+            if origin_block.id != *origin_block_id {
+                return Err(Error::InvalidOriginBlock(format!(
+                    "expected origin block {:?}, got {:?}",
+                    origin_block_id, origin_block.id
+                )));
+            }
+        }
+        network_parameters
+            .check()
+            .map_err(|e| Error::InvalidNetworkParameters(format!("{}", e)))?;
+        block_version_schedule
+            .check()
+            .map_err(|e| Error::InvalidBlockVersionSchedule(format!("{}", e)))?;
+        if !network_parameters.is_committed_by(block_version_schedule, origin_block) {
+            return Err(Error::InvalidNetworkParameters(
+                "not committed to by the origin block".into(),
+            ));
+        }
+        *self.network_parameters.lock()? = *network_parameters;
+        *self.block_version_schedule.lock()? = block_version_schedule.clone();
+        Ok(())
+    }
 }
 
 impl ConsensusEnclave for SgxConsensusEnclave {
@@ -135,7 +193,12 @@ impl ConsensusEnclave for SgxConsensusEnclave {
         peer_self_id: &ResponderId,
         client_self_id: &ResponderId,
         sealed_key: &Option<SealedBlockSigningKey>,
+        origin_block: &Block,
+        network_parameters: &NetworkParameters,
+        block_version_schedule: &BlockVersionSchedule,
     ) -> Result<SealedBlockSigningKey> {
+        self.set_network_config(origin_block, network_parameters, block_version_schedule)?;
+
         self.ake
             .init(peer_self_id.clone(), client_self_id.clone())?;
 
@@ -301,7 +364,16 @@ impl ConsensusEnclave for SgxConsensusEnclave {
 
         // Validate.
         let mut csprng = McRng::default();
-        mc_transaction_core::validation::validate(&tx, block_index, &proofs, &mut csprng)?;
+        let network_parameters = *self.network_parameters.lock()?;
+        mc_transaction_core::validation::validate(
+            &tx,
+            block_index,
+            &proofs,
+            &network_parameters,
+            &mut csprng,
+        )?;
+        let block_version = self.block_version_schedule.lock()?.version_at(block_index);
+        mc_transaction_core::validation::validate_features(&tx, block_version)?;
 
         // Convert into a well formed encrypted transaction + context.
         let well_formed_tx_context = WellFormedTxContext::from(&tx);
@@ -346,7 +418,49 @@ impl ConsensusEnclave for SgxConsensusEnclave {
         &self,
         parent_block: &Block,
         encrypted_txs_with_proofs: &[(WellFormedEncryptedTx, Vec<TxOutMembershipProof>)],
+        close_time: u64,
     ) -> Result<(Block, BlockContents, BlockSignature)> {
+        let network_parameters = *self.network_parameters.lock()?;
+        if encrypted_txs_with_proofs.len() > network_parameters.max_transactions_per_block {
+            return Err(Error::FormBlock(format!(
+                "Too many transactions: {}",
+                encrypted_txs_with_proofs.len()
+            )));
+        }
+
+        // The first block must extend the origin block the network config was checked against.
+        if let Some(origin_block_id) = &self.origin_block_id {
+            if parent_block.index == 0 && parent_block.id != *origin_block_id {
+                return Err(Error::FormBlock(format!(
+                    "Parent block {:?} is not the origin block {:?}",
+                    parent_block.id, origin_block_id
+                )));
+            }
+        }
+
+        // Block versions never decrease, even if the schedule was changed after the parent was
+        // formed.
+        let block_version = self
+            .block_version_schedule
+            .lock()?
+            .version_at(parent_block.index + 1);
+        if block_version < parent_block.version {
+            return Err(Error::FormBlock(format!(
+                "Block version {} is lower than the parent's version {}",
+                block_version, parent_block.version
+            )));
+        }
+
+        // The host can not be trusted with the close time, but consensus only agrees on close
+        // times that do not precede the parent's, and that are not ahead of the nodes' clocks.
+        if Feature::BlockTimestamp.is_enabled(block_version) && close_time < parent_block.timestamp
+        {
+            return Err(Error::FormBlock(format!(
+                "Close time {} precedes the parent's timestamp {}",
+                close_time, parent_block.timestamp
+            )));
+        }
+
         // This implicitly converts Vec<Result<(Tx Vec<TxOutMembershipProof>),_>> into Result<Vec<(Tx, Vec<TxOutMembershipProof>)>, _>,
         // and terminates the iteration when the first Error is encountered.
         let transactions_with_proofs = encrypted_txs_with_proofs
@@ -369,8 +483,10 @@ impl ConsensusEnclave for SgxConsensusEnclave {
                 tx,
                 parent_block.index + 1,
                 proofs,
+                &network_parameters,
                 &mut rng,
             )?;
+            mc_transaction_core::validation::validate_features(tx, block_version)?;
 
             for proof in proofs {
                 let root_element = proof
@@ -458,16 +574,18 @@ impl ConsensusEnclave for SgxConsensusEnclave {
         let block_contents = BlockContents::new(key_images, outputs);
 
         // Form the block.
-        let block = Block::new_with_parent(
-            BLOCK_VERSION,
+        let block = Block::new_with_parent_and_timestamp(
+            block_version,
             &parent_block,
             &root_elements[0],
             &block_contents,
+            close_time,
         );
 
         // Sign the block.
         let public_key = self.ake.get_identity().signing_keypair.lock()?;
-        let signature = BlockSignature::from_block_and_keypair(&block, &public_key)?;
+        let signature =
+            BlockSignature::from_block_keypair_and_time(&block, &public_key, close_time)?;
 
         Ok((block, block_contents, signature))
     }
@@ -515,8 +633,8 @@ mod tests {
     use mc_ledger_db::Ledger;
     use mc_transaction_core::{
         account_keys::AccountKey, constants::FEE_VIEW_PRIVATE_KEY,
-        onetime_keys::view_key_matches_output, tx::TxOutMembershipHash,
-        validation::TransactionValidationError, view_key::ViewKey,
+        onetime_keys::view_key_matches_output, tx::TxOutMembershipHash, validate_block_version,
+        validation::TransactionValidationError, view_key::ViewKey, BlockVersionActivation,
     };
     use mc_transaction_core_test_utils::{create_ledger, create_transaction, initialize_ledger};
     use rand_core::SeedableRng;
@@ -751,7 +869,11 @@ mod tests {
         let parent_block = ledger.get_block(ledger.num_blocks().unwrap() - 1).unwrap();
 
         let (block, block_contents, signature) = enclave
-            .form_block(&parent_block, &well_formed_encrypted_txs_with_proofs)
+            .form_block(
+                &parent_block,
+                &well_formed_encrypted_txs_with_proofs,
+                1_590_000_000,
+            )
             .unwrap();
 
         // Verify signature.
@@ -767,6 +889,7 @@ mod tests {
                     .public_key()
             );
 
+            assert_eq!(signature.signed_at(), 1_590_000_000);
             assert!(signature.verify(&block).is_ok());
         }
 
@@ -807,6 +930,237 @@ mod tests {
         assert_eq!(value, total_fee);
     }
 
+    #[test]
+    /// The enclave should only accept network parameters that the origin block commits to.
+    fn test_set_network_config_checks_origin_block() {
+        let enclave = SgxConsensusEnclave::default();
+        let parameters = NetworkParameters {
+            base_fee: 1,
+            ..Default::default()
+        };
+        let schedule = BlockVersionSchedule::default();
+
+        // Parameters must be committed to by the origin block.
+        let default_origin_block = Block::new_origin_block(&[]);
+        match enclave.set_network_config(&default_origin_block, &parameters, &schedule) {
+            Err(Error::InvalidNetworkParameters(_)) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        assert_eq!(
+            *enclave.network_parameters.lock().unwrap(),
+            NetworkParameters::default()
+        );
+
+        let origin_block = Block::new_origin_block_with_parameters(&[], &parameters);
+        enclave
+            .set_network_config(&origin_block, &parameters, &schedule)
+            .unwrap();
+        assert_eq!(*enclave.network_parameters.lock().unwrap(), parameters);
+    }
+
+    #[test]
+    /// An enclave built for a network should only accept that network's origin block, and only
+    /// form blocks extending it.
+    fn test_set_network_config_checks_origin_block_id() {
+        let parameters = NetworkParameters {
+            base_fee: 1,
+            ..Default::default()
+        };
+        let schedule = BlockVersionSchedule::default();
+        let origin_block = Block::new_origin_block_with_parameters(&[], &parameters);
+        let enclave = SgxConsensusEnclave {
+            origin_block_id: Some(origin_block.id.clone()),
+            ..Default::default()
+        };
+
+        // The host can not substitute an origin block that commits to looser limits.
+        let relaxed_parameters = NetworkParameters {
+            base_fee: 0,
+            ..parameters
+        };
+        let relaxed_origin_block =
+            Block::new_origin_block_with_parameters(&[], &relaxed_parameters);
+        match enclave.set_network_config(&relaxed_origin_block, &relaxed_parameters, &schedule) {
+            Err(Error::InvalidOriginBlock(_)) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        // Nor an origin block whose ID does not match its contents.
+        let mut forged_origin_block = relaxed_origin_block.clone();
+        forged_origin_block.id = origin_block.id.clone();
+        match enclave.set_network_config(&forged_origin_block, &relaxed_parameters, &schedule) {
+            Err(Error::InvalidOriginBlock(_)) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        assert_eq!(
+            *enclave.network_parameters.lock().unwrap(),
+            NetworkParameters::default()
+        );
+
+        enclave
+            .set_network_config(&origin_block, &parameters, &schedule)
+            .unwrap();
+        assert_eq!(*enclave.network_parameters.lock().unwrap(), parameters);
+
+        match enclave.form_block(&relaxed_origin_block, &[], 0) {
+            Err(Error::FormBlock(_)) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    /// The enclave should only accept a block version schedule that the origin block commits to.
+    fn test_set_network_config_checks_block_version_schedule() {
+        let enclave = SgxConsensusEnclave::default();
+        let parameters = NetworkParameters::default();
+        let schedule = BlockVersionSchedule {
+            activations: vec![BlockVersionActivation {
+                version: 1,
+                block_index: 10,
+            }],
+        };
+
+        // A schedule must be committed to by the origin block.
+        let default_origin_block = Block::new_origin_block(&[]);
+        match enclave.set_network_config(&default_origin_block, &parameters, &schedule) {
+            Err(Error::InvalidNetworkParameters(_)) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        assert_eq!(
+            *enclave.block_version_schedule.lock().unwrap(),
+            BlockVersionSchedule::default()
+        );
+
+        let origin_block = Block::new_origin_block_with_schedule(&[], &parameters, &schedule);
+        match enclave.set_network_config(&origin_block, &parameters, &Default::default()) {
+            Err(Error::InvalidNetworkParameters(_)) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        enclave
+            .set_network_config(&origin_block, &parameters, &schedule)
+            .unwrap();
+        assert_eq!(*enclave.block_version_schedule.lock().unwrap(), schedule);
+    }
+
+    #[test]
+    /// form_block should use the block version scheduled for the new block, and never decrease it.
+    fn test_form_block_uses_block_version_schedule() {
+        let mut rng = Hc128Rng::from_seed([77u8; 32]);
+        let enclave = SgxConsensusEnclave::default();
+        *enclave.block_version_schedule.lock().unwrap() = BlockVersionSchedule {
+            activations: vec![BlockVersionActivation {
+                version: 1,
+                block_index: 1,
+            }],
+        };
+
+        let sender = AccountKey::random(&mut rng);
+        let recipient = AccountKey::random(&mut rng);
+        let mut ledger = create_ledger();
+        let n_blocks = 1;
+        initialize_ledger(&mut ledger, n_blocks, &sender, &mut rng);
+
+        let tx_out = ledger.get_block_contents(0).unwrap().outputs[0].clone();
+        let tx = create_transaction(
+            &mut ledger,
+            &tx_out,
+            &sender,
+            &recipient.default_subaddress(),
+            n_blocks + 1,
+            &mut rng,
+        );
+        let well_formed_tx = WellFormedTx::from(tx);
+        let encrypted_tx = enclave
+            .encrypt_well_formed_tx(&well_formed_tx, &mut rng)
+            .unwrap();
+        let highest_indices = well_formed_tx.tx.get_membership_proof_highest_indices();
+        let membership_proofs = ledger
+            .get_tx_out_proof_of_memberships(&highest_indices)
+            .expect("failed getting proof");
+        let encrypted_txs_with_proofs = vec![(encrypted_tx, membership_proofs)];
+
+        let parent_block = ledger.get_block(0).unwrap();
+        let (block, block_contents, _signature) = enclave
+            .form_block(&parent_block, &encrypted_txs_with_proofs, 0)
+            .unwrap();
+        assert_eq!(block.version, 1);
+        assert!(block_contents.is_sorted());
+        assert_eq!(
+            validate_block_version(&block, &block_contents, Some(&parent_block)),
+            Ok(())
+        );
+
+        // A schedule that would decrease the version relative to the parent is refused.
+        *enclave.block_version_schedule.lock().unwrap() = BlockVersionSchedule::default();
+        let mut upgraded_parent = parent_block;
+        upgraded_parent.version = 1;
+        match enclave.form_block(&upgraded_parent, &encrypted_txs_with_proofs, 0) {
+            Err(Error::FormBlock(_)) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    /// form_block should record the close time in blocks whose version enables it, and refuse a
+    /// close time that precedes the parent's.
+    fn test_form_block_records_close_time() {
+        let mut rng = Hc128Rng::from_seed([77u8; 32]);
+        let enclave = SgxConsensusEnclave::default();
+        *enclave.block_version_schedule.lock().unwrap() = BlockVersionSchedule {
+            activations: vec![BlockVersionActivation {
+                version: 2,
+                block_index: 1,
+            }],
+        };
+
+        let sender = AccountKey::random(&mut rng);
+        let recipient = AccountKey::random(&mut rng);
+        let mut ledger = create_ledger();
+        let n_blocks = 1;
+        initialize_ledger(&mut ledger, n_blocks, &sender, &mut rng);
+
+        let tx_out = ledger.get_block_contents(0).unwrap().outputs[0].clone();
+        let tx = create_transaction(
+            &mut ledger,
+            &tx_out,
+            &sender,
+            &recipient.default_subaddress(),
+            n_blocks + 1,
+            &mut rng,
+        );
+        let well_formed_tx = WellFormedTx::from(tx);
+        let encrypted_tx = enclave
+            .encrypt_well_formed_tx(&well_formed_tx, &mut rng)
+            .unwrap();
+        let highest_indices = well_formed_tx.tx.get_membership_proof_highest_indices();
+        let membership_proofs = ledger
+            .get_tx_out_proof_of_memberships(&highest_indices)
+            .expect("failed getting proof");
+        let encrypted_txs_with_proofs = vec![(encrypted_tx, membership_proofs)];
+
+        let parent_block = ledger.get_block(0).unwrap();
+        let (block, block_contents, signature) = enclave
+            .form_block(&parent_block, &encrypted_txs_with_proofs, 1_590_000_000)
+            .unwrap();
+        assert_eq!(block.version, 2);
+        assert_eq!(block.timestamp, 1_590_000_000);
+        assert_eq!(signature.signed_at(), 1_590_000_000);
+        assert!(signature.verify(&block).is_ok());
+        assert_eq!(
+            validate_block_version(&block, &block_contents, Some(&parent_block)),
+            Ok(())
+        );
+
+        let mut later_parent = parent_block;
+        later_parent.version = 2;
+        later_parent.timestamp = 1_590_000_001;
+        match enclave.form_block(&later_parent, &encrypted_txs_with_proofs, 1_590_000_000) {
+            Err(Error::FormBlock(_)) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
     #[test]
     /// form_block should return an error if the input transactions contain a double-spend.
     fn test_form_block_prevents_duplicate_spend() {
@@ -877,7 +1231,7 @@ mod tests {
         let parent_block = ledger.get_block(ledger.num_blocks().unwrap() - 1).unwrap();
 
         let form_block_result =
-            enclave.form_block(&parent_block, &well_formed_encrypted_txs_with_proofs);
+            enclave.form_block(&parent_block, &well_formed_encrypted_txs_with_proofs, 0);
         let expected_duplicate_key_image = new_transactions[0].key_images()[0];
 
         // Check
@@ -964,7 +1318,7 @@ mod tests {
         let parent_block = ledger.get_block(ledger.num_blocks().unwrap() - 1).unwrap();
 
         let form_block_result =
-            enclave.form_block(&parent_block, &well_formed_encrypted_txs_with_proofs);
+            enclave.form_block(&parent_block, &well_formed_encrypted_txs_with_proofs, 0);
 
         // Check
         let expected = Err(Error::MalformedTx(
@@ -44,6 +44,9 @@ fn main() {
         builder.signed_enclave(PathBuf::from(&value));
     }
 
+    // Read by the enclave implementation's build script, when building the enclave from scratch.
+    rerun_if_env_changed!("CONSENSUS_ENCLAVE_ORIGIN_BLOCK_ID");
+
     rerun_if_env_changed!("CONSENSUS_ENCLAVE_LDS");
     if let Ok(value) = var("CONSENSUS_ENCLAVE_LDS") {
         builder.lds(PathBuf::from(&value));
@@ -18,16 +18,18 @@ use mc_crypto_rand::McRng;
 use mc_transaction_core::{
     ring_signature::KeyImage,
     tx::{Tx, TxOut, TxOutMembershipProof},
-    Block, BlockContents, BlockSignature, BLOCK_VERSION,
+    Block, BlockContents, BlockSignature, BlockVersionSchedule, Feature, NetworkParameters,
 };
 use mc_util_from_random::FromRandom;
 use rand_core::SeedableRng;
 use rand_hc::Hc128Rng;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
 pub struct ConsensusServiceMockEnclave {
     pub signing_keypair: Arc<Ed25519Pair>,
+    pub network_parameters: Arc<Mutex<NetworkParameters>>,
+    pub block_version_schedule: Arc<Mutex<BlockVersionSchedule>>,
 }
 
 impl Default for ConsensusServiceMockEnclave {
@@ -35,7 +37,14 @@ impl Default for ConsensusServiceMockEnclave {
         let mut csprng = Hc128Rng::seed_from_u64(0);
         let signing_keypair = Arc::new(Ed25519Pair::from_random(&mut csprng));
 
-        Self { signing_keypair }
+        let network_parameters = Arc::new(Mutex::new(NetworkParameters::default()));
+        let block_version_schedule = Arc::new(Mutex::new(BlockVersionSchedule::default()));
+
+        Self {
+            signing_keypair,
+            network_parameters,
+            block_version_schedule,
+        }
     }
 }
 
@@ -61,7 +70,29 @@ impl ConsensusEnclave for ConsensusServiceMockEnclave {
         _self_peer_id: &ResponderId,
         _self_client_id: &ResponderId,
         _sealed_key: &Option<SealedBlockSigningKey>,
+        origin_block: &Block,
+        network_parameters: &NetworkParameters,
+        block_version_schedule: &BlockVersionSchedule,
     ) -> Result<SealedBlockSigningKey> {
+        if origin_block.index != 0 || !origin_block.is_block_id_valid() {
+            return Err(Error::InvalidOriginBlock(format!(
+                "block {} with ID {:?} is not a valid origin block",
+                origin_block.index, origin_block.id
+            )));
+        }
+        network_parameters
+            .check()
+            .map_err(|e| Error::InvalidNetworkParameters(e.to_string()))?;
+        block_version_schedule
+            .check()
+            .map_err(|e| Error::InvalidBlockVersionSchedule(e.to_string()))?;
+        if !network_parameters.is_committed_by(block_version_schedule, origin_block) {
+            return Err(Error::InvalidNetworkParameters(
+                "not committed to by the origin block".to_string(),
+            ));
+        }
+        *self.network_parameters.lock()? = *network_parameters;
+        *self.block_version_schedule.lock()? = block_version_schedule.clone();
         Ok(vec![])
     }
 
@@ -156,7 +187,34 @@ impl ConsensusEnclave for ConsensusServiceMockEnclave {
         &self,
         parent_block: &Block,
         encrypted_txs_with_proofs: &[(WellFormedEncryptedTx, Vec<TxOutMembershipProof>)],
+        close_time: u64,
     ) -> Result<(Block, BlockContents, BlockSignature)> {
+        let network_parameters = *self.network_parameters.lock()?;
+        if encrypted_txs_with_proofs.len() > network_parameters.max_transactions_per_block {
+            return Err(Error::FormBlock(format!(
+                "Too many transactions: {}",
+                encrypted_txs_with_proofs.len()
+            )));
+        }
+
+        let block_version = self
+            .block_version_schedule
+            .lock()?
+            .version_at(parent_block.index + 1);
+        if block_version < parent_block.version {
+            return Err(Error::FormBlock(format!(
+                "Block version {} is lower than the parent's version {}",
+                block_version, parent_block.version
+            )));
+        }
+        if Feature::BlockTimestamp.is_enabled(block_version) && close_time < parent_block.timestamp
+        {
+            return Err(Error::FormBlock(format!(
+                "Close time {} precedes the parent's timestamp {}",
+                close_time, parent_block.timestamp
+            )));
+        }
+
         let transactions_with_proofs: Vec<(Tx, Vec<TxOutMembershipProof>)> =
             encrypted_txs_with_proofs
                 .iter()
@@ -178,8 +236,10 @@ impl ConsensusEnclave for ConsensusServiceMockEnclave {
                 tx,
                 parent_block.index + 1,
                 proofs,
+                &network_parameters,
                 &mut rng,
             )?;
+            mc_transaction_core::validation::validate_features(tx, block_version)?;
 
             for proof in proofs {
                 let root_element = proof
@@ -204,16 +264,21 @@ impl ConsensusEnclave for ConsensusServiceMockEnclave {
             outputs.extend(tx.prefix.outputs.into_iter());
         }
 
-        let block_contents = BlockContents::new(key_images, outputs);
+        let mut block_contents = BlockContents::new(key_images, outputs);
+        if Feature::SortedBlockContents.is_enabled(block_version) {
+            block_contents.sort();
+        }
 
-        let block = Block::new_with_parent(
-            BLOCK_VERSION,
+        let block = Block::new_with_parent_and_timestamp(
+            block_version,
             &parent_block,
             &root_elements[0],
             &block_contents,
+            close_time,
         );
 
-        let signature = BlockSignature::from_block_and_keypair(&block, &self.signing_keypair)?;
+        let signature =
+            BlockSignature::from_block_keypair_and_time(&block, &self.signing_keypair, close_time)?;
 
         Ok((block, block_contents, signature))
     }
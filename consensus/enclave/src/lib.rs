@@ -19,7 +19,10 @@ use mc_crypto_keys::{Ed25519Public, X25519Public};
 use mc_enclave_boundary::untrusted::make_variable_length_ecall;
 use mc_sgx_types::{sgx_enclave_id_t, sgx_status_t, *};
 use mc_sgx_urts::SgxEnclave;
-use mc_transaction_core::{tx::TxOutMembershipProof, Block, BlockContents, BlockSignature};
+use mc_transaction_core::{
+    tx::TxOutMembershipProof, Block, BlockContents, BlockSignature, BlockVersionSchedule,
+    NetworkParameters,
+};
 use std::{path, result::Result as StdResult, sync::Arc};
 
 #[allow(unused_imports)]
@@ -41,6 +44,9 @@ impl ConsensusServiceSgxEnclave {
         self_peer_id: &ResponderId,
         self_client_id: &ResponderId,
         sealed_key: &Option<SealedBlockSigningKey>,
+        origin_block: &Block,
+        network_parameters: &NetworkParameters,
+        block_version_schedule: &BlockVersionSchedule,
     ) -> (ConsensusServiceSgxEnclave, SealedBlockSigningKey) {
         let mut launch_token: sgx_launch_token_t = [0; 1024];
         let mut launch_token_updated: i32 = 0;
@@ -63,7 +69,14 @@ impl ConsensusServiceSgxEnclave {
         };
 
         let sealed_key = sgx_enclave
-            .enclave_init(self_peer_id, self_client_id, &sealed_key)
+            .enclave_init(
+                self_peer_id,
+                self_client_id,
+                &sealed_key,
+                origin_block,
+                network_parameters,
+                block_version_schedule,
+            )
             .expect("enclave_init failed");
 
         (sgx_enclave, sealed_key)
@@ -88,11 +101,17 @@ impl ConsensusEnclave for ConsensusServiceSgxEnclave {
         self_peer_id: &ResponderId,
         self_client_id: &ResponderId,
         sealed_key: &Option<SealedBlockSigningKey>,
+        origin_block: &Block,
+        network_parameters: &NetworkParameters,
+        block_version_schedule: &BlockVersionSchedule,
     ) -> Result<SealedBlockSigningKey> {
         let inbuf = mc_util_serial::serialize(&EnclaveCall::EnclaveInit(
             self_peer_id.clone(),
             self_client_id.clone(),
             sealed_key.clone(),
+            origin_block.clone(),
+            *network_parameters,
+            block_version_schedule.clone(),
         ))?;
         let outbuf = self.enclave_call(&inbuf)?;
         mc_util_serial::deserialize(&outbuf[..])?
@@ -222,10 +241,12 @@ impl ConsensusEnclave for ConsensusServiceSgxEnclave {
         &self,
         parent_block: &Block,
         txs_with_proofs: &[(WellFormedEncryptedTx, Vec<TxOutMembershipProof>)],
+        close_time: u64,
     ) -> Result<(Block, BlockContents, BlockSignature)> {
         let inbuf = mc_util_serial::serialize(&EnclaveCall::FormBlock(
             parent_block.clone(),
             txs_with_proofs.to_vec(),
+            close_time,
         ))?;
         let outbuf = self.enclave_call(&inbuf)?;
         mc_util_serial::deserialize(&outbuf[..])?
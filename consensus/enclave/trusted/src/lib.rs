@@ -33,10 +33,22 @@ pub fn ecall_dispatcher(inbuf: &[u8]) -> Result<Vec<u8>, sgx_status_t> {
     // And actually do it
     let outdata = match call_details {
         // Utility methods
-        EnclaveCall::EnclaveInit(peer_self_id, client_self_id, sealed_key) => {
-            serialize(&ENCLAVE.enclave_init(&peer_self_id, &client_self_id, &sealed_key))
-                .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
-        }
+        EnclaveCall::EnclaveInit(
+            peer_self_id,
+            client_self_id,
+            sealed_key,
+            origin_block,
+            network_parameters,
+            block_version_schedule,
+        ) => serialize(&ENCLAVE.enclave_init(
+            &peer_self_id,
+            &client_self_id,
+            &sealed_key,
+            &origin_block,
+            &network_parameters,
+            &block_version_schedule,
+        ))
+        .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?,
         // Node-to-Node Attestation
         EnclaveCall::PeerInit(node_id) => {
             serialize(&ENCLAVE.peer_init(&node_id)).or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
@@ -91,8 +103,8 @@ pub fn ecall_dispatcher(inbuf: &[u8]) -> Result<Vec<u8>, sgx_status_t> {
                 .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
         }
 
-        EnclaveCall::FormBlock(parent_block, encrypted_txs_with_proofs) => {
-            serialize(&ENCLAVE.form_block(&parent_block, &encrypted_txs_with_proofs))
+        EnclaveCall::FormBlock(parent_block, encrypted_txs_with_proofs, close_time) => {
+            serialize(&ENCLAVE.form_block(&parent_block, &encrypted_txs_with_proofs, close_time))
                 .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
         }
     };
@@ -1,31 +1,42 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
 //! A utility to play back SCP messages logged by `LoggingScpNode`.
+//!
+//! Each node's log is replayed through a fresh `Node` and the messages it sends are checked
+//! against the logged ones. A per-slot timeline of every node's progress is printed.
 
-use mc_common::{logger::log, NodeID};
+mod replay;
+mod timeline;
+
+use mc_common::{logger::Logger, NodeID, ResponderId};
 use mc_consensus_scp::{
     scp_log::{LoggedMsg, ScpLogReader, StoredMsg},
-    Msg, Node, QuorumSet, ScpNode, SlotIndex,
+    Node, QuorumSet, SlotIndex,
 };
-use mc_transaction_core::{constants::MAX_TRANSACTIONS_PER_BLOCK, tx::TxHash};
+use mc_transaction_core::{constants::MAX_TRANSACTIONS_PER_BLOCK, ConsensusValue};
 use mc_util_uri::ConsensusPeerUri as PeerUri;
+use replay::{replay, ReplayReport};
 use std::{
-    collections::{BTreeSet, VecDeque},
+    collections::{BTreeMap, BTreeSet},
     fmt,
     iter::FromIterator,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    process,
     str::FromStr,
     sync::Arc,
+    thread,
     thread::sleep,
     time::Duration,
 };
 use structopt::StructOpt;
+use timeline::{build_timelines, SlotTimeline};
 
 #[derive(Debug, StructOpt)]
 pub struct Config {
     /// Node Id
     ///
-    /// Should be specified with a PeerURI, with consensus-msg-key param provided
+    /// Should be specified with a PeerURI, with consensus-msg-key param provided. Overrides the
+    /// node id found in every log.
     #[structopt(long, parse(try_from_str=parse_node_id_from_uri))]
     pub node_id: Option<NodeID>,
 
@@ -33,12 +44,21 @@ pub struct Config {
     ///
     /// The quorum set is represented in JSON. For example:
     /// {"threshold":1,"members":[{"type":"Node","args":"node2.test.mobilecoin.com:8443"},{"type":"Node","args":"node3.test.mobilecoin.com:4843"}]}
+    /// Overrides the quorum set found in every log.
     #[structopt(long, parse(try_from_str=parse_quorum_set_from_json))]
     pub quorum_set: Option<QuorumSet>,
 
-    /// SCP debug dump.
-    #[structopt(long, parse(from_os_str))]
-    pub scp_debug_dump: PathBuf,
+    /// SCP debug dump of a node. Repeat to replay the logs of several nodes side by side.
+    #[structopt(long, required = true, parse(from_os_str))]
+    pub scp_debug_dump: Vec<PathBuf>,
+
+    /// How much faster than real time to replay the logs.
+    #[structopt(long, default_value = "1")]
+    pub speed: f64,
+
+    /// Only print the timeline, without replaying the logs.
+    #[structopt(long)]
+    pub timeline_only: bool,
 }
 
 fn parse_quorum_set_from_json(src: &str) -> Result<QuorumSet, String> {
@@ -59,24 +79,33 @@ impl fmt::Display for TransactionValidationError {
     }
 }
 
-fn trivial_validity_fn(_value: &TxHash) -> Result<(), TransactionValidationError> {
+fn trivial_validity_fn(_value: &ConsensusValue) -> Result<(), TransactionValidationError> {
     Ok(())
 }
 
-fn trivial_combine_fn(values: BTreeSet<TxHash>) -> BTreeSet<TxHash> {
-    BTreeSet::from_iter(values.into_iter().take(MAX_TRANSACTIONS_PER_BLOCK))
+fn trivial_combine_fn(values: BTreeSet<ConsensusValue>) -> BTreeSet<ConsensusValue> {
+    let close_time = values.iter().filter_map(ConsensusValue::close_time).max();
+    let mut combined = BTreeSet::from_iter(
+        values
+            .into_iter()
+            .filter(|value| value.tx_hash().is_some())
+            .take(MAX_TRANSACTIONS_PER_BLOCK),
+    );
+    combined.extend(close_time.map(ConsensusValue::CloseTime));
+    combined
 }
 
-fn main() {
-    let (logger, _global_logger_guard) =
-        mc_common::logger::create_app_logger(mc_common::logger::o!());
-    let config = Config::from_args();
-
-    let validity_fn = Arc::new(trivial_validity_fn);
-    let combine_fn = Arc::new(trivial_combine_fn);
+/// A node's log.
+struct NodeLog {
+    node_id: NodeID,
+    quorum_set: QuorumSet,
+    path: PathBuf,
+    stored_msgs: Vec<StoredMsg<ConsensusValue>>,
+}
 
-    let mut scp_reader =
-        ScpLogReader::<TxHash>::new(&config.scp_debug_dump).expect("failed creating ScpLogReader");
+fn read_log(path: &Path, config: &Config) -> NodeLog {
+    let mut scp_reader = ScpLogReader::<ConsensusValue>::new(&path.to_path_buf())
+        .expect("failed creating ScpLogReader");
 
     // The first entry is expected to be a NodeSettings entry.
     let (node_id, quorum_set) = match scp_reader.next() {
@@ -84,93 +113,143 @@ fn main() {
             msg: LoggedMsg::NodeSettings(node_id, quorum_set),
             ..
         }) => (node_id, quorum_set),
-        _ => panic!("failed getting NodeSettings entry"),
+        _ => panic!("failed getting NodeSettings entry from {:?}", path),
     };
 
     // Allow config to override these.
-    let local_node_id = config.node_id.clone().unwrap_or(node_id);
-    let local_quorum_set = config.quorum_set.unwrap_or(quorum_set);
+    NodeLog {
+        node_id: config.node_id.clone().unwrap_or(node_id),
+        quorum_set: config.quorum_set.clone().unwrap_or(quorum_set),
+        path: path.to_path_buf(),
+        stored_msgs: scp_reader.collect(),
+    }
+}
 
+/// Replay a node's log through a fresh node.
+fn replay_log(node_log: &NodeLog, speed: f64, logger: Logger) -> ReplayReport<ConsensusValue> {
     // Create the simulated node.
     let mut scp_node = Node::new(
-        local_node_id.clone(),
-        local_quorum_set,
-        validity_fn,
-        combine_fn,
+        node_log.node_id.clone(),
+        node_log.quorum_set.clone(),
+        Arc::new(trivial_validity_fn),
+        Arc::new(trivial_combine_fn),
         logger.clone(),
     );
+    scp_node.scp_timebase = Duration::from_secs_f64(scp_node.scp_timebase.as_secs_f64() / speed);
 
-    let mut prev_timestamp = 0;
-    let mut sent_msgs: VecDeque<Msg<TxHash>> = VecDeque::new();
-    let mut cur_slot_index: Option<SlotIndex> = None;
-    for stored_msg in scp_reader {
-        sleep(Duration::from_millis(
-            stored_msg.msec_since_start - prev_timestamp,
-        ));
-        prev_timestamp = stored_msg.msec_since_start;
-
-        log::trace!(
-            logger,
-            "------------------------------------------------------------"
-        );
-        log::trace!(logger, "processing {:?}", stored_msg.msg);
+    replay(&node_log.stored_msgs, &mut scp_node, speed, &logger)
+}
 
-        match stored_msg.msg {
-            LoggedMsg::NodeSettings(..) => {
-                panic!("Unexpected NodeSettings entry");
+fn print_timelines(timelines: &BTreeMap<SlotIndex, Vec<(&ResponderId, SlotTimeline)>>) {
+    for (slot_index, node_timelines) in timelines {
+        println!("Slot {}", slot_index);
+        for (responder_id, timeline) in node_timelines {
+            match timeline.externalized_at() {
+                Some(msec) => println!("  {}: externalized after {} ms", responder_id, msec),
+                None => println!(
+                    "  {}: did not externalize, last logged at {} ms",
+                    responder_id, timeline.last_msec_since_start
+                ),
             }
-
-            LoggedMsg::IncomingMsg(msg) => {
-                assert_eq!(msg.slot_index, cur_slot_index.unwrap_or(msg.slot_index));
-                cur_slot_index = Some(msg.slot_index);
-
-                if let Some(out_msg) = scp_node.handle(&msg).expect("scp handle failed") {
-                    sent_msgs.push_back(out_msg);
-                }
+            for entry in timeline.entries.iter() {
+                println!("    {:>8} ms  {}", entry.msec_since_start, entry.event);
             }
-
-            LoggedMsg::Nominate(slot_index, values) => {
-                assert_eq!(slot_index, cur_slot_index.unwrap_or(slot_index));
-                cur_slot_index = Some(slot_index);
-
-                if let Some(out_msg) = scp_node
-                    .nominate(slot_index, values)
-                    .expect("scp nominate failed")
-                {
-                    sent_msgs.push_back(out_msg);
+            if !timeline.peers.is_empty() {
+                println!("    Last heard from peers:");
+                for (peer, status) in timeline.peers.iter() {
+                    println!(
+                        "      {:>8} ms  {}: {}",
+                        status.msec_since_start, peer, status.stage
+                    );
                 }
             }
+        }
+        println!();
+    }
+}
 
-            LoggedMsg::OutgoingMsg(msg) => {
-                assert_eq!(msg.slot_index, cur_slot_index.unwrap_or(msg.slot_index));
-                assert_eq!(msg.sender_id, local_node_id);
-                cur_slot_index = Some(msg.slot_index);
-
-                let expected_msg = sent_msgs.pop_front().unwrap();
-                assert_eq!(expected_msg, msg);
-            }
-
-            LoggedMsg::ProcessTimeouts(msgs) => {
-                for msg in scp_node.process_timeouts() {
-                    sent_msgs.push_back(msg);
-                }
+fn print_report(node_log: &NodeLog, report: &ReplayReport<ConsensusValue>) {
+    println!(
+        "{} ({:?}): {} of {} logged messages replayed identically",
+        node_log.node_id.responder_id,
+        node_log.path,
+        report.num_checked - report.mismatches.len(),
+        report.num_checked
+    );
+    for mismatch in report.mismatches.iter() {
+        println!(
+            "  {:>8} ms  logged:   {:?}",
+            mismatch.msec_since_start, mismatch.logged
+        );
+        match &mismatch.replayed {
+            Some(msg) => println!("               replayed: {:?}", msg),
+            None => println!("               replayed: nothing"),
+        }
+    }
+    for (msec, err) in report.errors.iter() {
+        println!("  {:>8} ms  error: {}", msec, err);
+    }
+    if report.num_extra > 0 {
+        println!(
+            "  The replayed node sent {} more messages after the log ended",
+            report.num_extra
+        );
+    }
+}
 
-                for msg in msgs {
-                    assert_eq!(msg.slot_index, cur_slot_index.unwrap_or(msg.slot_index));
-                    assert_eq!(msg.sender_id, local_node_id);
-                    cur_slot_index = Some(msg.slot_index);
+fn main() {
+    let (logger, _global_logger_guard) =
+        mc_common::logger::create_app_logger(mc_common::logger::o!());
+    let config = Config::from_args();
+    assert!(config.speed > 0.0, "--speed must be positive");
+
+    let node_logs: Vec<Arc<NodeLog>> = config
+        .scp_debug_dump
+        .iter()
+        .map(|path| Arc::new(read_log(path, &config)))
+        .collect();
+
+    // Slot timings are relative to when each node started logging the slot, so they are only
+    // roughly comparable across nodes.
+    let mut timelines: BTreeMap<SlotIndex, Vec<(&ResponderId, SlotTimeline)>> = BTreeMap::new();
+    for node_log in node_logs.iter() {
+        for timeline in build_timelines(&node_log.stored_msgs) {
+            timelines
+                .entry(timeline.slot_index)
+                .or_insert_with(Vec::new)
+                .push((&node_log.node_id.responder_id, timeline));
+        }
+    }
+    print_timelines(&timelines);
 
-                    let expected_msg = sent_msgs.pop_front().unwrap();
-                    assert_eq!(expected_msg, msg);
-                }
-            }
+    if config.timeline_only {
+        return;
+    }
 
-            LoggedMsg::Marker(s) => {
-                log::info!(logger, "MARKER: {}", s);
-            }
-        }
+    // Replay the nodes concurrently, so that their logs keep their relative timing.
+    let replay_threads: Vec<_> = node_logs
+        .iter()
+        .map(|node_log| {
+            let node_log = node_log.clone();
+            let speed = config.speed;
+            let logger = logger.clone();
+            thread::spawn(move || replay_log(&node_log, speed, logger))
+        })
+        .collect();
+    let reports: Vec<ReplayReport<ConsensusValue>> = replay_threads
+        .into_iter()
+        .map(|handle| handle.join().expect("replay thread panicked"))
+        .collect();
+    let mut all_ok = true;
+    for (node_log, report) in node_logs.iter().zip(reports.iter()) {
+        print_report(node_log, report);
+        all_ok &= report.is_ok();
     }
 
     // Give log messages time to flush
     sleep(Duration::from_secs(1));
+
+    if !all_ok {
+        process::exit(1);
+    }
 }
@@ -0,0 +1,245 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Replays a node's SCP log through a fresh node, and checks that it sends the logged messages.
+
+use mc_common::logger::{log, Logger};
+use mc_consensus_scp::{
+    scp_log::{LoggedMsg, StoredMsg},
+    Msg, ScpNode, Value,
+};
+use std::{
+    collections::VecDeque,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+/// A logged outgoing message that the replayed node did not send.
+#[derive(Debug)]
+pub struct Mismatch<V: Value> {
+    /// Milliseconds since the start of the slot.
+    pub msec_since_start: u64,
+
+    /// The message the node sent when the log was recorded.
+    pub logged: Msg<V>,
+
+    /// The message the replayed node sent instead, if any.
+    pub replayed: Option<Msg<V>>,
+}
+
+/// The outcome of replaying a log.
+#[derive(Debug)]
+pub struct ReplayReport<V: Value> {
+    /// Number of logged outgoing messages compared with the replayed node's messages.
+    pub num_checked: usize,
+
+    /// Logged outgoing messages that the replayed node did not send.
+    pub mismatches: Vec<Mismatch<V>>,
+
+    /// Messages the replayed node sent after the last logged outgoing message.
+    pub num_extra: usize,
+
+    /// Errors returned by the replayed node, with the time of the entry that caused them.
+    pub errors: Vec<(u64, String)>,
+}
+
+impl<V: Value> ReplayReport<V> {
+    /// True if the replayed node sent every logged message and returned no errors.
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty() && self.errors.is_empty()
+    }
+}
+
+/// Feed a log to `scp_node`, keeping the logged timing, and compare the messages it sends with the
+/// logged outgoing messages.
+///
+/// Timeouts depend on timing, so `scp_node`'s timebase should be scaled by the same `speed`.
+///
+/// # Arguments
+/// * `stored_msgs` - The log, without its leading `NodeSettings` entry.
+/// * `scp_node` - A fresh node with the logged node's ID and quorum set.
+/// * `speed` - How much faster than real time to replay.
+/// * `logger` - Logger.
+pub fn replay<V: Value, N: ScpNode<V>>(
+    stored_msgs: &[StoredMsg<V>],
+    scp_node: &mut N,
+    speed: f64,
+    logger: &Logger,
+) -> ReplayReport<V> {
+    let mut report = ReplayReport {
+        num_checked: 0,
+        mismatches: Vec::new(),
+        num_extra: 0,
+        errors: Vec::new(),
+    };
+
+    // Messages sent by the replayed node that were not yet compared with the log.
+    let mut sent_msgs: VecDeque<Msg<V>> = VecDeque::new();
+
+    let start = Instant::now();
+    for stored_msg in stored_msgs {
+        let msec = stored_msg.msec_since_start;
+        let due = start + Duration::from_secs_f64(msec as f64 / 1000.0 / speed);
+        let now = Instant::now();
+        if due > now {
+            sleep(due - now);
+        }
+
+        // The logged node processed timeouts continuously, but only logged the ones that sent a
+        // message.
+        match scp_node.process_timeouts() {
+            Ok(out_msgs) => sent_msgs.extend(out_msgs),
+            Err(err) => report.errors.push((msec, err)),
+        }
+
+        log::trace!(logger, "processing {:?}", stored_msg.msg);
+
+        match &stored_msg.msg {
+            LoggedMsg::NodeSettings(..) => {
+                report
+                    .errors
+                    .push((msec, "Unexpected NodeSettings entry".to_string()));
+            }
+
+            LoggedMsg::IncomingMsg(msg) => match scp_node.handle(msg) {
+                Ok(out_msg) => sent_msgs.extend(out_msg),
+                Err(err) => report.errors.push((msec, err)),
+            },
+
+            LoggedMsg::Nominate(slot_index, values) => {
+                match scp_node.nominate(*slot_index, values.clone()) {
+                    Ok(out_msg) => sent_msgs.extend(out_msg),
+                    Err(err) => report.errors.push((msec, err)),
+                }
+            }
+
+            LoggedMsg::OutgoingMsg(msg) => check(&mut report, msec, msg, &mut sent_msgs),
+
+            LoggedMsg::ProcessTimeouts(msgs) => {
+                match scp_node.process_timeouts() {
+                    Ok(out_msgs) => sent_msgs.extend(out_msgs),
+                    Err(err) => report.errors.push((msec, err)),
+                }
+                for msg in msgs {
+                    check(&mut report, msec, msg, &mut sent_msgs);
+                }
+            }
+
+            LoggedMsg::Marker(s) => {
+                log::info!(logger, "MARKER: {}", s);
+            }
+        }
+    }
+
+    report.num_extra = sent_msgs.len();
+    report
+}
+
+/// Compare a logged outgoing message with the oldest message sent by the replayed node.
+fn check<V: Value>(
+    report: &mut ReplayReport<V>,
+    msec_since_start: u64,
+    logged: &Msg<V>,
+    sent_msgs: &mut VecDeque<Msg<V>>,
+) {
+    report.num_checked += 1;
+    let replayed = sent_msgs.pop_front();
+    if replayed.as_ref() != Some(logged) {
+        report.mismatches.push(Mismatch {
+            msec_since_start,
+            logged: logged.clone(),
+            replayed,
+        });
+    }
+}
+
+#[cfg(test)]
+mod replay_tests {
+    use super::*;
+    use mc_common::logger::test_with_logger;
+    use mc_consensus_scp::{test_utils::*, Node, QuorumSet, SlotIndex, Topic};
+    use std::{collections::BTreeSet, iter::FromIterator, sync::Arc};
+
+    fn new_node(node: u32, peer: u32, logger: Logger) -> Node<u32, TransactionValidationError> {
+        Node::new(
+            test_node_id(node),
+            QuorumSet::new_with_node_ids(1, vec![test_node_id(peer)]),
+            Arc::new(trivial_validity_fn),
+            Arc::new(trivial_combine_fn),
+            logger,
+        )
+    }
+
+    /// Run a two-node network to completion, and return node 2's log.
+    fn two_node_log(logger: Logger) -> Vec<StoredMsg<u32>> {
+        let slot_index: SlotIndex = 1;
+        let mut node1 = new_node(1, 2, logger.clone());
+        let mut node2 = new_node(2, 1, logger);
+
+        let mut log = Vec::new();
+        let mut stored = |msg: LoggedMsg<u32>| {
+            log.push(StoredMsg {
+                msec_since_start: 0,
+                msg,
+            })
+        };
+
+        let values = BTreeSet::from_iter(vec![1000, 2000]);
+        stored(LoggedMsg::Nominate(slot_index, values.clone()));
+        let mut msg_to_node1 = node2.nominate(slot_index, values).unwrap();
+        stored(LoggedMsg::OutgoingMsg(msg_to_node1.clone().unwrap()));
+
+        while let Some(msg) = msg_to_node1 {
+            let msg_to_node2 = match node1.handle(&msg).unwrap() {
+                Some(msg) => msg,
+                None => break,
+            };
+            stored(LoggedMsg::IncomingMsg(msg_to_node2.clone()));
+            msg_to_node1 = node2.handle(&msg_to_node2).unwrap();
+            if let Some(msg) = &msg_to_node1 {
+                stored(LoggedMsg::OutgoingMsg(msg.clone()));
+            }
+        }
+
+        assert!(node2.has_externalized_values(slot_index));
+        log
+    }
+
+    #[test_with_logger]
+    // A faithful log replays without mismatches.
+    fn replay_matches_log(logger: Logger) {
+        let log = two_node_log(logger.clone());
+
+        let mut scp_node = new_node(2, 1, logger.clone());
+        let report = replay(&log, &mut scp_node, 1.0, &logger);
+        assert!(report.is_ok(), "{:?}", report);
+        assert!(report.num_checked > 1);
+        assert_eq!(report.num_extra, 0);
+        assert!(scp_node.has_externalized_values(1));
+    }
+
+    #[test_with_logger]
+    // An outgoing message that the replayed node does not send is reported.
+    fn replay_reports_mismatch(logger: Logger) {
+        let mut log = two_node_log(logger.clone());
+        let tampered = log
+            .iter_mut()
+            .find_map(|stored_msg| match &mut stored_msg.msg {
+                LoggedMsg::OutgoingMsg(msg) => Some(msg),
+                _ => None,
+            })
+            .unwrap();
+        if let Topic::Nominate(payload) = &mut tampered.topic {
+            payload.X.insert(3000);
+        } else {
+            panic!("expected the first outgoing message to be a nomination");
+        }
+        let tampered = tampered.clone();
+
+        let mut scp_node = new_node(2, 1, logger.clone());
+        let report = replay(&log, &mut scp_node, 1.0, &logger);
+        assert!(!report.is_ok());
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(report.mismatches[0].logged, tampered);
+        assert!(report.mismatches[0].replayed.is_some());
+    }
+}
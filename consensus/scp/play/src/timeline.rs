@@ -0,0 +1,357 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Per-slot timelines of a node's progress through SCP, built from its logged messages.
+
+use mc_common::ResponderId;
+use mc_consensus_scp::{
+    scp_log::{LoggedMsg, StoredMsg},
+    Msg, SlotIndex, Topic, Value,
+};
+use std::{collections::BTreeMap, fmt};
+
+/// The SCP phase a message was sent in, and its ballot counter.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Stage {
+    /// Nominating values.
+    Nominate,
+
+    /// Preparing a ballot with the given counter.
+    Prepare(u32),
+
+    /// Committing a ballot with the given counter.
+    Commit(u32),
+
+    /// Externalized.
+    Externalize,
+}
+
+impl Stage {
+    /// The stage of the node that sent `msg`.
+    pub fn of<V: Value>(msg: &Msg<V>) -> Self {
+        match &msg.topic {
+            Topic::Nominate(_) => Stage::Nominate,
+            Topic::NominatePrepare(_, payload) | Topic::Prepare(payload) => {
+                Stage::Prepare(payload.B.N)
+            }
+            Topic::Commit(payload) => Stage::Commit(payload.B.N),
+            Topic::Externalize(_) => Stage::Externalize,
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stage::Nominate => write!(f, "nominate"),
+            Stage::Prepare(counter) => write!(f, "prepare ballot {}", counter),
+            Stage::Commit(counter) => write!(f, "commit ballot {}", counter),
+            Stage::Externalize => write!(f, "externalize"),
+        }
+    }
+}
+
+/// A step in the local node's progress through a slot.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// The node was first asked to nominate the given number of values.
+    NominateRequested(usize),
+
+    /// A timeout fired and made the node send a message.
+    Timeout,
+
+    /// The node sent its first message in a new stage.
+    Entered(Stage),
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::NominateRequested(num_values) => {
+                write!(f, "asked to nominate {} values", num_values)
+            }
+            Event::Timeout => write!(f, "timeout"),
+            Event::Entered(stage) => write!(f, "{}", stage),
+        }
+    }
+}
+
+/// An event, and when it happened.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TimelineEntry {
+    /// Milliseconds since the start of the slot.
+    pub msec_since_start: u64,
+
+    /// What happened.
+    pub event: Event,
+}
+
+/// The latest message received from a peer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PeerStatus {
+    /// Milliseconds since the start of the slot.
+    pub msec_since_start: u64,
+
+    /// The stage the peer was in.
+    pub stage: Stage,
+}
+
+/// A node's progress through a single slot.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SlotTimeline {
+    /// The slot.
+    pub slot_index: SlotIndex,
+
+    /// Events, in the order they happened.
+    pub entries: Vec<TimelineEntry>,
+
+    /// The latest message received from each peer.
+    pub peers: BTreeMap<ResponderId, PeerStatus>,
+
+    /// Time of the last logged entry for this slot.
+    pub last_msec_since_start: u64,
+}
+
+impl SlotTimeline {
+    fn new(slot_index: SlotIndex) -> Self {
+        Self {
+            slot_index,
+            entries: Vec::new(),
+            peers: BTreeMap::new(),
+            last_msec_since_start: 0,
+        }
+    }
+
+    /// The stage of the last message the local node sent.
+    pub fn stage(&self) -> Option<Stage> {
+        self.entries
+            .iter()
+            .rev()
+            .find_map(|entry| match entry.event {
+                Event::Entered(stage) => Some(stage),
+                _ => None,
+            })
+    }
+
+    /// When the local node externalized, if it did.
+    pub fn externalized_at(&self) -> Option<u64> {
+        self.entries
+            .iter()
+            .find(|entry| entry.event == Event::Entered(Stage::Externalize))
+            .map(|entry| entry.msec_since_start)
+    }
+
+    fn push(&mut self, msec_since_start: u64, event: Event) {
+        self.entries.push(TimelineEntry {
+            msec_since_start,
+            event,
+        });
+    }
+
+    fn sent(&mut self, msec_since_start: u64, msg: &Msg<impl Value>) {
+        let stage = Stage::of(msg);
+        if self.stage() != Some(stage) {
+            self.push(msec_since_start, Event::Entered(stage));
+        }
+    }
+}
+
+/// Build the timeline of every slot that appears in a node's log, ordered by slot.
+///
+/// # Arguments
+/// * `stored_msgs` - The node's log, as read by `ScpLogReader`.
+pub fn build_timelines<V: Value>(stored_msgs: &[StoredMsg<V>]) -> Vec<SlotTimeline> {
+    let mut timelines: BTreeMap<SlotIndex, SlotTimeline> = BTreeMap::new();
+
+    for stored_msg in stored_msgs {
+        let msec = stored_msg.msec_since_start;
+
+        match &stored_msg.msg {
+            LoggedMsg::NodeSettings(..) | LoggedMsg::Marker(_) => {}
+
+            LoggedMsg::Nominate(slot_index, values) => {
+                let timeline = timeline_for(&mut timelines, *slot_index, msec);
+                if timeline.entries.is_empty() {
+                    timeline.push(msec, Event::NominateRequested(values.len()));
+                }
+            }
+
+            LoggedMsg::IncomingMsg(msg) => {
+                timeline_for(&mut timelines, msg.slot_index, msec)
+                    .peers
+                    .insert(
+                        msg.sender_id.responder_id.clone(),
+                        PeerStatus {
+                            msec_since_start: msec,
+                            stage: Stage::of(msg),
+                        },
+                    );
+            }
+
+            LoggedMsg::OutgoingMsg(msg) => {
+                timeline_for(&mut timelines, msg.slot_index, msec).sent(msec, msg);
+            }
+
+            LoggedMsg::ProcessTimeouts(msgs) => {
+                let mut timed_out_slots: Vec<SlotIndex> = Vec::new();
+                for msg in msgs {
+                    let timeline = timeline_for(&mut timelines, msg.slot_index, msec);
+                    if !timed_out_slots.contains(&msg.slot_index) {
+                        timed_out_slots.push(msg.slot_index);
+                        timeline.push(msec, Event::Timeout);
+                    }
+                    timeline.sent(msec, msg);
+                }
+            }
+        }
+    }
+
+    timelines
+        .into_iter()
+        .map(|(_, timeline)| timeline)
+        .collect()
+}
+
+/// The timeline of `slot_index`, updated to end at `msec_since_start`.
+fn timeline_for(
+    timelines: &mut BTreeMap<SlotIndex, SlotTimeline>,
+    slot_index: SlotIndex,
+    msec_since_start: u64,
+) -> &mut SlotTimeline {
+    let timeline = timelines
+        .entry(slot_index)
+        .or_insert_with(|| SlotTimeline::new(slot_index));
+    timeline.last_msec_since_start = msec_since_start;
+    timeline
+}
+
+#[cfg(test)]
+mod timeline_tests {
+    use super::*;
+    use mc_consensus_scp::{
+        core_types::Ballot,
+        msg::{CommitPayload, ExternalizePayload, NominatePayload, PreparePayload},
+        test_utils::test_node_id,
+        QuorumSet,
+    };
+    use std::{collections::BTreeSet, iter::FromIterator};
+
+    fn msg(sender: u32, topic: Topic<u32>) -> Msg<u32> {
+        Msg::new(
+            test_node_id(sender),
+            QuorumSet::new_with_node_ids(1, vec![test_node_id(1), test_node_id(2)]),
+            7,
+            topic,
+        )
+    }
+
+    fn prepare(counter: u32) -> Topic<u32> {
+        Topic::Prepare(PreparePayload {
+            B: Ballot::new(counter, &[5]),
+            P: None,
+            PP: None,
+            CN: 0,
+            HN: 0,
+        })
+    }
+
+    fn stored(msec_since_start: u64, msg: LoggedMsg<u32>) -> StoredMsg<u32> {
+        StoredMsg {
+            msec_since_start,
+            msg,
+        }
+    }
+
+    #[test]
+    // Stage changes of sent messages, timeouts and peers' latest messages are recorded.
+    fn build_timeline() {
+        let nominate = Topic::Nominate(NominatePayload {
+            X: BTreeSet::from_iter(vec![5]),
+            Y: BTreeSet::new(),
+        });
+        let log = vec![
+            stored(0, LoggedMsg::Nominate(7, BTreeSet::from_iter(vec![5, 6]))),
+            stored(1, LoggedMsg::OutgoingMsg(msg(0, nominate.clone()))),
+            stored(5, LoggedMsg::Nominate(7, BTreeSet::from_iter(vec![5]))),
+            stored(10, LoggedMsg::IncomingMsg(msg(1, nominate))),
+            stored(20, LoggedMsg::OutgoingMsg(msg(0, prepare(1)))),
+            stored(25, LoggedMsg::OutgoingMsg(msg(0, prepare(1)))),
+            stored(30, LoggedMsg::IncomingMsg(msg(2, prepare(1)))),
+            stored(1000, LoggedMsg::ProcessTimeouts(vec![msg(0, prepare(2))])),
+            stored(
+                1100,
+                LoggedMsg::OutgoingMsg(msg(
+                    0,
+                    Topic::Commit(CommitPayload {
+                        B: Ballot::new(2, &[5]),
+                        PN: 2,
+                        CN: 1,
+                        HN: 2,
+                    }),
+                )),
+            ),
+        ];
+
+        let timelines = build_timelines(&log);
+        assert_eq!(timelines.len(), 1);
+        let timeline = &timelines[0];
+        assert_eq!(timeline.slot_index, 7);
+        assert_eq!(timeline.last_msec_since_start, 1100);
+        assert_eq!(timeline.stage(), Some(Stage::Commit(2)));
+        assert_eq!(timeline.externalized_at(), None);
+        assert_eq!(
+            timeline
+                .entries
+                .iter()
+                .map(|entry| (entry.msec_since_start, entry.event.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, Event::NominateRequested(2)),
+                (1, Event::Entered(Stage::Nominate)),
+                (20, Event::Entered(Stage::Prepare(1))),
+                (1000, Event::Timeout),
+                (1000, Event::Entered(Stage::Prepare(2))),
+                (1100, Event::Entered(Stage::Commit(2))),
+            ]
+        );
+        assert_eq!(
+            timeline.peers[&test_node_id(1).responder_id],
+            PeerStatus {
+                msec_since_start: 10,
+                stage: Stage::Nominate
+            }
+        );
+        assert_eq!(
+            timeline.peers[&test_node_id(2).responder_id],
+            PeerStatus {
+                msec_since_start: 30,
+                stage: Stage::Prepare(1)
+            }
+        );
+    }
+
+    #[test]
+    // Every slot in the log gets its own timeline.
+    fn build_timelines_for_each_slot() {
+        let mut next_slot_msg = msg(
+            0,
+            Topic::Externalize(ExternalizePayload {
+                C: Ballot::new(1, &[5]),
+                HN: 1,
+            }),
+        );
+        next_slot_msg.slot_index = 8;
+        let log = vec![
+            stored(0, LoggedMsg::OutgoingMsg(msg(0, prepare(1)))),
+            stored(500, LoggedMsg::OutgoingMsg(next_slot_msg)),
+        ];
+
+        let timelines = build_timelines(&log);
+        assert_eq!(
+            timelines
+                .iter()
+                .map(|timeline| (timeline.slot_index, timeline.externalized_at()))
+                .collect::<Vec<_>>(),
+            vec![(7, None), (8, Some(500))]
+        );
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A utility that checks whether the network configurations of a set of nodes enjoy quorum
+//! intersection, and reports which node failures would halt or fork the network.
+
+use mc_common::{HashMap, ResponderId};
+use mc_consensus_scp::{
+    quorum_analysis::{analyze, QuorumAnalysis},
+    QuorumSet,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+};
+use structopt::StructOpt;
+
+/// Maximal number of sets printed for each kind of set.
+const MAX_PRINTED_SETS: usize = 20;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "scp-quorum-analyzer",
+    about = "Checks the quorum sets of a network for quorum intersection and liveness."
+)]
+pub struct Config {
+    /// A node and its network configuration file, as `<responder id>=<path>`.
+    ///
+    /// The file is the network.toml/json the node's consensus service is started with. Repeat
+    /// for every node in the network. Nodes whose configuration is not provided are treated as
+    /// faulty.
+    #[structopt(long = "node", required = true, parse(try_from_str=parse_node))]
+    pub nodes: Vec<(ResponderId, PathBuf)>,
+
+    /// Print the analysis as JSON.
+    #[structopt(long)]
+    pub json: bool,
+}
+
+fn parse_node(src: &str) -> Result<(ResponderId, PathBuf), String> {
+    let mut parts = src.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some(responder_id), Some(path)) => Ok((
+            ResponderId::from_str(responder_id)
+                .map_err(|err| format!("Invalid responder id {}: {:?}", responder_id, err))?,
+            PathBuf::from(path),
+        )),
+        _ => Err(format!("Expected <responder id>=<path>, got {}", src)),
+    }
+}
+
+/// The part of a network configuration file this utility needs.
+#[derive(Deserialize)]
+struct NetworkFile {
+    quorum_set: QuorumSet<ResponderId>,
+}
+
+fn load_quorum_set(path: &Path) -> QuorumSet<ResponderId> {
+    let data = fs::read_to_string(path)
+        .unwrap_or_else(|err| panic!("failed reading {:?}: {:?}", path, err));
+    let network: NetworkFile = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&data)
+            .unwrap_or_else(|err| panic!("failed TOML parsing {:?}: {:?}", path, err)),
+        Some("json") => serde_json::from_str(&data)
+            .unwrap_or_else(|err| panic!("failed JSON parsing {:?}: {:?}", path, err)),
+        _ => panic!("Unrecognized extension in path {:?}", path),
+    };
+    network.quorum_set
+}
+
+fn format_set(set: &[ResponderId]) -> String {
+    let names: Vec<String> = set.iter().map(ToString::to_string).collect();
+    format!("{{{}}}", names.join(", "))
+}
+
+fn print_sets(title: &str, sets: &[Vec<ResponderId>]) {
+    println!("{} ({}):", title, sets.len());
+    for set in sets.iter().take(MAX_PRINTED_SETS) {
+        println!("  {}", format_set(set));
+    }
+    if sets.len() > MAX_PRINTED_SETS {
+        println!("  ... and {} more", sets.len() - MAX_PRINTED_SETS);
+    }
+}
+
+fn print_report(analysis: &QuorumAnalysis<ResponderId>) {
+    println!("Nodes: {}", format_set(&analysis.nodes));
+    if !analysis.unknown_nodes.is_empty() {
+        println!(
+            "Nodes without a configuration, treated as faulty: {}",
+            format_set(&analysis.unknown_nodes)
+        );
+    }
+    println!();
+
+    print_sets("Minimal quorums", &analysis.minimal_quorums);
+    match &analysis.disjoint_quorums {
+        None => println!("Quorum intersection: yes"),
+        Some((quorum, other_quorum)) => println!(
+            "Quorum intersection: NO, {} and {} are disjoint quorums",
+            format_set(quorum),
+            format_set(other_quorum)
+        ),
+    }
+    println!();
+
+    print_sets("Minimal blocking sets", &analysis.minimal_blocking_sets);
+    if let Some(size) = analysis.min_blocking_set_size() {
+        println!("The network halts when {} node(s) fail.", size);
+    }
+    println!();
+
+    print_sets("Minimal splitting sets", &analysis.minimal_splitting_sets);
+    match analysis.min_splitting_set_size() {
+        Some(size) => println!("The network can fork when {} node(s) are Byzantine.", size),
+        None => println!("The network cannot fork."),
+    }
+    println!();
+
+    println!("Weakest nodes (smallest blocking sets, smallest splitting sets they belong to):");
+    for criticality in analysis.weakest_nodes() {
+        println!(
+            "  {}: {}, {}",
+            criticality.node_id, criticality.num_blocking_sets, criticality.num_splitting_sets
+        );
+    }
+}
+
+fn main() {
+    let config = Config::from_args();
+
+    let mut quorum_sets = HashMap::default();
+    for (responder_id, path) in config.nodes.iter() {
+        if quorum_sets
+            .insert(responder_id.clone(), load_quorum_set(path))
+            .is_some()
+        {
+            panic!("Duplicate node {}", responder_id);
+        }
+    }
+
+    let analysis = analyze(&quorum_sets).unwrap_or_else(|err| panic!("{}", err));
+
+    if config.json {
+        let weakest_nodes: Vec<serde_json::Value> = analysis
+            .weakest_nodes()
+            .into_iter()
+            .map(|criticality| {
+                json!({
+                    "node_id": criticality.node_id,
+                    "num_blocking_sets": criticality.num_blocking_sets,
+                    "num_splitting_sets": criticality.num_splitting_sets,
+                })
+            })
+            .collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({
+                "nodes": analysis.nodes,
+                "unknown_nodes": analysis.unknown_nodes,
+                "minimal_quorums": analysis.minimal_quorums,
+                "has_quorum_intersection": analysis.has_quorum_intersection(),
+                "disjoint_quorums": analysis.disjoint_quorums,
+                "minimal_blocking_sets": analysis.minimal_blocking_sets,
+                "min_blocking_set_size": analysis.min_blocking_set_size(),
+                "minimal_splitting_sets": analysis.minimal_splitting_sets,
+                "min_splitting_set_size": analysis.min_splitting_set_size(),
+                "weakest_nodes": weakest_nodes,
+            }))
+            .expect("failed serializing analysis")
+        );
+    } else {
+        print_report(&analysis);
+    }
+
+    // Let scripts check a configuration change before it is deployed.
+    if !analysis.has_quorum_intersection() {
+        process::exit(1);
+    }
+}
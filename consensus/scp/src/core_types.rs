@@ -11,6 +11,7 @@ use std::{
     fmt::{Debug, Display},
     hash::{Hash, Hasher},
     sync::Arc,
+    time::Instant,
 };
 
 /// A generic node identifier.
@@ -26,6 +27,9 @@ pub type CombineFn<V> = Arc<(dyn Fn(BTreeSet<V>) -> BTreeSet<V> + Sync + Send)>;
 /// Application-specific validation of value.
 pub type ValidityFn<V, E> = Arc<(dyn Fn(&V) -> Result<(), E> + Sync + Send)>;
 
+/// Source of the current time, used to schedule and fire nomination and ballot timeouts.
+pub type ClockFn = Arc<(dyn Fn() -> Instant + Sync + Send)>;
+
 /// The node identifier is used when reasoning about messages in federated voting.
 ///
 /// For example, in production SCP, a message is signed by the node that emitted
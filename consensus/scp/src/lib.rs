@@ -9,15 +9,18 @@ pub mod core_types;
 pub mod msg;
 pub mod node;
 pub mod predicates;
+pub mod quorum_analysis;
 pub mod quorum_set;
 pub mod scp_log;
+pub mod scp_state;
+pub mod simulator;
 pub mod slot;
 pub mod test_utils;
 mod utils;
 
 #[doc(inline)]
 pub use self::{
-    core_types::{CombineFn, GenericNodeId, Identifier, SlotIndex, ValidityFn, Value},
+    core_types::{ClockFn, CombineFn, GenericNodeId, Identifier, SlotIndex, ValidityFn, Value},
     msg::{Msg, Topic},
     node::{Node, ScpNode},
     quorum_set::{QuorumSet, QuorumSetMember},
@@ -2,10 +2,10 @@
 
 //! A node determines whether transactions are valid, and participates in voting with the members of its quorum set.
 use crate::{
-    core_types::{CombineFn, SlotIndex, ValidityFn, Value},
+    core_types::{ClockFn, CombineFn, SlotIndex, ValidityFn, Value},
     msg::{ExternalizePayload, Msg, Topic},
     quorum_set::QuorumSet,
-    slot::{Slot, SlotMetrics},
+    slot::{Slot, SlotMetrics, SlotState},
 };
 use mc_common::{
     fast_hash,
@@ -13,7 +13,12 @@ use mc_common::{
     Hash, LruCache, NodeID,
 };
 use mc_util_serial;
-use std::{collections::BTreeSet, fmt::Display, time::Duration};
+use std::{
+    collections::BTreeSet,
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 /// Max number of pending slots to store.
 const MAX_PENDING_SLOTS: usize = 10;
@@ -54,6 +59,10 @@ pub struct Node<V: Value, ValidationError: Display> {
     /// Sets the 'base round timeout' and the 'base ballot timeout' when creating a slot.
     /// (Defaults to 1 second to match the SCP whitepaper specification.)
     pub scp_timebase: Duration,
+
+    /// Source of the current time for the timeouts of slots created from now on.
+    /// (Defaults to the system clock.)
+    pub clock: ClockFn,
 }
 
 impl<V: Value, ValidationError: Display> Node<V, ValidationError> {
@@ -75,6 +84,7 @@ impl<V: Value, ValidationError: Display> Node<V, ValidationError> {
             seen_msg_hashes: LruCache::new(LAST_SEEN_HISTORY_SIZE),
             logger,
             scp_timebase: Duration::from_millis(1000),
+            clock: Arc::new(Instant::now),
         }
     }
 
@@ -95,6 +105,7 @@ impl<V: Value, ValidationError: Display> Node<V, ValidationError> {
             );
             slot.base_round_interval = self.scp_timebase;
             slot.base_ballot_interval = self.scp_timebase;
+            slot.clock = self.clock.clone();
             self.pending.put(slot_index, slot);
         }
 
@@ -155,7 +166,7 @@ pub trait ScpNode<V: Value>: Send {
     fn has_externalized_values(&self, slot_index: SlotIndex) -> bool;
 
     /// Process pending timeouts.
-    fn process_timeouts(&mut self) -> Vec<Msg<V>>;
+    fn process_timeouts(&mut self) -> Result<Vec<Msg<V>>, String>;
 
     /// Get metrics for a specific slot.
     fn get_slot_metrics(&mut self, slot_index: SlotIndex) -> Option<SlotMetrics>;
@@ -163,6 +174,16 @@ pub trait ScpNode<V: Value>: Send {
     /// Clear the list of pending slots. This is useful if the user of this object realizes they
     /// have fallen behind their peers, and as such they want to abort processing of current slots.
     fn clear_pending_slots(&mut self);
+
+    /// Replace the local node quorum set. Slots that are already pending keep the quorum set they
+    /// were created with, so callers should do this between slots.
+    fn set_quorum_set(&mut self, quorum_set: QuorumSet);
+
+    /// Get the state of a pending slot that must survive a restart of the local node.
+    fn get_slot_state(&mut self, slot_index: SlotIndex) -> Option<SlotState<V>>;
+
+    /// Replace a pending slot with one restored from a previously saved state.
+    fn restore_slot_state(&mut self, state: SlotState<V>) -> Result<(), String>;
 }
 
 impl<V: Value, ValidationError: Display> ScpNode<V> for Node<V, ValidationError> {
@@ -285,14 +306,14 @@ impl<V: Value, ValidationError: Display> ScpNode<V> for Node<V, ValidationError>
     }
 
     /// Process pending timeouts.
-    fn process_timeouts(&mut self) -> Vec<Msg<V>> {
+    fn process_timeouts(&mut self) -> Result<Vec<Msg<V>>, String> {
         let mut msgs = Vec::<Msg<V>>::new();
 
         for (_, slot) in self.pending.iter_mut() {
             msgs.extend(slot.process_timeouts());
         }
 
-        msgs
+        Ok(msgs)
     }
 
     /// Get metrics for a specific slot.
@@ -305,6 +326,42 @@ impl<V: Value, ValidationError: Display> ScpNode<V> for Node<V, ValidationError>
     fn clear_pending_slots(&mut self) {
         self.pending.clear();
     }
+
+    fn set_quorum_set(&mut self, quorum_set: QuorumSet) {
+        self.Q = quorum_set;
+    }
+
+    fn get_slot_state(&mut self, slot_index: SlotIndex) -> Option<SlotState<V>> {
+        self.pending.get(&slot_index).map(|slot| slot.get_state())
+    }
+
+    fn restore_slot_state(&mut self, state: SlotState<V>) -> Result<(), String> {
+        let slot_index = state.slot_index;
+        let last_sent_msg = state.last_sent_msg.clone();
+
+        let mut slot = Slot::from_state(
+            self.ID.clone(),
+            self.Q.clone(),
+            state,
+            self.validity_fn.clone(),
+            self.combine_fn.clone(),
+            self.logger.clone(),
+        );
+        slot.base_round_interval = self.scp_timebase;
+        slot.base_ballot_interval = self.scp_timebase;
+        slot.clock = self.clock.clone();
+        slot.restart_timers();
+        self.pending.put(slot_index, slot);
+
+        // A slot that externalized before the restart should be reported as externalized again.
+        if let Some(msg) = last_sent_msg {
+            if let Topic::Externalize(ext_payload) = &msg.topic {
+                self.externalize(slot_index, ext_payload)?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -314,6 +371,22 @@ mod tests {
     use mc_common::logger::test_with_logger;
     use std::{iter::FromIterator, sync::Arc};
 
+    #[test_with_logger]
+    /// `set_quorum_set` replaces the local node quorum set.
+    fn set_quorum_set(logger: Logger) {
+        let mut node = Node::<u32, TransactionValidationError>::new(
+            test_node_id(1),
+            QuorumSet::new_with_node_ids(1, vec![test_node_id(2)]),
+            Arc::new(trivial_validity_fn),
+            Arc::new(trivial_combine_fn),
+            logger,
+        );
+
+        let quorum_set = QuorumSet::new_with_node_ids(2, vec![test_node_id(2), test_node_id(3)]);
+        node.set_quorum_set(quorum_set.clone());
+        assert_eq!(node.quorum_set(), quorum_set);
+    }
+
     #[test_with_logger]
     /// Steps through a sequence of messages that allow a two-node network to reach consensus.
     fn basic_two_node_consensus(logger: Logger) {
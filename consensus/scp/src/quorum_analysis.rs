@@ -0,0 +1,556 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Static analysis of the quorum sets of every node in a network.
+//!
+//! `QuorumSet::findQuorum` and `QuorumSet::findBlockingSet` answer questions about messages a
+//! node has received at runtime. This module answers questions about a network configuration
+//! before it is deployed:
+//! * Does every pair of quorums intersect? If not, the network can fork even if every node is
+//!   honest.
+//! * Which sets of nodes halt the network when they fail? These are the minimal blocking sets,
+//!   the sets that intersect every quorum.
+//! * Which sets of nodes can fork the network when they are Byzantine? These are the minimal
+//!   splitting sets, the intersections of pairs of minimal quorums.
+//!
+//! A set of nodes is a quorum if it is non-empty and every node in it has its quorum set
+//! satisfied by the set. A node's quorum set need not list the node itself.
+
+use crate::{
+    core_types::GenericNodeId,
+    quorum_set::{QuorumSet, QuorumSetMember},
+};
+use mc_common::{HashMap, HashSet};
+use std::fmt;
+
+/// The largest number of nodes a network may have for it to be analyzed.
+pub const MAX_ANALYZED_NODES: usize = 64;
+
+/// A set of nodes, as a bit mask over node indices.
+type NodeSet = u64;
+
+/// Errors that prevent a network from being analyzed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum QuorumAnalysisError {
+    /// The network has more than `MAX_ANALYZED_NODES` nodes.
+    TooManyNodes(usize),
+}
+
+impl fmt::Display for QuorumAnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooManyNodes(num_nodes) => write!(
+                f,
+                "Cannot analyze {} nodes, the maximum is {}",
+                num_nodes, MAX_ANALYZED_NODES
+            ),
+        }
+    }
+}
+
+/// How often a node appears in the smallest blocking and splitting sets of a network.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NodeCriticality<ID> {
+    /// The node.
+    pub node_id: ID,
+
+    /// Number of smallest minimal blocking sets containing the node.
+    pub num_blocking_sets: usize,
+
+    /// Number of smallest minimal splitting sets containing the node.
+    pub num_splitting_sets: usize,
+}
+
+/// The result of analyzing a network.
+///
+/// Every list of nodes is ordered by the nodes' string representation, and lists of sets are
+/// ordered from the smallest set to the largest.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QuorumAnalysis<ID> {
+    /// Nodes whose quorum set was provided.
+    pub nodes: Vec<ID>,
+
+    /// Nodes that appear in a quorum set, but whose own quorum set was not provided. They are
+    /// treated as faulty, and are never part of a quorum.
+    pub unknown_nodes: Vec<ID>,
+
+    /// Quorums that contain no other quorum.
+    pub minimal_quorums: Vec<Vec<ID>>,
+
+    /// Two quorums with no node in common, if the network does not enjoy quorum intersection.
+    pub disjoint_quorums: Option<(Vec<ID>, Vec<ID>)>,
+
+    /// Minimal sets of nodes whose failure leaves the remaining nodes without a quorum.
+    ///
+    /// Contains only the empty set if the network has no quorum at all.
+    pub minimal_blocking_sets: Vec<Vec<ID>>,
+
+    /// Minimal sets of nodes that, when Byzantine, can cause two quorums to diverge.
+    ///
+    /// Contains only the empty set if the network does not enjoy quorum intersection.
+    pub minimal_splitting_sets: Vec<Vec<ID>>,
+}
+
+impl<ID: GenericNodeId> QuorumAnalysis<ID> {
+    /// Returns true if every two quorums have a node in common.
+    pub fn has_quorum_intersection(&self) -> bool {
+        self.disjoint_quorums.is_none()
+    }
+
+    /// The smallest number of failed nodes that halts the network.
+    pub fn min_blocking_set_size(&self) -> Option<usize> {
+        self.minimal_blocking_sets.iter().map(Vec::len).min()
+    }
+
+    /// The smallest number of Byzantine nodes that can fork the network, or None if no set of
+    /// nodes can.
+    pub fn min_splitting_set_size(&self) -> Option<usize> {
+        self.minimal_splitting_sets.iter().map(Vec::len).min()
+    }
+
+    /// The weakest points of the network: nodes that belong to a smallest blocking set or a
+    /// smallest splitting set, most critical first.
+    pub fn weakest_nodes(&self) -> Vec<NodeCriticality<ID>> {
+        let smallest = |sets: &[Vec<ID>]| -> Vec<Vec<ID>> {
+            let min_len = sets.iter().map(Vec::len).min().unwrap_or(0);
+            sets.iter()
+                .filter(|set| set.len() == min_len)
+                .cloned()
+                .collect()
+        };
+        let blocking_sets = smallest(&self.minimal_blocking_sets);
+        let splitting_sets = smallest(&self.minimal_splitting_sets);
+
+        let mut result: Vec<NodeCriticality<ID>> = self
+            .nodes
+            .iter()
+            .map(|node_id| NodeCriticality {
+                node_id: node_id.clone(),
+                num_blocking_sets: blocking_sets
+                    .iter()
+                    .filter(|set| set.contains(node_id))
+                    .count(),
+                num_splitting_sets: splitting_sets
+                    .iter()
+                    .filter(|set| set.contains(node_id))
+                    .count(),
+            })
+            .filter(|criticality| {
+                criticality.num_blocking_sets > 0 || criticality.num_splitting_sets > 0
+            })
+            .collect();
+
+        // Sorting is stable, so equally critical nodes remain ordered by name.
+        result.sort_by_key(|criticality| {
+            std::cmp::Reverse(criticality.num_blocking_sets + criticality.num_splitting_sets)
+        });
+        result
+    }
+}
+
+/// Analyzes a network.
+///
+/// # Arguments
+/// * `quorum_sets` - The quorum set of each node in the network.
+pub fn analyze<ID: GenericNodeId>(
+    quorum_sets: &HashMap<ID, QuorumSet<ID>>,
+) -> Result<QuorumAnalysis<ID>, QuorumAnalysisError> {
+    let mut nodes: Vec<ID> = quorum_sets.keys().cloned().collect();
+    nodes.sort_by_key(|node_id| node_id.to_string());
+    if nodes.len() > MAX_ANALYZED_NODES {
+        return Err(QuorumAnalysisError::TooManyNodes(nodes.len()));
+    }
+
+    let mut unknown_nodes: Vec<ID> = quorum_sets
+        .values()
+        .flat_map(|quorum_set| quorum_set.nodes())
+        .filter(|node_id| !quorum_sets.contains_key(node_id))
+        .collect::<HashSet<ID>>()
+        .into_iter()
+        .collect();
+    unknown_nodes.sort_by_key(|node_id| node_id.to_string());
+
+    let network = IndexedNetwork::new(&nodes, quorum_sets);
+    let to_ids = |set: NodeSet| -> Vec<ID> {
+        nodes
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| set & bit(*index) != 0)
+            .map(|(_, node_id)| node_id.clone())
+            .collect()
+    };
+
+    let minimal_quorums = network.minimal_quorums();
+
+    let mut disjoint_quorums = None;
+    let mut intersections = Vec::new();
+    for (i, quorum) in minimal_quorums.iter().enumerate() {
+        for other_quorum in minimal_quorums[i + 1..].iter() {
+            let intersection = quorum & other_quorum;
+            if intersection == 0 && disjoint_quorums.is_none() {
+                disjoint_quorums = Some((to_ids(*quorum), to_ids(*other_quorum)));
+            }
+            intersections.push(intersection);
+        }
+    }
+
+    let minimal_blocking_sets = minimal_hitting_sets(&minimal_quorums);
+    let minimal_splitting_sets = minimal_sets(intersections);
+
+    Ok(QuorumAnalysis {
+        nodes: nodes.clone(),
+        unknown_nodes,
+        minimal_quorums: minimal_quorums.iter().cloned().map(to_ids).collect(),
+        disjoint_quorums,
+        minimal_blocking_sets: minimal_blocking_sets.into_iter().map(to_ids).collect(),
+        minimal_splitting_sets: minimal_splitting_sets.into_iter().map(to_ids).collect(),
+    })
+}
+
+fn bit(index: usize) -> NodeSet {
+    1 << index
+}
+
+/// Indices of the nodes in `set`.
+fn indices(set: NodeSet) -> impl Iterator<Item = usize> {
+    (0..MAX_ANALYZED_NODES).filter(move |index| set & bit(*index) != 0)
+}
+
+/// A quorum set over node indices.
+struct IndexedQuorumSet {
+    threshold: u32,
+    members: Vec<IndexedMember>,
+}
+
+enum IndexedMember {
+    Node(usize),
+    InnerSet(IndexedQuorumSet),
+
+    /// A node whose quorum set is not known. It never satisfies a quorum set.
+    Unknown,
+}
+
+impl IndexedQuorumSet {
+    fn new<ID: GenericNodeId>(quorum_set: &QuorumSet<ID>, index_of: &HashMap<ID, usize>) -> Self {
+        Self {
+            threshold: quorum_set.threshold,
+            members: quorum_set
+                .members
+                .iter()
+                .map(|member| match member {
+                    QuorumSetMember::Node(node_id) => index_of
+                        .get(node_id)
+                        .map(|index| IndexedMember::Node(*index))
+                        .unwrap_or(IndexedMember::Unknown),
+                    QuorumSetMember::InnerSet(inner_set) => {
+                        IndexedMember::InnerSet(Self::new(inner_set, index_of))
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns true if the nodes in `set` meet this quorum set's threshold.
+    fn is_satisfied_by(&self, set: NodeSet) -> bool {
+        let num_satisfied = self
+            .members
+            .iter()
+            .filter(|member| match member {
+                IndexedMember::Node(index) => set & bit(*index) != 0,
+                IndexedMember::InnerSet(inner_set) => inner_set.is_satisfied_by(set),
+                IndexedMember::Unknown => false,
+            })
+            .count();
+        num_satisfied >= self.threshold as usize
+    }
+
+    /// Nodes referenced by this quorum set.
+    fn nodes(&self) -> NodeSet {
+        self.members.iter().fold(0, |set, member| match member {
+            IndexedMember::Node(index) => set | bit(*index),
+            IndexedMember::InnerSet(inner_set) => set | inner_set.nodes(),
+            IndexedMember::Unknown => set,
+        })
+    }
+}
+
+struct IndexedNetwork {
+    quorum_sets: Vec<IndexedQuorumSet>,
+}
+
+impl IndexedNetwork {
+    fn new<ID: GenericNodeId>(nodes: &[ID], quorum_sets: &HashMap<ID, QuorumSet<ID>>) -> Self {
+        let index_of: HashMap<ID, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node_id)| (node_id.clone(), index))
+            .collect();
+        Self {
+            quorum_sets: nodes
+                .iter()
+                .map(|node_id| IndexedQuorumSet::new(&quorum_sets[node_id], &index_of))
+                .collect(),
+        }
+    }
+
+    fn all_nodes(&self) -> NodeSet {
+        indices(NodeSet::max_value())
+            .take(self.quorum_sets.len())
+            .fold(0, |set, index| set | bit(index))
+    }
+
+    /// The largest quorum contained in `set`, or 0 if there is none. This is the union of all
+    /// quorums contained in `set`.
+    fn max_quorum_within(&self, set: NodeSet) -> NodeSet {
+        let mut cur = set;
+        loop {
+            let next = indices(cur)
+                .filter(|index| self.quorum_sets[*index].is_satisfied_by(cur))
+                .fold(0, |set, index| set | bit(index));
+            if next == cur {
+                return cur;
+            }
+            cur = next;
+        }
+    }
+
+    fn is_quorum(&self, set: NodeSet) -> bool {
+        set != 0 && self.max_quorum_within(set) == set
+    }
+
+    fn minimal_quorums(&self) -> Vec<NodeSet> {
+        let mut found = Vec::new();
+        self.find_quorums(0, self.all_nodes(), &mut found);
+        minimal_sets(found)
+    }
+
+    /// Finds quorums that contain `selected` and are contained in `available`. Every minimal
+    /// such quorum is added to `found`, possibly along with non-minimal ones.
+    fn find_quorums(&self, selected: NodeSet, available: NodeSet, found: &mut Vec<NodeSet>) {
+        if self.is_quorum(selected) {
+            found.push(selected);
+            return;
+        }
+
+        // A quorum containing a quorum we already found is not minimal.
+        if found.iter().any(|quorum| quorum & !selected == 0) {
+            return;
+        }
+
+        // Nodes outside of the largest quorum within `available` belong to no quorum we are
+        // looking for.
+        let available = self.max_quorum_within(available);
+        if available & selected != selected {
+            return;
+        }
+
+        // Decide on a node next. Once a node is selected, only nodes its quorum set depends on
+        // are worth adding: any other node would make the quorum non-minimal.
+        let undecided = available & !selected;
+        let candidates = if selected == 0 {
+            undecided
+        } else {
+            undecided
+                & indices(selected).fold(0, |set, index| set | self.quorum_sets[index].nodes())
+        };
+        let index = match indices(candidates).next() {
+            Some(index) => index,
+            None => return,
+        };
+
+        self.find_quorums(selected | bit(index), available, found);
+        self.find_quorums(selected, available & !bit(index), found);
+    }
+}
+
+/// The minimal sets that intersect every set in `sets`.
+fn minimal_hitting_sets(sets: &[NodeSet]) -> Vec<NodeSet> {
+    fn helper(sets: &[NodeSet], chosen: NodeSet, found: &mut Vec<NodeSet>) {
+        if found.iter().any(|set| set & !chosen == 0) {
+            return;
+        }
+        // Branch on the members of the smallest set we do not intersect yet.
+        match sets
+            .iter()
+            .filter(|set| *set & chosen == 0)
+            .min_by_key(|set| set.count_ones())
+        {
+            None => found.push(chosen),
+            Some(set) => {
+                for index in indices(*set) {
+                    helper(sets, chosen | bit(index), found);
+                }
+            }
+        }
+    }
+
+    let mut found = Vec::new();
+    helper(sets, 0, &mut found);
+    minimal_sets(found)
+}
+
+/// The sets in `sets` that do not contain another set, smallest first.
+fn minimal_sets(mut sets: Vec<NodeSet>) -> Vec<NodeSet> {
+    sets.sort_by_key(|set| (set.count_ones(), *set));
+    sets.dedup();
+
+    let mut result: Vec<NodeSet> = Vec::new();
+    for set in sets {
+        if !result.iter().any(|smaller| smaller & !set == 0) {
+            result.push(set);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod quorum_analysis_tests {
+    use super::*;
+    use crate::test_utils::test_node_id;
+    use mc_common::NodeID;
+
+    /// Each node trusts `k` of the other nodes (see `SCPNetwork::new_mesh`).
+    fn mesh(num_nodes: u32, k: u32) -> HashMap<NodeID, QuorumSet> {
+        (0..num_nodes)
+            .map(|node| {
+                let others = (0..num_nodes)
+                    .filter(|other| *other != node)
+                    .map(test_node_id)
+                    .collect();
+                (test_node_id(node), QuorumSet::new_with_node_ids(k, others))
+            })
+            .collect()
+    }
+
+    /// Each node trusts the next one (see `SCPNetwork::new_cyclic`).
+    fn cyclic(num_nodes: u32) -> HashMap<NodeID, QuorumSet> {
+        (0..num_nodes)
+            .map(|node| {
+                (
+                    test_node_id(node),
+                    QuorumSet::new_with_node_ids(1, vec![test_node_id((node + 1) % num_nodes)]),
+                )
+            })
+            .collect()
+    }
+
+    fn ids(nodes: &[u32]) -> Vec<NodeID> {
+        let mut ids: Vec<NodeID> = nodes.iter().cloned().map(test_node_id).collect();
+        ids.sort_by_key(|node_id| node_id.to_string());
+        ids
+    }
+
+    #[test]
+    fn test_minimal_sets() {
+        assert_eq!(
+            minimal_sets(vec![0b111, 0b011, 0b110, 0b011]),
+            vec![0b011, 0b110]
+        );
+        assert_eq!(minimal_sets(vec![0b101, 0]), vec![0]);
+        assert_eq!(minimal_hitting_sets(&[0b011, 0b110]), vec![0b010, 0b101]);
+        assert_eq!(minimal_hitting_sets(&[]), vec![0]);
+    }
+
+    #[test]
+    fn test_mesh_network() {
+        // Any 3 of 4 nodes form a quorum.
+        let analysis = analyze(&mesh(4, 2)).unwrap();
+        assert_eq!(analysis.nodes, ids(&[0, 1, 2, 3]));
+        assert!(analysis.unknown_nodes.is_empty());
+        assert_eq!(analysis.minimal_quorums.len(), 4);
+        assert!(analysis
+            .minimal_quorums
+            .iter()
+            .all(|quorum| quorum.len() == 3));
+        assert!(analysis.has_quorum_intersection());
+
+        // Any 2 nodes halt the network, and 2 Byzantine nodes can fork it.
+        assert_eq!(analysis.minimal_blocking_sets.len(), 6);
+        assert_eq!(analysis.min_blocking_set_size(), Some(2));
+        assert_eq!(analysis.minimal_splitting_sets.len(), 6);
+        assert_eq!(analysis.min_splitting_set_size(), Some(2));
+
+        // All nodes are equally critical.
+        let weakest_nodes = analysis.weakest_nodes();
+        assert_eq!(weakest_nodes.len(), 4);
+        assert!(weakest_nodes
+            .iter()
+            .all(|criticality| criticality.num_blocking_sets == 3
+                && criticality.num_splitting_sets == 3));
+    }
+
+    #[test]
+    fn test_mesh_network_without_intersection() {
+        // Any 2 of 4 nodes form a quorum, so two disjoint pairs can externalize different values.
+        let analysis = analyze(&mesh(4, 1)).unwrap();
+        assert_eq!(analysis.minimal_quorums.len(), 6);
+        assert!(!analysis.has_quorum_intersection());
+        let (quorum, other_quorum) = analysis.disjoint_quorums.clone().unwrap();
+        assert!(quorum.iter().all(|node_id| !other_quorum.contains(node_id)));
+        assert_eq!(analysis.minimal_splitting_sets, vec![Vec::<NodeID>::new()]);
+        assert_eq!(analysis.min_splitting_set_size(), Some(0));
+
+        // Three nodes must fail to halt it.
+        assert_eq!(analysis.min_blocking_set_size(), Some(3));
+    }
+
+    #[test]
+    fn test_cyclic_network() {
+        // The only quorum is the whole network, so any single node halts it.
+        let analysis = analyze(&cyclic(5)).unwrap();
+        assert_eq!(analysis.minimal_quorums, vec![ids(&[0, 1, 2, 3, 4])]);
+        assert!(analysis.has_quorum_intersection());
+        assert_eq!(analysis.minimal_blocking_sets.len(), 5);
+        assert_eq!(analysis.min_blocking_set_size(), Some(1));
+
+        // There is a single minimal quorum, so the network cannot fork.
+        assert!(analysis.minimal_splitting_sets.is_empty());
+        assert_eq!(analysis.min_splitting_set_size(), None);
+    }
+
+    #[test]
+    fn test_tiered_network() {
+        // Nodes 0-3 form a core that trusts 3 of its 4 members. Nodes 4 and 5 trust 2 core nodes.
+        let mut quorum_sets = mesh(4, 2);
+        for node in 4..6 {
+            quorum_sets.insert(
+                test_node_id(node),
+                QuorumSet::new_with_node_ids(2, ids(&[0, 1, 2])),
+            );
+        }
+
+        let analysis = analyze(&quorum_sets).unwrap();
+        // Nodes outside the core belong to no minimal quorum, and are not critical.
+        assert_eq!(analysis.minimal_quorums.len(), 4);
+        assert!(analysis.has_quorum_intersection());
+        assert_eq!(analysis.min_blocking_set_size(), Some(2));
+        assert!(analysis
+            .weakest_nodes()
+            .iter()
+            .all(|criticality| ids(&[0, 1, 2, 3]).contains(&criticality.node_id)));
+    }
+
+    #[test]
+    fn test_unknown_nodes() {
+        // Node 2 is trusted by node 0, but its quorum set is not known.
+        let mut quorum_sets = HashMap::default();
+        quorum_sets.insert(
+            test_node_id(0),
+            QuorumSet::new_with_node_ids(2, ids(&[1, 2])),
+        );
+        quorum_sets.insert(test_node_id(1), QuorumSet::new_with_node_ids(1, ids(&[0])));
+
+        let analysis = analyze(&quorum_sets).unwrap();
+        assert_eq!(analysis.unknown_nodes, ids(&[2]));
+        assert!(analysis.minimal_quorums.is_empty());
+        assert_eq!(analysis.minimal_blocking_sets, vec![Vec::<NodeID>::new()]);
+        assert_eq!(analysis.min_blocking_set_size(), Some(0));
+    }
+
+    #[test]
+    fn test_too_many_nodes() {
+        assert_eq!(
+            analyze(&mesh(MAX_ANALYZED_NODES as u32 + 1, 1)),
+            Err(QuorumAnalysisError::TooManyNodes(MAX_ANALYZED_NODES + 1))
+        );
+    }
+}
@@ -80,6 +80,20 @@ impl<
         Self::new(0, vec![])
     }
 
+    /// Returns true if every threshold in this quorum set can be met: each threshold must be
+    /// non-zero and at most the number of members of its set. The empty quorum set is valid.
+    pub fn is_valid(&self) -> bool {
+        if self.members.is_empty() {
+            return self.threshold == 0;
+        }
+        self.threshold > 0
+            && self.threshold as usize <= self.members.len()
+            && self.members.iter().all(|member| match member {
+                QuorumSetMember::Node(_) => true,
+                QuorumSetMember::InnerSet(quorum_set) => quorum_set.is_valid(),
+            })
+    }
+
     /// Returns a flattened set of all nodes contained in q and its nested QSets.
     pub fn nodes(&self) -> HashSet<ID> {
         let mut result = HashSet::<ID>::default();
@@ -369,6 +383,31 @@ mod quorum_set_tests {
     use crate::{core_types::*, msg::*, predicates::*, test_utils::test_node_id};
     use mc_common::ResponderId;
 
+    #[test]
+    fn test_is_valid() {
+        assert!(QuorumSet::<NodeID>::empty().is_valid());
+        assert!(QuorumSet::new_with_node_ids(1, vec![test_node_id(1)]).is_valid());
+        assert!(QuorumSet::new_with_node_ids(2, vec![test_node_id(1), test_node_id(2)]).is_valid());
+
+        // Thresholds that can never be met.
+        assert!(!QuorumSet::<NodeID>::new_with_node_ids(1, vec![]).is_valid());
+        assert!(!QuorumSet::new_with_node_ids(0, vec![test_node_id(1)]).is_valid());
+        assert!(
+            !QuorumSet::new_with_node_ids(3, vec![test_node_id(1), test_node_id(2)]).is_valid()
+        );
+
+        // Inner sets are checked too.
+        let invalid_inner_set = QuorumSet::new_with_node_ids(2, vec![test_node_id(3)]);
+        assert!(!QuorumSet::new_with_inner_sets(
+            1,
+            vec![
+                QuorumSet::new_with_node_ids(1, vec![test_node_id(1)]),
+                invalid_inner_set
+            ]
+        )
+        .is_valid());
+    }
+
     #[test]
     // findBlockingSet returns an empty set when there is no blocking set
     fn test_no_blocking_set() {
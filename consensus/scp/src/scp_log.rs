@@ -1,7 +1,10 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
 //! This crate provides a logging framework for recording and replaying SCP messages.
-use crate::{slot::SlotMetrics, Msg, QuorumSet, ScpNode, SlotIndex, Value};
+use crate::{
+    slot::{SlotMetrics, SlotState},
+    Msg, QuorumSet, ScpNode, SlotIndex, Value,
+};
 use mc_common::NodeID;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -179,15 +182,14 @@ impl<V: Value, N: ScpNode<V>> ScpNode<V> for LoggingScpNode<V, N> {
         self.node.has_externalized_values(slot_index)
     }
 
-    fn process_timeouts(&mut self) -> Vec<Msg<V>> {
-        let out_msgs = self.node.process_timeouts();
+    fn process_timeouts(&mut self) -> Result<Vec<Msg<V>>, String> {
+        let out_msgs = self.node.process_timeouts()?;
 
         if !out_msgs.is_empty() {
-            self.write(LoggedMsg::ProcessTimeouts(out_msgs.clone()))
-                .expect("failed writing");
+            self.write(LoggedMsg::ProcessTimeouts(out_msgs.clone()))?;
         }
 
-        out_msgs
+        Ok(out_msgs)
     }
 
     fn get_slot_metrics(&mut self, slot_index: SlotIndex) -> Option<SlotMetrics> {
@@ -197,6 +199,18 @@ impl<V: Value, N: ScpNode<V>> ScpNode<V> for LoggingScpNode<V, N> {
     fn clear_pending_slots(&mut self) {
         self.node.clear_pending_slots()
     }
+
+    fn set_quorum_set(&mut self, quorum_set: QuorumSet) {
+        self.node.set_quorum_set(quorum_set)
+    }
+
+    fn get_slot_state(&mut self, slot_index: SlotIndex) -> Option<SlotState<V>> {
+        self.node.get_slot_state(slot_index)
+    }
+
+    fn restore_slot_state(&mut self, state: SlotState<V>) -> Result<(), String> {
+        self.node.restore_slot_state(state)
+    }
 }
 
 /// An SCP log reader, to read a series of SCP messages.
@@ -0,0 +1,268 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Durable storage of SCP slot state, so that a node that restarts mid-slot does not contradict
+//! the statements it emitted before the restart.
+use crate::{
+    slot::{SlotMetrics, SlotState},
+    Msg, QuorumSet, ScpNode, SlotIndex, Value,
+};
+use mc_common::NodeID;
+use serde::de::DeserializeOwned;
+use std::{
+    collections::BTreeSet,
+    fs::{create_dir_all, read, rename, File},
+    io::{ErrorKind, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+/// A node that saves the state of its current slot before any outgoing message is returned to the
+/// caller for broadcasting.
+pub struct PersistentScpNode<V: Value, N: ScpNode<V>> {
+    /// File holding the state of the highest slot we have emitted a message for.
+    state_path: PathBuf,
+
+    /// Highest slot number we've saved the state of so far.
+    highest_slot_index: Option<SlotIndex>,
+
+    /// Underlying node implementation.
+    node: N,
+
+    _v: PhantomData<V>,
+}
+
+impl<V: Value, N: ScpNode<V>> PersistentScpNode<V, N> {
+    /// Create a new PersistentScpNode.
+    ///
+    /// # Arguments
+    /// * `node` - The node whose state should be persisted.
+    /// * `state_path` - File to save the slot state to. Its directory is created if missing.
+    pub fn new(node: N, state_path: PathBuf) -> Result<Self, String> {
+        if let Some(dir) = state_path.parent() {
+            create_dir_all(dir)
+                .map_err(|e| format!("Failed creating directory {:?}: {:?}", dir, e))?;
+        }
+
+        Ok(Self {
+            state_path,
+            highest_slot_index: None,
+            node,
+            _v: Default::default(),
+        })
+    }
+
+    /// Save the state of `slot_index`, unless a later slot has already been saved.
+    fn save(&mut self, slot_index: SlotIndex) -> Result<(), String> {
+        if self
+            .highest_slot_index
+            .map_or(false, |highest| slot_index < highest)
+        {
+            return Ok(());
+        }
+
+        let state = match self.node.get_slot_state(slot_index) {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        let bytes =
+            mc_util_serial::serialize(&state).map_err(|e| format!("failed serialize: {:?}", e))?;
+
+        // Write to a temporary file and rename it over the previous state, so that a crash while
+        // writing never leaves a truncated state behind.
+        let tmp_path = self.state_path.with_extension("tmp");
+        let mut file = File::create(&tmp_path)
+            .map_err(|e| format!("failed creating {:?}: {:?}", tmp_path, e))?;
+        file.write_all(&bytes)
+            .map_err(|e| format!("failed writing {:?}: {:?}", tmp_path, e))?;
+        file.sync_all()
+            .map_err(|e| format!("failed syncing {:?}: {:?}", tmp_path, e))?;
+        rename(&tmp_path, &self.state_path).map_err(|e| {
+            format!(
+                "failed renaming {:?} to {:?}: {:?}",
+                tmp_path, self.state_path, e
+            )
+        })?;
+        if let Some(dir) = self.state_path.parent() {
+            File::open(dir)
+                .and_then(|dir| dir.sync_all())
+                .map_err(|e| format!("failed syncing {:?}: {:?}", dir, e))?;
+        }
+
+        self.highest_slot_index = Some(slot_index);
+        Ok(())
+    }
+}
+
+impl<V: Value + DeserializeOwned, N: ScpNode<V>> PersistentScpNode<V, N> {
+    /// Restore the saved slot state, if it is for `slot_index`.
+    ///
+    /// A state saved for any other slot is stale (e.g. the slot was externalized and written to
+    /// the ledger before the restart) and is ignored.
+    ///
+    /// Returns true if a slot was restored.
+    pub fn restore(&mut self, slot_index: SlotIndex) -> Result<bool, String> {
+        match load_slot_state(&self.state_path)? {
+            Some(state) if state.slot_index == slot_index => {
+                self.node.restore_slot_state(state)?;
+                self.highest_slot_index = Some(slot_index);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+/// Load a slot state saved by a `PersistentScpNode`, if there is one.
+pub fn load_slot_state<V: Value + DeserializeOwned>(
+    state_path: &Path,
+) -> Result<Option<SlotState<V>>, String> {
+    let bytes = match read(state_path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("failed reading {:?}: {:?}", state_path, e)),
+    };
+    mc_util_serial::deserialize(&bytes)
+        .map(Some)
+        .map_err(|e| format!("failed deserializing {:?}: {:?}", state_path, e))
+}
+
+impl<V: Value, N: ScpNode<V>> ScpNode<V> for PersistentScpNode<V, N> {
+    fn node_id(&self) -> NodeID {
+        self.node.node_id()
+    }
+
+    fn quorum_set(&self) -> QuorumSet {
+        self.node.quorum_set()
+    }
+
+    fn nominate(
+        &mut self,
+        slot_index: SlotIndex,
+        values: BTreeSet<V>,
+    ) -> Result<Option<Msg<V>>, String> {
+        let out_msg = self.node.nominate(slot_index, values)?;
+
+        if let Some(ref msg) = out_msg {
+            self.save(msg.slot_index)?;
+        }
+
+        Ok(out_msg)
+    }
+
+    fn handle(&mut self, msg: &Msg<V>) -> Result<Option<Msg<V>>, String> {
+        let out_msg = self.node.handle(msg)?;
+
+        if let Some(ref msg) = out_msg {
+            self.save(msg.slot_index)?;
+        }
+
+        Ok(out_msg)
+    }
+
+    fn get_externalized_values(&self, slot_index: SlotIndex) -> Vec<V> {
+        self.node.get_externalized_values(slot_index)
+    }
+
+    fn has_externalized_values(&self, slot_index: SlotIndex) -> bool {
+        self.node.has_externalized_values(slot_index)
+    }
+
+    fn process_timeouts(&mut self) -> Result<Vec<Msg<V>>, String> {
+        let out_msgs = self.node.process_timeouts()?;
+
+        let mut slot_indexes: Vec<SlotIndex> = out_msgs.iter().map(|msg| msg.slot_index).collect();
+        slot_indexes.sort();
+        slot_indexes.dedup();
+        for slot_index in slot_indexes {
+            // Broadcasting a message whose state was not saved could violate safety after a
+            // restart.
+            self.save(slot_index)?;
+        }
+
+        Ok(out_msgs)
+    }
+
+    fn get_slot_metrics(&mut self, slot_index: SlotIndex) -> Option<SlotMetrics> {
+        self.node.get_slot_metrics(slot_index)
+    }
+
+    fn clear_pending_slots(&mut self) {
+        self.node.clear_pending_slots()
+    }
+
+    fn set_quorum_set(&mut self, quorum_set: QuorumSet) {
+        self.node.set_quorum_set(quorum_set)
+    }
+
+    fn get_slot_state(&mut self, slot_index: SlotIndex) -> Option<SlotState<V>> {
+        self.node.get_slot_state(slot_index)
+    }
+
+    fn restore_slot_state(&mut self, state: SlotState<V>) -> Result<(), String> {
+        self.node.restore_slot_state(state)
+    }
+}
+
+#[cfg(test)]
+mod scp_state_tests {
+    use super::*;
+    use crate::{node::Node, test_utils::*};
+    use mc_common::logger::{test_with_logger, Logger};
+    use std::{iter::FromIterator, sync::Arc};
+    use tempdir::TempDir;
+
+    fn new_node(logger: Logger) -> Node<u32, TransactionValidationError> {
+        Node::new(
+            test_node_id(2),
+            QuorumSet::new_with_node_ids(1, vec![test_node_id(1)]),
+            Arc::new(trivial_validity_fn),
+            Arc::new(trivial_combine_fn),
+            logger,
+        )
+    }
+
+    #[test_with_logger]
+    // The state saved before a message is returned restores an identical slot.
+    fn save_and_restore(logger: Logger) {
+        let dir = TempDir::new("scp_state").unwrap();
+        let state_path = dir.path().join("slot_state");
+
+        let mut node =
+            PersistentScpNode::new(new_node(logger.clone()), state_path.clone()).unwrap();
+        let msg = node
+            .nominate(1, BTreeSet::from_iter(vec![1000, 2000]))
+            .unwrap()
+            .expect("expected an outgoing message");
+
+        let saved: SlotState<u32> = load_slot_state(&state_path).unwrap().unwrap();
+        assert_eq!(saved.slot_index, 1);
+        assert_eq!(saved.last_sent_msg, Some(msg));
+        assert_eq!(Some(saved.clone()), node.get_slot_state(1));
+
+        // A restarted node does not restore a stale slot.
+        let mut restarted =
+            PersistentScpNode::new(new_node(logger.clone()), state_path.clone()).unwrap();
+        assert_eq!(restarted.restore(2), Ok(false));
+        assert_eq!(restarted.get_slot_state(2), None);
+
+        // It restores the slot it was working on.
+        let mut restarted = PersistentScpNode::new(new_node(logger), state_path).unwrap();
+        assert_eq!(restarted.restore(1), Ok(true));
+        assert_eq!(restarted.get_slot_state(1), Some(saved));
+
+        // Nominating the same values again does not emit a duplicate message.
+        assert_eq!(
+            restarted.nominate(1, BTreeSet::from_iter(vec![1000, 2000])),
+            Ok(None)
+        );
+    }
+
+    #[test_with_logger]
+    // Nothing is restored when no state was saved.
+    fn restore_without_saved_state(logger: Logger) {
+        let dir = TempDir::new("scp_state").unwrap();
+        let mut node =
+            PersistentScpNode::new(new_node(logger), dir.path().join("slot_state")).unwrap();
+        assert_eq!(node.restore(0), Ok(false));
+    }
+}
@@ -0,0 +1,931 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A deterministic network simulator for SCP.
+//!
+//! Every node runs in a single thread against a virtual clock, so a simulation is a pure function
+//! of its configuration, seed and fault schedule. Messages are delivered with seeded random
+//! delays (which reorders them), and a `Schedule` can additionally drop or delay individual
+//! messages and partition the network for a window of time. Nodes may also be Byzantine.
+//!
+//! After a run, the externalized values of all honest nodes are checked for agreement (and,
+//! optionally, for liveness). When a schedule makes a run fail, `shrink` reduces it to a
+//! schedule that still fails but from which no single fault can be removed.
+
+use crate::{
+    core_types::{Ballot, SlotIndex},
+    msg::{CommitPayload, ExternalizePayload, Msg, NominatePayload, PreparePayload, Topic},
+    node::{Node, ScpNode},
+    quorum_set::QuorumSet,
+    test_utils::{test_node_id, trivial_combine_fn, trivial_validity_fn},
+};
+use mc_common::{
+    logger::{log, Logger},
+    HashMap, HashSet,
+};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_hc::Hc128Rng as FixedRng;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Values nominated by honest nodes are below this bound.
+const EQUIVOCATION_BIT: u32 = 1 << 31;
+
+/// Each node also nominates a value of its own, at or above this bound.
+const UNIQUE_VALUES_START: u32 = 1 << 30;
+
+/// How a Byzantine node misbehaves.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ByzantineBehavior {
+    /// Runs the protocol, but never sends a message.
+    Withhold,
+
+    /// Sends its real messages to even-numbered peers, and the same messages with every value
+    /// replaced by a different one to odd-numbered peers.
+    Equivocate,
+}
+
+/// A fault injected into a simulation.
+///
+/// Messages are identified by their sequence number: every message sent to a peer gets the next
+/// number, starting at zero. A simulation is deterministic, so the same message gets the same
+/// number in every run of the same configuration and seed, as long as the faults that precede it
+/// are unchanged.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Fault {
+    /// The message is never delivered.
+    Drop(u64),
+
+    /// The message is delivered later than it would otherwise be, after messages sent after it.
+    Delay(u64, Duration),
+
+    /// Messages between `isolated` and the other nodes are dropped while the virtual time is in
+    /// `[start, end)`.
+    Partition {
+        /// Start of the partition.
+        start: Duration,
+
+        /// End of the partition.
+        end: Duration,
+
+        /// Nodes cut off from the rest of the network.
+        isolated: BTreeSet<usize>,
+    },
+}
+
+/// The faults injected into a simulation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Schedule {
+    /// Faults, in no particular order.
+    pub faults: Vec<Fault>,
+}
+
+impl Schedule {
+    /// A schedule with no faults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A random schedule.
+    ///
+    /// Partitions end before half of `config.max_duration`, so that a network that tolerates
+    /// them has time to recover.
+    ///
+    /// # Arguments
+    /// * `config` - The simulation the schedule is for.
+    /// * `seed` - Seed for choosing the faults.
+    /// * `num_faults` - Number of faults.
+    /// * `max_seq` - Messages with a sequence number below this may be dropped or delayed.
+    pub fn random(config: &SimulationConfig, seed: u64, num_faults: usize, max_seq: u64) -> Self {
+        let mut rng = FixedRng::seed_from_u64(seed);
+        let num_nodes = config.quorum_sets.len();
+        let max_msec = config.max_duration.as_millis() as u64 / 2;
+
+        let faults = (0..num_faults)
+            .map(|_| match rng.gen_range(0, 3) {
+                0 => Fault::Drop(rng.gen_range(0, max_seq)),
+                1 => Fault::Delay(
+                    rng.gen_range(0, max_seq),
+                    Duration::from_millis(
+                        rng.gen_range(1, 5 * config.max_delay.as_millis() as u64 + 2),
+                    ),
+                ),
+                _ => {
+                    let start = rng.gen_range(0, max_msec);
+                    let end = rng.gen_range(start, max_msec + 1);
+                    let mut nodes: Vec<usize> = (0..num_nodes).collect();
+                    nodes.shuffle(&mut rng);
+                    let num_isolated = rng.gen_range(1, num_nodes / 2 + 1);
+                    Fault::Partition {
+                        start: Duration::from_millis(start),
+                        end: Duration::from_millis(end),
+                        isolated: nodes.into_iter().take(num_isolated).collect(),
+                    }
+                }
+            })
+            .collect();
+
+        Self { faults }
+    }
+
+    /// Number of faults.
+    pub fn len(&self) -> usize {
+        self.faults.len()
+    }
+
+    /// True if the schedule has no faults.
+    pub fn is_empty(&self) -> bool {
+        self.faults.is_empty()
+    }
+}
+
+/// The network to simulate.
+#[derive(Clone, Debug)]
+pub struct SimulationConfig {
+    /// Quorum set of each node. Node `i` has ID `test_node_id(i)`.
+    pub quorum_sets: Vec<QuorumSet>,
+
+    /// Nodes that are Byzantine, and how.
+    pub byzantine: BTreeMap<usize, ByzantineBehavior>,
+
+    /// Number of slots every node tries to externalize, starting at slot 0.
+    pub num_slots: SlotIndex,
+
+    /// Number of values shared by all nodes in each slot. Every node nominates a random subset of
+    /// them, and a value of its own.
+    pub values_per_slot: usize,
+
+    /// Shortest delay of a message.
+    pub min_delay: Duration,
+
+    /// Longest delay of a message, unless it is delayed by a fault.
+    pub max_delay: Duration,
+
+    /// The nodes' `scp_timebase`.
+    pub timebase: Duration,
+
+    /// How often nodes process timeouts.
+    pub tick_interval: Duration,
+
+    /// How often nodes resend the last message they sent for each slot, so that they recover
+    /// from dropped messages.
+    pub rebroadcast_interval: Duration,
+
+    /// The simulation stops at this virtual time.
+    pub max_duration: Duration,
+
+    /// Whether it is a violation for an honest node to not externalize every slot.
+    pub check_liveness: bool,
+}
+
+impl SimulationConfig {
+    /// A network where every node requires `threshold` of its peers, with no Byzantine nodes.
+    ///
+    /// # Arguments
+    /// * `num_nodes` - Number of nodes.
+    /// * `threshold` - Number of peers in each node's quorum set threshold.
+    pub fn mesh(num_nodes: usize, threshold: u32) -> Self {
+        let quorum_sets = (0..num_nodes)
+            .map(|node| {
+                let peers = (0..num_nodes)
+                    .filter(|peer| *peer != node)
+                    .map(|peer| test_node_id(peer as u32))
+                    .collect();
+                QuorumSet::new_with_node_ids(threshold, peers)
+            })
+            .collect();
+
+        Self {
+            quorum_sets,
+            byzantine: BTreeMap::new(),
+            num_slots: 3,
+            values_per_slot: 5,
+            min_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(50),
+            timebase: Duration::from_millis(100),
+            tick_interval: Duration::from_millis(10),
+            rebroadcast_interval: Duration::from_millis(500),
+            max_duration: Duration::from_secs(60),
+            check_liveness: true,
+        }
+    }
+
+    fn is_honest(&self, node: usize) -> bool {
+        !self.byzantine.contains_key(&node)
+    }
+}
+
+/// A broken invariant.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Violation {
+    /// Two honest nodes externalized different values for a slot.
+    Disagreement {
+        /// The slot.
+        slot_index: SlotIndex,
+
+        /// The first node.
+        node_a: usize,
+
+        /// Values the first node externalized.
+        values_a: Vec<u32>,
+
+        /// The second node.
+        node_b: usize,
+
+        /// Values the second node externalized.
+        values_b: Vec<u32>,
+    },
+
+    /// An honest node did not externalize a slot before the end of the simulation.
+    NoProgress {
+        /// The node.
+        node: usize,
+
+        /// The first slot it did not externalize.
+        slot_index: SlotIndex,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Disagreement {
+                slot_index,
+                node_a,
+                values_a,
+                node_b,
+                values_b,
+            } => write!(
+                f,
+                "Slot {}: node {} externalized {:?}, node {} externalized {:?}",
+                slot_index, node_a, values_a, node_b, values_b
+            ),
+            Self::NoProgress { node, slot_index } => {
+                write!(f, "Node {} did not externalize slot {}", node, slot_index)
+            }
+        }
+    }
+}
+
+/// The outcome of a simulation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SimulationResult {
+    /// Values each node externalized, by slot.
+    pub externalized: Vec<BTreeMap<SlotIndex, Vec<u32>>>,
+
+    /// Broken invariants.
+    pub violations: Vec<Violation>,
+
+    /// Number of messages sent to peers, including dropped ones.
+    pub num_msgs_sent: u64,
+
+    /// Number of messages dropped by faults.
+    pub num_msgs_dropped: u64,
+
+    /// Errors returned by nodes while handling messages.
+    pub num_errors: u64,
+
+    /// Virtual time when the simulation stopped.
+    pub elapsed: Duration,
+}
+
+impl SimulationResult {
+    /// True if no invariant was broken.
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Something that happens at a point in virtual time.
+enum Event {
+    /// Deliver a message to a node.
+    Deliver(usize, Msg<u32>),
+
+    /// Every node processes timeouts and rebroadcasts.
+    Tick,
+}
+
+/// A simulated node.
+struct SimNode {
+    scp_node: Box<dyn ScpNode<u32>>,
+
+    /// The first slot this node has not externalized.
+    current_slot: SlotIndex,
+
+    externalized: BTreeMap<SlotIndex, Vec<u32>>,
+
+    /// The last message sent for each slot.
+    last_sent: BTreeMap<SlotIndex, Msg<u32>>,
+}
+
+/// The state of a running simulation.
+struct Simulation<'a> {
+    config: &'a SimulationConfig,
+    rng: FixedRng,
+
+    /// Current virtual time.
+    now: Duration,
+
+    /// Current virtual time in nanoseconds, shared with the nodes' clocks.
+    clock_nanos: Arc<AtomicU64>,
+
+    nodes: Vec<SimNode>,
+
+    /// Pending events, by time and then by the order they were scheduled in.
+    queue: BTreeMap<(Duration, u64), Event>,
+    num_events: u64,
+
+    /// Shared values nominated in each slot.
+    slot_values: Vec<Vec<u32>>,
+
+    drops: HashSet<u64>,
+    delays: HashMap<u64, Duration>,
+    partitions: Vec<(Duration, Duration, BTreeSet<usize>)>,
+    last_rebroadcast: Duration,
+
+    num_msgs_sent: u64,
+    num_msgs_dropped: u64,
+    num_errors: u64,
+    logger: Logger,
+}
+
+/// Run a simulation.
+///
+/// # Arguments
+/// * `config` - The network to simulate.
+/// * `seed` - Seed for message delays and nominated values.
+/// * `schedule` - Faults to inject.
+/// * `logger` - Logger.
+pub fn run(
+    config: &SimulationConfig,
+    seed: u64,
+    schedule: &Schedule,
+    logger: Logger,
+) -> SimulationResult {
+    let mut sim = Simulation::new(config, seed, schedule, logger);
+    sim.run();
+    sim.result()
+}
+
+impl<'a> Simulation<'a> {
+    fn new(config: &'a SimulationConfig, seed: u64, schedule: &Schedule, logger: Logger) -> Self {
+        let mut rng = FixedRng::seed_from_u64(seed);
+
+        let slot_values = (0..config.num_slots)
+            .map(|_| {
+                (0..config.values_per_slot)
+                    .map(|_| rng.gen_range(0, UNIQUE_VALUES_START))
+                    .collect()
+            })
+            .collect();
+
+        let base = Instant::now();
+        let clock_nanos = Arc::new(AtomicU64::new(0));
+
+        let nodes = config
+            .quorum_sets
+            .iter()
+            .enumerate()
+            .map(|(i, quorum_set)| {
+                let mut node = Node::new(
+                    test_node_id(i as u32),
+                    quorum_set.clone(),
+                    Arc::new(trivial_validity_fn),
+                    Arc::new(trivial_combine_fn),
+                    logger.clone(),
+                );
+                node.scp_timebase = config.timebase;
+                let nanos = clock_nanos.clone();
+                node.clock =
+                    Arc::new(move || base + Duration::from_nanos(nanos.load(Ordering::SeqCst)));
+
+                SimNode {
+                    scp_node: Box::new(node),
+                    current_slot: 0,
+                    externalized: BTreeMap::new(),
+                    last_sent: BTreeMap::new(),
+                }
+            })
+            .collect();
+
+        let mut drops = HashSet::default();
+        let mut delays = HashMap::default();
+        let mut partitions = Vec::new();
+        for fault in &schedule.faults {
+            match fault {
+                Fault::Drop(seq) => {
+                    drops.insert(*seq);
+                }
+                Fault::Delay(seq, delay) => {
+                    *delays.entry(*seq).or_insert_with(Duration::default) += *delay;
+                }
+                Fault::Partition {
+                    start,
+                    end,
+                    isolated,
+                } => partitions.push((*start, *end, isolated.clone())),
+            }
+        }
+
+        Self {
+            config,
+            rng,
+            now: Duration::default(),
+            clock_nanos,
+            nodes,
+            queue: BTreeMap::new(),
+            num_events: 0,
+            slot_values,
+            drops,
+            delays,
+            partitions,
+            last_rebroadcast: Duration::default(),
+            num_msgs_sent: 0,
+            num_msgs_dropped: 0,
+            num_errors: 0,
+            logger,
+        }
+    }
+
+    fn run(&mut self) {
+        for node in 0..self.nodes.len() {
+            self.nominate(node);
+            self.advance(node);
+        }
+        self.schedule(self.config.tick_interval, Event::Tick);
+
+        while !self.is_done() {
+            let key = match self.queue.keys().next() {
+                Some(key) => *key,
+                None => break,
+            };
+            if key.0 > self.config.max_duration {
+                break;
+            }
+            let event = self.queue.remove(&key).unwrap();
+
+            self.now = key.0;
+            self.clock_nanos
+                .store(self.now.as_nanos() as u64, Ordering::SeqCst);
+
+            match event {
+                Event::Deliver(node, msg) => {
+                    match self.nodes[node].scp_node.handle(&msg) {
+                        Ok(Some(out_msg)) => self.send(node, out_msg),
+                        Ok(None) => {}
+                        Err(err) => {
+                            log::debug!(self.logger, "Node {} failed handling msg: {}", node, err);
+                            self.num_errors += 1;
+                        }
+                    }
+                    self.advance(node);
+                }
+
+                Event::Tick => {
+                    for node in 0..self.nodes.len() {
+                        let out_msgs = self.nodes[node]
+                            .scp_node
+                            .process_timeouts()
+                            .expect("process_timeouts failed");
+                        for out_msg in out_msgs {
+                            self.send(node, out_msg);
+                        }
+                        self.advance(node);
+                    }
+
+                    if self.now - self.last_rebroadcast >= self.config.rebroadcast_interval {
+                        self.last_rebroadcast = self.now;
+                        for node in 0..self.nodes.len() {
+                            let msgs: Vec<Msg<u32>> =
+                                self.nodes[node].last_sent.values().cloned().collect();
+                            for msg in msgs {
+                                self.broadcast(node, &msg);
+                            }
+                        }
+                    }
+
+                    self.schedule(self.now + self.config.tick_interval, Event::Tick);
+                }
+            }
+        }
+    }
+
+    /// True once every honest node has externalized every slot.
+    fn is_done(&self) -> bool {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.config.is_honest(*i))
+            .all(|(_, node)| node.current_slot >= self.config.num_slots)
+    }
+
+    fn schedule(&mut self, at: Duration, event: Event) {
+        self.queue.insert((at, self.num_events), event);
+        self.num_events += 1;
+    }
+
+    /// Nominate values for the node's current slot.
+    fn nominate(&mut self, node: usize) {
+        let slot_index = self.nodes[node].current_slot;
+        if slot_index >= self.config.num_slots {
+            return;
+        }
+
+        let rng = &mut self.rng;
+        let mut values: BTreeSet<u32> = self.slot_values[slot_index as usize]
+            .iter()
+            .filter(|_| rng.gen::<bool>())
+            .cloned()
+            .collect();
+        values.insert(UNIQUE_VALUES_START + (slot_index as usize * self.nodes.len() + node) as u32);
+
+        match self.nodes[node].scp_node.nominate(slot_index, values) {
+            Ok(Some(out_msg)) => self.send(node, out_msg),
+            Ok(None) => {}
+            Err(err) => {
+                log::debug!(self.logger, "Node {} failed nominating: {}", node, err);
+                self.num_errors += 1;
+            }
+        }
+    }
+
+    /// Record the slots the node has externalized, and move it on to the next slot.
+    fn advance(&mut self, node: usize) {
+        loop {
+            let sim_node = &mut self.nodes[node];
+            let slot_index = sim_node.current_slot;
+            if slot_index >= self.config.num_slots
+                || !sim_node.scp_node.has_externalized_values(slot_index)
+            {
+                return;
+            }
+
+            let values = sim_node.scp_node.get_externalized_values(slot_index);
+            sim_node.externalized.insert(slot_index, values);
+            sim_node.current_slot += 1;
+            self.nominate(node);
+        }
+    }
+
+    /// Send a message the node emitted.
+    fn send(&mut self, node: usize, msg: Msg<u32>) {
+        self.broadcast(node, &msg);
+        self.nodes[node].last_sent.insert(msg.slot_index, msg);
+    }
+
+    /// Send a message to every peer of the node, subject to its Byzantine behavior.
+    fn broadcast(&mut self, node: usize, msg: &Msg<u32>) {
+        let behavior = self.config.byzantine.get(&node).cloned();
+        if behavior == Some(ByzantineBehavior::Withhold) {
+            return;
+        }
+
+        for peer in 0..self.nodes.len() {
+            if peer == node {
+                continue;
+            }
+            let peer_msg = if behavior == Some(ByzantineBehavior::Equivocate) && peer % 2 == 1 {
+                equivocate(msg)
+            } else {
+                msg.clone()
+            };
+            self.transmit(node, peer, peer_msg);
+        }
+    }
+
+    /// Schedule the delivery of a message, unless a fault drops it.
+    fn transmit(&mut self, from: usize, to: usize, msg: Msg<u32>) {
+        let seq = self.num_msgs_sent;
+        self.num_msgs_sent += 1;
+
+        let now = self.now;
+        let partitioned = self.partitions.iter().any(|(start, end, isolated)| {
+            *start <= now && now < *end && isolated.contains(&from) != isolated.contains(&to)
+        });
+        if partitioned || self.drops.contains(&seq) {
+            self.num_msgs_dropped += 1;
+            return;
+        }
+
+        let min_nanos = self.config.min_delay.as_nanos() as u64;
+        let max_nanos = self.config.max_delay.as_nanos() as u64;
+        let mut delay = Duration::from_nanos(self.rng.gen_range(min_nanos, max_nanos + 1));
+        if let Some(extra_delay) = self.delays.get(&seq) {
+            delay += *extra_delay;
+        }
+
+        self.schedule(now + delay, Event::Deliver(to, msg));
+    }
+
+    fn result(self) -> SimulationResult {
+        let config = self.config;
+        let honest: Vec<usize> = (0..self.nodes.len())
+            .filter(|node| config.is_honest(*node))
+            .collect();
+
+        let mut violations = Vec::new();
+        for slot_index in 0..config.num_slots {
+            // The first honest node that externalized the slot.
+            let mut first: Option<(usize, &Vec<u32>)> = None;
+            for node in &honest {
+                if let Some(values) = self.nodes[*node].externalized.get(&slot_index) {
+                    match first {
+                        None => first = Some((*node, values)),
+                        Some((node_a, values_a)) if values_a != values => {
+                            violations.push(Violation::Disagreement {
+                                slot_index,
+                                node_a,
+                                values_a: values_a.clone(),
+                                node_b: *node,
+                                values_b: values.clone(),
+                            })
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+        }
+
+        if config.check_liveness {
+            for node in &honest {
+                let slot_index = self.nodes[*node].current_slot;
+                if slot_index < config.num_slots {
+                    violations.push(Violation::NoProgress {
+                        node: *node,
+                        slot_index,
+                    });
+                }
+            }
+        }
+
+        SimulationResult {
+            externalized: self
+                .nodes
+                .into_iter()
+                .map(|node| node.externalized)
+                .collect(),
+            violations,
+            num_msgs_sent: self.num_msgs_sent,
+            num_msgs_dropped: self.num_msgs_dropped,
+            num_errors: self.num_errors,
+            elapsed: self.now,
+        }
+    }
+}
+
+/// The message with every value replaced by a value that honest nodes never nominate.
+fn equivocate(msg: &Msg<u32>) -> Msg<u32> {
+    let values = |values: &BTreeSet<u32>| -> BTreeSet<u32> {
+        values.iter().map(|v| v | EQUIVOCATION_BIT).collect()
+    };
+    let ballot = |ballot: &Ballot<u32>| -> Ballot<u32> {
+        let X: Vec<u32> = ballot.X.iter().map(|v| v | EQUIVOCATION_BIT).collect();
+        Ballot::new(ballot.N, &X)
+    };
+    let nominate = |payload: &NominatePayload<u32>| NominatePayload {
+        X: values(&payload.X),
+        Y: values(&payload.Y),
+    };
+    let prepare = |payload: &PreparePayload<u32>| PreparePayload {
+        B: ballot(&payload.B),
+        P: payload.P.as_ref().map(ballot),
+        PP: payload.PP.as_ref().map(ballot),
+        CN: payload.CN,
+        HN: payload.HN,
+    };
+
+    let topic = match &msg.topic {
+        Topic::Nominate(payload) => Topic::Nominate(nominate(payload)),
+        Topic::NominatePrepare(nominate_payload, prepare_payload) => {
+            Topic::NominatePrepare(nominate(nominate_payload), prepare(prepare_payload))
+        }
+        Topic::Prepare(payload) => Topic::Prepare(prepare(payload)),
+        Topic::Commit(payload) => Topic::Commit(CommitPayload {
+            B: ballot(&payload.B),
+            PN: payload.PN,
+            CN: payload.CN,
+            HN: payload.HN,
+        }),
+        Topic::Externalize(payload) => Topic::Externalize(ExternalizePayload {
+            C: ballot(&payload.C),
+            HN: payload.HN,
+        }),
+    };
+
+    Msg::new(
+        msg.sender_id.clone(),
+        msg.quorum_set.clone(),
+        msg.slot_index,
+        topic,
+    )
+}
+
+/// Reduce a schedule that makes a simulation fail to one that still fails, but from which no
+/// single fault can be removed without the simulation passing. Delays and partition windows are
+/// also shortened as far as possible.
+///
+/// # Arguments
+/// * `config` - The network to simulate.
+/// * `seed` - Seed of the failing simulation.
+/// * `schedule` - The failing schedule.
+/// * `fails` - Whether a simulation result counts as a failure.
+/// * `logger` - Logger for the simulations.
+pub fn shrink(
+    config: &SimulationConfig,
+    seed: u64,
+    schedule: &Schedule,
+    fails: impl Fn(&SimulationResult) -> bool,
+    logger: Logger,
+) -> Schedule {
+    let still_fails = |faults: &[Fault]| {
+        let schedule = Schedule {
+            faults: faults.to_vec(),
+        };
+        fails(&run(config, seed, &schedule, logger.clone()))
+    };
+
+    let mut faults = schedule.faults.clone();
+    loop {
+        let num_faults = faults.len();
+        faults = remove_faults(faults, &still_fails);
+        let simplified = simplify_faults(&mut faults, &still_fails);
+        if faults.len() == num_faults && !simplified {
+            return Schedule { faults };
+        }
+    }
+}
+
+/// Remove chunks of faults, halving the chunk size down to single faults (delta debugging).
+fn remove_faults(mut faults: Vec<Fault>, still_fails: &impl Fn(&[Fault]) -> bool) -> Vec<Fault> {
+    let mut chunk_size = (faults.len() + 1) / 2;
+    while chunk_size > 0 {
+        let mut start = 0;
+        while start < faults.len() {
+            let end = (start + chunk_size).min(faults.len());
+            let candidate: Vec<Fault> = faults[..start]
+                .iter()
+                .chain(faults[end..].iter())
+                .cloned()
+                .collect();
+            if still_fails(&candidate) {
+                faults = candidate;
+            } else {
+                start = end;
+            }
+        }
+        chunk_size /= 2;
+    }
+    faults
+}
+
+/// Halve each delay and partition window for as long as the schedule still fails.
+///
+/// Returns true if any fault was changed.
+fn simplify_faults(faults: &mut [Fault], still_fails: &impl Fn(&[Fault]) -> bool) -> bool {
+    let mut simplified = false;
+    for i in 0..faults.len() {
+        loop {
+            let smaller = match &faults[i] {
+                Fault::Drop(_) => None,
+                Fault::Delay(seq, delay) if *delay >= Duration::from_millis(2) => {
+                    Some(Fault::Delay(*seq, *delay / 2))
+                }
+                Fault::Delay(..) => None,
+                Fault::Partition {
+                    start,
+                    end,
+                    isolated,
+                } if *end - *start >= Duration::from_millis(2) => Some(Fault::Partition {
+                    start: *start,
+                    end: *start + (*end - *start) / 2,
+                    isolated: isolated.clone(),
+                }),
+                Fault::Partition { .. } => None,
+            };
+
+            let smaller = match smaller {
+                Some(smaller) => smaller,
+                None => break,
+            };
+            let previous = std::mem::replace(&mut faults[i], smaller);
+            if still_fails(&faults[..]) {
+                simplified = true;
+            } else {
+                faults[i] = previous;
+                break;
+            }
+        }
+    }
+    simplified
+}
+
+#[cfg(test)]
+mod simulator_tests {
+    use super::*;
+    use mc_common::logger::test_with_logger;
+
+    #[test_with_logger]
+    // Honest nodes agree on every slot, and a simulation is reproducible from its seed.
+    fn honest_mesh_is_deterministic(logger: Logger) {
+        let config = SimulationConfig::mesh(4, 2);
+
+        let result = run(&config, 7, &Schedule::new(), logger.clone());
+        assert!(result.is_ok(), "{:?}", result.violations);
+        assert_eq!(result.num_msgs_dropped, 0);
+        for externalized in &result.externalized {
+            assert_eq!(externalized.len(), config.num_slots as usize);
+        }
+
+        assert_eq!(result, run(&config, 7, &Schedule::new(), logger));
+    }
+
+    #[test_with_logger]
+    // Dropped, delayed and partitioned messages do not prevent agreement or progress.
+    fn random_faults(logger: Logger) {
+        let config = SimulationConfig::mesh(4, 2);
+
+        for seed in 0..5 {
+            let schedule = Schedule::random(&config, seed, 20, 500);
+            let result = run(&config, seed, &schedule, logger.clone());
+            assert!(
+                result.is_ok(),
+                "seed {}, {:?}: {:?}",
+                seed,
+                schedule,
+                result.violations
+            );
+        }
+    }
+
+    #[test_with_logger]
+    // A node that withholds its messages cannot stop the others from externalizing.
+    fn withholding_node(logger: Logger) {
+        let mut config = SimulationConfig::mesh(4, 2);
+        config.byzantine.insert(3, ByzantineBehavior::Withhold);
+
+        let result = run(&config, 11, &Schedule::new(), logger);
+        assert!(result.is_ok(), "{:?}", result.violations);
+    }
+
+    #[test_with_logger]
+    // A node that tells different peers different things cannot make honest nodes disagree.
+    fn equivocating_node(logger: Logger) {
+        let mut config = SimulationConfig::mesh(4, 2);
+        config.byzantine.insert(0, ByzantineBehavior::Equivocate);
+
+        for seed in 0..3 {
+            let result = run(&config, seed, &Schedule::new(), logger.clone());
+            assert!(result.is_ok(), "seed {}: {:?}", seed, result.violations);
+        }
+    }
+
+    #[test_with_logger]
+    // Without quorum intersection, a partition forks the network, and shrinking a failing
+    // schedule leaves only faults that are needed for the failure.
+    fn shrink_partitioned_network(logger: Logger) {
+        let mut config = SimulationConfig::mesh(4, 1);
+        config.num_slots = 1;
+        config.check_liveness = false;
+        let seed = 3;
+
+        let partition = Fault::Partition {
+            start: Duration::default(),
+            end: config.max_duration,
+            isolated: vec![0, 1].into_iter().collect(),
+        };
+        // The partition, followed by faults that do not matter.
+        let mut schedule = Schedule::new();
+        schedule.faults.push(partition);
+        for seq in 0..8 {
+            schedule.faults.push(if seq % 2 == 0 {
+                Fault::Drop(seq * 3)
+            } else {
+                Fault::Delay(seq * 3, Duration::from_millis(200))
+            });
+        }
+
+        let fails = |result: &SimulationResult| {
+            result.violations.iter().any(|violation| match violation {
+                Violation::Disagreement { .. } => true,
+                Violation::NoProgress { .. } => false,
+            })
+        };
+        assert!(fails(&run(&config, seed, &schedule, logger.clone())));
+
+        let shrunk = shrink(&config, seed, &schedule, fails, logger.clone());
+        assert!(shrunk.len() < schedule.len());
+        assert!(fails(&run(&config, seed, &shrunk, logger.clone())));
+
+        for i in 0..shrunk.len() {
+            let mut smaller = shrunk.clone();
+            smaller.faults.remove(i);
+            assert!(!fails(&run(&config, seed, &smaller, logger.clone())));
+        }
+    }
+}
@@ -4,7 +4,7 @@
 //!
 //! The transactions validated in this slot determine the values to include in the next block appended to the ledger.
 use crate::{
-    core_types::{Ballot, CombineFn, SlotIndex, ValidityFn, Value},
+    core_types::{Ballot, ClockFn, CombineFn, SlotIndex, ValidityFn, Value},
     msg::*,
     predicates::{
         BallotRangePredicate, BallotSetPredicate, FuncPredicate, Predicate, ValueSetPredicate,
@@ -127,6 +127,9 @@ pub struct Slot<V: Value, ValidationError: Display> {
     /// This parameter sets the base interval for ballot timeout.
     /// SCP suggests this should be one second.
     pub base_ballot_interval: Duration,
+
+    /// Source of the current time for timeouts.
+    pub clock: ClockFn,
 }
 
 /// Metrics and information about a given slot.
@@ -150,6 +153,55 @@ pub struct SlotMetrics {
     pub bN: u32,
 }
 
+/// The part of a slot's state that a node must remember across restarts.
+///
+/// SCP safety relies on a node never contradicting the statements it has already emitted, so this
+/// is saved before outgoing messages are broadcast and restored when the node starts again.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SlotState<V: Value> {
+    /// Slot number.
+    pub slot_index: SlotIndex,
+
+    /// Highest message from each node, including the local node.
+    pub M: Vec<Msg<V>>,
+
+    /// Set of values that have been proposed, but not yet voted for.
+    pub W: BTreeSet<V>,
+
+    /// Set of values we have voted to nominate.
+    pub X: BTreeSet<V>,
+
+    /// Set of values we have accepted as nominated.
+    pub Y: BTreeSet<V>,
+
+    /// Set of values we have confirmed as nominated.
+    pub Z: BTreeSet<V>,
+
+    /// Current ballot we are trying to pass.
+    pub B: Ballot<V>,
+
+    /// The highest accepted prepared ballot, if any.
+    pub P: Option<Ballot<V>>,
+
+    /// The highest accepted prepared ballot that is less-than-and-incompatible with P.
+    pub PP: Option<Ballot<V>>,
+
+    /// See `Slot::H`.
+    pub H: Option<Ballot<V>>,
+
+    /// See `Slot::C`.
+    pub C: Option<Ballot<V>>,
+
+    /// Current phase of the protocol.
+    pub phase: Phase,
+
+    /// Last message sent by us.
+    pub last_sent_msg: Option<Msg<V>>,
+
+    /// Current nomination round number.
+    pub nominate_round: u32,
+}
+
 impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
     ///////////////////////////////////////////////////////////////////////////
     // Public methods (how the Slot interfaces with the Node)
@@ -190,6 +242,7 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
             logger: logger.new(o!("mc.scp.slot" => slot_index)),
             base_round_interval: Duration::from_millis(1000),
             base_ballot_interval: Duration::from_millis(1000),
+            clock: Arc::new(Instant::now),
         };
 
         let max_priority_peer = slot.find_max_priority_peer(slot.nominate_round);
@@ -210,6 +263,92 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
         }
     }
 
+    /// Get the state that must survive a restart of the local node.
+    pub fn get_state(&self) -> SlotState<V> {
+        let mut M: Vec<Msg<V>> = self.M.values().cloned().collect();
+        M.sort_by(|a, b| a.sender_id.cmp(&b.sender_id));
+
+        SlotState {
+            slot_index: self.slot_index,
+            M,
+            W: self.W.clone(),
+            X: self.X.clone(),
+            Y: self.Y.clone(),
+            Z: self.Z.clone(),
+            B: self.B.clone(),
+            P: self.P.clone(),
+            PP: self.PP.clone(),
+            H: self.H.clone(),
+            C: self.C.clone(),
+            phase: self.phase,
+            last_sent_msg: self.last_sent_msg.clone(),
+            nominate_round: self.nominate_round,
+        }
+    }
+
+    /// Re-create a slot from a previously saved state.
+    ///
+    /// Timers are not armed; call `restart_timers` once the base intervals are set.
+    ///
+    /// # Arguments
+    /// * `node_id` - Local node ID.
+    /// * `quorum_set` - Local node quorum set.
+    /// * `state` - State returned by `get_state` before the node stopped.
+    /// * `validity_fn` - Application-specific validation of value.
+    /// * `combine_fn` - Application-specific function for combining multiple values.
+    /// * `logger` - Logger.
+    pub fn from_state(
+        node_id: NodeID,
+        quorum_set: QuorumSet,
+        state: SlotState<V>,
+        validity_fn: ValidityFn<V, ValidationError>,
+        combine_fn: CombineFn<V>,
+        logger: Logger,
+    ) -> Self {
+        let mut slot = Self::new(
+            node_id,
+            quorum_set,
+            state.slot_index,
+            validity_fn,
+            combine_fn,
+            logger,
+        );
+
+        slot.M = state
+            .M
+            .into_iter()
+            .map(|msg| (msg.sender_id.clone(), msg))
+            .collect();
+        slot.W = state.W;
+        slot.X = state.X;
+        slot.Y = state.Y;
+        slot.Z = state.Z;
+        slot.B = state.B;
+        slot.P = state.P;
+        slot.PP = state.PP;
+        slot.H = state.H;
+        slot.C = state.C;
+        slot.phase = state.phase;
+        slot.last_sent_msg = state.last_sent_msg;
+        slot.nominate_round = state.nominate_round;
+
+        // Leaders accumulate across nomination rounds.
+        for round in 2..=slot.nominate_round {
+            let max_priority_peer = slot.find_max_priority_peer(round);
+            slot.max_priority_peers.insert(max_priority_peer);
+        }
+
+        slot
+    }
+
+    /// Arm the nomination and ballot timers of a restored slot.
+    pub fn restart_timers(&mut self) {
+        if self.phase == Phase::NominatePrepare {
+            self.schedule_next_nomination_round();
+        }
+        self.maybe_set_ballot_timer();
+    }
+
     /// Processes any timeouts that may have occurred.
     /// Returns list of messages to broadcast to network.
     pub fn process_timeouts(&mut self) -> Vec<Msg<V>> {
@@ -219,7 +358,7 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
 
         // Nomination round timeout.
         if self.next_nominate_round_at.is_some()
-            && Instant::now() > self.next_nominate_round_at.unwrap()
+            && (self.clock)() > self.next_nominate_round_at.unwrap()
         {
             timeout_occurred = true;
             // Canceling is required since schedule_next_nomination_round will not schedule a round
@@ -242,7 +381,7 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
         }
 
         // Ballot timeout.
-        if self.next_ballot_at.is_some() && Instant::now() > self.next_ballot_at.unwrap() {
+        if self.next_ballot_at.is_some() && (self.clock)() > self.next_ballot_at.unwrap() {
             log::debug!(
                 self.logger,
                 "Ballot {} timed out in {:?} phase",
@@ -452,7 +591,7 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
     fn schedule_next_nomination_round(&mut self) {
         if self.next_nominate_round_at.is_none() {
             self.next_nominate_round_at =
-                Some(Instant::now() + self.base_round_interval * self.nominate_round);
+                Some((self.clock)() + self.base_round_interval * self.nominate_round);
         }
     }
 
@@ -1093,7 +1232,7 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
 
             if !quorum_ids.is_empty() {
                 self.next_ballot_at =
-                    Some(Instant::now() + self.base_ballot_interval * self.B.N.saturating_add(1));
+                    Some((self.clock)() + self.base_ballot_interval * self.B.N.saturating_add(1));
             }
         }
     }
@@ -13,12 +13,15 @@ use mc_consensus_scp::{
     msg::Msg,
     node::{Node, ScpNode},
     quorum_set::QuorumSet,
+    scp_state::PersistentScpNode,
+    slot::Phase,
     test_utils::{test_node_id, TransactionValidationError},
 };
 use rand::{rngs::StdRng, RngCore};
 use std::{
     collections::BTreeSet,
     iter::FromIterator,
+    path::PathBuf,
     sync::{Arc, Mutex},
     thread,
     thread::JoinHandle,
@@ -59,6 +62,34 @@ impl SCPNetwork {
         combine_fn: CombineFn<String>,
         logger: Logger,
     ) -> Self {
+        Self::new(
+            Self::mesh_node_options(num_nodes, k),
+            validity_fn,
+            combine_fn,
+            logger,
+        )
+    }
+
+    /// Constructs a mesh network whose nodes save their slot state in `state_dir`, so that they
+    /// can be stopped and restarted with `restart_node`.
+    pub fn new_persistent_mesh(
+        num_nodes: usize,
+        k: u32,
+        validity_fn: ValidityFn<String, TransactionValidationError>,
+        combine_fn: CombineFn<String>,
+        state_dir: PathBuf,
+        logger: Logger,
+    ) -> Self {
+        Self::new_with_state_dir(
+            Self::mesh_node_options(num_nodes, k),
+            validity_fn,
+            combine_fn,
+            Some(state_dir),
+            logger,
+        )
+    }
+
+    fn mesh_node_options(num_nodes: usize, k: u32) -> Vec<NodeOptions> {
         let mut node_options = Vec::<NodeOptions>::new();
         for node_id in 0..num_nodes {
             let other_node_ids: Vec<u32> = (0..num_nodes)
@@ -74,7 +105,7 @@ impl SCPNetwork {
             ));
         }
 
-        Self::new(node_options, validity_fn, combine_fn, logger)
+        node_options
     }
 
     /// Constructs a cyclic network (e.g. 1->2->3->4->1)
@@ -115,6 +146,16 @@ impl SCPNetwork {
         validity_fn: ValidityFn<String, TransactionValidationError>,
         combine_fn: CombineFn<String>,
         logger: Logger,
+    ) -> Self {
+        Self::new_with_state_dir(node_options, validity_fn, combine_fn, None, logger)
+    }
+
+    fn new_with_state_dir(
+        node_options: Vec<NodeOptions>,
+        validity_fn: ValidityFn<String, TransactionValidationError>,
+        combine_fn: CombineFn<String>,
+        state_dir: Option<PathBuf>,
+        logger: Logger,
     ) -> Self {
         let mut network = SCPNetwork {
             nodes_map: Arc::new(Mutex::new(HashMap::default())),
@@ -152,6 +193,9 @@ impl SCPNetwork {
                 Arc::new(move |logger, msg| {
                     SCPNetwork::broadcast_msg(logger, &nodes_map_clone, &peers, msg)
                 }),
+                state_dir
+                    .as_ref()
+                    .map(|dir| dir.join(node_id.responder_id.to_string())),
                 logger.new(o!("mc.local_node_id" => node_id.to_string())),
             );
             network
@@ -196,6 +240,65 @@ impl SCPNetwork {
         }
     }
 
+    /// Stop a node's thread and start it again, as if its process was killed and restarted.
+    ///
+    /// Messages sent to the node while it is down are delivered once it is back, and its ledger
+    /// survives the restart. Values submitted to it that were not yet externalized are lost.
+    pub fn restart_node(&mut self, node_id: &NodeID) {
+        self.nodes_map
+            .lock()
+            .expect("lock failed on nodes_map in restart_node")[node_id]
+            .send_stop();
+
+        // The thread may be waiting for nodes_map to broadcast, so it must not be locked here.
+        self.thread_handles
+            .remove(node_id)
+            .expect("failed to get handle option from thread_handles")
+            .expect("thread handle is missing")
+            .join()
+            .expect("SCPNode join failed");
+
+        let mut nodes_map = self
+            .nodes_map
+            .lock()
+            .expect("lock failed on nodes_map in restart_node");
+        let node = nodes_map
+            .remove(node_id)
+            .expect("failed to get node from nodes_map");
+        let (node, thread_handle) = node.restart();
+        nodes_map.insert(node_id.clone(), node);
+        self.thread_handles.insert(node_id.clone(), thread_handle);
+    }
+
+    /// Wait for a node's current slot to be in one of the given phases.
+    ///
+    /// Returns false if it did not happen within `max_wait`.
+    pub fn wait_for_phase(&self, node_id: &NodeID, phases: &[Phase], max_wait: Duration) -> bool {
+        let deadline = Instant::now() + max_wait;
+        while Instant::now() < deadline {
+            let local_node = Arc::clone(
+                &self
+                    .nodes_map
+                    .lock()
+                    .expect("lock failed on nodes_map getting node")[node_id]
+                    .local_node,
+            );
+            let current_slot = self.get_shared_data(node_id).ledger.len() as SlotIndex;
+            let phase = local_node
+                .lock()
+                .expect("lock failed on node getting slot metrics")
+                .get_slot_metrics(current_slot)
+                .map(|metrics| metrics.phase);
+            if phase.map_or(false, |phase| phases.contains(&phase)) {
+                return true;
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        false
+    }
+
     pub fn push_value(&self, node_id: &NodeID, value: &str) {
         let node: &SCPNode = {
             &self
@@ -346,10 +449,26 @@ impl SCPNodeSharedData {
     }
 }
 
+// Everything needed to start a node's thread again after it was stopped.
+#[derive(Clone)]
+struct SCPNodeConfig {
+    thread_name: String,
+    node_id: NodeID,
+    quorum_set: QuorumSet,
+    validity_fn: ValidityFn<String, TransactionValidationError>,
+    combine_fn: CombineFn<String>,
+    broadcast_msg_fn: Arc<dyn Fn(Logger, Msg<String>) + Sync + Send>,
+    state_path: Option<PathBuf>,
+    logger: Logger,
+}
+
 struct SCPNode {
-    local_node: Arc<Mutex<Node<String, TransactionValidationError>>>,
+    local_node: Arc<Mutex<Box<dyn ScpNode<String>>>>,
     sender: crossbeam_channel::Sender<SCPNodeTaskMessage>,
+    // Kept so that messages sent to a stopped node are delivered after it restarts.
+    receiver: crossbeam_channel::Receiver<SCPNodeTaskMessage>,
     shared_data: Arc<Mutex<SCPNodeSharedData>>,
+    config: SCPNodeConfig,
 }
 
 impl SCPNode {
@@ -360,26 +479,89 @@ impl SCPNode {
         validity_fn: ValidityFn<String, TransactionValidationError>,
         combine_fn: CombineFn<String>,
         broadcast_msg_fn: Arc<dyn Fn(Logger, Msg<String>) + Sync + Send>,
+        state_path: Option<PathBuf>,
         logger: Logger,
     ) -> (Self, Option<JoinHandle<()>>) {
         let (sender, receiver) = crossbeam_channel::unbounded();
-        let local_node = Arc::new(Mutex::new(Node::new(
+        let config = SCPNodeConfig {
+            thread_name,
+            node_id,
+            quorum_set,
+            validity_fn,
+            combine_fn,
+            broadcast_msg_fn,
+            state_path,
+            logger,
+        };
+        Self::start(
+            config,
+            sender,
+            receiver,
+            Arc::new(Mutex::new(SCPNodeSharedData { ledger: Vec::new() })),
+        )
+    }
+
+    /// Start a new thread for this stopped node, restoring its saved slot state.
+    pub fn restart(self) -> (Self, Option<JoinHandle<()>>) {
+        Self::start(self.config, self.sender, self.receiver, self.shared_data)
+    }
+
+    fn start(
+        config: SCPNodeConfig,
+        sender: crossbeam_channel::Sender<SCPNodeTaskMessage>,
+        receiver: crossbeam_channel::Receiver<SCPNodeTaskMessage>,
+        shared_data: Arc<Mutex<SCPNodeSharedData>>,
+    ) -> (Self, Option<JoinHandle<()>>) {
+        let SCPNodeConfig {
+            thread_name,
+            node_id,
+            quorum_set,
+            validity_fn,
+            combine_fn,
+            broadcast_msg_fn,
+            state_path,
+            logger,
+        } = config.clone();
+
+        // The ledger survives restarts, so it tells which slot we are working on.
+        let mut current_slot: usize = shared_data
+            .lock()
+            .expect("lock failed on shared_data getting current slot")
+            .ledger
+            .len();
+
+        let scp_node = Node::new(
             node_id.clone(),
             quorum_set,
-            validity_fn.clone(),
-            combine_fn.clone(),
+            validity_fn,
+            combine_fn,
             logger.clone(),
-        )));
+        );
+        let local_node: Box<dyn ScpNode<String>> = match state_path {
+            Some(state_path) => {
+                let mut persistent_node = PersistentScpNode::new(scp_node, state_path)
+                    .expect("failed creating PersistentScpNode");
+                if persistent_node
+                    .restore(current_slot as SlotIndex)
+                    .expect("failed restoring slot state")
+                {
+                    log::debug!(logger, "{}: Restored slot {}.", node_id, current_slot);
+                }
+                Box::new(persistent_node)
+            }
+            None => Box::new(scp_node),
+        };
 
         let node = Self {
-            local_node,
+            local_node: Arc::new(Mutex::new(local_node)),
             sender,
-            shared_data: Arc::new(Mutex::new(SCPNodeSharedData { ledger: Vec::new() })),
+            receiver: receiver.clone(),
+            shared_data,
+            config,
         };
 
         let thread_shared_data = Arc::clone(&node.shared_data);
         let thread_local_node = Arc::clone(&node.local_node);
-        let mut current_slot: usize = 0;
         let mut total_broadcasts: u32 = 0;
 
         let thread_handle = Some(
@@ -393,6 +575,7 @@ impl SCPNode {
                     'main_loop: loop {
                         // Collect and process any messages we have received
                         let mut incoming_msgs = Vec::<Arc<Msg<String>>>::new();
+                        let mut stop = false;
 
                         for msg in receiver.try_iter() {
                             // Handle message based on it's type
@@ -410,9 +593,11 @@ impl SCPNode {
                                     incoming_msgs.push(msg);
                                 }
 
-                                // Request to stop thread
+                                // Request to stop thread, once the messages received before it
+                                // are processed. Later messages stay queued for a restart.
                                 SCPNodeTaskMessage::StopTrigger => {
-                                    break 'main_loop;
+                                    stop = true;
+                                    break;
                                 }
                             };
                         }
@@ -463,6 +648,7 @@ impl SCPNode {
                             .lock()
                             .expect("lock failed on node processing timeouts in thread")
                             .process_timeouts()
+                            .expect("process_timeouts failed")
                             .into_iter()
                             .collect()
                         };
@@ -511,6 +697,10 @@ impl SCPNode {
                             pending_values = remaining_values;
                             current_slot += 1;
                         }
+
+                        if stop {
+                            break 'main_loop;
+                        }
                     }
 
                     // Wait
@@ -0,0 +1,94 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+mod mock_network;
+
+use mc_common::{
+    logger::{test_with_logger, Logger},
+    HashSet,
+};
+use mc_consensus_scp::{slot::Phase, test_utils};
+use rand::{rngs::StdRng, SeedableRng};
+use serial_test_derive::serial;
+use std::{sync::Arc, time::Duration};
+use tempdir::TempDir;
+
+/// Hack to skip certain tests (that are currently too slow) from running
+fn skip_slow_tests() -> bool {
+    std::env::var("SKIP_SLOW_TESTS") == Ok("1".to_string())
+}
+
+/// Nodes that are killed and restarted while balloting resume from their saved slot state, and
+/// the network still agrees on every value.
+#[test_with_logger]
+#[serial]
+fn restart_nodes_mid_ballot(logger: Logger) {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let num_nodes = 4;
+    let mut rng: StdRng = SeedableRng::from_seed([97u8; 32]);
+    let state_dir = TempDir::new("scp_node_restarts").unwrap();
+
+    // Every node needs all of its peers, so a restarted node that forgot or contradicted its
+    // votes would stall or fork the network.
+    let mut network = mock_network::SCPNetwork::new_persistent_mesh(
+        num_nodes,
+        3,
+        Arc::new(test_utils::trivial_validity_fn::<String>),
+        Arc::new(test_utils::trivial_combine_fn::<String>),
+        state_dir.path().to_path_buf(),
+        logger,
+    );
+
+    // Values are only submitted to node 0, which is never restarted, since values that a node
+    // has not yet externalized do not survive a restart.
+    let submitter = test_utils::test_node_id(0);
+    let mut values = Vec::<String>::new();
+    let mut num_restarts_mid_ballot = 0;
+
+    for round in 0..12 {
+        for _ in 0..50 {
+            let value = mock_network::random_str(&mut rng, 10);
+            network.push_value(&submitter, &value);
+            values.push(value);
+        }
+
+        let node_id = test_utils::test_node_id(1 + round % (num_nodes as u32 - 1));
+        if network.wait_for_phase(
+            &node_id,
+            &[Phase::Prepare, Phase::Commit],
+            Duration::from_secs(10),
+        ) {
+            num_restarts_mid_ballot += 1;
+        }
+        network.restart_node(&node_id);
+    }
+
+    assert!(num_restarts_mid_ballot > 0);
+
+    for node_num in 0..num_nodes {
+        let node_id = test_utils::test_node_id(node_num as u32);
+
+        network.wait_for_total_values(&node_id, values.len(), Duration::from_secs(60));
+
+        assert_eq!(
+            values.iter().cloned().collect::<HashSet<String>>(),
+            network
+                .get_shared_data(&node_id)
+                .get_all_values()
+                .iter()
+                .cloned()
+                .collect::<HashSet<String>>()
+        );
+    }
+
+    // Check all blocks in the ledger are the same
+    let node0_data = network.get_shared_data(&submitter).ledger;
+    for node_num in 0..num_nodes {
+        let node_data = network
+            .get_shared_data(&test_utils::test_node_id(node_num as u32))
+            .ledger;
+        assert_eq!(node0_data, node_data);
+    }
+}
@@ -4,18 +4,18 @@
 
 use mc_attest_core::DEBUG_ENCLAVE;
 use mc_attest_net::{Client, RaClient};
-use mc_common::logger::{create_app_logger, log, o};
+use mc_common::logger::{create_app_logger, log, o, Logger};
 use mc_consensus_enclave::{ConsensusServiceSgxEnclave, ENCLAVE_FILE};
 use mc_consensus_service::{
     config::Config,
     consensus_service::{ConsensusService, ConsensusServiceError},
 };
-use mc_ledger_db::LedgerDB;
+use mc_ledger_db::{import_snapshot, Ledger, LedgerDB};
 use std::{
     env,
     fs::File,
-    io::{Read, Write},
-    path::PathBuf,
+    io::{BufReader, Read, Write},
+    path::{Path, PathBuf},
 };
 use structopt::StructOpt;
 
@@ -46,6 +46,39 @@ fn main() -> Result<(), ConsensusServiceError> {
         scope.set_tag("local_node_id", local_node_id.responder_id.to_string());
     });
 
+    let network_parameters = config.network().network_parameters();
+    if let Err(err) = network_parameters.check() {
+        panic!("Invalid network parameters: {}", err);
+    }
+
+    let block_version_schedule = config.network().block_version_schedule();
+    if let Err(err) = block_version_schedule.check() {
+        panic!("Invalid block version schedule: {}", err);
+    }
+
+    if let Some(ledger_snapshot) = &config.ledger_snapshot {
+        import_ledger_snapshot(&config, ledger_snapshot, &logger);
+    }
+    setup_ledger_dir(&config.origin_block_path, &config.ledger_path);
+
+    // This node writes to the ledger, so it migrates a ledger created by an earlier version.
+    if LedgerDB::migrate(config.ledger_path.clone()).expect("Failed migrating LedgerDB") {
+        log::info!(logger, "Migrated ledger {:?}", config.ledger_path);
+    }
+
+    let local_ledger =
+        LedgerDB::open(config.ledger_path.clone()).expect("Failed creating LedgerDB");
+
+    // All nodes must agree on the parameters and block version schedule committed to by the
+    // origin block. The enclave checks this too, since it can not trust the host to have done so.
+    let origin_block = local_ledger
+        .get_block(0)
+        .expect("The ledger must contain an origin block");
+    assert!(
+        network_parameters.is_committed_by(&block_version_schedule, &origin_block),
+        "Configured network parameters or block version schedule do not match the origin block"
+    );
+
     let enclave_path = env::current_exe()
         .expect("Could not get the path of our executable")
         .with_file_name(ENCLAVE_FILE);
@@ -54,6 +87,9 @@ fn main() -> Result<(), ConsensusServiceError> {
         &config.peer_responder_id,
         &config.client_responder_id,
         &cached_key,
+        &origin_block,
+        &network_parameters,
+        &block_version_schedule,
     );
 
     // write the sealed block signing key
@@ -63,11 +99,6 @@ fn main() -> Result<(), ConsensusServiceError> {
         .write_all(&sealed_key)
         .expect("Failed to write sealed key bytes");
 
-    setup_ledger_dir(&config.origin_block_path, &config.ledger_path);
-
-    let local_ledger =
-        LedgerDB::open(config.ledger_path.clone()).expect("Failed creating LedgerDB");
-
     let ias_client = Client::new(&config.ias_api_key).expect("Could not create IAS client");
 
     if DEBUG_ENCLAVE {
@@ -93,6 +124,40 @@ fn main() -> Result<(), ConsensusServiceError> {
     panic!("Oh oh, our threads died");
 }
 
+/// Creates the ledger from a snapshot, unless the ledger already exists.
+fn import_ledger_snapshot(config: &Config, ledger_snapshot: &Path, logger: &Logger) {
+    if config.ledger_path.join("data.mdb").exists() {
+        return;
+    }
+
+    let policy = config
+        .block_signature_policy()
+        .unwrap_or_else(|err| panic!("Invalid block signature policy: {}", err));
+    if policy.min_trusted_signatures() == 0 {
+        panic!("--ledger-snapshot requires --min-trusted-block-signatures to be at least 1");
+    }
+    let mut file = BufReader::new(
+        File::open(ledger_snapshot)
+            .unwrap_or_else(|err| panic!("Failed opening {:?}: {}", ledger_snapshot, err)),
+    );
+    let header = policy
+        .read_trusted_snapshot_header(&mut file)
+        .unwrap_or_else(|err| panic!("Failed reading {:?}: {}", ledger_snapshot, err));
+
+    log::info!(
+        logger,
+        "Ledger {:?} does not exist, importing {} blocks from {:?}",
+        config.ledger_path,
+        header.num_blocks(),
+        ledger_snapshot
+    );
+    std::fs::create_dir_all(&config.ledger_path).expect("Could not create ledger directory");
+    LedgerDB::create(config.ledger_path.clone()).expect("Could not create ledger");
+    let mut ledger = LedgerDB::open(config.ledger_path.clone()).expect("Could not open ledger");
+    import_snapshot(file, &mut ledger, &header)
+        .unwrap_or_else(|err| panic!("Failed importing {:?}: {}", ledger_snapshot, err));
+}
+
 fn setup_ledger_dir(config_origin_path: &Option<PathBuf>, ledger_path: &PathBuf) {
     if let Some(origin_block_path) = config_origin_path.clone() {
         // Copy origin block to ledger_db path if there are not already contents in ledger_db.
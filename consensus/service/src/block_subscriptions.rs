@@ -0,0 +1,338 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Streams of blocks for `SubscribeBlocks` clients.
+//!
+//! Subscriptions do not have threads of their own. Each one is a stream that reads blocks from the
+//! ledger when the RPC polls it, and waits for a notification once it has sent every block in the
+//! ledger. A single watcher thread notifies every waiting subscription when the ledger grows.
+
+use crate::counters;
+use futures::{
+    task::{self, Task},
+    Async, Poll, Stream,
+};
+use grpcio::{Error as GrpcError, WriteFlags};
+use mc_common::logger::{log, Logger};
+use mc_consensus_api::blockchain;
+use mc_ledger_db::Ledger;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Default maximal number of concurrent block subscriptions.
+pub const DEFAULT_MAX_BLOCK_SUBSCRIPTIONS: usize = 100;
+
+/// How often the watcher thread checks the ledger for new blocks.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Tracks block subscriptions, and wakes them when blocks are appended to the ledger.
+#[derive(Clone)]
+pub struct BlockSubscriptions {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    /// Subscriptions that have sent every block in the ledger.
+    waiting: Mutex<Vec<Task>>,
+
+    /// Number of open subscriptions.
+    num_subscriptions: AtomicUsize,
+
+    /// Maximal number of open subscriptions.
+    max_subscriptions: usize,
+
+    /// Tells the watcher thread to stop.
+    stop_requested: AtomicBool,
+
+    /// The watcher thread, once started.
+    watcher: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+impl BlockSubscriptions {
+    /// Creates a new set of block subscriptions.
+    ///
+    /// # Arguments
+    /// * `max_subscriptions` - Maximal number of concurrent subscriptions.
+    pub fn new(max_subscriptions: usize) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                waiting: Mutex::new(Vec::new()),
+                num_subscriptions: AtomicUsize::new(0),
+                max_subscriptions,
+                stop_requested: AtomicBool::new(false),
+                watcher: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Starts the thread that watches the ledger for new blocks.
+    ///
+    /// # Arguments
+    /// * `ledger` - The ledger subscriptions read from.
+    /// * `poll_interval` - How often to check the ledger for new blocks.
+    /// * `logger` - Logger.
+    pub fn start<L: Ledger + Send + 'static>(
+        &self,
+        ledger: L,
+        poll_interval: Duration,
+        logger: Logger,
+    ) {
+        let inner = self.inner.clone();
+        let join_handle = thread::Builder::new()
+            .name("BlockSubscriptions".into())
+            .spawn(move || {
+                let mut num_blocks = ledger.num_blocks().unwrap_or(0);
+                while !inner.stop_requested.load(Ordering::SeqCst) {
+                    thread::sleep(poll_interval);
+                    match ledger.num_blocks() {
+                        Ok(current) if current > num_blocks => {
+                            num_blocks = current;
+                            inner.notify_waiting();
+                        }
+                        Ok(_) => {}
+                        Err(err) => log::error!(logger, "Failed getting num_blocks: {:?}", err),
+                    }
+                }
+                // Let waiting subscriptions notice that the service is going away.
+                inner.notify_waiting();
+            })
+            .expect("Failed spawning BlockSubscriptions");
+
+        *self.inner.watcher.lock().expect("mutex poisoned") = Some(join_handle);
+    }
+
+    /// Stops the watcher thread.
+    pub fn stop(&self) {
+        self.inner.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(join_handle) = self.inner.watcher.lock().expect("mutex poisoned").take() {
+            join_handle.join().expect("BlockSubscriptions join failed");
+        }
+    }
+
+    /// Opens a subscription to every block from `from_index` onwards, or returns None if the
+    /// maximal number of subscriptions are already open.
+    ///
+    /// # Arguments
+    /// * `ledger` - Ledger to read blocks from.
+    /// * `from_index` - Index of the first block to send.
+    /// * `logger` - Logger.
+    pub fn subscribe<L: Ledger>(
+        &self,
+        ledger: L,
+        from_index: u64,
+        logger: Logger,
+    ) -> Option<BlockStream<L>> {
+        let previous = self.inner.num_subscriptions.fetch_add(1, Ordering::SeqCst);
+        if previous >= self.inner.max_subscriptions {
+            self.inner.num_subscriptions.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        counters::BLOCK_SUBSCRIPTIONS.inc();
+
+        Some(BlockStream {
+            ledger,
+            next_index: from_index,
+            inner: self.inner.clone(),
+            logger,
+        })
+    }
+
+    /// Number of open subscriptions.
+    pub fn num_subscriptions(&self) -> usize {
+        self.inner.num_subscriptions.load(Ordering::SeqCst)
+    }
+}
+
+impl Inner {
+    /// Wakes every waiting subscription.
+    fn notify_waiting(&self) {
+        let waiting: Vec<Task> = self
+            .waiting
+            .lock()
+            .expect("mutex poisoned")
+            .drain(..)
+            .collect();
+        for task in waiting {
+            task.notify();
+        }
+    }
+
+    /// Registers the current task to be woken when the ledger grows.
+    fn wait_for_block(&self) {
+        let mut waiting = self.waiting.lock().expect("mutex poisoned");
+        if !waiting.iter().any(|task| task.will_notify_current()) {
+            waiting.push(task::current());
+        }
+    }
+}
+
+/// Every block from an index onwards, including blocks that are not yet in the ledger.
+///
+/// The stream ends when the ledger returns an error, or when the subscriptions are stopped.
+pub struct BlockStream<L: Ledger> {
+    ledger: L,
+    next_index: u64,
+    inner: Arc<Inner>,
+    logger: Logger,
+}
+
+impl<L: Ledger> Stream for BlockStream<L> {
+    type Item = (blockchain::ArchiveBlock, WriteFlags);
+    type Error = GrpcError;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let mut registered = false;
+        loop {
+            if self.inner.stop_requested.load(Ordering::SeqCst) {
+                return Ok(Async::Ready(None));
+            }
+
+            match get_archive_block(&self.ledger, self.next_index) {
+                Ok(archive_block) => {
+                    self.next_index += 1;
+                    return Ok(Async::Ready(Some((archive_block, WriteFlags::default()))));
+                }
+                // Register before checking again, so that a block appended in between is not
+                // missed.
+                Err(mc_ledger_db::Error::NotFound) if !registered => {
+                    self.inner.wait_for_block();
+                    registered = true;
+                }
+                Err(mc_ledger_db::Error::NotFound) => return Ok(Async::NotReady),
+                Err(err) => {
+                    log::error!(
+                        self.logger,
+                        "Error getting block {}: {:?}",
+                        self.next_index,
+                        err
+                    );
+                    return Ok(Async::Ready(None));
+                }
+            }
+        }
+    }
+}
+
+impl<L: Ledger> Drop for BlockStream<L> {
+    fn drop(&mut self) {
+        self.inner.num_subscriptions.fetch_sub(1, Ordering::SeqCst);
+        counters::BLOCK_SUBSCRIPTIONS.dec();
+    }
+}
+
+/// Returns a block with its contents and, when available, its signature.
+pub fn get_archive_block<L: Ledger>(
+    ledger: &L,
+    block_index: u64,
+) -> Result<blockchain::ArchiveBlock, mc_ledger_db::Error> {
+    let block = ledger.get_block(block_index)?;
+    let block_contents = ledger.get_block_contents(block_index)?;
+
+    let mut archive_block_v1 = blockchain::ArchiveBlockV1::new();
+    archive_block_v1.set_block(blockchain::Block::from(&block));
+    archive_block_v1.set_block_contents(blockchain::BlockContents::from(&block_contents));
+
+    match ledger.get_block_signature(block_index) {
+        Ok(signature) => {
+            archive_block_v1.set_signature(blockchain::BlockSignature::from(&signature))
+        }
+        // Blocks that were not created by consensus (e.g. the origin block) are not signed.
+        Err(mc_ledger_db::Error::NotFound) => {}
+        Err(err) => return Err(err),
+    }
+
+    let mut archive_block = blockchain::ArchiveBlock::new();
+    archive_block.set_v1(archive_block_v1);
+    Ok(archive_block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_common::logger::test_with_logger;
+    use mc_ledger_db::LedgerDB;
+    use mc_transaction_core::account_keys::AccountKey;
+    use mc_transaction_core_test_utils::{create_ledger, initialize_ledger};
+    use rand_core::SeedableRng;
+    use rand_hc::Hc128Rng;
+
+    /// Copies blocks [start, end) of `source` to `dest`.
+    fn copy_blocks(source: &LedgerDB, dest: &mut LedgerDB, start: u64, end: u64) {
+        for block_index in start..end {
+            let block = source.get_block(block_index).unwrap();
+            let block_contents = source.get_block_contents(block_index).unwrap();
+            dest.append_block(&block, &block_contents, None).unwrap();
+        }
+    }
+
+    #[test_with_logger]
+    // A subscription should send every block from `from_index`, followed by blocks as they are
+    // appended to the ledger.
+    fn test_subscribe(logger: Logger) {
+        let mut rng = Hc128Rng::from_seed([1u8; 32]);
+        let mut source_db = create_ledger();
+        initialize_ledger(&mut source_db, 12, &AccountKey::random(&mut rng), &mut rng);
+        let mut ledger_db = create_ledger();
+        copy_blocks(&source_db, &mut ledger_db, 0, 10);
+
+        let subscriptions = BlockSubscriptions::new(1);
+        subscriptions.start(ledger_db.clone(), Duration::from_millis(10), logger.clone());
+
+        let mut appending_db = ledger_db.clone();
+        let appending_source_db = source_db.clone();
+        let appender = thread::spawn(move || {
+            for block_index in 10..12 {
+                thread::sleep(Duration::from_millis(50));
+                copy_blocks(
+                    &appending_source_db,
+                    &mut appending_db,
+                    block_index,
+                    block_index + 1,
+                );
+            }
+        });
+
+        let stream = subscriptions
+            .subscribe(ledger_db.clone(), 7, logger.clone())
+            .unwrap();
+        assert_eq!(subscriptions.num_subscriptions(), 1);
+
+        // Only one subscription may be open at a time.
+        assert!(subscriptions
+            .subscribe(ledger_db.clone(), 0, logger.clone())
+            .is_none());
+
+        let streamed: Vec<blockchain::ArchiveBlock> =
+            stream.wait().take(5).map(|item| item.unwrap().0).collect();
+        appender.join().unwrap();
+
+        assert_eq!(streamed.len(), 5);
+        for (archive_block, block_index) in streamed.iter().zip(7..) {
+            let archive_block_v1 = archive_block.get_v1();
+            assert_eq!(
+                &blockchain::Block::from(&source_db.get_block(block_index).unwrap()),
+                archive_block_v1.get_block()
+            );
+            assert_eq!(
+                blockchain::BlockContents::from(
+                    &source_db.get_block_contents(block_index).unwrap()
+                ),
+                *archive_block_v1.get_block_contents()
+            );
+            assert!(!archive_block_v1.has_signature());
+        }
+
+        // Closing a subscription makes room for another one.
+        assert_eq!(subscriptions.num_subscriptions(), 0);
+        let stream = subscriptions.subscribe(ledger_db, 12, logger).unwrap();
+
+        // Stopping ends open subscriptions.
+        subscriptions.stop();
+        assert_eq!(stream.wait().count(), 0);
+    }
+}
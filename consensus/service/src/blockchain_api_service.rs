@@ -2,11 +2,15 @@
 
 //! Serves blockchain-related API requests.
 
-use grpcio::{RpcContext, RpcStatus, RpcStatusCode, UnarySink};
+use crate::block_subscriptions::BlockSubscriptions;
+use futures::{Future, Sink};
+use grpcio::{RpcContext, RpcStatus, RpcStatusCode, ServerStreamingSink, UnarySink};
 use mc_common::logger::{log, Logger};
 use mc_consensus_api::{
     blockchain,
-    consensus_common::{BlocksRequest, BlocksResponse, LastBlockInfoResponse},
+    consensus_common::{
+        BlocksRequest, BlocksResponse, LastBlockInfoResponse, SubscribeBlocksRequest,
+    },
     consensus_common_grpc::BlockchainApi,
     empty::Empty,
 };
@@ -21,6 +25,9 @@ pub struct BlockchainApiService<L: Ledger + Clone> {
     /// Ledger Database.
     ledger: L,
 
+    /// Open `SubscribeBlocks` streams.
+    block_subscriptions: BlockSubscriptions,
+
     /// Maximal number of results to return in API calls that return multiple results.
     max_page_size: u16,
 
@@ -29,9 +36,10 @@ pub struct BlockchainApiService<L: Ledger + Clone> {
 }
 
 impl<L: Ledger + Clone> BlockchainApiService<L> {
-    pub fn new(ledger: L, logger: Logger) -> Self {
+    pub fn new(ledger: L, block_subscriptions: BlockSubscriptions, logger: Logger) -> Self {
         BlockchainApiService {
             ledger,
+            block_subscriptions,
             max_page_size: 2000,
             logger,
         }
@@ -95,7 +103,7 @@ impl<L: Ledger + Clone> BlockchainApiService<L> {
     }
 }
 
-impl<L: Ledger + Clone> BlockchainApi for BlockchainApiService<L> {
+impl<L: Ledger + Clone + 'static> BlockchainApi for BlockchainApiService<L> {
     /// Gets the last block.
     fn get_last_block_info(
         &mut self,
@@ -136,6 +144,51 @@ impl<L: Ledger + Clone> BlockchainApi for BlockchainApiService<L> {
             send_result(ctx, sink, resp, &logger);
         });
     }
+
+    /// Streams every block from `from_index` onwards, followed by each block as it is appended
+    /// to the ledger.
+    fn subscribe_blocks(
+        &mut self,
+        ctx: RpcContext,
+        request: SubscribeBlocksRequest,
+        sink: ServerStreamingSink<blockchain::ArchiveBlock>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        let logger = rpc_logger(&ctx, &self.logger);
+        log::debug!(
+            logger,
+            "Received SubscribeBlocksRequest from block {}",
+            request.from_index
+        );
+
+        let blocks = match self.block_subscriptions.subscribe(
+            self.ledger.clone(),
+            request.from_index,
+            logger.clone(),
+        ) {
+            Some(blocks) => blocks,
+            None => {
+                log::warn!(
+                    logger,
+                    "Rejected block subscription: too many subscriptions"
+                );
+                let resp = sink
+                    .fail(RpcStatus::new(
+                        RpcStatusCode::RESOURCE_EXHAUSTED,
+                        Some("Too many block subscriptions".into()),
+                    ))
+                    .map_err(move |err| log::error!(logger, "failed to reply: {:?}", err));
+                ctx.spawn(resp);
+                return;
+            }
+        };
+
+        let resp = sink
+            .send_all(blocks)
+            .map(|_| ())
+            .map_err(move |err| log::debug!(logger, "Block subscription ended: {:?}", err));
+        ctx.spawn(resp);
+    }
 }
 
 #[cfg(test)]
@@ -236,7 +289,8 @@ mod tests {
             ledger_db.num_blocks().unwrap() - 1
         );
 
-        let mut blockchain_api_service = BlockchainApiService::new(ledger_db, logger);
+        let mut blockchain_api_service =
+            BlockchainApiService::new(ledger_db, BlockSubscriptions::new(1), logger);
 
         let block_response = blockchain_api_service.get_last_block_info_helper().unwrap();
         assert_eq!(block_response, expected_response);
@@ -252,7 +306,8 @@ mod tests {
             .map(|block_entity| blockchain::Block::from(&block_entity))
             .collect();
 
-        let mut blockchain_api_service = BlockchainApiService::new(ledger_db, logger);
+        let mut blockchain_api_service =
+            BlockchainApiService::new(ledger_db, BlockSubscriptions::new(1), logger);
 
         {
             // The empty range [0,0) should return an empty collection of Blocks.
@@ -285,7 +340,8 @@ mod tests {
     fn test_get_blocks_request_out_of_bounds(logger: Logger) {
         let mut ledger_db = create_db();
         let _blocks = populate_db(&mut ledger_db, 200);
-        let mut blockchain_api_service = BlockchainApiService::new(ledger_db, logger);
+        let mut blockchain_api_service =
+            BlockchainApiService::new(ledger_db, BlockSubscriptions::new(1), logger);
 
         {
             // The range [0, 1000) requests values that don't exist. The response should contain [0,200).
@@ -304,7 +360,8 @@ mod tests {
             .map(|block_entity| blockchain::Block::from(&block_entity))
             .collect();
 
-        let mut blockchain_api_service = BlockchainApiService::new(ledger_db, logger);
+        let mut blockchain_api_service =
+            BlockchainApiService::new(ledger_db, BlockSubscriptions::new(1), logger);
         blockchain_api_service.set_max_page_size(5);
 
         // The request exceeds the max_page_size, so only max_page_size items should be returned.
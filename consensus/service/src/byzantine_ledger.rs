@@ -15,7 +15,8 @@ use mc_common::{
 use mc_connection::{BlockchainConnection, ConnectionManager};
 use mc_consensus_enclave::ConsensusEnclaveProxy;
 use mc_consensus_scp::{
-    scp_log::LoggingScpNode, slot::Phase, Msg, Node, QuorumSet, ScpNode, SlotIndex,
+    scp_log::LoggingScpNode, scp_state::PersistentScpNode, slot::Phase, Msg, Node, QuorumSet,
+    ScpNode, SlotIndex,
 };
 use mc_crypto_keys::Ed25519Pair;
 use mc_ledger_db::Ledger;
@@ -24,14 +25,13 @@ use mc_peers::{
     ConsensusConnection, ConsensusMsg, RetryableConsensusConnection, ThreadedBroadcaster,
     VerifiedConsensusMsg,
 };
-use mc_transaction_core::{tx::TxHash, BlockID};
+use mc_transaction_core::{tx::TxHash, BlockID, ConsensusValue};
 use mc_util_metered_channel::{self, Receiver, Sender};
 use rayon::{iter::ParallelIterator, prelude::IntoParallelIterator};
 use retry::delay::Fibonacci;
 use std::{
     cmp::min,
     collections::{btree_map::Entry::Vacant, BTreeMap, BTreeSet},
-    iter::FromIterator,
     path::PathBuf,
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -60,6 +60,9 @@ enum ByzantineLedgerTaskMessage {
     /// SCP Statement.
     ConsensusMsg(VerifiedConsensusMsg, ResponderId),
 
+    /// A new quorum set and list of transaction source URLs, applied at the next slot boundary.
+    UpdateNetworkConfig(QuorumSet, Vec<String>),
+
     /// Stop trigger, used for notifying the worker thread to terminate.
     StopTrigger,
 }
@@ -86,6 +89,7 @@ impl ByzantineLedger {
         broadcaster: Arc<Mutex<ThreadedBroadcaster>>,
         msg_signer_key: Arc<Ed25519Pair>,
         tx_source_urls: Vec<String>,
+        opt_scp_state_path: Option<PathBuf>,
         opt_scp_debug_dump_dir: Option<PathBuf>,
         logger: Logger,
     ) -> Self {
@@ -96,15 +100,25 @@ impl ByzantineLedger {
         let scp_node = Node::new(
             node_id.clone(),
             quorum_set.clone(),
-            Arc::new(move |tx_hash| tx_manager_validate.validate_tx_by_hash(tx_hash)),
-            Arc::new(move |tx_hashes| tx_manager_combine.combine_txs_by_hash(tx_hashes)),
+            Arc::new(move |value| tx_manager_validate.validate_value(value)),
+            Arc::new(move |values| tx_manager_combine.combine_values(values)),
             logger.clone(),
         );
-        let wrapped_scp_node: Box<dyn ScpNode<TxHash>> = if let Some(path) = opt_scp_debug_dump_dir
-        {
-            Box::new(LoggingScpNode::new(scp_node, path).expect("Failed creating LoggingScpNode"))
+        let wrapped_scp_node = if let Some(path) = opt_scp_state_path {
+            // Resume the slot we were working on before a restart, so that we do not contradict
+            // the ballots we already voted for.
+            let mut persistent_node =
+                PersistentScpNode::new(scp_node, path).expect("Failed creating PersistentScpNode");
+            let cur_slot = ledger.num_blocks().expect("num blocks failed") as SlotIndex;
+            if persistent_node
+                .restore(cur_slot)
+                .expect("Failed restoring SCP state")
+            {
+                log::info!(logger, "Restored SCP state for slot {}", cur_slot);
+            }
+            Self::wrap_scp_node(persistent_node, opt_scp_debug_dump_dir)
         } else {
-            Box::new(scp_node)
+            Self::wrap_scp_node(scp_node, opt_scp_debug_dump_dir)
         };
 
         let highest_outgoing_consensus_msg = Arc::new(Mutex::new(None));
@@ -122,7 +136,7 @@ impl ByzantineLedger {
         let send_scp_message_ledger = ledger.clone();
         let send_scp_message_broadcaster = broadcaster.clone();
         let send_scp_message_node_id = node_id.clone();
-        let send_scp_message = move |scp_msg: Option<Msg<TxHash>>| {
+        let send_scp_message = move |scp_msg: Option<Msg<ConsensusValue>>| {
             if scp_msg.is_none() {
                 return;
             }
@@ -213,6 +227,23 @@ impl ByzantineLedger {
             .expect("Could not send consensus msg");
     }
 
+    /// Replace the quorum set and the transaction source URLs.
+    ///
+    /// The change takes effect at the next slot boundary, so that a slot is never run with two
+    /// different quorum sets.
+    ///
+    /// # Arguments
+    /// * `quorum_set` - The new local quorum set.
+    /// * `tx_source_urls` - The new URLs used to fetch transactions when catching up.
+    pub fn update_network_config(&self, quorum_set: QuorumSet, tx_source_urls: Vec<String>) {
+        self.sender
+            .send(ByzantineLedgerTaskMessage::UpdateNetworkConfig(
+                quorum_set,
+                tx_source_urls,
+            ))
+            .expect("Could not send network config");
+    }
+
     pub fn stop(&mut self) {
         let _ = self.sender.send(ByzantineLedgerTaskMessage::StopTrigger);
         self.join();
@@ -236,6 +267,18 @@ impl ByzantineLedger {
             .expect("mutex poisoned")
             .clone()
     }
+
+    /// Add SCP message logging to `scp_node`, if a debug dump directory is configured.
+    fn wrap_scp_node<N: ScpNode<ConsensusValue> + 'static>(
+        scp_node: N,
+        opt_scp_debug_dump_dir: Option<PathBuf>,
+    ) -> Box<dyn ScpNode<ConsensusValue>> {
+        if let Some(path) = opt_scp_debug_dump_dir {
+            Box::new(LoggingScpNode::new(scp_node, path).expect("Failed creating LoggingScpNode"))
+        } else {
+            Box::new(scp_node)
+        }
+    }
 }
 
 impl Drop for ByzantineLedger {
@@ -267,13 +310,13 @@ enum LedgerSyncState {
 
 struct ByzantineLedgerThread<
     E: ConsensusEnclaveProxy,
-    F: Fn(Option<Msg<TxHash>>),
+    F: Fn(Option<Msg<ConsensusValue>>),
     L: Ledger + 'static,
     PC: BlockchainConnection + ConsensusConnection + 'static,
     UI: UntrustedInterfaces = crate::validators::DefaultTxManagerUntrustedInterfaces<L>,
 > {
     receiver: Receiver<ByzantineLedgerTaskMessage>,
-    scp: Box<dyn ScpNode<TxHash>>,
+    scp: Box<dyn ScpNode<ConsensusValue>>,
     is_behind: Arc<AtomicBool>,
     send_scp_message: F,
     ledger: L,
@@ -314,11 +357,17 @@ struct ByzantineLedgerThread<
 
     // Ledger sync state.
     ledger_sync_state: LedgerSyncState,
+
+    // URLs the ledger sync service fetches transactions from.
+    tx_source_urls: Vec<String>,
+
+    // Network configuration waiting for the current slot to end before it is applied.
+    pending_network_config: Option<(QuorumSet, Vec<String>)>,
 }
 
 impl<
         E: ConsensusEnclaveProxy,
-        F: Fn(Option<Msg<TxHash>>),
+        F: Fn(Option<Msg<ConsensusValue>>),
         L: Ledger + 'static,
         PC: BlockchainConnection + ConsensusConnection + 'static,
         UI: UntrustedInterfaces + Send + 'static,
@@ -328,7 +377,7 @@ impl<
         node_id: NodeID,
         quorum_set: QuorumSet,
         receiver: Receiver<ByzantineLedgerTaskMessage>,
-        scp: Box<dyn ScpNode<TxHash>>,
+        scp: Box<dyn ScpNode<ConsensusValue>>,
         is_behind: Arc<AtomicBool>,
         send_scp_message: F,
         ledger: L,
@@ -341,8 +390,9 @@ impl<
         let cur_slot = ledger.num_blocks().unwrap();
         let prev_block_id = ledger.get_block(cur_slot - 1).unwrap().id;
 
-        let transactions_fetcher = ReqwestTransactionsFetcher::new(tx_source_urls, logger.clone())
-            .unwrap_or_else(|e| panic!("Failed creating transaction fetcher: {:?}", e));
+        let transactions_fetcher =
+            ReqwestTransactionsFetcher::new(tx_source_urls.clone(), logger.clone())
+                .unwrap_or_else(|e| panic!("Failed creating transaction fetcher: {:?}", e));
 
         let ledger_sync_service = LedgerSyncService::new(
             ledger.clone(),
@@ -373,6 +423,8 @@ impl<
             network_state,
             ledger_sync_service,
             ledger_sync_state: LedgerSyncState::InSync,
+            tx_source_urls,
+            pending_network_config: None,
         };
 
         loop {
@@ -543,6 +595,9 @@ impl<
             unreachable!();
         }
 
+        // Switch to a new network configuration before the current slot starts.
+        self.maybe_apply_network_config();
+
         // Nominate values for current slot.
         self.nominate_pending_values();
 
@@ -550,8 +605,15 @@ impl<
         self.process_consensus_msgs_for_cur_slot();
 
         // Process SCP timeouts.
-        for outgoing_msg in self.scp.process_timeouts().into_iter() {
-            (self.send_scp_message)(Some(outgoing_msg));
+        match self.scp.process_timeouts() {
+            Ok(outgoing_msgs) => {
+                for outgoing_msg in outgoing_msgs {
+                    (self.send_scp_message)(Some(outgoing_msg));
+                }
+            }
+            Err(err) => {
+                log::error!(self.logger, "Failed processing timeouts: {:?}", err);
+            }
         }
 
         // See if we're done with the current slot.
@@ -600,6 +662,11 @@ impl<
                     }
                 }
 
+                // New network configuration. A later update replaces one that was not applied yet.
+                ByzantineLedgerTaskMessage::UpdateNetworkConfig(quorum_set, tx_source_urls) => {
+                    self.pending_network_config = Some((quorum_set, tx_source_urls));
+                }
+
                 // Request to stop thread
                 ByzantineLedgerTaskMessage::StopTrigger => {
                     return false;
@@ -609,6 +676,54 @@ impl<
         true
     }
 
+    /// Applies the pending network configuration, unless SCP already started working on the
+    /// current slot.
+    fn maybe_apply_network_config(&mut self) {
+        if self.pending_network_config.is_none()
+            || self.scp.get_slot_metrics(self.cur_slot).is_some()
+        {
+            return;
+        }
+        let (quorum_set, tx_source_urls) = self.pending_network_config.take().unwrap();
+
+        log::info!(
+            self.logger,
+            "Using quorum set {:?} starting at slot {}",
+            quorum_set,
+            self.cur_slot,
+        );
+        self.scp.set_quorum_set(quorum_set.clone());
+        self.network_state.set_quorum_set(quorum_set);
+
+        if tx_source_urls != self.tx_source_urls {
+            match ReqwestTransactionsFetcher::new(tx_source_urls.clone(), self.logger.clone()) {
+                Ok(transactions_fetcher) => {
+                    log::info!(
+                        self.logger,
+                        "Fetching transactions from {:?}",
+                        tx_source_urls
+                    );
+                    self.ledger_sync_service = LedgerSyncService::new(
+                        self.ledger.clone(),
+                        self.peer_manager.clone(),
+                        transactions_fetcher,
+                        self.logger.clone(),
+                    );
+                    self.tx_source_urls = tx_source_urls;
+                }
+                Err(err) => {
+                    log::error!(
+                        self.logger,
+                        "Failed creating transaction fetcher for {:?}, keeping {:?}: {:?}",
+                        tx_source_urls,
+                        self.tx_source_urls,
+                        err
+                    );
+                }
+            }
+        }
+    }
+
     fn nominate_pending_values(&mut self) {
         if !self.need_nominate {
             return;
@@ -616,17 +731,24 @@ impl<
 
         assert!(!self.pending_values.is_empty());
 
+        // Nominate the pending transactions along with this node's close time for the block, if the
+        // block records one.
+        let close_time = self
+            .tx_manager
+            .close_time()
+            .expect("failed getting close time");
+        let mut values: BTreeSet<ConsensusValue> = self
+            .pending_values
+            .iter()
+            .take(MAX_PENDING_VALUES_TO_NOMINATE)
+            .cloned()
+            .map(ConsensusValue::TxHash)
+            .collect();
+        values.extend(close_time.map(ConsensusValue::CloseTime));
+
         let outgoing_msg = self
             .scp
-            .nominate(
-                self.cur_slot,
-                BTreeSet::from_iter(
-                    self.pending_values
-                        .iter()
-                        .take(MAX_PENDING_VALUES_TO_NOMINATE)
-                        .cloned(),
-                ),
-            )
+            .nominate(self.cur_slot, values)
             .expect("nominate failed");
         (self.send_scp_message)(outgoing_msg);
 
@@ -700,6 +822,14 @@ impl<
             return;
         }
 
+        // The externalized values are the block's transactions and its agreed close time.
+        let close_time = ext_vals.iter().filter_map(ConsensusValue::close_time).max();
+        let ext_vals: Vec<TxHash> = ext_vals
+            .iter()
+            .filter_map(ConsensusValue::tx_hash)
+            .cloned()
+            .collect();
+
         // Update pending value processing time metrics.
         for ext_val in ext_vals.iter() {
             if let Some(Some(timestamp)) = self.pending_values_map.get(ext_val) {
@@ -724,7 +854,7 @@ impl<
         {
             let (block, block_contents, signature) = self
                 .tx_manager
-                .tx_hashes_to_block(&ext_vals)
+                .tx_hashes_to_block(&ext_vals, close_time)
                 .unwrap_or_else(|e| panic!("Failed to build block from {:?}: {:?}", ext_vals, e));
 
             log::info!(
@@ -810,12 +940,17 @@ impl<
 
     fn fetch_missing_txs(
         &mut self,
-        scp_msg: &Msg<TxHash>,
+        scp_msg: &Msg<ConsensusValue>,
         from_responder_id: &ResponderId,
     ) -> bool {
         // Get txs for all the hashes we are missing. This will eventually be replaced with
         // an enclave call, since the message is going to be encrypted (MC-74).
-        let tx_hashes = scp_msg.values();
+        let tx_hashes: BTreeSet<TxHash> = scp_msg
+            .values()
+            .iter()
+            .filter_map(ConsensusValue::tx_hash)
+            .cloned()
+            .collect();
 
         let mut all_missing_hashes = self.tx_manager.missing_hashes(&tx_hashes);
 
@@ -951,7 +1086,10 @@ mod tests {
     use mc_crypto_keys::{DistinguishedEncoding, Ed25519Private};
     use mc_ledger_db::Ledger;
     use mc_peers_test_utils::MockPeerConnection;
-    use mc_transaction_core::account_keys::AccountKey;
+    use mc_transaction_core::{
+        account_keys::AccountKey, BlockVersionActivation, BlockVersionSchedule, Feature,
+        NetworkParameters,
+    };
     use mc_transaction_core_test_utils::{create_ledger, create_transaction, initialize_ledger};
     use mc_util_from_random::FromRandom;
     use mc_util_uri::{ConnectionUri, ConsensusPeerUri as PeerUri};
@@ -1055,7 +1193,14 @@ mod tests {
         let tx_manager = TxManager::new(
             enclave.clone(),
             ledger.clone(),
-            DefaultTxManagerUntrustedInterfaces::new(ledger.clone()),
+            DefaultTxManagerUntrustedInterfaces::new(ledger.clone(), NetworkParameters::default()),
+            NetworkParameters::default(),
+            BlockVersionSchedule {
+                activations: vec![BlockVersionActivation {
+                    version: Feature::BlockTimestamp.activation_version(),
+                    block_index: num_blocks,
+                }],
+            },
             logger.clone(),
         );
 
@@ -1069,6 +1214,7 @@ mod tests {
             local_signer_key.clone(),
             Vec::new(),
             None,
+            None,
             logger.clone(),
         );
 
@@ -1153,49 +1299,63 @@ mod tests {
 
         let slot_index = num_blocks as SlotIndex;
 
-        // After some time, this node should nominate its client values.
-        let expected_msg = ConsensusMsg::from_scp_msg(
-            &ledger,
-            Msg::new(
-                local_node_id.clone(),
-                local_quorum_set.clone(),
-                slot_index,
-                Topic::Nominate(NominatePayload {
-                    X: BTreeSet::from_iter(vec![hash_tx_zero, hash_tx_one, hash_tx_two]),
-                    Y: BTreeSet::default(),
-                }),
-            ),
-            &local_signer_key,
-        )
-        .unwrap();
+        // After some time, this node should nominate its client values, along with its close
+        // time for the block.
+        let nominated_tx_hashes = BTreeSet::from_iter(vec![
+            ConsensusValue::TxHash(hash_tx_zero),
+            ConsensusValue::TxHash(hash_tx_one),
+            ConsensusValue::TxHash(hash_tx_two),
+        ]);
+        let find_close_time = || -> Option<u64> {
+            let msgs = &mock_peer_state
+                .lock()
+                .expect("Could not lock mock peer state")
+                .msgs;
+            msgs.iter().find_map(|msg| match &msg.scp_msg.topic {
+                Topic::Nominate(payload)
+                    if msg.scp_msg.sender_id == local_node_id
+                        && msg.scp_msg.slot_index == slot_index
+                        && payload.Y.is_empty() =>
+                {
+                    let close_time = payload.X.iter().find_map(ConsensusValue::close_time)?;
+                    let mut tx_hashes = payload.X.clone();
+                    tx_hashes.remove(&ConsensusValue::CloseTime(close_time));
+                    if tx_hashes == nominated_tx_hashes {
+                        Some(close_time)
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            })
+        };
 
+        let mut close_time = None;
         let deadline = Instant::now() + Duration::from_secs(60);
         while Instant::now() < deadline {
-            {
-                if mock_peer_state
-                    .lock()
-                    .expect("Could not lock mock peer state")
-                    .msgs
-                    .contains(&expected_msg)
-                {
-                    break;
-                }
+            close_time = find_close_time();
+            if close_time.is_some() {
+                break;
             }
 
             thread::sleep(Duration::from_millis(100 as u64));
         }
 
-        {
-            let msgs = &mock_peer_state
-                .lock()
-                .expect("Could not lock mock peer state")
-                .msgs;
-            assert!(
-                msgs.contains(&expected_msg),
+        let close_time = close_time.unwrap_or_else(|| {
+            panic!(
                 "Nominate msg not found. msgs={:#?}",
-                msgs,
-            );
-        }
+                mock_peer_state
+                    .lock()
+                    .expect("Could not lock mock peer state")
+                    .msgs
+            )
+        });
+        let ballot_values = [
+            ConsensusValue::TxHash(hash_tx_zero),
+            ConsensusValue::TxHash(hash_tx_one),
+            ConsensusValue::TxHash(hash_tx_two),
+            ConsensusValue::CloseTime(close_time),
+        ];
 
         // Push ballot statements from node_a and node_b so that consensus is reached.
         byzantine_ledger.handle_consensus_msg(
@@ -1206,7 +1366,7 @@ mod tests {
                     node_a.1.clone(),
                     slot_index,
                     Topic::Commit(CommitPayload {
-                        B: Ballot::new(100, &[hash_tx_zero, hash_tx_one, hash_tx_two]),
+                        B: Ballot::new(100, &ballot_values),
                         PN: 77,
                         CN: 55,
                         HN: 66,
@@ -1228,7 +1388,7 @@ mod tests {
                     node_b.1,
                     slot_index,
                     Topic::Commit(CommitPayload {
-                        B: Ballot::new(100, &[hash_tx_zero, hash_tx_one, hash_tx_two]),
+                        B: Ballot::new(100, &ballot_values),
                         PN: 77,
                         CN: 55,
                         HN: 66,
@@ -1289,7 +1449,7 @@ mod tests {
                     local_quorum_set,
                     slot_index,
                     Topic::Externalize(ExternalizePayload {
-                        C: Ballot::new(55, &[hash_tx_zero, hash_tx_one, hash_tx_two,]),
+                        C: Ballot::new(55, &ballot_values),
                         HN: 66,
                     }),
                 ),
@@ -1308,6 +1468,9 @@ mod tests {
 
         let signature_verification_result = signature.verify(&block);
         assert!(signature_verification_result.is_ok());
+
+        // The block was signed at the agreed close time.
+        assert_eq!(signature.signed_at(), close_time);
     }
 
     #[test]
@@ -6,13 +6,14 @@ use crate::{
     consensus_service::ProposeTxCallback,
     counters,
     grpc_error::ConsensusGrpcError,
-    tx_manager::{TxManager, TxManagerError},
+    tx_manager::{FeeStats, TxManager, TxManagerError},
 };
 use grpcio::{RpcContext, UnarySink};
 use mc_attest_api::attest::Message;
 use mc_common::logger::{log, Logger};
 use mc_consensus_api::{
-    consensus_client_grpc::ConsensusClientApi, consensus_common::ProposeTxResponse,
+    consensus_client::FeeStatsResponse, consensus_client_grpc::ConsensusClientApi,
+    consensus_common::ProposeTxResponse, empty::Empty,
 };
 use mc_consensus_enclave::ConsensusEnclaveProxy;
 use mc_ledger_db::Ledger;
@@ -155,4 +156,29 @@ impl<E: ConsensusEnclaveProxy, L: Ledger + Clone> ConsensusClientApi for ClientA
             )
         });
     }
+
+    fn get_fee_stats(
+        &mut self,
+        ctx: RpcContext,
+        _request: Empty,
+        sink: UnarySink<FeeStatsResponse>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        mc_common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            let response = FeeStatsResponse::from(&self.tx_manager.fee_stats());
+            send_result(ctx, sink, Ok(response), &logger)
+        });
+    }
+}
+
+impl From<&FeeStats> for FeeStatsResponse {
+    fn from(src: &FeeStats) -> Self {
+        let mut response = FeeStatsResponse::new();
+        response.set_num_txs(src.num_txs);
+        response.set_min_fee(src.min_fee);
+        response.set_median_fee(src.median_fee);
+        response.set_max_fee(src.max_fee);
+        response.set_competitive_fee(src.competitive_fee);
+        response
+    }
 }
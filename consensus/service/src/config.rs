@@ -6,7 +6,10 @@ use base64;
 use mc_attest_core::ProviderId;
 use mc_common::{HashMap, HashSet, NodeID, ResponderId};
 use mc_consensus_scp::{QuorumSet, QuorumSetMember};
-use mc_crypto_keys::{DistinguishedEncoding, Ed25519Pair, Ed25519Private};
+use mc_crypto_keys::{DistinguishedEncoding, Ed25519Pair, Ed25519Private, Ed25519Public};
+use mc_ledger_sync::BlockSignaturePolicy;
+use mc_transaction_core::{BlockVersionSchedule, NetworkParameters};
+use mc_util_parse::parse_ed25519_public_from_hex;
 use mc_util_uri::{ConnectionUri, ConsensusClientUri as ClientUri, ConsensusPeerUri as PeerUri};
 use serde::{Deserialize, Serialize};
 use std::{fmt::Debug, fs, iter::FromIterator, path::PathBuf, string::String, sync::Arc};
@@ -41,6 +44,12 @@ pub struct Config {
     #[structopt(long = "network", parse(from_os_str))]
     pub network_path: PathBuf,
 
+    /// Reload the network configuration file whenever it changes.
+    ///
+    /// The file can also be reloaded through the management interface.
+    #[structopt(long)]
+    pub watch_network: bool,
+
     /// Your Intel IAS API key.
     #[structopt(long)]
     pub ias_api_key: String,
@@ -63,6 +72,49 @@ pub struct Config {
     #[structopt(long)]
     pub management_listen_addr: Option<String>,
 
+    /// Bearer token granting read-only access to the management server.
+    ///
+    /// Command line arguments are visible to other users of the host, so prefer setting
+    /// MC_MANAGEMENT_READER_TOKEN or `management_reader_token_file`.
+    #[structopt(long, env = "MC_MANAGEMENT_READER_TOKEN", hide_env_values = true)]
+    pub management_reader_token: Option<String>,
+
+    /// File containing the bearer token granting read-only access to the management server.
+    #[structopt(long, parse(from_os_str))]
+    pub management_reader_token_file: Option<PathBuf>,
+
+    /// Bearer token granting full access to the management server, including endpoints that
+    /// change the node's state. Those endpoints are disabled when no admin token is provided.
+    ///
+    /// Command line arguments are visible to other users of the host, so prefer setting
+    /// MC_MANAGEMENT_ADMIN_TOKEN or `management_admin_token_file`.
+    #[structopt(long, env = "MC_MANAGEMENT_ADMIN_TOKEN", hide_env_values = true)]
+    pub management_admin_token: Option<String>,
+
+    /// File containing the bearer token granting full access to the management server.
+    #[structopt(long, parse(from_os_str))]
+    pub management_admin_token_file: Option<PathBuf>,
+
+    /// Allow the read-only management endpoints to be used without a token.
+    ///
+    /// Otherwise they require the reader or admin token, and are disabled when neither is
+    /// provided.
+    #[structopt(long)]
+    pub management_allow_unauthenticated_reads: bool,
+
+    /// Require the reader or admin token to read `/metrics` and `/metrics-json`.
+    ///
+    /// Otherwise metrics can be scraped by anyone who can reach the management address, even when
+    /// no token is configured.
+    #[structopt(long)]
+    pub management_metrics_require_token: bool,
+
+    /// File that changes made through the management server are appended to, as JSON lines.
+    ///
+    /// Changes are always logged, and the most recent ones are listed by `GET /audit-log`.
+    #[structopt(long, parse(from_os_str))]
+    pub management_audit_log_path: Option<PathBuf>,
+
     /// The location to write the externalized blocks for the ledger.
     #[structopt(long, parse(from_os_str))]
     pub ledger_path: PathBuf,
@@ -71,6 +123,30 @@ pub struct Config {
     #[structopt(long, parse(from_os_str))]
     pub origin_block_path: Option<PathBuf>,
 
+    /// Path to a ledger snapshot, imported instead of the origin block when the ledger does not
+    /// exist yet.
+    ///
+    /// The snapshot is checked against its chain of block IDs, and its last block must be signed
+    /// by enough trusted block signers (see `--trusted-block-signer`). Blocks after it are then
+    /// synced from peers as usual.
+    #[structopt(long, parse(from_os_str))]
+    pub ledger_snapshot: Option<PathBuf>,
+
+    /// Public key of a block signer trusted to sign the last block of `--ledger-snapshot`,
+    /// hex-encoded. May be given multiple times.
+    #[structopt(long = "trusted-block-signer", parse(try_from_str=parse_ed25519_public_from_hex))]
+    pub trusted_block_signers: Vec<Ed25519Public>,
+
+    /// Number of distinct trusted signers that must have signed the last block of
+    /// `--ledger-snapshot`. Must be at least 1.
+    #[structopt(long, default_value = "1")]
+    pub min_trusted_block_signatures: usize,
+
+    /// File to save the SCP state of the current slot to, so that the node resumes the slot
+    /// instead of contradicting its earlier votes when it restarts.
+    #[structopt(long, parse(from_os_str))]
+    pub scp_state_path: Option<PathBuf>,
+
     /// SCP debug output.
     #[structopt(long, parse(from_os_str))]
     pub scp_debug_dump: Option<PathBuf>,
@@ -89,6 +165,33 @@ fn keypair_from_base64(src: &str) -> Result<Arc<Ed25519Pair>, String> {
     Ok(Arc::new(Ed25519Pair::from(secret_key)))
 }
 
+/// A token given either directly or as a file containing it. Surrounding whitespace in the file,
+/// such as a trailing newline, is not part of the token.
+fn read_token(
+    token: &Option<String>,
+    token_file: &Option<PathBuf>,
+) -> Result<Option<String>, String> {
+    let token = match (token, token_file) {
+        (Some(_), Some(path)) => {
+            return Err(format!(
+                "A token and a token file {:?} were both provided",
+                path
+            ))
+        }
+        (Some(token), None) => token.clone(),
+        (None, Some(path)) => fs::read_to_string(path)
+            .map_err(|err| format!("failed reading {:?}: {:?}", path, err))?
+            .trim()
+            .to_string(),
+        (None, None) => return Ok(None),
+    };
+
+    if token.is_empty() {
+        return Err("Management tokens must not be empty".to_string());
+    }
+    Ok(Some(token))
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub struct NetworkConfig {
     /// The set of nodes which you trust to validate transactions.
@@ -102,6 +205,13 @@ pub struct NetworkConfig {
 
     /// Optional list of peers we are aware of.
     pub known_peers: Option<Vec<PeerUri>>,
+
+    /// Optional transaction validation limits. The defaults are used when omitted.
+    pub network_parameters: Option<NetworkParameters>,
+
+    /// Optional schedule of block version upgrades. Blocks keep the initial version when omitted.
+    /// The origin block commits to the schedule, so it can not be changed on a running network.
+    pub block_version_schedule: Option<BlockVersionSchedule>,
 }
 
 impl NetworkConfig {
@@ -136,10 +246,59 @@ impl NetworkConfig {
         Self::resolve_quorum_set(&self.quorum_set, &peer_map)
     }
 
+    /// Checks that the quorum set is well formed and that every member of it is a known peer.
+    ///
+    /// `quorum_set()` panics when this does not hold.
+    pub fn check_quorum_set(&self) -> Result<(), String> {
+        let mut peer_map: HashMap<ResponderId, NodeID> = HashMap::default();
+        for uri in self
+            .broadcast_peers
+            .iter()
+            .chain(self.known_peers.iter().flatten())
+        {
+            let responder_id = uri
+                .responder_id()
+                .map_err(|e| format!("unable to get responder_id for {}: {:?}", uri, e))?;
+            let node_id = uri
+                .node_id()
+                .map_err(|e| format!("unable to get node_id for {}: {:?}", uri, e))?;
+            if peer_map.get(&responder_id).unwrap_or(&node_id) != &node_id {
+                return Err(format!("node id mismatch for {}", responder_id));
+            }
+            peer_map.insert(responder_id, node_id);
+        }
+
+        if let Some(responder_id) = self
+            .quorum_set
+            .nodes()
+            .into_iter()
+            .find(|responder_id| !peer_map.contains_key(responder_id))
+        {
+            return Err(format!(
+                "Unknown responder_id {} in quorum set",
+                responder_id
+            ));
+        }
+
+        if !self.quorum_set.is_valid() {
+            return Err(format!("Invalid quorum set {:?}", self.quorum_set));
+        }
+
+        Ok(())
+    }
+
     pub fn broadcast_peers(&self) -> Vec<PeerUri> {
         self.broadcast_peers.clone()
     }
 
+    pub fn network_parameters(&self) -> NetworkParameters {
+        self.network_parameters.unwrap_or_default()
+    }
+
+    pub fn block_version_schedule(&self) -> BlockVersionSchedule {
+        self.block_version_schedule.clone().unwrap_or_default()
+    }
+
     // Convert a QuorumSet<ResponderId> -> QuorumSet<NodeID> based on a
     // ResponderID -> NodeID map.
     fn resolve_quorum_set(
@@ -176,29 +335,64 @@ impl Config {
         }
     }
 
+    /// The policy used to decide whether `ledger_snapshot` is trusted.
+    pub fn block_signature_policy(&self) -> Result<BlockSignaturePolicy, String> {
+        BlockSignaturePolicy::new(
+            self.trusted_block_signers.iter().cloned(),
+            self.min_trusted_block_signatures,
+        )
+    }
+
+    /// The management reader token, read from `management_reader_token_file` if provided.
+    pub fn management_reader_token(&self) -> Result<Option<String>, String> {
+        read_token(
+            &self.management_reader_token,
+            &self.management_reader_token_file,
+        )
+    }
+
+    /// The management admin token, read from `management_admin_token_file` if provided.
+    pub fn management_admin_token(&self) -> Result<Option<String>, String> {
+        read_token(
+            &self.management_admin_token,
+            &self.management_admin_token_file,
+        )
+    }
+
     /// Get the network configuration by loading the network.toml/json file.
+    ///
+    /// Panics if the file cannot be loaded. See `load_network`.
     pub fn network(&self) -> NetworkConfig {
+        self.load_network().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Load the network.toml/json file and check that it is consistent.
+    pub fn load_network(&self) -> Result<NetworkConfig, String> {
         // Read configuration file.
         let data = fs::read_to_string(&self.network_path)
-            .unwrap_or_else(|err| panic!("failed reading {:?}: {:?}", self.network_path, err));
+            .map_err(|err| format!("failed reading {:?}: {:?}", self.network_path, err))?;
 
         // Parse configuration file.
         let network: NetworkConfig =
             match self.network_path.extension().and_then(|ext| ext.to_str()) {
-                None => panic!(
-                    "failed figuring out file extension for path {:?}",
-                    self.network_path
-                ),
-                Some("toml") => toml::from_str(&data).unwrap_or_else(|err| {
-                    panic!("failed TOML parsing {:?}: {:?}", self.network_path, err)
-                }),
-                Some("json") => serde_json::from_str(&data).unwrap_or_else(|err| {
-                    panic!("failed JSON parsing {:?}: {:?}", self.network_path, err)
-                }),
-                Some(ext) => panic!(
-                    "Unrecognized extension in path {:?}: {:?}",
-                    self.network_path, ext
-                ),
+                None => {
+                    return Err(format!(
+                        "failed figuring out file extension for path {:?}",
+                        self.network_path
+                    ))
+                }
+                Some("toml") => toml::from_str(&data).map_err(|err| {
+                    format!("failed TOML parsing {:?}: {:?}", self.network_path, err)
+                })?,
+                Some("json") => serde_json::from_str(&data).map_err(|err| {
+                    format!("failed JSON parsing {:?}: {:?}", self.network_path, err)
+                })?,
+                Some(ext) => {
+                    return Err(format!(
+                        "Unrecognized extension in path {:?}: {:?}",
+                        self.network_path, ext
+                    ))
+                }
             };
 
         // Sanity tests:
@@ -211,33 +405,33 @@ impl Config {
             .chain(network.known_peers.iter().flatten());
         let mut spotted_responder_ids = HashSet::default();
         for peer_uri in peer_uris {
-            let responder_id = peer_uri.responder_id().unwrap_or_else(|e| {
-                panic!("failed getting responder id for {:?}: {:?}", peer_uri, e)
-            });
+            let responder_id = peer_uri
+                .responder_id()
+                .map_err(|e| format!("failed getting responder id for {:?}: {:?}", peer_uri, e))?;
 
             if self.peer_responder_id == responder_id {
-                panic!(
+                return Err(format!(
                     "Our peer responder id ({}) should not appear in broadcast_peers or known_peers!",
                     responder_id
-                );
+                ));
             }
 
             if !spotted_responder_ids.insert(responder_id.clone()) {
-                panic!(
+                return Err(format!(
                     "Duplicate responder_id {} found in network configuration",
                     responder_id
-                );
+                ));
             }
         }
 
         // Sanity test: We should have at least one source of transactions, if we have any peers
         // configured.
         if !network.broadcast_peers.is_empty() && network.tx_source_urls.is_empty() {
-            panic!("Network configuration is missing tx_source_urls");
+            return Err("Network configuration is missing tx_source_urls".to_string());
         }
 
         // Success.
-        network
+        Ok(network)
     }
 }
 
@@ -246,7 +440,9 @@ mod tests {
     use super::*;
     use mc_consensus_scp::QuorumSetMember;
     use mc_crypto_keys::Ed25519Public;
+    use mc_transaction_core::BLOCK_VERSION;
     use std::str::FromStr;
+    use tempdir::TempDir;
 
     #[test]
     fn test_network_config_parsing() {
@@ -275,6 +471,53 @@ mod tests {
             assert_eq!(network.quorum_set.members.len(), 0);
             assert_eq!(network.broadcast_peers.len(), 0);
             assert!(network.known_peers.is_none());
+            assert_eq!(network.network_parameters(), NetworkParameters::default());
+            assert_eq!(
+                network.block_version_schedule(),
+                BlockVersionSchedule::default()
+            );
+        }
+
+        // Network parameters, with omitted values taking their defaults.
+        {
+            let input_toml: &str = r#"
+                broadcast_peers = []
+                tx_source_urls = []
+                quorum_set = { threshold = 2, members = [] }
+
+                [network_parameters]
+                ring_size = 5
+                base_fee = 1
+            "#;
+            let network: NetworkConfig = toml::from_str(input_toml).expect("failed parsing toml");
+
+            assert_eq!(
+                network.network_parameters(),
+                NetworkParameters {
+                    ring_size: 5,
+                    base_fee: 1,
+                    ..Default::default()
+                }
+            );
+        }
+
+        // Block version schedule.
+        {
+            let input_toml: &str = r#"
+                broadcast_peers = []
+                tx_source_urls = []
+                quorum_set = { threshold = 2, members = [] }
+
+                [[block_version_schedule.activations]]
+                version = 1
+                block_index = 1000
+            "#;
+            let network: NetworkConfig = toml::from_str(input_toml).expect("failed parsing toml");
+
+            let schedule = network.block_version_schedule();
+            assert_eq!(schedule.check(), Ok(()));
+            assert_eq!(schedule.version_at(999), BLOCK_VERSION);
+            assert_eq!(schedule.version_at(1000), 1);
         }
 
         // Real world configuration.
@@ -381,6 +624,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_check_quorum_set() {
+        let network_with_quorum_set = |quorum_set: &str| -> NetworkConfig {
+            let input_toml = format!(
+                r#"
+                broadcast_peers = [
+                    "insecure-mcp://0.0.0.0:8082?consensus-msg-key=MCowBQYDK2VwAyEA_ii3rCch5qhMbLZ2vVgpQr1iTrq1BBN2-i0mMPuAJhQ=",
+                    "insecure-mcp://0.0.0.0:8083?consensus-msg-key=MCowBQYDK2VwAyEA9C-J6AUm9XnSjrGEhplQpp_jMPNwIxBovFJrJRXtoVA=",
+                ]
+                tx_source_urls = ["file:///tmp/dump"]
+                quorum_set = {}
+            "#,
+                quorum_set
+            );
+            toml::from_str(&input_toml).expect("failed parsing toml")
+        };
+
+        let network = network_with_quorum_set(
+            r#"{ threshold = 2, members = [
+                { type = "Node", args = "0.0.0.0:8082" },
+                { type = "Node", args = "0.0.0.0:8083" },
+            ] }"#,
+        );
+        assert_eq!(network.check_quorum_set(), Ok(()));
+
+        // Members must be known peers.
+        let network = network_with_quorum_set(
+            r#"{ threshold = 1, members = [
+                { type = "Node", args = "0.0.0.0:8084" },
+            ] }"#,
+        );
+        assert!(network.check_quorum_set().is_err());
+
+        // The threshold must be reachable.
+        let network = network_with_quorum_set(
+            r#"{ threshold = 3, members = [
+                { type = "Node", args = "0.0.0.0:8082" },
+                { type = "Node", args = "0.0.0.0:8083" },
+            ] }"#,
+        );
+        assert!(network.check_quorum_set().is_err());
+    }
+
     #[test]
     fn test_local_uris_with_pubkey() {
         let config = Config {
@@ -391,14 +677,26 @@ mod tests {
             )
             .unwrap(),
             network_path: PathBuf::from("network.toml"),
+            watch_network: false,
             ias_api_key: "".to_string(),
             ias_spid: ProviderId::from_str("22222222222222222222222222222222").unwrap(),
             peer_listen_uri: PeerUri::from_str("insecure-mcp://0.0.0.0:8081/").unwrap(),
             client_listen_uri: ClientUri::from_str("insecure-mc://0.0.0.0:3223/").unwrap(),
             management_listen_addr: None,
+            management_reader_token: None,
+            management_reader_token_file: None,
+            management_admin_token: None,
+            management_admin_token_file: None,
+            management_allow_unauthenticated_reads: false,
+            management_metrics_require_token: false,
+            management_audit_log_path: None,
             ledger_path: PathBuf::default(),
+            scp_state_path: None,
             scp_debug_dump: None,
             origin_block_path: None,
+            ledger_snapshot: None,
+            trusted_block_signers: Vec::new(),
+            min_trusted_block_signatures: 1,
             sealed_block_signing_key: PathBuf::default(),
         };
 
@@ -440,14 +738,26 @@ mod tests {
                 "MC4CAQAwBQYDK2VwBCIEIC50QXQll2Y9qxztvmsUgcBBIxkmk7EQjxzQTa926bKo",
             ) .unwrap(),
             network_path: PathBuf::from("network.toml"),
+            watch_network: false,
             ias_api_key: "".to_string(),
             ias_spid: ProviderId::from_str("22222222222222222222222222222222").unwrap(),
             peer_listen_uri: PeerUri::from_str("mcp://0.0.0.0:8443/?tls-chain=./public/attest/test_certs/selfsigned_mobilecoin.crt&tls-key=./public/attest/test_certs/selfsigned_mobilecoin.key").unwrap(),
             client_listen_uri: ClientUri::from_str("insecure-mc://0.0.0.0:3223/").unwrap(),
             management_listen_addr: None,
+            management_reader_token: None,
+            management_reader_token_file: None,
+            management_admin_token: None,
+            management_admin_token_file: None,
+            management_allow_unauthenticated_reads: false,
+            management_metrics_require_token: false,
+            management_audit_log_path: None,
             ledger_path: PathBuf::default(),
+            scp_state_path: None,
             scp_debug_dump: None,
             origin_block_path: None,
+            ledger_snapshot: None,
+            trusted_block_signers: Vec::new(),
+            min_trusted_block_signatures: 1,
             sealed_block_signing_key: PathBuf::default(),
         };
 
@@ -480,4 +790,29 @@ mod tests {
             PeerUri::from_str("mcp://0.0.0.0:8443/?tls-chain=./public/attest/test_certs/selfsigned_mobilecoin.crt&tls-key=./public/attest/test_certs/selfsigned_mobilecoin.key").unwrap()
         );
     }
+
+    #[test]
+    fn test_read_token() {
+        let temp_dir = TempDir::new("tokens").unwrap();
+        let token_file = temp_dir.path().join("token");
+        fs::write(&token_file, "secret\n").unwrap();
+
+        assert_eq!(read_token(&None, &None), Ok(None));
+        assert_eq!(
+            read_token(&Some("secret".to_string()), &None),
+            Ok(Some("secret".to_string()))
+        );
+        assert_eq!(
+            read_token(&None, &Some(token_file.clone())),
+            Ok(Some("secret".to_string()))
+        );
+
+        // Ambiguous, empty and missing tokens are rejected.
+        assert!(read_token(&Some("secret".to_string()), &Some(token_file)).is_err());
+        assert!(read_token(&Some("".to_string()), &None).is_err());
+        let empty_file = temp_dir.path().join("empty");
+        fs::write(&empty_file, " \n").unwrap();
+        assert!(read_token(&None, &Some(empty_file)).is_err());
+        assert!(read_token(&None, &Some(temp_dir.path().join("missing"))).is_err());
+    }
 }
@@ -3,10 +3,20 @@
 //! The MobileCoin consensus service.
 
 use crate::{
-    attested_api_service::AttestedApiService, background_work_queue::BackgroundWorkQueue,
-    blockchain_api_service, byzantine_ledger::ByzantineLedger, client_api_service, config::Config,
-    counters, management::ManagementServer, peer_api_service, peer_keepalive::PeerKeepalive,
-    tx_manager::TxManager, validators::DefaultTxManagerUntrustedInterfaces,
+    attested_api_service::AttestedApiService,
+    background_work_queue::BackgroundWorkQueue,
+    block_subscriptions::{self, BlockSubscriptions},
+    blockchain_api_service,
+    byzantine_ledger::ByzantineLedger,
+    client_api_service,
+    config::Config,
+    counters,
+    management::ManagementServer,
+    network_config_reloader::{NetworkConfigReloader, NetworkConfigWatcher, ReloadNetworkConfigFn},
+    peer_api_service,
+    peer_keepalive::PeerKeepalive,
+    tx_manager::TxManager,
+    validators::DefaultTxManagerUntrustedInterfaces,
 };
 use failure::Fail;
 use futures::Future;
@@ -27,6 +37,7 @@ use mc_connection::{Connection, ConnectionManager, ConnectionUriGrpcioServer};
 use mc_consensus_api::{consensus_client_grpc, consensus_common_grpc, consensus_peer_grpc};
 use mc_consensus_enclave::{ConsensusEnclaveProxy, Error as EnclaveError};
 use mc_ledger_db::LedgerDB;
+use mc_ledger_query::LedgerQueryService;
 use mc_peers::{PeerConnection, ThreadedBroadcaster, VerifiedConsensusMsg};
 use mc_transaction_core::tx::TxHash;
 use mc_util_grpc::{BuildInfoService, HealthCheckStatus, HealthService};
@@ -126,9 +137,12 @@ pub struct ConsensusService<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync
     broadcaster: Arc<Mutex<ThreadedBroadcaster>>,
     tx_manager: TxManager<E, LedgerDB>,
     peer_keepalive: Arc<Mutex<PeerKeepalive>>,
+    network_config_reloader: Arc<NetworkConfigReloader<E>>,
+    network_config_watcher: Option<NetworkConfigWatcher>,
 
     consensus_rpc_server: Option<grpcio::Server>,
     user_rpc_server: Option<grpcio::Server>,
+    block_subscriptions: BlockSubscriptions,
     byzantine_ledger: Arc<Mutex<Option<ByzantineLedger>>>,
 }
 
@@ -152,10 +166,10 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
             BackgroundWorkQueue::new(&counters::CONSENSUS_MSGS_FROM_NETWORK_QUEUE_SIZE);
 
         let local_node_id = config.node_id();
+        let network = config.network();
 
         // Peers
-        let peers: Vec<PeerConnection<E>> = config
-            .network()
+        let peers: Vec<PeerConnection<E>> = network
             .broadcast_peers()
             .into_iter()
             .map(|peer_uri| {
@@ -179,10 +193,13 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
         )));
 
         // Tx Manager
+        let network_parameters = network.network_parameters();
         let tx_manager = TxManager::new(
             enclave.clone(),
             ledger_db.clone(),
-            DefaultTxManagerUntrustedInterfaces::new(ledger_db.clone()),
+            DefaultTxManagerUntrustedInterfaces::new(ledger_db.clone(), network_parameters),
+            network_parameters,
+            network.block_version_schedule(),
             logger.clone(),
         );
 
@@ -193,9 +210,29 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
             logger.clone(),
         )));
 
+        // Network configuration reloading
+        let byzantine_ledger = Arc::new(Mutex::new(None));
+        let network_config_reloader = Arc::new(NetworkConfigReloader::new(
+            config.clone(),
+            network,
+            enclave.clone(),
+            env.clone(),
+            peer_manager.clone(),
+            broadcaster.clone(),
+            peer_keepalive.clone(),
+            byzantine_ledger.clone(),
+            logger.clone(),
+        ));
+
         // Management Server
         let management_server = if config.management_listen_addr.is_some() {
-            Some(ManagementServer::new(config.clone(), logger.clone()))
+            let reloader = network_config_reloader.clone();
+            Some(ManagementServer::new(
+                config.clone(),
+                Arc::new(tx_manager.clone()),
+                Arc::new(move || reloader.reload()),
+                logger.clone(),
+            ))
         } else {
             None
         };
@@ -217,10 +254,15 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
             broadcaster,
             tx_manager,
             peer_keepalive,
+            network_config_reloader,
+            network_config_watcher: None,
 
             consensus_rpc_server: None,
             user_rpc_server: None,
-            byzantine_ledger: Arc::new(Mutex::new(None)),
+            block_subscriptions: BlockSubscriptions::new(
+                block_subscriptions::DEFAULT_MAX_BLOCK_SUBSCRIPTIONS,
+            ),
+            byzantine_ledger,
         }
     }
 
@@ -231,10 +273,16 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
     pub fn start(&mut self) -> Result<(), ConsensusServiceError> {
         let ret = {
             self.update_enclave_report_cache()?;
+            self.block_subscriptions.start(
+                self.ledger_db.clone(),
+                block_subscriptions::DEFAULT_POLL_INTERVAL,
+                self.logger.clone(),
+            );
             self.start_consensus_rpc_server()?;
             self.start_user_rpc_server()?;
             self.start_byzantine_ledger_service()?;
             self.start_management_server()?;
+            self.start_network_config_watcher();
 
             // Success.
             Ok(())
@@ -252,6 +300,10 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
             server.stop();
         }
 
+        if let Some(ref mut watcher) = self.network_config_watcher.take() {
+            watcher.stop();
+        }
+
         self.peer_keepalive.lock().expect("mutex poisoned").stop();
 
         if let Some(ref mut server) = self.user_rpc_server.take() {
@@ -270,6 +322,8 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
             })?
         }
 
+        self.block_subscriptions.stop();
+
         self.consensus_msgs_from_network.stop().map_err(|e| {
             ConsensusServiceError::BackgroundWorkQueueStop(format!(
                 "consensus_msgs_from_network: {:?}",
@@ -432,10 +486,14 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
         let blockchain_service = consensus_common_grpc::create_blockchain_api(
             blockchain_api_service::BlockchainApiService::new(
                 self.ledger_db.clone(),
+                self.block_subscriptions.clone(),
                 self.logger.clone(),
             ),
         );
 
+        let ledger_query_service =
+            LedgerQueryService::new(self.ledger_db.clone(), self.logger.clone()).into_service();
+
         let is_serving_user_requests = self.create_is_serving_user_requests_fn();
         let health_check_callback: Arc<dyn Fn(&str) -> HealthCheckStatus + Sync + Send> =
             Arc::new(move |_| {
@@ -460,6 +518,7 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
         let server_builder = grpcio::ServerBuilder::new(env)
             .register_service(client_service)
             .register_service(blockchain_service)
+            .register_service(ledger_query_service)
             .register_service(health_service)
             .register_service(attested_service)
             .register_service(build_info_service)
@@ -497,6 +556,7 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
         let blockchain_service = consensus_common_grpc::create_blockchain_api(
             blockchain_api_service::BlockchainApiService::new(
                 self.ledger_db.clone(),
+                self.block_subscriptions.clone(),
                 self.logger.clone(),
             ),
         );
@@ -509,7 +569,7 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
                 self.ledger_db.clone(),
                 self.tx_manager.clone(),
                 get_highest_scp_message_fn,
-                self.peer_manager.responder_ids(),
+                self.create_is_known_peer_fn(),
                 self.logger.clone(),
             ));
 
@@ -555,6 +615,7 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
             self.broadcaster.clone(),
             self.config.msg_signer_key.clone(),
             self.config.network().tx_source_urls,
+            self.config.scp_state_path.clone(),
             self.config.scp_debug_dump.clone(),
             self.logger.clone(),
         ));
@@ -600,6 +661,30 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
         Ok(())
     }
 
+    fn start_network_config_watcher(&mut self) {
+        if self.config.watch_network {
+            log::info!(
+                self.logger,
+                "Watching {:?} for changes.",
+                self.config.network_path
+            );
+            let reloader = self.network_config_reloader.clone();
+            let reload_fn: ReloadNetworkConfigFn = Arc::new(move || reloader.reload());
+            self.network_config_watcher = Some(NetworkConfigWatcher::start(
+                &self.config,
+                reload_fn,
+                self.logger.clone(),
+            ));
+        }
+    }
+
+    /// Creates a function that returns true if a responder ID belongs to one of our peers.
+    fn create_is_known_peer_fn(&self) -> Arc<dyn Fn(&ResponderId) -> bool + Sync + Send> {
+        let peer_manager = self.peer_manager.clone();
+
+        Arc::new(move |responder_id| peer_manager.conn(responder_id).is_some())
+    }
+
     /// Creates a function that returns true if the node is currently serving user requests.
     fn create_is_serving_user_requests_fn(&self) -> Arc<dyn Fn() -> bool + Sync + Send> {
         let byzantine_ledger = self.byzantine_ledger.clone();
@@ -613,6 +698,19 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
         })
     }
 
+    /// Returns true if transactions received from `responder_id` should be relayed to our other
+    /// peers. See comment in `create_scp_client_value_sender_fn` ("Broadcast to peers") for more
+    /// details.
+    fn is_relaying_from(
+        peer_manager: &ConnectionManager<PeerConnection<E>>,
+        responder_id: &ResponderId,
+    ) -> bool {
+        peer_manager
+            .conn(responder_id)
+            .map(|conn| conn.uri().consensus_relay_incoming_txs())
+            .unwrap_or(false)
+    }
+
     /// Creates a function that feeds client values into ByzantineLedger and broadcasts it to our
     /// peers.
     fn create_scp_client_value_sender_fn(&self) -> ProposeTxCallback {
@@ -621,31 +719,7 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
         let local_node_id = self.local_node_id.clone();
         let broadcaster = self.broadcaster.clone();
 
-        // Figure out which node IDs we are going to be relaying received transactions from.
-        // See comment below ("Broadcast to peers") for more details.
-        let relay_from_nodes: Vec<ResponderId> = self
-            .peer_manager
-            .conns()
-            .iter()
-            .filter_map(|conn| {
-                let uri = conn.uri();
-                if uri.consensus_relay_incoming_txs() {
-                    match uri.responder_id() {
-                        Ok(responder_id) => Some(responder_id),
-                        Err(_e) => {
-                            log::warn!(
-                                self.logger,
-                                "Could not get responder_id from {:?}",
-                                uri.to_string()
-                            );
-                            None
-                        }
-                    }
-                } else {
-                    None
-                }
-            })
-            .collect();
+        let peer_manager = self.peer_manager.clone();
 
         Arc::new(move |tx_hash, origin_node, relayed_from| {
             let origin_node = origin_node.unwrap_or(&local_node_id);
@@ -659,7 +733,8 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
             // However, in non-mesh configurations, network operators might want to selectively have
             // incoming transactions from certain peers be relayed to other peers in order to improve
             // consensus time.
-            if origin_node == &local_node_id || relay_from_nodes.contains(&origin_node.responder_id)
+            if origin_node == &local_node_id
+                || Self::is_relaying_from(&peer_manager, &origin_node.responder_id)
             {
                 if let Some(encrypted_tx) = tx_manager.get_encrypted_tx_by_hash(&tx_hash) {
                     broadcaster
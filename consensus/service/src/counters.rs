@@ -26,6 +26,9 @@ lazy_static::lazy_static! {
     // Blocks written through byzantine ledger service since this node started.
     pub static ref BLOCKS_WRITTEN_COUNT: IntCounter = OP_COUNTERS.counter("blocks_written_count");
 
+    // Number of open SubscribeBlocks streams.
+    pub static ref BLOCK_SUBSCRIPTIONS: IntGauge = OP_COUNTERS.gauge("block_subscriptions");
+
     // Number of blocks written to the ledger (by querying ledger)
     pub static ref BLOCKS_IN_LEDGER: IntGauge = OP_COUNTERS.gauge("num_blocks");
 
@@ -94,6 +97,12 @@ lazy_static::lazy_static! {
     // Number of entries in the transactions cache.
     pub static ref TX_CACHE_NUM_ENTRIES: IntGauge = OP_COUNTERS.gauge("tx_cache_num_entries");
 
+    // Number of entries in the transactions cache, by time since they entered the cache.
+    pub static ref TX_CACHE_NUM_ENTRIES_AGE_UNDER_10S: IntGauge = OP_COUNTERS.gauge("tx_cache_num_entries_age_under_10s");
+    pub static ref TX_CACHE_NUM_ENTRIES_AGE_10S_TO_1M: IntGauge = OP_COUNTERS.gauge("tx_cache_num_entries_age_10s_to_1m");
+    pub static ref TX_CACHE_NUM_ENTRIES_AGE_1M_TO_10M: IntGauge = OP_COUNTERS.gauge("tx_cache_num_entries_age_1m_to_10m");
+    pub static ref TX_CACHE_NUM_ENTRIES_AGE_OVER_10M: IntGauge = OP_COUNTERS.gauge("tx_cache_num_entries_age_over_10m");
+
     // Number of consensus messages dropped due to referencing an invalid previous block id.
     pub static ref SCP_MESSAGES_DROPPED_DUE_TO_INVALID_PREV_BLOCK_ID: IntCounter = OP_COUNTERS.counter("scp_messages_dropped_due_to_invalid_prev_block_id");
 
@@ -14,12 +14,14 @@ pub mod validators; // Public so that it can be benchmarked by the `benchmarks`
 
 mod attested_api_service;
 mod background_work_queue;
+mod block_subscriptions;
 mod blockchain_api_service;
 mod byzantine_ledger;
 mod client_api_service;
 mod counters;
 mod grpc_error;
 mod management;
+mod network_config_reloader;
 mod peer_api_service;
 mod peer_keepalive;
 
@@ -0,0 +1,206 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A record of changes made through the management interface.
+
+use super::auth::Role;
+use mc_common::logger::{log, Logger};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Maximal number of entries kept in memory. Every entry is also written to the log, and to the
+/// audit log file if there is one.
+pub const MAX_AUDIT_LOG_ENTRIES: usize = 1000;
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AuditLogEntry {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+
+    /// Address of the client that made the change.
+    pub remote_addr: String,
+
+    /// Role the client acted with.
+    pub role: Role,
+
+    /// The endpoint that made the change, e.g. "set-rust-log".
+    pub action: String,
+
+    /// What was changed.
+    pub details: String,
+}
+
+#[derive(Clone)]
+pub struct AuditLog {
+    /// The most recent entries, oldest first.
+    entries: Arc<Mutex<VecDeque<AuditLogEntry>>>,
+
+    /// File that every entry is appended to, as a line of JSON.
+    file: Option<Arc<Mutex<File>>>,
+
+    logger: Logger,
+}
+
+impl AuditLog {
+    /// Creates an audit log that is only kept in memory and written to the log.
+    pub fn new(logger: Logger) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(VecDeque::new())),
+            file: None,
+            logger,
+        }
+    }
+
+    /// Creates an audit log that is also appended to a file, starting with the most recent entries
+    /// already in it.
+    ///
+    /// # Arguments
+    /// * `path` - The audit log file. Created if it does not exist.
+    /// * `logger` - Logger.
+    pub fn open(path: &Path, logger: Logger) -> Result<Self, String> {
+        let mut entries = VecDeque::new();
+        if path.exists() {
+            let file =
+                File::open(path).map_err(|err| format!("failed opening {:?}: {:?}", path, err))?;
+            for line in BufReader::new(file).lines() {
+                let line = line.map_err(|err| format!("failed reading {:?}: {:?}", path, err))?;
+                let entry: AuditLogEntry = serde_json::from_str(&line)
+                    .map_err(|err| format!("failed parsing {:?}: {:?}", path, err))?;
+                if entries.len() >= MAX_AUDIT_LOG_ENTRIES {
+                    entries.pop_front();
+                }
+                entries.push_back(entry);
+            }
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| format!("failed opening {:?}: {:?}", path, err))?;
+
+        Ok(Self {
+            entries: Arc::new(Mutex::new(entries)),
+            file: Some(Arc::new(Mutex::new(file))),
+            logger,
+        })
+    }
+
+    /// Records a change.
+    ///
+    /// # Arguments
+    /// * `remote_addr` - Address of the client that made the change.
+    /// * `role` - Role the client acted with.
+    /// * `action` - The endpoint that made the change.
+    /// * `details` - What was changed.
+    pub fn record(&self, remote_addr: &str, role: Role, action: &str, details: &str) {
+        let entry = AuditLogEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+            remote_addr: remote_addr.to_string(),
+            role,
+            action: action.to_string(),
+            details: details.to_string(),
+        };
+
+        log::info!(
+            self.logger,
+            "Management audit: {} as {:?} from {}: {}",
+            entry.action,
+            entry.role,
+            entry.remote_addr,
+            entry.details,
+        );
+
+        if let Some(file) = &self.file {
+            let mut line = serde_json::to_string(&entry).expect("failed serializing audit entry");
+            line.push('\n');
+            let mut file = file.lock().expect("lock poisoned");
+            if let Err(err) = file
+                .write_all(line.as_bytes())
+                .and_then(|_| file.sync_data())
+            {
+                log::error!(
+                    self.logger,
+                    "Failed writing to the audit log file: {:?}",
+                    err
+                );
+            }
+        }
+
+        let mut entries = self.entries.lock().expect("lock poisoned");
+        if entries.len() >= MAX_AUDIT_LOG_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// The most recent entries, oldest first.
+    pub fn entries(&self) -> Vec<AuditLogEntry> {
+        self.entries
+            .lock()
+            .expect("lock poisoned")
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod audit_log_tests {
+    use super::*;
+    use mc_common::logger::test_with_logger;
+    use tempdir::TempDir;
+
+    #[test_with_logger]
+    fn test_record(logger: Logger) {
+        let audit_log = AuditLog::new(logger);
+        assert_eq!(audit_log.entries(), vec![]);
+
+        audit_log.record("127.0.0.1:1234", Role::Admin, "set-rust-log", "info");
+        let entries = audit_log.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].remote_addr, "127.0.0.1:1234");
+        assert_eq!(entries[0].role, Role::Admin);
+        assert_eq!(entries[0].action, "set-rust-log");
+        assert_eq!(entries[0].details, "info");
+
+        // The log is bounded, dropping the oldest entries first.
+        for i in 0..MAX_AUDIT_LOG_ENTRIES {
+            audit_log.record("127.0.0.1:1234", Role::Admin, "evict-tx", &i.to_string());
+        }
+        let entries = audit_log.entries();
+        assert_eq!(entries.len(), MAX_AUDIT_LOG_ENTRIES);
+        assert_eq!(entries[0].details, "0");
+    }
+
+    #[test_with_logger]
+    fn test_record_to_file(logger: Logger) {
+        let temp_dir = TempDir::new("audit_log").unwrap();
+        let path = temp_dir.path().join("audit.log");
+
+        let audit_log = AuditLog::open(&path, logger.clone()).unwrap();
+        assert_eq!(audit_log.entries(), vec![]);
+        audit_log.record("127.0.0.1:1234", Role::Admin, "set-rust-log", "info");
+        audit_log.record("127.0.0.1:1234", Role::Admin, "evict-tx", "00");
+
+        // Entries survive a restart, and new entries are appended after them.
+        let audit_log = AuditLog::open(&path, logger).unwrap();
+        let entries = audit_log.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].action, "set-rust-log");
+        assert_eq!(entries[1].action, "evict-tx");
+
+        audit_log.record("127.0.0.1:1234", Role::Admin, "reload-network", "");
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 3);
+    }
+}
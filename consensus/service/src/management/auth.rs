@@ -0,0 +1,262 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Bearer token authentication for the management interface.
+
+use rouille::Response;
+use serde::{Deserialize, Serialize};
+
+/// What a management client is allowed to do.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// May use read-only endpoints.
+    Reader,
+
+    /// May use every endpoint, including those that change the node's state.
+    Admin,
+}
+
+/// Reasons a request may not use an endpoint.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AuthError {
+    /// The endpoint requires a role that no token was configured for.
+    Disabled,
+
+    /// The request did not carry a valid token.
+    Unauthenticated,
+
+    /// The request's token does not grant the required role.
+    Forbidden,
+}
+
+impl AuthError {
+    pub fn into_response(self) -> Response {
+        match self {
+            Self::Disabled => {
+                Response::text("This endpoint is disabled on this node").with_status_code(403)
+            }
+            Self::Unauthenticated => Response::text("Unauthorized")
+                .with_status_code(401)
+                .with_unique_header("WWW-Authenticate", "Bearer"),
+            Self::Forbidden => Response::text("Forbidden").with_status_code(403),
+        }
+    }
+}
+
+/// Maps bearer tokens to roles.
+///
+/// Read-only endpoints require the reader or admin token, unless unauthenticated reads are
+/// explicitly allowed, and are disabled when neither is configured. Endpoints that change the
+/// node's state always require the admin token, and are disabled when it is not configured.
+#[derive(Clone)]
+pub struct Authenticator {
+    reader_token: Option<String>,
+    admin_token: Option<String>,
+    allow_unauthenticated_reads: bool,
+}
+
+impl Authenticator {
+    /// Creates a new authenticator.
+    ///
+    /// # Arguments
+    /// * `reader_token` - Token granting the `Reader` role.
+    /// * `admin_token` - Token granting the `Admin` role.
+    /// * `allow_unauthenticated_reads` - Grants the `Reader` role to requests without a token.
+    pub fn new(
+        reader_token: Option<String>,
+        admin_token: Option<String>,
+        allow_unauthenticated_reads: bool,
+    ) -> Self {
+        Self {
+            reader_token,
+            admin_token,
+            allow_unauthenticated_reads,
+        }
+    }
+
+    /// The role granted by an `Authorization` header, if any.
+    pub fn authenticate(&self, authorization: Option<&str>) -> Option<Role> {
+        let token = authorization
+            .filter(|value| value.starts_with("Bearer "))
+            .map(|value| &value["Bearer ".len()..])?;
+
+        // Check every token, so that the response time does not reveal which one matched.
+        let is_admin = token_matches(&self.admin_token, token);
+        let is_reader = token_matches(&self.reader_token, token);
+        if is_admin {
+            Some(Role::Admin)
+        } else if is_reader {
+            Some(Role::Reader)
+        } else {
+            None
+        }
+    }
+
+    /// Checks that an `Authorization` header grants `required`, and returns the role the request
+    /// acts with.
+    pub fn authorize(
+        &self,
+        authorization: Option<&str>,
+        required: Role,
+    ) -> Result<Role, AuthError> {
+        let role = self.authenticate(authorization);
+        match required {
+            Role::Reader => {
+                if let Some(role) = role {
+                    return Ok(role);
+                }
+                if self.allow_unauthenticated_reads {
+                    return Ok(Role::Reader);
+                }
+                if self.reader_token.is_none() && self.admin_token.is_none() {
+                    return Err(AuthError::Disabled);
+                }
+                Err(AuthError::Unauthenticated)
+            }
+            Role::Admin => {
+                if self.admin_token.is_none() {
+                    return Err(AuthError::Disabled);
+                }
+                match role {
+                    Some(Role::Admin) => Ok(Role::Admin),
+                    Some(Role::Reader) => Err(AuthError::Forbidden),
+                    None => Err(AuthError::Unauthenticated),
+                }
+            }
+        }
+    }
+}
+
+fn token_matches(expected: &Option<String>, provided: &str) -> bool {
+    expected
+        .as_ref()
+        .map(|expected| constant_time_eq(expected.as_bytes(), provided.as_bytes()))
+        .unwrap_or(false)
+}
+
+/// Compares two byte strings in time that depends only on their lengths.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+        == 0
+}
+
+#[cfg(test)]
+mod auth_tests {
+    use super::*;
+
+    fn authenticator(reader_token: Option<&str>, admin_token: Option<&str>) -> Authenticator {
+        Authenticator::new(
+            reader_token.map(str::to_string),
+            admin_token.map(str::to_string),
+            false,
+        )
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"", b""));
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+    }
+
+    #[test]
+    fn test_authenticate() {
+        let auth = authenticator(Some("read"), Some("admin"));
+        assert_eq!(auth.authenticate(Some("Bearer admin")), Some(Role::Admin));
+        assert_eq!(auth.authenticate(Some("Bearer read")), Some(Role::Reader));
+        assert_eq!(auth.authenticate(Some("Bearer other")), None);
+        assert_eq!(auth.authenticate(Some("admin")), None);
+        assert_eq!(auth.authenticate(Some("Basic admin")), None);
+        assert_eq!(auth.authenticate(None), None);
+
+        // An empty token never matches an unconfigured role.
+        let auth = authenticator(None, None);
+        assert_eq!(auth.authenticate(Some("Bearer ")), None);
+    }
+
+    #[test]
+    fn test_authorize_without_tokens() {
+        // Every endpoint is disabled.
+        let auth = authenticator(None, None);
+        assert_eq!(auth.authorize(None, Role::Reader), Err(AuthError::Disabled));
+        assert_eq!(
+            auth.authorize(Some("Bearer anything"), Role::Reader),
+            Err(AuthError::Disabled)
+        );
+        assert_eq!(auth.authorize(None, Role::Admin), Err(AuthError::Disabled));
+        assert_eq!(
+            auth.authorize(Some("Bearer anything"), Role::Admin),
+            Err(AuthError::Disabled)
+        );
+    }
+
+    #[test]
+    fn test_authorize_with_tokens() {
+        let auth = authenticator(Some("read"), Some("admin"));
+
+        assert_eq!(
+            auth.authorize(None, Role::Reader),
+            Err(AuthError::Unauthenticated)
+        );
+        assert_eq!(
+            auth.authorize(Some("Bearer read"), Role::Reader),
+            Ok(Role::Reader)
+        );
+        assert_eq!(
+            auth.authorize(Some("Bearer admin"), Role::Reader),
+            Ok(Role::Admin)
+        );
+
+        assert_eq!(
+            auth.authorize(Some("Bearer wrong"), Role::Admin),
+            Err(AuthError::Unauthenticated)
+        );
+        assert_eq!(
+            auth.authorize(Some("Bearer read"), Role::Admin),
+            Err(AuthError::Forbidden)
+        );
+        assert_eq!(
+            auth.authorize(Some("Bearer admin"), Role::Admin),
+            Ok(Role::Admin)
+        );
+    }
+
+    #[test]
+    fn test_authorize_with_admin_token_only() {
+        // Configuring any token protects the read-only endpoints.
+        let auth = authenticator(None, Some("admin"));
+        assert_eq!(
+            auth.authorize(None, Role::Reader),
+            Err(AuthError::Unauthenticated)
+        );
+        assert_eq!(
+            auth.authorize(Some("Bearer admin"), Role::Reader),
+            Ok(Role::Admin)
+        );
+    }
+
+    #[test]
+    fn test_authorize_with_unauthenticated_reads() {
+        // Read-only endpoints are open, while mutating endpoints still require the admin token.
+        let auth = Authenticator::new(None, Some("admin".to_string()), true);
+        assert_eq!(auth.authorize(None, Role::Reader), Ok(Role::Reader));
+        assert_eq!(
+            auth.authorize(Some("Bearer admin"), Role::Reader),
+            Ok(Role::Admin)
+        );
+        assert_eq!(
+            auth.authorize(None, Role::Admin),
+            Err(AuthError::Unauthenticated)
+        );
+
+        let auth = Authenticator::new(None, None, true);
+        assert_eq!(auth.authorize(None, Role::Reader), Ok(Role::Reader));
+        assert_eq!(auth.authorize(None, Role::Admin), Err(AuthError::Disabled));
+    }
+}
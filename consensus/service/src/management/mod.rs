@@ -1,21 +1,38 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
-use crate::config::Config;
+//! The management interface: node information, metrics, and administration endpoints.
+//!
+//! Endpoints are protected by bearer tokens (see `auth`), and changes made through the
+//! interface are recorded in an audit log.
+
+mod audit_log;
+mod auth;
+
+use crate::{
+    config::Config,
+    network_config_reloader::ReloadNetworkConfigFn,
+    tx_manager::{EvacuationRecord, MempoolAdmin, PendingTxInfo, PendingTxStatus},
+};
+use audit_log::AuditLog;
+use auth::{Authenticator, Role};
 use handlebars::Handlebars;
 use lazy_static::lazy_static;
 use mc_common::logger::{log, o, Logger};
+use mc_transaction_core::tx::TxHash;
 use mc_util_build_info;
 use mc_util_metrics::OpMetrics;
 use prometheus::{self, Encoder};
 use rouille::{router, Request, Response, Server};
 use serde_json::json;
 use std::{
+    convert::TryFrom,
     env,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
     thread,
+    time::UNIX_EPOCH,
 };
 
 lazy_static! {
@@ -27,23 +44,62 @@ pub struct ManagementServer {
     stop_requested: Arc<AtomicBool>,
     thread_handle: Option<thread::JoinHandle<()>>,
     config: Config,
+    mempool: Arc<dyn MempoolAdmin>,
+    reload_network_fn: ReloadNetworkConfigFn,
+    authenticator: Authenticator,
+    audit_log: AuditLog,
     logger: Logger,
 }
 
 impl ManagementServer {
-    pub fn new(config: Config, logger: Logger) -> Self {
+    /// Creates a new management server.
+    ///
+    /// # Arguments
+    /// * `config` - The consensus service configuration.
+    /// * `mempool` - The transactions cache, inspected by the admin endpoints.
+    /// * `reload_network_fn` - Reloads the network configuration file.
+    /// * `logger` - Logger.
+    pub fn new(
+        config: Config,
+        mempool: Arc<dyn MempoolAdmin>,
+        reload_network_fn: ReloadNetworkConfigFn,
+        logger: Logger,
+    ) -> Self {
         let stop_requested = Arc::new(AtomicBool::new(false));
+        let authenticator = Authenticator::new(
+            config
+                .management_reader_token()
+                .unwrap_or_else(|err| panic!("{}", err)),
+            config
+                .management_admin_token()
+                .unwrap_or_else(|err| panic!("{}", err)),
+            config.management_allow_unauthenticated_reads,
+        );
+        let audit_log = match &config.management_audit_log_path {
+            Some(path) => {
+                AuditLog::open(path, logger.clone()).unwrap_or_else(|err| panic!("{}", err))
+            }
+            None => AuditLog::new(logger.clone()),
+        };
 
         Self {
             stop_requested,
             thread_handle: None,
             config,
+            mempool,
+            reload_network_fn,
+            authenticator,
+            audit_log,
             logger,
         }
     }
 
     pub fn start(&mut self) {
         let server_config = self.config.clone();
+        let server_mempool = self.mempool.clone();
+        let server_reload_network_fn = self.reload_network_fn.clone();
+        let server_authenticator = self.authenticator.clone();
+        let server_audit_log = self.audit_log.clone();
         let server_logger = self.logger.clone();
         let listen_addr =
             self.config.management_listen_addr.clone().expect(
@@ -71,12 +127,24 @@ impl ManagementServer {
             let req_method = request.method().to_string();
             let req_url = request.raw_url().to_string();
             let handler_config = server_config.clone();
+            let handler_mempool = server_mempool.clone();
+            let handler_reload_network_fn = server_reload_network_fn.clone();
+            let handler_authenticator = server_authenticator.clone();
+            let handler_audit_log = server_audit_log.clone();
             let handler_logger =
                 server_logger.new(o!("req_method" => req_method, "req_url" => req_url));
 
             rouille::log_custom(request, log_ok, log_err, || {
                 OP_COUNTER.inc("requests");
-                Self::handle_request(request, handler_config, handler_logger)
+                Self::handle_request(
+                    request,
+                    handler_config,
+                    handler_mempool,
+                    handler_reload_network_fn,
+                    handler_authenticator,
+                    handler_audit_log,
+                    handler_logger,
+                )
             })
         })
         .expect("failed creating management web server");
@@ -112,8 +180,17 @@ impl ManagementServer {
         }
     }
 
-    fn handle_request(request: &Request, config: Config, logger: Logger) -> Response {
+    fn handle_request(
+        request: &Request,
+        config: Config,
+        mempool: Arc<dyn MempoolAdmin>,
+        reload_network_fn: ReloadNetworkConfigFn,
+        authenticator: Authenticator,
+        audit_log: AuditLog,
+        logger: Logger,
+    ) -> Response {
         let reg = Handlebars::new();
+        let remote_addr = request.remote_addr().to_string();
 
         router!(request,
              (GET) (/) => {
@@ -126,74 +203,192 @@ impl ManagementServer {
                 ).expect("Could not render template");
                 Response::html(body)
             },
-            (GET) (/set-rust-log) => {
-                if let Some(val) = request.get_param("rust_log") {
+            (POST) (/set-rust-log) => {
+                Self::with_role(request, &authenticator, Role::Admin, |role| {
+                    let val = match request.get_param("rust_log") {
+                        Some(val) => val,
+                        None => return Response::text("Missing rust_log").with_status_code(400),
+                    };
+                    audit_log.record(&remote_addr, role, "set-rust-log", &val);
                     log::info!(logger, "Updating RUST_LOG to '{}'", val);
                     env::set_var("RUST_LOG", val);
                     mc_common::logger::recreate_app_logger();
-                }
-
-                Response::redirect_302("/")
+                    Response::text("OK")
+                })
             },
             (GET) (/info) => {
-                let build : serde_json::Value = {
-                    let mut buf = String::new();
-                    mc_util_build_info::write_report(&mut buf).unwrap();
-                    serde_json::from_str(&buf).expect("build_info wrote a bad json")
-                };
-                Self::json(&json!({
-                    "build": build,
-                    "rust_log": env::var("RUST_LOG").unwrap_or_else(|_| "".to_string()),
-                    "config": json!({
-                        "public_key": config.node_id().public_key,
-                        "peer_responder_id": config.peer_responder_id,
-                        "client_responder_id": config.client_responder_id,
-                        "message_pubkey": config.msg_signer_key.public_key(),
-                        "network": config.network_path,
-                        "ias_api_key": config.ias_api_key,
-                        "ias_spid": config.ias_spid,
-                        "peer_listen_uri": config.peer_listen_uri,
-                        "client_listen_uri": config.client_listen_uri,
-                        "management_listen_addr": config.management_listen_addr,
-                        "ledger_path": config.ledger_path,
-                        "scp_debug_dump": config.scp_debug_dump,
-                    }),
-                    "network": config.network(),
-                }))
+                Self::with_role(request, &authenticator, Role::Reader, |_role| {
+                    let build : serde_json::Value = {
+                        let mut buf = String::new();
+                        mc_util_build_info::write_report(&mut buf).unwrap();
+                        serde_json::from_str(&buf).expect("build_info wrote a bad json")
+                    };
+                    Self::json(&json!({
+                        "build": build,
+                        "rust_log": env::var("RUST_LOG").unwrap_or_else(|_| "".to_string()),
+                        "config": json!({
+                            "public_key": config.node_id().public_key,
+                            "peer_responder_id": config.peer_responder_id,
+                            "client_responder_id": config.client_responder_id,
+                            "message_pubkey": config.msg_signer_key.public_key(),
+                            "network": config.network_path,
+                            "ias_api_key": redact(&config.ias_api_key),
+                            "ias_spid": config.ias_spid,
+                            "peer_listen_uri": config.peer_listen_uri,
+                            "client_listen_uri": config.client_listen_uri,
+                            "management_listen_addr": config.management_listen_addr,
+                            "ledger_path": config.ledger_path,
+                            "scp_state_path": config.scp_state_path,
+                            "scp_debug_dump": config.scp_debug_dump,
+                        }),
+                        "network": config.load_network().map(|network| json!(network))
+                            .unwrap_or_else(|err| json!({ "error": err })),
+                    }))
+                })
             },
             (GET) (/metrics) => {
-                let metric_families = prometheus::gather();
-                let encoder = prometheus::TextEncoder::new();
-                let mut buffer = vec![];
-                encoder.encode(&metric_families, &mut buffer).unwrap();
-                Response::text(String::from_utf8(buffer).unwrap_or_else(|_| "from_utf8 failed".to_string()))
+                Self::with_metrics_access(request, &authenticator, config.management_metrics_require_token, || {
+                    let metric_families = prometheus::gather();
+                    let encoder = prometheus::TextEncoder::new();
+                    let mut buffer = vec![];
+                    encoder.encode(&metric_families, &mut buffer).unwrap();
+                    Response::text(String::from_utf8(buffer).unwrap_or_else(|_| "from_utf8 failed".to_string()))
+                })
             },
             (GET) (/metrics-json) => {
-                let metric_families = prometheus::gather();
-                let encoder = mc_util_metrics::MetricsJsonEncoder {};
-                let mut buffer = vec![];
-                encoder.encode(&metric_families, &mut buffer).unwrap();
-                Response::text(
-                    String::from_utf8(buffer).unwrap_or_else(|_| "from_utf8 failed".to_string())
-                ).with_unique_header("Content-Type", "application/json; charset=utf-8")
+                Self::with_metrics_access(request, &authenticator, config.management_metrics_require_token, || {
+                    let metric_families = prometheus::gather();
+                    let encoder = mc_util_metrics::MetricsJsonEncoder {};
+                    let mut buffer = vec![];
+                    encoder.encode(&metric_families, &mut buffer).unwrap();
+                    Response::text(
+                        String::from_utf8(buffer).unwrap_or_else(|_| "from_utf8 failed".to_string())
+                    ).with_unique_header("Content-Type", "application/json; charset=utf-8")
+                })
+            },
+            (GET) (/mempool) => {
+                Self::with_role(request, &authenticator, Role::Reader, |_role| {
+                    let fee_stats = mempool.fee_stats();
+                    let pending_txs: Vec<serde_json::Value> =
+                        mempool.pending_txs().iter().map(pending_tx_json).collect();
+                    Self::json(&json!({
+                        "fee_stats": json!({
+                            "num_txs": fee_stats.num_txs,
+                            "min_fee": fee_stats.min_fee,
+                            "median_fee": fee_stats.median_fee,
+                            "max_fee": fee_stats.max_fee,
+                            "competitive_fee": fee_stats.competitive_fee,
+                        }),
+                        "pending_txs": pending_txs,
+                    }))
+                })
+            },
+            (GET) (/mempool/evacuations) => {
+                Self::with_role(request, &authenticator, Role::Reader, |_role| {
+                    let evacuations: Vec<serde_json::Value> =
+                        mempool.evacuation_history().iter().map(evacuation_json).collect();
+                    Self::json(&json!({ "evacuations": evacuations }))
+                })
+            },
+            (GET) (/mempool/tx/{tx_hash: String}) => {
+                Self::with_role(request, &authenticator, Role::Reader, |_role| {
+                    let tx_hash = match parse_tx_hash(&tx_hash) {
+                        Some(tx_hash) => tx_hash,
+                        None => return Response::text("Invalid tx hash").with_status_code(400),
+                    };
+                    match mempool.pending_tx(&tx_hash) {
+                        Some(pending_tx) => Self::json(&pending_tx_json(&pending_tx)),
+                        None => Response::empty_404(),
+                    }
+                })
+            },
+            (POST) (/mempool/tx/{tx_hash: String}/evict) => {
+                Self::with_role(request, &authenticator, Role::Admin, |role| {
+                    let tx_hash = match parse_tx_hash(&tx_hash) {
+                        Some(tx_hash) => tx_hash,
+                        None => return Response::text("Invalid tx hash").with_status_code(400),
+                    };
+                    if mempool.evict_tx(&tx_hash) {
+                        audit_log.record(&remote_addr, role, "evict-tx", &hex::encode(tx_hash.0));
+                        Response::text("OK")
+                    } else {
+                        Response::empty_404()
+                    }
+                })
+            },
+            (POST) (/reload-network) => {
+                Self::with_role(request, &authenticator, Role::Admin, |role| {
+                    match reload_network_fn() {
+                        Ok(update) => {
+                            audit_log.record(&remote_addr, role, "reload-network", &format!("{:?}", update));
+                            Self::json(&json!(update))
+                        }
+                        Err(err) => {
+                            audit_log.record(&remote_addr, role, "reload-network", &format!("rejected: {}", err));
+                            Response::text(err.to_string()).with_status_code(400)
+                        }
+                    }
+                })
+            },
+            (GET) (/audit-log) => {
+                Self::with_role(request, &authenticator, Role::Admin, |_role| {
+                    Self::json(&json!({ "entries": audit_log.entries() }))
+                })
             },
 
             // TODO: Debug endpoints, remove those once no longer needed.
             (GET) (/debug/log-error) => {
-                log::error!(logger, "Test log message!");
-                Response::text("OK")
+                Self::with_role(request, &authenticator, Role::Admin, |_role| {
+                    log::error!(logger, "Test log message!");
+                    Response::text("OK")
+                })
             },
             (GET) (/debug/panic) => {
-                // Need to panic in a thread to avoid unreachable code error.
-                std::thread::spawn(|| {
-                    panic!("test panic!");
-                });
-                Response::text("OK")
+                Self::with_role(request, &authenticator, Role::Admin, |role| {
+                    audit_log.record(&remote_addr, role, "debug-panic", "");
+                    // Need to panic in a thread to avoid unreachable code error.
+                    std::thread::spawn(|| {
+                        panic!("test panic!");
+                    });
+                    Response::text("OK")
+                })
             },
             _ => Response::empty_404()
         )
     }
 
+    /// Responds with `handler` if the request's bearer token grants `required`, and with an
+    /// error otherwise. `handler` is given the role the request acts with.
+    fn with_role<F: FnOnce(Role) -> Response>(
+        request: &Request,
+        authenticator: &Authenticator,
+        required: Role,
+        handler: F,
+    ) -> Response {
+        match authenticator.authorize(request.header("Authorization"), required) {
+            Ok(role) => handler(role),
+            Err(err) => {
+                OP_COUNTER.inc("auth_failures");
+                err.into_response()
+            }
+        }
+    }
+
+    /// Responds with `handler` if the request may read metrics. Metrics can be scraped without a
+    /// token, unless `require_token` is set, in which case they require the `Reader` role.
+    fn with_metrics_access<F: FnOnce() -> Response>(
+        request: &Request,
+        authenticator: &Authenticator,
+        require_token: bool,
+        handler: F,
+    ) -> Response {
+        if require_token {
+            Self::with_role(request, authenticator, Role::Reader, |_role| handler())
+        } else {
+            handler()
+        }
+    }
+
     // Copied from rouille::Response::json, changed to return pretty JSON
     #[inline]
     fn json<T>(content: &T) -> Response
@@ -217,3 +412,71 @@ impl ManagementServer {
         self.inner.lock().expect("lock poisoned")
     }*/
 }
+
+/// Hides a secret configuration value, while showing whether it is set.
+fn redact(secret: &str) -> &'static str {
+    if secret.is_empty() {
+        ""
+    } else {
+        "[redacted]"
+    }
+}
+
+/// Parses a hex-encoded tx hash.
+fn parse_tx_hash(src: &str) -> Option<TxHash> {
+    let bytes = hex::decode(src).ok()?;
+    TxHash::try_from(&bytes[..]).ok()
+}
+
+fn pending_tx_json(pending_tx: &PendingTxInfo) -> serde_json::Value {
+    let (status, invalid_reason) = match &pending_tx.status {
+        PendingTxStatus::Nominatable => ("nominatable", None),
+        PendingTxStatus::Outbid => ("outbid", None),
+        PendingTxStatus::Invalid(err) => ("invalid", Some(err.to_string())),
+    };
+    json!({
+        "tx_hash": hex::encode(pending_tx.tx_hash.0),
+        "fee": pending_tx.fee,
+        "tombstone_block": pending_tx.tombstone_block,
+        "num_key_images": pending_tx.num_key_images,
+        "age_secs": pending_tx.age.as_secs(),
+        "status": status,
+        "invalid_reason": invalid_reason,
+    })
+}
+
+fn evacuation_json(record: &EvacuationRecord) -> serde_json::Value {
+    let tx_hashes: Vec<String> = record
+        .tx_hashes
+        .iter()
+        .map(|tx_hash| hex::encode(tx_hash.0))
+        .collect();
+    json!({
+        "block_index": record.block_index,
+        "evacuated_at": record
+            .evacuated_at
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0),
+        "tx_hashes": tx_hashes,
+    })
+}
+
+#[cfg(test)]
+mod management_tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(redact(""), "");
+        assert_eq!(redact("0123456789abcdef"), "[redacted]");
+    }
+
+    #[test]
+    fn test_parse_tx_hash() {
+        let tx_hash = TxHash::from([7u8; 32]);
+        assert_eq!(parse_tx_hash(&hex::encode(tx_hash.0)), Some(tx_hash));
+        assert_eq!(parse_tx_hash("0707"), None);
+        assert_eq!(parse_tx_hash("not hex"), None);
+    }
+}
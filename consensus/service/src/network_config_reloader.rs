@@ -0,0 +1,380 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Reloads the network configuration file while the node is running.
+//!
+//! Peer connections are reconciled with the new list of broadcast peers right away, while the new
+//! quorum set and transaction source URLs are handed to ByzantineLedger, which switches to them at
+//! the next slot boundary.
+
+use crate::{
+    byzantine_ledger::ByzantineLedger,
+    config::{Config, NetworkConfig},
+    peer_keepalive::PeerKeepalive,
+};
+use failure::Fail;
+use mc_common::{
+    logger::{log, Logger},
+    NodeID, ResponderId,
+};
+use mc_connection::{ConnectionChanges, ConnectionManager};
+use mc_consensus_enclave::ConsensusEnclaveProxy;
+use mc_peers::{PeerConnection, ThreadedBroadcaster};
+use serde::Serialize;
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// How often the watcher checks whether the network configuration file changed.
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Eq, Fail, PartialEq)]
+pub enum NetworkConfigReloadError {
+    #[fail(display = "Failed loading network configuration: {}", _0)]
+    Load(String),
+
+    #[fail(display = "Invalid quorum set: {}", _0)]
+    InvalidQuorumSet(String),
+
+    #[fail(display = "Network parameters cannot change without a restart")]
+    NetworkParametersChanged,
+
+    #[fail(display = "The block version schedule cannot change without a restart")]
+    BlockVersionScheduleChanged,
+}
+
+/// What a reload changed.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct NetworkConfigUpdate {
+    /// Broadcast peers that were added.
+    pub peers_added: Vec<ResponderId>,
+
+    /// Broadcast peers that were removed.
+    pub peers_removed: Vec<ResponderId>,
+
+    /// Broadcast peers whose URI changed.
+    pub peers_replaced: Vec<ResponderId>,
+
+    /// True if a new quorum set will be used starting at the next slot.
+    pub quorum_set_changed: bool,
+
+    /// True if new transaction source URLs will be used starting at the next slot.
+    pub tx_source_urls_changed: bool,
+}
+
+impl NetworkConfigUpdate {
+    /// Returns true if the reload did not change anything.
+    pub fn is_empty(&self) -> bool {
+        self.peers_added.is_empty()
+            && self.peers_removed.is_empty()
+            && self.peers_replaced.is_empty()
+            && !self.quorum_set_changed
+            && !self.tx_source_urls_changed
+    }
+}
+
+impl From<ConnectionChanges> for NetworkConfigUpdate {
+    fn from(src: ConnectionChanges) -> Self {
+        Self {
+            peers_added: src.added,
+            peers_removed: src.removed,
+            peers_replaced: src.replaced,
+            ..Default::default()
+        }
+    }
+}
+
+/// A callback that reloads the network configuration.
+pub type ReloadNetworkConfigFn =
+    Arc<dyn Fn() -> Result<NetworkConfigUpdate, NetworkConfigReloadError> + Sync + Send>;
+
+pub struct NetworkConfigReloader<E: ConsensusEnclaveProxy> {
+    config: Config,
+    local_node_id: NodeID,
+    enclave: E,
+    env: Arc<grpcio::Environment>,
+    peer_manager: ConnectionManager<PeerConnection<E>>,
+    broadcaster: Arc<Mutex<ThreadedBroadcaster>>,
+    peer_keepalive: Arc<Mutex<PeerKeepalive>>,
+    byzantine_ledger: Arc<Mutex<Option<ByzantineLedger>>>,
+
+    /// The network configuration currently in use. The lock also serializes reloads.
+    network: Mutex<NetworkConfig>,
+
+    logger: Logger,
+}
+
+impl<E: ConsensusEnclaveProxy> NetworkConfigReloader<E> {
+    /// Creates a new reloader.
+    ///
+    /// # Arguments
+    /// * `config` - Node configuration, pointing at the network configuration file.
+    /// * `network` - The network configuration the node was started with.
+    /// * `enclave` - Enclave used for connecting to new peers.
+    /// * `env` - gRPC environment used for connecting to new peers.
+    /// * `peer_manager` - Connections to broadcast peers.
+    /// * `broadcaster` - Broadcaster sending messages to broadcast peers.
+    /// * `peer_keepalive` - Keepalive pinging broadcast peers.
+    /// * `byzantine_ledger` - Consensus, once started.
+    /// * `logger` - Logger.
+    pub fn new(
+        config: Config,
+        network: NetworkConfig,
+        enclave: E,
+        env: Arc<grpcio::Environment>,
+        peer_manager: ConnectionManager<PeerConnection<E>>,
+        broadcaster: Arc<Mutex<ThreadedBroadcaster>>,
+        peer_keepalive: Arc<Mutex<PeerKeepalive>>,
+        byzantine_ledger: Arc<Mutex<Option<ByzantineLedger>>>,
+        logger: Logger,
+    ) -> Self {
+        Self {
+            local_node_id: config.node_id(),
+            config,
+            enclave,
+            env,
+            peer_manager,
+            broadcaster,
+            peer_keepalive,
+            byzantine_ledger,
+            network: Mutex::new(network),
+            logger,
+        }
+    }
+
+    /// Reloads the network configuration file and applies it.
+    ///
+    /// Nothing is changed if the new configuration is invalid.
+    pub fn reload(&self) -> Result<NetworkConfigUpdate, NetworkConfigReloadError> {
+        let new_network = self
+            .config
+            .load_network()
+            .map_err(NetworkConfigReloadError::Load)?;
+
+        let mut network = self.network.lock().expect("lock poisoned");
+        check_compatible(&network, &new_network)?;
+        if *network == new_network {
+            return Ok(NetworkConfigUpdate::default());
+        }
+
+        // Reconcile peer connections. Connections to peers whose URI did not change are kept.
+        let peers: Vec<PeerConnection<E>> = new_network
+            .broadcast_peers()
+            .into_iter()
+            .map(|peer_uri| {
+                PeerConnection::new(
+                    self.enclave.clone(),
+                    self.local_node_id.clone(),
+                    peer_uri,
+                    self.env.clone(),
+                    self.logger.clone(),
+                )
+            })
+            .collect();
+        let mut update = NetworkConfigUpdate::from(self.peer_manager.update(peers));
+        self.broadcaster
+            .lock()
+            .expect("lock poisoned")
+            .update_peers(&self.peer_manager);
+        self.peer_keepalive
+            .lock()
+            .expect("lock poisoned")
+            .set_peers(&self.peer_manager.responder_ids());
+
+        // Hand the quorum set and transaction sources over to consensus.
+        let quorum_set = new_network.quorum_set();
+        update.quorum_set_changed = quorum_set != network.quorum_set();
+        update.tx_source_urls_changed = new_network.tx_source_urls != network.tx_source_urls;
+        if update.quorum_set_changed || update.tx_source_urls_changed {
+            if let Some(byzantine_ledger) = &*self.byzantine_ledger.lock().expect("lock poisoned") {
+                byzantine_ledger
+                    .update_network_config(quorum_set, new_network.tx_source_urls.clone());
+            }
+        }
+
+        log::info!(self.logger, "Reloaded network configuration: {:?}", update);
+        *network = new_network;
+        Ok(update)
+    }
+}
+
+/// Checks that a new network configuration can be applied without restarting the node.
+fn check_compatible(
+    current: &NetworkConfig,
+    new: &NetworkConfig,
+) -> Result<(), NetworkConfigReloadError> {
+    new.check_quorum_set()
+        .map_err(NetworkConfigReloadError::InvalidQuorumSet)?;
+
+    // Both are loaded into the enclave when the node starts.
+    if new.network_parameters() != current.network_parameters() {
+        return Err(NetworkConfigReloadError::NetworkParametersChanged);
+    }
+    if new.block_version_schedule() != current.block_version_schedule() {
+        return Err(NetworkConfigReloadError::BlockVersionScheduleChanged);
+    }
+
+    Ok(())
+}
+
+/// Reloads the network configuration whenever the file is modified.
+pub struct NetworkConfigWatcher {
+    join_handle: Option<thread::JoinHandle<()>>,
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl NetworkConfigWatcher {
+    /// Starts watching the network configuration file.
+    ///
+    /// # Arguments
+    /// * `config` - Node configuration, pointing at the network configuration file.
+    /// * `reload_fn` - Called whenever the file is modified.
+    /// * `logger` - Logger.
+    pub fn start(config: &Config, reload_fn: ReloadNetworkConfigFn, logger: Logger) -> Self {
+        let network_path = config.network_path.clone();
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+
+        let join_handle = Some(
+            thread::Builder::new()
+                .name("NetworkConfigWatcher".into())
+                .spawn(move || {
+                    let modified_at = || -> Option<SystemTime> {
+                        fs::metadata(&network_path)
+                            .and_then(|metadata| metadata.modified())
+                            .ok()
+                    };
+                    let mut last_modified_at = modified_at();
+
+                    while !thread_stop_requested.load(Ordering::SeqCst) {
+                        thread::sleep(WATCH_INTERVAL);
+
+                        let cur_modified_at = modified_at();
+                        if cur_modified_at == last_modified_at {
+                            continue;
+                        }
+                        last_modified_at = cur_modified_at;
+
+                        log::info!(logger, "{:?} changed, reloading", network_path);
+                        if let Err(err) = reload_fn() {
+                            log::error!(
+                                logger,
+                                "Not applying network configuration {:?}: {}",
+                                network_path,
+                                err
+                            );
+                        }
+                    }
+                })
+                .expect("Failed spawning NetworkConfigWatcher thread"),
+        );
+
+        Self {
+            join_handle,
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.join_handle.take() {
+            thread
+                .join()
+                .expect("NetworkConfigWatcher thread join failed");
+        }
+    }
+}
+
+impl Drop for NetworkConfigWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod network_config_reloader_tests {
+    use super::*;
+    use mc_transaction_core::{BlockVersionActivation, BlockVersionSchedule, NetworkParameters};
+
+    fn network_config() -> NetworkConfig {
+        toml::from_str(
+            r#"
+            broadcast_peers = [
+                "insecure-mcp://0.0.0.0:8082?consensus-msg-key=MCowBQYDK2VwAyEA_ii3rCch5qhMbLZ2vVgpQr1iTrq1BBN2-i0mMPuAJhQ=",
+            ]
+            tx_source_urls = ["file:///tmp/dump"]
+            quorum_set = { threshold = 1, members = [
+                { type = "Node", args = "0.0.0.0:8082" },
+            ] }
+        "#,
+        )
+        .expect("failed parsing toml")
+    }
+
+    #[test]
+    fn test_check_compatible() {
+        let current = network_config();
+        assert_eq!(check_compatible(&current, &current), Ok(()));
+
+        // Peers and transaction sources may change.
+        let mut new = current.clone();
+        new.tx_source_urls = vec!["file:///tmp/other".to_string()];
+        assert_eq!(check_compatible(&current, &new), Ok(()));
+
+        // The quorum set must be valid.
+        let mut new = current.clone();
+        new.quorum_set.threshold = 2;
+        match check_compatible(&current, &new) {
+            Err(NetworkConfigReloadError::InvalidQuorumSet(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // Enclave parameters may not change.
+        let mut new = current.clone();
+        new.network_parameters = Some(NetworkParameters {
+            max_tombstone_blocks: 1,
+            ..Default::default()
+        });
+        assert_eq!(
+            check_compatible(&current, &new),
+            Err(NetworkConfigReloadError::NetworkParametersChanged)
+        );
+
+        // Setting the defaults explicitly is not a change.
+        let mut new = current.clone();
+        new.network_parameters = Some(NetworkParameters::default());
+        assert_eq!(check_compatible(&current, &new), Ok(()));
+
+        let mut new = current.clone();
+        new.block_version_schedule = Some(BlockVersionSchedule {
+            activations: vec![BlockVersionActivation {
+                version: 1,
+                block_index: 1000,
+            }],
+        });
+        assert_eq!(
+            check_compatible(&current, &new),
+            Err(NetworkConfigReloadError::BlockVersionScheduleChanged)
+        );
+    }
+
+    #[test]
+    fn test_update_is_empty() {
+        assert!(NetworkConfigUpdate::default().is_empty());
+        assert!(!NetworkConfigUpdate {
+            quorum_set_changed: true,
+            ..Default::default()
+        }
+        .is_empty());
+        assert!(!NetworkConfigUpdate::from(ConnectionChanges {
+            added: vec![ResponderId("0.0.0.0:8082".to_string())],
+            ..Default::default()
+        })
+        .is_empty());
+    }
+}
@@ -42,6 +42,11 @@ use std::{
 // implement the `fetch_latest_msg` RPC call.
 type FetchLatestMsgFn = Arc<dyn Fn() -> Option<mc_peers::ConsensusMsg> + Sync + Send>;
 
+// Callback method for checking whether a responder ID belongs to a peer we are connected to.
+// The set of peers may change while the service is running, when the network configuration is
+// reloaded.
+type IsKnownPeerFn = Arc<dyn Fn(&ResponderId) -> bool + Sync + Send>;
+
 #[derive(Clone)]
 pub struct PeerApiService<E: ConsensusEnclaveProxy, L: Ledger> {
     /// Enclave instance.
@@ -62,11 +67,11 @@ pub struct PeerApiService<E: ConsensusEnclaveProxy, L: Ledger> {
     /// Callback function for getting the latest SCP statement the local node has issued.
     fetch_latest_msg_fn: FetchLatestMsgFn,
 
-    /// List of recognized responder IDs to accept messages from.
+    /// Checks whether a responder ID is recognized, and messages from it should be accepted.
     /// We only want to accept messages from peers we can initiate outgoing requests to. That is
     /// necessary for resolving TxHashes into Txs. If we received a consensus message from a peer
     /// not on this list, we won't be able to reach out to it to ask for the transaction contents.
-    known_responder_ids: Vec<ResponderId>,
+    is_known_peer_fn: IsKnownPeerFn,
 
     /// Logger.
     logger: Logger,
@@ -80,7 +85,7 @@ impl<E: ConsensusEnclaveProxy, L: Ledger> PeerApiService<E, L> {
         ledger: L,
         tx_manager: TxManager<E, L>,
         fetch_latest_msg_fn: FetchLatestMsgFn,
-        known_responder_ids: Vec<ResponderId>,
+        is_known_peer_fn: IsKnownPeerFn,
         logger: Logger,
     ) -> Self {
         Self {
@@ -90,7 +95,7 @@ impl<E: ConsensusEnclaveProxy, L: Ledger> PeerApiService<E, L> {
             ledger,
             tx_manager,
             fetch_latest_msg_fn,
-            known_responder_ids,
+            is_known_peer_fn,
             logger,
         }
     }
@@ -255,7 +260,7 @@ impl<E: ConsensusEnclaveProxy, L: Ledger> ConsensusPeerApi for PeerApiService<E,
             };
 
             // See if we recognize this peer.
-            if !self.known_responder_ids.contains(&from_responder_id) {
+            if !(self.is_known_peer_fn)(&from_responder_id) {
                 let mut resp = ConsensusMsgResponse::new();
                 resp.set_result(ConsensusMsgResult::UnknownPeer);
                 send_result(ctx, sink, Ok(resp), &logger);
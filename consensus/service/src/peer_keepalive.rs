@@ -92,6 +92,23 @@ impl PeerKeepalive {
         responder_id_to_last_heard.insert(responder_id, Instant::now());
     }
 
+    /// Replaces the set of peers to keep alive. Newly added peers are assumed to have been heard
+    /// from just now.
+    pub fn set_peers(&self, responder_ids: &[ResponderId]) {
+        let mut responder_id_to_last_heard = self
+            .responder_id_to_last_heard
+            .lock()
+            .expect("mutex poisoned");
+        responder_id_to_last_heard.retain(|responder_id, _| responder_ids.contains(responder_id));
+
+        let now = Instant::now();
+        for responder_id in responder_ids {
+            responder_id_to_last_heard
+                .entry(responder_id.clone())
+                .or_insert(now);
+        }
+    }
+
     fn thread_entrypoint<CC: ConsensusConnection>(
         conn_manager: ConnectionManager<CC>,
         stop_requested: Arc<AtomicBool>,
@@ -15,18 +15,25 @@ use mc_consensus_enclave::{
 };
 use mc_ledger_db::{Error as LedgerDbError, Ledger};
 use mc_transaction_core::{
-    constants::MAX_TRANSACTIONS_PER_BLOCK,
     ring_signature::KeyImage,
     tx::{TxHash, TxOutMembershipProof},
     validation::{TransactionValidationError, TransactionValidationResult},
-    Block, BlockContents, BlockSignature,
+    Block, BlockContents, BlockSignature, BlockVersionSchedule, ConsensusValue, Feature,
+    NetworkParameters,
 };
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeSet, VecDeque},
     iter::FromIterator,
     sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
+/// Maximal number of `evacuate_expired` results remembered for inspection.
+pub const MAX_EVACUATION_HISTORY: usize = 100;
+
+/// How far ahead of this node's clock a nominated close time may be.
+pub const MAX_CLOSE_TIME_DRIFT: Duration = Duration::from_secs(30);
+
 #[derive(Clone, Debug, Fail)]
 pub enum TxManagerError {
     #[fail(display = "Enclave error: {}", _0)]
@@ -43,6 +50,12 @@ pub enum TxManagerError {
 
     #[fail(display = "Ledger error: {}", _0)]
     LedgerDb(LedgerDbError),
+
+    #[fail(display = "Invalid close time ({})", _0)]
+    InvalidCloseTime(u64),
+
+    #[fail(display = "Close times are not enabled for the next block")]
+    CloseTimeNotEnabled,
 }
 
 impl From<ConsensusEnclaveError> for TxManagerError {
@@ -69,10 +82,96 @@ impl From<LedgerDbError> for TxManagerError {
 
 pub type TxManagerResult<T> = Result<T, TxManagerError>;
 
+/// Statistics about the fees of cached transactions, which clients can use to choose a fee.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FeeStats {
+    /// Number of cached transactions.
+    pub num_txs: u64,
+
+    /// Lowest fee of a cached transaction.
+    pub min_fee: u64,
+
+    /// Median fee of cached transactions.
+    pub median_fee: u64,
+
+    /// Highest fee of a cached transaction.
+    pub max_fee: u64,
+
+    /// The lowest fee that would currently place a transaction among those chosen for the next
+    /// block. This is the base fee when all cached transactions fit in a single block.
+    pub competitive_fee: u64,
+}
+
+/// Why a cached transaction is, or is not, about to be externalized.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PendingTxStatus {
+    /// The transaction would be included in a block proposed now.
+    Nominatable,
+
+    /// The transaction is valid, but other transactions are preferred over it, either because
+    /// they pay higher fees or because they spend the same key images.
+    Outbid,
+
+    /// The transaction cannot currently be appended to the ledger.
+    Invalid(TransactionValidationError),
+}
+
+/// A cached transaction, as presented to node operators.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingTxInfo {
+    pub tx_hash: TxHash,
+
+    pub fee: u64,
+
+    pub tombstone_block: u64,
+
+    pub num_key_images: usize,
+
+    /// Time since the transaction entered the cache.
+    pub age: Duration,
+
+    pub status: PendingTxStatus,
+}
+
+/// Transactions removed from the cache by a single call to `evacuate_expired`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EvacuationRecord {
+    /// The block index passed to `evacuate_expired`.
+    pub block_index: u64,
+
+    /// When the transactions were removed.
+    pub evacuated_at: SystemTime,
+
+    /// Hashes of the removed transactions, in ascending order.
+    pub tx_hashes: Vec<TxHash>,
+}
+
+/// Operator access to the transactions cache. This is a trait so that the management server does
+/// not need to know the concrete `TxManager` type.
+pub trait MempoolAdmin: Send + Sync {
+    /// All cached transactions, in descending order of fee.
+    fn pending_txs(&self) -> Vec<PendingTxInfo>;
+
+    /// A single cached transaction, if present.
+    fn pending_tx(&self, tx_hash: &TxHash) -> Option<PendingTxInfo>;
+
+    /// Removes a transaction from the cache. Returns true if the transaction was present.
+    fn evict_tx(&self, tx_hash: &TxHash) -> bool;
+
+    /// Recent `evacuate_expired` results, oldest first.
+    fn evacuation_history(&self) -> Vec<EvacuationRecord>;
+
+    /// Statistics about the fees of cached transactions.
+    fn fee_stats(&self) -> FeeStats;
+}
+
 struct CacheEntry {
     encrypted_tx: WellFormedEncryptedTx,
 
     context: WellFormedTxContext,
+
+    /// When the transaction entered the cache.
+    received_at: Instant,
 }
 
 impl CacheEntry {
@@ -102,7 +201,7 @@ pub trait UntrustedInterfaces: Clone {
 
     /// Combines a set of "candidate values" into a "composite value". This assumes all values are well
     /// formed and safe to append to the ledger individually.
-    /// IT ALSO ASSUMES VALUES ARE SORTED.
+    /// The result must not depend on the order of `tx_contexts`.
     ///
     /// # Arguments
     /// * `tx_contexts` - "Candidate" transactions. Each is assumed to be individually valid.
@@ -132,22 +231,41 @@ pub struct TxManager<
     /// values.
     untrusted: UI,
 
+    /// Limits of the network this node participates in.
+    network_parameters: NetworkParameters,
+
+    /// Block versions scheduled by the network, which decide whether blocks record a close time.
+    block_version_schedule: BlockVersionSchedule,
+
     /// Logger.
     logger: Logger,
 
     /// Map of tx hashes to data we hold for each tx.
     cache: Arc<Mutex<HashMap<TxHash, CacheEntry>>>,
+
+    /// The most recent non-empty results of `evacuate_expired`, oldest first.
+    evacuation_history: Arc<Mutex<VecDeque<EvacuationRecord>>>,
 }
 
 impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E, L, UI> {
     /// Construct a new TxManager instance.
-    pub fn new(enclave: E, ledger: L, untrusted: UI, logger: Logger) -> Self {
+    pub fn new(
+        enclave: E,
+        ledger: L,
+        untrusted: UI,
+        network_parameters: NetworkParameters,
+        block_version_schedule: BlockVersionSchedule,
+        logger: Logger,
+    ) -> Self {
         Self {
             enclave,
             ledger,
             untrusted,
+            network_parameters,
+            block_version_schedule,
             logger,
             cache: Arc::new(Mutex::new(HashMap::default())),
+            evacuation_history: Arc::new(Mutex::new(VecDeque::new())),
         }
     }
 
@@ -200,9 +318,10 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
                 CacheEntry {
                     encrypted_tx: well_formed_encrypted_tx,
                     context: well_formed_tx_context.clone(),
+                    received_at: Instant::now(),
                 },
             );
-            counters::TX_CACHE_NUM_ENTRIES.set(cache.len() as i64);
+            update_cache_gauges(&cache);
         }
 
         // Success!
@@ -232,7 +351,22 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
             hashes_after_purge,
         );
 
-        counters::TX_CACHE_NUM_ENTRIES.set(cache.len() as i64);
+        update_cache_gauges(&cache);
+
+        if !purged_hashes.is_empty() {
+            let mut tx_hashes: Vec<TxHash> = purged_hashes.iter().cloned().collect();
+            tx_hashes.sort();
+
+            let mut history = self.evacuation_history.lock().expect("lock poisoned");
+            if history.len() >= MAX_EVACUATION_HISTORY {
+                history.pop_front();
+            }
+            history.push_back(EvacuationRecord {
+                block_index: cur_block,
+                evacuated_at: SystemTime::now(),
+                tx_hashes,
+            });
+        }
 
         purged_hashes
     }
@@ -270,6 +404,67 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
         }
     }
 
+    /// Validate a value nominated for the next block: a transaction, or a close time that does not
+    /// precede the last block's timestamp and is at most `MAX_CLOSE_TIME_DRIFT` ahead of this
+    /// node's clock. Close times are only valid once the next block records them.
+    pub fn validate_value(&self, value: &ConsensusValue) -> TxManagerResult<()> {
+        match value {
+            ConsensusValue::TxHash(tx_hash) => self.validate_tx_by_hash(tx_hash),
+            ConsensusValue::CloseTime(close_time) => {
+                if !self.is_close_time_enabled()? {
+                    return Err(TxManagerError::CloseTimeNotEnabled);
+                }
+                let num_blocks = self.ledger.num_blocks()?;
+                let last_block = self.ledger.get_block(num_blocks - 1)?;
+                if *close_time < last_block.timestamp
+                    || *close_time > seconds_since_epoch() + MAX_CLOSE_TIME_DRIFT.as_secs()
+                {
+                    return Err(TxManagerError::InvalidCloseTime(*close_time));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Combine values nominated for the next block: the transactions are combined by
+    /// `combine_txs_by_hash`, and only the latest close time is kept. A close time is never
+    /// combined on its own, since a block needs transactions.
+    pub fn combine_values(&self, values: BTreeSet<ConsensusValue>) -> BTreeSet<ConsensusValue> {
+        let close_time = values.iter().filter_map(ConsensusValue::close_time).max();
+        let tx_hashes = values.iter().filter_map(ConsensusValue::tx_hash).cloned();
+        let mut combined: BTreeSet<ConsensusValue> = self
+            .combine_txs_by_hash(BTreeSet::from_iter(tx_hashes))
+            .into_iter()
+            .map(ConsensusValue::TxHash)
+            .collect();
+        if !combined.is_empty() {
+            combined.extend(close_time.map(ConsensusValue::CloseTime));
+        }
+        combined
+    }
+
+    /// The close time this node nominates for the next block: the current time, or the last
+    /// block's timestamp if this node's clock is behind it. None if the next block does not
+    /// record a close time.
+    pub fn close_time(&self) -> TxManagerResult<Option<u64>> {
+        if !self.is_close_time_enabled()? {
+            return Ok(None);
+        }
+        let num_blocks = self.ledger.num_blocks()?;
+        let last_block = self.ledger.get_block(num_blocks - 1)?;
+        Ok(Some(std::cmp::max(
+            seconds_since_epoch(),
+            last_block.timestamp,
+        )))
+    }
+
+    /// True if the version scheduled for the next block enables `Feature::BlockTimestamp`, i.e.
+    /// if nodes agree on the next block's close time.
+    fn is_close_time_enabled(&self) -> TxManagerResult<bool> {
+        let num_blocks = self.ledger.num_blocks()?;
+        Ok(Feature::BlockTimestamp.is_enabled(self.block_version_schedule.version_at(num_blocks)))
+    }
+
     /// Combine a list of transactions by their hashes and return the list of hashes of
     /// the combined transaction set.
     /// This will silently ignore non-existent hashes. Our combine methods are allowed to filter
@@ -286,14 +481,21 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
             }
         }
 
-        self.untrusted
-            .combine(&tx_contexts, MAX_TRANSACTIONS_PER_BLOCK)
+        self.untrusted.combine(
+            &tx_contexts,
+            self.network_parameters.max_transactions_per_block,
+        )
     }
 
     /// A "shim" that converts the output of consensus into something that can be written to the ledger.
+    ///
+    /// # Arguments
+    /// * `tx_hashes` - The externalized transactions.
+    /// * `close_time` - The externalized close time, or None to use the parent's timestamp.
     pub fn tx_hashes_to_block(
         &self,
         tx_hashes: &[TxHash],
+        close_time: Option<u64>,
     ) -> TxManagerResult<(Block, BlockContents, BlockSignature)> {
         let cache = self.lock_cache();
 
@@ -313,9 +515,10 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
 
         let num_blocks = self.ledger.num_blocks()?;
         let parent_block = self.ledger.get_block(num_blocks - 1)?;
-        let (block, block_contents, signature) = self
-            .enclave
-            .form_block(&parent_block, &encrypted_txs_with_proofs)?;
+        let close_time = close_time.unwrap_or(parent_block.timestamp);
+        let (block, block_contents, signature) =
+            self.enclave
+                .form_block(&parent_block, &encrypted_txs_with_proofs, close_time)?;
 
         Ok((block, block_contents, signature))
     }
@@ -354,21 +557,225 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
         self.lock_cache().len()
     }
 
+    /// Statistics about the fees of cached transactions.
+    pub fn fee_stats(&self) -> FeeStats {
+        let mut fees: Vec<u64> = self
+            .lock_cache()
+            .values()
+            .map(|entry| entry.context().fee())
+            .collect();
+        fees.sort();
+
+        let base_fee = self.network_parameters.base_fee;
+        let max_transactions_per_block = self.network_parameters.max_transactions_per_block;
+        if fees.is_empty() {
+            return FeeStats {
+                competitive_fee: base_fee,
+                ..Default::default()
+            };
+        }
+
+        // Transactions are chosen in order of decreasing fee, so a transaction must outbid the
+        // lowest fee among the `max_transactions_per_block` highest fees.
+        let competitive_fee = if fees.len() < max_transactions_per_block {
+            base_fee
+        } else {
+            let lowest_included_fee = fees[fees.len() - max_transactions_per_block];
+            base_fee.max(lowest_included_fee.saturating_add(1))
+        };
+
+        FeeStats {
+            num_txs: fees.len() as u64,
+            min_fee: fees[0],
+            median_fee: fees[fees.len() / 2],
+            max_fee: fees[fees.len() - 1],
+            competitive_fee,
+        }
+    }
+
+    /// All cached transactions, in descending order of fee.
+    pub fn pending_txs(&self) -> Vec<PendingTxInfo> {
+        // Validation reads the ledger, so it is done on a snapshot rather than while holding the
+        // cache lock, which would stall `insert_proposed_tx` and consensus.
+        let now = Instant::now();
+        let snapshot: Vec<(WellFormedTxContext, Duration)> = self
+            .lock_cache()
+            .values()
+            .map(|entry| {
+                (
+                    entry.context().clone(),
+                    now.duration_since(entry.received_at),
+                )
+            })
+            .collect();
+
+        // Determine which of the valid transactions would be chosen for the next block.
+        let mut statuses: HashMap<TxHash, PendingTxStatus> = HashMap::default();
+        let mut valid_contexts = Vec::new();
+        for (context, _age) in snapshot.iter() {
+            match self.untrusted.is_valid(context) {
+                Ok(()) => valid_contexts.push(context),
+                Err(err) => {
+                    statuses.insert(*context.tx_hash(), PendingTxStatus::Invalid(err));
+                }
+            }
+        }
+        let selected = self.untrusted.combine(
+            &valid_contexts,
+            self.network_parameters.max_transactions_per_block,
+        );
+
+        let mut pending_txs: Vec<PendingTxInfo> = snapshot
+            .iter()
+            .map(|(context, age)| {
+                let tx_hash = context.tx_hash();
+                let status = statuses.remove(tx_hash).unwrap_or_else(|| {
+                    if selected.contains(tx_hash) {
+                        PendingTxStatus::Nominatable
+                    } else {
+                        PendingTxStatus::Outbid
+                    }
+                });
+                PendingTxInfo {
+                    tx_hash: *tx_hash,
+                    fee: context.fee(),
+                    tombstone_block: context.tombstone_block(),
+                    num_key_images: context.key_images().len(),
+                    age: *age,
+                    status,
+                }
+            })
+            .collect();
+
+        pending_txs.sort_by(|a, b| b.fee.cmp(&a.fee).then_with(|| a.tx_hash.cmp(&b.tx_hash)));
+        pending_txs
+    }
+
+    /// A single cached transaction, if present.
+    pub fn pending_tx(&self, tx_hash: &TxHash) -> Option<PendingTxInfo> {
+        self.pending_txs()
+            .into_iter()
+            .find(|pending_tx| pending_tx.tx_hash == *tx_hash)
+    }
+
+    /// Removes a transaction from the cache. Returns true if the transaction was present.
+    ///
+    /// Consensus drops pending values that are no longer in the cache, so an evicted transaction
+    /// will not be externalized unless it is submitted again.
+    pub fn evict_tx(&self, tx_hash: &TxHash) -> bool {
+        let mut cache = self.lock_cache();
+        let evicted = cache.remove(tx_hash).is_some();
+        if evicted {
+            log::info!(self.logger, "Evicted tx {} from cache", tx_hash);
+            update_cache_gauges(&cache);
+        }
+        evicted
+    }
+
+    /// Recent non-empty `evacuate_expired` results, oldest first.
+    pub fn evacuation_history(&self) -> Vec<EvacuationRecord> {
+        self.evacuation_history
+            .lock()
+            .expect("lock poisoned")
+            .iter()
+            .cloned()
+            .collect()
+    }
+
     fn lock_cache(&self) -> MutexGuard<HashMap<TxHash, CacheEntry>> {
         self.cache.lock().expect("lock poisoned")
     }
 }
 
+impl<E, L, UI> MempoolAdmin for TxManager<E, L, UI>
+where
+    E: ConsensusEnclaveProxy,
+    L: Ledger + Sync,
+    UI: UntrustedInterfaces + Send + Sync,
+{
+    fn pending_txs(&self) -> Vec<PendingTxInfo> {
+        TxManager::pending_txs(self)
+    }
+
+    fn pending_tx(&self, tx_hash: &TxHash) -> Option<PendingTxInfo> {
+        TxManager::pending_tx(self, tx_hash)
+    }
+
+    fn evict_tx(&self, tx_hash: &TxHash) -> bool {
+        TxManager::evict_tx(self, tx_hash)
+    }
+
+    fn evacuation_history(&self) -> Vec<EvacuationRecord> {
+        TxManager::evacuation_history(self)
+    }
+
+    fn fee_stats(&self) -> FeeStats {
+        TxManager::fee_stats(self)
+    }
+}
+
+/// Updates the cache size gauges, including the number of entries per age bucket.
+fn update_cache_gauges(cache: &HashMap<TxHash, CacheEntry>) {
+    let now = Instant::now();
+    let mut buckets = [0i64; 4];
+    for entry in cache.values() {
+        let age = now.duration_since(entry.received_at).as_secs();
+        let bucket = match age {
+            0..=9 => 0,
+            10..=59 => 1,
+            60..=599 => 2,
+            _ => 3,
+        };
+        buckets[bucket] += 1;
+    }
+
+    counters::TX_CACHE_NUM_ENTRIES.set(cache.len() as i64);
+    counters::TX_CACHE_NUM_ENTRIES_AGE_UNDER_10S.set(buckets[0]);
+    counters::TX_CACHE_NUM_ENTRIES_AGE_10S_TO_1M.set(buckets[1]);
+    counters::TX_CACHE_NUM_ENTRIES_AGE_1M_TO_10M.set(buckets[2]);
+    counters::TX_CACHE_NUM_ENTRIES_AGE_OVER_10M.set(buckets[3]);
+}
+
+/// This node's current time, in seconds since the Unix epoch. A clock set before the epoch is
+/// reported as the epoch.
+fn seconds_since_epoch() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::validators::DefaultTxManagerUntrustedInterfaces;
     use mc_common::logger::test_with_logger;
     use mc_consensus_enclave_mock::ConsensusServiceMockEnclave;
-    use mc_transaction_core::account_keys::AccountKey;
+    use mc_ledger_db::LedgerDB;
+    use mc_transaction_core::{account_keys::AccountKey, BlockVersionActivation};
     use mc_transaction_core_test_utils::{create_ledger, create_transaction, initialize_ledger};
     use rand::{rngs::StdRng, SeedableRng};
 
+    /// Inserts a transaction context directly into the cache, bypassing the well-formed check.
+    fn insert_cached_tx(
+        tx_manager: &TxManager<ConsensusServiceMockEnclave, LedgerDB>,
+        fee: u64,
+        tx_hash_byte: u8,
+        tombstone_block: u64,
+    ) -> TxHash {
+        let tx_hash = TxHash::from(&[tx_hash_byte; 32]);
+        let context = WellFormedTxContext::new(fee, tx_hash, tombstone_block, vec![], vec![]);
+        tx_manager.lock_cache().insert(
+            tx_hash,
+            CacheEntry {
+                encrypted_tx: Default::default(),
+                context,
+                received_at: Instant::now(),
+            },
+        );
+        tx_hash
+    }
+
     #[test_with_logger]
     fn test_hashes_to_block(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([77u8; 32]);
@@ -379,7 +786,9 @@ mod tests {
         let tx_manager = TxManager::new(
             ConsensusServiceMockEnclave::default(),
             ledger.clone(),
-            DefaultTxManagerUntrustedInterfaces::new(ledger.clone()),
+            DefaultTxManagerUntrustedInterfaces::new(ledger.clone(), NetworkParameters::default()),
+            NetworkParameters::default(),
+            BlockVersionSchedule::default(),
             logger.clone(),
         );
 
@@ -467,28 +876,28 @@ mod tests {
 
         // Attempting to assemble a block with a non-existent hash should fail
         assert!(tx_manager
-            .tx_hashes_to_block(&[hash_tx_two, hash_tx_three])
+            .tx_hashes_to_block(&[hash_tx_two, hash_tx_three], None)
             .is_err());
 
         // Attempting to assemble a block with a duplicate transaction should fail.
         // TODO: The logic for actually making sure of this lives inside the Enclave, so it cannot
         // currently be tested here.
         // assert!(tx_manager
-        //     .tx_hashes_to_block(&vec![hash_tx_zero, hash_tx_one, hash_tx_zero])
+        //     .tx_hashes_to_block(&vec![hash_tx_zero, hash_tx_one, hash_tx_zero], None)
         //     .is_err());
 
         // Attempting to assemble a block with a duplicate and a missing transaction should fail
         // TODO: The logic for actually making sure of this lives inside the Enclave, so it cannot
         // currently be tested here.
         // assert!(tx_manager
-        //     .tx_hashes_to_block(&vec![hash_tx_zero, hash_tx_zero, hash_tx_three])
+        //     .tx_hashes_to_block(&vec![hash_tx_zero, hash_tx_zero, hash_tx_three], None)
         //     .is_err());
 
         // Attempting to assemble a block without duplicates or missing transactions should
         // succeed.
         // TODO: Right now this relies on ConsensusServiceMockEnclave::form_block
         let (block, block_contents, _signature) = tx_manager
-            .tx_hashes_to_block(&[hash_tx_zero, hash_tx_one])
+            .tx_hashes_to_block(&[hash_tx_zero, hash_tx_one], None)
             .expect("failed assembling block");
         assert_eq!(
             client_tx_zero.prefix.outputs[0].public_key,
@@ -502,4 +911,235 @@ mod tests {
         // The ledger was previously initialized with 3 blocks.
         assert_eq!(block.index, 3);
     }
+
+    #[test_with_logger]
+    // Close times should only be nominated once the next block records them, be bounded by the
+    // last block and this node's clock, and combining values should keep the latest close time.
+    fn test_validate_and_combine_values(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([77u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let mut ledger = create_ledger();
+        initialize_ledger(&mut ledger, 3, &sender, &mut rng);
+        let new_tx_manager = |block_version_schedule| {
+            TxManager::new(
+                ConsensusServiceMockEnclave::default(),
+                ledger.clone(),
+                DefaultTxManagerUntrustedInterfaces::new(
+                    ledger.clone(),
+                    NetworkParameters::default(),
+                ),
+                NetworkParameters::default(),
+                block_version_schedule,
+                logger.clone(),
+            )
+        };
+
+        // Before block timestamps are enabled, no close time is nominated or accepted.
+        let tx_manager = new_tx_manager(BlockVersionSchedule::default());
+        assert_eq!(tx_manager.close_time().unwrap(), None);
+        match tx_manager.validate_value(&ConsensusValue::CloseTime(seconds_since_epoch())) {
+            Err(TxManagerError::CloseTimeNotEnabled) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        // Block timestamps are enabled from the next block, at index 3.
+        let tx_manager = new_tx_manager(BlockVersionSchedule {
+            activations: vec![BlockVersionActivation {
+                version: Feature::BlockTimestamp.activation_version(),
+                block_index: 3,
+            }],
+        });
+        let close_time = tx_manager.close_time().unwrap().unwrap();
+        assert!(close_time >= seconds_since_epoch() - 1);
+        assert!(tx_manager
+            .validate_value(&ConsensusValue::CloseTime(close_time))
+            .is_ok());
+        let too_late = close_time + MAX_CLOSE_TIME_DRIFT.as_secs() + 60;
+        match tx_manager.validate_value(&ConsensusValue::CloseTime(too_late)) {
+            Err(TxManagerError::InvalidCloseTime(time)) if time == too_late => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        let tx_hash_a = insert_cached_tx(&tx_manager, 10, 1, 100);
+        let tx_hash_b = insert_cached_tx(&tx_manager, 10, 2, 100);
+        let combined = tx_manager.combine_values(BTreeSet::from_iter(vec![
+            ConsensusValue::TxHash(tx_hash_a),
+            ConsensusValue::CloseTime(1_590_000_000),
+            ConsensusValue::TxHash(tx_hash_b),
+            ConsensusValue::CloseTime(1_590_000_100),
+        ]));
+        assert_eq!(
+            combined,
+            BTreeSet::from_iter(vec![
+                ConsensusValue::TxHash(tx_hash_a),
+                ConsensusValue::TxHash(tx_hash_b),
+                ConsensusValue::CloseTime(1_590_000_100),
+            ])
+        );
+
+        // A close time is not combined without transactions.
+        assert!(tx_manager
+            .combine_values(BTreeSet::from_iter(vec![ConsensusValue::CloseTime(
+                1_590_000_000
+            )]))
+            .is_empty());
+    }
+
+    #[test_with_logger]
+    fn test_fee_stats(logger: Logger) {
+        let ledger = create_ledger();
+        let network_parameters = NetworkParameters {
+            base_fee: 5,
+            max_transactions_per_block: 3,
+            ..Default::default()
+        };
+        let tx_manager = TxManager::new(
+            ConsensusServiceMockEnclave::default(),
+            ledger.clone(),
+            DefaultTxManagerUntrustedInterfaces::new(ledger, network_parameters),
+            network_parameters,
+            BlockVersionSchedule::default(),
+            logger,
+        );
+
+        // An empty cache only requires the base fee.
+        assert_eq!(
+            tx_manager.fee_stats(),
+            FeeStats {
+                competitive_fee: 5,
+                ..Default::default()
+            }
+        );
+
+        let insert = |fee: u64, tx_hash_byte: u8| {
+            insert_cached_tx(&tx_manager, fee, tx_hash_byte, 100);
+        };
+
+        // The cached transactions fit in a block.
+        insert(10, 1);
+        insert(30, 2);
+        assert_eq!(
+            tx_manager.fee_stats(),
+            FeeStats {
+                num_txs: 2,
+                min_fee: 10,
+                median_fee: 30,
+                max_fee: 30,
+                competitive_fee: 5,
+            }
+        );
+
+        // The cache holds more transactions than fit in a block.
+        insert(20, 3);
+        insert(40, 4);
+        insert(50, 5);
+        assert_eq!(
+            tx_manager.fee_stats(),
+            FeeStats {
+                num_txs: 5,
+                min_fee: 10,
+                median_fee: 30,
+                max_fee: 50,
+                competitive_fee: 31,
+            }
+        );
+    }
+
+    #[test_with_logger]
+    fn test_pending_txs_and_evict(logger: Logger) {
+        let ledger = create_ledger();
+        let network_parameters = NetworkParameters {
+            max_transactions_per_block: 2,
+            ..Default::default()
+        };
+        let tx_manager = TxManager::new(
+            ConsensusServiceMockEnclave::default(),
+            ledger.clone(),
+            DefaultTxManagerUntrustedInterfaces::new(ledger, network_parameters),
+            network_parameters,
+            BlockVersionSchedule::default(),
+            logger,
+        );
+        assert_eq!(tx_manager.pending_txs(), vec![]);
+
+        let low_fee = insert_cached_tx(&tx_manager, 10, 1, 50);
+        let high_fee = insert_cached_tx(&tx_manager, 30, 2, 50);
+        let mid_fee = insert_cached_tx(&tx_manager, 20, 3, 50);
+        // The ledger is empty, so a tombstone block of 0 has already been exceeded.
+        let expired = insert_cached_tx(&tx_manager, 40, 4, 0);
+
+        let statuses: Vec<(TxHash, PendingTxStatus)> = tx_manager
+            .pending_txs()
+            .into_iter()
+            .map(|pending_tx| (pending_tx.tx_hash, pending_tx.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (
+                    expired,
+                    PendingTxStatus::Invalid(TransactionValidationError::TombstoneBlockExceeded)
+                ),
+                (high_fee, PendingTxStatus::Nominatable),
+                (mid_fee, PendingTxStatus::Nominatable),
+                (low_fee, PendingTxStatus::Outbid),
+            ]
+        );
+
+        let pending_tx = tx_manager.pending_tx(&mid_fee).unwrap();
+        assert_eq!(pending_tx.fee, 20);
+        assert_eq!(pending_tx.tombstone_block, 50);
+        assert_eq!(pending_tx.num_key_images, 0);
+
+        // Evicting a transaction makes room for the next highest fee.
+        assert!(tx_manager.evict_tx(&high_fee));
+        assert!(!tx_manager.evict_tx(&high_fee));
+        assert_eq!(tx_manager.pending_tx(&high_fee), None);
+        assert_eq!(
+            tx_manager.pending_tx(&low_fee).unwrap().status,
+            PendingTxStatus::Nominatable
+        );
+        assert_eq!(tx_manager.num_entries(), 3);
+    }
+
+    #[test_with_logger]
+    fn test_evacuation_history(logger: Logger) {
+        let ledger = create_ledger();
+        let tx_manager = TxManager::new(
+            ConsensusServiceMockEnclave::default(),
+            ledger.clone(),
+            DefaultTxManagerUntrustedInterfaces::new(ledger, NetworkParameters::default()),
+            NetworkParameters::default(),
+            BlockVersionSchedule::default(),
+            logger,
+        );
+
+        let tx_hash_1 = insert_cached_tx(&tx_manager, 10, 1, 5);
+        let tx_hash_2 = insert_cached_tx(&tx_manager, 10, 2, 5);
+        let tx_hash_3 = insert_cached_tx(&tx_manager, 10, 3, 8);
+
+        // Evacuations that remove nothing are not recorded.
+        assert!(tx_manager.evacuate_expired(5).is_empty());
+        assert_eq!(tx_manager.evacuation_history(), vec![]);
+
+        tx_manager.evacuate_expired(6);
+        tx_manager.evacuate_expired(9);
+        let history = tx_manager.evacuation_history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].block_index, 6);
+        let mut expected = vec![tx_hash_1, tx_hash_2];
+        expected.sort();
+        assert_eq!(history[0].tx_hashes, expected);
+        assert_eq!(history[1].block_index, 9);
+        assert_eq!(history[1].tx_hashes, vec![tx_hash_3]);
+
+        // The history is bounded.
+        for i in 0..MAX_EVACUATION_HISTORY as u64 {
+            insert_cached_tx(&tx_manager, 10, 4, 10 + i);
+            tx_manager.evacuate_expired(11 + i);
+        }
+        let history = tx_manager.evacuation_history();
+        assert_eq!(history.len(), MAX_EVACUATION_HISTORY);
+        assert_eq!(history[0].block_index, 11);
+    }
 }
@@ -22,17 +22,22 @@ use mc_transaction_core::{
     ring_signature::KeyImage,
     tx::{TxHash, TxOutMembershipProof},
     validation::{validate_tombstone, TransactionValidationError, TransactionValidationResult},
+    NetworkParameters,
 };
 use std::collections::BTreeSet;
 
 #[derive(Clone)]
 pub struct DefaultTxManagerUntrustedInterfaces<L: Ledger> {
     ledger: L,
+    network_parameters: NetworkParameters,
 }
 
 impl<L: Ledger> DefaultTxManagerUntrustedInterfaces<L> {
-    pub fn new(ledger: L) -> Self {
-        Self { ledger }
+    pub fn new(ledger: L, network_parameters: NetworkParameters) -> Self {
+        Self {
+            ledger,
+            network_parameters,
+        }
     }
 }
 
@@ -84,7 +89,11 @@ impl<L: Ledger> TxManagerUntrustedInterfaces for DefaultTxManagerUntrustedInterf
             .num_blocks()
             .map_err(|e| TransactionValidationError::Ledger(e.to_string()))?;
 
-        validate_tombstone(current_block_index, context.tombstone_block())?;
+        validate_tombstone(
+            current_block_index,
+            context.tombstone_block(),
+            self.network_parameters.max_tombstone_blocks,
+        )?;
 
         // The `key_images` must not have already been spent.
         // TODO: this should use proofs of non-membership.
@@ -103,7 +112,11 @@ impl<L: Ledger> TxManagerUntrustedInterfaces for DefaultTxManagerUntrustedInterf
 
     /// Combines a set of "candidate values" into a "composite value". This assumes all values are well
     /// formed and safe to append to the ledger individually.
-    /// ASSUMES VALUES ARE SORTED.
+    ///
+    /// Transactions are admitted in order of decreasing fee, with ties broken by increasing tx
+    /// hash so that every node makes the same choice. A transaction that would spend a key image
+    /// already spent by an admitted transaction is omitted, so conflicts are resolved in favor of
+    /// the transaction with the higher fee.
     ///
     /// # Arguments
     /// * `tx_contexts` - "Candidate" transactions. Each is assumed to be individually valid.
@@ -115,11 +128,18 @@ impl<L: Ledger> TxManagerUntrustedInterfaces for DefaultTxManagerUntrustedInterf
         tx_contexts: &[&WellFormedTxContext],
         max_elements: usize,
     ) -> BTreeSet<TxHash> {
+        let mut candidates: Vec<&WellFormedTxContext> = tx_contexts.to_vec();
+        candidates.sort_by(|a, b| {
+            b.fee()
+                .cmp(&a.fee())
+                .then_with(|| a.tx_hash().cmp(b.tx_hash()))
+        });
+
         // Allow transactions that do not introduce key image double-spends.
         let mut allowed_hashes = BTreeSet::new();
         let mut used_key_images = HashSet::default();
 
-        for tx_context in tx_contexts.iter() {
+        for tx_context in candidates {
             if allowed_hashes.len() >= max_elements {
                 // Enforce maximum size.
                 break;
@@ -162,7 +182,8 @@ pub mod well_formed_tests {
     fn is_well_formed(tx: &Tx, ledger: &LedgerDB) -> TransactionValidationResult<()> {
         let mut rng = Hc128Rng::from_seed([77u8; 32]);
 
-        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger.clone());
+        let untrusted =
+            DefaultTxManagerUntrustedInterfaces::new(ledger.clone(), NetworkParameters::default());
 
         let key_images: Vec<KeyImage> = tx.key_images();
         let membership_proof_highest_indices = tx.get_membership_proof_highest_indices();
@@ -174,6 +195,7 @@ pub mod well_formed_tests {
             &tx,
             cur_block_index,
             &membership_proofs,
+            &NetworkParameters::default(),
             &mut rng,
         )
     }
@@ -495,7 +517,8 @@ mod is_valid_tests {
     use rand_hc::Hc128Rng;
 
     fn is_valid(tx: &Tx, ledger: &LedgerDB) -> TransactionValidationResult<()> {
-        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger.clone());
+        let untrusted =
+            DefaultTxManagerUntrustedInterfaces::new(ledger.clone(), NetworkParameters::default());
         untrusted.is_valid(&WellFormedTxContext::from(tx))
     }
 
@@ -555,6 +578,44 @@ mod is_valid_tests {
         );
     }
 
+    #[test]
+    /// `is_valid` should enforce the configured `max_tombstone_blocks`.
+    fn is_valid_uses_network_parameters() {
+        let mut rng = Hc128Rng::from_seed([79u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let recipient = AccountKey::random(&mut rng);
+
+        let mut ledger = create_ledger();
+        let n_blocks = 3;
+        initialize_ledger(&mut ledger, n_blocks, &sender, &mut rng);
+
+        // Choose a TxOut to spend. Only the output of the last block is unspent.
+        let block_contents = ledger.get_block_contents(n_blocks - 1).unwrap();
+        let tx_out = block_contents.outputs[0].clone();
+
+        let tx = create_transaction(
+            &mut ledger,
+            &tx_out,
+            &sender,
+            &recipient.default_subaddress(),
+            n_blocks + 5,
+            &mut rng,
+        );
+        assert_eq!(Ok(()), is_valid(&tx, &ledger));
+
+        let network_parameters = NetworkParameters {
+            max_tombstone_blocks: 2,
+            ..Default::default()
+        };
+        let untrusted =
+            DefaultTxManagerUntrustedInterfaces::new(ledger.clone(), network_parameters);
+        assert_eq!(
+            Err(TransactionValidationError::TombstoneBlockTooFar),
+            untrusted.is_valid(&WellFormedTxContext::from(&tx))
+        );
+    }
+
     #[test]
     /// `is_valid` should reject a transaction with an already spent key image .
     fn is_valid_rejects_spent_keyimage() {
@@ -607,7 +668,8 @@ mod combine_tests {
 
     fn combine(tx_contexts: Vec<WellFormedTxContext>, max_elements: usize) -> BTreeSet<TxHash> {
         let ledger = get_mock_ledger(10);
-        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger);
+        let untrusted =
+            DefaultTxManagerUntrustedInterfaces::new(ledger, NetworkParameters::default());
         let ref_tx_contexts: Vec<&WellFormedTxContext> = tx_contexts.iter().collect();
         untrusted.combine(&ref_tx_contexts[..], max_elements)
     }
@@ -918,4 +980,70 @@ mod combine_tests {
         assert_eq!(combined_transactions.len(), 2);
         assert!(combined_transactions.contains(third_client_tx.tx_hash()));
     }
+
+    fn tx_context(fee: u64, tx_hash_byte: u8, key_images: &[u64]) -> WellFormedTxContext {
+        WellFormedTxContext::new(
+            fee,
+            TxHash::from(&[tx_hash_byte; 32]),
+            100,
+            key_images.iter().cloned().map(KeyImage::from).collect(),
+            vec![],
+        )
+    }
+
+    #[test]
+    // `combine` should prefer transactions with higher fees.
+    fn combine_prefers_higher_fees() {
+        let transaction_set = vec![
+            tx_context(10, 1, &[1]),
+            tx_context(50, 2, &[2]),
+            tx_context(20, 3, &[3]),
+            tx_context(40, 4, &[4]),
+            tx_context(30, 5, &[5]),
+        ];
+
+        let combined_transactions = combine(transaction_set, 3);
+        let expected: BTreeSet<TxHash> = [2u8, 4, 5]
+            .iter()
+            .map(|byte| TxHash::from(&[*byte; 32]))
+            .collect();
+        assert_eq!(combined_transactions, expected);
+    }
+
+    #[test]
+    // Transactions with equal fees should be chosen by tx hash, regardless of input order.
+    fn combine_breaks_ties_by_tx_hash() {
+        let transaction_set = vec![
+            tx_context(10, 9, &[9]),
+            tx_context(10, 3, &[3]),
+            tx_context(10, 7, &[7]),
+            tx_context(10, 1, &[1]),
+        ];
+        let mut reversed_set = transaction_set.clone();
+        reversed_set.reverse();
+
+        let combined_transactions = combine(transaction_set, 2);
+        let expected: BTreeSet<TxHash> = [1u8, 3]
+            .iter()
+            .map(|byte| TxHash::from(&[*byte; 32]))
+            .collect();
+        assert_eq!(combined_transactions, expected);
+        assert_eq!(combine(reversed_set, 2), expected);
+    }
+
+    #[test]
+    // When two transactions spend the same key image, the one with the higher fee should win.
+    fn combine_resolves_key_image_conflicts_by_fee() {
+        let low_fee_tx = tx_context(10, 1, &[1, 2]);
+        let high_fee_tx = tx_context(20, 2, &[2, 3]);
+        let unrelated_tx = tx_context(5, 3, &[4]);
+
+        let combined_transactions = combine(
+            vec![low_fee_tx, high_fee_tx.clone(), unrelated_tx.clone()],
+            10,
+        );
+        assert_eq!(combined_transactions.len(), 2);
+        assert!(combined_transactions.contains(high_fee_tx.tx_hash()));
+        assert!(combined_transactions.contains(unrelated_tx.tx_hash()));
+    }
 }
@@ -0,0 +1,43 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+use failure::Fail;
+use retry::Error as RetryError;
+
+#[derive(Debug, Fail)]
+pub enum BlockStorageError {
+    #[fail(display = "Not found: {}", _0)]
+    NotFound(String),
+
+    #[fail(display = "Invalid path: {}", _0)]
+    InvalidPath(String),
+
+    #[fail(display = "IO error on {}: {:?}", _0, _1)]
+    IO(String, std::io::Error),
+
+    #[fail(display = "HTTP error on {}: {:?}", _0, _1)]
+    Http(String, reqwest::Error),
+
+    #[fail(display = "HTTP status {} on {}", _1, _0)]
+    HttpStatus(String, u16),
+
+    #[fail(display = "S3 error on {}: {}", _0, _1)]
+    S3(String, String),
+
+    #[fail(display = "Read back {} does not match what was written", _0)]
+    VerificationFailed(String),
+
+    #[fail(display = "Listing is not supported: {}", _0)]
+    ListingUnsupported(String),
+
+    #[fail(display = "Retry error: {}", _0)]
+    RetryInternal(String),
+}
+
+impl From<RetryError<Self>> for BlockStorageError {
+    fn from(src: RetryError<Self>) -> Self {
+        match src {
+            RetryError::Operation { error, .. } => error,
+            RetryError::Internal(s) => BlockStorageError::RetryInternal(s),
+        }
+    }
+}
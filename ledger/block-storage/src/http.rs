@@ -0,0 +1,79 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Block storage behind a plain HTTP server, read with GET and written with PUT.
+
+use crate::{BlockStorage, BlockStorageError};
+use reqwest::{blocking::Client, StatusCode};
+use url::Url;
+
+pub struct HttpStorage {
+    /// URL of the directory holding the objects. Ends with a '/'.
+    base_url: Url,
+
+    client: Client,
+}
+
+impl HttpStorage {
+    /// Create a new HttpStorage.
+    ///
+    /// # Arguments
+    /// * `base_url` - URL of the directory holding the objects.
+    /// * `client` - HTTP client.
+    pub fn new(mut base_url: Url, client: Client) -> Self {
+        if !base_url.path().ends_with('/') {
+            let path = format!("{}/", base_url.path());
+            base_url.set_path(&path);
+        }
+        Self { base_url, client }
+    }
+
+    fn url(&self, path: &str) -> Result<Url, BlockStorageError> {
+        self.base_url
+            .join(path)
+            .map_err(|_| BlockStorageError::InvalidPath(path.to_owned()))
+    }
+}
+
+impl BlockStorage for HttpStorage {
+    fn get(&self, path: &str) -> Result<Vec<u8>, BlockStorageError> {
+        let url = self.url(path)?;
+        let mut response = self
+            .client
+            .get(url.clone())
+            .send()
+            .map_err(|err| BlockStorageError::Http(url.to_string(), err))?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Err(BlockStorageError::NotFound(url.to_string())),
+            status if !status.is_success() => Err(BlockStorageError::HttpStatus(
+                url.to_string(),
+                status.as_u16(),
+            )),
+            _ => {
+                let mut bytes = Vec::new();
+                response
+                    .copy_to(&mut bytes)
+                    .map_err(|err| BlockStorageError::Http(url.to_string(), err))?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    fn put(&self, path: &str, bytes: &[u8]) -> Result<(), BlockStorageError> {
+        let url = self.url(path)?;
+        let response = self
+            .client
+            .put(url.clone())
+            .body(bytes.to_vec())
+            .send()
+            .map_err(|err| BlockStorageError::Http(url.to_string(), err))?;
+
+        if !response.status().is_success() {
+            return Err(BlockStorageError::HttpStatus(
+                url.to_string(),
+                response.status().as_u16(),
+            ));
+        }
+        Ok(())
+    }
+}
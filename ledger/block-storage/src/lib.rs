@@ -0,0 +1,102 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Storage for block archives. Ledger distribution writes blocks to it, and ledger sync reads them
+//! back.
+//!
+//! Objects are addressed by a relative path, such as the one returned by
+//! `mc_api::conversions::block_num_to_s3block_path`. Backends are provided for the local
+//! filesystem, plain HTTP GET/PUT, and S3-compatible object stores.
+
+mod error;
+mod http;
+mod local;
+mod s3;
+mod uri;
+
+pub use crate::{
+    error::BlockStorageError,
+    http::HttpStorage,
+    local::LocalStorage,
+    s3::S3Storage,
+    uri::{StorageLocation, StorageUri, UriParseError},
+};
+
+use blake2::{Blake2b, Digest};
+use mc_common::logger::{log, Logger};
+
+/// An object listed by `BlockStorage::list`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StoredObject {
+    /// Path of the object, relative to the storage root.
+    pub path: String,
+
+    /// Size of the object, in bytes.
+    pub size: u64,
+}
+
+/// A place where block archives are written and read.
+pub trait BlockStorage: Send + Sync {
+    /// Reads the object at `path`.
+    ///
+    /// Returns `BlockStorageError::NotFound` if there is no such object.
+    fn get(&self, path: &str) -> Result<Vec<u8>, BlockStorageError>;
+
+    /// Writes `bytes` as the object at `path`, replacing any previous object.
+    fn put(&self, path: &str, bytes: &[u8]) -> Result<(), BlockStorageError>;
+
+    /// Lists the objects whose path starts with `prefix`, in no particular order.
+    ///
+    /// Returns `BlockStorageError::ListingUnsupported` if the backend cannot list its objects.
+    fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, BlockStorageError> {
+        Err(BlockStorageError::ListingUnsupported(prefix.to_owned()))
+    }
+}
+
+/// Writes an object, reads it back, and checks that the hash of what was read matches the hash of
+/// what was written. Failed attempts are retried with an exponential backoff.
+///
+/// # Arguments
+/// * `storage` - Where to write.
+/// * `path` - Path of the object.
+/// * `bytes` - Contents of the object.
+/// * `max_attempts` - Maximal number of attempts.
+/// * `logger` - Logger.
+pub fn put_verified(
+    storage: &dyn BlockStorage,
+    path: &str,
+    bytes: &[u8],
+    max_attempts: usize,
+    logger: &Logger,
+) -> Result<(), BlockStorageError> {
+    let expected_hash = Blake2b::digest(bytes);
+
+    retry::retry(
+        retry::delay::Exponential::from_millis(10)
+            .map(retry::delay::jitter)
+            .take(max_attempts.saturating_sub(1)),
+        || {
+            put_and_read_back(storage, path, bytes)
+                .and_then(|read_back| {
+                    if Blake2b::digest(&read_back) == expected_hash {
+                        Ok(())
+                    } else {
+                        Err(BlockStorageError::VerificationFailed(path.to_owned()))
+                    }
+                })
+                .map_err(|err| {
+                    log::warn!(logger, "Failed writing {}: {}", path, err);
+                    err
+                })
+        },
+    )
+    .map_err(BlockStorageError::from)
+}
+
+fn put_and_read_back(
+    storage: &dyn BlockStorage,
+    path: &str,
+    bytes: &[u8],
+) -> Result<Vec<u8>, BlockStorageError> {
+    storage.put(path, bytes)?;
+    storage.get(path)
+}
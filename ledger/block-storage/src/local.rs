@@ -0,0 +1,97 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Block storage in a local directory.
+
+use crate::{BlockStorage, BlockStorageError, StoredObject};
+use std::{
+    ffi::OsStr,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+pub struct LocalStorage {
+    /// Directory holding the objects.
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    /// Create a new LocalStorage.
+    ///
+    /// # Arguments
+    /// * `root` - Directory holding the objects. Created on the first write if missing.
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+}
+
+impl BlockStorage for LocalStorage {
+    fn get(&self, path: &str) -> Result<Vec<u8>, BlockStorageError> {
+        let full_path = self.root.join(path);
+        fs::read(&full_path).map_err(|err| {
+            let location = full_path.display().to_string();
+            if err.kind() == ErrorKind::NotFound {
+                BlockStorageError::NotFound(location)
+            } else {
+                BlockStorageError::IO(location, err)
+            }
+        })
+    }
+
+    fn put(&self, path: &str, bytes: &[u8]) -> Result<(), BlockStorageError> {
+        let full_path = self.root.join(path);
+        let location = full_path.display().to_string();
+        let dir = full_path
+            .parent()
+            .ok_or_else(|| BlockStorageError::InvalidPath(location.clone()))?;
+        fs::create_dir_all(dir).map_err(|err| BlockStorageError::IO(location.clone(), err))?;
+
+        // Write to a temporary file first, so that readers never see a partially written object.
+        let mut tmp_path = full_path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, bytes).map_err(|err| BlockStorageError::IO(location.clone(), err))?;
+        fs::rename(&tmp_path, &full_path).map_err(|err| BlockStorageError::IO(location, err))
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, BlockStorageError> {
+        let io_error = |path: &Path, err| BlockStorageError::IO(path.display().to_string(), err);
+
+        let mut objects = Vec::new();
+        let mut dirs = vec![self.root.clone()];
+        while let Some(dir) = dirs.pop() {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(io_error(&dir, err)),
+            };
+            for entry in entries {
+                let entry = entry.map_err(|err| io_error(&dir, err))?;
+                let full_path = entry.path();
+                let metadata = entry.metadata().map_err(|err| io_error(&full_path, err))?;
+                if metadata.is_dir() {
+                    dirs.push(full_path);
+                    continue;
+                }
+                // Objects still being written by `put` are not listed.
+                if full_path.extension() == Some(OsStr::new("tmp")) {
+                    continue;
+                }
+
+                let path = full_path
+                    .strip_prefix(&self.root)
+                    .map_err(|_| BlockStorageError::InvalidPath(full_path.display().to_string()))?
+                    .iter()
+                    .map(|component| component.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                if path.starts_with(prefix) {
+                    objects.push(StoredObject {
+                        path,
+                        size: metadata.len(),
+                    });
+                }
+            }
+        }
+        Ok(objects)
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Block storage in an S3 bucket, or in any object store with an S3-compatible API.
+
+use crate::{BlockStorage, BlockStorageError, StoredObject};
+use rusoto_core::{Region, RusotoError};
+use rusoto_s3::{
+    GetObjectError, GetObjectRequest, ListObjectsV2Request, PutObjectRequest, S3Client, S3,
+};
+use std::io::Read;
+
+pub struct S3Storage {
+    client: S3Client,
+
+    /// Bucket holding the objects.
+    bucket: String,
+
+    /// Prefix of the keys of the objects, without leading or trailing '/'. May be empty.
+    prefix: String,
+}
+
+impl S3Storage {
+    /// Create a new S3Storage, with credentials taken from the environment.
+    ///
+    /// # Arguments
+    /// * `bucket` - Bucket holding the objects.
+    /// * `prefix` - Prefix of the keys of the objects.
+    /// * `region` - Region of the bucket. Use `Region::Custom` for other S3-compatible endpoints.
+    pub fn new(bucket: &str, prefix: &str, region: Region) -> Self {
+        Self::new_with_client(S3Client::new(region), bucket, prefix)
+    }
+
+    /// Create a new S3Storage with a given client.
+    pub fn new_with_client(client: S3Client, bucket: &str, prefix: &str) -> Self {
+        Self {
+            client,
+            bucket: bucket.to_owned(),
+            prefix: prefix.trim_matches('/').to_owned(),
+        }
+    }
+
+    fn key(&self, path: &str) -> String {
+        if self.prefix.is_empty() {
+            path.to_owned()
+        } else {
+            format!("{}/{}", self.prefix, path)
+        }
+    }
+
+    /// The path of the object with the given key, if the key has our prefix.
+    fn path(&self, key: &str) -> Option<String> {
+        if self.prefix.is_empty() {
+            Some(key.to_owned())
+        } else if key.starts_with(&self.prefix) && key[self.prefix.len()..].starts_with('/') {
+            Some(key[self.prefix.len() + 1..].to_owned())
+        } else {
+            None
+        }
+    }
+
+    fn location(&self, key: &str) -> String {
+        format!("s3://{}/{}", self.bucket, key)
+    }
+}
+
+impl BlockStorage for S3Storage {
+    fn get(&self, path: &str) -> Result<Vec<u8>, BlockStorageError> {
+        let key = self.key(path);
+        let location = self.location(&key);
+        let request = GetObjectRequest {
+            bucket: self.bucket.clone(),
+            key,
+            ..Default::default()
+        };
+
+        match self.client.get_object(request).sync() {
+            Ok(output) => {
+                let body = output.body.ok_or_else(|| {
+                    BlockStorageError::S3(location.clone(), "missing body".to_owned())
+                })?;
+                let mut bytes = Vec::new();
+                body.into_blocking_read()
+                    .read_to_end(&mut bytes)
+                    .map_err(|err| BlockStorageError::IO(location, err))?;
+                Ok(bytes)
+            }
+            Err(RusotoError::Service(GetObjectError::NoSuchKey(_))) => {
+                Err(BlockStorageError::NotFound(location))
+            }
+            // Some S3-compatible stores answer a missing key with a bare 404.
+            Err(RusotoError::Unknown(ref response)) if response.status.as_u16() == 404 => {
+                Err(BlockStorageError::NotFound(location))
+            }
+            Err(err) => Err(BlockStorageError::S3(location, format!("{:?}", err))),
+        }
+    }
+
+    fn put(&self, path: &str, bytes: &[u8]) -> Result<(), BlockStorageError> {
+        let key = self.key(path);
+        let location = self.location(&key);
+        let request = PutObjectRequest {
+            bucket: self.bucket.clone(),
+            key,
+            body: Some(bytes.to_vec().into()),
+            acl: Some("public-read".to_string()),
+            ..Default::default()
+        };
+
+        self.client
+            .put_object(request)
+            .sync()
+            .map(|_| ())
+            .map_err(|err| BlockStorageError::S3(location, format!("{:?}", err)))
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, BlockStorageError> {
+        let key_prefix = self.key(prefix);
+        let location = self.location(&key_prefix);
+
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let request = ListObjectsV2Request {
+                bucket: self.bucket.clone(),
+                prefix: Some(key_prefix.clone()),
+                continuation_token: continuation_token.take(),
+                ..Default::default()
+            };
+            let output = self
+                .client
+                .list_objects_v2(request)
+                .sync()
+                .map_err(|err| BlockStorageError::S3(location.clone(), format!("{:?}", err)))?;
+
+            for object in output.contents.unwrap_or_default() {
+                if let Some(path) = object.key.as_ref().and_then(|key| self.path(key)) {
+                    objects.push(StoredObject {
+                        path,
+                        size: object.size.unwrap_or(0) as u64,
+                    });
+                }
+            }
+
+            match output.next_continuation_token {
+                Some(token) if output.is_truncated == Some(true) => {
+                    continuation_token = Some(token)
+                }
+                _ => break,
+            }
+        }
+        Ok(objects)
+    }
+}
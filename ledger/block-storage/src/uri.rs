@@ -0,0 +1,211 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! URIs of block storage locations.
+//!
+//! * `s3://<bucket>/<prefix>?region=<region>` - an S3 bucket. Add `endpoint=<url>` to use another
+//!   S3-compatible object store.
+//! * `http://<host>/<path>` or `https://...` - a plain HTTP server accepting GET and PUT.
+//! * `file://<path>` - a local directory.
+
+use crate::{BlockStorage, HttpStorage, LocalStorage, S3Storage};
+use failure::Fail;
+use rusoto_core::{region::ParseRegionError, Region};
+use std::{path::PathBuf, str::FromStr};
+use url::Url;
+
+#[derive(Clone, Debug)]
+pub enum StorageLocation {
+    S3 {
+        bucket: String,
+        prefix: String,
+        region: Region,
+    },
+    Http {
+        url: Url,
+    },
+    Local {
+        path: PathBuf,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct StorageUri {
+    /// The original Url used to construct this object.
+    pub url: Url,
+
+    /// Where blocks are stored.
+    pub location: StorageLocation,
+}
+
+#[derive(Debug, Fail)]
+pub enum UriParseError {
+    #[fail(display = "Url parse error: {}", _0)]
+    UrlParse(url::ParseError),
+
+    #[fail(display = "Unknown scheme: {}", _0)]
+    UnknownScheme(String),
+
+    #[fail(display = "Missing path")]
+    MissingPath,
+
+    #[fail(display = "Invalid S3 region: {}", _0)]
+    InvalidS3Region(ParseRegionError),
+}
+
+impl StorageUri {
+    /// Creates the storage this URI points to, using a default HTTP client.
+    pub fn storage(&self) -> Box<dyn BlockStorage> {
+        self.storage_with_http_client(reqwest::blocking::Client::new())
+    }
+
+    /// Creates the storage this URI points to.
+    ///
+    /// # Arguments
+    /// * `http_client` - Client used by HTTP storage.
+    pub fn storage_with_http_client(
+        &self,
+        http_client: reqwest::blocking::Client,
+    ) -> Box<dyn BlockStorage> {
+        match &self.location {
+            StorageLocation::S3 {
+                bucket,
+                prefix,
+                region,
+            } => Box::new(S3Storage::new(bucket, prefix, region.clone())),
+            StorageLocation::Http { url } => Box::new(HttpStorage::new(url.clone(), http_client)),
+            StorageLocation::Local { path } => Box::new(LocalStorage::new(path.clone())),
+        }
+    }
+}
+
+impl FromStr for StorageUri {
+    type Err = UriParseError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let url = Url::parse(src).map_err(UriParseError::UrlParse)?;
+
+        let location = match url.scheme() {
+            "s3" => {
+                let path = url[url::Position::BeforeHost..url::Position::AfterPath]
+                    .trim_matches('/')
+                    .to_string();
+                if path.is_empty() {
+                    return Err(UriParseError::MissingPath);
+                }
+                let mut parts = path.splitn(2, '/');
+                let bucket = parts.next().unwrap_or_default().to_string();
+                let prefix = parts.next().unwrap_or_default().to_string();
+
+                let query_param = |name: &str| {
+                    url.query_pairs().find_map(|(k, v)| {
+                        if k == name && !v.is_empty() {
+                            Some(v.to_string())
+                        } else {
+                            None
+                        }
+                    })
+                };
+
+                let region = match (query_param("region"), query_param("endpoint")) {
+                    (region, Some(endpoint)) => Region::Custom {
+                        name: region.unwrap_or_else(|| Region::default().name().to_owned()),
+                        endpoint,
+                    },
+                    (Some(region), None) => {
+                        Region::from_str(&region).map_err(UriParseError::InvalidS3Region)?
+                    }
+                    (None, None) => Region::default(),
+                };
+
+                StorageLocation::S3 {
+                    bucket,
+                    prefix,
+                    region,
+                }
+            }
+
+            "http" | "https" => StorageLocation::Http { url: url.clone() },
+
+            "file" => {
+                let path = url[url::Position::BeforeHost..url::Position::AfterPath]
+                    .trim_end_matches('/')
+                    .to_string();
+                if path.is_empty() {
+                    return Err(UriParseError::MissingPath);
+                }
+
+                StorageLocation::Local {
+                    path: PathBuf::from(path),
+                }
+            }
+
+            _ => return Err(UriParseError::UnknownScheme(url.scheme().to_string())),
+        };
+
+        Ok(Self { url, location })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_s3() {
+        let uri = StorageUri::from_str("s3://bucket/some/prefix/?region=us-west-1").unwrap();
+        match uri.location {
+            StorageLocation::S3 {
+                bucket,
+                prefix,
+                region,
+            } => {
+                assert_eq!(bucket, "bucket");
+                assert_eq!(prefix, "some/prefix");
+                assert_eq!(region, Region::UsWest1);
+            }
+            location => panic!("unexpected location {:?}", location),
+        }
+
+        let uri = StorageUri::from_str("s3://bucket?endpoint=http://localhost:9000&region=local")
+            .unwrap();
+        match uri.location {
+            StorageLocation::S3 { prefix, region, .. } => {
+                assert_eq!(prefix, "");
+                assert_eq!(
+                    region,
+                    Region::Custom {
+                        name: "local".to_owned(),
+                        endpoint: "http://localhost:9000".to_owned(),
+                    }
+                );
+            }
+            location => panic!("unexpected location {:?}", location),
+        }
+
+        assert!(StorageUri::from_str("s3://bucket?region=nowhere").is_err());
+        assert!(StorageUri::from_str("s3:///").is_err());
+    }
+
+    #[test]
+    fn test_parse_http_and_file() {
+        match StorageUri::from_str("https://example.com/blocks/")
+            .unwrap()
+            .location
+        {
+            StorageLocation::Http { url } => {
+                assert_eq!(url.as_str(), "https://example.com/blocks/")
+            }
+            location => panic!("unexpected location {:?}", location),
+        }
+
+        match StorageUri::from_str("file:///tmp/blocks/")
+            .unwrap()
+            .location
+        {
+            StorageLocation::Local { path } => assert_eq!(path, PathBuf::from("/tmp/blocks")),
+            location => panic!("unexpected location {:?}", location),
+        }
+
+        assert!(StorageUri::from_str("ftp://example.com/").is_err());
+    }
+}
@@ -0,0 +1,272 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! An in-process object store, serving GET and PUT over HTTP, for testing block storage backends.
+//!
+//! Objects are keyed by their request path, without the leading '/'. Requests made by S3 clients
+//! to a custom endpoint use path-style addressing, so their objects are keyed by
+//! `<bucket>/<key>`, and `GET <bucket>?list-type=2` lists them (in a single page). Request
+//! signatures are not checked.
+
+use mc_common::HashMap;
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+use url::form_urlencoded;
+
+#[derive(Default)]
+struct State {
+    /// Stored objects, by key.
+    objects: HashMap<String, Vec<u8>>,
+
+    /// Number of upcoming PUT requests to answer with an error.
+    failing_puts: usize,
+
+    /// Number of upcoming PUT requests whose object is stored corrupted.
+    corrupted_puts: usize,
+
+    /// Number of PUT requests received.
+    num_puts: usize,
+}
+
+pub struct MockObjectStore {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    stop: Arc<AtomicBool>,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+impl MockObjectStore {
+    /// Starts serving on a free local port.
+    pub fn new() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed binding mock object store");
+        let addr = listener.local_addr().expect("Failed getting local address");
+        let state = Arc::new(Mutex::new(State::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_state = state.clone();
+        let thread_stop = stop.clone();
+        let join_handle = thread::Builder::new()
+            .name("MockObjectStore".into())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if thread_stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        let state = thread_state.clone();
+                        thread::spawn(move || {
+                            let _ = handle_connection(stream, &state);
+                        });
+                    }
+                }
+            })
+            .expect("Failed spawning mock object store thread");
+
+        Self {
+            addr,
+            state,
+            stop,
+            join_handle: Some(join_handle),
+        }
+    }
+
+    /// The URL of the store, ending with a '/'.
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// Gets a stored object.
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        self.lock().objects.get(key).cloned()
+    }
+
+    /// Answers the next `n` PUT requests with an error, without storing their object.
+    pub fn fail_next_puts(&self, n: usize) {
+        self.lock().failing_puts = n;
+    }
+
+    /// Stores the object of the next `n` PUT requests with its last byte flipped.
+    pub fn corrupt_next_puts(&self, n: usize) {
+        self.lock().corrupted_puts = n;
+    }
+
+    /// Number of PUT requests received so far.
+    pub fn num_puts(&self) -> usize {
+        self.lock().num_puts
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<State> {
+        self.state.lock().expect("mutex poisoned")
+    }
+}
+
+impl Default for MockObjectStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for MockObjectStore {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake up the listening thread.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(join_handle) = self.join_handle.take() {
+            let _ = join_handle.join();
+        }
+    }
+}
+
+/// Serves a single request, and closes the connection.
+fn handle_connection(stream: TcpStream, state: &Mutex<State>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default();
+    let mut target_parts = target.splitn(2, '?');
+    let key = target_parts
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches('/')
+        .to_string();
+    let query: HashMap<String, String> =
+        form_urlencoded::parse(target_parts.next().unwrap_or_default().as_bytes())
+            .into_owned()
+            .collect();
+
+    let mut content_length = 0;
+    let mut chunked = false;
+    let mut expect_continue = false;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(index) = line.find(':') {
+            let name = line[..index].trim().to_ascii_lowercase();
+            let value = line[index + 1..].trim();
+            match name.as_str() {
+                "content-length" => content_length = value.parse().unwrap_or(0),
+                "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+                "expect" => expect_continue = value.eq_ignore_ascii_case("100-continue"),
+                _ => {}
+            }
+        }
+    }
+
+    if expect_continue {
+        writer.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+    }
+
+    let body = if chunked {
+        read_chunked_body(&mut reader)?
+    } else {
+        let mut body = vec![0u8; content_length];
+        reader.read_exact(&mut body)?;
+        body
+    };
+
+    let mut state = state.lock().expect("mutex poisoned");
+    match method.as_str() {
+        "GET" if query.get("list-type").map(String::as_str) == Some("2") => {
+            let prefix = format!(
+                "{}/{}",
+                key,
+                query.get("prefix").map(String::as_str).unwrap_or_default()
+            );
+            respond(
+                &mut writer,
+                "200 OK",
+                list_objects_v2_xml(&key, &state.objects, &prefix).as_bytes(),
+            )
+        }
+        "GET" | "HEAD" => match state.objects.get(&key) {
+            Some(object) if method == "GET" => respond(&mut writer, "200 OK", object),
+            Some(_) => respond(&mut writer, "200 OK", &[]),
+            None => respond(&mut writer, "404 Not Found", &[]),
+        },
+        "PUT" => {
+            state.num_puts += 1;
+            if state.failing_puts > 0 {
+                state.failing_puts -= 1;
+                return respond(&mut writer, "500 Internal Server Error", &[]);
+            }
+
+            let mut object = body;
+            if state.corrupted_puts > 0 {
+                state.corrupted_puts -= 1;
+                if let Some(last) = object.last_mut() {
+                    *last ^= 1;
+                }
+            }
+            state.objects.insert(key, object);
+            respond(&mut writer, "200 OK", &[])
+        }
+        _ => respond(&mut writer, "405 Method Not Allowed", &[]),
+    }
+}
+
+/// An S3 ListObjectsV2 response listing the objects of `bucket` whose key starts with `prefix`.
+fn list_objects_v2_xml(bucket: &str, objects: &HashMap<String, Vec<u8>>, prefix: &str) -> String {
+    let contents: Vec<String> = objects
+        .iter()
+        .filter(|(key, _)| key.starts_with(prefix))
+        .map(|(key, object)| {
+            format!(
+                "<Contents><Key>{}</Key><Size>{}</Size></Contents>",
+                &key[bucket.len() + 1..],
+                object.len()
+            )
+        })
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <ListBucketResult xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+         <Name>{}</Name><KeyCount>{}</KeyCount><IsTruncated>false</IsTruncated>{}\
+         </ListBucketResult>",
+        bucket,
+        contents.len(),
+        contents.concat()
+    )
+}
+
+fn read_chunked_body<R: BufRead>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let mut size_line = String::new();
+        reader.read_line(&mut size_line)?;
+        let size_str = size_line.trim().split(';').next().unwrap_or_default();
+        let size = usize::from_str_radix(size_str, 16)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        let mut chunk = vec![0u8; size + 2];
+        reader.read_exact(&mut chunk)?;
+        if size == 0 {
+            return Ok(body);
+        }
+        body.extend_from_slice(&chunk[..size]);
+    }
+}
+
+fn respond<W: Write>(writer: &mut W, status: &str, body: &[u8]) -> io::Result<()> {
+    write!(
+        writer,
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    )?;
+    writer.write_all(body)?;
+    writer.flush()
+}
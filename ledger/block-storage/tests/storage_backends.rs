@@ -0,0 +1,129 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Tests of the block storage backends, against a local directory and an in-process object store.
+
+use mc_common::logger::{test_with_logger, Logger};
+use mc_ledger_block_storage::{
+    put_verified, BlockStorage, BlockStorageError, HttpStorage, LocalStorage, S3Storage,
+    StoredObject,
+};
+use mc_ledger_block_storage_test_utils::MockObjectStore;
+use rusoto_core::{credential::StaticProvider, HttpClient, Region};
+use rusoto_s3::S3Client;
+use tempdir::TempDir;
+use url::Url;
+
+const PATH: &str = "00/00/00/00/00/00/00/0000000000000001.pb";
+
+/// Writes and reads back an object, and checks that missing objects are reported as such.
+fn check_round_trip(storage: &dyn BlockStorage) {
+    match storage.get(PATH) {
+        Err(BlockStorageError::NotFound(_)) => {}
+        result => panic!("unexpected result {:?}", result),
+    }
+
+    storage.put(PATH, b"first").unwrap();
+    assert_eq!(storage.get(PATH).unwrap(), b"first".to_vec());
+
+    storage.put(PATH, b"second").unwrap();
+    assert_eq!(storage.get(PATH).unwrap(), b"second".to_vec());
+}
+
+/// Lists the objects written by `check_round_trip`, along with a bundle.
+fn check_list(storage: &dyn BlockStorage) {
+    let bundle_path = "bundles/2/00/00/00/00/00/00/00/0000000000000000.pb.gz";
+    storage.put(bundle_path, b"bundle").unwrap();
+
+    let mut objects = storage.list("").unwrap();
+    objects.sort_by(|a, b| a.path.cmp(&b.path));
+    assert_eq!(
+        objects,
+        vec![
+            StoredObject {
+                path: PATH.to_owned(),
+                size: 6,
+            },
+            StoredObject {
+                path: bundle_path.to_owned(),
+                size: 6,
+            },
+        ]
+    );
+
+    let objects = storage.list("bundles/").unwrap();
+    assert_eq!(objects.len(), 1);
+    assert_eq!(objects[0].path, bundle_path);
+}
+
+fn http_storage(store: &MockObjectStore, prefix: &str) -> HttpStorage {
+    let url = Url::parse(&store.url()).unwrap().join(prefix).unwrap();
+    HttpStorage::new(url, reqwest::blocking::Client::new())
+}
+
+#[test]
+fn test_local_storage() {
+    let dir = TempDir::new("block_storage").unwrap();
+    let storage = LocalStorage::new(dir.path().join("blocks"));
+    check_round_trip(&storage);
+    assert!(dir.path().join("blocks").join(PATH).exists());
+    check_list(&storage);
+}
+
+#[test]
+fn test_http_storage() {
+    let store = MockObjectStore::new();
+    let storage = http_storage(&store, "blocks/");
+    check_round_trip(&storage);
+    assert_eq!(
+        store.get(&format!("blocks/{}", PATH)).unwrap(),
+        b"second".to_vec()
+    );
+
+    match storage.list("") {
+        Err(BlockStorageError::ListingUnsupported(_)) => {}
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+// The object store stands in for an S3-compatible endpoint.
+fn test_s3_storage_with_custom_endpoint() {
+    let store = MockObjectStore::new();
+    let region = Region::Custom {
+        name: "local".to_owned(),
+        endpoint: store.url().trim_end_matches('/').to_owned(),
+    };
+    let client = S3Client::new_with(
+        HttpClient::new().unwrap(),
+        StaticProvider::new_minimal("access_key".to_owned(), "secret_key".to_owned()),
+        region,
+    );
+    let storage = S3Storage::new_with_client(client, "bucket", "/node1/");
+    check_round_trip(&storage);
+    assert_eq!(
+        store.get(&format!("bucket/node1/{}", PATH)).unwrap(),
+        b"second".to_vec()
+    );
+    check_list(&storage);
+}
+
+#[test_with_logger]
+// Failed and corrupted writes are retried until the read back object matches.
+fn test_put_verified(logger: Logger) {
+    let store = MockObjectStore::new();
+    let storage = http_storage(&store, "");
+
+    store.fail_next_puts(1);
+    store.corrupt_next_puts(1);
+    put_verified(&storage, PATH, b"block", 3, &logger).unwrap();
+    assert_eq!(store.num_puts(), 3);
+    assert_eq!(store.get(PATH).unwrap(), b"block".to_vec());
+
+    // Give up after `max_attempts`.
+    store.corrupt_next_puts(2);
+    match put_verified(&storage, PATH, b"other block", 2, &logger) {
+        Err(BlockStorageError::VerificationFailed(_)) => {}
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert_eq!(store.num_puts(), 5);
+}
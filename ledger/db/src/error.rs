@@ -30,6 +30,9 @@ pub enum Error {
     #[fail(display = "InvalidBlockID")]
     InvalidBlockID,
 
+    #[fail(display = "InvalidBlockSignature")]
+    InvalidBlockSignature,
+
     #[fail(display = "NoOutputs")]
     NoOutputs,
 
@@ -51,6 +54,18 @@ pub enum Error {
 
     #[fail(display = "Database version {} is incompatible with {}", _0, _1)]
     VersionIncompatible(u64, u64),
+
+    #[fail(display = "Unsupported block version: {}", _0)]
+    UnsupportedBlockVersion(u32),
+
+    #[fail(display = "Invalid snapshot: {}", _0)]
+    InvalidSnapshot(&'static str),
+
+    #[fail(display = "Invalid snapshot block {}: {}", _0, _1)]
+    InvalidSnapshotBlock(u64, &'static str),
+
+    #[fail(display = "IO error: {:?}", _0)]
+    Io(std::io::ErrorKind),
 }
 
 impl From<lmdb::Error> for Error {
@@ -66,6 +81,12 @@ impl From<lmdb::Error> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(io_error: std::io::Error) -> Self {
+        Error::Io(io_error.kind())
+    }
+}
+
 impl From<mc_util_serial::decode::Error> for Error {
     fn from(_: mc_util_serial::decode::Error) -> Self {
         Error::Deserialization
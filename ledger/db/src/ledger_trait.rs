@@ -5,7 +5,7 @@ use mc_common::Hash;
 use mc_transaction_core::{
     ring_signature::KeyImage,
     tx::{TxOut, TxOutMembershipProof},
-    Block, BlockContents, BlockSignature,
+    Block, BlockContents, BlockSignature, Feature,
 };
 
 pub trait Ledger: Clone + Send {
@@ -17,6 +17,16 @@ pub trait Ledger: Clone + Send {
         signature: Option<&BlockSignature>,
     ) -> Result<(), Error>;
 
+    /// Appends a block along with transactions, and the signatures over it that were collected
+    /// from other nodes, all at once.
+    fn append_block_with_signatures(
+        &mut self,
+        block: &Block,
+        block_contents: &BlockContents,
+        signature: Option<&BlockSignature>,
+        collected_signatures: &[BlockSignature],
+    ) -> Result<(), Error>;
+
     /// Get the total number of blocks in the ledger.
     fn num_blocks(&self) -> Result<u64, Error>;
 
@@ -29,6 +39,20 @@ pub trait Ledger: Clone + Send {
     /// Gets a block signature by its index in the blockchain.
     fn get_block_signature(&self, block_number: u64) -> Result<BlockSignature, Error>;
 
+    /// Gets the signatures over a block that were collected from other nodes.
+    fn get_collected_block_signatures(
+        &self,
+        block_number: u64,
+    ) -> Result<Vec<BlockSignature>, Error>;
+
+    /// Stores signatures over a block that were collected from other nodes, keeping at most one
+    /// signature per signer.
+    fn add_collected_block_signatures(
+        &mut self,
+        block_number: u64,
+        signatures: &[BlockSignature],
+    ) -> Result<(), Error>;
+
     /// Get the total number of TxOuts in the ledger.
     fn num_txos(&self) -> Result<u64, Error>;
 
@@ -44,6 +68,19 @@ pub trait Ledger: Clone + Send {
         indexes: &[u64],
     ) -> Result<Vec<TxOutMembershipProof>, Error>;
 
+    /// Updates proofs of membership, created from an earlier state of the ledger, to the current
+    /// state of the ledger.
+    ///
+    /// The default implementation recomputes each proof. Implementations may instead re-use the
+    /// parts of each proof that are unaffected by TxOuts added since it was created.
+    fn update_tx_out_proof_of_memberships(
+        &self,
+        proofs: &[TxOutMembershipProof],
+    ) -> Result<Vec<TxOutMembershipProof>, Error> {
+        let indexes: Vec<u64> = proofs.iter().map(|proof| proof.index).collect();
+        self.get_tx_out_proof_of_memberships(&indexes)
+    }
+
     /// Returns true if the Ledger contains the given key image.
     fn contains_key_image(&self, key_image: &KeyImage) -> Result<bool, Error> {
         self.check_key_image(key_image).map(|x| x.is_some())
@@ -56,4 +93,72 @@ pub trait Ledger: Clone + Send {
 
     /// Gets the key images used by transactions in a single block.
     fn get_key_images_by_block(&self, block_number: u64) -> Result<Vec<KeyImage>, Error>;
+
+    /// Gets the index of the block containing the TxOut with the given index.
+    ///
+    /// The default implementation binary searches the blocks' cumulative TxOut counts.
+    fn get_block_index_by_tx_out_index(&self, tx_out_index: u64) -> Result<u64, Error> {
+        // The TxOut is in the first block whose cumulative TxOut count exceeds its index.
+        let num_blocks = self.num_blocks()?;
+        let (mut low, mut high) = (0, num_blocks);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.get_block(mid)?.cumulative_txo_count > tx_out_index {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        if low == num_blocks {
+            return Err(Error::NotFound);
+        }
+        Ok(low)
+    }
+
+    /// Gets the time at which a block was formed, in seconds since the Unix epoch (see
+    /// `block_timestamp`). Ok(None) is returned when the block's version does not record a close
+    /// time and none of its signatures records a signing time.
+    fn get_block_timestamp(&self, block_number: u64) -> Result<Option<u64>, Error> {
+        let block = self.get_block(block_number)?;
+        if Feature::BlockTimestamp.is_enabled(block.version) {
+            return Ok(Some(block.timestamp));
+        }
+
+        let mut signatures = self.get_collected_block_signatures(block_number)?;
+        match self.get_block_signature(block_number) {
+            Ok(signature) => signatures.push(signature),
+            Err(Error::NotFound) => {}
+            Err(err) => return Err(err),
+        }
+        Ok(block_timestamp(&block, &signatures))
+    }
+}
+
+/// The time at which a block was formed, in seconds since the Unix epoch, or None if it is not
+/// known.
+///
+/// Blocks whose version enables `Feature::BlockTimestamp` record the close time agreed on by
+/// consensus. For earlier blocks, this is the median of the times at which their signers signed
+/// them, so that a minority of signers with wrong times cannot move the timestamp outside of the
+/// range of the others.
+///
+/// # Arguments
+/// * `block` - The block.
+/// * `signatures` - Signatures over the block.
+pub fn block_timestamp(block: &Block, signatures: &[BlockSignature]) -> Option<u64> {
+    if Feature::BlockTimestamp.is_enabled(block.version) {
+        return Some(block.timestamp);
+    }
+
+    let mut signing_times: Vec<u64> = signatures
+        .iter()
+        .map(|signature| signature.signed_at())
+        .filter(|signed_at| *signed_at != 0)
+        .collect();
+    if signing_times.is_empty() {
+        return None;
+    }
+    signing_times.sort_unstable();
+    // With an even number of signing times, the lower median is an actual signing time.
+    Some(signing_times[(signing_times.len() - 1) / 2])
 }
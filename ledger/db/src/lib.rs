@@ -9,29 +9,42 @@ extern crate test;
 
 use core::convert::TryInto;
 use lmdb::{
-    Database, DatabaseFlags, Environment, EnvironmentFlags, RoTransaction, RwTransaction,
+    Cursor, Database, DatabaseFlags, Environment, EnvironmentFlags, RoTransaction, RwTransaction,
     Transaction, WriteFlags,
 };
-use mc_transaction_core::{Block, BlockContents, BlockID, BlockSignature, BLOCK_VERSION};
+use mc_transaction_core::{
+    validate_block_version, Block, BlockContents, BlockID, BlockSignature, BlockVersionError,
+};
 use mc_util_serial::{decode, encode, Message};
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 mod error;
 mod ledger_trait;
 pub mod metadata;
+pub mod snapshot;
 pub mod tx_out_store;
+mod validation;
 
 #[cfg(any(test, feature = "test_utils"))]
 pub mod test_utils;
 
 pub use error::Error;
-pub use ledger_trait::Ledger;
+pub use ledger_trait::{block_timestamp, Ledger};
 use mc_transaction_core::{
     ring_signature::KeyImage,
     tx::{TxOut, TxOutMembershipProof},
 };
 pub use metadata::MetadataStore;
+use metadata::{MetadataVersion, BLOCK_INDEXES_MIGRATION_VERSION};
+pub use snapshot::{
+    export_snapshot, import_snapshot, verify_snapshot, SnapshotBlock, SnapshotHeader,
+    SnapshotReader,
+};
 use tx_out_store::TxOutStore;
+pub use validation::validate_tx;
 
 const MAX_LMDB_FILE_SIZE: usize = 1_099_511_627_776; // 1 TB
 
@@ -39,9 +52,12 @@ const MAX_LMDB_FILE_SIZE: usize = 1_099_511_627_776; // 1 TB
 pub const COUNTS_DB_NAME: &str = "ledger_db:counts";
 pub const BLOCKS_DB_NAME: &str = "ledger_db:blocks";
 pub const BLOCK_SIGNATURES_DB_NAME: &str = "ledger_db:block_signatures";
+pub const COLLECTED_BLOCK_SIGNATURES_DB_NAME: &str = "ledger_db:collected_block_signatures";
 pub const KEY_IMAGES_DB_NAME: &str = "ledger_db:key_images";
 pub const KEY_IMAGES_BY_BLOCK_DB_NAME: &str = "ledger_db:key_images_by_block";
 pub const TX_OUTS_BY_BLOCK_DB_NAME: &str = "ledger_db:tx_outs_by_block";
+pub const BLOCK_NUMBER_BY_TX_OUT_INDEX_DB_NAME: &str = "ledger_db:block_number_by_tx_out_index";
+pub const BLOCK_TIMESTAMPS_DB_NAME: &str = "ledger_db:block_timestamps";
 
 // Keys used by the `counts` database.
 const NUM_BLOCKS_KEY: &str = "num_blocks";
@@ -66,6 +82,13 @@ pub struct KeyImageList {
     pub key_images: Vec<KeyImage>,
 }
 
+// A list of block signatures that can be prost-encoded.
+#[derive(Clone, Message)]
+pub struct BlockSignatureList {
+    #[prost(message, repeated, tag = "1")]
+    pub signatures: Vec<BlockSignature>,
+}
+
 #[derive(Clone)]
 pub struct LedgerDB {
     env: Arc<Environment>,
@@ -80,6 +103,9 @@ pub struct LedgerDB {
     /// Block signatures by number. `block number -> BlockSignature`
     block_signatures: Database,
 
+    /// Signatures collected from other nodes, by block number. `block number -> BlockSignatureList`
+    collected_block_signatures: Database,
+
     /// Key Images
     key_images: Database,
 
@@ -97,6 +123,15 @@ pub struct LedgerDB {
     /// querying `tx_out_store`.
     tx_outs_by_block: Database,
 
+    /// Block numbers by the index of the last TxOut in each block.
+    /// `last TxOut index -> block number`. The block containing a TxOut is the first entry at or
+    /// after the TxOut's index.
+    block_number_by_tx_out_index: Database,
+
+    /// Block timestamps by block number, in seconds since the Unix epoch.
+    /// `block number -> timestamp`. Blocks without a timestamp have no entry.
+    block_timestamps: Database,
+
     /// Location on filesystem.
     path: PathBuf,
 }
@@ -114,12 +149,36 @@ impl Ledger for LedgerDB {
         block: &Block,
         block_contents: &BlockContents,
         signature: Option<&BlockSignature>,
+    ) -> Result<(), Error> {
+        self.append_block_with_signatures(block, block_contents, signature, &[])
+    }
+
+    /// Appends a block and its associated transactions to the blockchain, along with signatures
+    /// collected from other nodes, in a single transaction.
+    ///
+    /// # Arguments
+    /// * `block` - A block.
+    /// * `block_contents` - The contents of the block.
+    /// * `signature` - This node's signature over the block.
+    /// * `collected_signatures` - Signatures over the block by other nodes. Each must be valid.
+    fn append_block_with_signatures(
+        &mut self,
+        block: &Block,
+        block_contents: &BlockContents,
+        signature: Option<&BlockSignature>,
+        collected_signatures: &[BlockSignature],
     ) -> Result<(), Error> {
         // Note: This function must update every LMDB database managed by LedgerDB.
         let mut db_transaction = self.env.begin_rw_txn()?;
 
         // Validate the block is safe to append.
         self.validate_append_block(block, block_contents)?;
+        if collected_signatures
+            .iter()
+            .any(|signature| signature.verify(block).is_err())
+        {
+            return Err(Error::InvalidBlockSignature);
+        }
 
         // Write key images included in block.
         self.write_key_images(block.index, &block_contents.key_images, &mut db_transaction)?;
@@ -130,6 +189,20 @@ impl Ledger for LedgerDB {
         // Write block.
         self.write_block(block, signature, &mut db_transaction)?;
 
+        // Write the collected signatures, and the block's timestamp from the signing times of
+        // every signature.
+        let mut signatures = if collected_signatures.is_empty() {
+            Vec::new()
+        } else {
+            self.write_collected_block_signatures(
+                block.index,
+                collected_signatures,
+                &mut db_transaction,
+            )?
+        };
+        signatures.extend(signature.cloned());
+        self.write_block_timestamp(block, &signatures, &mut db_transaction)?;
+
         // Commit.
         db_transaction.commit()?;
         Ok(())
@@ -193,6 +266,61 @@ impl Ledger for LedgerDB {
         Ok(signature)
     }
 
+    /// Gets the signatures over a block that were collected from other nodes.
+    fn get_collected_block_signatures(
+        &self,
+        block_number: u64,
+    ) -> Result<Vec<BlockSignature>, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        match db_transaction.get(
+            self.collected_block_signatures,
+            &u64_to_key_bytes(block_number),
+        ) {
+            Ok(bytes) => {
+                let signature_list: BlockSignatureList = decode(bytes)?;
+                Ok(signature_list.signatures)
+            }
+            Err(lmdb::Error::NotFound) => Ok(Vec::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Stores signatures over a block that were collected from other nodes.
+    ///
+    /// Each signature must be valid for the block. Signatures by a signer that already has a
+    /// signature stored for the block are ignored.
+    fn add_collected_block_signatures(
+        &mut self,
+        block_number: u64,
+        signatures: &[BlockSignature],
+    ) -> Result<(), Error> {
+        let mut db_transaction = self.env.begin_rw_txn()?;
+
+        let block: Block =
+            decode(db_transaction.get(self.blocks, &u64_to_key_bytes(block_number))?)?;
+        if signatures
+            .iter()
+            .any(|signature| signature.verify(&block).is_err())
+        {
+            return Err(Error::InvalidBlockSignature);
+        }
+
+        let key = u64_to_key_bytes(block_number);
+        let mut all_signatures =
+            self.write_collected_block_signatures(block_number, signatures, &mut db_transaction)?;
+
+        // The block's timestamp now also depends on the collected signatures.
+        match db_transaction.get(self.block_signatures, &key) {
+            Ok(bytes) => all_signatures.push(decode(bytes)?),
+            Err(lmdb::Error::NotFound) => {}
+            Err(err) => return Err(err.into()),
+        }
+        self.write_block_timestamp(&block, &all_signatures, &mut db_transaction)?;
+
+        db_transaction.commit()?;
+        Ok(())
+    }
+
     /// Returns the index of the TxOut with the given hash.
     fn get_tx_out_index_by_hash(&self, tx_out_hash: &[u8; 32]) -> Result<u64, Error> {
         let db_transaction: RoTransaction = self.env.begin_ro_txn()?;
@@ -230,6 +358,39 @@ impl Ledger for LedgerDB {
         Ok(key_image_list.key_images)
     }
 
+    /// Gets the index of the block containing the TxOut with the given index.
+    fn get_block_index_by_tx_out_index(&self, tx_out_index: u64) -> Result<u64, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        if tx_out_index >= self.tx_out_store.num_tx_outs(&db_transaction)? {
+            return Err(Error::NotFound);
+        }
+
+        // Every TxOut index below the number of TxOuts is at or before the last TxOut of the last
+        // block, so the cursor always finds an entry.
+        let mut cursor = db_transaction.open_ro_cursor(self.block_number_by_tx_out_index)?;
+        match cursor.iter_from(u64_to_key_bytes(tx_out_index)).next() {
+            Some((_last_tx_out_index, block_number_bytes)) => {
+                Ok(key_bytes_to_u64(block_number_bytes))
+            }
+            None => Err(Error::NotFound),
+        }
+    }
+
+    /// Gets the time at which a block was formed, in seconds since the Unix epoch.
+    fn get_block_timestamp(&self, block_number: u64) -> Result<Option<u64>, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        let key = u64_to_key_bytes(block_number);
+        match db_transaction.get(self.block_timestamps, &key) {
+            Ok(bytes) => Ok(Some(key_bytes_to_u64(bytes))),
+            Err(lmdb::Error::NotFound) => {
+                // Distinguish blocks without a timestamp from blocks that are not in the ledger.
+                db_transaction.get(self.blocks, &key)?;
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
     /// Gets a proof of memberships for TxOuts with indexes `indexes`.
     fn get_tx_out_proof_of_memberships(
         &self,
@@ -244,43 +405,117 @@ impl Ledger for LedgerDB {
             })
             .collect()
     }
+
+    /// Updates proofs of membership to the current state of the ledger, re-using hashes of
+    /// ranges that did not change.
+    fn update_tx_out_proof_of_memberships(
+        &self,
+        proofs: &[TxOutMembershipProof],
+    ) -> Result<Vec<TxOutMembershipProof>, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        proofs
+            .iter()
+            .map(|proof| {
+                self.tx_out_store
+                    .update_merkle_proof_of_membership(proof, &db_transaction)
+            })
+            .collect()
+    }
 }
 
 impl LedgerDB {
     /// Opens an existing Ledger Database in the given path.
+    ///
+    /// Opening does not modify the database. A database created by an earlier version must first
+    /// be migrated with `migrate`.
     pub fn open(path: PathBuf) -> Result<LedgerDB, Error> {
-        let env = Environment::new()
+        let env = Self::open_environment(&path)?;
+        let metadata_store = MetadataStore::new(&env)?;
+
+        // Check if the database we opened is compatible with the current implementation.
+        let db_txn = env.begin_ro_txn()?;
+        let version = metadata_store.get_version(&db_txn)?;
+        version.is_compatible_with_latest()?;
+        db_txn.commit()?;
+
+        Self::open_databases(env, metadata_store, path)
+    }
+
+    /// Migrates a Ledger Database created by an earlier version to the latest format.
+    /// Returns true if the database was migrated, or false if it already had the latest format.
+    ///
+    /// Only the process that writes to the ledger should migrate it, before opening it.
+    ///
+    /// # Arguments
+    /// * `path` - The directory of the database.
+    pub fn migrate(path: PathBuf) -> Result<bool, Error> {
+        let env = Self::open_environment(&path)?;
+        let metadata_store = MetadataStore::new(&env)?;
+
+        let db_txn = env.begin_ro_txn()?;
+        let version = metadata_store.get_version(&db_txn)?;
+        db_txn.commit()?;
+        if version.database_format_version != BLOCK_INDEXES_MIGRATION_VERSION {
+            version.is_compatible_with_latest()?;
+            return Ok(false);
+        }
+
+        // Databases added since that version. Ledgers created before signatures were collected do
+        // not have the collected signatures database either.
+        for name in &[
+            COLLECTED_BLOCK_SIGNATURES_DB_NAME,
+            BLOCK_NUMBER_BY_TX_OUT_INDEX_DB_NAME,
+            BLOCK_TIMESTAMPS_DB_NAME,
+        ] {
+            env.create_db(Some(name), DatabaseFlags::empty())?;
+        }
+
+        let ledger_db = Self::open_databases(env, metadata_store, path)?;
+        ledger_db.migrate_block_indexes(version)?;
+        Ok(true)
+    }
+
+    /// Opens the LMDB environment of an existing Ledger Database.
+    fn open_environment(path: &Path) -> Result<Environment, Error> {
+        Ok(Environment::new()
             .set_max_dbs(20)
             .set_map_size(MAX_LMDB_FILE_SIZE)
             // TODO - needed because currently our test cloud machines have slow disks.
             .set_flags(EnvironmentFlags::NO_SYNC)
-            .open(&path)?;
+            .open(path)?)
+    }
 
+    /// Opens the databases of an existing Ledger Database.
+    fn open_databases(
+        env: Environment,
+        metadata_store: MetadataStore,
+        path: PathBuf,
+    ) -> Result<LedgerDB, Error> {
         let counts = env.open_db(Some(COUNTS_DB_NAME))?;
         let blocks = env.open_db(Some(BLOCKS_DB_NAME))?;
         let block_signatures = env.open_db(Some(BLOCK_SIGNATURES_DB_NAME))?;
+        let collected_block_signatures = env.open_db(Some(COLLECTED_BLOCK_SIGNATURES_DB_NAME))?;
         let key_images = env.open_db(Some(KEY_IMAGES_DB_NAME))?;
         let key_images_by_block = env.open_db(Some(KEY_IMAGES_BY_BLOCK_DB_NAME))?;
         let tx_outs_by_block = env.open_db(Some(TX_OUTS_BY_BLOCK_DB_NAME))?;
+        let block_number_by_tx_out_index =
+            env.open_db(Some(BLOCK_NUMBER_BY_TX_OUT_INDEX_DB_NAME))?;
+        let block_timestamps = env.open_db(Some(BLOCK_TIMESTAMPS_DB_NAME))?;
 
-        let metadata_store = MetadataStore::new(&env)?;
         let tx_out_store = TxOutStore::new(&env)?;
 
-        // Check if the database we opened is compatible with the current implementation.
-        let db_txn = env.begin_ro_txn()?;
-        let version = metadata_store.get_version(&db_txn)?;
-        version.is_compatible_with_latest()?;
-        db_txn.commit()?;
-
         Ok(LedgerDB {
             env: Arc::new(env),
             path,
             counts,
             blocks,
             block_signatures,
+            collected_block_signatures,
             key_images,
             key_images_by_block,
             tx_outs_by_block,
+            block_number_by_tx_out_index,
+            block_timestamps,
             metadata_store,
             tx_out_store,
         })
@@ -302,9 +537,18 @@ impl LedgerDB {
         let counts = env.create_db(Some(COUNTS_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(BLOCKS_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(BLOCK_SIGNATURES_DB_NAME), DatabaseFlags::empty())?;
+        env.create_db(
+            Some(COLLECTED_BLOCK_SIGNATURES_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
         env.create_db(Some(KEY_IMAGES_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(KEY_IMAGES_BY_BLOCK_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(TX_OUTS_BY_BLOCK_DB_NAME), DatabaseFlags::empty())?;
+        env.create_db(
+            Some(BLOCK_NUMBER_BY_TX_OUT_INDEX_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+        env.create_db(Some(BLOCK_TIMESTAMPS_DB_NAME), DatabaseFlags::empty())?;
 
         MetadataStore::create(&env)?;
         TxOutStore::create(&env)?;
@@ -400,17 +644,22 @@ impl LedgerDB {
         let next_tx_out_index = self.tx_out_store.num_tx_outs(db_transaction)?;
 
         // Store information about the TxOuts included in this block.
-        let bytes = encode(&TxOutsByBlockValue {
+        let tx_outs_by_block_value = TxOutsByBlockValue {
             first_tx_out_index: next_tx_out_index,
             num_tx_outs: tx_outs.len() as u64,
-        });
+        };
 
         db_transaction.put(
             self.tx_outs_by_block,
             &u64_to_key_bytes(block_index),
-            &bytes,
+            &encode(&tx_outs_by_block_value),
             WriteFlags::empty(),
         )?;
+        self.write_block_number_by_tx_out_index(
+            block_index,
+            &tx_outs_by_block_value,
+            db_transaction,
+        )?;
 
         // Write the actual TxOuts.
         for tx_out in tx_outs {
@@ -421,17 +670,134 @@ impl LedgerDB {
         Ok(())
     }
 
+    /// Indexes a block by the index of its last TxOut.
+    fn write_block_number_by_tx_out_index(
+        &self,
+        block_index: u64,
+        tx_outs_by_block_value: &TxOutsByBlockValue,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<(), lmdb::Error> {
+        // Blocks without TxOuts cannot be appended, but nothing could be found in them anyway.
+        if tx_outs_by_block_value.num_tx_outs == 0 {
+            return Ok(());
+        }
+        let last_tx_out_index =
+            tx_outs_by_block_value.first_tx_out_index + tx_outs_by_block_value.num_tx_outs - 1;
+        db_transaction.put(
+            self.block_number_by_tx_out_index,
+            &u64_to_key_bytes(last_tx_out_index),
+            &u64_to_key_bytes(block_index),
+            WriteFlags::empty(),
+        )
+    }
+
+    /// Adds signatures to those collected for a block, keeping at most one signature per signer.
+    ///
+    /// Returns every collected signature for the block.
+    fn write_collected_block_signatures(
+        &self,
+        block_index: u64,
+        signatures: &[BlockSignature],
+        db_transaction: &mut RwTransaction,
+    ) -> Result<Vec<BlockSignature>, Error> {
+        let key = u64_to_key_bytes(block_index);
+        let mut signature_list: BlockSignatureList =
+            match db_transaction.get(self.collected_block_signatures, &key) {
+                Ok(bytes) => decode(bytes)?,
+                Err(lmdb::Error::NotFound) => BlockSignatureList {
+                    signatures: Vec::new(),
+                },
+                Err(err) => return Err(err.into()),
+            };
+        for signature in signatures {
+            if signature_list
+                .signatures
+                .iter()
+                .all(|stored| stored.signer() != signature.signer())
+            {
+                signature_list.signatures.push(signature.clone());
+            }
+        }
+
+        db_transaction.put(
+            self.collected_block_signatures,
+            &key,
+            &encode(&signature_list),
+            WriteFlags::empty(),
+        )?;
+        Ok(signature_list.signatures)
+    }
+
+    /// Writes the timestamp of a block, recorded in the block or computed from all signatures
+    /// over it.
+    fn write_block_timestamp(
+        &self,
+        block: &Block,
+        signatures: &[BlockSignature],
+        db_transaction: &mut RwTransaction,
+    ) -> Result<(), lmdb::Error> {
+        match block_timestamp(block, signatures) {
+            Some(timestamp) => db_transaction.put(
+                self.block_timestamps,
+                &u64_to_key_bytes(block.index),
+                &u64_to_key_bytes(timestamp),
+                WriteFlags::empty(),
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Migrates a database created before blocks were indexed by TxOut index and timestamp, by
+    /// indexing every block.
+    ///
+    /// # Arguments
+    /// * `version` - The version of the database.
+    fn migrate_block_indexes(&self, version: MetadataVersion) -> Result<(), Error> {
+        let mut db_transaction = self.env.begin_rw_txn()?;
+        let num_blocks = key_bytes_to_u64(db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?);
+
+        for block_index in 0..num_blocks {
+            let key = u64_to_key_bytes(block_index);
+
+            let tx_outs_by_block_value: TxOutsByBlockValue =
+                decode(db_transaction.get(self.tx_outs_by_block, &key)?)?;
+            self.write_block_number_by_tx_out_index(
+                block_index,
+                &tx_outs_by_block_value,
+                &mut db_transaction,
+            )?;
+
+            let mut signatures = match db_transaction.get(self.collected_block_signatures, &key) {
+                Ok(bytes) => decode::<BlockSignatureList>(bytes)?.signatures,
+                Err(lmdb::Error::NotFound) => Vec::new(),
+                Err(err) => return Err(err.into()),
+            };
+            match db_transaction.get(self.block_signatures, &key) {
+                Ok(bytes) => signatures.push(decode(bytes)?),
+                Err(lmdb::Error::NotFound) => {}
+                Err(err) => return Err(err.into()),
+            }
+            let block: Block = decode(db_transaction.get(self.blocks, &key)?)?;
+            self.write_block_timestamp(&block, &signatures, &mut db_transaction)?;
+        }
+
+        // The crate version that created the database is kept.
+        let migrated_version = MetadataVersion {
+            database_format_version: metadata::LATEST_VERSION,
+            ..version
+        };
+        self.metadata_store
+            .set_version(&mut db_transaction, &migrated_version)?;
+        db_transaction.commit()?;
+        Ok(())
+    }
+
     /// Checks if a block can be appended to the db.
     fn validate_append_block(
         &self,
         block: &Block,
         block_contents: &BlockContents,
     ) -> Result<(), Error> {
-        // Check that version is correct
-        if block.version != BLOCK_VERSION {
-            return Err(Error::InvalidBlock);
-        }
-
         // A block must have outputs.
         if block_contents.outputs.is_empty() {
             return Err(Error::NoOutputs);
@@ -445,18 +811,27 @@ impl LedgerDB {
 
         // Check if block is being appended at the correct place.
         let num_blocks = self.num_blocks()?;
-        if num_blocks == 0 {
+        let parent = if num_blocks == 0 {
             // This must be an origin block.
             if block.index != 0 || block.parent_id != BlockID::default() {
                 return Err(Error::InvalidBlock);
             }
+            None
         } else {
             // The block must have the correct index and parent.
             let last_block = self.get_block(num_blocks - 1)?;
             if block.index != num_blocks || block.parent_id != last_block.id {
                 return Err(Error::InvalidBlock);
             }
-        }
+            Some(last_block)
+        };
+
+        // Check that the block follows the rules of its version.
+        validate_block_version(block, block_contents, parent.as_ref()).map_err(|e| match e {
+            BlockVersionError::Unsupported(version) => Error::UnsupportedBlockVersion(version),
+            BlockVersionError::InvalidContents(_) => Error::InvalidBlockContents,
+            _ => Error::InvalidBlock,
+        })?;
 
         // Check that the block contents match the hash.
         if block.contents_hash != block_contents.hash() {
@@ -496,8 +871,8 @@ pub fn key_bytes_to_u64(bytes: &[u8]) -> u64 {
 mod ledger_db_test {
     use super::*;
     use core::convert::TryFrom;
-    use mc_crypto_keys::RistrettoPrivate;
-    use mc_transaction_core::{account_keys::AccountKey, compute_block_id};
+    use mc_crypto_keys::{Ed25519Pair, RistrettoPrivate};
+    use mc_transaction_core::{account_keys::AccountKey, compute_block_id, BLOCK_VERSION};
     use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
     use rand_core::RngCore;
@@ -853,6 +1228,293 @@ mod ledger_db_test {
         assert_eq!(key_images, returned_key_images);
     }
 
+    #[test]
+    // Collected block signatures are stored once per signer, and must be valid for the block.
+    fn test_collected_block_signatures() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+        let (blocks, _block_contents) = populate_db(&mut ledger_db, 3, 1);
+
+        let signer_a = Ed25519Pair::from_random(&mut rng);
+        let signer_b = Ed25519Pair::from_random(&mut rng);
+        let sign = |block: &Block, signer: &Ed25519Pair| {
+            BlockSignature::from_block_and_keypair(block, signer).unwrap()
+        };
+
+        assert_eq!(
+            ledger_db.get_collected_block_signatures(1).unwrap(),
+            Vec::new()
+        );
+
+        ledger_db
+            .add_collected_block_signatures(1, &[sign(&blocks[1], &signer_a)])
+            .unwrap();
+        // A second signature by `signer_a` is ignored.
+        ledger_db
+            .add_collected_block_signatures(
+                1,
+                &[sign(&blocks[1], &signer_b), sign(&blocks[1], &signer_a)],
+            )
+            .unwrap();
+        assert_eq!(
+            ledger_db.get_collected_block_signatures(1).unwrap(),
+            vec![sign(&blocks[1], &signer_a), sign(&blocks[1], &signer_b)]
+        );
+
+        // Signatures over another block are rejected.
+        assert_eq!(
+            ledger_db.add_collected_block_signatures(2, &[sign(&blocks[1], &signer_a)]),
+            Err(Error::InvalidBlockSignature)
+        );
+        assert_eq!(
+            ledger_db.get_collected_block_signatures(2).unwrap(),
+            Vec::new()
+        );
+
+        // Signatures over a block that is not in the ledger are rejected.
+        assert_eq!(
+            ledger_db.add_collected_block_signatures(3, &[sign(&blocks[1], &signer_a)]),
+            Err(Error::NotFound)
+        );
+    }
+
+    #[test]
+    // A block and its collected signatures should be appended together, or not at all.
+    fn test_append_block_with_signatures() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut source_db = create_db();
+        let (blocks, blocks_contents) = populate_db(&mut source_db, 3, 1);
+
+        let mut ledger_db = create_db();
+        for (block, block_contents) in blocks.iter().zip(&blocks_contents).take(2) {
+            ledger_db.append_block(block, block_contents, None).unwrap();
+        }
+
+        let signer_a = Ed25519Pair::from_random(&mut rng);
+        let signer_b = Ed25519Pair::from_random(&mut rng);
+        let sign = |block: &Block, signer: &Ed25519Pair| {
+            BlockSignature::from_block_and_keypair(block, signer).unwrap()
+        };
+
+        // A signature over another block fails the append.
+        assert_eq!(
+            ledger_db.append_block_with_signatures(
+                &blocks[2],
+                &blocks_contents[2],
+                None,
+                &[sign(&blocks[2], &signer_a), sign(&blocks[1], &signer_b)],
+            ),
+            Err(Error::InvalidBlockSignature)
+        );
+        assert_eq!(ledger_db.num_blocks().unwrap(), 2);
+
+        ledger_db
+            .append_block_with_signatures(
+                &blocks[2],
+                &blocks_contents[2],
+                None,
+                &[sign(&blocks[2], &signer_a), sign(&blocks[2], &signer_b)],
+            )
+            .unwrap();
+        assert_eq!(ledger_db.num_blocks().unwrap(), 3);
+        assert_eq!(
+            ledger_db.get_collected_block_signatures(2).unwrap(),
+            vec![sign(&blocks[2], &signer_a), sign(&blocks[2], &signer_b)]
+        );
+    }
+
+    #[test]
+    // Each TxOut index should map to the block containing the TxOut.
+    fn test_get_block_index_by_tx_out_index() {
+        let mut ledger_db = create_db();
+        populate_db(&mut ledger_db, 5, 3);
+
+        for tx_out_index in 0..15 {
+            assert_eq!(
+                ledger_db
+                    .get_block_index_by_tx_out_index(tx_out_index)
+                    .unwrap(),
+                tx_out_index / 3
+            );
+        }
+        assert_eq!(
+            ledger_db.get_block_index_by_tx_out_index(15),
+            Err(Error::NotFound)
+        );
+    }
+
+    #[test]
+    // A block's timestamp should be the median signing time of its signatures.
+    fn test_get_block_timestamp() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+        let (blocks, _block_contents) = populate_db(&mut ledger_db, 2, 1);
+        let sign = |block: &Block, signed_at: u64, rng: &mut StdRng| {
+            let signer = Ed25519Pair::from_random(rng);
+            BlockSignature::from_block_keypair_and_time(block, &signer, signed_at).unwrap()
+        };
+
+        // Blocks without signatures have no timestamp.
+        assert_eq!(ledger_db.get_block_timestamp(1), Ok(None));
+        assert_eq!(ledger_db.get_block_timestamp(2), Err(Error::NotFound));
+
+        // Signatures without a signing time are ignored.
+        ledger_db
+            .add_collected_block_signatures(1, &[sign(&blocks[1], 0, &mut rng)])
+            .unwrap();
+        assert_eq!(ledger_db.get_block_timestamp(1), Ok(None));
+
+        ledger_db
+            .add_collected_block_signatures(
+                1,
+                &[
+                    sign(&blocks[1], 1_590_000_300, &mut rng),
+                    sign(&blocks[1], 1_590_000_100, &mut rng),
+                ],
+            )
+            .unwrap();
+        assert_eq!(ledger_db.get_block_timestamp(1), Ok(Some(1_590_000_100)));
+
+        ledger_db
+            .add_collected_block_signatures(1, &[sign(&blocks[1], 1_590_000_200, &mut rng)])
+            .unwrap();
+        assert_eq!(ledger_db.get_block_timestamp(1), Ok(Some(1_590_000_200)));
+    }
+
+    #[test]
+    // A block appended with a signature should take its timestamp from the signature.
+    fn test_append_block_with_signed_at() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+        let (blocks, _block_contents) = populate_db(&mut ledger_db, 1, 1);
+        let account_key = AccountKey::random(&mut rng);
+
+        let outputs = vec![TxOut::new(
+            10,
+            &account_key.default_subaddress(),
+            &RistrettoPrivate::from_random(&mut rng),
+            Default::default(),
+            &mut rng,
+        )
+        .unwrap()];
+        let block_contents = BlockContents::new(vec![KeyImage::from(rng.next_u64())], outputs);
+        let block = Block::new_with_parent(
+            BLOCK_VERSION,
+            &blocks[0],
+            &Default::default(),
+            &block_contents,
+        );
+        let signer = Ed25519Pair::from_random(&mut rng);
+        let signature =
+            BlockSignature::from_block_keypair_and_time(&block, &signer, 1_590_000_000).unwrap();
+        ledger_db
+            .append_block(&block, &block_contents, Some(&signature))
+            .unwrap();
+
+        assert_eq!(ledger_db.get_block_timestamp(0), Ok(None));
+        assert_eq!(ledger_db.get_block_timestamp(1), Ok(Some(1_590_000_000)));
+    }
+
+    #[test]
+    // A block whose version records its close time should take its timestamp from the block,
+    // whatever the signing times of its signatures.
+    fn test_append_block_with_timestamp() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+        let (blocks, _block_contents) = populate_db(&mut ledger_db, 1, 1);
+        let account_key = AccountKey::random(&mut rng);
+
+        let outputs = vec![TxOut::new(
+            10,
+            &account_key.default_subaddress(),
+            &RistrettoPrivate::from_random(&mut rng),
+            Default::default(),
+            &mut rng,
+        )
+        .unwrap()];
+        let block_contents = BlockContents::new(vec![KeyImage::from(rng.next_u64())], outputs);
+        let block = Block::new_with_parent_and_timestamp(
+            2,
+            &blocks[0],
+            &Default::default(),
+            &block_contents,
+            1_590_000_000,
+        );
+        let signer = Ed25519Pair::from_random(&mut rng);
+        let signature =
+            BlockSignature::from_block_keypair_and_time(&block, &signer, 1_590_000_100).unwrap();
+        ledger_db
+            .append_block(&block, &block_contents, Some(&signature))
+            .unwrap();
+
+        assert_eq!(ledger_db.get_block_timestamp(1), Ok(Some(1_590_000_000)));
+    }
+
+    #[test]
+    // Migrating a database created before blocks were indexed should index them. Opening it
+    // should fail until it is migrated.
+    fn test_migrate_block_indexes() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let temp_dir = TempDir::new("test").unwrap();
+        let path = temp_dir.path().to_path_buf();
+        LedgerDB::create(path.clone()).unwrap();
+        let mut ledger_db = LedgerDB::open(path.clone()).unwrap();
+        let (blocks, _block_contents) = populate_db(&mut ledger_db, 3, 2);
+
+        let signer = Ed25519Pair::from_random(&mut rng);
+        let signature =
+            BlockSignature::from_block_keypair_and_time(&blocks[2], &signer, 1_590_000_000)
+                .unwrap();
+        ledger_db
+            .add_collected_block_signatures(2, &[signature])
+            .unwrap();
+
+        // Revert the database to the format without the indexes.
+        {
+            let mut db_transaction = ledger_db.env.begin_rw_txn().unwrap();
+            db_transaction
+                .clear_db(ledger_db.block_number_by_tx_out_index)
+                .unwrap();
+            db_transaction.clear_db(ledger_db.block_timestamps).unwrap();
+            let version = MetadataVersion {
+                database_format_version: BLOCK_INDEXES_MIGRATION_VERSION,
+                ..MetadataVersion::latest()
+            };
+            ledger_db
+                .metadata_store
+                .set_version(&mut db_transaction, &version)
+                .unwrap();
+            db_transaction.commit().unwrap();
+        }
+        drop(ledger_db);
+
+        match LedgerDB::open(path.clone()) {
+            Err(Error::VersionIncompatible(BLOCK_INDEXES_MIGRATION_VERSION, _)) => {}
+            result => panic!("Unexpected result: {:?}", result.map(|_| ())),
+        }
+        assert_eq!(LedgerDB::migrate(path.clone()), Ok(true));
+        assert_eq!(LedgerDB::migrate(path.clone()), Ok(false));
+
+        let ledger_db = LedgerDB::open(path).unwrap();
+        for tx_out_index in 0..6 {
+            assert_eq!(
+                ledger_db
+                    .get_block_index_by_tx_out_index(tx_out_index)
+                    .unwrap(),
+                tx_out_index / 2
+            );
+        }
+        assert_eq!(ledger_db.get_block_timestamp(1), Ok(None));
+        assert_eq!(ledger_db.get_block_timestamp(2), Ok(Some(1_590_000_000)));
+
+        let db_transaction = ledger_db.env.begin_ro_txn().unwrap();
+        let version = ledger_db
+            .metadata_store
+            .get_version(&db_transaction)
+            .unwrap();
+        assert_eq!(version.database_format_version, metadata::LATEST_VERSION);
+    }
+
     #[test]
     /// Attempting to append an empty block should return Error::InvalidBlock.
     fn test_append_empty_block() {
@@ -890,7 +1552,7 @@ mod ledger_db_test {
     }
 
     #[test]
-    /// Appending an block of incorrect version should return Error::InvalidBlock.
+    /// Appending an block of unsupported version should return Error::UnsupportedBlockVersion.
     fn test_append_block_with_invalid_version() {
         let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
         let mut ledger_db = create_db();
@@ -908,12 +1570,81 @@ mod ledger_db_test {
             block.cumulative_txo_count,
             &block.root_element,
             &block.contents_hash,
+            block.timestamp,
+        );
+
+        assert_eq!(
+            ledger_db.append_block(&block, &block_contents, None),
+            Err(Error::UnsupportedBlockVersion(wrong_version))
+        );
+    }
+
+    #[test]
+    /// Block versions may increase, but not decrease, and each block must follow the rules of its
+    /// version.
+    fn test_append_block_across_version_upgrade() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+        let account_key = AccountKey::random(&mut rng);
+
+        let (origin_block, origin_block_contents) = get_origin_block_and_contents(&account_key);
+        ledger_db
+            .append_block(&origin_block, &origin_block_contents, None)
+            .unwrap();
+
+        let mut get_block_contents = || {
+            let outputs: Vec<TxOut> = (0..4)
+                .map(|_i| {
+                    TxOut::new(
+                        10,
+                        &account_key.default_subaddress(),
+                        &RistrettoPrivate::from_random(&mut rng),
+                        Default::default(),
+                        &mut rng,
+                    )
+                    .unwrap()
+                })
+                .collect();
+            let key_images: Vec<KeyImage> =
+                (0..4).map(|_i| KeyImage::from(rng.next_u64())).collect();
+            BlockContents::new(key_images, outputs)
+        };
+
+        // A version 1 block with unsorted contents is rejected.
+        let mut unsorted_contents = get_block_contents();
+        unsorted_contents.sort();
+        unsorted_contents.outputs.reverse();
+        assert!(!unsorted_contents.is_sorted());
+        let block =
+            Block::new_with_parent(1, &origin_block, &Default::default(), &unsorted_contents);
+        assert_eq!(
+            ledger_db.append_block(&block, &unsorted_contents, None),
+            Err(Error::InvalidBlockContents)
         );
 
+        // A version 1 block with sorted contents is accepted.
+        let mut block_contents = get_block_contents();
+        block_contents.sort();
+        let v1_block =
+            Block::new_with_parent(1, &origin_block, &Default::default(), &block_contents);
+        ledger_db
+            .append_block(&v1_block, &block_contents, None)
+            .unwrap();
+        assert_eq!(ledger_db.get_block(1).unwrap(), v1_block);
+
+        // The version can not decrease after the upgrade.
+        let block_contents = get_block_contents();
+        let block = Block::new_with_parent(
+            BLOCK_VERSION,
+            &v1_block,
+            &Default::default(),
+            &block_contents,
+        );
         assert_eq!(
             ledger_db.append_block(&block, &block_contents, None),
             Err(Error::InvalidBlock)
         );
+        assert_eq!(ledger_db.num_blocks().unwrap(), 2);
     }
 
     #[test]
@@ -1,5 +1,5 @@
 use crate::Error;
-use lmdb::{Database, DatabaseFlags, Environment, Transaction, WriteFlags};
+use lmdb::{Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
 use mc_util_serial::{decode, encode};
 use prost::Message;
 
@@ -7,7 +7,12 @@ use prost::Message;
 // If this is properly maintained, we could check during ledger db opening for any
 // incompatibilities, and either refuse to open or perform a migration.
 #[allow(clippy::unreadable_literal)]
-pub const LATEST_VERSION: u64 = 20200427;
+pub const LATEST_VERSION: u64 = 20200610;
+
+// Version of databases created before blocks were indexed by TxOut index and timestamp. They are
+// migrated to the latest version by `LedgerDB::migrate`.
+#[allow(clippy::unreadable_literal)]
+pub const BLOCK_INDEXES_MIGRATION_VERSION: u64 = 20200427;
 
 // Metadata information about the ledger databse.
 #[derive(Clone, Message)]
@@ -85,4 +90,19 @@ impl MetadataStore {
     pub fn get_version(&self, db_txn: &impl Transaction) -> Result<MetadataVersion, Error> {
         Ok(decode(db_txn.get(self.metadata, &METADATA_VERSION_KEY)?)?)
     }
+
+    // Set version data, e.g. after a migration.
+    pub fn set_version(
+        &self,
+        db_txn: &mut RwTransaction,
+        version: &MetadataVersion,
+    ) -> Result<(), Error> {
+        db_txn.put(
+            self.metadata,
+            &METADATA_VERSION_KEY,
+            &encode(version),
+            WriteFlags::empty(),
+        )?;
+        Ok(())
+    }
 }
@@ -0,0 +1,549 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Snapshots of the ledger at a given block height, used to bootstrap a new ledger without
+//! replaying every block from the network.
+//!
+//! A snapshot is a stream of length-prefixed records: a header, followed by one record per block
+//! holding its header, key images, TxOuts and signatures. Snapshots are written and read one block
+//! at a time, so that they never need to fit in memory.
+//!
+//! The header names the last block and its signatures, so that the caller can decide whether the
+//! snapshot is trusted, e.g. by checking those signatures, before reading the rest. It also holds
+//! a hash of every block record that follows it, which is checked as the blocks are read, so that
+//! the header the caller trusted covers the whole snapshot. A snapshot is only imported once every
+//! block has been checked against the chain of block IDs: every header must link to its parent and
+//! have a valid ID, the contents rebuilt from the snapshot must hash to each header's
+//! `contents_hash`, and the chain must end at the block named in the header. Merkle hashes are not
+//! shipped; they are recomputed from the TxOuts on import.
+
+use crate::{Error, Ledger};
+use digest::Input;
+use mc_transaction_core::{
+    blake2b_256::Blake2b256, ring_signature::KeyImage, tx::TxOut, Block, BlockContents, BlockID,
+    BlockSignature,
+};
+use mc_util_serial::{decode, encode, Message};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Version of the snapshot file format.
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 3;
+
+/// Maximal size of a record, in bytes.
+const MAX_RECORD_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Size of the length that prefixes each record, in bytes.
+const RECORD_LEN_BYTES: usize = 8;
+
+/// The first record of a snapshot.
+#[derive(Clone, PartialEq, Message)]
+pub struct SnapshotHeader {
+    #[prost(uint32, tag = "1")]
+    format_version: u32,
+
+    /// Number of blocks in the snapshot.
+    #[prost(uint64, tag = "2")]
+    num_blocks: u64,
+
+    /// The last block in the snapshot, if any.
+    #[prost(message, optional, tag = "3")]
+    last_block: Option<Block>,
+
+    /// All signatures over the last block.
+    #[prost(message, repeated, tag = "4")]
+    last_block_signatures: Vec<BlockSignature>,
+
+    /// Blake2b256 hash of the block records that follow the header.
+    #[prost(bytes, tag = "5")]
+    content_hash: Vec<u8>,
+}
+
+impl SnapshotHeader {
+    /// Number of blocks in the snapshot.
+    pub fn num_blocks(&self) -> u64 {
+        self.num_blocks
+    }
+
+    /// The last block in the snapshot, if any.
+    pub fn last_block(&self) -> Option<&Block> {
+        self.last_block.as_ref()
+    }
+
+    /// All signatures over the last block. These are not checked until the snapshot is read.
+    pub fn last_block_signatures(&self) -> &[BlockSignature] {
+        &self.last_block_signatures
+    }
+
+    /// Blake2b256 hash of the block records that follow the header. This is checked as the blocks
+    /// are read.
+    pub fn content_hash(&self) -> &[u8] {
+        &self.content_hash
+    }
+}
+
+/// A block in a snapshot.
+#[derive(Clone, Message)]
+pub struct SnapshotBlock {
+    /// The block header.
+    #[prost(message, required, tag = "1")]
+    pub block: Block,
+
+    /// Key images spent in the block.
+    #[prost(message, repeated, tag = "2")]
+    pub key_images: Vec<KeyImage>,
+
+    /// Signature by the node that produced the ledger, if any.
+    #[prost(message, optional, tag = "3")]
+    pub signature: Option<BlockSignature>,
+
+    /// Signatures collected from other nodes.
+    #[prost(message, repeated, tag = "4")]
+    pub collected_signatures: Vec<BlockSignature>,
+
+    /// TxOuts created in the block.
+    #[prost(message, repeated, tag = "5")]
+    pub tx_outs: Vec<TxOut>,
+}
+
+impl SnapshotBlock {
+    /// All signatures over the block.
+    pub fn signatures(&self) -> Vec<BlockSignature> {
+        self.signature
+            .iter()
+            .chain(self.collected_signatures.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// Reads a block, and its contents, from the ledger.
+    fn from_ledger<L: Ledger>(ledger: &L, block_index: u64) -> Result<Self, Error> {
+        let block = ledger.get_block(block_index)?;
+        let block_contents = ledger.get_block_contents(block_index)?;
+        let signature = match ledger.get_block_signature(block_index) {
+            Ok(signature) => Some(signature),
+            Err(Error::NotFound) => None,
+            Err(err) => return Err(err),
+        };
+        let collected_signatures = ledger.get_collected_block_signatures(block_index)?;
+
+        Ok(Self {
+            block,
+            key_images: block_contents.key_images,
+            signature,
+            collected_signatures,
+            tx_outs: block_contents.outputs,
+        })
+    }
+}
+
+/// Writes a snapshot of the first `num_blocks` blocks of `ledger`.
+///
+/// The blocks are read twice: once to hash them for the header, and once to write them.
+///
+/// # Arguments
+/// * `ledger` - The ledger to snapshot.
+/// * `num_blocks` - Number of blocks to include. Must not exceed the ledger's number of blocks.
+/// * `writer` - Where to write the snapshot.
+pub fn export_snapshot<L: Ledger, W: Write>(
+    ledger: &L,
+    num_blocks: u64,
+    mut writer: W,
+) -> Result<(), Error> {
+    if num_blocks > ledger.num_blocks()? {
+        return Err(Error::IndexOutOfBounds(num_blocks));
+    }
+
+    let (last_block, last_block_signatures) = match num_blocks.checked_sub(1) {
+        Some(last_index) => {
+            let last_block = SnapshotBlock::from_ledger(ledger, last_index)?;
+            let signatures = last_block.signatures();
+            (Some(last_block.block), signatures)
+        }
+        None => (None, Vec::new()),
+    };
+
+    let mut hasher = Blake2b256::new();
+    for block_index in 0..num_blocks {
+        hasher.input(encode_record(&SnapshotBlock::from_ledger(
+            ledger,
+            block_index,
+        )?));
+    }
+
+    writer.write_all(&encode_record(&SnapshotHeader {
+        format_version: SNAPSHOT_FORMAT_VERSION,
+        num_blocks,
+        last_block,
+        last_block_signatures,
+        content_hash: hasher.result().to_vec(),
+    }))?;
+
+    for block_index in 0..num_blocks {
+        writer.write_all(&encode_record(&SnapshotBlock::from_ledger(
+            ledger,
+            block_index,
+        )?))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads the blocks of a snapshot, one at a time.
+///
+/// The blocks are checked against the header's content hash: reading the last block fails if the
+/// records read do not match it. This does not check the blocks against the chain of block IDs;
+/// see `verify_snapshot`.
+pub struct SnapshotReader<R: Read> {
+    reader: R,
+    header: SnapshotHeader,
+    next_block_index: u64,
+    hasher: Blake2b256,
+}
+
+impl<R: Read> SnapshotReader<R> {
+    /// Reads the header of a snapshot, and checks its format version.
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let header: SnapshotHeader = decode(&read_record(&mut reader)?)?;
+        if header.format_version != SNAPSHOT_FORMAT_VERSION {
+            return Err(Error::InvalidSnapshot("unsupported format version"));
+        }
+        if header.last_block.is_some() != (header.num_blocks > 0) {
+            return Err(Error::InvalidSnapshot(
+                "header does not match its last block",
+            ));
+        }
+        let snapshot_reader = Self {
+            reader,
+            header,
+            next_block_index: 0,
+            hasher: Blake2b256::new(),
+        };
+        if snapshot_reader.header.num_blocks == 0 {
+            snapshot_reader.check_content_hash()?;
+        }
+        Ok(snapshot_reader)
+    }
+
+    /// The header of the snapshot.
+    pub fn header(&self) -> &SnapshotHeader {
+        &self.header
+    }
+
+    /// Reads the next block, and checks the content hash once the last one is read.
+    fn read_block(&mut self) -> Result<SnapshotBlock, Error> {
+        let record = read_record(&mut self.reader)?;
+        self.hasher.input(&record);
+        let snapshot_block = decode(&record[RECORD_LEN_BYTES..])?;
+        if self.next_block_index == self.header.num_blocks {
+            self.check_content_hash()?;
+        }
+        Ok(snapshot_block)
+    }
+
+    /// Checks that the records read so far match the header's content hash.
+    fn check_content_hash(&self) -> Result<(), Error> {
+        if self.hasher.clone().result().as_slice() != &self.header.content_hash[..] {
+            return Err(Error::InvalidSnapshot("content hash mismatch"));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for SnapshotReader<R> {
+    type Item = Result<SnapshotBlock, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_block_index >= self.header.num_blocks {
+            return None;
+        }
+        self.next_block_index += 1;
+        Some(self.read_block())
+    }
+}
+
+/// Checks the blocks of a snapshot as they are read, keeping only the last one.
+#[derive(Default)]
+struct SnapshotVerifier {
+    parent: Option<Block>,
+}
+
+impl SnapshotVerifier {
+    /// Checks that `snapshot_block` extends the chain, and returns its contents.
+    ///
+    /// The block must have a valid ID and link to the previous block, the contents rebuilt from
+    /// the snapshot must match its contents hash, and its signatures must be valid for it.
+    fn verify_block(&mut self, snapshot_block: &SnapshotBlock) -> Result<BlockContents, Error> {
+        let block = &snapshot_block.block;
+        let (index, expected_parent_id, txo_count_before) = match &self.parent {
+            Some(parent) => (
+                parent.index + 1,
+                parent.id.clone(),
+                parent.cumulative_txo_count,
+            ),
+            None => (0, BlockID::default(), 0),
+        };
+
+        if block.index != index || block.parent_id != expected_parent_id {
+            return Err(Error::InvalidSnapshotBlock(
+                index,
+                "does not extend the chain",
+            ));
+        }
+        if !block.is_block_id_valid() {
+            return Err(Error::InvalidSnapshotBlock(index, "has an invalid ID"));
+        }
+        if block.cumulative_txo_count.checked_sub(txo_count_before)
+            != Some(snapshot_block.tx_outs.len() as u64)
+        {
+            return Err(Error::InvalidSnapshotBlock(
+                index,
+                "has the wrong number of TxOuts",
+            ));
+        }
+
+        let block_contents = BlockContents::new(
+            snapshot_block.key_images.clone(),
+            snapshot_block.tx_outs.clone(),
+        );
+        if block_contents.hash() != block.contents_hash {
+            return Err(Error::InvalidSnapshotBlock(
+                index,
+                "contents do not match the header",
+            ));
+        }
+        if snapshot_block
+            .signatures()
+            .iter()
+            .any(|signature| signature.verify(block).is_err())
+        {
+            return Err(Error::InvalidSnapshotBlock(
+                index,
+                "has an invalid signature",
+            ));
+        }
+
+        self.parent = Some(block.clone());
+        Ok(block_contents)
+    }
+
+    /// Checks that the chain ends at the last block named in `header`, and that the header's
+    /// signatures are valid for it.
+    fn finish(&self, header: &SnapshotHeader) -> Result<(), Error> {
+        if self.parent != header.last_block {
+            return Err(Error::InvalidSnapshot(
+                "chain does not end at the last block in the header",
+            ));
+        }
+        if let Some(last_block) = &self.parent {
+            if header
+                .last_block_signatures
+                .iter()
+                .any(|signature| signature.verify(last_block).is_err())
+            {
+                return Err(Error::InvalidSnapshot("header has an invalid signature"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a snapshot, and checks every block against the chain of block IDs.
+///
+/// Returns the snapshot's header.
+pub fn verify_snapshot<R: Read>(reader: R) -> Result<SnapshotHeader, Error> {
+    let mut snapshot_reader = SnapshotReader::new(reader)?;
+    let mut verifier = SnapshotVerifier::default();
+    for snapshot_block in &mut snapshot_reader {
+        verifier.verify_block(&snapshot_block?)?;
+    }
+    verifier.finish(snapshot_reader.header())?;
+    Ok(snapshot_reader.header)
+}
+
+/// Verifies a snapshot, and appends its blocks to an empty ledger.
+///
+/// The snapshot is read twice: once to verify every block, and once to import them, so that
+/// nothing is appended from a snapshot that fails verification. Blocks are checked again while
+/// importing.
+///
+/// # Arguments
+/// * `reader` - The snapshot.
+/// * `ledger` - An empty ledger.
+/// * `expected_header` - The header the caller decided to trust, e.g. from `SnapshotReader`.
+pub fn import_snapshot<R: Read + Seek, L: Ledger>(
+    mut reader: R,
+    ledger: &mut L,
+    expected_header: &SnapshotHeader,
+) -> Result<(), Error> {
+    if ledger.num_blocks()? != 0 {
+        return Err(Error::InvalidSnapshot(
+            "snapshots can only be imported into an empty ledger",
+        ));
+    }
+
+    reader.seek(SeekFrom::Start(0))?;
+    if verify_snapshot(&mut reader)? != *expected_header {
+        return Err(Error::InvalidSnapshot("header changed"));
+    }
+
+    reader.seek(SeekFrom::Start(0))?;
+    let mut snapshot_reader = SnapshotReader::new(&mut reader)?;
+    if snapshot_reader.header() != expected_header {
+        return Err(Error::InvalidSnapshot("header changed"));
+    }
+    let mut verifier = SnapshotVerifier::default();
+    for snapshot_block in &mut snapshot_reader {
+        let snapshot_block = snapshot_block?;
+        let block_contents = verifier.verify_block(&snapshot_block)?;
+        ledger.append_block_with_signatures(
+            &snapshot_block.block,
+            &block_contents,
+            snapshot_block.signature.as_ref(),
+            &snapshot_block.collected_signatures,
+        )?;
+    }
+    verifier.finish(expected_header)
+}
+
+/// Encodes a message as a record: its length, followed by the message.
+fn encode_record<M: Message>(message: &M) -> Vec<u8> {
+    let bytes = encode(message);
+    let mut record = (bytes.len() as u64).to_le_bytes().to_vec();
+    record.extend_from_slice(&bytes);
+    record
+}
+
+/// Reads a record written by `encode_record`, including its length.
+fn read_record<R: Read>(reader: &mut R) -> Result<Vec<u8>, Error> {
+    let mut len_bytes = [0u8; RECORD_LEN_BYTES];
+    reader.read_exact(&mut len_bytes)?;
+    let len = u64::from_le_bytes(len_bytes);
+    if len > MAX_RECORD_BYTES {
+        return Err(Error::InvalidSnapshot("record too large"));
+    }
+
+    let mut record = len_bytes.to_vec();
+    record.resize(RECORD_LEN_BYTES + len as usize, 0);
+    reader.read_exact(&mut record[RECORD_LEN_BYTES..])?;
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{get_mock_ledger, MockLedger};
+    use std::io::Cursor;
+
+    /// Serializes a header and blocks, as `export_snapshot` would, but keeping the header's
+    /// content hash.
+    fn to_bytes(header: &SnapshotHeader, blocks: &[SnapshotBlock]) -> Vec<u8> {
+        let mut bytes = encode_record(header);
+        for block in blocks {
+            bytes.extend(encode_record(block));
+        }
+        bytes
+    }
+
+    /// Serializes a header and blocks, with a content hash matching the blocks.
+    fn to_rehashed_bytes(header: &SnapshotHeader, blocks: &[SnapshotBlock]) -> Vec<u8> {
+        let mut hasher = Blake2b256::new();
+        for block in blocks {
+            hasher.input(encode_record(block));
+        }
+        let mut header = header.clone();
+        header.content_hash = hasher.result().to_vec();
+        to_bytes(&header, blocks)
+    }
+
+    /// Reads back the header and blocks of a snapshot.
+    fn from_bytes(bytes: &[u8]) -> (SnapshotHeader, Vec<SnapshotBlock>) {
+        let snapshot_reader = SnapshotReader::new(bytes).unwrap();
+        let header = snapshot_reader.header().clone();
+        let blocks = snapshot_reader.map(Result::unwrap).collect();
+        (header, blocks)
+    }
+
+    #[test]
+    // A snapshot should be readable block by block, and import into an identical ledger.
+    fn test_export_and_import() {
+        let ledger = get_mock_ledger(5);
+        let mut bytes = Vec::new();
+        export_snapshot(&ledger, 3, &mut bytes).unwrap();
+
+        let header = SnapshotReader::new(&bytes[..]).unwrap().header().clone();
+        assert_eq!(header.num_blocks(), 3);
+        assert_eq!(header.last_block(), Some(&ledger.get_block(2).unwrap()));
+        assert_eq!(verify_snapshot(&bytes[..]).unwrap(), header);
+
+        let mut imported = MockLedger::default();
+        import_snapshot(Cursor::new(&bytes), &mut imported, &header).unwrap();
+
+        assert_eq!(imported.num_blocks().unwrap(), 3);
+        for block_index in 0..3 {
+            assert_eq!(
+                imported.get_block(block_index).unwrap(),
+                ledger.get_block(block_index).unwrap()
+            );
+            assert_eq!(
+                imported.get_block_contents(block_index).unwrap(),
+                ledger.get_block_contents(block_index).unwrap()
+            );
+        }
+
+        // Importing into a non-empty ledger is refused.
+        assert!(import_snapshot(Cursor::new(&bytes), &mut imported, &header).is_err());
+
+        // Exporting more blocks than the ledger holds is refused.
+        assert!(export_snapshot(&ledger, 6, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    // Snapshots that do not match their content hash or block headers should be rejected, without
+    // importing anything.
+    fn test_rejects_tampered_snapshots() {
+        let ledger = get_mock_ledger(3);
+        let mut bytes = Vec::new();
+        export_snapshot(&ledger, 3, &mut bytes).unwrap();
+        let (header, blocks) = from_bytes(&bytes);
+        assert!(verify_snapshot(&bytes[..]).is_ok());
+
+        // A truncated snapshot.
+        assert!(verify_snapshot(&bytes[..bytes.len() - 1]).is_err());
+
+        // Blocks that do not match the header's content hash.
+        let mut tampered_header = header.clone();
+        tampered_header.content_hash = vec![0u8; 32];
+        let tampered_bytes = to_bytes(&tampered_header, &blocks);
+        assert!(SnapshotReader::new(&tampered_bytes[..])
+            .unwrap()
+            .any(|block| block.is_err()));
+        assert!(verify_snapshot(&tampered_bytes[..]).is_err());
+
+        // TxOuts that do not match the contents hash.
+        let mut tampered = blocks.clone();
+        tampered[2].tx_outs = blocks[1].tx_outs.clone();
+        let tampered_bytes = to_bytes(&header, &tampered);
+        assert!(verify_snapshot(&to_rehashed_bytes(&header, &tampered)[..]).is_err());
+        assert!(verify_snapshot(&tampered_bytes[..]).is_err());
+        let mut imported = MockLedger::default();
+        assert!(import_snapshot(Cursor::new(&tampered_bytes), &mut imported, &header).is_err());
+        assert_eq!(imported.num_blocks().unwrap(), 0);
+
+        // A block that does not extend the chain.
+        let mut tampered = blocks.clone();
+        tampered.remove(1);
+        let mut tampered_header = header.clone();
+        tampered_header.num_blocks = 2;
+        assert!(verify_snapshot(&to_rehashed_bytes(&tampered_header, &tampered)[..]).is_err());
+
+        // A chain that does not end at the last block in the header.
+        let mut tampered_header = header.clone();
+        tampered_header.num_blocks = 2;
+        assert!(verify_snapshot(&to_rehashed_bytes(&tampered_header, &blocks[..2])[..]).is_err());
+
+        // A header that differs from the one the caller trusted.
+        let mut trusted_header = header;
+        trusted_header.last_block = Some(blocks[1].block.clone());
+        let mut imported = MockLedger::default();
+        assert!(import_snapshot(Cursor::new(&bytes), &mut imported, &trusted_header).is_err());
+    }
+}
@@ -25,6 +25,7 @@ pub struct MockLedgerInner {
     pub membership_proofs: HashMap<u64, TxOutMembershipProof>,
     pub key_images_by_block_number: HashMap<u64, Vec<KeyImage>>,
     pub key_images: HashMap<KeyImage, u64>,
+    pub collected_block_signatures: HashMap<u64, Vec<BlockSignature>>,
 }
 
 #[derive(Clone)]
@@ -43,6 +44,7 @@ impl Default for MockLedger {
                 membership_proofs: HashMap::default(),
                 key_images_by_block_number: HashMap::default(),
                 key_images: HashMap::default(),
+                collected_block_signatures: HashMap::default(),
             })),
         }
     }
@@ -97,6 +99,26 @@ impl Ledger for MockLedger {
         Ok(())
     }
 
+    fn append_block_with_signatures(
+        &mut self,
+        block: &Block,
+        block_contents: &BlockContents,
+        signature: Option<&BlockSignature>,
+        collected_signatures: &[BlockSignature],
+    ) -> Result<(), Error> {
+        if collected_signatures
+            .iter()
+            .any(|signature| signature.verify(block).is_err())
+        {
+            return Err(Error::InvalidBlockSignature);
+        }
+        self.append_block(block, block_contents, signature)?;
+        if !collected_signatures.is_empty() {
+            self.add_collected_block_signatures(block.index, collected_signatures)?;
+        }
+        Ok(())
+    }
+
     fn num_blocks(&self) -> Result<u64, Error> {
         Ok(self.lock().blocks_by_block_number.len() as u64)
     }
@@ -125,6 +147,48 @@ impl Ledger for MockLedger {
         Err(Error::NotFound)
     }
 
+    fn get_collected_block_signatures(
+        &self,
+        block_number: u64,
+    ) -> Result<Vec<BlockSignature>, Error> {
+        Ok(self
+            .lock()
+            .collected_block_signatures
+            .get(&block_number)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn add_collected_block_signatures(
+        &mut self,
+        block_number: u64,
+        signatures: &[BlockSignature],
+    ) -> Result<(), Error> {
+        let mut inner = self.lock();
+        let block = inner
+            .blocks_by_block_number
+            .get(&block_number)
+            .cloned()
+            .ok_or(Error::NotFound)?;
+        if signatures
+            .iter()
+            .any(|signature| signature.verify(&block).is_err())
+        {
+            return Err(Error::InvalidBlockSignature);
+        }
+
+        let stored = inner
+            .collected_block_signatures
+            .entry(block_number)
+            .or_insert_with(Vec::new);
+        for signature in signatures {
+            if stored.iter().all(|s| s.signer() != signature.signer()) {
+                stored.push(signature.clone());
+            }
+        }
+        Ok(())
+    }
+
     fn get_tx_out_index_by_hash(&self, _tx_out_hash: &[u8; 32]) -> Result<u64, Error> {
         // Unused for these tests.
         unimplemented!()
@@ -235,6 +299,7 @@ pub fn get_test_ledger_blocks(n_blocks: usize) -> Vec<(Block, BlockContents)> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use mc_crypto_keys::Ed25519Pair;
     use mc_transaction_core::compute_block_id;
 
     #[test]
@@ -274,6 +339,7 @@ mod tests {
                 block.cumulative_txo_count,
                 &block.root_element,
                 &block.contents_hash,
+                block.timestamp,
             );
             assert_eq!(block.id, derived_block_id);
         }
@@ -283,4 +349,37 @@ mod tests {
             assert_eq!(block.contents_hash, block_contents.hash());
         }
     }
+
+    #[test]
+    // The default implementations of the block indexes should use the block headers and the
+    // collected signatures.
+    fn test_default_block_indexes() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut mock_ledger = get_mock_ledger(3);
+
+        // Each block of the mock ledger has one output.
+        for tx_out_index in 0..3 {
+            assert_eq!(
+                mock_ledger
+                    .get_block_index_by_tx_out_index(tx_out_index)
+                    .unwrap(),
+                tx_out_index
+            );
+        }
+        assert_eq!(
+            mock_ledger.get_block_index_by_tx_out_index(3),
+            Err(Error::NotFound)
+        );
+
+        let block = mock_ledger.get_block(1).unwrap();
+        let signer = Ed25519Pair::from_random(&mut rng);
+        let signature =
+            BlockSignature::from_block_keypair_and_time(&block, &signer, 1_590_000_000).unwrap();
+        mock_ledger
+            .add_collected_block_signatures(1, &[signature])
+            .unwrap();
+        assert_eq!(mock_ledger.get_block_timestamp(0), Ok(None));
+        assert_eq!(mock_ledger.get_block_timestamp(1), Ok(Some(1_590_000_000)));
+        assert_eq!(mock_ledger.get_block_timestamp(3), Err(Error::NotFound));
+    }
 }
@@ -270,6 +270,54 @@ impl TxOutStore {
         db_transaction: &T,
     ) -> Result<TxOutMembershipProof, Error> {
         let num_tx_outs = self.num_tx_outs(db_transaction)?;
+        self.merkle_proof_of_membership(index, num_tx_outs, &HashMap::default(), db_transaction)
+    }
+
+    /// Updates a proof-of-membership to the current state of the Merkle tree.
+    ///
+    /// The hash of a range only changes when TxOuts are added to it, so hashes in `proof` of
+    /// ranges that were already full when the proof was created are re-used, and only the ranges
+    /// containing TxOuts added since `proof.highest_index` are read from the database.
+    ///
+    /// # Arguments
+    /// * `proof` - A proof-of-membership created by this store. Assumed to be valid.
+    /// * `db_transaction` - an LMDB transaction.
+    pub fn update_merkle_proof_of_membership<T: Transaction>(
+        &self,
+        proof: &TxOutMembershipProof,
+        db_transaction: &T,
+    ) -> Result<TxOutMembershipProof, Error> {
+        let num_tx_outs = self.num_tx_outs(db_transaction)?;
+        if proof.highest_index >= num_tx_outs {
+            // The proof refers to TxOuts this store does not contain.
+            return Err(Error::IndexOutOfBounds(proof.highest_index));
+        }
+
+        let unchanged_hashes: HashMap<Range, [u8; 32]> = proof
+            .elements
+            .iter()
+            .filter(|element| element.range.to <= proof.highest_index)
+            .map(|element| (element.range, *element.hash.as_ref()))
+            .collect();
+
+        self.merkle_proof_of_membership(proof.index, num_tx_outs, &unchanged_hashes, db_transaction)
+    }
+
+    /// Merkle proof-of-membership for TxOut with the given index, when the tree contains
+    /// `num_tx_outs` TxOuts.
+    ///
+    /// # Arguments
+    /// * `index` - The index of the TxOut.
+    /// * `num_tx_outs` - The number of TxOuts in the tree.
+    /// * `known_hashes` - Hashes of ranges that do not need to be read from the database.
+    /// * `db_transaction` - an LMDB transaction.
+    fn merkle_proof_of_membership<T: Transaction>(
+        &self,
+        index: u64,
+        num_tx_outs: u64,
+        known_hashes: &HashMap<Range, [u8; 32]>,
+        db_transaction: &T,
+    ) -> Result<TxOutMembershipProof, Error> {
         if index >= num_tx_outs {
             return Err(Error::IndexOutOfBounds(index));
         }
@@ -299,6 +347,8 @@ impl TxOutStore {
                 // Note: Nil hashes could probably be omitted as an optimization if validation
                 // knows that it must supply them for any range where `low >= num_tx_outs`.
                 *NIL_HASH
+            } else if let Some(hash) = known_hashes.get(&range) {
+                *hash
             } else {
                 self.get_merkle_hash(&range, db_transaction)?
             };
@@ -720,10 +770,10 @@ pub mod tx_out_store_tests {
         account_keys::AccountKey,
         amount::Amount,
         encrypted_fog_hint::EncryptedFogHint,
-        membership_proofs::{hash_leaf, hash_nodes, NIL_HASH},
+        membership_proofs::{hash_leaf, hash_nodes, is_membership_proof_valid, NIL_HASH},
         onetime_keys::*,
         range::Range,
-        tx::TxOut,
+        tx::{TxOut, TxOutMembershipProof},
     };
     use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
@@ -1166,6 +1216,76 @@ pub mod tx_out_store_tests {
         }
     }
 
+    #[test]
+    // An updated proof should equal a proof computed from scratch at the new state of the tree.
+    fn test_update_merkle_proof_of_membership() {
+        let (tx_out_store, env) = init_tx_out_store();
+        let tx_outs = get_tx_outs(40);
+
+        let mut rw_transaction = env.begin_rw_txn().unwrap();
+        for tx_out in &tx_outs[..5] {
+            tx_out_store.push(tx_out, &mut rw_transaction).unwrap();
+        }
+        rw_transaction.commit().unwrap();
+
+        let initial_proofs: Vec<TxOutMembershipProof> = {
+            let db_transaction = env.begin_ro_txn().unwrap();
+            (0..5)
+                .map(|index| {
+                    tx_out_store
+                        .get_merkle_proof_of_membership(index, &db_transaction)
+                        .unwrap()
+                })
+                .collect()
+        };
+
+        // Grow the tree, including past the next power of two.
+        for num_tx_outs in &[6, 8, 9, 40] {
+            let mut rw_transaction = env.begin_rw_txn().unwrap();
+            let current = tx_out_store.num_tx_outs(&rw_transaction).unwrap() as usize;
+            for tx_out in &tx_outs[current..*num_tx_outs] {
+                tx_out_store.push(tx_out, &mut rw_transaction).unwrap();
+            }
+            rw_transaction.commit().unwrap();
+
+            let db_transaction = env.begin_ro_txn().unwrap();
+            let root_hash = tx_out_store.get_root_merkle_hash(&db_transaction).unwrap();
+            for initial_proof in &initial_proofs {
+                let updated_proof = tx_out_store
+                    .update_merkle_proof_of_membership(initial_proof, &db_transaction)
+                    .unwrap();
+                let expected_proof = tx_out_store
+                    .get_merkle_proof_of_membership(initial_proof.index, &db_transaction)
+                    .unwrap();
+                assert_eq!(updated_proof, expected_proof);
+
+                let tx_out = &tx_outs[initial_proof.index as usize];
+                assert!(is_membership_proof_valid(tx_out, &updated_proof, &root_hash).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    // `update_merkle_proof_of_membership` should reject proofs of a larger tree.
+    fn test_update_merkle_proof_of_membership_errors() {
+        let (tx_out_store, env) = init_tx_out_store();
+        let mut rw_transaction = env.begin_rw_txn().unwrap();
+        for tx_out in &get_tx_outs(4) {
+            tx_out_store.push(tx_out, &mut rw_transaction).unwrap();
+        }
+        rw_transaction.commit().unwrap();
+
+        let db_transaction = env.begin_ro_txn().unwrap();
+        let mut proof = tx_out_store
+            .get_merkle_proof_of_membership(1, &db_transaction)
+            .unwrap();
+        proof.highest_index = 9;
+        assert_eq!(
+            tx_out_store.update_merkle_proof_of_membership(&proof, &db_transaction),
+            Err(Error::IndexOutOfBounds(9))
+        );
+    }
+
     #[test]
     // `get_merkle_proof_of_membership` should return an error if the TxOut index is out of bounds.
     fn test_get_merkle_proof_of_membership_errors() {
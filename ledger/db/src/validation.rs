@@ -0,0 +1,78 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Validates a transaction against the local ledger without submitting it to the network.
+//!
+//! This mirrors the checks a consensus node performs when a transaction is proposed: the untrusted
+//! key image and membership proof lookups, followed by `mc_transaction_core::validation`. Unlike the
+//! consensus path, every failing check is reported, which lets clients pinpoint all of the problems
+//! with a transaction before submitting it.
+
+use crate::{Error, Ledger};
+use mc_transaction_core::{
+    tx::Tx,
+    validation::{validate_all, validate_features, TransactionValidationError},
+    BlockVersionSchedule, NetworkParameters,
+};
+use rand_core::{CryptoRng, RngCore};
+
+/// Validates `tx` against the current state of `ledger`, returning every failing check.
+///
+/// An empty result means the transaction would currently be accepted by a consensus node whose
+/// ledger matches `ledger`. An error is only returned if the ledger itself cannot be read.
+///
+/// # Arguments
+/// * `ledger` - The local copy of the ledger.
+/// * `tx` - The transaction to validate.
+/// * `parameters` - Limits of the network `ledger` belongs to.
+/// * `block_version_schedule` - Block versions of the network `ledger` belongs to.
+/// * `rng` - Cryptographically secure random number generator.
+pub fn validate_tx<L: Ledger, R: RngCore + CryptoRng>(
+    ledger: &L,
+    tx: &Tx,
+    parameters: &NetworkParameters,
+    block_version_schedule: &BlockVersionSchedule,
+    rng: &mut R,
+) -> Result<Vec<TransactionValidationError>, Error> {
+    let mut errors = Vec::new();
+
+    // The `key_images` must not have already been spent.
+    let mut contains_spent_key_image = false;
+    for key_image in tx.key_images() {
+        match ledger.contains_key_image(&key_image) {
+            Ok(spent) => contains_spent_key_image |= spent,
+            Err(e) => errors.push(TransactionValidationError::Ledger(e.to_string())),
+        }
+    }
+    if contains_spent_key_image {
+        errors.push(TransactionValidationError::ContainsSpentKeyImage);
+    }
+
+    // If the ledger cannot provide proofs for the highest indices referenced by the transaction,
+    // validation proceeds without them and reports `InvalidLedgerContext`.
+    let highest_indices = tx.get_membership_proof_highest_indices();
+    let root_proofs = match ledger.get_tx_out_proof_of_memberships(&highest_indices) {
+        Ok(proofs) => proofs,
+        Err(e) => {
+            errors.push(TransactionValidationError::Ledger(e.to_string()));
+            Vec::new()
+        }
+    };
+
+    let current_block_index = ledger.num_blocks()?;
+
+    // The transaction would be included in the next block, which enables the features of the
+    // version scheduled for it.
+    let block_version = block_version_schedule.version_at(current_block_index);
+    if let Err(e) = validate_features(tx, block_version) {
+        errors.push(e);
+    }
+
+    errors.extend(validate_all(
+        tx,
+        current_block_index,
+        &root_proofs,
+        parameters,
+        rng,
+    ));
+    Ok(errors)
+}
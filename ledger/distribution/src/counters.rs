@@ -0,0 +1,31 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+use lazy_static;
+use mc_util_metrics::{Histogram, IntCounter, IntGauge, OpMetrics};
+
+lazy_static::lazy_static! {
+    pub static ref OP_COUNTERS: OpMetrics = OpMetrics::new_and_registered("ledger_distribution");
+}
+
+lazy_static::lazy_static! {
+    // Blocks uploaded since this process started.
+    pub static ref BLOCKS_UPLOADED_COUNT: IntCounter = OP_COUNTERS.counter("blocks_uploaded_count");
+
+    // Uploads that failed after exhausting all write attempts.
+    pub static ref UPLOAD_FAILURES_COUNT: IntCounter = OP_COUNTERS.counter("upload_failures_count");
+
+    // Index of the next block to upload.
+    pub static ref NEXT_BLOCK: IntGauge = OP_COUNTERS.gauge("next_block");
+
+    // Number of blocks in the ledger that have not been uploaded yet.
+    pub static ref UPLOAD_LAG: IntGauge = OP_COUNTERS.gauge("upload_lag");
+
+    // Blocks and bundles found missing from the destination during verification.
+    pub static ref MISSING_FILES_COUNT: IntCounter = OP_COUNTERS.counter("missing_files_count");
+
+    // Blocks and bundles found corrupt at the destination during verification.
+    pub static ref CORRUPT_FILES_COUNT: IntCounter = OP_COUNTERS.counter("corrupt_files_count");
+
+    // Time it takes to upload a block.
+    pub static ref UPLOAD_BLOCK_TIME: Histogram = OP_COUNTERS.histogram("upload_block");
+}
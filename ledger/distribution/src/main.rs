@@ -1,20 +1,40 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
 //! A helper utility for collecting blocks from a local ledger file and storing them as
-//! Protobuf-serialized files on S3.
-
-pub mod uri;
-
-use crate::uri::{Destination, Uri};
-use mc_api::{blockchain, conversions::block_num_to_s3block_path};
-use mc_common::logger::{create_app_logger, log, o, Logger};
+//! Protobuf-serialized files on S3, optionally along with compressed bundles of consecutive blocks.
+//! It can also verify what is already at the destination against the ledger, and re-upload
+//! missing or corrupt files.
+
+mod counters;
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use mc_api::{
+    blockchain,
+    conversions::{
+        block_bundle_to_s3_path, block_num_to_s3block_path, s3block_path_to_block_num,
+        S3BLOCK_BUNDLES_MANIFEST_PATH,
+    },
+};
+use mc_common::{
+    logger::{create_app_logger, log, o, Logger},
+    HashMap,
+};
+use mc_ledger_block_storage::{
+    put_verified, BlockStorage, BlockStorageError, StorageUri, StoredObject,
+};
 use mc_ledger_db::{Error as LedgerDbError, Ledger, LedgerDB};
 use mc_transaction_core::{Block, BlockContents, BlockIndex, BlockSignature};
 use protobuf::Message;
-use rusoto_core::{Region, RusotoError};
-use rusoto_s3::{PutObjectError, PutObjectRequest, S3Client, S3};
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf, str::FromStr};
+use std::{
+    cmp::{max, min},
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    thread,
+    time::Duration,
+};
 use structopt::StructOpt;
 
 pub trait BlockHandler {
@@ -23,7 +43,16 @@ pub trait BlockHandler {
         block: &Block,
         block_contents: &BlockContents,
         signature: &Option<BlockSignature>,
-    );
+    ) -> Result<(), BlockStorageError>;
+
+    /// Writes a file at `path`, relative to the destination.
+    fn write_file(&mut self, path: &Path, bytes: &[u8]) -> Result<(), BlockStorageError>;
+
+    /// Reads the file at `path`, relative to the destination.
+    fn read_file(&mut self, path: &Path) -> Result<Vec<u8>, BlockStorageError>;
+
+    /// Lists every file at the destination.
+    fn list_files(&mut self) -> Result<Vec<StoredObject>, BlockStorageError>;
 }
 
 /// Block to start syncing from.
@@ -62,9 +91,15 @@ pub struct Config {
     #[structopt(long, parse(from_os_str))]
     pub ledger_path: PathBuf,
 
-    /// Destination to upload to.
+    /// Destination to upload to: `s3://<bucket>/<path>?region=<region>` (add
+    /// `&endpoint=<url>` for other S3-compatible stores), an http(s) URL accepting PUT requests,
+    /// or `file://<path>`.
     #[structopt(long = "dest")]
-    pub destination: Uri,
+    pub destination: StorageUri,
+
+    /// Number of attempts at writing each file before giving up.
+    #[structopt(long, default_value = "20")]
+    pub max_write_attempts: usize,
 
     /// Block to start from.
     #[structopt(long, default_value = "zero")]
@@ -73,6 +108,24 @@ pub struct Config {
     /// State file, defaults to ~/.mc-ledger-distribution-state
     #[structopt(long)]
     pub state_file: Option<PathBuf>,
+
+    /// Also write gzip-compressed bundles of this many consecutive blocks, and a manifest
+    /// describing them.
+    #[structopt(long)]
+    pub bundle_size: Option<u64>,
+
+    /// Before distributing new blocks, check every block (and bundle) below the starting block
+    /// against the destination, and re-upload the missing or corrupt ones.
+    #[structopt(long)]
+    pub verify: bool,
+
+    /// Exit once verification is done, instead of distributing new blocks. Implies `--verify`.
+    #[structopt(long)]
+    pub verify_only: bool,
+
+    /// Address on which to serve Prometheus metrics at `/metrics`, e.g. `0.0.0.0:9090`.
+    #[structopt(long)]
+    pub metrics_listen_addr: Option<String>,
 }
 
 /// State file contents.
@@ -81,161 +134,492 @@ pub struct StateData {
     next_block: BlockIndex,
 }
 
-/// S3 block writer.
-pub struct S3BlockWriter {
-    path: PathBuf,
-    s3_client: S3Client,
+/// Writes blocks to a block storage, reading each file back to verify it.
+pub struct StorageBlockWriter {
+    storage: Box<dyn BlockStorage>,
+    max_write_attempts: usize,
     logger: Logger,
 }
 
-impl S3BlockWriter {
-    fn new(path: PathBuf, region: Region, logger: Logger) -> S3BlockWriter {
+impl StorageBlockWriter {
+    fn new(destination: &StorageUri, max_write_attempts: usize, logger: Logger) -> Self {
         log::debug!(
             logger,
-            "Creating S3 Block Writer with path={:?} region={:?}",
-            path,
-            region
+            "Creating Storage Block Writer with destination={:?}",
+            destination.location
         );
 
-        let s3_client = S3Client::new(region);
-        S3BlockWriter {
-            path,
-            s3_client,
+        Self {
+            storage: destination.storage(),
+            max_write_attempts,
             logger,
         }
     }
-
-    fn write_bytes_to_s3(&self, path: &str, filename: &str, value: &[u8]) {
-        let result: Result<
-            retry::OperationResult<(), ()>,
-            retry::Error<retry::OperationResult<(), RusotoError<PutObjectError>>>,
-        > = retry::retry(
-            retry::delay::Exponential::from_millis(10).map(retry::delay::jitter),
-            || {
-                let req = PutObjectRequest {
-                    bucket: path.to_string(),
-                    key: String::from(filename),
-                    body: Some(value.to_vec().into()),
-                    acl: Some("public-read".to_string()),
-                    ..Default::default()
-                };
-
-                self.s3_client
-                    .put_object(req)
-                    .sync()
-                    .map(|_| retry::OperationResult::Ok(()))
-                    .map_err(|err: RusotoError<PutObjectError>| {
-                        log::warn!(
-                            self.logger,
-                            "Failed writing {}: {:?}, retrying...",
-                            filename,
-                            err
-                        );
-                        retry::OperationResult::Retry(err)
-                    })
-            },
-        );
-
-        // We should always succeed since retrying should never stop until that happens.
-        assert!(result.is_ok());
-    }
 }
 
-impl BlockHandler for S3BlockWriter {
+impl BlockHandler for StorageBlockWriter {
     fn handle_block(
         &mut self,
         block: &Block,
         block_contents: &BlockContents,
         signature: &Option<BlockSignature>,
-    ) {
-        log::info!(self.logger, "S3: Handling block {}", block.index);
+    ) -> Result<(), BlockStorageError> {
+        log::info!(self.logger, "Handling block {}", block.index);
 
-        let bc_block = blockchain::Block::from(block);
-        let bc_block_contents = blockchain::BlockContents::from(block_contents);
+        let archive_block = archive_block(block, block_contents, signature);
 
-        let mut archive_block_v1 = blockchain::ArchiveBlockV1::new();
-        archive_block_v1.set_block(bc_block);
-        archive_block_v1.set_block_contents(bc_block_contents);
+        self.write_file(
+            &block_num_to_s3block_path(block.index),
+            &archive_block
+                .write_to_bytes()
+                .expect("failed to serialize ArchiveBlock"),
+        )
+    }
 
-        if let Some(signature) = signature {
-            let bc_signature = blockchain::BlockSignature::from(signature);
-            archive_block_v1.set_signature(bc_signature);
-        }
+    fn write_file(&mut self, path: &Path, bytes: &[u8]) -> Result<(), BlockStorageError> {
+        let path = path
+            .to_str()
+            .ok_or_else(|| BlockStorageError::InvalidPath(format!("{:?}", path)))?;
+        put_verified(
+            &*self.storage,
+            path,
+            bytes,
+            self.max_write_attempts,
+            &self.logger,
+        )
+    }
 
-        let mut archive_block = blockchain::ArchiveBlock::new();
-        archive_block.set_v1(archive_block_v1);
+    fn read_file(&mut self, path: &Path) -> Result<Vec<u8>, BlockStorageError> {
+        let path = path
+            .to_str()
+            .ok_or_else(|| BlockStorageError::InvalidPath(format!("{:?}", path)))?;
+        self.storage.get(path)
+    }
 
-        let dest = self
-            .path
-            .as_path()
-            .join(block_num_to_s3block_path(block.index));
+    fn list_files(&mut self) -> Result<Vec<StoredObject>, BlockStorageError> {
+        self.storage.list("")
+    }
+}
 
-        let dir = dest.as_path().parent().expect("failed getting parent");
-        let filename = dest.file_name().unwrap();
+/// What verifying the destination found.
+#[derive(Debug, Default)]
+pub struct VerificationReport {
+    /// Number of files checked.
+    pub num_checked: u64,
 
-        self.write_bytes_to_s3(
-            dir.to_str().unwrap(),
-            filename.to_str().unwrap(),
-            &archive_block
-                .write_to_bytes()
-                .expect("failed to serialize ArchiveBlock"),
-        );
+    /// Files that were missing from the destination.
+    pub missing: Vec<PathBuf>,
+
+    /// Files whose contents did not match the ledger.
+    pub corrupt: Vec<PathBuf>,
+
+    /// Missing or corrupt files that could not be re-uploaded.
+    pub failed: Vec<PathBuf>,
+}
+
+/// Packages a block, its contents and its signature into an ArchiveBlock.
+fn archive_block(
+    block: &Block,
+    block_contents: &BlockContents,
+    signature: &Option<BlockSignature>,
+) -> blockchain::ArchiveBlock {
+    let bc_block = blockchain::Block::from(block);
+    let bc_block_contents = blockchain::BlockContents::from(block_contents);
+
+    let mut archive_block_v1 = blockchain::ArchiveBlockV1::new();
+    archive_block_v1.set_block(bc_block);
+    archive_block_v1.set_block_contents(bc_block_contents);
+
+    if let Some(signature) = signature {
+        let bc_signature = blockchain::BlockSignature::from(signature);
+        archive_block_v1.set_signature(bc_signature);
     }
+
+    let mut archive_block = blockchain::ArchiveBlock::new();
+    archive_block.set_v1(archive_block_v1);
+    archive_block
 }
 
-/// Local directory block writer.
-pub struct LocalBlockWriter {
-    path: PathBuf,
-    logger: Logger,
+/// Gets a block, its contents and its signature, if any, from the ledger.
+fn get_block_data(
+    ledger_db: &LedgerDB,
+    block_index: BlockIndex,
+    logger: &Logger,
+) -> Result<(Block, BlockContents, Option<BlockSignature>), LedgerDbError> {
+    let block = ledger_db.get_block(block_index)?;
+    let block_contents = ledger_db.get_block_contents(block_index)?;
+
+    let signature = match ledger_db.get_block_signature(block_index) {
+        Ok(signature) => Some(signature),
+        Err(LedgerDbError::NotFound) => None,
+        Err(err) => {
+            log::error!(
+                logger,
+                "Failed getting signature for block #{}: {:?}",
+                block_index,
+                err
+            );
+            None
+        }
+    };
+
+    Ok((block, block_contents, signature))
+}
+
+/// Gets the `bundle_size` blocks starting at `first_block_index` from the ledger, packaged as
+/// ArchiveBlocks.
+fn archive_blocks(
+    ledger_db: &LedgerDB,
+    first_block_index: BlockIndex,
+    bundle_size: u64,
+    logger: &Logger,
+) -> Result<blockchain::ArchiveBlocks, LedgerDbError> {
+    let mut archive_blocks = blockchain::ArchiveBlocks::new();
+    for block_index in first_block_index..first_block_index + bundle_size {
+        let (block, block_contents, signature) = get_block_data(ledger_db, block_index, logger)?;
+        archive_blocks
+            .mut_blocks()
+            .push(archive_block(&block, &block_contents, &signature));
+    }
+    Ok(archive_blocks)
+}
+
+/// Gzip-compresses bytes.
+fn compress(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes).expect("failed to compress bytes");
+    encoder.finish().expect("failed to compress bytes")
 }
 
-impl LocalBlockWriter {
-    fn new(path: PathBuf, logger: Logger) -> LocalBlockWriter {
-        log::debug!(logger, "Creating Local Block Writer with path={:?}", path,);
+/// Decompresses gzip-compressed bytes.
+fn decompress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Serializes a manifest stating that bundles of `bundle_size` blocks cover the blocks
+/// [first_block_index, num_blocks).
+fn bundles_manifest_bytes(bundle_size: u64, first_block_index: u64, num_blocks: u64) -> Vec<u8> {
+    let mut manifest = blockchain::ArchiveBlocksManifest::new();
+    manifest.set_bundle_size(bundle_size);
+    manifest.set_first_block_index(first_block_index);
+    manifest.set_num_blocks(num_blocks);
+    manifest
+        .write_to_bytes()
+        .expect("failed to serialize ArchiveBlocksManifest")
+}
 
-        LocalBlockWriter { path, logger }
+/// Writes a gzip-compressed bundle of blocks, and then a manifest stating which blocks bundles
+/// cover.
+///
+/// If this bundle extends the bundles of the manifest already at the destination, the manifest
+/// covers them all. Otherwise, e.g. when distribution starts after the last bundled block, the
+/// manifest only covers this bundle.
+///
+/// # Arguments
+/// * `block_handler` - Destination of the bundle.
+/// * `first_block_index` - First block of the bundle. Must be a multiple of the bundle size.
+/// * `archive_blocks` - The blocks of the bundle.
+/// * `logger` - Logger.
+fn write_bundle(
+    block_handler: &mut dyn BlockHandler,
+    first_block_index: BlockIndex,
+    archive_blocks: &blockchain::ArchiveBlocks,
+    logger: &Logger,
+) -> Result<(), BlockStorageError> {
+    let bundle_size = archive_blocks.get_blocks().len() as u64;
+    log::info!(
+        logger,
+        "Writing bundle of blocks [{}, {})",
+        first_block_index,
+        first_block_index + bundle_size
+    );
+
+    let bytes = archive_blocks
+        .write_to_bytes()
+        .expect("failed to serialize ArchiveBlocks");
+    block_handler.write_file(
+        &block_bundle_to_s3_path(first_block_index, bundle_size),
+        &compress(&bytes),
+    )?;
+
+    let manifest_path = Path::new(S3BLOCK_BUNDLES_MANIFEST_PATH);
+    let mut covered = (first_block_index, first_block_index + bundle_size);
+    match block_handler.read_file(manifest_path) {
+        Ok(bytes) => {
+            if let Ok(manifest) =
+                protobuf::parse_from_bytes::<blockchain::ArchiveBlocksManifest>(&bytes)
+            {
+                if manifest.get_bundle_size() == bundle_size
+                    && manifest.get_first_block_index() <= first_block_index
+                    && first_block_index <= manifest.get_num_blocks()
+                {
+                    covered = (
+                        manifest.get_first_block_index(),
+                        max(covered.1, manifest.get_num_blocks()),
+                    );
+                }
+            }
+        }
+        Err(BlockStorageError::NotFound(_)) => {}
+        Err(err) => log::warn!(logger, "Failed reading bundles manifest: {}", err),
     }
+
+    block_handler.write_file(
+        manifest_path,
+        &bundles_manifest_bytes(bundle_size, covered.0, covered.1),
+    )
 }
 
-impl BlockHandler for LocalBlockWriter {
-    fn handle_block(
-        &mut self,
-        block: &Block,
-        block_contents: &BlockContents,
-        signature: &Option<BlockSignature>,
-    ) {
-        log::info!(self.logger, "S3: Handling block {}", block.index);
+/// Uploads a block, and the bundle it completes, if any.
+fn upload_block(
+    ledger_db: &LedgerDB,
+    block_handler: &mut dyn BlockHandler,
+    block: &Block,
+    block_contents: &BlockContents,
+    signature: &Option<BlockSignature>,
+    bundle_size: Option<u64>,
+    logger: &Logger,
+) -> Result<(), BlockStorageError> {
+    let _timer = counters::UPLOAD_BLOCK_TIME.start_timer();
+
+    block_handler.handle_block(block, block_contents, signature)?;
+
+    // Write a bundle once all of its blocks are in the ledger.
+    if let Some(bundle_size) = bundle_size {
+        let num_blocks = block.index + 1;
+        if bundle_size > 0 && num_blocks % bundle_size == 0 {
+            let first_block_index = num_blocks - bundle_size;
+            let archive_blocks = archive_blocks(ledger_db, first_block_index, bundle_size, logger)
+                .expect("Failed reading bundled blocks from ledger");
+            write_bundle(block_handler, first_block_index, &archive_blocks, logger)?;
+        }
+    }
 
-        let bc_block = blockchain::Block::from(block);
-        let bc_block_contents = blockchain::BlockContents::from(block_contents);
+    Ok(())
+}
 
-        let mut archive_block_v1 = blockchain::ArchiveBlockV1::new();
-        archive_block_v1.set_block(bc_block);
-        archive_block_v1.set_block_contents(bc_block_contents);
+/// Why a file at the destination is re-uploaded.
+enum FileProblem {
+    Missing,
+    Corrupt,
+}
 
-        if let Some(signature) = signature {
-            let bc_signature = blockchain::BlockSignature::from(signature);
-            archive_block_v1.set_signature(bc_signature);
+/// Checks a file at the destination, and re-uploads `expected_bytes` if the file is missing or
+/// `is_valid` rejects its contents.
+fn verify_file(
+    block_handler: &mut dyn BlockHandler,
+    path: &Path,
+    expected_bytes: &[u8],
+    is_valid: impl Fn(&[u8]) -> bool,
+    report: &mut VerificationReport,
+    logger: &Logger,
+) {
+    report.num_checked += 1;
+
+    let problem = match block_handler.read_file(path) {
+        Ok(bytes) if is_valid(&bytes) => return,
+        Ok(_) => FileProblem::Corrupt,
+        Err(BlockStorageError::NotFound(_)) => FileProblem::Missing,
+        Err(err) => {
+            log::warn!(logger, "Failed reading {:?}: {}", path, err);
+            FileProblem::Corrupt
         }
+    };
+    repair_file(block_handler, path, expected_bytes, problem, report, logger);
+}
 
-        let mut archive_block = blockchain::ArchiveBlock::new();
-        archive_block.set_v1(archive_block_v1);
+/// Records a missing or corrupt file, and re-uploads `expected_bytes` in its place.
+fn repair_file(
+    block_handler: &mut dyn BlockHandler,
+    path: &Path,
+    expected_bytes: &[u8],
+    problem: FileProblem,
+    report: &mut VerificationReport,
+    logger: &Logger,
+) {
+    match problem {
+        FileProblem::Missing => {
+            log::warn!(logger, "{:?} is missing", path);
+            counters::MISSING_FILES_COUNT.inc();
+            report.missing.push(path.to_path_buf());
+        }
+        FileProblem::Corrupt => {
+            log::warn!(logger, "{:?} does not match the ledger", path);
+            counters::CORRUPT_FILES_COUNT.inc();
+            report.corrupt.push(path.to_path_buf());
+        }
+    }
+
+    if let Err(err) = block_handler.write_file(path, expected_bytes) {
+        log::error!(logger, "Failed re-uploading {:?}: {}", path, err);
+        counters::UPLOAD_FAILURES_COUNT.inc();
+        report.failed.push(path.to_path_buf());
+    }
+}
+
+/// Checks the blocks uploaded to the destination, and the bundles covering them, against the
+/// ledger. Missing or corrupt files are re-uploaded.
+///
+/// The blocks checked are at least the first `num_blocks`, and every block up to the last one
+/// listed at the destination. When the destination can be listed, blocks are checked against the
+/// listing: a block is missing if it is not listed, and corrupt if its size does not match the
+/// ledger. Otherwise, every block is read back. Bundles and the manifest are always read back.
+///
+/// # Arguments
+/// * `ledger_db` - Ledger containing the blocks.
+/// * `block_handler` - Destination to verify.
+/// * `num_blocks` - Number of blocks known to have been uploaded, starting from the origin block.
+/// * `bundle_size` - Size of bundles, if bundles are written.
+/// * `logger` - Logger.
+fn verify_destination(
+    ledger_db: &LedgerDB,
+    block_handler: &mut dyn BlockHandler,
+    num_blocks: u64,
+    bundle_size: Option<u64>,
+    logger: &Logger,
+) -> Result<VerificationReport, LedgerDbError> {
+    let mut report = VerificationReport::default();
+
+    // Sizes of the blocks at the destination, by block index.
+    let listed_blocks: Option<HashMap<BlockIndex, u64>> = match block_handler.list_files() {
+        Ok(objects) => Some(
+            objects
+                .into_iter()
+                .filter_map(|object| {
+                    s3block_path_to_block_num(Path::new(&object.path))
+                        .map(|block_index| (block_index, object.size))
+                })
+                .collect(),
+        ),
+        Err(err) => {
+            log::info!(
+                logger,
+                "Could not list the destination, reading every block back instead: {}",
+                err
+            );
+            None
+        }
+    };
+
+    let num_listed_blocks = listed_blocks
+        .as_ref()
+        .and_then(|listed_blocks| listed_blocks.keys().max())
+        .map_or(0, |last_block_index| last_block_index + 1);
+    let num_blocks = min(max(num_blocks, num_listed_blocks), ledger_db.num_blocks()?);
+    log::info!(
+        logger,
+        "Verifying blocks [0, {}) at the destination",
+        num_blocks
+    );
 
-        let bytes = archive_block
+    for block_index in 0..num_blocks {
+        let (block, block_contents, signature) = get_block_data(ledger_db, block_index, logger)?;
+        let expected_bytes = archive_block(&block, &block_contents, &signature)
             .write_to_bytes()
             .expect("failed to serialize ArchiveBlock");
+        let path = block_num_to_s3block_path(block_index);
+
+        match &listed_blocks {
+            Some(listed_blocks) => {
+                report.num_checked += 1;
+                let problem = match listed_blocks.get(&block_index) {
+                    Some(size) if *size == expected_bytes.len() as u64 => continue,
+                    Some(_) => FileProblem::Corrupt,
+                    None => FileProblem::Missing,
+                };
+                repair_file(
+                    block_handler,
+                    &path,
+                    &expected_bytes,
+                    problem,
+                    &mut report,
+                    logger,
+                );
+            }
+            None => verify_file(
+                block_handler,
+                &path,
+                &expected_bytes,
+                |bytes| bytes == &expected_bytes[..],
+                &mut report,
+                logger,
+            ),
+        }
+    }
+
+    if let Some(bundle_size) = bundle_size.filter(|bundle_size| *bundle_size > 0) {
+        let num_bundled_blocks = num_blocks - num_blocks % bundle_size;
 
-        let dest = self
-            .path
-            .as_path()
-            .join(block_num_to_s3block_path(block.index));
-        let dir = dest.as_path().parent().expect("failed getting parent");
+        let mut first_block_index = 0;
+        while first_block_index < num_bundled_blocks {
+            let expected_bytes = archive_blocks(ledger_db, first_block_index, bundle_size, logger)?
+                .write_to_bytes()
+                .expect("failed to serialize ArchiveBlocks");
+            // Compression is not guaranteed to be deterministic, so compare decompressed contents.
+            verify_file(
+                block_handler,
+                &block_bundle_to_s3_path(first_block_index, bundle_size),
+                &compress(&expected_bytes),
+                |bytes| decompress(bytes).ok().as_ref() == Some(&expected_bytes),
+                &mut report,
+                logger,
+            );
+            first_block_index += bundle_size;
+        }
 
-        fs::create_dir_all(dir)
-            .unwrap_or_else(|e| panic!("failed creating directory {:?}: {:?}", dir, e));
-        fs::write(&dest, bytes)
-            .unwrap_or_else(|_| panic!("failed writing block #{} to {:?}", block.index, dest));
+        if num_bundled_blocks > 0 {
+            // The manifest may already cover blocks beyond the verified ones.
+            verify_file(
+                block_handler,
+                Path::new(S3BLOCK_BUNDLES_MANIFEST_PATH),
+                &bundles_manifest_bytes(bundle_size, 0, num_bundled_blocks),
+                |bytes| {
+                    protobuf::parse_from_bytes::<blockchain::ArchiveBlocksManifest>(bytes)
+                        .map(|manifest| {
+                            manifest.get_bundle_size() == bundle_size
+                                && manifest.get_first_block_index() == 0
+                                && manifest.get_num_blocks() >= num_bundled_blocks
+                        })
+                        .unwrap_or(false)
+                },
+                &mut report,
+                logger,
+            );
+        }
     }
+
+    Ok(report)
+}
+
+/// Serves Prometheus metrics at `/metrics` from a background thread.
+fn start_metrics_server(listen_addr: &str, logger: Logger) {
+    let server = rouille::Server::new(listen_addr, |request| {
+        if request.method() != "GET" || request.url() != "/metrics" {
+            return rouille::Response::empty_404();
+        }
+
+        let metric_families = prometheus::gather();
+        let encoder = prometheus::TextEncoder::new();
+        let mut buffer = vec![];
+        encoder
+            .encode(&metric_families, &mut buffer)
+            .expect("failed encoding metrics");
+        rouille::Response::text(
+            String::from_utf8(buffer).unwrap_or_else(|_| "from_utf8 failed".to_string()),
+        )
+    })
+    .unwrap_or_else(|err| panic!("Failed creating metrics server on {}: {}", listen_addr, err));
+
+    let listen_addr = listen_addr.to_owned();
+    thread::Builder::new()
+        .name("MetricsServer".to_string())
+        .spawn(move || {
+            log::info!(logger, "Serving metrics on {}", listen_addr);
+            server.run();
+        })
+        .expect("Failed spawning metrics server");
 }
 
 // Implements the ledger db polling loop
@@ -246,6 +630,10 @@ fn main() {
     let _sentry_guard = mc_common::sentry::init();
     let (logger, _global_logger_guard) = create_app_logger(o!());
 
+    if let Some(listen_addr) = config.metrics_listen_addr.as_ref() {
+        start_metrics_server(listen_addr, logger.clone());
+    }
+
     // Get path to our state file.
     let state_file_path = config.state_file.clone().unwrap_or_else(|| {
         let mut home_dir = dirs::home_dir().unwrap_or_else(|| panic!("Unable to get home directory, please specify state file explicitly with --state-file"));
@@ -260,40 +648,65 @@ fn main() {
     let ledger_db = LedgerDB::open(config.ledger_path.clone()).expect("Could not read ledger DB");
 
     // Figure out the first block to sync from.
+    // See if the state file exists and read it if it does.
+    let state_data = if state_file_path.as_path().exists() {
+        let file_data = fs::read_to_string(&state_file_path)
+            .unwrap_or_else(|e| panic!("Failed reading state file {:?}: {:?}", state_file_path, e));
+        let state_data: StateData = serde_json::from_str(&file_data)
+            .unwrap_or_else(|e| panic!("Failed parsing state file {:?}: {:?}", state_file_path, e));
+        Some(state_data)
+    } else {
+        None
+    };
+
     let first_desired_block = match config.start_from {
         StartFrom::Zero => 0,
-        StartFrom::Next => {
-            // See if the state file exists and read it if it does.
-            if state_file_path.as_path().exists() {
-                let file_data = fs::read_to_string(&state_file_path).unwrap_or_else(|e| {
-                    panic!("Failed reading state file {:?}: {:?}", state_file_path, e)
-                });
-                let state_data: StateData = serde_json::from_str(&file_data).unwrap_or_else(|e| {
-                    panic!("Failed parsing state file {:?}: {:?}", state_file_path, e)
-                });
-                state_data.next_block
-            } else {
-                0
-            }
-        }
+        StartFrom::Next => state_data
+            .as_ref()
+            .map_or(0, |state_data| state_data.next_block),
         StartFrom::Last => ledger_db
             .num_blocks()
             .expect("Failed getting number of blocks in ledger"),
     };
 
     // Create block handler
-    let mut block_handler: Box<dyn BlockHandler> = match config.destination.destination {
-        Destination::S3 { path, region } => {
-            Box::new(S3BlockWriter::new(path, region, logger.clone()))
-        }
+    let mut block_handler: Box<dyn BlockHandler> = Box::new(StorageBlockWriter::new(
+        &config.destination,
+        config.max_write_attempts,
+        logger.clone(),
+    ));
+
+    // Check the blocks that should already be at the destination: those below the starting
+    // block, and those uploaded by earlier runs.
+    if config.verify || config.verify_only {
+        let num_uploaded_blocks = max(
+            first_desired_block,
+            state_data.map_or(0, |state_data| state_data.next_block),
+        );
+        let report = verify_destination(
+            &ledger_db,
+            block_handler.as_mut(),
+            num_uploaded_blocks,
+            config.bundle_size,
+            &logger,
+        )
+        .expect("Failed reading blocks from ledger");
+        log::info!(
+            logger,
+            "Verified {} files: {} missing, {} corrupt, {} could not be re-uploaded",
+            report.num_checked,
+            report.missing.len(),
+            report.corrupt.len(),
+            report.failed.len()
+        );
 
-        Destination::Local { path } => {
-            fs::create_dir_all(&path).unwrap_or_else(|_| {
-                panic!("Failed creating local destination directory {:?}", path)
-            });
-            Box::new(LocalBlockWriter::new(path, logger.clone()))
+        if config.verify_only {
+            if !report.failed.is_empty() {
+                panic!("Failed re-uploading {:?}", report.failed);
+            }
+            return;
         }
-    };
+    }
 
     // Poll ledger for new blocks and process them as they come.
     log::info!(
@@ -303,27 +716,36 @@ fn main() {
     );
     let mut next_block_num = first_desired_block;
     loop {
-        while let (Ok(block_contents), Ok(block)) = (
-            ledger_db.get_block_contents(next_block_num),
-            ledger_db.get_block(next_block_num),
-        ) {
+        let mut upload_failed = false;
+
+        while let Ok((block, block_contents, signature)) =
+            get_block_data(&ledger_db, next_block_num, &logger)
+        {
             log::trace!(logger, "Handling block #{}", next_block_num);
 
-            let signature = match ledger_db.get_block_signature(next_block_num) {
-                Ok(signature) => Some(signature),
-                Err(LedgerDbError::NotFound) => None,
-                Err(err) => {
-                    log::error!(
-                        logger,
-                        "Failed getting signature for block #{}: {:?}",
-                        next_block_num,
-                        err
-                    );
-                    None
-                }
-            };
+            // A block that could not be uploaded is retried, rather than leaving a hole at the
+            // destination.
+            if let Err(err) = upload_block(
+                &ledger_db,
+                block_handler.as_mut(),
+                &block,
+                &block_contents,
+                &signature,
+                config.bundle_size,
+                &logger,
+            ) {
+                log::error!(
+                    logger,
+                    "Failed uploading block #{}, will retry: {}",
+                    next_block_num,
+                    err
+                );
+                counters::UPLOAD_FAILURES_COUNT.inc();
+                upload_failed = true;
+                break;
+            }
 
-            block_handler.handle_block(&block, &block_contents, &signature);
+            counters::BLOCKS_UPLOADED_COUNT.inc();
             next_block_num += 1;
 
             let state = StateData {
@@ -333,7 +755,157 @@ fn main() {
             fs::write(&state_file_path, json_data).expect("failed writing state file");
         }
 
+        counters::NEXT_BLOCK.set(next_block_num as i64);
+        match ledger_db.num_blocks() {
+            Ok(num_blocks) => {
+                counters::UPLOAD_LAG.set(num_blocks.saturating_sub(next_block_num) as i64)
+            }
+            Err(err) => log::error!(logger, "Failed getting number of blocks in ledger: {}", err),
+        }
+
         // TODO: make this configurable
-        std::thread::sleep(std::time::Duration::from_millis(10));
+        if upload_failed {
+            thread::sleep(Duration::from_secs(1));
+        } else {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_common::logger::test_with_logger;
+    use mc_transaction_core_test_utils::{create_ledger, initialize_ledger, AccountKey};
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+
+    #[test_with_logger]
+    // Missing and corrupt blocks, bundles and manifests are detected and re-uploaded.
+    fn test_verify_destination(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_ledger();
+        let account_key = AccountKey::random(&mut rng);
+        initialize_ledger(&mut ledger_db, 5, &account_key, &mut rng);
+
+        let dir = TempDir::new("ledger_distribution").unwrap();
+        let destination =
+            StorageUri::from_str(&format!("file://{}", dir.path().display())).unwrap();
+        let mut block_handler = StorageBlockWriter::new(&destination, 3, logger.clone());
+
+        for block_index in 0..5 {
+            let (block, block_contents, signature) =
+                get_block_data(&ledger_db, block_index, &logger).unwrap();
+            upload_block(
+                &ledger_db,
+                &mut block_handler,
+                &block,
+                &block_contents,
+                &signature,
+                Some(2),
+                &logger,
+            )
+            .unwrap();
+        }
+
+        // 5 blocks, 2 bundles and the manifest.
+        let report =
+            verify_destination(&ledger_db, &mut block_handler, 5, Some(2), &logger).unwrap();
+        assert_eq!(report.num_checked, 8);
+        assert!(report.missing.is_empty());
+        assert!(report.corrupt.is_empty());
+
+        let bundle_path = block_bundle_to_s3_path(0, 2);
+        let manifest_path = PathBuf::from(S3BLOCK_BUNDLES_MANIFEST_PATH);
+        let original_block = fs::read(dir.path().join(block_num_to_s3block_path(1))).unwrap();
+        let original_bundle = fs::read(dir.path().join(&bundle_path)).unwrap();
+
+        fs::remove_file(dir.path().join(block_num_to_s3block_path(1))).unwrap();
+        fs::write(dir.path().join(block_num_to_s3block_path(3)), b"garbage").unwrap();
+        fs::write(dir.path().join(&bundle_path), b"garbage").unwrap();
+        fs::remove_file(dir.path().join(&manifest_path)).unwrap();
+
+        let report =
+            verify_destination(&ledger_db, &mut block_handler, 5, Some(2), &logger).unwrap();
+        assert_eq!(report.num_checked, 8);
+        assert_eq!(
+            report.missing,
+            vec![block_num_to_s3block_path(1), manifest_path]
+        );
+        assert_eq!(
+            report.corrupt,
+            vec![block_num_to_s3block_path(3), bundle_path.clone()]
+        );
+        assert!(report.failed.is_empty());
+
+        assert_eq!(
+            fs::read(dir.path().join(block_num_to_s3block_path(1))).unwrap(),
+            original_block
+        );
+        assert_eq!(
+            decompress(&fs::read(dir.path().join(&bundle_path)).unwrap()).unwrap(),
+            decompress(&original_bundle).unwrap()
+        );
+
+        // Everything has been repaired.
+        let report =
+            verify_destination(&ledger_db, &mut block_handler, 5, Some(2), &logger).unwrap();
+        assert!(report.missing.is_empty());
+        assert!(report.corrupt.is_empty());
+
+        // Blocks up to the last one at the destination are checked, even when no block is known
+        // to have been uploaded, e.g. when starting from block zero.
+        fs::remove_file(dir.path().join(block_num_to_s3block_path(2))).unwrap();
+        let report =
+            verify_destination(&ledger_db, &mut block_handler, 0, Some(2), &logger).unwrap();
+        assert_eq!(report.num_checked, 8);
+        assert_eq!(report.missing, vec![block_num_to_s3block_path(2)]);
+        assert!(report.corrupt.is_empty());
+    }
+
+    #[test_with_logger]
+    // The manifest should only cover the bundles that were written.
+    fn test_write_bundle_manifest(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_ledger();
+        let account_key = AccountKey::random(&mut rng);
+        initialize_ledger(&mut ledger_db, 10, &account_key, &mut rng);
+
+        let dir = TempDir::new("ledger_distribution").unwrap();
+        let destination =
+            StorageUri::from_str(&format!("file://{}", dir.path().display())).unwrap();
+        let mut block_handler = StorageBlockWriter::new(&destination, 3, logger.clone());
+
+        let read_manifest = || -> (u64, u64) {
+            let manifest: blockchain::ArchiveBlocksManifest = protobuf::parse_from_bytes(
+                &fs::read(dir.path().join(S3BLOCK_BUNDLES_MANIFEST_PATH)).unwrap(),
+            )
+            .unwrap();
+            (manifest.get_first_block_index(), manifest.get_num_blocks())
+        };
+        let mut write = |first_block_index| {
+            let archive_blocks = archive_blocks(&ledger_db, first_block_index, 2, &logger).unwrap();
+            write_bundle(
+                &mut block_handler,
+                first_block_index,
+                &archive_blocks,
+                &logger,
+            )
+            .unwrap();
+        };
+
+        // Distribution started at block 4.
+        write(4);
+        assert_eq!(read_manifest(), (4, 6));
+        write(6);
+        assert_eq!(read_manifest(), (4, 8));
+
+        // Rewriting an earlier bundle keeps the later ones.
+        write(4);
+        assert_eq!(read_manifest(), (4, 8));
+
+        // A bundle that does not extend the others replaces them.
+        write(0);
+        assert_eq!(read_manifest(), (0, 2));
     }
 }
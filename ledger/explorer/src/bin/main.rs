@@ -0,0 +1,38 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Serves a read-only explorer for a local ledger.
+
+use mc_common::logger::{create_app_logger, o};
+use mc_ledger_db::LedgerDB;
+use mc_ledger_explorer::{serve, Explorer};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "ledger-explorer",
+    about = "Serves a read-only explorer for a local ledger."
+)]
+pub struct Config {
+    /// Path to the ledger db (lmdb).
+    #[structopt(long, parse(from_os_str))]
+    pub ledger_db: PathBuf,
+
+    /// Address to serve the explorer on.
+    #[structopt(long, default_value = "127.0.0.1:8080")]
+    pub listen_addr: String,
+}
+
+fn main() {
+    mc_common::setup_panic_handler();
+    let (logger, _global_logger_guard) = create_app_logger(o!());
+    let config = Config::from_args();
+
+    let ledger_db = LedgerDB::open(config.ledger_db).expect("Could not open ledger db");
+    serve(Explorer::new(ledger_db), &config.listen_addr, logger).unwrap_or_else(|err| {
+        panic!(
+            "Failed serving the explorer on {}: {}",
+            config.listen_addr, err
+        )
+    });
+}
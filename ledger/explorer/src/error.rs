@@ -0,0 +1,25 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+use failure::Fail;
+use mc_ledger_db::Error as LedgerError;
+
+#[derive(Debug, Fail)]
+pub enum ExplorerError {
+    #[fail(display = "Not found")]
+    NotFound,
+
+    #[fail(display = "Invalid argument: {}", _0)]
+    InvalidArgument(String),
+
+    #[fail(display = "Ledger error: {}", _0)]
+    Ledger(LedgerError),
+}
+
+impl From<LedgerError> for ExplorerError {
+    fn from(src: LedgerError) -> Self {
+        match src {
+            LedgerError::NotFound => Self::NotFound,
+            err => Self::Ledger(err),
+        }
+    }
+}
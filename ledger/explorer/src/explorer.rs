@@ -0,0 +1,330 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Read-only queries over a ledger, answered with JSON.
+
+use crate::{
+    error::ExplorerError,
+    json::{
+        block_contents_json, block_json, block_signature_json, key_image_hex,
+        membership_proof_json, tx_out_json,
+    },
+};
+use mc_ledger_db::{Error as LedgerError, Ledger};
+use mc_transaction_core::{ring_signature::KeyImage, BlockIndex};
+use serde_json::{json, Value};
+
+/// Number of blocks listed per page, unless the request asks for another number.
+pub const DEFAULT_PAGE_SIZE: u64 = 20;
+
+/// Maximal number of blocks listed per page.
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+/// Number of recent blocks that statistics cover, unless the request asks for another number.
+pub const DEFAULT_STATS_WINDOW: u64 = 1000;
+
+/// Maximal number of recent blocks that statistics cover.
+pub const MAX_STATS_WINDOW: u64 = 10_000;
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+pub struct Explorer<L: Ledger> {
+    ledger: L,
+}
+
+impl<L: Ledger> Explorer<L> {
+    pub fn new(ledger: L) -> Self {
+        Self { ledger }
+    }
+
+    /// A page of block headers, from the newest to the oldest.
+    ///
+    /// # Arguments
+    /// * `before` - Index of the block after the first block of the page. Defaults to the number
+    ///   of blocks, i.e. the page starts with the newest block.
+    /// * `limit` - Maximal number of blocks in the page.
+    pub fn blocks(&self, before: Option<BlockIndex>, limit: u64) -> Result<Value, ExplorerError> {
+        if limit == 0 || limit > MAX_PAGE_SIZE {
+            return Err(ExplorerError::InvalidArgument(format!(
+                "limit must be between 1 and {}",
+                MAX_PAGE_SIZE
+            )));
+        }
+
+        let num_blocks = self.ledger.num_blocks()?;
+        let end = before.unwrap_or(num_blocks).min(num_blocks);
+        let start = end.saturating_sub(limit);
+
+        let mut blocks = Vec::new();
+        for index in (start..end).rev() {
+            let block = self.ledger.get_block(index)?;
+            let block_contents = self.ledger.get_block_contents(index)?;
+            let mut block_summary = block_json(&block);
+            block_summary["num_outputs"] = json!(block_contents.outputs.len());
+            block_summary["num_key_images"] = json!(block_contents.key_images.len());
+            block_summary["timestamp"] = json!(self.ledger.get_block_timestamp(index)?);
+            blocks.push(block_summary);
+        }
+
+        Ok(json!({
+            "num_blocks": num_blocks,
+            "blocks": blocks,
+            "next_before": if start > 0 { Some(start) } else { None },
+        }))
+    }
+
+    /// A block, with its contents and signatures.
+    pub fn block(&self, index: BlockIndex) -> Result<Value, ExplorerError> {
+        let block = self.ledger.get_block(index)?;
+        let block_contents = self.ledger.get_block_contents(index)?;
+        let signature = match self.ledger.get_block_signature(index) {
+            Ok(signature) => Some(block_signature_json(&signature)),
+            Err(LedgerError::NotFound) => None,
+            Err(err) => return Err(err.into()),
+        };
+        let collected_signatures: Vec<Value> = self
+            .ledger
+            .get_collected_block_signatures(index)?
+            .iter()
+            .map(block_signature_json)
+            .collect();
+
+        Ok(json!({
+            "block": block_json(&block),
+            "timestamp": self.ledger.get_block_timestamp(index)?,
+            "contents": block_contents_json(&block_contents),
+            "signature": signature,
+            "collected_signatures": collected_signatures,
+        }))
+    }
+
+    /// A TxOut, with the block containing it and a proof of its membership in the ledger.
+    pub fn tx_out_by_index(&self, index: u64) -> Result<Value, ExplorerError> {
+        let tx_out = self.ledger.get_tx_out_by_index(index)?;
+        let block_index = self.ledger.get_block_index_by_tx_out_index(index)?;
+        let proofs = self.ledger.get_tx_out_proof_of_memberships(&[index])?;
+        let proof = proofs.first().ok_or(ExplorerError::NotFound)?;
+
+        Ok(json!({
+            "index": index,
+            "block_index": block_index,
+            "tx_out": tx_out_json(&tx_out),
+            "membership_proof": membership_proof_json(proof),
+        }))
+    }
+
+    /// A TxOut, found by its hash, with a proof of its membership in the ledger.
+    pub fn tx_out_by_hash(&self, hash: &[u8; 32]) -> Result<Value, ExplorerError> {
+        let index = self.ledger.get_tx_out_index_by_hash(hash)?;
+        self.tx_out_by_index(index)
+    }
+
+    /// Whether a key image is spent, and in which block.
+    pub fn key_image(&self, key_image: &KeyImage) -> Result<Value, ExplorerError> {
+        let block_index = self.ledger.check_key_image(key_image)?;
+        Ok(json!({
+            "key_image": key_image_hex(key_image),
+            "spent": block_index.is_some(),
+            "block_index": block_index,
+        }))
+    }
+
+    /// Statistics over the whole ledger, and over its most recent blocks.
+    ///
+    /// # Arguments
+    /// * `window` - Number of recent blocks to compute per-block statistics over.
+    pub fn stats(&self, window: u64) -> Result<Value, ExplorerError> {
+        if window == 0 || window > MAX_STATS_WINDOW {
+            return Err(ExplorerError::InvalidArgument(format!(
+                "window must be between 1 and {}",
+                MAX_STATS_WINDOW
+            )));
+        }
+
+        let num_blocks = self.ledger.num_blocks()?;
+        let num_txos = self.ledger.num_txos()?;
+        let start = num_blocks.saturating_sub(window);
+
+        let mut num_outputs: u64 = 0;
+        let mut num_key_images: u64 = 0;
+        let mut max_outputs: u64 = 0;
+        for index in start..num_blocks {
+            let block_contents = self.ledger.get_block_contents(index)?;
+            num_outputs += block_contents.outputs.len() as u64;
+            num_key_images += block_contents.key_images.len() as u64;
+            max_outputs = max_outputs.max(block_contents.outputs.len() as u64);
+        }
+
+        let num_window_blocks = num_blocks - start;
+        let per_block = |count: u64| {
+            if num_window_blocks == 0 {
+                0.0
+            } else {
+                count as f64 / num_window_blocks as f64
+            }
+        };
+
+        // The rate of blocks is measured between the first and the last block of the window, if
+        // both have a timestamp.
+        let blocks_per_day = if num_window_blocks < 2 {
+            None
+        } else {
+            let first_timestamp = self.ledger.get_block_timestamp(start)?;
+            let last_timestamp = self.ledger.get_block_timestamp(num_blocks - 1)?;
+            match (first_timestamp, last_timestamp) {
+                (Some(first), Some(last)) if last > first => {
+                    Some((num_window_blocks - 1) as f64 * SECONDS_PER_DAY / (last - first) as f64)
+                }
+                _ => None,
+            }
+        };
+
+        Ok(json!({
+            "num_blocks": num_blocks,
+            "num_txos": num_txos,
+            "recent_blocks": {
+                "first_block": start,
+                "num_blocks": num_window_blocks,
+                "num_outputs": num_outputs,
+                "num_key_images": num_key_images,
+                "outputs_per_block": per_block(num_outputs),
+                "key_images_per_block": per_block(num_key_images),
+                "max_outputs_per_block": max_outputs,
+                "blocks_per_day": blocks_per_day,
+            },
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_crypto_keys::Ed25519Pair;
+    use mc_transaction_core::{account_keys::AccountKey, BlockSignature};
+    use mc_transaction_core_test_utils::{create_ledger, initialize_ledger};
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn explorer(num_blocks: u64) -> Explorer<mc_ledger_db::LedgerDB> {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let mut ledger = create_ledger();
+        initialize_ledger(&mut ledger, num_blocks, &account_key, &mut rng);
+        Explorer::new(ledger)
+    }
+
+    #[test]
+    // Pages should list blocks from the newest to the oldest, and link to the next page.
+    fn test_blocks_pagination() {
+        let explorer = explorer(5);
+
+        let page = explorer.blocks(None, 2).unwrap();
+        assert_eq!(page["num_blocks"], 5);
+        let indexes: Vec<u64> = page["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|block| block["index"].as_u64().unwrap())
+            .collect();
+        assert_eq!(indexes, vec![4, 3]);
+        assert_eq!(page["next_before"], 3);
+
+        let page = explorer.blocks(Some(1), 2).unwrap();
+        assert_eq!(page["blocks"].as_array().unwrap().len(), 1);
+        assert_eq!(page["blocks"][0]["index"], 0);
+        assert_eq!(page["next_before"], Value::Null);
+
+        // Pages past the end of the ledger start at the newest block.
+        let page = explorer.blocks(Some(100), 1).unwrap();
+        assert_eq!(page["blocks"][0]["index"], 4);
+
+        assert!(explorer.blocks(None, 0).is_err());
+        assert!(explorer.blocks(None, MAX_PAGE_SIZE + 1).is_err());
+    }
+
+    #[test]
+    // TxOuts should be found by index and by hash, and key images by value.
+    fn test_lookups() {
+        let explorer = explorer(3);
+        let block_contents = explorer.ledger.get_block_contents(2).unwrap();
+
+        let block = explorer.block(2).unwrap();
+        assert_eq!(block["block"]["index"], 2);
+        assert_eq!(
+            block["contents"]["outputs"].as_array().unwrap().len(),
+            block_contents.outputs.len()
+        );
+        assert_eq!(block["signature"], Value::Null);
+        assert_eq!(block["timestamp"], Value::Null);
+        match explorer.block(3) {
+            Err(ExplorerError::NotFound) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let tx_out = &block_contents.outputs[0];
+        let index = explorer
+            .ledger
+            .get_tx_out_index_by_hash(&tx_out.hash())
+            .unwrap();
+        let by_index = explorer.tx_out_by_index(index).unwrap();
+        assert_eq!(by_index["block_index"], 2);
+        assert_eq!(by_index["tx_out"]["hash"], hex::encode(tx_out.hash()));
+        assert_eq!(by_index["membership_proof"]["index"], index);
+        assert_eq!(explorer.tx_out_by_hash(&tx_out.hash()).unwrap(), by_index);
+        match explorer.tx_out_by_hash(&[0u8; 32]) {
+            Err(ExplorerError::NotFound) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let key_image = block_contents.key_images[0];
+        let spent = explorer.key_image(&key_image).unwrap();
+        assert_eq!(spent["spent"], true);
+        assert_eq!(spent["block_index"], 2);
+        let unspent = explorer.key_image(&KeyImage::from(1)).unwrap();
+        assert_eq!(unspent["spent"], false);
+        assert_eq!(unspent["block_index"], Value::Null);
+    }
+
+    #[test]
+    fn test_stats() {
+        let explorer = explorer(4);
+        let stats = explorer.stats(3).unwrap();
+        assert_eq!(stats["num_blocks"], 4);
+        assert_eq!(stats["num_txos"], explorer.ledger.num_txos().unwrap());
+        assert_eq!(stats["recent_blocks"]["first_block"], 1);
+        assert_eq!(stats["recent_blocks"]["num_blocks"], 3);
+        // Each block after the origin block spends one TxOut.
+        assert_eq!(stats["recent_blocks"]["num_key_images"], 3);
+        assert_eq!(stats["recent_blocks"]["key_images_per_block"], 1.0);
+        // Blocks without signatures have no timestamp.
+        assert_eq!(stats["recent_blocks"]["blocks_per_day"], Value::Null);
+
+        assert!(explorer.stats(0).is_err());
+    }
+
+    #[test]
+    // The rate of blocks should be measured from the timestamps of the blocks.
+    fn test_stats_blocks_per_day() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut explorer = explorer(4);
+        let signer = Ed25519Pair::from_random(&mut rng);
+        for (index, signed_at) in &[(1, 1_590_000_000), (3, 1_590_003_600)] {
+            let block = explorer.ledger.get_block(*index).unwrap();
+            let signature =
+                BlockSignature::from_block_keypair_and_time(&block, &signer, *signed_at).unwrap();
+            explorer
+                .ledger
+                .add_collected_block_signatures(*index, &[signature])
+                .unwrap();
+        }
+
+        // Two blocks were formed in an hour.
+        let stats = explorer.stats(3).unwrap();
+        assert_eq!(stats["recent_blocks"]["blocks_per_day"], 48.0);
+        let page = explorer.blocks(None, 1).unwrap();
+        assert_eq!(page["blocks"][0]["timestamp"], 1_590_003_600);
+
+        // The first block of this window has no timestamp.
+        let stats = explorer.stats(2).unwrap();
+        assert_eq!(stats["recent_blocks"]["blocks_per_day"], Value::Null);
+    }
+}
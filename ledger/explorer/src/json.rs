@@ -0,0 +1,83 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! JSON representations of ledger data. Byte strings are hex-encoded.
+
+use mc_transaction_core::{
+    ring_signature::KeyImage,
+    tx::{TxOut, TxOutMembershipElement, TxOutMembershipProof},
+    Block, BlockContents, BlockSignature,
+};
+use serde_json::{json, Value};
+
+fn hex32(bytes: &[u8; 32]) -> String {
+    hex::encode(bytes)
+}
+
+pub fn block_json(block: &Block) -> Value {
+    json!({
+        "id": hex::encode(block.id.0.as_slice()),
+        "version": block.version,
+        "parent_id": hex::encode(block.parent_id.0.as_slice()),
+        "index": block.index,
+        "cumulative_txo_count": block.cumulative_txo_count,
+        "root_element": membership_element_json(&block.root_element),
+        "contents_hash": hex::encode(block.contents_hash.0.as_slice()),
+    })
+}
+
+pub fn block_contents_json(block_contents: &BlockContents) -> Value {
+    let key_images: Vec<String> = block_contents
+        .key_images
+        .iter()
+        .map(key_image_hex)
+        .collect();
+    let outputs: Vec<Value> = block_contents.outputs.iter().map(tx_out_json).collect();
+    json!({
+        "key_images": key_images,
+        "outputs": outputs,
+    })
+}
+
+pub fn block_signature_json(signature: &BlockSignature) -> Value {
+    json!({
+        "signature": hex::encode(signature.signature()),
+        "signer": hex::encode(signature.signer()),
+        "signed_at": signature.signed_at(),
+    })
+}
+
+pub fn key_image_hex(key_image: &KeyImage) -> String {
+    hex32(key_image.as_bytes())
+}
+
+pub fn tx_out_json(tx_out: &TxOut) -> Value {
+    json!({
+        "hash": hex::encode(tx_out.hash()),
+        "amount": {
+            "commitment": hex32(tx_out.amount.commitment.as_ref()),
+            "masked_value": tx_out.amount.masked_value,
+        },
+        "target_key": hex::encode(&tx_out.target_key),
+        "public_key": hex::encode(&tx_out.public_key),
+        "e_account_hint": hex::encode(&tx_out.e_account_hint.to_bytes()[..]),
+    })
+}
+
+pub fn membership_element_json(element: &TxOutMembershipElement) -> Value {
+    json!({
+        "range": {
+            "from": element.range.from,
+            "to": element.range.to,
+        },
+        "hash": hex32(element.hash.as_ref()),
+    })
+}
+
+pub fn membership_proof_json(proof: &TxOutMembershipProof) -> Value {
+    let elements: Vec<Value> = proof.elements.iter().map(membership_element_json).collect();
+    json!({
+        "index": proof.index,
+        "highest_index": proof.highest_index,
+        "elements": elements,
+    })
+}
@@ -0,0 +1,17 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A read-only explorer for the ledger: a web page and JSON endpoints that serve blocks, TxOuts
+//! with their membership proofs, key images, and summary statistics.
+
+mod error;
+mod explorer;
+mod json;
+mod server;
+
+pub use crate::{
+    error::ExplorerError,
+    explorer::{
+        Explorer, DEFAULT_PAGE_SIZE, DEFAULT_STATS_WINDOW, MAX_PAGE_SIZE, MAX_STATS_WINDOW,
+    },
+    server::{handle_request, serve},
+};
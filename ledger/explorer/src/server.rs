@@ -0,0 +1,186 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! The explorer's HTTP interface: a web page, and JSON endpoints under `/api`.
+
+use crate::{
+    error::ExplorerError,
+    explorer::{Explorer, DEFAULT_PAGE_SIZE, DEFAULT_STATS_WINDOW},
+};
+use mc_common::logger::{log, Logger};
+use mc_ledger_db::Ledger;
+use mc_transaction_core::ring_signature::KeyImage;
+use rouille::{router, Request, Response, Server};
+use serde_json::{json, Value};
+use std::{convert::TryFrom, error::Error, str::FromStr, time::Duration};
+
+/// Serves the explorer until the process exits. Only returns if the server could not be started.
+///
+/// # Arguments
+/// * `explorer` - Answers queries.
+/// * `listen_addr` - Address to listen on, e.g. `127.0.0.1:8080`.
+/// * `logger` - Logger.
+pub fn serve<L: Ledger + Sync + 'static>(
+    explorer: Explorer<L>,
+    listen_addr: &str,
+    logger: Logger,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let server_logger = logger.clone();
+    let server = Server::new(listen_addr, move |request| {
+        let log_ok = |req: &Request, resp: &Response, _elapsed: Duration| {
+            log::trace!(
+                server_logger,
+                "{} {}: {}",
+                req.method(),
+                req.raw_url(),
+                resp.status_code
+            );
+        };
+        let log_err = |req: &Request, _elapsed: Duration| {
+            log::error!(
+                server_logger,
+                "Request handler failed: {} {}",
+                req.method(),
+                req.raw_url()
+            );
+        };
+        rouille::log_custom(request, log_ok, log_err, || {
+            handle_request(request, &explorer)
+        })
+    })?;
+
+    log::info!(logger, "Serving the ledger explorer on {}", listen_addr);
+    loop {
+        server.poll_timeout(Duration::from_secs(1));
+    }
+}
+
+pub fn handle_request<L: Ledger>(request: &Request, explorer: &Explorer<L>) -> Response {
+    router!(request,
+        (GET) (/) => {
+            Response::html(include_str!("templates/index.html"))
+        },
+        (GET) (/api/blocks) => {
+            let before = match optional_param(request, "before") {
+                Ok(before) => before,
+                Err(response) => return response,
+            };
+            let limit = match optional_param(request, "limit") {
+                Ok(limit) => limit.unwrap_or(DEFAULT_PAGE_SIZE),
+                Err(response) => return response,
+            };
+            json_response(explorer.blocks(before, limit))
+        },
+        (GET) (/api/blocks/{index: u64}) => {
+            json_response(explorer.block(index))
+        },
+        (GET) (/api/tx-outs/{index: u64}) => {
+            json_response(explorer.tx_out_by_index(index))
+        },
+        (GET) (/api/tx-outs/by-hash/{hash: String}) => {
+            match parse_hex32(&hash) {
+                Some(hash) => json_response(explorer.tx_out_by_hash(&hash)),
+                None => bad_request("Invalid TxOut hash"),
+            }
+        },
+        (GET) (/api/key-images/{key_image: String}) => {
+            match parse_hex32(&key_image) {
+                Some(key_image) => json_response(explorer.key_image(&KeyImage::from(key_image))),
+                None => bad_request("Invalid key image"),
+            }
+        },
+        (GET) (/api/stats) => {
+            let window = match optional_param(request, "window") {
+                Ok(window) => window.unwrap_or(DEFAULT_STATS_WINDOW),
+                Err(response) => return response,
+            };
+            json_response(explorer.stats(window))
+        },
+        _ => Response::empty_404()
+    )
+}
+
+/// Parses an optional query parameter, or responds with an error.
+fn optional_param<T: FromStr>(request: &Request, name: &str) -> Result<Option<T>, Response> {
+    match request.get_param(name) {
+        None => Ok(None),
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| bad_request(&format!("Invalid {}", name))),
+    }
+}
+
+/// Parses 32 hex-encoded bytes.
+fn parse_hex32(src: &str) -> Option<[u8; 32]> {
+    let bytes = hex::decode(src).ok()?;
+    <[u8; 32]>::try_from(&bytes[..]).ok()
+}
+
+fn bad_request(message: &str) -> Response {
+    Response::json(&json!({ "error": message })).with_status_code(400)
+}
+
+fn json_response(result: Result<Value, ExplorerError>) -> Response {
+    match result {
+        Ok(value) => Response::json(&value),
+        Err(ExplorerError::NotFound) => {
+            Response::json(&json!({ "error": "Not found" })).with_status_code(404)
+        }
+        Err(ExplorerError::InvalidArgument(message)) => bad_request(&message),
+        Err(err) => Response::json(&json!({ "error": err.to_string() })).with_status_code(500),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_transaction_core::account_keys::AccountKey;
+    use mc_transaction_core_test_utils::{create_ledger, initialize_ledger};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::io::Read;
+
+    fn get(explorer: &Explorer<mc_ledger_db::LedgerDB>, url: &str) -> (u16, Value) {
+        let request = Request::fake_http("GET", url, vec![], vec![]);
+        let response = handle_request(&request, explorer);
+        let (mut reader, _size) = response.data.into_reader_and_size();
+        let mut body = String::new();
+        reader.read_to_string(&mut body).unwrap();
+        (response.status_code, serde_json::from_str(&body).unwrap())
+    }
+
+    #[test]
+    fn test_parse_hex32() {
+        assert_eq!(parse_hex32(&hex::encode([7u8; 32])), Some([7u8; 32]));
+        assert_eq!(parse_hex32("0707"), None);
+        assert_eq!(parse_hex32("not hex"), None);
+    }
+
+    #[test]
+    // Endpoints should answer with JSON, and with the right status codes on errors.
+    fn test_routes() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let mut ledger = create_ledger();
+        initialize_ledger(&mut ledger, 3, &account_key, &mut rng);
+        let key_image = ledger.get_block_contents(1).unwrap().key_images[0];
+        let explorer = Explorer::new(ledger);
+
+        let (status, page) = get(&explorer, "/api/blocks?limit=2&before=2");
+        assert_eq!(status, 200);
+        assert_eq!(page["blocks"][0]["index"], 1);
+        assert_eq!(get(&explorer, "/api/blocks?limit=many").0, 400);
+
+        assert_eq!(get(&explorer, "/api/blocks/2").0, 200);
+        assert_eq!(get(&explorer, "/api/blocks/3").0, 404);
+        assert_eq!(get(&explorer, "/api/tx-outs/0").0, 200);
+        assert_eq!(get(&explorer, "/api/tx-outs/by-hash/00").0, 400);
+
+        let url = format!("/api/key-images/{}", hex::encode(key_image.as_bytes()));
+        let (status, spent) = get(&explorer, &url);
+        assert_eq!(status, 200);
+        assert_eq!(spent["block_index"], 1);
+
+        assert_eq!(get(&explorer, "/api/stats?window=2").0, 200);
+        assert_eq!(get(&explorer, "/api/stats?window=0").0, 400);
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Per-client limits on the number of items queried.
+
+use mc_common::HashMap;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Maximum number of tracked clients. When a new client arrives and this many are tracked,
+/// clients whose allowance has fully refilled are forgotten, and failing that, the least recently
+/// updated client is.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Limits the rate at which each client may query items, with a token bucket per client.
+///
+/// A client starts with `burst_size` tokens, and regains `items_per_second` tokens per second,
+/// up to `burst_size`. Each queried item costs one token.
+pub struct ClientRateLimiter {
+    /// Number of items a client may query at once.
+    burst_size: u64,
+
+    /// Number of items a client may query per second, on average.
+    items_per_second: u64,
+
+    /// Remaining tokens of each client, and when they were last updated.
+    buckets: Mutex<HashMap<String, (f64, Instant)>>,
+}
+
+impl ClientRateLimiter {
+    /// Create a new ClientRateLimiter.
+    ///
+    /// # Arguments
+    /// * `items_per_second` - Number of items a client may query per second, on average.
+    /// * `burst_size` - Number of items a client may query at once.
+    pub fn new(items_per_second: u64, burst_size: u64) -> Self {
+        Self {
+            burst_size,
+            items_per_second,
+            buckets: Mutex::new(HashMap::default()),
+        }
+    }
+
+    /// Takes `num_items` tokens from `client`'s bucket. Returns false, and takes nothing, if the
+    /// client does not have enough tokens.
+    ///
+    /// # Arguments
+    /// * `client` - Identifies the client.
+    /// * `num_items` - Number of items the client is querying.
+    /// * `now` - The current time.
+    pub fn try_acquire(&self, client: &str, num_items: u64, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock().expect("mutex poisoned");
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(client) {
+            let refill_duration = self.refill_duration();
+            buckets.retain(|_client, (_tokens, updated_at)| {
+                now.saturating_duration_since(*updated_at) < refill_duration
+            });
+
+            if buckets.len() >= MAX_TRACKED_CLIENTS {
+                let least_recently_updated = buckets
+                    .iter()
+                    .min_by_key(|(_client, (_tokens, updated_at))| *updated_at)
+                    .map(|(client, _)| client.clone());
+                if let Some(evicted) = least_recently_updated {
+                    buckets.remove(&evicted);
+                }
+            }
+        }
+
+        let burst_size = self.burst_size as f64;
+        let (tokens, updated_at) = buckets
+            .entry(client.to_string())
+            .or_insert((burst_size, now));
+
+        let elapsed = now.saturating_duration_since(*updated_at);
+        *tokens = (*tokens + elapsed.as_secs_f64() * self.items_per_second as f64).min(burst_size);
+        *updated_at = now;
+
+        if *tokens < num_items as f64 {
+            return false;
+        }
+        *tokens -= num_items as f64;
+        true
+    }
+
+    /// How long an empty bucket takes to refill.
+    fn refill_duration(&self) -> Duration {
+        if self.items_per_second == 0 {
+            return Duration::from_secs(std::u64::MAX);
+        }
+        Duration::from_secs_f64(self.burst_size as f64 / self.items_per_second as f64)
+    }
+}
+
+/// Identifies the client of a request by its address, without the port, so that a client cannot
+/// get a fresh allowance by opening a new connection.
+///
+/// # Arguments
+/// * `peer` - The peer of a request, e.g. "ipv4:127.0.0.1:34567".
+pub fn client_id_from_peer(peer: &str) -> &str {
+    match peer.rfind(':') {
+        Some(index) if peer[..index].contains(':') => &peer[..index],
+        _ => peer,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // A client may query up to its burst size, and then at the refill rate.
+    fn test_try_acquire() {
+        let rate_limiter = ClientRateLimiter::new(10, 100);
+        let start = Instant::now();
+
+        assert!(rate_limiter.try_acquire("a", 60, start));
+        assert!(rate_limiter.try_acquire("a", 40, start));
+        assert!(!rate_limiter.try_acquire("a", 1, start));
+
+        // Other clients have their own allowance.
+        assert!(rate_limiter.try_acquire("b", 100, start));
+
+        // After two seconds, 20 tokens have been regained.
+        let later = start + Duration::from_secs(2);
+        assert!(!rate_limiter.try_acquire("a", 21, later));
+        assert!(rate_limiter.try_acquire("a", 20, later));
+        assert!(!rate_limiter.try_acquire("a", 1, later));
+
+        // The allowance never exceeds the burst size.
+        let much_later = start + Duration::from_secs(1000);
+        assert!(!rate_limiter.try_acquire("b", 101, much_later));
+        assert!(rate_limiter.try_acquire("b", 100, much_later));
+    }
+
+    #[test]
+    // The number of tracked clients stays bounded, even when no allowance has refilled.
+    fn test_try_acquire_bounds_tracked_clients() {
+        let rate_limiter = ClientRateLimiter::new(1, 100);
+        let start = Instant::now();
+
+        for i in 0..MAX_TRACKED_CLIENTS {
+            let now = start + Duration::from_millis(i as u64);
+            assert!(rate_limiter.try_acquire(&i.to_string(), 100, now));
+        }
+
+        let now = start + Duration::from_secs(20);
+        assert!(rate_limiter.try_acquire("new", 100, now));
+        let buckets = rate_limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), MAX_TRACKED_CLIENTS);
+
+        // The least recently updated client was forgotten.
+        assert!(!buckets.contains_key("0"));
+        assert!(buckets.contains_key("1"));
+        assert!(buckets.contains_key("new"));
+    }
+
+    #[test]
+    fn test_client_id_from_peer() {
+        assert_eq!(
+            client_id_from_peer("ipv4:127.0.0.1:34567"),
+            "ipv4:127.0.0.1"
+        );
+        assert_eq!(client_id_from_peer("ipv6:[::1]:34567"), "ipv6:[::1]");
+        assert_eq!(client_id_from_peer("unix:/tmp/socket"), "unix:/tmp/socket");
+    }
+}
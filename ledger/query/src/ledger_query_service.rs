@@ -0,0 +1,421 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Serves read-only queries about key images and TxOuts in the ledger.
+
+use crate::client_rate_limiter::{client_id_from_peer, ClientRateLimiter};
+use grpcio::{RpcContext, RpcStatus, RpcStatusCode, UnarySink};
+use mc_api::external;
+use mc_common::{
+    logger::{log, Logger},
+    Hash,
+};
+use mc_consensus_api::{
+    ledger_query::{
+        CheckKeyImagesRequest, CheckKeyImagesResponse, GetTxOutsRequest, GetTxOutsResponse,
+        KeyImageResult, TxOutResult, TxOutResultCode,
+    },
+    ledger_query_grpc::{create_ledger_query_api, LedgerQueryApi},
+};
+use mc_ledger_db::{Error as LedgerError, Ledger};
+use mc_transaction_core::ring_signature::KeyImage;
+use mc_util_grpc::{
+    rpc_database_err, rpc_invalid_arg_error, rpc_logger, rpc_out_of_range_error, send_result,
+};
+use mc_util_metrics::SVC_COUNTERS;
+use protobuf::RepeatedField;
+use std::{cmp, convert::TryFrom, ops::Range, sync::Arc, time::Instant};
+
+/// Default maximal number of items answered by a single request.
+pub const DEFAULT_MAX_PAGE_SIZE: usize = 1000;
+
+/// Default number of items a client may query per second, on average.
+pub const DEFAULT_ITEMS_PER_SECOND: u64 = 2000;
+
+/// Default number of items a client may query at once.
+pub const DEFAULT_BURST_SIZE: u64 = 10_000;
+
+#[derive(Clone)]
+pub struct LedgerQueryService<L: Ledger + Clone> {
+    /// Ledger Database.
+    ledger: L,
+
+    /// Maximal number of items answered by a single request.
+    max_page_size: usize,
+
+    /// Limits the number of items each client may query.
+    rate_limiter: Arc<ClientRateLimiter>,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl<L: Ledger + Clone + 'static> LedgerQueryService<L> {
+    pub fn new(ledger: L, logger: Logger) -> Self {
+        Self {
+            ledger,
+            max_page_size: DEFAULT_MAX_PAGE_SIZE,
+            rate_limiter: Arc::new(ClientRateLimiter::new(
+                DEFAULT_ITEMS_PER_SECOND,
+                DEFAULT_BURST_SIZE,
+            )),
+            logger,
+        }
+    }
+
+    /// Set the maximum number of items answered by a single request.
+    pub fn set_max_page_size(&mut self, max_page_size: usize) {
+        self.max_page_size = max_page_size;
+    }
+
+    /// Set how many items each client may query.
+    ///
+    /// # Arguments
+    /// * `items_per_second` - Number of items a client may query per second, on average.
+    /// * `burst_size` - Number of items a client may query at once.
+    pub fn set_rate_limit(&mut self, items_per_second: u64, burst_size: u64) {
+        self.rate_limiter = Arc::new(ClientRateLimiter::new(items_per_second, burst_size));
+    }
+
+    /// Package this into a grpc service.
+    pub fn into_service(self) -> grpcio::Service {
+        create_ledger_query_api(self)
+    }
+
+    /// The range of items answered by a request with `num_items` items, starting at `offset`.
+    fn page(
+        &self,
+        num_items: usize,
+        offset: u64,
+        logger: &Logger,
+    ) -> Result<Range<usize>, RpcStatus> {
+        if offset > num_items as u64 {
+            return Err(rpc_out_of_range_error(
+                "page",
+                format!("offset {} exceeds {} items", offset, num_items),
+                logger,
+            ));
+        }
+        let start = offset as usize;
+        let end = cmp::min(num_items, start + self.max_page_size);
+        Ok(start..end)
+    }
+
+    /// Charges `client` for querying `num_items` items.
+    fn acquire(&self, client: &str, num_items: usize, logger: &Logger) -> Result<(), RpcStatus> {
+        if self
+            .rate_limiter
+            .try_acquire(client, num_items as u64, Instant::now())
+        {
+            Ok(())
+        } else {
+            log::debug!(logger, "Client exceeded its rate limit");
+            Err(RpcStatus::new(
+                RpcStatusCode::RESOURCE_EXHAUSTED,
+                Some("Too many items queried, retry later".to_string()),
+            ))
+        }
+    }
+
+    /// Checks whether each key image in a page of the request is in the ledger.
+    ///
+    /// # Arguments
+    /// * `client` - Identifies the client making the request.
+    /// * `request` - The request.
+    /// * `logger` - Logger.
+    fn check_key_images_helper(
+        &self,
+        client: &str,
+        request: &CheckKeyImagesRequest,
+        logger: &Logger,
+    ) -> Result<CheckKeyImagesResponse, RpcStatus> {
+        let page = self.page(request.get_key_images().len(), request.get_offset(), logger)?;
+        let key_images = &request.get_key_images()[page];
+        self.acquire(client, key_images.len(), logger)?;
+
+        let num_blocks = self
+            .ledger
+            .num_blocks()
+            .map_err(|err| rpc_database_err(err, logger))?;
+
+        let results = key_images
+            .iter()
+            .map(|proto_key_image| {
+                let key_image = KeyImage::try_from(proto_key_image)
+                    .map_err(|err| rpc_invalid_arg_error("key_image", err, logger))?;
+                let spent_at = self
+                    .ledger
+                    .check_key_image(&key_image)
+                    .map_err(|err| rpc_database_err(err, logger))?;
+
+                let mut result = KeyImageResult::new();
+                result.set_key_image(proto_key_image.clone());
+                if let Some(block_index) = spent_at {
+                    result.set_spent(true);
+                    result.set_spent_at(block_index);
+                }
+                Ok(result)
+            })
+            .collect::<Result<Vec<_>, RpcStatus>>()?;
+
+        let mut response = CheckKeyImagesResponse::new();
+        response.set_results(RepeatedField::from_vec(results));
+        response.set_num_blocks(num_blocks);
+        Ok(response)
+    }
+
+    /// Finds the index and membership proof of each TxOut in a page of the request.
+    ///
+    /// # Arguments
+    /// * `client` - Identifies the client making the request.
+    /// * `request` - The request.
+    /// * `logger` - Logger.
+    fn get_tx_outs_helper(
+        &self,
+        client: &str,
+        request: &GetTxOutsRequest,
+        logger: &Logger,
+    ) -> Result<GetTxOutsResponse, RpcStatus> {
+        let page = self.page(
+            request.get_tx_out_hashes().len(),
+            request.get_offset(),
+            logger,
+        )?;
+        let tx_out_hashes = &request.get_tx_out_hashes()[page];
+        self.acquire(client, tx_out_hashes.len(), logger)?;
+
+        let num_blocks = self
+            .ledger
+            .num_blocks()
+            .map_err(|err| rpc_database_err(err, logger))?;
+
+        let mut results = Vec::with_capacity(tx_out_hashes.len());
+        let mut found_indexes = Vec::new();
+        for tx_out_hash in tx_out_hashes {
+            let hash = Hash::try_from(&tx_out_hash[..])
+                .map_err(|err| rpc_invalid_arg_error("tx_out_hash", err, logger))?;
+
+            let mut result = TxOutResult::new();
+            result.set_tx_out_hash(tx_out_hash.clone());
+            match self.ledger.get_tx_out_index_by_hash(&hash) {
+                Ok(tx_out_index) => {
+                    result.set_result_code(TxOutResultCode::Found);
+                    result.set_tx_out_index(tx_out_index);
+                    found_indexes.push(tx_out_index);
+                }
+                Err(LedgerError::NotFound) => result.set_result_code(TxOutResultCode::NotFound),
+                Err(err) => return Err(rpc_database_err(err, logger)),
+            }
+            results.push(result);
+        }
+
+        let mut proofs = self
+            .ledger
+            .get_tx_out_proof_of_memberships(&found_indexes)
+            .map_err(|err| rpc_database_err(err, logger))?
+            .into_iter();
+        for result in results.iter_mut() {
+            if result.get_result_code() == TxOutResultCode::Found {
+                let proof = proofs
+                    .next()
+                    .expect("Expected one proof per TxOut that was found");
+                result.set_proof(external::TxOutMembershipProof::from(&proof));
+            }
+        }
+
+        let mut response = GetTxOutsResponse::new();
+        response.set_results(RepeatedField::from_vec(results));
+        response.set_num_blocks(num_blocks);
+        Ok(response)
+    }
+}
+
+impl<L: Ledger + Clone + 'static> LedgerQueryApi for LedgerQueryService<L> {
+    fn check_key_images(
+        &mut self,
+        ctx: RpcContext,
+        request: CheckKeyImagesRequest,
+        sink: UnarySink<CheckKeyImagesResponse>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        mc_common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            let peer = ctx.peer();
+            let resp = self.check_key_images_helper(client_id_from_peer(&peer), &request, &logger);
+            send_result(ctx, sink, resp, &logger);
+        });
+    }
+
+    fn get_tx_outs(
+        &mut self,
+        ctx: RpcContext,
+        request: GetTxOutsRequest,
+        sink: UnarySink<GetTxOutsResponse>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        mc_common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            let peer = ctx.peer();
+            let resp = self.get_tx_outs_helper(client_id_from_peer(&peer), &request, &logger);
+            send_result(ctx, sink, resp, &logger);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_common::logger::test_with_logger;
+    use mc_ledger_db::LedgerDB;
+    use mc_transaction_core::account_keys::AccountKey;
+    use mc_transaction_core_test_utils::{create_ledger, initialize_ledger};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn create_service(n_blocks: u64, logger: Logger) -> LedgerQueryService<LedgerDB> {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger = create_ledger();
+        let account_key = AccountKey::random(&mut rng);
+        initialize_ledger(&mut ledger, n_blocks, &account_key, &mut rng);
+        LedgerQueryService::new(ledger, logger)
+    }
+
+    #[test_with_logger]
+    // Spent key images are reported with the block that contains them.
+    fn test_check_key_images(logger: Logger) {
+        let service = create_service(5, logger.clone());
+
+        let spent: Vec<KeyImage> = service.ledger.get_key_images_by_block(3).unwrap();
+        assert!(!spent.is_empty());
+        let unspent = KeyImage::from(123u64);
+
+        let mut request = CheckKeyImagesRequest::new();
+        request.set_key_images(RepeatedField::from_vec(vec![
+            external::KeyImage::from(&spent[0]),
+            external::KeyImage::from(&unspent),
+        ]));
+
+        let response = service
+            .check_key_images_helper("client", &request, &logger)
+            .unwrap();
+        assert_eq!(response.get_num_blocks(), 5);
+
+        let results = response.get_results();
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            KeyImage::try_from(results[0].get_key_image()).unwrap(),
+            spent[0]
+        );
+        assert!(results[0].get_spent());
+        assert_eq!(results[0].get_spent_at(), 3);
+        assert_eq!(
+            KeyImage::try_from(results[1].get_key_image()).unwrap(),
+            unspent
+        );
+        assert!(!results[1].get_spent());
+    }
+
+    #[test_with_logger]
+    // Found TxOuts are reported with their index and a proof of membership.
+    fn test_get_tx_outs(logger: Logger) {
+        let service = create_service(3, logger.clone());
+
+        let tx_out = service.ledger.get_tx_out_by_index(2).unwrap();
+        let mut request = GetTxOutsRequest::new();
+        request.set_tx_out_hashes(RepeatedField::from_vec(vec![
+            vec![7u8; 32],
+            tx_out.hash().to_vec(),
+        ]));
+
+        let response = service
+            .get_tx_outs_helper("client", &request, &logger)
+            .unwrap();
+        assert_eq!(response.get_num_blocks(), 3);
+
+        let results = response.get_results();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].get_result_code(), TxOutResultCode::NotFound);
+        assert!(!results[0].has_proof());
+
+        assert_eq!(results[1].get_result_code(), TxOutResultCode::Found);
+        assert_eq!(results[1].get_tx_out_index(), 2);
+        let expected_proof = service
+            .ledger
+            .get_tx_out_proof_of_memberships(&[2])
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(
+            results[1].get_proof(),
+            &external::TxOutMembershipProof::from(&expected_proof)
+        );
+
+        // Malformed hashes are rejected.
+        request.set_tx_out_hashes(RepeatedField::from_vec(vec![vec![7u8; 31]]));
+        let status = service
+            .get_tx_outs_helper("client", &request, &logger)
+            .unwrap_err();
+        assert_eq!(status.status, RpcStatusCode::INVALID_ARGUMENT);
+    }
+
+    #[test_with_logger]
+    // Requests with more items than the page size are answered one page at a time.
+    fn test_pagination(logger: Logger) {
+        let mut service = create_service(2, logger.clone());
+        service.set_max_page_size(3);
+
+        let key_images: Vec<external::KeyImage> = (0..7u64)
+            .map(|i| external::KeyImage::from(&KeyImage::from(i)))
+            .collect();
+        let mut request = CheckKeyImagesRequest::new();
+        request.set_key_images(RepeatedField::from_vec(key_images.clone()));
+
+        let mut results = Vec::new();
+        loop {
+            request.set_offset(results.len() as u64);
+            let response = service
+                .check_key_images_helper("client", &request, &logger)
+                .unwrap();
+            assert!(response.get_results().len() <= 3);
+            if response.get_results().is_empty() {
+                break;
+            }
+            results.extend(response.get_results().iter().cloned());
+        }
+        let checked: Vec<external::KeyImage> = results
+            .iter()
+            .map(|result| result.get_key_image().clone())
+            .collect();
+        assert_eq!(checked, key_images);
+
+        // An offset past the end of the batch is rejected.
+        request.set_offset(8);
+        let status = service
+            .check_key_images_helper("client", &request, &logger)
+            .unwrap_err();
+        assert_eq!(status.status, RpcStatusCode::OUT_OF_RANGE);
+    }
+
+    #[test_with_logger]
+    // Clients that query too many items are turned away.
+    fn test_rate_limit(logger: Logger) {
+        let mut service = create_service(2, logger.clone());
+        service.set_rate_limit(0, 5);
+
+        let mut request = CheckKeyImagesRequest::new();
+        request.set_key_images(RepeatedField::from_vec(
+            (0..3u64)
+                .map(|i| external::KeyImage::from(&KeyImage::from(i)))
+                .collect(),
+        ));
+
+        assert!(service
+            .check_key_images_helper("client", &request, &logger)
+            .is_ok());
+        let status = service
+            .check_key_images_helper("client", &request, &logger)
+            .unwrap_err();
+        assert_eq!(status.status, RpcStatusCode::RESOURCE_EXHAUSTED);
+
+        // Other clients are not affected.
+        assert!(service
+            .check_key_images_helper("other client", &request, &logger)
+            .is_ok());
+    }
+}
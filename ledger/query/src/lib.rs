@@ -0,0 +1,12 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Read-only queries about key images and TxOuts, for clients that do not keep a copy of the
+//! ledger.
+
+mod client_rate_limiter;
+mod ledger_query_service;
+
+pub use client_rate_limiter::ClientRateLimiter;
+pub use ledger_query_service::{
+    LedgerQueryService, DEFAULT_BURST_SIZE, DEFAULT_ITEMS_PER_SECOND, DEFAULT_MAX_PAGE_SIZE,
+};
@@ -0,0 +1,115 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A utility that exports snapshots of a ledger, and verifies and imports them into new ledgers.
+
+use mc_common::logger::{create_app_logger, log, o};
+use mc_crypto_keys::Ed25519Public;
+use mc_ledger_db::{export_snapshot, import_snapshot, Ledger, LedgerDB};
+use mc_ledger_sync::BlockSignaturePolicy;
+use mc_util_parse::parse_ed25519_public_from_hex;
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "ledger-snapshot",
+    about = "Exports ledger snapshots, and imports them into new ledgers."
+)]
+pub enum Command {
+    /// Writes a snapshot of the first blocks of a ledger.
+    Export {
+        /// Path to the ledger db (lmdb).
+        #[structopt(long, parse(from_os_str))]
+        ledger_db: PathBuf,
+
+        /// Number of blocks in the snapshot. Defaults to every block in the ledger.
+        #[structopt(long)]
+        num_blocks: Option<u64>,
+
+        /// Path of the snapshot to write.
+        #[structopt(long, parse(from_os_str))]
+        output: PathBuf,
+    },
+
+    /// Verifies a snapshot, and imports it into a new ledger db.
+    Import {
+        /// Path of the snapshot to import.
+        #[structopt(long, parse(from_os_str))]
+        snapshot: PathBuf,
+
+        /// Path to the ledger db (lmdb) to create.
+        #[structopt(long, parse(from_os_str))]
+        ledger_db: PathBuf,
+
+        /// Public key of a trusted block signer, hex-encoded. May be given multiple times.
+        #[structopt(
+            long = "trusted-block-signer",
+            required = true,
+            parse(try_from_str=parse_ed25519_public_from_hex)
+        )]
+        trusted_block_signers: Vec<Ed25519Public>,
+
+        /// Number of distinct trusted signers that must have signed the last block.
+        #[structopt(long, default_value = "1")]
+        min_trusted_block_signatures: usize,
+    },
+}
+
+fn main() {
+    let (logger, _global_logger_guard) = create_app_logger(o!());
+
+    match Command::from_args() {
+        Command::Export {
+            ledger_db,
+            num_blocks,
+            output,
+        } => {
+            let ledger_db = LedgerDB::open(ledger_db).expect("Could not open ledger db");
+            let num_blocks = num_blocks.unwrap_or_else(|| {
+                ledger_db
+                    .num_blocks()
+                    .expect("Failed getting number of blocks")
+            });
+
+            log::info!(logger, "Exporting {} blocks", num_blocks);
+            let file = File::create(&output)
+                .unwrap_or_else(|err| panic!("Failed creating {:?}: {}", output, err));
+            export_snapshot(&ledger_db, num_blocks, BufWriter::new(file))
+                .unwrap_or_else(|err| panic!("Failed writing {:?}: {}", output, err));
+            log::info!(logger, "Wrote snapshot to {:?}", output);
+        }
+
+        Command::Import {
+            snapshot,
+            ledger_db,
+            trusted_block_signers,
+            min_trusted_block_signatures,
+        } => {
+            let policy =
+                BlockSignaturePolicy::new(trusted_block_signers, min_trusted_block_signatures)
+                    .unwrap_or_else(|err| panic!("Invalid block signature policy: {}", err));
+
+            let mut file = BufReader::new(
+                File::open(&snapshot)
+                    .unwrap_or_else(|err| panic!("Failed opening {:?}: {}", snapshot, err)),
+            );
+            let header = policy
+                .read_trusted_snapshot_header(&mut file)
+                .unwrap_or_else(|err| panic!("Failed reading {:?}: {}", snapshot, err));
+
+            fs::create_dir_all(&ledger_db)
+                .unwrap_or_else(|err| panic!("Failed creating {:?}: {}", ledger_db, err));
+            LedgerDB::create(ledger_db.clone()).expect("Could not create ledger db");
+            let mut db = LedgerDB::open(ledger_db).expect("Could not open ledger db");
+
+            log::info!(logger, "Importing {} blocks", header.num_blocks());
+            import_snapshot(file, &mut db, &header)
+                .unwrap_or_else(|err| panic!("Failed importing {:?}: {}", snapshot, err));
+            log::info!(logger, "Import completed");
+        }
+    }
+}
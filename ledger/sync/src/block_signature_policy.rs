@@ -0,0 +1,262 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Which block signers ledger sync trusts, and how many of them must have signed a block before it
+//! is appended to the local ledger.
+
+use mc_common::HashSet;
+use mc_crypto_keys::Ed25519Public;
+use mc_ledger_db::{Error as LedgerError, SnapshotHeader, SnapshotReader};
+use mc_transaction_core::{Block, BlockSignature};
+use std::io::Read;
+
+#[derive(Clone, Debug, Default)]
+pub struct BlockSignaturePolicy {
+    /// Public keys of the trusted block signers.
+    trusted_signers: HashSet<Ed25519Public>,
+
+    /// Number of distinct trusted signers that must have signed each block.
+    min_trusted_signatures: usize,
+}
+
+impl BlockSignaturePolicy {
+    /// Create a new BlockSignaturePolicy.
+    ///
+    /// # Arguments
+    /// * `trusted_signers` - Public keys of the trusted block signers.
+    /// * `min_trusted_signatures` - Number of distinct trusted signers that must have signed each
+    ///   block. Must not exceed the number of trusted signers.
+    pub fn new(
+        trusted_signers: impl IntoIterator<Item = Ed25519Public>,
+        min_trusted_signatures: usize,
+    ) -> Result<Self, String> {
+        let trusted_signers: HashSet<Ed25519Public> = trusted_signers.into_iter().collect();
+        if min_trusted_signatures > trusted_signers.len() {
+            return Err(format!(
+                "Requiring {} signatures, but only {} signers are trusted",
+                min_trusted_signatures,
+                trusted_signers.len()
+            ));
+        }
+        Ok(Self {
+            trusted_signers,
+            min_trusted_signatures,
+        })
+    }
+
+    /// True if signatures should be collected for each block, i.e. if any signer is trusted.
+    ///
+    /// The default policy trusts no signer, and requires no signature.
+    pub fn collects_signatures(&self) -> bool {
+        !self.trusted_signers.is_empty()
+    }
+
+    /// Public keys of the trusted block signers.
+    pub fn trusted_signers(&self) -> Vec<Ed25519Public> {
+        self.trusted_signers.iter().cloned().collect()
+    }
+
+    /// Number of distinct trusted signers that must have signed each block.
+    pub fn min_trusted_signatures(&self) -> usize {
+        self.min_trusted_signatures
+    }
+
+    /// Returns the valid signatures over `block` by trusted signers, at most one per signer.
+    ///
+    /// # Arguments
+    /// * `block` - The signed block.
+    /// * `signatures` - Signatures over the block, from any signer.
+    pub fn trusted_signatures(
+        &self,
+        block: &Block,
+        signatures: &[BlockSignature],
+    ) -> Vec<BlockSignature> {
+        let mut signers: HashSet<Ed25519Public> = HashSet::default();
+        signatures
+            .iter()
+            .filter(|signature| {
+                self.trusted_signers.contains(signature.signer())
+                    && signature.verify(block).is_ok()
+                    && signers.insert(*signature.signer())
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// True if `trusted_signatures` are enough for a block to be appended.
+    ///
+    /// # Arguments
+    /// * `trusted_signatures` - Signatures returned by `trusted_signatures`.
+    pub fn is_satisfied_by(&self, trusted_signatures: &[BlockSignature]) -> bool {
+        trusted_signatures.len() >= self.min_trusted_signatures
+    }
+
+    /// True if the last block named in a snapshot's header is signed by enough trusted signers
+    /// for the snapshot to be imported. Since a snapshot replaces syncing from the origin block, a
+    /// policy that requires no trusted signature trusts no snapshot.
+    ///
+    /// This does not check the rest of the snapshot against the last block; `import_snapshot`
+    /// does, given the trusted header.
+    pub fn trusts_snapshot(&self, header: &SnapshotHeader) -> bool {
+        match header.last_block() {
+            Some(last_block) if self.min_trusted_signatures > 0 => self.is_satisfied_by(
+                &self.trusted_signatures(last_block, header.last_block_signatures()),
+            ),
+            _ => false,
+        }
+    }
+
+    /// Reads the header of a snapshot, and checks that its last block is trusted.
+    ///
+    /// The header can then be passed to `import_snapshot`, which checks the rest of the snapshot
+    /// against it.
+    pub fn read_trusted_snapshot_header<R: Read>(
+        &self,
+        reader: R,
+    ) -> Result<SnapshotHeader, LedgerError> {
+        if self.min_trusted_signatures == 0 {
+            return Err(LedgerError::InvalidSnapshot(
+                "importing a snapshot requires at least one trusted block signature",
+            ));
+        }
+        let header = SnapshotReader::new(reader)?.header().clone();
+        if !self.trusts_snapshot(&header) {
+            return Err(LedgerError::InvalidSnapshot(
+                "last block is not signed by enough trusted block signers",
+            ));
+        }
+        Ok(header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_crypto_keys::Ed25519Pair;
+    use mc_ledger_db::{
+        export_snapshot, import_snapshot,
+        test_utils::{get_mock_ledger, get_test_ledger_blocks, MockLedger},
+        Ledger,
+    };
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::io::Cursor;
+
+    #[test]
+    // Only valid signatures by distinct trusted signers count towards the policy.
+    fn test_trusted_signatures() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let trusted_a = Ed25519Pair::from_random(&mut rng);
+        let trusted_b = Ed25519Pair::from_random(&mut rng);
+        let untrusted = Ed25519Pair::from_random(&mut rng);
+
+        let policy =
+            BlockSignaturePolicy::new(vec![trusted_a.public_key(), trusted_b.public_key()], 2)
+                .unwrap();
+        assert!(policy.collects_signatures());
+
+        let blocks = get_test_ledger_blocks(2);
+        let (block, other_block) = (&blocks[1].0, &blocks[0].0);
+        let sign = |block: &Block, signer: &Ed25519Pair| {
+            BlockSignature::from_block_and_keypair(block, signer).unwrap()
+        };
+
+        let signatures = vec![
+            sign(block, &untrusted),
+            sign(other_block, &trusted_b),
+            sign(block, &trusted_a),
+            sign(block, &trusted_a),
+        ];
+        let trusted_signatures = policy.trusted_signatures(block, &signatures);
+        assert_eq!(trusted_signatures, vec![sign(block, &trusted_a)]);
+        assert!(!policy.is_satisfied_by(&trusted_signatures));
+
+        let signatures = vec![sign(block, &trusted_b), sign(block, &trusted_a)];
+        let trusted_signatures = policy.trusted_signatures(block, &signatures);
+        assert_eq!(trusted_signatures, signatures);
+        assert!(policy.is_satisfied_by(&trusted_signatures));
+    }
+
+    #[test]
+    // A policy cannot require more signatures than there are trusted signers.
+    fn test_new_rejects_unsatisfiable_policy() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let signer = Ed25519Pair::from_random(&mut rng);
+        assert!(BlockSignaturePolicy::new(vec![signer.public_key()], 2).is_err());
+
+        let policy = BlockSignaturePolicy::default();
+        assert!(!policy.collects_signatures());
+        assert!(policy.is_satisfied_by(&[]));
+    }
+
+    #[test]
+    // A snapshot is trusted when its last block has enough trusted signatures.
+    fn test_trusts_snapshot() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let trusted = Ed25519Pair::from_random(&mut rng);
+        let untrusted = Ed25519Pair::from_random(&mut rng);
+        let policy = BlockSignaturePolicy::new(vec![trusted.public_key()], 1).unwrap();
+
+        let mut ledger = get_mock_ledger(3);
+        let header = |ledger: &MockLedger| {
+            let mut bytes = Vec::new();
+            export_snapshot(ledger, 3, &mut bytes).unwrap();
+            SnapshotReader::new(&bytes[..]).unwrap().header().clone()
+        };
+        assert!(!policy.trusts_snapshot(&header(&ledger)));
+
+        let last_block = ledger.get_block(2).unwrap();
+        let signatures = vec![
+            BlockSignature::from_block_and_keypair(&last_block, &untrusted).unwrap(),
+            BlockSignature::from_block_and_keypair(&last_block, &trusted).unwrap(),
+        ];
+        ledger
+            .add_collected_block_signatures(2, &signatures[..1])
+            .unwrap();
+        assert!(!policy.trusts_snapshot(&header(&ledger)));
+
+        ledger
+            .add_collected_block_signatures(2, &signatures)
+            .unwrap();
+        let snapshot = header(&ledger);
+        assert!(policy.trusts_snapshot(&snapshot));
+        assert!(!BlockSignaturePolicy::default().trusts_snapshot(&snapshot));
+
+        // Trusting signers without requiring any of their signatures trusts no snapshot.
+        let unsigned_policy = BlockSignaturePolicy::new(vec![trusted.public_key()], 0).unwrap();
+        assert!(!unsigned_policy.trusts_snapshot(&snapshot));
+    }
+
+    #[test]
+    // Only snapshots whose last block is trusted are imported.
+    fn test_read_trusted_snapshot_header() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let trusted = Ed25519Pair::from_random(&mut rng);
+        let policy = BlockSignaturePolicy::new(vec![trusted.public_key()], 1).unwrap();
+
+        let mut ledger = get_mock_ledger(3);
+        let mut bytes = Vec::new();
+        export_snapshot(&ledger, 3, &mut bytes).unwrap();
+        assert!(policy.read_trusted_snapshot_header(&bytes[..]).is_err());
+
+        let last_block = ledger.get_block(2).unwrap();
+        ledger
+            .add_collected_block_signatures(
+                2,
+                &[BlockSignature::from_block_and_keypair(&last_block, &trusted).unwrap()],
+            )
+            .unwrap();
+        bytes.clear();
+        export_snapshot(&ledger, 3, &mut bytes).unwrap();
+        let header = policy.read_trusted_snapshot_header(&bytes[..]).unwrap();
+
+        // A policy requiring no trusted signature imports no snapshot, even a signed one.
+        let unsigned_policy = BlockSignaturePolicy::new(vec![trusted.public_key()], 0).unwrap();
+        assert!(unsigned_policy
+            .read_trusted_snapshot_header(&bytes[..])
+            .is_err());
+
+        let mut imported = MockLedger::default();
+        import_snapshot(Cursor::new(&bytes), &mut imported, &header).unwrap();
+        assert_eq!(imported.get_block(2).unwrap(), last_block);
+    }
+}
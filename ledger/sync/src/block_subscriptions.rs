@@ -0,0 +1,338 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Subscriptions to the blocks appended by each peer, used to learn about new blocks as soon as
+//! they are committed instead of polling peers on an interval.
+//!
+//! Streamed blocks whose contents and signature check out are kept, so that the sync service does
+//! not need to fetch their contents again.
+
+use futures::{
+    future::Shared,
+    stream,
+    sync::oneshot::{self, Receiver, Sender},
+    Future, Stream,
+};
+use mc_common::{
+    logger::{log, Logger},
+    HashMap, ResponderId,
+};
+use mc_connection::{
+    BlockStreamConnection, Connection, ConnectionManager, Error as ConnectionError,
+};
+use mc_ledger_db::Ledger;
+use mc_transaction_core::{Block, BlockContents, BlockIndex, BlockSignature};
+use mc_util_uri::ConnectionUri;
+use std::{
+    cmp::max,
+    collections::BTreeMap,
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// How long to wait before subscribing again after a subscription failed or ended.
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Maximal number of streamed blocks kept until the sync service takes them.
+const MAX_STREAMED_BLOCKS: usize = 100;
+
+/// What the subscriptions received since the last call to `wait`.
+#[derive(Default)]
+struct Received {
+    /// Latest block index received from each peer.
+    latest_block_indexes: HashMap<ResponderId, BlockIndex>,
+
+    /// Valid streamed blocks and their contents, by block index.
+    blocks: BTreeMap<BlockIndex, (Block, BlockContents)>,
+}
+
+/// Block subscriptions to every peer of a `ConnectionManager`.
+pub struct BlockSubscriptions {
+    received: Arc<(Mutex<Received>, Condvar)>,
+
+    /// Set when stopping, to interrupt the wait before resubscribing.
+    stop_requested: Arc<(Mutex<bool>, Condvar)>,
+
+    /// Ends the open subscriptions when dropped.
+    stop_sender: Option<Sender<()>>,
+
+    join_handles: Vec<thread::JoinHandle<()>>,
+}
+
+impl BlockSubscriptions {
+    /// Subscribe to the blocks of every peer, starting from the peer's latest block, or after the
+    /// last block in our ledger if that is later.
+    ///
+    /// Each subscription runs in its own thread, and is renewed if it fails or ends.
+    ///
+    /// # Arguments
+    /// * `ledger` - Local ledger.
+    /// * `manager` - Connections to the peers to subscribe to.
+    /// * `logger` - Logger.
+    pub fn new<L: Ledger + 'static, BC: BlockStreamConnection + 'static>(
+        ledger: L,
+        manager: &ConnectionManager<BC>,
+        logger: Logger,
+    ) -> Self {
+        let received: Arc<(Mutex<Received>, Condvar)> = Default::default();
+        let stop_requested: Arc<(Mutex<bool>, Condvar)> = Default::default();
+        let (stop_sender, stop_receiver) = oneshot::channel::<()>();
+        let stop_receiver = stop_receiver.shared();
+
+        let join_handles = manager
+            .conns()
+            .into_iter()
+            .map(|conn| {
+                let responder_id = conn
+                    .uri()
+                    .responder_id()
+                    .expect("Could not get responder_id from URI");
+
+                let thread_ledger = ledger.clone();
+                let thread_received = received.clone();
+                let thread_stop_requested = stop_requested.clone();
+                let thread_stop_receiver = stop_receiver.clone();
+                let thread_logger = logger.clone();
+                thread::Builder::new()
+                    .name(format!("Subscribe:{}", responder_id))
+                    .spawn(move || loop {
+                        // The connection is only locked while the subscription is created.
+                        let last_block_index = conn.write().fetch_block_height();
+                        let result = last_block_index.and_then(|last_block_index| {
+                            let num_blocks = thread_ledger
+                                .num_blocks()
+                                .expect("Failed getting number of blocks in ledger");
+                            let from_index = max(num_blocks, last_block_index);
+                            log::debug!(
+                                thread_logger,
+                                "Subscribing to blocks of {} from block {}",
+                                conn,
+                                from_index
+                            );
+                            conn.write().subscribe_blocks(from_index)
+                        });
+
+                        match result {
+                            Ok(block_stream) => {
+                                let stream_result = receive_blocks(
+                                    block_stream,
+                                    thread_stop_receiver.clone(),
+                                    |block, block_contents, signature| {
+                                        add_block(
+                                            &thread_received,
+                                            &responder_id,
+                                            block,
+                                            block_contents,
+                                            signature,
+                                            &thread_logger,
+                                        )
+                                    },
+                                );
+                                if let Err(err) = stream_result {
+                                    log::warn!(
+                                        thread_logger,
+                                        "Block subscription to {} failed: {}",
+                                        conn,
+                                        err
+                                    );
+                                }
+                            }
+                            Err(err) => log::warn!(
+                                thread_logger,
+                                "Failed subscribing to blocks of {}: {}",
+                                conn,
+                                err
+                            ),
+                        }
+
+                        let &(ref lock, ref condvar) = &*thread_stop_requested;
+                        let (stop_requested, _timeout_result) = condvar
+                            .wait_timeout_until(
+                                lock.lock().expect("mutex poisoned"),
+                                RESUBSCRIBE_INTERVAL,
+                                |stop_requested| *stop_requested,
+                            )
+                            .expect("waiting on condvar failed");
+                        if *stop_requested {
+                            break;
+                        }
+                    })
+                    .expect("Failed spawning block subscription thread")
+            })
+            .collect();
+
+        Self {
+            received,
+            stop_requested,
+            stop_sender: Some(stop_sender),
+            join_handles,
+        }
+    }
+
+    /// Wait until a peer sends a block, or until `timeout` elapses.
+    ///
+    /// Returns the index of the latest block received from each peer since the last call.
+    pub fn wait(&self, timeout: Duration) -> HashMap<ResponderId, BlockIndex> {
+        let &(ref lock, ref condvar) = &*self.received;
+        let (mut received, _timeout_result) = condvar
+            .wait_timeout_until(lock.lock().expect("mutex poisoned"), timeout, |received| {
+                !received.latest_block_indexes.is_empty()
+            })
+            .expect("waiting on condvar failed");
+        received.latest_block_indexes.drain().collect()
+    }
+
+    /// Takes the valid blocks, and their contents, streamed since the last call.
+    pub fn take_blocks(&self) -> BTreeMap<BlockIndex, (Block, BlockContents)> {
+        let mut received = self.received.0.lock().expect("mutex poisoned");
+        std::mem::replace(&mut received.blocks, BTreeMap::new())
+    }
+
+    /// End every subscription, and wait for their threads to exit.
+    pub fn stop(&mut self) {
+        {
+            let &(ref lock, ref condvar) = &*self.stop_requested;
+            *lock.lock().expect("mutex poisoned") = true;
+            condvar.notify_all();
+        }
+        if let Some(stop_sender) = self.stop_sender.take() {
+            let _ = stop_sender.send(());
+        }
+        for join_handle in self.join_handles.drain(..) {
+            join_handle
+                .join()
+                .expect("Block subscription thread join failed");
+        }
+    }
+}
+
+impl Drop for BlockSubscriptions {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Passes each block of `block_stream` to `on_block`, until the stream ends or `stop` completes.
+fn receive_blocks(
+    block_stream: mc_connection::BlockStream,
+    stop: Shared<Receiver<()>>,
+    mut on_block: impl FnMut(Block, BlockContents, Option<BlockSignature>),
+) -> Result<(), ConnectionError> {
+    // Both streams end with None, so that whichever ends first ends the loop.
+    let stopped = stop.then(|_| Ok::<_, ConnectionError>(None)).into_stream();
+    let blocks = block_stream
+        .map(Some)
+        .chain(stream::once(Ok(None)))
+        .select(stopped);
+
+    for item in blocks.wait() {
+        match item? {
+            Some((block, block_contents, signature)) => on_block(block, block_contents, signature),
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+/// Records a block streamed by a peer, and keeps it if it is valid.
+fn add_block(
+    received: &(Mutex<Received>, Condvar),
+    responder_id: &ResponderId,
+    block: Block,
+    block_contents: BlockContents,
+    signature: Option<BlockSignature>,
+    logger: &Logger,
+) {
+    let &(ref lock, ref condvar) = received;
+    let mut received = lock.lock().expect("mutex poisoned");
+    received
+        .latest_block_indexes
+        .insert(responder_id.clone(), block.index);
+
+    if is_valid_block(&block, &block_contents, signature.as_ref()) {
+        if received.blocks.len() < MAX_STREAMED_BLOCKS {
+            received
+                .blocks
+                .entry(block.index)
+                .or_insert((block, block_contents));
+        }
+    } else {
+        log::warn!(
+            logger,
+            "{} streamed an invalid block {}",
+            responder_id,
+            block.index
+        );
+    }
+    condvar.notify_all();
+}
+
+/// Checks that a streamed block's ID, contents and signature, if any, are consistent.
+fn is_valid_block(
+    block: &Block,
+    block_contents: &BlockContents,
+    signature: Option<&BlockSignature>,
+) -> bool {
+    block.is_block_id_valid()
+        && block_contents.hash() == block.contents_hash
+        && signature.map_or(true, |signature| signature.verify(block).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_common::logger::test_with_logger;
+    use mc_connection_test_utils::{test_client_uri, MockBlockchainConnection};
+    use mc_ledger_db::test_utils::get_mock_ledger;
+
+    #[test_with_logger]
+    // The latest block sent by each peer is reported.
+    fn test_wait_for_blocks(logger: Logger) {
+        let ledger = get_mock_ledger(10);
+        let conn_manager = ConnectionManager::new(
+            vec![
+                MockBlockchainConnection::new(test_client_uri(1), get_mock_ledger(15), 0),
+                MockBlockchainConnection::new(test_client_uri(2), get_mock_ledger(12), 0),
+            ],
+            logger.clone(),
+        );
+
+        let mut block_subscriptions = BlockSubscriptions::new(ledger, &conn_manager, logger);
+
+        let mut block_indexes: HashMap<ResponderId, BlockIndex> = HashMap::default();
+        for _ in 0..10 {
+            block_indexes.extend(block_subscriptions.wait(Duration::from_secs(1)));
+            if block_indexes.len() == 2 {
+                break;
+            }
+        }
+
+        let responder_id = |node_id| test_client_uri(node_id).responder_id().unwrap();
+        assert_eq!(block_indexes.len(), 2);
+        assert_eq!(block_indexes[&responder_id(1)], 14);
+        assert_eq!(block_indexes[&responder_id(2)], 11);
+
+        // Subscriptions start from each peer's latest block.
+        let blocks = block_subscriptions.take_blocks();
+        assert_eq!(blocks.keys().cloned().collect::<Vec<_>>(), vec![11, 14]);
+        assert_eq!(blocks[&14].0, get_mock_ledger(15).get_block(14).unwrap());
+
+        block_subscriptions.stop();
+    }
+
+    #[test]
+    // Blocks whose ID or contents do not match are not kept.
+    fn test_is_valid_block() {
+        let ledger = get_mock_ledger(3);
+        let block = ledger.get_block(2).unwrap();
+        let block_contents = ledger.get_block_contents(2).unwrap();
+        assert!(is_valid_block(&block, &block_contents, None));
+
+        let other_contents = ledger.get_block_contents(1).unwrap();
+        assert!(!is_valid_block(&block, &other_contents, None));
+
+        let mut bad_block = block.clone();
+        bad_block.cumulative_txo_count += 1;
+        assert!(!is_valid_block(&bad_block, &block_contents, None));
+    }
+}
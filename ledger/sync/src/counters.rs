@@ -22,4 +22,18 @@ lazy_static::lazy_static! {
 
     // Time it takes to perform append_block
     pub static ref APPEND_BLOCK_TIME: Histogram = OP_COUNTERS.histogram("append_block");
+
+    // Time it takes to fetch a block from a transactions source. Per-source average latencies
+    // are exported as the "source_latency_ms" peer gauge.
+    pub static ref SOURCE_FETCH_TIME: Histogram = OP_COUNTERS.histogram("source_fetch");
+
+    // Failed fetches from transactions sources.
+    pub static ref SOURCE_FETCH_ERRORS_COUNT: IntCounter = OP_COUNTERS.counter("source_fetch_errors_count");
+
+    // Times a transactions source was quarantined for serving an invalid block. Currently
+    // quarantined sources are exported as the "source_quarantined" peer gauge.
+    pub static ref SOURCE_QUARANTINE_COUNT: IntCounter = OP_COUNTERS.counter("source_quarantine_count");
+
+    // Block contents fetched ahead, or past a block whose contents are missing, kept for the next attempt.
+    pub static ref PREFETCHED_BLOCKS: IntGauge = OP_COUNTERS.gauge("prefetched_blocks");
 }
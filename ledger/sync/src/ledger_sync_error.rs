@@ -37,6 +37,9 @@ pub enum LedgerSyncError {
 
     #[fail(display = "No transaction data.")]
     NoTransactionData,
+
+    #[fail(display = "Block {} does not have enough trusted signatures.", _0)]
+    InsufficientBlockSignatures(u64),
 }
 
 impl<TFE: TransactionFetcherError + 'static> From<TFE> for LedgerSyncError {
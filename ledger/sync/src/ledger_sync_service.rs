@@ -5,8 +5,8 @@
 //! (`TransactionsFetcher`) for fetching actual transaction data.
 
 use crate::{
-    counters, ledger_sync_error::LedgerSyncError, network_state_trait::NetworkState,
-    transactions_fetcher_trait::TransactionsFetcher,
+    block_signature_policy::BlockSignaturePolicy, counters, ledger_sync_error::LedgerSyncError,
+    network_state_trait::NetworkState, transactions_fetcher_trait::TransactionsFetcher,
 };
 use mc_common::{
     logger::{log, Logger},
@@ -17,14 +17,19 @@ use mc_connection::{
 };
 use mc_ledger_db::Ledger;
 use mc_transaction_core::{
-    compute_block_id, ring_signature::KeyImage, Block, BlockContents, BlockID, BlockIndex,
+    compute_block_id, ring_signature::KeyImage, validate_block_version, Block, BlockContents,
+    BlockID, BlockIndex, BlockSignature,
 };
 use mc_util_uri::ConnectionUri;
 use retry::delay::Fibonacci;
 use std::{
+    cmp::min,
     collections::BTreeMap,
     iter::FromIterator,
-    sync::{Arc, Condvar, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
     thread,
     time::{Duration, Instant},
 };
@@ -33,6 +38,15 @@ use std::{
 const DEFAULT_GET_BLOCKS_TIMEOUT: Duration = Duration::from_secs(30);
 const DEFAULT_GET_TRANSACTIONS_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// Default maximal number of blocks whose contents are fetched concurrently.
+const DEFAULT_MAX_CONCURRENT_FETCHES: usize = 5;
+
+/// Default number of blocks past each sync attempt whose contents are fetched in the background.
+const DEFAULT_LOOK_AHEAD_BLOCKS: usize = 10;
+
+/// Maximal number of blocks whose contents are kept for a later sync attempt.
+const MAX_PREFETCHED_BLOCKS: usize = 100;
+
 pub struct LedgerSyncService<L: Ledger, BC: BlockchainConnection, TF: TransactionsFetcher> {
     /// Local ledger database.
     ledger: L,
@@ -47,6 +61,22 @@ pub struct LedgerSyncService<L: Ledger, BC: BlockchainConnection, TF: Transactio
     get_blocks_timeout: Duration,
     get_transactions_timeout: Duration,
 
+    /// Trusted block signers, and how many of them must have signed each block.
+    block_signature_policy: BlockSignaturePolicy,
+
+    /// Maximal number of blocks whose contents are fetched concurrently.
+    max_concurrent_fetches: usize,
+
+    /// Number of blocks past each sync attempt whose contents are fetched in the background.
+    look_ahead_blocks: usize,
+
+    /// Set while a look-ahead fetch is running.
+    look_ahead_running: Arc<AtomicBool>,
+
+    /// Blocks and contents kept for a later sync attempt: fetched ahead, fetched past a block
+    /// whose contents could not be fetched, or added with `add_prefetched_block_contents`.
+    prefetched_block_contents: Arc<Mutex<BTreeMap<BlockIndex, (Block, BlockContents)>>>,
+
     /// Logger.
     logger: Logger,
 }
@@ -74,10 +104,57 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
             transactions_fetcher: Arc::new(transactions_fetcher),
             get_blocks_timeout: DEFAULT_GET_BLOCKS_TIMEOUT,
             get_transactions_timeout: DEFAULT_GET_TRANSACTIONS_TIMEOUT,
+            block_signature_policy: BlockSignaturePolicy::default(),
+            max_concurrent_fetches: DEFAULT_MAX_CONCURRENT_FETCHES,
+            look_ahead_blocks: DEFAULT_LOOK_AHEAD_BLOCKS,
+            look_ahead_running: Arc::new(AtomicBool::new(false)),
+            prefetched_block_contents: Arc::new(Mutex::new(BTreeMap::new())),
             logger,
         }
     }
 
+    /// Set which block signers are trusted, and how many of them must have signed a block before
+    /// it is appended. Signatures by trusted signers are stored in the ledger.
+    pub fn set_block_signature_policy(&mut self, block_signature_policy: BlockSignaturePolicy) {
+        self.block_signature_policy = block_signature_policy;
+    }
+
+    /// Set the maximal number of blocks whose contents are fetched concurrently.
+    pub fn set_max_concurrent_fetches(&mut self, max_concurrent_fetches: usize) {
+        assert!(max_concurrent_fetches > 0);
+        self.max_concurrent_fetches = max_concurrent_fetches;
+    }
+
+    /// Set the number of blocks past each sync attempt whose contents are fetched in the
+    /// background, so that the next attempt does not wait for them. Zero disables looking ahead.
+    pub fn set_look_ahead_blocks(&mut self, look_ahead_blocks: usize) {
+        assert!(look_ahead_blocks <= MAX_PREFETCHED_BLOCKS);
+        self.look_ahead_blocks = look_ahead_blocks;
+    }
+
+    /// Add blocks and contents received from elsewhere, e.g. from block subscriptions, so that
+    /// the next sync attempt does not fetch their contents again. Their contents are only used if
+    /// a sufficient set of peers agree on the block.
+    ///
+    /// # Arguments
+    /// * `blocks_and_contents` - Blocks, and their contents, by block index.
+    pub fn add_prefetched_block_contents(
+        &mut self,
+        mut blocks_and_contents: BTreeMap<BlockIndex, (Block, BlockContents)>,
+    ) {
+        let num_blocks = self
+            .ledger
+            .num_blocks()
+            .expect("Failed getting number of blocks in ledger");
+        keep_prefetched_block_contents(
+            &mut self
+                .prefetched_block_contents
+                .lock()
+                .expect("mutex poisoned"),
+            blocks_and_contents.split_off(&num_blocks),
+        );
+    }
+
     /// Check if our ledger is currently behind.
     pub fn is_behind<NS: NetworkState>(&self, network_state: &NS) -> bool {
         let num_blocks: u64 = self
@@ -96,13 +173,16 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
     ///
     /// 1. Get blocks from peers.
     /// 2. Identify blocks that are “potentially safe”, and the peers who have them.
-    /// 3. Download transactions for “potentially safe” blocks.
+    /// 3. Download transactions for “potentially safe” blocks, and start downloading those of the
+    ///    next `look_ahead_blocks` potentially safe blocks in the background, for the next attempt.
     /// 4. Identify “safe” blocks (and their transactions). Each block satisfies:
     ///     * A sufficient set of peers have externalized the block,
     ///     * The block is part of a blockchain of safe blocks, rooted at the highest block in the local node’s ledger,
     ///     * The block’s ID agrees with the merkle hash of its transactions,
     ///     * None of the key images in the block have appeared before.
-    /// 5. Append safe blocks to the ledger.
+    /// 5. Collect signatures for safe blocks, if the block signature policy trusts any signer.
+    /// 6. Append safe blocks that satisfy the block signature policy to the ledger, along with
+    ///    their trusted signatures.
     ///
     /// # Arguments
     /// * `network_state` - Current state of the network, used to determine if we're behind.
@@ -112,9 +192,15 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
         network_state: &impl NetworkState,
         limit: u32,
     ) -> Result<(), LedgerSyncError> {
-        let (responder_ids, _, potentially_safe_blocks) = self
-            .get_potentially_safe_blocks(network_state, limit)
+        let (responder_ids, _, mut potentially_safe_blocks) = self
+            .get_potentially_safe_blocks(
+                network_state,
+                limit.saturating_add(self.look_ahead_blocks as u32),
+            )
             .ok_or(LedgerSyncError::NoSafeBlocks)?;
+        potentially_safe_blocks.sort_by_key(|block| block.index);
+        let look_ahead_blocks =
+            potentially_safe_blocks.split_off(min(limit as usize, potentially_safe_blocks.len()));
 
         if potentially_safe_blocks.is_empty() {
             return Err(LedgerSyncError::EmptyBlockVec);
@@ -122,45 +208,60 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
 
         let num_potentially_safe_blocks = potentially_safe_blocks.len();
 
+        // Reuse contents fetched by previous attempts, as long as their block is unchanged.
+        let mut block_index_to_opt_transactions: BTreeMap<BlockIndex, Option<BlockContents>> =
+            BTreeMap::new();
+        let mut blocks_to_fetch: Vec<Block> = Vec::new();
+        {
+            let mut prefetched_block_contents = self
+                .prefetched_block_contents
+                .lock()
+                .expect("mutex poisoned");
+            for block in potentially_safe_blocks.iter() {
+                match prefetched_block_contents.remove(&block.index) {
+                    Some((prefetched_block, block_contents)) if prefetched_block == *block => {
+                        block_index_to_opt_transactions.insert(block.index, Some(block_contents));
+                    }
+                    _ => blocks_to_fetch.push(block.clone()),
+                }
+            }
+            // Only contents past the potentially safe blocks may still be of use.
+            let next_block_index = potentially_safe_blocks
+                .iter()
+                .map(|block| block.index + 1)
+                .max()
+                .unwrap_or(0);
+            *prefetched_block_contents = prefetched_block_contents.split_off(&next_block_index);
+            counters::PREFETCHED_BLOCKS.set(prefetched_block_contents.len() as i64);
+        }
+
+        self.start_look_ahead(&responder_ids, look_ahead_blocks);
+
         // Get transactions.
-        let block_index_to_opt_transactions: BTreeMap<BlockIndex, Option<BlockContents>> =
-            get_block_contents(
+        if !blocks_to_fetch.is_empty() {
+            block_index_to_opt_transactions.extend(get_block_contents(
                 self.transactions_fetcher.clone(),
                 &responder_ids,
-                &potentially_safe_blocks,
+                &blocks_to_fetch,
                 self.get_transactions_timeout,
+                self.max_concurrent_fetches,
                 &self.logger,
-            );
-
-        let mut blocks_and_contents: Vec<(Block, BlockContents)> = Vec::new();
-
-        {
-            // Populate `blocks_with_transactions`. This just returns all (block, transactions) until
-            // it reaches a None.
-            let mut block_index_to_transactions: BTreeMap<BlockIndex, BlockContents> =
-                block_index_to_opt_transactions
-                    .into_iter()
-                    .take_while(|(_block_index, transactions_opt)| transactions_opt.is_some())
-                    .map(|(block_index, transactions_opt)| (block_index, transactions_opt.unwrap()))
-                    .collect();
-
-            let block_index_to_block: BTreeMap<BlockIndex, Block> = potentially_safe_blocks
-                .into_iter()
-                .map(|block| (block.index, block))
-                .collect();
-
-            // Join blocks and transactions, allowing for the possibility that transactions
-            // may not be available for some blocks due to failed network requests for transactions.
-            for (block_index, block) in block_index_to_block {
-                if let Some(block_contents) = block_index_to_transactions.remove(&block_index) {
-                    blocks_and_contents.push((block, block_contents));
-                } else {
-                    log::error!(self.logger, "No transactions for block {:?}", block);
-                    break;
-                }
-            }
+            ));
         }
 
+        let (blocks_and_contents, prefetched_block_contents) = join_block_contents(
+            potentially_safe_blocks,
+            block_index_to_opt_transactions,
+            &self.logger,
+        );
+        keep_prefetched_block_contents(
+            &mut self
+                .prefetched_block_contents
+                .lock()
+                .expect("mutex poisoned"),
+            prefetched_block_contents,
+        );
+
         if blocks_and_contents.is_empty() {
             log::error!(
                 self.logger,
@@ -179,7 +280,22 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
         if let Ok(safe_blocks) =
             identify_safe_blocks(&self.ledger, &blocks_and_contents, &self.logger)
         {
-            self.append_safe_blocks(&safe_blocks)?;
+            let signatures = collect_trusted_signatures(
+                self.transactions_fetcher.clone(),
+                &responder_ids,
+                &safe_blocks,
+                &self.block_signature_policy,
+                self.max_concurrent_fetches,
+                &self.logger,
+            );
+            let num_signed_blocks = signatures.len();
+            self.append_safe_blocks(&safe_blocks[..num_signed_blocks], &signatures)?;
+
+            if let Some((unsigned_block, _contents)) = safe_blocks.get(num_signed_blocks) {
+                return Err(LedgerSyncError::InsufficientBlockSignatures(
+                    unsigned_block.index,
+                ));
+            }
         } else {
             log::info!(self.logger, "No safe blocks.");
         }
@@ -187,6 +303,72 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
         Ok(())
     }
 
+    /// Fetches the contents of `blocks` in the background, and keeps them for a later sync
+    /// attempt. Only one look-ahead fetch runs at a time, and blocks whose contents are already
+    /// kept are skipped.
+    ///
+    /// # Arguments
+    /// * `responder_ids` - Peers that agree on `blocks`.
+    /// * `blocks` - Potentially safe blocks past the current sync attempt.
+    fn start_look_ahead(&self, responder_ids: &[ResponderId], blocks: Vec<Block>) {
+        if self.look_ahead_running.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let blocks: Vec<Block> = {
+            let prefetched_block_contents = self
+                .prefetched_block_contents
+                .lock()
+                .expect("mutex poisoned");
+            blocks
+                .into_iter()
+                .filter(|block| !prefetched_block_contents.contains_key(&block.index))
+                .take(MAX_PREFETCHED_BLOCKS.saturating_sub(prefetched_block_contents.len()))
+                .collect()
+        };
+        if blocks.is_empty() {
+            return;
+        }
+        log::debug!(
+            self.logger,
+            "Looking ahead at the contents of {} blocks",
+            blocks.len()
+        );
+
+        self.look_ahead_running.store(true, Ordering::SeqCst);
+        let running = self.look_ahead_running.clone();
+        let prefetched_block_contents = self.prefetched_block_contents.clone();
+        let transactions_fetcher = self.transactions_fetcher.clone();
+        let responder_ids = responder_ids.to_vec();
+        let timeout = self.get_transactions_timeout;
+        let max_concurrent_fetches = self.max_concurrent_fetches;
+        let logger = self.logger.clone();
+        thread::Builder::new()
+            .name("LookAhead".to_string())
+            .spawn(move || {
+                let mut block_index_to_opt_contents = get_block_contents(
+                    transactions_fetcher,
+                    &responder_ids,
+                    &blocks,
+                    timeout,
+                    max_concurrent_fetches,
+                    &logger,
+                );
+                let blocks_and_contents = blocks.into_iter().filter_map(|block| {
+                    block_index_to_opt_contents
+                        .remove(&block.index)
+                        .flatten()
+                        .map(|block_contents| (block.index, (block, block_contents)))
+                });
+                keep_prefetched_block_contents(
+                    &mut prefetched_block_contents.lock().expect("mutex poisoned"),
+                    blocks_and_contents,
+                );
+                running.store(false, Ordering::SeqCst);
+            })
+            .expect("Failed spawning look-ahead thread");
+    }
+
     /// Identifies Blocks that are potentially safe to append to the local ledger.
     ///
     /// A block is "potentially safe" if it is part of a chain of blocks rooted on the highest block
@@ -288,9 +470,14 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
     }
 
     /// Append safe blocks to the local ledger.
+    ///
+    /// # Arguments
+    /// * `blocks_and_contents` - Safe blocks, in increasing order of block number.
+    /// * `signatures` - Trusted signatures over each block.
     fn append_safe_blocks(
         &mut self,
         blocks_and_contents: &[(Block, BlockContents)],
+        signatures: &[Vec<BlockSignature>],
     ) -> Result<(), LedgerSyncError> {
         log::info!(
             self.logger,
@@ -306,10 +493,15 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
             blocks_and_contents.len()
         );
 
-        for (block, contents) in blocks_and_contents {
+        for ((block, contents), block_signatures) in blocks_and_contents.iter().zip(signatures) {
             {
                 let _timer = counters::APPEND_BLOCK_TIME.start_timer();
-                self.ledger.append_block(block, contents, None)?;
+                self.ledger.append_block_with_signatures(
+                    block,
+                    contents,
+                    None,
+                    block_signatures,
+                )?;
             }
 
             // FIXME: MC-365 Move ledger counters into ledger_db
@@ -417,7 +609,7 @@ fn get_blocks<BC: BlockchainConnection + 'static>(
     )
 }
 
-fn verify_block_ids(
+pub(crate) fn verify_block_ids(
     blocks: Vec<Block>,
     append_after_block: &Block,
 ) -> Result<Vec<Block>, LedgerSyncError> {
@@ -484,6 +676,7 @@ fn group_by_block(
 ///                     `blocks` we want to fetch.
 /// * `blocks` - List of blocks to fetch transactions for.
 /// * `timeout` - Overall request timeout.
+/// * `max_concurrent_fetches` - Maximal number of blocks fetched concurrently.
 ///
 /// Peers are queried concurrently. Currently, this method will run indefinitely until all
 /// transactions have been retrieved.
@@ -492,6 +685,7 @@ fn get_block_contents<TF: TransactionsFetcher + 'static>(
     safe_responder_ids: &[ResponderId],
     blocks: &[Block],
     timeout: Duration,
+    max_concurrent_fetches: usize,
     logger: &Logger,
 ) -> BTreeMap<BlockIndex, Option<BlockContents>> {
     type ResultsMap = BTreeMap<BlockIndex, Option<BlockContents>>;
@@ -525,8 +719,7 @@ fn get_block_contents<TF: TransactionsFetcher + 'static>(
     // Spawn worker threads.
     let mut thread_handles = Vec::new();
 
-    // TODO: hardcoded limit
-    let num_workers = std::cmp::min(5, blocks.len());
+    let num_workers = std::cmp::min(max_concurrent_fetches, blocks.len());
     for worker_num in 0..num_workers {
         let thread_results_and_condvar = results_and_condvar.clone();
         let thread_sender = sender.clone();
@@ -677,6 +870,169 @@ fn get_block_contents<TF: TransactionsFetcher + 'static>(
     results.clone()
 }
 
+/// Joins blocks with their contents, up to the first block whose contents are missing.
+///
+/// Returns the joined blocks, in increasing order of block number, and the blocks and contents
+/// that follow the first missing contents, which are kept for a later attempt.
+///
+/// # Arguments
+/// * `blocks` - Blocks to join.
+/// * `block_index_to_opt_contents` - Contents of the blocks, if they could be fetched.
+fn join_block_contents(
+    mut blocks: Vec<Block>,
+    mut block_index_to_opt_contents: BTreeMap<BlockIndex, Option<BlockContents>>,
+    logger: &Logger,
+) -> (
+    Vec<(Block, BlockContents)>,
+    BTreeMap<BlockIndex, (Block, BlockContents)>,
+) {
+    let mut blocks_and_contents: Vec<(Block, BlockContents)> = Vec::new();
+    let mut prefetched: BTreeMap<BlockIndex, (Block, BlockContents)> = BTreeMap::new();
+    let mut found_missing_contents = false;
+
+    blocks.sort_by_key(|block| block.index);
+    for block in blocks {
+        match block_index_to_opt_contents.remove(&block.index).flatten() {
+            Some(block_contents) if !found_missing_contents => {
+                blocks_and_contents.push((block, block_contents));
+            }
+            Some(block_contents) => {
+                prefetched.insert(block.index, (block, block_contents));
+            }
+            None => {
+                if !found_missing_contents {
+                    log::error!(logger, "No transactions for block {:?}", block);
+                }
+                found_missing_contents = true;
+            }
+        }
+    }
+
+    (blocks_and_contents, prefetched)
+}
+
+/// Keeps blocks and contents for a later sync attempt, up to MAX_PREFETCHED_BLOCKS of them.
+/// Contents already kept for a block are not replaced.
+fn keep_prefetched_block_contents(
+    prefetched_block_contents: &mut BTreeMap<BlockIndex, (Block, BlockContents)>,
+    blocks_and_contents: impl IntoIterator<Item = (BlockIndex, (Block, BlockContents))>,
+) {
+    for (block_index, block_and_contents) in blocks_and_contents {
+        if prefetched_block_contents.len() >= MAX_PREFETCHED_BLOCKS {
+            break;
+        }
+        prefetched_block_contents
+            .entry(block_index)
+            .or_insert(block_and_contents);
+    }
+    counters::PREFETCHED_BLOCKS.set(prefetched_block_contents.len() as i64);
+}
+
+/// Collects the trusted signatures over each block, as required by `policy`.
+///
+/// Returns the trusted signatures over the longest prefix of `blocks_and_contents` in which each
+/// block satisfies `policy`. If `policy` does not trust any signer, no signatures are fetched and
+/// every block is returned with no signatures.
+///
+/// # Arguments
+/// * `transactions_fetcher` - The mechanism used for fetching block signatures.
+/// * `safe_responder_ids` - ResponderIds that have been identified as agreeing with eachother on the
+///                     blocks.
+/// * `blocks_and_contents` - Safe blocks, in increasing order of block number.
+/// * `policy` - Trusted block signers, and how many of them must have signed each block.
+/// * `max_concurrent_fetches` - Maximal number of blocks whose signatures are fetched concurrently.
+fn collect_trusted_signatures<TF: TransactionsFetcher + 'static>(
+    transactions_fetcher: Arc<TF>,
+    safe_responder_ids: &[ResponderId],
+    blocks_and_contents: &[(Block, BlockContents)],
+    policy: &BlockSignaturePolicy,
+    max_concurrent_fetches: usize,
+    logger: &Logger,
+) -> Vec<Vec<BlockSignature>> {
+    if !policy.collects_signatures() {
+        return vec![Vec::new(); blocks_and_contents.len()];
+    }
+
+    // Fetch the signatures over each block concurrently.
+    let (sender, receiver) = crossbeam_channel::bounded(blocks_and_contents.len());
+    for (block, _contents) in blocks_and_contents {
+        sender
+            .send(block.clone())
+            .expect("failed sending to channel");
+    }
+    drop(sender);
+
+    let signers = policy.trusted_signers();
+    let results: Arc<Mutex<HashMap<BlockIndex, Vec<BlockSignature>>>> = Default::default();
+    let num_workers = std::cmp::min(max_concurrent_fetches, blocks_and_contents.len());
+    let thread_handles: Vec<_> = (0..num_workers)
+        .map(|worker_num| {
+            let thread_receiver = receiver.clone();
+            let thread_results = results.clone();
+            let thread_transactions_fetcher = transactions_fetcher.clone();
+            let thread_safe_responder_ids = safe_responder_ids.to_owned();
+            let thread_signers = signers.clone();
+            let thread_logger = logger.clone();
+            thread::Builder::new()
+                .name(format!("GetSigs:{}", worker_num))
+                .spawn(move || {
+                    for block in thread_receiver.iter() {
+                        let signatures = thread_transactions_fetcher
+                            .get_block_signatures(
+                                &thread_safe_responder_ids,
+                                &block,
+                                &thread_signers,
+                            )
+                            .unwrap_or_else(|err| {
+                                log::error!(
+                                    thread_logger,
+                                    "Failed getting signatures for block {}: {:?}",
+                                    block.index,
+                                    err
+                                );
+                                Vec::new()
+                            });
+                        thread_results
+                            .lock()
+                            .expect("mutex poisoned")
+                            .insert(block.index, signatures);
+                    }
+                })
+                .expect("Failed spawning GetSigs thread")
+        })
+        .collect();
+
+    for thread_handle in thread_handles {
+        if let Err(err) = thread_handle.join() {
+            log::error!(
+                logger,
+                "Failed joining get_block_signatures worker thread: {:?}",
+                err
+            );
+        }
+    }
+    let mut signatures_by_block = results.lock().expect("mutex poisoned");
+
+    let mut trusted_signatures_by_block = Vec::with_capacity(blocks_and_contents.len());
+    for (block, _contents) in blocks_and_contents {
+        let signatures = signatures_by_block.remove(&block.index).unwrap_or_default();
+
+        let trusted_signatures = policy.trusted_signatures(block, &signatures);
+        if !policy.is_satisfied_by(&trusted_signatures) {
+            log::error!(
+                logger,
+                "Block {} has {} trusted signatures, {} are required",
+                block.index,
+                trusted_signatures.len(),
+                policy.min_trusted_signatures(),
+            );
+            break;
+        }
+        trusted_signatures_by_block.push(trusted_signatures);
+    }
+    trusted_signatures_by_block
+}
+
 /// Identify a sequence of blocks that are safe to append to the local node's ledger.
 ///
 /// A "safe" block satisfies:
@@ -729,6 +1085,7 @@ fn identify_safe_blocks<L: Ledger>(
             block.cumulative_txo_count,
             &block.root_element,
             &block_contents.hash(),
+            block.timestamp,
         );
 
         // The block's ID must agree with the merkle hash of its transactions.
@@ -742,6 +1099,12 @@ fn identify_safe_blocks<L: Ledger>(
             break;
         }
 
+        // The block must follow the rules of its version.
+        if let Err(e) = validate_block_version(block, block_contents, Some(&last_safe_block)) {
+            log::error!(logger, "Invalid block version in block {:?}: {}", block, e);
+            break;
+        }
+
         // No key images in the block may have been previously seen.
         for key_image in &block_contents.key_images {
             // Check if the key image is already in the local ledger.
@@ -795,8 +1158,12 @@ mod tests {
     use crate::{test_utils::MockTransactionsFetcher, SCPNetworkState};
     use mc_common::{logger::test_with_logger, NodeID};
     use mc_consensus_scp::{core_types::Ballot, msg::*, *};
+    use mc_crypto_keys::Ed25519Pair;
     use mc_ledger_db::test_utils::{get_mock_ledger, get_test_ledger_blocks};
     use mc_peers_test_utils::{test_node_id, test_peer_uri, MockPeerConnection};
+    use mc_transaction_core::BLOCK_VERSION;
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
     use std::convert::TryFrom;
 
     #[test_with_logger]
@@ -816,6 +1183,72 @@ mod tests {
         assert_eq!(sync_service.is_behind(&network_state), false);
     }
 
+    #[test_with_logger]
+    // Only blocks past the end of the ledger should be kept, up to MAX_PREFETCHED_BLOCKS.
+    fn test_add_prefetched_block_contents(logger: Logger) {
+        let ledger = get_mock_ledger(10);
+        let conn_manager = ConnectionManager::<MockPeerConnection>::new(vec![], logger.clone());
+        let transactions_fetcher = MockTransactionsFetcher::new(ledger.clone());
+        let mut sync_service =
+            LedgerSyncService::new(ledger, conn_manager, transactions_fetcher, logger.clone());
+
+        let (block, block_contents) = get_test_ledger_blocks(1).pop().unwrap();
+        let blocks_and_contents: BTreeMap<BlockIndex, (Block, BlockContents)> = (5..200)
+            .map(|block_index| (block_index, (block.clone(), block_contents.clone())))
+            .collect();
+        sync_service.add_prefetched_block_contents(blocks_and_contents);
+
+        let block_indexes: Vec<BlockIndex> = sync_service
+            .prefetched_block_contents
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        assert_eq!(
+            block_indexes,
+            (10..10 + MAX_PREFETCHED_BLOCKS as u64).collect::<Vec<_>>()
+        );
+    }
+
+    #[test_with_logger]
+    // Looking ahead fetches the contents of the given blocks in the background.
+    fn test_start_look_ahead(logger: Logger) {
+        let network_ledger = get_mock_ledger(20);
+        let conn_manager = ConnectionManager::<MockPeerConnection>::new(vec![], logger.clone());
+        let transactions_fetcher = MockTransactionsFetcher::new(network_ledger.clone());
+        let sync_service = LedgerSyncService::new(
+            get_mock_ledger(10),
+            conn_manager,
+            transactions_fetcher,
+            logger.clone(),
+        );
+
+        let blocks: Vec<Block> = (10..15)
+            .map(|block_index| network_ledger.get_block(block_index).unwrap())
+            .collect();
+        sync_service.start_look_ahead(&[], blocks);
+        for _ in 0..100 {
+            if !sync_service.look_ahead_running.load(Ordering::SeqCst) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        let prefetched_block_contents = sync_service.prefetched_block_contents.lock().unwrap();
+        assert_eq!(
+            prefetched_block_contents
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>(),
+            (10..15).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            prefetched_block_contents[&12].1,
+            network_ledger.get_block_contents(12).unwrap()
+        );
+    }
+
     // A blocking set of peers on a higher slot isn't enough to consider this node "behind".
     #[test_with_logger]
     fn test_is_behind(logger: Logger) {
@@ -951,6 +1384,7 @@ mod tests {
             &responder_ids.as_slice(),
             &blocks,
             Duration::from_secs(1),
+            DEFAULT_MAX_CONCURRENT_FETCHES,
             &logger,
         );
 
@@ -1029,6 +1463,7 @@ mod tests {
             &responder_ids.as_slice(),
             &blocks,
             Duration::from_secs(1),
+            DEFAULT_MAX_CONCURRENT_FETCHES,
             &logger,
         );
 
@@ -1316,6 +1751,66 @@ mod tests {
         );
     }
 
+    #[test_with_logger]
+    // Signatures are collected up to the first block that does not satisfy the policy.
+    fn test_collect_trusted_signatures(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let trusted_a = Ed25519Pair::from_random(&mut rng);
+        let trusted_b = Ed25519Pair::from_random(&mut rng);
+        let untrusted = Ed25519Pair::from_random(&mut rng);
+
+        let mut source_ledger = get_mock_ledger(5);
+        let blocks_and_contents = get_test_ledger_blocks(5);
+        let sign = |block_index: usize, signer: &Ed25519Pair| {
+            BlockSignature::from_block_and_keypair(&blocks_and_contents[block_index].0, signer)
+                .unwrap()
+        };
+        source_ledger
+            .add_collected_block_signatures(1, &[sign(1, &trusted_a), sign(1, &trusted_b)])
+            .unwrap();
+        source_ledger
+            .add_collected_block_signatures(2, &[sign(2, &untrusted), sign(2, &trusted_b)])
+            .unwrap();
+        source_ledger
+            .add_collected_block_signatures(3, &[sign(3, &untrusted)])
+            .unwrap();
+        source_ledger
+            .add_collected_block_signatures(4, &[sign(4, &trusted_a)])
+            .unwrap();
+        let transactions_fetcher = Arc::new(MockTransactionsFetcher::new(source_ledger));
+
+        // Without trusted signers, no signatures are collected.
+        let signatures = collect_trusted_signatures(
+            transactions_fetcher.clone(),
+            &[],
+            &blocks_and_contents[1..],
+            &BlockSignaturePolicy::default(),
+            2,
+            &logger,
+        );
+        assert_eq!(signatures, vec![Vec::new(); 4]);
+
+        // Block 3 has no trusted signature.
+        let policy =
+            BlockSignaturePolicy::new(vec![trusted_a.public_key(), trusted_b.public_key()], 1)
+                .unwrap();
+        let signatures = collect_trusted_signatures(
+            transactions_fetcher,
+            &[],
+            &blocks_and_contents[1..],
+            &policy,
+            2,
+            &logger,
+        );
+        assert_eq!(
+            signatures,
+            vec![
+                vec![sign(1, &trusted_a), sign(1, &trusted_b)],
+                vec![sign(2, &trusted_b)],
+            ]
+        );
+    }
+
     #[test_with_logger]
     // A block with invalid parent_id is not safe.
     fn test_identify_safe_blocks_wrong_parent_id(logger: Logger) {
@@ -1442,6 +1937,52 @@ mod tests {
         assert_eq!(safe_blocks.len(), 0);
     }
 
+    /// Rebuilds the chain from `blocks_and_contents[from_index]` onwards using `version`, with
+    /// sorted block contents.
+    fn with_version_from(
+        blocks_and_contents: &[(Block, BlockContents)],
+        from_index: usize,
+        version: u32,
+    ) -> Vec<(Block, BlockContents)> {
+        let mut result: Vec<(Block, BlockContents)> = blocks_and_contents[..from_index].to_vec();
+        for (block, block_contents) in &blocks_and_contents[from_index..] {
+            let (parent, _) = result.last().unwrap();
+            let mut block_contents = block_contents.clone();
+            block_contents.sort();
+            let block =
+                Block::new_with_parent(version, parent, &block.root_element, &block_contents);
+            result.push((block, block_contents));
+        }
+        result
+    }
+
+    #[test_with_logger]
+    // Blocks that upgrade to a newer, supported block version should be safe.
+    fn test_identify_safe_blocks_across_version_upgrade(logger: Logger) {
+        let local_ledger = get_mock_ledger(1);
+        let blocks_and_contents = with_version_from(&get_test_ledger_blocks(5), 3, 1);
+
+        let safe_blocks = identify_safe_blocks(&local_ledger, &blocks_and_contents[1..], &logger)
+            .expect("All inputs blocks should be safe.");
+        assert_eq!(safe_blocks.len(), 4);
+        assert_eq!(safe_blocks.last().unwrap().0.version, 1);
+    }
+
+    #[test_with_logger]
+    // A block with a lower version than its parent is not safe.
+    fn test_identify_safe_blocks_version_downgrade(logger: Logger) {
+        let local_ledger = get_mock_ledger(1);
+        let upgraded = with_version_from(&get_test_ledger_blocks(5), 2, 1);
+        let blocks_and_contents = with_version_from(&upgraded, 3, BLOCK_VERSION);
+
+        let safe_blocks = identify_safe_blocks(&local_ledger, &blocks_and_contents[1..], &logger)
+            .expect("All inputs blocks should be safe.");
+
+        // Blocks one and two are safe, but block three downgrades the version.
+        assert_eq!(safe_blocks.len(), 2);
+        assert_eq!(safe_blocks.last().unwrap().0.index, 2);
+    }
+
     #[test]
     // Without a fork, nodes contain subsets of the longest blockchain. For each slot (aka, block index),
     // `group_by_block` should return a single group of nodes who have externalized a block for that slot.
@@ -1512,4 +2053,41 @@ mod tests {
             assert!(nodes.contains(&test_peer_uri(2).responder_id().unwrap()));
         }
     }
+
+    #[test_with_logger]
+    // Blocks should be joined with their contents up to the first missing contents. The contents
+    // of later blocks should be kept.
+    fn test_join_block_contents(logger: Logger) {
+        let blocks_and_contents = get_test_ledger_blocks(6);
+        let blocks: Vec<Block> = blocks_and_contents
+            .iter()
+            .rev()
+            .map(|(block, _contents)| block.clone())
+            .collect();
+
+        let block_index_to_opt_contents: BTreeMap<BlockIndex, Option<BlockContents>> =
+            blocks_and_contents
+                .iter()
+                .filter(|(block, _contents)| block.index != 4)
+                .map(|(block, contents)| {
+                    if block.index == 2 {
+                        (block.index, None)
+                    } else {
+                        (block.index, Some(contents.clone()))
+                    }
+                })
+                .collect();
+
+        let (joined, prefetched) =
+            join_block_contents(blocks, block_index_to_opt_contents, &logger);
+
+        assert_eq!(joined, blocks_and_contents[0..2].to_vec());
+        assert_eq!(
+            prefetched.into_iter().collect::<Vec<_>>(),
+            vec![
+                (3, blocks_and_contents[3].clone()),
+                (5, blocks_and_contents[5].clone())
+            ]
+        );
+    }
 }
@@ -3,9 +3,12 @@
 //! An integration between `PollingNetworkState` and `LedgerSyncService` that performs the sync in
 //! a background thread.
 
-use crate::{LedgerSyncService, PollingNetworkState, TransactionsFetcher};
+use crate::{
+    BlockSignaturePolicy, BlockSubscriptions, LedgerSyncService, PollingNetworkState,
+    TransactionsFetcher,
+};
 use mc_common::logger::{log, Logger};
-use mc_connection::{BlockchainConnection, ConnectionManager};
+use mc_connection::{BlockStreamConnection, BlockchainConnection, ConnectionManager};
 use mc_ledger_db::Ledger;
 use std::{
     sync::{
@@ -13,7 +16,7 @@ use std::{
         Arc, Mutex,
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 /// Maximal number of blocks to attempt to sync at each loop iteration.
@@ -35,15 +38,71 @@ impl LedgerSyncServiceThread {
         manager: ConnectionManager<BC>,
         network_state: Arc<Mutex<PollingNetworkState<BC>>>,
         transactions_fetcher: TF,
+        block_signature_policy: BlockSignaturePolicy,
+        poll_interval: Duration,
+        logger: Logger,
+    ) -> Self {
+        Self::start(
+            ledger,
+            manager,
+            network_state,
+            transactions_fetcher,
+            block_signature_policy,
+            poll_interval,
+            None,
+            logger,
+        )
+    }
+
+    /// Like `new`, but learns about new blocks from block subscriptions to every peer instead of
+    /// polling them. Peers are still polled every `poll_interval` if no block arrives.
+    pub fn new_with_block_subscriptions<
+        L: Ledger + 'static,
+        BC: BlockStreamConnection + 'static,
+        TF: TransactionsFetcher + 'static,
+    >(
+        ledger: L,
+        manager: ConnectionManager<BC>,
+        network_state: Arc<Mutex<PollingNetworkState<BC>>>,
+        transactions_fetcher: TF,
+        block_signature_policy: BlockSignaturePolicy,
+        poll_interval: Duration,
+        logger: Logger,
+    ) -> Self {
+        let block_subscriptions = BlockSubscriptions::new(ledger.clone(), &manager, logger.clone());
+        Self::start(
+            ledger,
+            manager,
+            network_state,
+            transactions_fetcher,
+            block_signature_policy,
+            poll_interval,
+            Some(block_subscriptions),
+            logger,
+        )
+    }
+
+    fn start<
+        L: Ledger + 'static,
+        BC: BlockchainConnection + 'static,
+        TF: TransactionsFetcher + 'static,
+    >(
+        ledger: L,
+        manager: ConnectionManager<BC>,
+        network_state: Arc<Mutex<PollingNetworkState<BC>>>,
+        transactions_fetcher: TF,
+        block_signature_policy: BlockSignaturePolicy,
         poll_interval: Duration,
+        block_subscriptions: Option<BlockSubscriptions>,
         logger: Logger,
     ) -> Self {
-        let ledger_sync_service = LedgerSyncService::new(
+        let mut ledger_sync_service = LedgerSyncService::new(
             ledger.clone(),
             manager,
             transactions_fetcher,
             logger.clone(),
         );
+        ledger_sync_service.set_block_signature_policy(block_signature_policy);
 
         let currently_behind = Arc::new(AtomicBool::new(false));
         let stop_requested = Arc::new(AtomicBool::new(false));
@@ -59,6 +118,7 @@ impl LedgerSyncServiceThread {
                         ledger_sync_service,
                         network_state,
                         poll_interval,
+                        block_subscriptions,
                         thread_currently_behind,
                         thread_stop_requested,
                         logger,
@@ -94,25 +154,34 @@ impl LedgerSyncServiceThread {
         mut ledger_sync_service: LedgerSyncService<L, BC, TF>,
         network_state: Arc<Mutex<PollingNetworkState<BC>>>,
         poll_interval: Duration,
+        block_subscriptions: Option<BlockSubscriptions>,
         currently_behind: Arc<AtomicBool>,
         stop_requested: Arc<AtomicBool>,
         logger: Logger,
     ) {
         log::debug!(logger, "LedgerSyncServiceThread has started.");
 
+        let mut last_poll: Option<Instant> = None;
+
         loop {
             if stop_requested.load(Ordering::SeqCst) {
                 log::debug!(logger, "LedgerSyncServiceThread stop requested.");
                 break;
             }
 
-            // See if we're currently behind. If we're not, poll to be sure.
+            // See if we're currently behind. If we're not, poll to be sure, unless block
+            // subscriptions keep us up to date and we polled recently.
             let is_behind = {
                 let mut network_state = network_state.lock().expect("mutex poisoned");
                 if ledger_sync_service.is_behind(&*network_state) {
                     true
+                } else if block_subscriptions.is_some()
+                    && last_poll.map_or(false, |last_poll| last_poll.elapsed() < poll_interval)
+                {
+                    false
                 } else {
                     network_state.poll();
+                    last_poll = Some(Instant::now());
                     ledger_sync_service.is_behind(&*network_state)
                 }
             };
@@ -140,7 +209,18 @@ impl LedgerSyncServiceThread {
                     "Sleeping, num_blocks = {}...",
                     ledger.num_blocks().unwrap()
                 );
-                std::thread::sleep(poll_interval);
+                match &block_subscriptions {
+                    Some(block_subscriptions) => {
+                        let block_indexes = block_subscriptions.wait(poll_interval);
+                        let mut network_state = network_state.lock().expect("mutex poisoned");
+                        for (responder_id, block_index) in block_indexes {
+                            network_state.push_block_index(&responder_id, block_index);
+                        }
+                        ledger_sync_service
+                            .add_prefetched_block_contents(block_subscriptions.take_blocks());
+                    }
+                    None => std::thread::sleep(poll_interval),
+                }
             }
         }
     }
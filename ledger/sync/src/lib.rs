@@ -3,6 +3,8 @@
 #![feature(wait_timeout_until)]
 #![feature(wait_until)]
 
+mod block_signature_policy;
+mod block_subscriptions;
 mod counters;
 mod ledger_sync_error;
 mod ledger_sync_service;
@@ -11,8 +13,11 @@ mod network_state_trait;
 mod polling_network_state;
 mod reqwest_transactions_fetcher;
 mod scp_network_state;
+mod source_health;
 mod transactions_fetcher_trait;
 
+pub use block_signature_policy::BlockSignaturePolicy;
+pub use block_subscriptions::BlockSubscriptions;
 pub use ledger_sync_error::LedgerSyncError;
 pub use ledger_sync_service::LedgerSyncService;
 pub use ledger_sync_service_thread::LedgerSyncServiceThread;
@@ -121,22 +121,28 @@ impl<BC: BlockchainConnection + 'static> PollingNetworkState<BC> {
             results
         );
 
-        // Hackishly feed into SCPNetworkState
         for (responder_id, block_index) in results.iter() {
             if let Some(block_index) = block_index {
-                self.scp_network_state.push(Msg::<&str, ResponderId>::new(
-                    responder_id.clone(),
-                    QuorumSet::empty(),
-                    *block_index as SlotIndex,
-                    Topic::Externalize(ExternalizePayload {
-                        C: Ballot::new(1, &["fake"]),
-                        HN: 1,
-                    }),
-                ));
+                self.push_block_index(responder_id, *block_index);
             }
         }
     }
 
+    /// Records that a peer has the block with the given index, e.g. because it sent it to a
+    /// block subscription.
+    pub fn push_block_index(&mut self, responder_id: &ResponderId, block_index: BlockIndex) {
+        // Hackishly feed into SCPNetworkState
+        self.scp_network_state.push(Msg::<&str, ResponderId>::new(
+            responder_id.clone(),
+            QuorumSet::empty(),
+            block_index as SlotIndex,
+            Topic::Externalize(ExternalizePayload {
+                C: Ballot::new(1, &["fake"]),
+                HN: 1,
+            }),
+        ));
+    }
+
     pub fn peer_to_current_block_index(&self) -> &HashMap<ResponderId, BlockIndex> {
         self.scp_network_state.peer_to_current_slot()
     }
@@ -1,25 +1,63 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
-//! Implementation of the `TransactionsFetcher` trait that fetches transactions data over http(s)
-//! using the `reqwest` library. It can be used, for example, to get transaction data from S3.
+//! Implementation of the `TransactionsFetcher` trait that fetches transactions data from block
+//! storage (see `mc-ledger-block-storage`), such as S3 or any http(s) server. Http(s) sources are
+//! read with the `reqwest` library.
+//!
+//! When a source publishes compressed bundles of blocks (see `mc-ledger-distribution`), blocks are
+//! fetched a bundle at a time, falling back to single blocks for those not covered by a bundle.
+//!
+//! Sources are tried from the healthiest to the least healthy (see `SourceHealth`). Sources that
+//! serve blocks with invalid or unexpected IDs are quarantined.
 
-use crate::transactions_fetcher_trait::{TransactionFetcherError, TransactionsFetcher};
+use crate::{
+    ledger_sync_service::verify_block_ids,
+    source_health::{SourceHealth, DEFAULT_QUARANTINE_DURATION},
+    transactions_fetcher_trait::{TransactionFetcherError, TransactionsFetcher},
+};
 use failure::Fail;
-use mc_api::{blockchain, conversions::block_num_to_s3block_path};
+use flate2::read::GzDecoder;
+use mc_api::{
+    blockchain,
+    conversions::{
+        block_bundle_to_s3_path, block_num_to_s3block_path, S3BLOCK_BUNDLES_MANIFEST_PATH,
+    },
+};
 use mc_common::{
     logger::{log, Logger},
-    ResponderId,
+    HashMap, HashSet, ResponderId,
 };
-use mc_transaction_core::{Block, BlockContents, BlockSignature};
+use mc_crypto_keys::Ed25519Public;
+use mc_ledger_block_storage::{BlockStorage, BlockStorageError, StorageUri, UriParseError};
+use mc_transaction_core::{Block, BlockContents, BlockIndex, BlockSignature};
 use reqwest::Error as ReqwestError;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     convert::TryFrom,
-    fs,
-    sync::atomic::{AtomicU64, Ordering},
+    fmt::Display,
+    io::Read,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
 };
 use url::Url;
 
+/// How long a source's bundles manifest is used before being fetched again.
+const BUNDLES_MANIFEST_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long a bundle that could not be fetched is not fetched again.
+const FAILED_BUNDLE_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Maximal size of a decompressed bundle.
+const MAX_DECOMPRESSED_BUNDLE_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Maximal number of blocks whose signatures are kept after fetching their contents.
+const MAX_SIGNATURE_CACHE_BLOCKS: usize = 1000;
+
 #[derive(Debug, Fail)]
 pub enum ReqwestTransactionsFetcherError {
     #[fail(display = "Url parse error on {}: {}", _0, _1)]
@@ -33,6 +71,21 @@ pub enum ReqwestTransactionsFetcherError {
 
     #[fail(display = "Received an invalid block from {}: {}", _0, _1)]
     InvalidBlockReceived(String, String),
+
+    #[fail(display = "Received an invalid bundle from {}: {}", _0, _1)]
+    InvalidBundleReceived(String, String),
+
+    #[fail(display = "Invalid source {}: {}", _0, _1)]
+    UriParse(String, UriParseError),
+
+    #[fail(display = "Storage error on {}: {}", _0, _1)]
+    Storage(String, BlockStorageError),
+
+    #[fail(
+        display = "Received block {} with an invalid or unexpected ID from {}",
+        _1, _0
+    )]
+    InvalidBlockId(String, BlockIndex),
 }
 
 impl From<ReqwestError> for ReqwestTransactionsFetcherError {
@@ -53,11 +106,38 @@ pub struct ArchiveBlockData {
     pub signature: Option<BlockSignature>,
 }
 
+/// Bundles manifests of the sources, and the blocks of the most recently fetched bundle.
+#[derive(Default)]
+struct BundleCache {
+    /// The manifest of each source, or None if it could not be fetched, and when it was fetched.
+    manifests: HashMap<usize, (Option<blockchain::ArchiveBlocksManifest>, Instant)>,
+
+    /// Blocks of the most recently fetched bundle, by index.
+    blocks: HashMap<BlockIndex, ArchiveBlockData>,
+
+    /// When fetching a bundle from a source failed, by source and first block of the bundle.
+    failed_bundles: HashMap<(usize, BlockIndex), Instant>,
+}
+
+/// Signatures served along with the blocks whose contents were fetched, so that they need not be
+/// fetched again, and the signer each source was last seen serving.
+#[derive(Default)]
+struct SignatureCache {
+    /// Signatures over fetched blocks, and the source each came from, by block index.
+    signatures: BTreeMap<BlockIndex, Vec<(usize, BlockSignature)>>,
+
+    /// Signer of the last signature served by each source.
+    source_signers: HashMap<usize, Ed25519Public>,
+}
+
 pub struct ReqwestTransactionsFetcher {
     pub source_urls: Vec<Url>,
-    client: reqwest::blocking::Client,
+    storages: Vec<Box<dyn BlockStorage>>,
     logger: Logger,
     source_index_counter: AtomicU64,
+    source_health: SourceHealth,
+    bundle_cache: Mutex<BundleCache>,
+    signature_cache: Mutex<SignatureCache>,
 }
 
 impl ReqwestTransactionsFetcher {
@@ -68,139 +148,541 @@ impl ReqwestTransactionsFetcher {
         Self::new_with_client(source_urls, reqwest::blocking::Client::new(), logger)
     }
 
+    /// Create a new ReqwestTransactionsFetcher.
+    ///
+    /// # Arguments
+    /// * `source_urls` - Block storage URIs to fetch from (see `mc_ledger_block_storage::StorageUri`).
+    /// * `client` - Client used by http(s) sources.
+    /// * `logger` - Logger.
     pub fn new_with_client(
         source_urls: Vec<String>,
         client: reqwest::blocking::Client,
         logger: Logger,
     ) -> Result<Self, ReqwestTransactionsFetcherError> {
-        let source_urls: Result<Vec<Url>, ReqwestTransactionsFetcherError> = source_urls
+        let source_uris: Vec<StorageUri> = source_urls
             .into_iter()
-            // All source_urls must end with a '/'
-            .map(|mut url| {
-                if !url.ends_with('/') {
-                    url.push_str("/");
-                }
-
-                url
-            })
-            // Parse into a Url object
             .map(|url| {
-                Url::parse(&url).map_err(|err| ReqwestTransactionsFetcherError::UrlParse(url, err))
+                StorageUri::from_str(&url)
+                    .map_err(|err| ReqwestTransactionsFetcherError::UriParse(url, err))
             })
+            .collect::<Result<_, _>>()?;
+
+        let storages = source_uris
+            .iter()
+            .map(|uri| uri.storage_with_http_client(client.clone()))
             .collect();
 
+        let source_urls: Vec<Url> = source_uris.into_iter().map(|uri| uri.url).collect();
+        let source_health = SourceHealth::new(
+            source_urls.iter().map(Url::to_string).collect(),
+            DEFAULT_QUARANTINE_DURATION,
+        );
+
         Ok(Self {
-            source_urls: source_urls?,
-            client,
+            source_urls,
+            storages,
             logger,
             source_index_counter: AtomicU64::new(0),
+            source_health,
+            bundle_cache: Mutex::new(BundleCache::default()),
+            signature_cache: Mutex::new(SignatureCache::default()),
         })
     }
 
-    pub fn block_from_url(
+    /// Describes the location of an object, for logs and errors.
+    fn location(&self, source_index: usize, path: &str) -> String {
+        format!("{} from {}", path, self.source_urls[source_index])
+    }
+
+    /// Fetches the object at `path` from a source.
+    fn fetch(
         &self,
-        url: &Url,
-    ) -> Result<ArchiveBlockData, ReqwestTransactionsFetcherError> {
-        // Special treatment for file:// to read from a local directory.
-        let bytes: Vec<u8> = if url.scheme() == "file" {
-            let path = &url[url::Position::BeforeHost..url::Position::AfterPath];
-            fs::read(path)
-                .map_err(|err| ReqwestTransactionsFetcherError::IO(path.to_string(), err))?
-                .to_vec()
-        } else {
-            let mut response = self.client.get(url.as_str()).send().map_err(|err| {
-                ReqwestTransactionsFetcherError::ReqwestError(url.to_string(), err)
-            })?;
+        source_index: usize,
+        path: &str,
+    ) -> Result<Vec<u8>, ReqwestTransactionsFetcherError> {
+        self.storages[source_index].get(path).map_err(|err| {
+            ReqwestTransactionsFetcherError::Storage(self.location(source_index, path), err)
+        })
+    }
 
-            let mut bytes = Vec::new();
-            response.copy_to(&mut bytes)?;
-            bytes
-        };
+    /// Fetches a single block from a source.
+    ///
+    /// # Arguments
+    /// * `source_index` - Index of the source in `source_urls`.
+    /// * `path` - Path of the block.
+    pub fn block_from_source(
+        &self,
+        source_index: usize,
+        path: &str,
+    ) -> Result<ArchiveBlockData, ReqwestTransactionsFetcherError> {
+        let bytes = self.fetch(source_index, path)?;
+        let location = self.location(source_index, path);
 
         let archive_block: blockchain::ArchiveBlock =
             protobuf::parse_from_bytes(&bytes).map_err(|err| {
                 ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                    url.to_string(),
+                    location.clone(),
                     format!("protobuf parse failed: {:?}", err),
                 )
             })?;
 
-        if !archive_block.has_v1() {
-            return Err(ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                url.to_string(),
-                "v1 block not present".to_owned(),
-            ));
-        }
+        archive_block_data(&location, &archive_block)
+    }
 
-        let block = Block::try_from(archive_block.get_v1().get_block()).map_err(|err| {
-            ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                url.to_string(),
-                format!("Block conversion failed: {:?}", err),
-            )
-        })?;
+    /// Fetches a gzip-compressed bundle of blocks from a source, and checks that it holds
+    /// `bundle_size` consecutive blocks starting at `first_block_index`.
+    ///
+    /// # Arguments
+    /// * `source_index` - Index of the source in `source_urls`.
+    /// * `path` - Path of the bundle.
+    /// * `first_block_index` - Index of the first block in the bundle.
+    /// * `bundle_size` - Number of blocks in the bundle.
+    pub fn blocks_from_bundle(
+        &self,
+        source_index: usize,
+        path: &str,
+        first_block_index: BlockIndex,
+        bundle_size: u64,
+    ) -> Result<Vec<ArchiveBlockData>, ReqwestTransactionsFetcherError> {
+        let compressed_bytes = self.fetch(source_index, path)?;
+        let location = self.location(source_index, path);
 
-        let block_contents = BlockContents::try_from(archive_block.get_v1().get_block_contents())
-            .map_err(|err| {
-            ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                url.to_string(),
-                format!("Block contents conversion failed: {:?}", err),
-            )
-        })?;
+        let mut bytes = Vec::new();
+        GzDecoder::new(&compressed_bytes[..])
+            .take(MAX_DECOMPRESSED_BUNDLE_BYTES + 1)
+            .read_to_end(&mut bytes)
+            .map_err(|err| ReqwestTransactionsFetcherError::IO(location.clone(), err))?;
+        if bytes.len() as u64 > MAX_DECOMPRESSED_BUNDLE_BYTES {
+            return Err(ReqwestTransactionsFetcherError::InvalidBundleReceived(
+                location,
+                format!(
+                    "Decompresses to more than {} bytes",
+                    MAX_DECOMPRESSED_BUNDLE_BYTES
+                ),
+            ));
+        }
 
-        let signature = archive_block
-            .get_v1()
-            .signature
-            .as_ref()
-            .map(BlockSignature::try_from)
-            .transpose()
+        let archive_blocks: blockchain::ArchiveBlocks = protobuf::parse_from_bytes(&bytes)
             .map_err(|err| {
-                ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                    url.to_string(),
-                    format!("Invalid block signature: {:?}", err),
+                ReqwestTransactionsFetcherError::InvalidBundleReceived(
+                    location.clone(),
+                    format!("protobuf parse failed: {:?}", err),
                 )
             })?;
 
-        if let Some(signature) = signature.as_ref() {
-            signature.verify(&block).map_err(|err| {
-                ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                    url.to_string(),
-                    format!("Unable to verify block signature: {:?}", err),
-                )
+        if archive_blocks.get_blocks().len() as u64 != bundle_size {
+            return Err(ReqwestTransactionsFetcherError::InvalidBundleReceived(
+                location,
+                format!(
+                    "Expected {} blocks, got {}",
+                    bundle_size,
+                    archive_blocks.get_blocks().len()
+                ),
+            ));
+        }
+
+        let archive_blocks_data: Vec<ArchiveBlockData> = archive_blocks
+            .get_blocks()
+            .iter()
+            .zip(first_block_index..)
+            .map(|(archive_block, block_index)| {
+                let archive_block_data = archive_block_data(&location, archive_block)?;
+                if archive_block_data.block.index != block_index {
+                    return Err(ReqwestTransactionsFetcherError::InvalidBundleReceived(
+                        location.clone(),
+                        format!(
+                            "Expected block {}, got block {}",
+                            block_index, archive_block_data.block.index
+                        ),
+                    ));
+                }
+                Ok(archive_block_data)
+            })
+            .collect::<Result<_, _>>()?;
+
+        // Bundled blocks must form a chain.
+        if let Some((first, rest)) = archive_blocks_data.split_first() {
+            verify_block_ids(
+                rest.iter()
+                    .map(|archive_block_data| archive_block_data.block.clone())
+                    .collect(),
+                &first.block,
+            )
+            .map_err(|_| {
+                ReqwestTransactionsFetcherError::InvalidBlockId(location.clone(), first.block.index)
             })?;
         }
 
-        if block.contents_hash != block_contents.hash() {
+        Ok(archive_blocks_data)
+    }
+
+    /// Fetches the bundles manifest of a source.
+    pub fn bundles_manifest_from_source(
+        &self,
+        source_index: usize,
+    ) -> Result<blockchain::ArchiveBlocksManifest, ReqwestTransactionsFetcherError> {
+        let bytes = self.fetch(source_index, S3BLOCK_BUNDLES_MANIFEST_PATH)?;
+        protobuf::parse_from_bytes(&bytes).map_err(|err| {
+            ReqwestTransactionsFetcherError::InvalidBundleReceived(
+                self.location(source_index, S3BLOCK_BUNDLES_MANIFEST_PATH),
+                format!("manifest parse failed: {:?}", err),
+            )
+        })
+    }
+
+    /// Gets a block from a bundle published by a source, if the source bundles it.
+    ///
+    /// The blocks of the fetched bundle are cached, so that the following blocks are not fetched
+    /// again. Returns None if the block is not bundled, or if the bundle could not be fetched, in
+    /// which case it is not fetched again for `FAILED_BUNDLE_RETRY_INTERVAL`. The cache is not
+    /// locked while fetching.
+    ///
+    /// # Arguments
+    /// * `source_index` - Index of the source in `source_urls`.
+    /// * `block_index` - Index of the wanted block.
+    fn block_from_bundle(
+        &self,
+        source_index: usize,
+        block_index: BlockIndex,
+    ) -> Option<ArchiveBlockData> {
+        let now = Instant::now();
+        let is_stale = {
+            let bundle_cache = self.bundle_cache.lock().expect("mutex poisoned");
+            if let Some(archive_block_data) = bundle_cache.blocks.get(&block_index) {
+                return Some(archive_block_data.clone());
+            }
+
+            match bundle_cache.manifests.get(&source_index) {
+                Some((_, fetched_at)) => {
+                    now.saturating_duration_since(*fetched_at) >= BUNDLES_MANIFEST_REFRESH_INTERVAL
+                }
+                None => true,
+            }
+        };
+
+        if is_stale {
+            let manifest = match self.bundles_manifest_from_source(source_index) {
+                Ok(manifest) => Some(manifest),
+                Err(err) => {
+                    log::debug!(
+                        self.logger,
+                        "No bundles manifest from {}: {}",
+                        self.source_urls[source_index],
+                        err
+                    );
+                    None
+                }
+            };
+            self.bundle_cache
+                .lock()
+                .expect("mutex poisoned")
+                .manifests
+                .insert(source_index, (manifest, now));
+        }
+
+        let (first_block_index, bundle_size) = {
+            let mut bundle_cache = self.bundle_cache.lock().expect("mutex poisoned");
+            let bundle_size = match bundle_cache.manifests.get(&source_index) {
+                Some((Some(manifest), _))
+                    if manifest.get_bundle_size() > 0
+                        && manifest.get_first_block_index() <= block_index
+                        && block_index < manifest.get_num_blocks() =>
+                {
+                    manifest.get_bundle_size()
+                }
+                _ => return None,
+            };
+            let first_block_index = block_index - block_index % bundle_size;
+
+            bundle_cache.failed_bundles.retain(|_, failed_at| {
+                now.saturating_duration_since(*failed_at) < FAILED_BUNDLE_RETRY_INTERVAL
+            });
+            if bundle_cache
+                .failed_bundles
+                .contains_key(&(source_index, first_block_index))
+            {
+                return None;
+            }
+            (first_block_index, bundle_size)
+        };
+
+        let path = block_bundle_to_s3_path(first_block_index, bundle_size)
+            .into_os_string()
+            .into_string()
+            .unwrap();
+
+        log::debug!(
+            self.logger,
+            "Attempting to fetch bundle of block {} from {}",
+            block_index,
+            self.source_urls[source_index]
+        );
+
+        let result = self.blocks_from_bundle(source_index, &path, first_block_index, bundle_size);
+
+        let mut bundle_cache = self.bundle_cache.lock().expect("mutex poisoned");
+        match result {
+            Ok(blocks) => {
+                bundle_cache.blocks = blocks
+                    .into_iter()
+                    .map(|archive_block_data| (archive_block_data.block.index, archive_block_data))
+                    .collect();
+                bundle_cache.blocks.get(&block_index).cloned()
+            }
+            Err(err) => {
+                bundle_cache
+                    .failed_bundles
+                    .insert((source_index, first_block_index), now);
+                drop(bundle_cache);
+
+                if let ReqwestTransactionsFetcherError::InvalidBlockId(..) = err {
+                    self.quarantine(source_index, &err);
+                } else {
+                    log::warn!(self.logger, "Failed fetching bundle: {}", err);
+                }
+                None
+            }
+        }
+    }
+
+    /// Fetches the contents of a block from a source, preferring bundles when the source
+    /// publishes one containing the block.
+    ///
+    /// # Arguments
+    /// * `source_index` - Index of the source in `source_urls`.
+    /// * `block` - The block we want to fetch contents for.
+    fn block_contents_from_source(
+        &self,
+        source_index: usize,
+        block: &Block,
+    ) -> Result<BlockContents, ReqwestTransactionsFetcherError> {
+        if let Some(archive_block_data) = self.block_from_bundle(source_index, block.index) {
+            if *block == archive_block_data.block {
+                self.cache_signature(source_index, block.index, archive_block_data.signature);
+                return Ok(archive_block_data.block_contents);
+            }
+            if block.id != archive_block_data.block.id {
+                return Err(ReqwestTransactionsFetcherError::InvalidBlockId(
+                    self.source_urls[source_index].to_string(),
+                    block.index,
+                ));
+            }
+            log::warn!(
+                self.logger,
+                "Bundled block {} from {} does not match, fetching it alone",
+                block.index,
+                self.source_urls[source_index]
+            );
+        }
+
+        // Construct the path of the block we are trying to fetch.
+        let path = block_num_to_s3block_path(block.index)
+            .into_os_string()
+            .into_string()
+            .unwrap();
+
+        // Try and get the block.
+        log::debug!(
+            self.logger,
+            "Attempting to fetch block {} from {}",
+            block.index,
+            self.source_urls[source_index]
+        );
+
+        let s3_block_data = self.block_from_source(source_index, &path)?;
+
+        // Check that we received data for the block we actually asked about.
+        if block.id != s3_block_data.block.id {
+            return Err(ReqwestTransactionsFetcherError::InvalidBlockId(
+                self.location(source_index, &path),
+                block.index,
+            ));
+        }
+        if *block != s3_block_data.block {
             return Err(ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                url.to_string(),
-                format!(
-                    "Invalid block contents hash. Block: {:?}, BlockContents: {:?}",
-                    block, block_contents
-                ),
+                self.location(source_index, &path),
+                "block data mismatch".to_string(),
             ));
         }
 
-        let archive_block_data = ArchiveBlockData {
-            block,
-            block_contents,
-            signature,
+        // Got what we wanted!
+        self.cache_signature(source_index, block.index, s3_block_data.signature);
+        Ok(s3_block_data.block_contents)
+    }
+
+    /// Keeps the signature a source served along with a block's contents, for
+    /// `get_block_signatures`.
+    ///
+    /// # Arguments
+    /// * `source_index` - Index of the source in `source_urls`.
+    /// * `block_index` - Index of the signed block.
+    /// * `signature` - The signature served with the block, if any.
+    fn cache_signature(
+        &self,
+        source_index: usize,
+        block_index: BlockIndex,
+        signature: Option<BlockSignature>,
+    ) {
+        let signature = match signature {
+            Some(signature) => signature,
+            None => return,
         };
-        Ok(archive_block_data)
+        let mut signature_cache = self.signature_cache.lock().expect("mutex poisoned");
+        signature_cache
+            .source_signers
+            .insert(source_index, *signature.signer());
+        signature_cache
+            .signatures
+            .entry(block_index)
+            .or_insert_with(Vec::new)
+            .push((source_index, signature));
+
+        // Forget the oldest blocks, whose signatures were not asked for.
+        while signature_cache.signatures.len() > MAX_SIGNATURE_CACHE_BLOCKS {
+            let oldest = *signature_cache
+                .signatures
+                .keys()
+                .next()
+                .expect("cache is not empty");
+            signature_cache.signatures.remove(&oldest);
+        }
+    }
+
+    /// The sources named after one of `responder_ids`, or every source if none is.
+    ///
+    /// A source is named after a responder when the host of the responder is the last segment of
+    /// the source's path, e.g. `https://bucket/node1.example.com/` for `node1.example.com:443`, or
+    /// the host of the source.
+    fn responder_sources(&self, responder_ids: &[ResponderId]) -> Vec<usize> {
+        let responder_hosts: HashSet<&str> = responder_ids
+            .iter()
+            .filter_map(|responder_id| responder_id.0.split(':').next())
+            .collect();
+
+        let named_sources: Vec<usize> = self
+            .source_urls
+            .iter()
+            .enumerate()
+            .filter(|(_, url)| {
+                let last_segment = url
+                    .path_segments()
+                    .and_then(|segments| segments.filter(|segment| !segment.is_empty()).last());
+                last_segment
+                    .into_iter()
+                    .chain(url.host_str())
+                    .any(|name| responder_hosts.contains(name))
+            })
+            .map(|(source_index, _)| source_index)
+            .collect();
+
+        if named_sources.is_empty() {
+            (0..self.source_urls.len()).collect()
+        } else {
+            named_sources
+        }
+    }
+
+    /// Quarantines a source that served an invalid block.
+    fn quarantine(&self, source_index: usize, reason: &dyn Display) {
+        log::warn!(
+            self.logger,
+            "Quarantining {}: {}",
+            self.source_urls[source_index],
+            reason
+        );
+        self.source_health.quarantine(source_index);
     }
 
     pub fn get_origin_block_and_transactions(
         &self,
     ) -> Result<(Block, BlockContents), ReqwestTransactionsFetcherError> {
-        let source_url = &self.source_urls[0];
-        let filename = block_num_to_s3block_path(0)
+        let path = block_num_to_s3block_path(0)
             .into_os_string()
             .into_string()
             .unwrap();
-        let url = source_url.join(&filename).unwrap();
-        let s3block = self.block_from_url(&url)?;
+        let s3block = self.block_from_source(0, &path)?;
         Ok((s3block.block, s3block.block_contents))
     }
 }
 
+/// Validates an ArchiveBlock and converts it into ArchiveBlockData.
+///
+/// # Arguments
+/// * `location` - Where the block was received from.
+/// * `archive_block` - The received block.
+fn archive_block_data(
+    location: &str,
+    archive_block: &blockchain::ArchiveBlock,
+) -> Result<ArchiveBlockData, ReqwestTransactionsFetcherError> {
+    if !archive_block.has_v1() {
+        return Err(ReqwestTransactionsFetcherError::InvalidBlockReceived(
+            location.to_string(),
+            "v1 block not present".to_owned(),
+        ));
+    }
+
+    let block = Block::try_from(archive_block.get_v1().get_block()).map_err(|err| {
+        ReqwestTransactionsFetcherError::InvalidBlockReceived(
+            location.to_string(),
+            format!("Block conversion failed: {:?}", err),
+        )
+    })?;
+
+    if !block.is_block_id_valid() {
+        return Err(ReqwestTransactionsFetcherError::InvalidBlockId(
+            location.to_string(),
+            block.index,
+        ));
+    }
+
+    let block_contents = BlockContents::try_from(archive_block.get_v1().get_block_contents())
+        .map_err(|err| {
+            ReqwestTransactionsFetcherError::InvalidBlockReceived(
+                location.to_string(),
+                format!("Block contents conversion failed: {:?}", err),
+            )
+        })?;
+
+    let signature = archive_block
+        .get_v1()
+        .signature
+        .as_ref()
+        .map(BlockSignature::try_from)
+        .transpose()
+        .map_err(|err| {
+            ReqwestTransactionsFetcherError::InvalidBlockReceived(
+                location.to_string(),
+                format!("Invalid block signature: {:?}", err),
+            )
+        })?;
+
+    if let Some(signature) = signature.as_ref() {
+        signature.verify(&block).map_err(|err| {
+            ReqwestTransactionsFetcherError::InvalidBlockReceived(
+                location.to_string(),
+                format!("Unable to verify block signature: {:?}", err),
+            )
+        })?;
+    }
+
+    if block.contents_hash != block_contents.hash() {
+        return Err(ReqwestTransactionsFetcherError::InvalidBlockReceived(
+            location.to_string(),
+            format!(
+                "Invalid block contents hash. Block: {:?}, BlockContents: {:?}",
+                block, block_contents
+            ),
+        ));
+    }
+
+    let archive_block_data = ArchiveBlockData {
+        block,
+        block_contents,
+        signature,
+    };
+    Ok(archive_block_data)
+}
+
 impl TransactionsFetcher for ReqwestTransactionsFetcher {
     type Error = ReqwestTransactionsFetcherError;
 
@@ -209,39 +691,381 @@ impl TransactionsFetcher for ReqwestTransactionsFetcher {
         _safe_responder_ids: &[ResponderId],
         block: &Block,
     ) -> Result<BlockContents, Self::Error> {
-        // Get the source to fetch from.
-        let source_index_counter =
-            self.source_index_counter.fetch_add(1, Ordering::SeqCst) as usize;
-        let source_url = &self.source_urls[source_index_counter % self.source_urls.len()];
+        // Try the healthiest sources first, rotating between equally healthy ones.
+        let offset = self.source_index_counter.fetch_add(1, Ordering::SeqCst) as usize;
+
+        let mut last_err = None;
+        for source_index in self.source_health.ranked_sources(offset) {
+            let start = Instant::now();
+            match self.block_contents_from_source(source_index, block) {
+                Ok(block_contents) => {
+                    self.source_health
+                        .record_success(source_index, start.elapsed());
+                    return Ok(block_contents);
+                }
+                Err(err) => {
+                    if let ReqwestTransactionsFetcherError::InvalidBlockId(..) = err {
+                        self.quarantine(source_index, &err);
+                    } else {
+                        log::debug!(
+                            self.logger,
+                            "Failed fetching block {} from {}: {}",
+                            block.index,
+                            self.source_urls[source_index],
+                            err
+                        );
+                        self.source_health.record_failure(source_index);
+                    }
+                    last_err = Some(err);
+                }
+            }
+        }
+
+        Err(last_err.expect("No transactions sources"))
+    }
+
+    /// Returns the signatures served along with the block's contents, and fetches the block from
+    /// other sources until every signer in `signers` is covered.
+    ///
+    /// Sources that last served a signature by a signer that is not wanted are skipped. When some
+    /// sources are named after one of `safe_responder_ids`, only those are fetched from. Sources
+    /// that fail, or that serve a different block, are skipped.
+    fn get_block_signatures(
+        &self,
+        safe_responder_ids: &[ResponderId],
+        block: &Block,
+        signers: &[Ed25519Public],
+    ) -> Result<Vec<BlockSignature>, Self::Error> {
+        let (cached_signatures, source_signers) = {
+            let mut signature_cache = self.signature_cache.lock().expect("mutex poisoned");
+            (
+                signature_cache
+                    .signatures
+                    .remove(&block.index)
+                    .unwrap_or_default(),
+                signature_cache.source_signers.clone(),
+            )
+        };
 
-        // Construct URL for the block we are trying to fetch.
-        let filename = block_num_to_s3block_path(block.index)
+        let mut fetched_sources: HashSet<usize> = HashSet::default();
+        let mut signatures = Vec::new();
+        for (source_index, signature) in cached_signatures {
+            fetched_sources.insert(source_index);
+            signatures.push(signature);
+        }
+        let mut missing_signers: HashSet<Ed25519Public> = signers
+            .iter()
+            .filter(|signer| {
+                signatures
+                    .iter()
+                    .all(|signature: &BlockSignature| signature.signer() != *signer)
+            })
+            .cloned()
+            .collect();
+
+        let path = block_num_to_s3block_path(block.index)
             .into_os_string()
             .into_string()
             .unwrap();
-        let url = source_url
-            .join(&filename)
-            .map_err(|e| ReqwestTransactionsFetcherError::UrlParse(filename, e))?;
 
-        // Try and get the block.
-        log::debug!(
-            self.logger,
-            "Attempting to fetch block {} from {}",
-            block.index,
-            url
+        for source_index in self.responder_sources(safe_responder_ids) {
+            if missing_signers.is_empty() {
+                break;
+            }
+            if fetched_sources.contains(&source_index) {
+                continue;
+            }
+            if let Some(signer) = source_signers.get(&source_index) {
+                if !missing_signers.contains(signer) {
+                    continue;
+                }
+            }
+
+            match self.block_from_source(source_index, &path) {
+                Ok(s3_block_data) if s3_block_data.block == *block => {
+                    if let Some(signature) = s3_block_data.signature {
+                        self.signature_cache
+                            .lock()
+                            .expect("mutex poisoned")
+                            .source_signers
+                            .insert(source_index, *signature.signer());
+                        missing_signers.remove(signature.signer());
+                        signatures.push(signature);
+                    }
+                }
+                Ok(s3_block_data) if s3_block_data.block.id != block.id => self.quarantine(
+                    source_index,
+                    &format!("{} does not match block {}", path, block.index),
+                ),
+                Ok(_) => log::warn!(
+                    self.logger,
+                    "{} does not match block {}",
+                    self.location(source_index, &path),
+                    block.index
+                ),
+                Err(err @ ReqwestTransactionsFetcherError::InvalidBlockId(..)) => {
+                    self.quarantine(source_index, &err)
+                }
+                Err(err) => log::warn!(
+                    self.logger,
+                    "Failed fetching signature of block {}: {}",
+                    block.index,
+                    err
+                ),
+            }
+        }
+
+        Ok(signatures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use mc_common::logger::test_with_logger;
+    use mc_crypto_keys::Ed25519Pair;
+    use mc_ledger_db::test_utils::get_test_ledger_blocks;
+    use mc_util_from_random::FromRandom;
+    use protobuf::Message;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::{fs, path::Path};
+    use tempdir::TempDir;
+
+    fn archive_block(block: &Block, block_contents: &BlockContents) -> blockchain::ArchiveBlock {
+        let mut archive_block_v1 = blockchain::ArchiveBlockV1::new();
+        archive_block_v1.set_block(block.into());
+        archive_block_v1.set_block_contents(block_contents.into());
+
+        let mut archive_block = blockchain::ArchiveBlock::new();
+        archive_block.set_v1(archive_block_v1);
+        archive_block
+    }
+
+    fn write_file(dir: &Path, path: &Path, bytes: &[u8]) {
+        let dest = dir.join(path);
+        fs::create_dir_all(dest.parent().unwrap()).unwrap();
+        fs::write(dest, bytes).unwrap();
+    }
+
+    #[test_with_logger]
+    // Bundled blocks should be fetched from their bundle, and other blocks one at a time.
+    fn test_get_block_contents_prefers_bundles(logger: Logger) {
+        let blocks = get_test_ledger_blocks(5);
+        let dir = TempDir::new("bundles").unwrap();
+
+        // Blocks 0 to 3 are only available in a bundle.
+        let mut archive_blocks = blockchain::ArchiveBlocks::new();
+        for (block, block_contents) in &blocks[0..4] {
+            archive_blocks
+                .mut_blocks()
+                .push(archive_block(block, block_contents));
+        }
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        archive_blocks.write_to_writer(&mut encoder).unwrap();
+        write_file(
+            dir.path(),
+            &block_bundle_to_s3_path(0, 4),
+            &encoder.finish().unwrap(),
         );
 
-        let s3_block_data = self.block_from_url(&url)?;
+        let mut manifest = blockchain::ArchiveBlocksManifest::new();
+        manifest.set_bundle_size(4);
+        manifest.set_num_blocks(4);
+        write_file(
+            dir.path(),
+            Path::new(S3BLOCK_BUNDLES_MANIFEST_PATH),
+            &manifest.write_to_bytes().unwrap(),
+        );
 
-        // Check that we received data for the block we actually asked about.
-        if *block != s3_block_data.block {
-            return Err(ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                url.to_string(),
-                "block data mismatch".to_string(),
-            ));
+        // Block 4 is only available on its own.
+        let (block, block_contents) = &blocks[4];
+        write_file(
+            dir.path(),
+            &block_num_to_s3block_path(block.index),
+            &archive_block(block, block_contents)
+                .write_to_bytes()
+                .unwrap(),
+        );
+
+        let fetcher = ReqwestTransactionsFetcher::new(
+            vec![format!("file://{}", dir.path().display())],
+            logger,
+        )
+        .unwrap();
+
+        for (block, block_contents) in &blocks {
+            assert_eq!(
+                fetcher.get_block_contents(&[], block).unwrap(),
+                *block_contents
+            );
         }
 
-        // Got what we wanted!
-        Ok(s3_block_data.block_contents)
+        // A block that does not match the bundle is fetched on its own, which fails here.
+        let mut wrong_block = blocks[1].0.clone();
+        wrong_block.cumulative_txo_count += 1;
+        assert!(fetcher.get_block_contents(&[], &wrong_block).is_err());
+    }
+
+    #[test_with_logger]
+    // Blocks before the first bundled block should not be looked up in bundles, and bundles that
+    // could not be fetched should not be fetched again right away.
+    fn test_get_block_contents_skips_unbundled_and_failed_bundles(logger: Logger) {
+        let blocks = get_test_ledger_blocks(4);
+        let dir = TempDir::new("bundles").unwrap();
+
+        // Bundles supposedly cover blocks 2 to 5, but are missing.
+        let mut manifest = blockchain::ArchiveBlocksManifest::new();
+        manifest.set_bundle_size(2);
+        manifest.set_first_block_index(2);
+        manifest.set_num_blocks(6);
+        write_file(
+            dir.path(),
+            Path::new(S3BLOCK_BUNDLES_MANIFEST_PATH),
+            &manifest.write_to_bytes().unwrap(),
+        );
+        for (block, block_contents) in &blocks {
+            write_file(
+                dir.path(),
+                &block_num_to_s3block_path(block.index),
+                &archive_block(block, block_contents)
+                    .write_to_bytes()
+                    .unwrap(),
+            );
+        }
+
+        let fetcher = ReqwestTransactionsFetcher::new(
+            vec![format!("file://{}", dir.path().display())],
+            logger,
+        )
+        .unwrap();
+        let failed_bundles = || -> Vec<(usize, BlockIndex)> {
+            let bundle_cache = fetcher.bundle_cache.lock().unwrap();
+            bundle_cache.failed_bundles.keys().cloned().collect()
+        };
+
+        for (block, block_contents) in &blocks[0..2] {
+            assert_eq!(
+                fetcher.get_block_contents(&[], block).unwrap(),
+                *block_contents
+            );
+        }
+        assert!(failed_bundles().is_empty());
+
+        for (block, block_contents) in &blocks[2..4] {
+            assert_eq!(
+                fetcher.get_block_contents(&[], block).unwrap(),
+                *block_contents
+            );
+            assert_eq!(failed_bundles(), vec![(0, 2)]);
+        }
+    }
+
+    #[test_with_logger]
+    // A source serving blocks with invalid IDs should be quarantined, and blocks should be fetched
+    // from the other sources.
+    fn test_get_block_contents_quarantines_invalid_sources(logger: Logger) {
+        let blocks = get_test_ledger_blocks(3);
+        let bad_dir = TempDir::new("bad_source").unwrap();
+        let good_dir = TempDir::new("good_source").unwrap();
+
+        for (block, block_contents) in &blocks {
+            let path = block_num_to_s3block_path(block.index);
+
+            let mut tampered_block = block.clone();
+            tampered_block.cumulative_txo_count += 1;
+            write_file(
+                bad_dir.path(),
+                &path,
+                &archive_block(&tampered_block, block_contents)
+                    .write_to_bytes()
+                    .unwrap(),
+            );
+
+            write_file(
+                good_dir.path(),
+                &path,
+                &archive_block(block, block_contents)
+                    .write_to_bytes()
+                    .unwrap(),
+            );
+        }
+
+        let fetcher = ReqwestTransactionsFetcher::new(
+            vec![
+                format!("file://{}", bad_dir.path().display()),
+                format!("file://{}", good_dir.path().display()),
+            ],
+            logger,
+        )
+        .unwrap();
+
+        for (block, block_contents) in &blocks {
+            assert_eq!(
+                fetcher.get_block_contents(&[], block).unwrap(),
+                *block_contents
+            );
+        }
+
+        assert!(fetcher.source_health.is_quarantined(0));
+        assert!(!fetcher.source_health.is_quarantined(1));
+        assert_eq!(fetcher.source_health.ranked_sources(0), vec![1, 0]);
+    }
+
+    #[test_with_logger]
+    // Signatures served with the block's contents should be reused, and only the sources of
+    // missing signers, among the responders' sources, should be fetched from.
+    fn test_get_block_signatures(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let signer_a = Ed25519Pair::from_random(&mut rng);
+        let signer_b = Ed25519Pair::from_random(&mut rng);
+
+        let blocks = get_test_ledger_blocks(2);
+        let (block, block_contents) = &blocks[1];
+        let signature_a = BlockSignature::from_block_and_keypair(block, &signer_a).unwrap();
+        let signature_b = BlockSignature::from_block_and_keypair(block, &signer_b).unwrap();
+
+        // Each source is named after the node that signed its blocks.
+        let dir = TempDir::new("sources").unwrap();
+        let mut source_urls = Vec::new();
+        for (name, signature) in &[
+            ("node_a.example.com", &signature_a),
+            ("node_b.example.com", &signature_b),
+        ] {
+            let mut archive_block = archive_block(block, block_contents);
+            archive_block.mut_v1().set_signature((*signature).into());
+            write_file(
+                &dir.path().join(name),
+                &block_num_to_s3block_path(block.index),
+                &archive_block.write_to_bytes().unwrap(),
+            );
+            source_urls.push(format!("file://{}/{}", dir.path().display(), name));
+        }
+
+        let fetcher = ReqwestTransactionsFetcher::new(source_urls, logger).unwrap();
+        let signers = vec![signer_a.public_key(), signer_b.public_key()];
+
+        // Only the sources of the responders are fetched from.
+        let responder_ids = vec![ResponderId("node_b.example.com:443".to_string())];
+        assert_eq!(
+            fetcher
+                .get_block_signatures(&responder_ids, block, &signers)
+                .unwrap(),
+            vec![signature_b.clone()]
+        );
+
+        // The signature served with the contents is reused, and the other one is fetched.
+        fetcher.get_block_contents(&[], block).unwrap();
+        let mut signatures = fetcher.get_block_signatures(&[], block, &signers).unwrap();
+        signatures.sort_by_key(|signature| *signature.signer() == signer_b.public_key());
+        assert_eq!(signatures, vec![signature_a.clone(), signature_b]);
+
+        // Sources known to serve other signers are not fetched from.
+        assert_eq!(
+            fetcher
+                .get_block_signatures(&[], block, &[signer_a.public_key()])
+                .unwrap(),
+            vec![signature_a]
+        );
     }
 }
@@ -64,6 +64,11 @@ impl<ID: GenericNodeId + Clone + Eq + PartialEq + Hash> SCPNetworkState<ID> {
         }
     }
 
+    /// Replace the quorum set of the node we are tracking state for.
+    pub fn set_quorum_set(&mut self, local_quorum_set: QuorumSet<ID>) {
+        self.local_quorum_set = local_quorum_set;
+    }
+
     pub fn peer_to_current_slot(&self) -> &HashMap<ID, SlotIndex> {
         &self.id_to_current_slot
     }
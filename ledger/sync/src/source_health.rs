@@ -0,0 +1,213 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Tracks how well each transactions source has been serving blocks, so that fetches go to the
+//! healthiest sources first. Sources that serve invalid blocks are quarantined for a while.
+
+use crate::counters;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How long a source that served an invalid block is avoided.
+pub const DEFAULT_QUARANTINE_DURATION: Duration = Duration::from_secs(600);
+
+/// How long a source that failed a fetch is ranked below sources that did not.
+const FAILURE_PENALTY_DURATION: Duration = Duration::from_secs(30);
+
+/// Sources whose average latencies are within this much of each other are considered equally
+/// fast, and share the load.
+const LATENCY_GRANULARITY: Duration = Duration::from_millis(50);
+
+/// Weight of a new sample in the average latency of a source.
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+#[derive(Clone, Debug, Default)]
+struct SourceStats {
+    /// Exponentially weighted moving average of the latency of successful fetches.
+    avg_latency: Option<Duration>,
+
+    /// When the last failed fetch happened.
+    last_failure: Option<Instant>,
+
+    /// Until when the source is quarantined.
+    quarantined_until: Option<Instant>,
+}
+
+pub struct SourceHealth {
+    /// Names of the sources, used as metric labels.
+    names: Vec<String>,
+
+    /// Statistics of each source.
+    stats: Mutex<Vec<SourceStats>>,
+
+    /// How long a source that served an invalid block is avoided.
+    quarantine_duration: Duration,
+}
+
+impl SourceHealth {
+    /// Creates a new SourceHealth.
+    ///
+    /// # Arguments
+    /// * `names` - Name of each source.
+    /// * `quarantine_duration` - How long a source that served an invalid block is avoided.
+    pub fn new(names: Vec<String>, quarantine_duration: Duration) -> Self {
+        let stats = vec![SourceStats::default(); names.len()];
+        Self {
+            names,
+            stats: Mutex::new(stats),
+            quarantine_duration,
+        }
+    }
+
+    /// Records a successful fetch from a source.
+    pub fn record_success(&self, source_index: usize, latency: Duration) {
+        let mut stats = self.stats.lock().expect("mutex poisoned");
+        let source_stats = &mut stats[source_index];
+
+        let avg_latency = match source_stats.avg_latency {
+            Some(avg_latency) => {
+                avg_latency.mul_f64(1.0 - LATENCY_SMOOTHING) + latency.mul_f64(LATENCY_SMOOTHING)
+            }
+            None => latency,
+        };
+        source_stats.avg_latency = Some(avg_latency);
+        if !Self::is_quarantined_at(source_stats, Instant::now()) {
+            source_stats.quarantined_until = None;
+            counters::OP_COUNTERS
+                .peer_gauge("source_quarantined", &self.names[source_index])
+                .set(0);
+        }
+
+        counters::SOURCE_FETCH_TIME.observe(latency.as_secs_f64());
+        counters::OP_COUNTERS
+            .peer_gauge("source_latency_ms", &self.names[source_index])
+            .set(avg_latency.as_millis() as i64);
+    }
+
+    /// Records a failed fetch from a source.
+    pub fn record_failure(&self, source_index: usize) {
+        let mut stats = self.stats.lock().expect("mutex poisoned");
+        stats[source_index].last_failure = Some(Instant::now());
+
+        counters::SOURCE_FETCH_ERRORS_COUNT.inc();
+    }
+
+    /// Quarantines a source that served an invalid block.
+    pub fn quarantine(&self, source_index: usize) {
+        let now = Instant::now();
+        let mut stats = self.stats.lock().expect("mutex poisoned");
+        stats[source_index].last_failure = Some(now);
+        stats[source_index].quarantined_until = Some(now + self.quarantine_duration);
+
+        counters::SOURCE_QUARANTINE_COUNT.inc();
+        counters::SOURCE_FETCH_ERRORS_COUNT.inc();
+        counters::OP_COUNTERS
+            .peer_gauge("source_quarantined", &self.names[source_index])
+            .set(1);
+    }
+
+    /// Whether a source is currently quarantined.
+    pub fn is_quarantined(&self, source_index: usize) -> bool {
+        let stats = self.stats.lock().expect("mutex poisoned");
+        Self::is_quarantined_at(&stats[source_index], Instant::now())
+    }
+
+    /// Indices of the sources, in the order in which they should be tried.
+    ///
+    /// Quarantined sources come last, after sources that recently failed. Other sources are
+    /// ordered from fastest to slowest. Sources that are equally good are rotated by `offset`, so
+    /// that they share the load.
+    pub fn ranked_sources(&self, offset: usize) -> Vec<usize> {
+        let now = Instant::now();
+        let stats = self.stats.lock().expect("mutex poisoned");
+        let num_sources = stats.len();
+
+        let mut ranked: Vec<_> = stats
+            .iter()
+            .enumerate()
+            .map(|(source_index, source_stats)| {
+                let is_quarantined = Self::is_quarantined_at(source_stats, now);
+
+                let recently_failed = source_stats.last_failure.map_or(false, |last_failure| {
+                    now.saturating_duration_since(last_failure) < FAILURE_PENALTY_DURATION
+                });
+
+                // Sources without latency measurements are tried early, to measure them.
+                let latency_bucket = source_stats.avg_latency.map_or(0, |avg_latency| {
+                    avg_latency.as_millis() / LATENCY_GRANULARITY.as_millis()
+                });
+
+                let rotation = (source_index + num_sources - offset % num_sources) % num_sources;
+
+                (
+                    (is_quarantined, recently_failed, latency_bucket, rotation),
+                    source_index,
+                )
+            })
+            .collect();
+        ranked.sort();
+
+        ranked
+            .into_iter()
+            .map(|(_key, source_index)| source_index)
+            .collect()
+    }
+
+    fn is_quarantined_at(source_stats: &SourceStats, now: Instant) -> bool {
+        source_stats
+            .quarantined_until
+            .map_or(false, |quarantined_until| now < quarantined_until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source_health(num_sources: usize, quarantine_duration: Duration) -> SourceHealth {
+        SourceHealth::new(
+            (0..num_sources)
+                .map(|i| format!("file:///source{}", i))
+                .collect(),
+            quarantine_duration,
+        )
+    }
+
+    #[test]
+    // Equally good sources share the load.
+    fn test_ranked_sources_rotates_equal_sources() {
+        let health = source_health(3, DEFAULT_QUARANTINE_DURATION);
+        assert_eq!(health.ranked_sources(0), vec![0, 1, 2]);
+        assert_eq!(health.ranked_sources(1), vec![1, 2, 0]);
+        assert_eq!(health.ranked_sources(5), vec![2, 0, 1]);
+    }
+
+    #[test]
+    // Faster sources come first, then sources that failed, then quarantined sources.
+    fn test_ranked_sources_prefers_healthy_sources() {
+        let health = source_health(4, DEFAULT_QUARANTINE_DURATION);
+        health.record_success(0, Duration::from_millis(500));
+        health.record_success(1, Duration::from_millis(10));
+        health.record_failure(2);
+        health.quarantine(3);
+
+        assert!(health.is_quarantined(3));
+        assert!(!health.is_quarantined(2));
+        for offset in 0..4 {
+            assert_eq!(health.ranked_sources(offset), vec![1, 0, 2, 3]);
+        }
+
+        // A single fast fetch only moves the average part of the way.
+        health.record_success(0, Duration::from_millis(10));
+        assert_eq!(health.ranked_sources(0), vec![1, 0, 2, 3]);
+    }
+
+    #[test]
+    // Quarantine ends after the quarantine duration.
+    fn test_quarantine_expires() {
+        let health = source_health(2, Duration::from_millis(0));
+        health.quarantine(0);
+        assert!(!health.is_quarantined(0));
+    }
+}
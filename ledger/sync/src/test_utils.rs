@@ -2,8 +2,9 @@
 
 use crate::{TransactionFetcherError, TransactionsFetcher};
 use mc_common::ResponderId;
+use mc_crypto_keys::Ed25519Public;
 use mc_ledger_db::Ledger;
-use mc_transaction_core::{Block, BlockContents};
+use mc_transaction_core::{Block, BlockContents, BlockSignature};
 
 impl TransactionFetcherError for String {}
 
@@ -30,4 +31,21 @@ impl<L: Ledger + Sync> TransactionsFetcher for MockTransactionsFetcher<L> {
             .get_block_contents(block.index)
             .map_err(|e| format!("Error getting contents of block #{}: {:?}", block.index, e))
     }
+
+    /// Provides the signatures collected in `ledger`.
+    fn get_block_signatures(
+        &self,
+        _safe_responder_ids: &[ResponderId],
+        block: &Block,
+        _signers: &[Ed25519Public],
+    ) -> Result<Vec<BlockSignature>, Self::Error> {
+        self.ledger
+            .get_collected_block_signatures(block.index)
+            .map_err(|e| {
+                format!(
+                    "Error getting signatures of block #{}: {:?}",
+                    block.index, e
+                )
+            })
+    }
 }
@@ -4,7 +4,8 @@
 //! fetching transaction data.
 
 use mc_common::ResponderId;
-use mc_transaction_core::{Block, BlockContents};
+use mc_crypto_keys::Ed25519Public;
+use mc_transaction_core::{Block, BlockContents, BlockSignature};
 use std::fmt::Debug;
 
 pub trait TransactionFetcherError: Debug + Send + Sync {}
@@ -24,4 +25,23 @@ pub trait TransactionsFetcher: Sized + Sync + Send {
         safe_responder_ids: &[ResponderId],
         block: &Block,
     ) -> Result<BlockContents, Self::Error>;
+
+    /// Fetches signatures over a given block, from as many of `signers` as available. Signatures
+    /// are not required to be valid, nor to be by one of `signers`: the caller verifies them.
+    ///
+    /// The default implementation provides no signatures.
+    ///
+    /// # Arguments
+    /// * `safe_responder_ids` - List of responder IDs that have been identified as being able to provide a
+    /// consistent copy of the blockchain.
+    /// * `block` - The block we want to fetch signatures for.
+    /// * `signers` - The signers whose signatures are wanted.
+    fn get_block_signatures(
+        &self,
+        _safe_responder_ids: &[ResponderId],
+        _block: &Block,
+        _signers: &[Ed25519Public],
+    ) -> Result<Vec<BlockSignature>, Self::Error> {
+        Ok(Vec::new())
+    }
 }
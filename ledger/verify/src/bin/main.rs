@@ -0,0 +1,128 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A utility that checks that a ledger is self-consistent, e.g. to validate a backup or detect disk
+//! corruption.
+
+use mc_common::logger::{create_app_logger, log, o};
+use mc_ledger_db::{Ledger, LedgerDB};
+use mc_ledger_verify::{verify_blocks, verify_totals, VerificationState};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "ledger-verify",
+    about = "Checks that a ledger is self-consistent."
+)]
+pub struct Config {
+    /// Path to the ledger db (lmdb).
+    #[structopt(long, parse(from_os_str))]
+    pub ledger_db: PathBuf,
+
+    /// Path of a file where the progress of verification is saved. If the file exists,
+    /// verification resumes from it.
+    #[structopt(long, parse(from_os_str))]
+    pub state_file: Option<PathBuf>,
+
+    /// Number of blocks verified between saves of the state file.
+    #[structopt(long, default_value = "1000")]
+    pub checkpoint_interval: u64,
+
+    /// Path of the JSON report to write. Defaults to standard output.
+    #[structopt(long, parse(from_os_str))]
+    pub report: Option<PathBuf>,
+
+    /// Number of blocks to verify. Defaults to every block in the ledger.
+    #[structopt(long)]
+    pub num_blocks: Option<u64>,
+}
+
+fn load_state(path: &Path) -> VerificationState {
+    let bytes = fs::read(path).unwrap_or_else(|err| panic!("Failed reading {:?}: {}", path, err));
+    serde_json::from_slice(&bytes)
+        .unwrap_or_else(|err| panic!("Failed parsing {:?}: {}", path, err))
+}
+
+/// Writes the state to a temporary file first, so that an interrupted save does not lose the
+/// previous state.
+fn save_state(path: &Path, state: &VerificationState) {
+    let bytes = serde_json::to_vec(state).expect("Failed serializing state");
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, bytes)
+        .unwrap_or_else(|err| panic!("Failed writing {:?}: {}", tmp_path, err));
+    fs::rename(&tmp_path, path)
+        .unwrap_or_else(|err| panic!("Failed renaming {:?} to {:?}: {}", tmp_path, path, err));
+}
+
+fn main() {
+    let (logger, _global_logger_guard) = create_app_logger(o!());
+    let config = Config::from_args();
+
+    let ledger_db = LedgerDB::open(config.ledger_db.clone()).expect("Could not open ledger db");
+    let num_blocks = config.num_blocks.unwrap_or_else(|| {
+        ledger_db
+            .num_blocks()
+            .expect("Failed getting number of blocks")
+    });
+
+    let mut state = match &config.state_file {
+        Some(path) if path.exists() => {
+            let state = load_state(path);
+            log::info!(
+                logger,
+                "Resuming verification at block {} from {:?}",
+                state.next_block(),
+                path
+            );
+            state
+        }
+        _ => VerificationState::default(),
+    };
+
+    let checkpoint_interval = config.checkpoint_interval.max(1);
+    let mut completed = true;
+    while state.next_block() < num_blocks {
+        let end_block = (state.next_block() + checkpoint_interval).min(num_blocks);
+        completed = verify_blocks(&ledger_db, &mut state, end_block);
+        if let Some(path) = &config.state_file {
+            save_state(path, &state);
+        }
+        log::info!(
+            logger,
+            "Verified {} of {} blocks, {} problems found",
+            state.next_block(),
+            num_blocks,
+            state.report.num_problems
+        );
+        if !completed {
+            log::error!(logger, "Stopped at block {}", state.next_block());
+            break;
+        }
+    }
+
+    if completed && !state.report.complete && config.num_blocks.is_none() {
+        verify_totals(&ledger_db, &mut state);
+        if let Some(path) = &config.state_file {
+            save_state(path, &state);
+        }
+    }
+
+    let report = serde_json::to_string_pretty(&state.report).expect("Failed serializing report");
+    match &config.report {
+        Some(path) => {
+            fs::write(path, report)
+                .unwrap_or_else(|err| panic!("Failed writing {:?}: {}", path, err));
+            log::info!(logger, "Wrote report to {:?}", path);
+        }
+        None => println!("{}", report),
+    }
+
+    if state.report.num_problems > 0 {
+        log::error!(logger, "Found {} problems", state.report.num_problems);
+        exit(1);
+    }
+}
@@ -0,0 +1,423 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Walks a ledger and checks that it is self-consistent: that blocks are correctly chained, that
+//! their contents match their headers, that the TxOut Merkle tree matches the root elements of
+//! blocks, that key images are spent only once, and that stored block signatures are valid.
+//!
+//! Verification is incremental. Its state can be saved after any block and restored later, so
+//! that verifying a large ledger can be interrupted and resumed.
+
+mod merkle;
+
+pub use merkle::MerkleFrontier;
+
+use mc_common::HashSet;
+use mc_ledger_db::{Error as LedgerError, Ledger};
+use mc_transaction_core::{Block, BlockContents, BlockID, BlockIndex};
+use serde::{Deserialize, Serialize};
+
+/// Maximal number of problems listed in a report. Further problems are only counted.
+pub const MAX_REPORTED_PROBLEMS: usize = 1000;
+
+/// The kinds of inconsistencies that verification detects.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    /// Reading from the ledger failed.
+    Unreadable,
+
+    /// The block's ID does not match its header.
+    InvalidBlockId,
+
+    /// The block's index does not match its position in the ledger.
+    WrongIndex,
+
+    /// The block's parent ID is not the ID of the previous block.
+    WrongParent,
+
+    /// The block's contents hash does not match the stored contents.
+    ContentsHashMismatch,
+
+    /// The block's cumulative TxOut count does not match the number of TxOuts up to that block.
+    CumulativeTxoCountMismatch,
+
+    /// The block's root element does not match the Merkle tree of the TxOuts before it.
+    RootElementMismatch,
+
+    /// A TxOut of the block is not stored at the expected index of the TxOut store.
+    TxOutMismatch,
+
+    /// A key image appears more than once in the ledger.
+    DuplicateKeyImage,
+
+    /// The key image index disagrees with the block contents.
+    KeyImageIndexMismatch,
+
+    /// A stored signature does not verify against its block.
+    InvalidSignature,
+
+    /// The TxOut store holds a different number of TxOuts than the blocks.
+    TxOutCountMismatch,
+
+    /// The root of the stored Merkle tree does not match the TxOuts of the blocks.
+    MerkleRootMismatch,
+}
+
+/// An inconsistency found in the ledger.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Problem {
+    /// Index of the block where the problem was found, if the problem is about a block.
+    pub block_index: Option<BlockIndex>,
+
+    /// What is wrong.
+    pub kind: ProblemKind,
+
+    /// Human-readable details.
+    pub details: String,
+}
+
+/// The outcome of verifying a ledger.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct VerificationReport {
+    /// Number of blocks verified.
+    pub num_blocks: u64,
+
+    /// Number of TxOuts verified.
+    pub num_tx_outs: u64,
+
+    /// Number of key images verified.
+    pub num_key_images: u64,
+
+    /// Number of stored signatures verified.
+    pub num_signatures: u64,
+
+    /// Number of blocks whose root element was left at its default value, and could not be
+    /// checked.
+    pub num_unchecked_root_elements: u64,
+
+    /// Total number of problems found.
+    pub num_problems: u64,
+
+    /// Problems found, up to `MAX_REPORTED_PROBLEMS`.
+    pub problems: Vec<Problem>,
+
+    /// True once every block and the TxOut store as a whole have been verified.
+    pub complete: bool,
+}
+
+impl VerificationReport {
+    fn add_problem(&mut self, block_index: Option<BlockIndex>, kind: ProblemKind, details: String) {
+        self.num_problems += 1;
+        if self.problems.len() < MAX_REPORTED_PROBLEMS {
+            self.problems.push(Problem {
+                block_index,
+                kind,
+                details,
+            });
+        }
+    }
+}
+
+/// Everything needed to resume verification after the last verified block.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct VerificationState {
+    /// The last verified block.
+    last_block: Option<Block>,
+
+    /// The Merkle tree of the TxOuts of verified blocks.
+    tx_outs_tree: MerkleFrontier,
+
+    /// The report so far.
+    pub report: VerificationReport,
+}
+
+impl VerificationState {
+    /// Index of the next block to verify.
+    pub fn next_block(&self) -> BlockIndex {
+        self.last_block
+            .as_ref()
+            .map_or(0, |last_block| last_block.index + 1)
+    }
+}
+
+/// Verifies blocks, from the next block of `state` up to `end_block` (excluded).
+///
+/// Returns false if verification stopped early because the ledger could not be read. The failure
+/// is recorded in the report, and `state` is left after the last verified block.
+///
+/// # Arguments
+/// * `ledger` - The ledger to verify.
+/// * `state` - State of the verification, updated after each block.
+/// * `end_block` - Index of the block after the last block to verify.
+pub fn verify_blocks<L: Ledger>(
+    ledger: &L,
+    state: &mut VerificationState,
+    end_block: BlockIndex,
+) -> bool {
+    while state.next_block() < end_block {
+        let block_index = state.next_block();
+        if let Err(err) = verify_block(ledger, state, block_index) {
+            state.report.add_problem(
+                Some(block_index),
+                ProblemKind::Unreadable,
+                format!("Failed reading the ledger: {}", err),
+            );
+            return false;
+        }
+    }
+    true
+}
+
+/// Checks the TxOut store as a whole against the verified blocks, and marks the report complete.
+///
+/// This should be called after every block has been verified with `verify_blocks`.
+///
+/// # Arguments
+/// * `ledger` - The ledger to verify.
+/// * `state` - State of the verification.
+pub fn verify_totals<L: Ledger>(ledger: &L, state: &mut VerificationState) {
+    let report = &mut state.report;
+
+    match ledger.num_txos() {
+        Ok(num_txos) if num_txos != state.tx_outs_tree.num_leaves() => report.add_problem(
+            None,
+            ProblemKind::TxOutCountMismatch,
+            format!(
+                "The TxOut store holds {} TxOuts, but blocks contain {}",
+                num_txos,
+                state.tx_outs_tree.num_leaves()
+            ),
+        ),
+        Ok(_) => {}
+        Err(err) => report.add_problem(
+            None,
+            ProblemKind::Unreadable,
+            format!("Failed getting the number of TxOuts: {}", err),
+        ),
+    }
+
+    if let Some(expected_root) = state.tx_outs_tree.root_element() {
+        match ledger.get_tx_out_proof_of_memberships(&[0]) {
+            Ok(proofs) => {
+                let stored_root = proofs
+                    .first()
+                    .and_then(|proof| proof.elements.last().cloned());
+                if stored_root.as_ref() != Some(&expected_root) {
+                    report.add_problem(
+                        None,
+                        ProblemKind::MerkleRootMismatch,
+                        format!(
+                            "The root of the TxOut store is {:?}, expected {:?}",
+                            stored_root, expected_root
+                        ),
+                    );
+                }
+            }
+            Err(err) => report.add_problem(
+                None,
+                ProblemKind::Unreadable,
+                format!("Failed getting the root of the TxOut store: {}", err),
+            ),
+        }
+    }
+
+    report.complete = true;
+}
+
+/// Verifies a single block, and advances `state` past it.
+fn verify_block<L: Ledger>(
+    ledger: &L,
+    state: &mut VerificationState,
+    block_index: BlockIndex,
+) -> Result<(), LedgerError> {
+    let block = ledger.get_block(block_index)?;
+    let block_contents = ledger.get_block_contents(block_index)?;
+    let key_images_by_block = ledger.get_key_images_by_block(block_index)?;
+
+    // Read everything before modifying the state, so that a read failure leaves it untouched.
+    let mut stored_tx_outs = Vec::with_capacity(block_contents.outputs.len());
+    let mut tx_out_indexes = Vec::with_capacity(block_contents.outputs.len());
+    for (i, tx_out) in block_contents.outputs.iter().enumerate() {
+        let expected_index = state.tx_outs_tree.num_leaves() + i as u64;
+        stored_tx_outs.push(allow_not_found(ledger.get_tx_out_by_index(expected_index))?);
+        tx_out_indexes.push(allow_not_found(
+            ledger.get_tx_out_index_by_hash(&tx_out.hash()),
+        )?);
+    }
+    let mut key_image_blocks = Vec::with_capacity(block_contents.key_images.len());
+    for key_image in &block_contents.key_images {
+        key_image_blocks.push(ledger.check_key_image(key_image)?);
+    }
+    let signature = allow_not_found(ledger.get_block_signature(block_index))?;
+    let collected_signatures = ledger.get_collected_block_signatures(block_index)?;
+
+    let first_tx_out_index = state.tx_outs_tree.num_leaves();
+    let expected_root_element = state.tx_outs_tree.root_element();
+    let expected_parent_id = state
+        .last_block
+        .as_ref()
+        .map_or_else(BlockID::default, |last_block| last_block.id.clone());
+
+    let report = &mut state.report;
+    let mut problems = Vec::new();
+    let mut add_problem = |kind: ProblemKind, details: String| problems.push((kind, details));
+
+    // Header.
+    if block.index != block_index {
+        add_problem(
+            ProblemKind::WrongIndex,
+            format!("Block is stored at index {}", block_index),
+        );
+    }
+    if !block.is_block_id_valid() {
+        add_problem(
+            ProblemKind::InvalidBlockId,
+            format!("Block ID {:?} does not match the block header", block.id),
+        );
+    }
+    if block.parent_id != expected_parent_id {
+        add_problem(
+            ProblemKind::WrongParent,
+            format!(
+                "Parent ID is {:?}, expected {:?}",
+                block.parent_id, expected_parent_id
+            ),
+        );
+    }
+    if block.contents_hash != block_contents.hash() {
+        add_problem(
+            ProblemKind::ContentsHashMismatch,
+            format!(
+                "Contents hash is {:?}, but the stored contents hash to {:?}",
+                block.contents_hash,
+                block_contents.hash()
+            ),
+        );
+    }
+
+    // TxOuts.
+    let expected_cumulative_txo_count = first_tx_out_index + block_contents.outputs.len() as u64;
+    if block.cumulative_txo_count != expected_cumulative_txo_count {
+        add_problem(
+            ProblemKind::CumulativeTxoCountMismatch,
+            format!(
+                "Cumulative TxOut count is {}, expected {}",
+                block.cumulative_txo_count, expected_cumulative_txo_count
+            ),
+        );
+    }
+    // The origin block has no TxOuts before it, so its root element is meaningless. Some ledgers
+    // leave root elements at their default value; those are counted, but cannot be checked.
+    if let Some(expected_root_element) = expected_root_element {
+        if block.root_element == Default::default() {
+            report.num_unchecked_root_elements += 1;
+        } else if block.root_element != expected_root_element {
+            add_problem(
+                ProblemKind::RootElementMismatch,
+                format!(
+                    "Root element is {:?}, expected {:?}",
+                    block.root_element, expected_root_element
+                ),
+            );
+        }
+    }
+    for (i, tx_out) in block_contents.outputs.iter().enumerate() {
+        let expected_index = first_tx_out_index + i as u64;
+        if stored_tx_outs[i].as_ref() != Some(tx_out) {
+            add_problem(
+                ProblemKind::TxOutMismatch,
+                format!(
+                    "TxOut {} of the block is not stored at index {}",
+                    i, expected_index
+                ),
+            );
+        }
+        if tx_out_indexes[i] != Some(expected_index) {
+            add_problem(
+                ProblemKind::TxOutMismatch,
+                format!(
+                    "TxOut {} of the block is indexed at {:?}, expected {}",
+                    i, tx_out_indexes[i], expected_index
+                ),
+            );
+        }
+    }
+
+    // Key images.
+    let mut key_images_in_block = HashSet::default();
+    for (key_image, spent_at) in block_contents.key_images.iter().zip(&key_image_blocks) {
+        if !key_images_in_block.insert(*key_image) {
+            add_problem(
+                ProblemKind::DuplicateKeyImage,
+                format!(
+                    "Key image {:?} appears more than once in the block",
+                    key_image
+                ),
+            );
+        }
+        match spent_at {
+            Some(spent_at) if *spent_at == block_index => {}
+            Some(spent_at) => add_problem(
+                ProblemKind::DuplicateKeyImage,
+                format!(
+                    "Key image {:?} is also spent in block {}",
+                    key_image, spent_at
+                ),
+            ),
+            None => add_problem(
+                ProblemKind::KeyImageIndexMismatch,
+                format!("Key image {:?} is not indexed", key_image),
+            ),
+        }
+    }
+    if key_images_by_block != block_contents.key_images {
+        add_problem(
+            ProblemKind::KeyImageIndexMismatch,
+            format!(
+                "The key images indexed for the block ({}) differ from its contents ({})",
+                key_images_by_block.len(),
+                block_contents.key_images.len()
+            ),
+        );
+    }
+
+    // Signatures.
+    let mut num_signatures = 0;
+    for signature in signature.iter().chain(&collected_signatures) {
+        num_signatures += 1;
+        if signature.verify(&block).is_err() {
+            add_problem(
+                ProblemKind::InvalidSignature,
+                format!("Signature by {:?} does not verify", signature.signer()),
+            );
+        }
+    }
+
+    for (kind, details) in problems {
+        report.add_problem(Some(block_index), kind, details);
+    }
+    report.num_blocks += 1;
+    report.num_tx_outs += block_contents.outputs.len() as u64;
+    report.num_key_images += block_contents.key_images.len() as u64;
+    report.num_signatures += num_signatures;
+    report.complete = false;
+    advance(state, block, &block_contents);
+    Ok(())
+}
+
+fn advance(state: &mut VerificationState, block: Block, block_contents: &BlockContents) {
+    for tx_out in &block_contents.outputs {
+        state.tx_outs_tree.push(tx_out);
+    }
+    state.last_block = Some(block);
+}
+
+/// Maps `Error::NotFound` to `None`, so that missing data is reported as a problem rather than
+/// stopping verification.
+fn allow_not_found<T>(result: Result<T, LedgerError>) -> Result<Option<T>, LedgerError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(LedgerError::NotFound) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
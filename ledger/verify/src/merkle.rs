@@ -0,0 +1,135 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Incremental computation of the root of the Merkle tree of TxOuts (see
+//! `mc_ledger_db::tx_out_store`), as TxOuts are added to it.
+
+use mc_transaction_core::{
+    membership_proofs::{hash_leaf, hash_nodes, NIL_HASH},
+    range::Range,
+    tx::{TxOut, TxOutMembershipElement, TxOutMembershipHash},
+};
+use serde::{Deserialize, Serialize};
+
+/// The hashes of the full subtrees of a Merkle tree, which are all that is needed to compute its
+/// root, and the roots of the trees obtained by adding more TxOuts.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MerkleFrontier {
+    /// Number of TxOuts in the tree.
+    num_leaves: u64,
+
+    /// Number of leaves and hash of each full subtree, from the leftmost (and largest) one to the
+    /// rightmost one.
+    subtrees: Vec<(u64, [u8; 32])>,
+}
+
+impl MerkleFrontier {
+    /// Number of TxOuts in the tree.
+    pub fn num_leaves(&self) -> u64 {
+        self.num_leaves
+    }
+
+    /// Adds a TxOut to the tree.
+    pub fn push(&mut self, tx_out: &TxOut) {
+        self.num_leaves += 1;
+        self.subtrees.push((1, hash_leaf(tx_out)));
+
+        // Merge subtrees of equal sizes.
+        while self.subtrees.len() >= 2 {
+            let (right_size, right_hash) = self.subtrees[self.subtrees.len() - 1];
+            let (left_size, left_hash) = self.subtrees[self.subtrees.len() - 2];
+            if left_size != right_size {
+                break;
+            }
+            self.subtrees.pop();
+            self.subtrees.pop();
+            self.subtrees
+                .push((left_size * 2, hash_nodes(&left_hash, &right_hash)));
+        }
+    }
+
+    /// The root element of the tree, as found at the end of a membership proof. None if the tree
+    /// is empty.
+    pub fn root_element(&self) -> Option<TxOutMembershipElement> {
+        let num_leaves_full_tree = self.num_leaves.checked_next_power_of_two()?;
+
+        // Combine subtrees from right to left. Ranges that contain no TxOuts hash to NIL_HASH.
+        let mut subtrees = self.subtrees.iter().rev();
+        let &(mut size, mut hash) = subtrees.next()?;
+        for (left_size, left_hash) in subtrees {
+            while size < *left_size {
+                hash = hash_nodes(&hash, &NIL_HASH);
+                size *= 2;
+            }
+            hash = hash_nodes(left_hash, &hash);
+            size *= 2;
+        }
+        while size < num_leaves_full_tree {
+            hash = hash_nodes(&hash, &NIL_HASH);
+            size *= 2;
+        }
+
+        Some(TxOutMembershipElement {
+            range: Range::new(0, num_leaves_full_tree - 1).ok()?,
+            hash: TxOutMembershipHash::from(hash),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_crypto_keys::RistrettoPrivate;
+    use mc_transaction_core::account_keys::AccountKey;
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Hash of the range `[low, high]` of a tree of `tx_outs`, computed from the definition.
+    fn range_hash(tx_outs: &[TxOut], low: u64, high: u64) -> [u8; 32] {
+        if low >= tx_outs.len() as u64 {
+            *NIL_HASH
+        } else if low == high {
+            hash_leaf(&tx_outs[low as usize])
+        } else {
+            let mid = (low + high) / 2;
+            hash_nodes(
+                &range_hash(tx_outs, low, mid),
+                &range_hash(tx_outs, mid + 1, high),
+            )
+        }
+    }
+
+    #[test]
+    // The root should match the one computed from the definition of the tree, for every size.
+    fn test_root_element() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let recipient = AccountKey::random(&mut rng);
+        let tx_outs: Vec<TxOut> = (0..17)
+            .map(|_i| {
+                TxOut::new(
+                    10,
+                    &recipient.default_subaddress(),
+                    &RistrettoPrivate::from_random(&mut rng),
+                    Default::default(),
+                    &mut rng,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        let mut frontier = MerkleFrontier::default();
+        assert_eq!(frontier.root_element(), None);
+
+        for (i, tx_out) in tx_outs.iter().enumerate() {
+            frontier.push(tx_out);
+            let num_leaves = i as u64 + 1;
+            assert_eq!(frontier.num_leaves(), num_leaves);
+
+            let high = num_leaves.next_power_of_two() - 1;
+            let expected = TxOutMembershipElement {
+                range: Range::new(0, high).unwrap(),
+                hash: TxOutMembershipHash::from(range_hash(&tx_outs[..=i], 0, high)),
+            };
+            assert_eq!(frontier.root_element(), Some(expected));
+        }
+    }
+}
@@ -0,0 +1,304 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Tests of ledger verification, against ledgers written to a LedgerDB.
+
+use mc_crypto_keys::{Ed25519Pair, RistrettoPrivate};
+use mc_ledger_db::{Ledger, LedgerDB};
+use mc_ledger_verify::{
+    verify_blocks, verify_totals, ProblemKind, VerificationReport, VerificationState,
+};
+use mc_transaction_core::{
+    account_keys::AccountKey,
+    ring_signature::KeyImage,
+    tx::{TxOut, TxOutMembershipElement},
+    Block, BlockContents, BlockSignature, BLOCK_VERSION,
+};
+use mc_util_from_random::FromRandom;
+use rand::{rngs::StdRng, SeedableRng};
+use tempdir::TempDir;
+
+/// Writes blocks to a new LedgerDB.
+struct TestLedger {
+    // Holds the directory of the ledger until the test ends.
+    _dir: TempDir,
+    ledger_db: LedgerDB,
+    rng: StdRng,
+    account_key: AccountKey,
+    signer: Ed25519Pair,
+    last_block: Option<Block>,
+    next_key_image: u64,
+}
+
+impl TestLedger {
+    fn new() -> Self {
+        let dir = TempDir::new("ledger_verify").unwrap();
+        let path = dir.path().to_path_buf();
+        LedgerDB::create(path.clone()).unwrap();
+        let ledger_db = LedgerDB::open(path).unwrap();
+
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let signer = Ed25519Pair::from_random(&mut rng);
+
+        Self {
+            _dir: dir,
+            ledger_db,
+            rng,
+            account_key,
+            signer,
+            last_block: None,
+            next_key_image: 1,
+        }
+    }
+
+    /// Contents of a block with two new outputs, and two new key images unless it is the origin
+    /// block.
+    fn next_block_contents(&mut self) -> BlockContents {
+        let outputs: Vec<TxOut> = (0..2)
+            .map(|_i| {
+                TxOut::new(
+                    10,
+                    &self.account_key.default_subaddress(),
+                    &RistrettoPrivate::from_random(&mut self.rng),
+                    Default::default(),
+                    &mut self.rng,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        let key_images = if self.last_block.is_none() {
+            vec![]
+        } else {
+            self.next_key_image += 2;
+            vec![
+                KeyImage::from(self.next_key_image - 2),
+                KeyImage::from(self.next_key_image - 1),
+            ]
+        };
+
+        let mut block_contents = BlockContents::new(key_images, outputs);
+        block_contents.sort();
+        block_contents
+    }
+
+    /// The root element of the TxOuts currently in the ledger, as stored by the TxOut store.
+    fn root_element(&self) -> TxOutMembershipElement {
+        let proofs = self
+            .ledger_db
+            .get_tx_out_proof_of_memberships(&[0])
+            .unwrap();
+        proofs[0].elements.last().unwrap().clone()
+    }
+
+    /// The header of a correct next block.
+    fn next_block(&self, block_contents: &BlockContents) -> Block {
+        self.next_block_with_root_element(block_contents, &self.root_element())
+    }
+
+    /// The header of a next block, with the given root element.
+    ///
+    /// The cumulative TxOut count is taken from the ledger rather than from the parent block, so
+    /// that a wrong count in one block does not carry over to the next ones.
+    fn next_block_with_root_element(
+        &self,
+        block_contents: &BlockContents,
+        root_element: &TxOutMembershipElement,
+    ) -> Block {
+        match &self.last_block {
+            None => Block::new_origin_block(&block_contents.outputs),
+            Some(parent) => Block::new(
+                BLOCK_VERSION,
+                &parent.id,
+                parent.index + 1,
+                self.ledger_db.num_txos().unwrap() + block_contents.outputs.len() as u64,
+                root_element,
+                block_contents,
+            ),
+        }
+    }
+
+    /// Appends a signed block.
+    fn append(&mut self, block: Block, block_contents: &BlockContents) {
+        let signature = BlockSignature::from_block_and_keypair(&block, &self.signer).unwrap();
+        self.ledger_db
+            .append_block(&block, block_contents, Some(&signature))
+            .unwrap();
+        self.last_block = Some(block);
+    }
+
+    /// Appends correct blocks.
+    fn append_valid_blocks(&mut self, num_blocks: usize) {
+        for _ in 0..num_blocks {
+            let block_contents = self.next_block_contents();
+            let block = self.next_block(&block_contents);
+            self.append(block, &block_contents);
+        }
+    }
+}
+
+fn verify(ledger_db: &LedgerDB) -> VerificationReport {
+    let mut state = VerificationState::default();
+    assert!(verify_blocks(
+        ledger_db,
+        &mut state,
+        ledger_db.num_blocks().unwrap()
+    ));
+    verify_totals(ledger_db, &mut state);
+    state.report
+}
+
+fn problem_kinds(report: &VerificationReport) -> Vec<(Option<u64>, ProblemKind)> {
+    report
+        .problems
+        .iter()
+        .map(|problem| (problem.block_index, problem.kind))
+        .collect()
+}
+
+#[test]
+// A ledger written by LedgerDB from valid blocks should be consistent.
+fn test_verify_consistent_ledger() {
+    let mut test_ledger = TestLedger::new();
+    test_ledger.append_valid_blocks(10);
+
+    let report = verify(&test_ledger.ledger_db);
+    assert_eq!(report.problems, vec![]);
+    assert_eq!(report.num_problems, 0);
+    assert_eq!(report.num_blocks, 10);
+    assert_eq!(report.num_tx_outs, 20);
+    assert_eq!(report.num_key_images, 18);
+    assert_eq!(report.num_signatures, 10);
+    assert_eq!(report.num_unchecked_root_elements, 0);
+    assert!(report.complete);
+}
+
+#[test]
+// Blocks whose headers disagree with the TxOut store should be reported.
+fn test_verify_detects_inconsistent_headers() {
+    let mut test_ledger = TestLedger::new();
+    test_ledger.append_valid_blocks(3);
+
+    // Block 3 has a wrong cumulative TxOut count.
+    let block_contents = test_ledger.next_block_contents();
+    let parent = test_ledger.last_block.clone().unwrap();
+    let block = Block::new(
+        BLOCK_VERSION,
+        &parent.id,
+        parent.index + 1,
+        parent.cumulative_txo_count + block_contents.outputs.len() as u64 + 1,
+        &test_ledger.root_element(),
+        &block_contents,
+    );
+    test_ledger.append(block, &block_contents);
+
+    // Block 4 has the root element of an earlier ledger.
+    let block_contents = test_ledger.next_block_contents();
+    let block = test_ledger.next_block_with_root_element(
+        &block_contents,
+        &test_ledger.ledger_db.get_block(3).unwrap().root_element,
+    );
+    test_ledger.append(block, &block_contents);
+
+    // Block 5 has a signature over another block.
+    let block_contents = test_ledger.next_block_contents();
+    let block = test_ledger.next_block(&block_contents);
+    let signature = BlockSignature::from_block_and_keypair(
+        test_ledger.last_block.as_ref().unwrap(),
+        &test_ledger.signer,
+    )
+    .unwrap();
+    test_ledger
+        .ledger_db
+        .append_block(&block, &block_contents, Some(&signature))
+        .unwrap();
+    test_ledger.last_block = Some(block);
+
+    test_ledger.append_valid_blocks(2);
+
+    let report = verify(&test_ledger.ledger_db);
+    assert_eq!(
+        problem_kinds(&report),
+        vec![
+            (Some(3), ProblemKind::CumulativeTxoCountMismatch),
+            (Some(4), ProblemKind::RootElementMismatch),
+            (Some(5), ProblemKind::InvalidSignature),
+        ]
+    );
+    assert_eq!(report.num_problems, 3);
+    assert_eq!(report.num_blocks, 8);
+    assert!(report.complete);
+}
+
+#[test]
+// A key image that appears twice in a block should be reported.
+fn test_verify_detects_duplicate_key_images() {
+    let mut test_ledger = TestLedger::new();
+    test_ledger.append_valid_blocks(2);
+
+    let mut block_contents = test_ledger.next_block_contents();
+    let key_image = block_contents.key_images[0];
+    block_contents.key_images.push(key_image);
+    block_contents.sort();
+    let block = test_ledger.next_block(&block_contents);
+    test_ledger.append(block, &block_contents);
+
+    let report = verify(&test_ledger.ledger_db);
+    assert_eq!(
+        problem_kinds(&report),
+        vec![(Some(2), ProblemKind::DuplicateKeyImage)]
+    );
+}
+
+#[test]
+// Blocks whose root element was left at its default value should be counted, not reported.
+fn test_verify_counts_default_root_elements() {
+    let mut test_ledger = TestLedger::new();
+    test_ledger.append_valid_blocks(1);
+
+    for _ in 0..3 {
+        let block_contents = test_ledger.next_block_contents();
+        let block = test_ledger.next_block_with_root_element(&block_contents, &Default::default());
+        test_ledger.append(block, &block_contents);
+    }
+
+    let report = verify(&test_ledger.ledger_db);
+    assert_eq!(report.num_problems, 0);
+    assert_eq!(report.num_unchecked_root_elements, 3);
+}
+
+#[test]
+// Verification interrupted after any block and resumed from its saved state should produce the same
+// report as an uninterrupted verification.
+fn test_verify_resumes_from_saved_state() {
+    let mut test_ledger = TestLedger::new();
+    test_ledger.append_valid_blocks(4);
+
+    // Blocks with a default and a wrong root element, so that the report is not trivial.
+    let block_contents = test_ledger.next_block_contents();
+    let block = test_ledger.next_block_with_root_element(&block_contents, &Default::default());
+    test_ledger.append(block, &block_contents);
+    let block_contents = test_ledger.next_block_contents();
+    let block = test_ledger.next_block_with_root_element(
+        &block_contents,
+        &test_ledger.ledger_db.get_block(1).unwrap().root_element,
+    );
+    test_ledger.append(block, &block_contents);
+    test_ledger.append_valid_blocks(4);
+
+    let ledger_db = &test_ledger.ledger_db;
+    let expected_report = verify(ledger_db);
+    assert_eq!(expected_report.num_problems, 1);
+    assert_eq!(expected_report.num_unchecked_root_elements, 1);
+
+    let mut state = VerificationState::default();
+    for end_block in 1..=ledger_db.num_blocks().unwrap() {
+        assert!(verify_blocks(ledger_db, &mut state, end_block));
+        assert_eq!(state.next_block(), end_block);
+        let saved = serde_json::to_string(&state).unwrap();
+        state = serde_json::from_str(&saved).unwrap();
+    }
+    verify_totals(ledger_db, &mut state);
+
+    assert_eq!(state.report, expected_report);
+}
@@ -4,7 +4,7 @@
 
 use crate::mobilecoind_api;
 use mc_api::external;
-use mc_transaction_core::account_keys;
+use mc_transaction_core::{account_keys, validation::TransactionValidationError};
 use std::convert::{From, TryFrom};
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -119,6 +119,62 @@ impl TryFrom<&mobilecoind_api::PublicAddress> for account_keys::PublicAddress {
     }
 }
 
+impl From<&TransactionValidationError> for mobilecoind_api::TxValidationError {
+    fn from(src: &TransactionValidationError) -> Self {
+        use mobilecoind_api::TxValidationError_Code as Code;
+
+        let code = match src {
+            TransactionValidationError::InputsProofsLengthMismatch => {
+                Code::InputsProofsLengthMismatch
+            }
+            TransactionValidationError::NoInputs => Code::NoInputs,
+            TransactionValidationError::TooManyInputs => Code::TooManyInputs,
+            TransactionValidationError::InsufficientInputSignatures => {
+                Code::InsufficientInputSignatures
+            }
+            TransactionValidationError::InvalidInputSignature => Code::InvalidInputSignature,
+            TransactionValidationError::InvalidTransactionSignature(_e) => {
+                Code::InvalidTransactionSignature
+            }
+            TransactionValidationError::InvalidRangeProof => Code::InvalidRangeProof,
+            TransactionValidationError::InsufficientRingSize => Code::InsufficientRingSize,
+            TransactionValidationError::TombstoneBlockExceeded => Code::TombstoneBlockExceeded,
+            TransactionValidationError::TombstoneBlockTooFar => Code::TombstoneBlockTooFar,
+            TransactionValidationError::NoOutputs => Code::NoOutputs,
+            TransactionValidationError::TooManyOutputs => Code::TooManyOutputs,
+            TransactionValidationError::ExcessiveRingSize => Code::ExcessiveRingSize,
+            TransactionValidationError::DuplicateRingElements => Code::DuplicateRingElements,
+            TransactionValidationError::UnsortedRingElements => Code::UnsortedRingElements,
+            TransactionValidationError::UnequalRingSizes => Code::UnequalRingSizes,
+            TransactionValidationError::UnsortedKeyImages => Code::UnsortedKeyImages,
+            TransactionValidationError::ContainsSpentKeyImage => Code::ContainsSpentKeyImage,
+            TransactionValidationError::DuplicateKeyImages => Code::DuplicateKeyImages,
+            TransactionValidationError::MissingTxOutMembershipProof => {
+                Code::MissingTxOutMembershipProof
+            }
+            TransactionValidationError::InvalidTxOutMembershipProof => {
+                Code::InvalidTxOutMembershipProof
+            }
+            TransactionValidationError::InvalidRistrettoPublicKey => {
+                Code::InvalidRistrettoPublicKey
+            }
+            TransactionValidationError::InvalidLedgerContext => Code::InvalidLedgerContext,
+            TransactionValidationError::Ledger(_) => Code::Ledger,
+            TransactionValidationError::MembershipProofValidationError => {
+                Code::MembershipProofValidationError
+            }
+            TransactionValidationError::TxFeeError => Code::TxFeeError,
+            TransactionValidationError::KeyError => Code::KeyError,
+            TransactionValidationError::UnsortedOutputs => Code::UnsortedOutputs,
+        };
+
+        let mut dst = mobilecoind_api::TxValidationError::new();
+        dst.set_code(code);
+        dst.set_description(src.to_string());
+        dst
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::mobilecoind_api;
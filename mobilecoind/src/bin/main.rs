@@ -5,13 +5,15 @@
 use mc_attest_core::MrSigner;
 use mc_common::logger::{create_app_logger, log, o, Logger};
 use mc_consensus_enclave_measurement::sigstruct;
-use mc_ledger_db::{Ledger, LedgerDB};
+use mc_ledger_db::{import_snapshot, Ledger, LedgerDB};
 use mc_ledger_sync::{LedgerSyncServiceThread, PollingNetworkState, ReqwestTransactionsFetcher};
 use mc_mobilecoind::{
     config::Config, database::Database, payments::TransactionsManager, service::Service,
 };
 use std::{
     convert::TryFrom,
+    fs::File,
+    io::BufReader,
     path::Path,
     sync::{Arc, Mutex},
 };
@@ -45,14 +47,40 @@ fn main() {
     // Create the ledger_db.
     let ledger_db = create_or_open_ledger_db(&config, &logger, &transactions_fetcher);
 
-    let _ledger_sync_service_thread = LedgerSyncServiceThread::new(
-        ledger_db.clone(),
-        peer_manager.clone(),
-        network_state.clone(),
-        transactions_fetcher,
-        config.poll_interval,
-        logger.clone(),
-    );
+    // Transactions must be built under the parameters the network's origin block commits to.
+    let origin_block = ledger_db
+        .get_block(0)
+        .expect("Failed getting the origin block");
+    if !config
+        .network_parameters()
+        .is_committed_by(&config.block_version_schedule(), &origin_block)
+    {
+        panic!(
+            "Configured network parameters or block version schedule do not match the origin block"
+        );
+    }
+
+    let _ledger_sync_service_thread = if config.subscribe_blocks {
+        LedgerSyncServiceThread::new_with_block_subscriptions(
+            ledger_db.clone(),
+            peer_manager.clone(),
+            network_state.clone(),
+            transactions_fetcher,
+            config.block_signature_policy(),
+            config.poll_interval,
+            logger.clone(),
+        )
+    } else {
+        LedgerSyncServiceThread::new(
+            ledger_db.clone(),
+            peer_manager.clone(),
+            network_state.clone(),
+            transactions_fetcher,
+            config.block_signature_policy(),
+            config.poll_interval,
+            logger.clone(),
+        )
+    };
 
     // Potentially launch API server
     match (&config.mobilecoind_db, &config.service_port) {
@@ -68,6 +96,9 @@ fn main() {
                 ledger_db.clone(),
                 mobilecoind_db.clone(),
                 peer_manager,
+                config.network_parameters(),
+                config.block_version_schedule(),
+                config.proof_cache_size(),
                 logger.clone(),
             );
 
@@ -101,11 +132,27 @@ fn main() {
     }
 }
 
+fn migrate_ledger_db(config: &Config, logger: &Logger) {
+    if LedgerDB::migrate(config.ledger_db.clone()).unwrap_or_else(|err| {
+        panic!(
+            "Could not migrate ledger db {:?}: {}",
+            config.ledger_db, err
+        )
+    }) {
+        log::info!(logger, "Ledger DB {:?} migrated", config.ledger_db);
+    }
+}
+
 fn create_or_open_ledger_db(
     config: &Config,
     logger: &Logger,
     transactions_fetcher: &ReqwestTransactionsFetcher,
 ) -> LedgerDB {
+    // Migrate a ledger created by an earlier version, so that it is not mistaken for a missing one.
+    if Path::new(&config.ledger_db).join("data.mdb").exists() {
+        migrate_ledger_db(config, logger);
+    }
+
     // Attempt to open the ledger and see if it has anything in it.
     if let Ok(ledger_db) = LedgerDB::open(config.ledger_db.clone()) {
         if let Ok(num_blocks) = ledger_db.num_blocks() {
@@ -123,7 +170,8 @@ fn create_or_open_ledger_db(
         }
     }
 
-    // Ledger doesn't exist, or is empty. Copy a bootstrapped ledger or try and get it from the network.
+    // Ledger doesn't exist, or is empty. Copy a bootstrapped ledger, import a snapshot, or try and
+    // get it from the network.
     let ledger_db_file = Path::new(&config.ledger_db).join("data.mdb");
     match &config.ledger_db_bootstrap {
         Some(ledger_db_bootstrap) => {
@@ -150,6 +198,35 @@ fn create_or_open_ledger_db(
                 )
             });
         }
+        None if config.ledger_snapshot.is_some() => {
+            let snapshot_path = config.ledger_snapshot.as_ref().unwrap();
+            log::info!(
+                logger,
+                "Ledger DB {:?} does not exist, importing snapshot {:?}",
+                config.ledger_db,
+                snapshot_path
+            );
+            let mut file = BufReader::new(
+                File::open(snapshot_path)
+                    .unwrap_or_else(|err| panic!("Failed opening {:?}: {}", snapshot_path, err)),
+            );
+            let header = config
+                .block_signature_policy()
+                .read_trusted_snapshot_header(&mut file)
+                .unwrap_or_else(|err| panic!("Failed reading {:?}: {}", snapshot_path, err));
+
+            std::fs::create_dir_all(config.ledger_db.clone()).expect("Could not create ledger dir");
+            LedgerDB::create(config.ledger_db.clone()).expect("Could not create ledger_db");
+            let mut db =
+                LedgerDB::open(config.ledger_db.clone()).expect("Could not open ledger_db");
+            import_snapshot(file, &mut db, &header)
+                .unwrap_or_else(|err| panic!("Failed importing {:?}: {}", snapshot_path, err));
+            log::info!(
+                logger,
+                "Imported {} blocks from snapshot",
+                header.num_blocks()
+            );
+        }
         None => {
             log::info!(
                     logger,
@@ -169,6 +246,9 @@ fn create_or_open_ledger_db(
         }
     }
 
+    // A bootstrapped ledger may have been created by an earlier version.
+    migrate_ledger_db(config, logger);
+
     // Open ledger and verify it has (at least) the origin block.
     log::debug!(logger, "Opening Ledger DB {:?}", config.ledger_db);
     let ledger_db = LedgerDB::open(config.ledger_db.clone())
@@ -2,10 +2,18 @@
 
 //! Configuration parameters for mobilecoind
 
+use crate::membership_proof_cache::DEFAULT_PROOF_CACHE_SIZE;
 use mc_attest_core::Measurement;
 use mc_common::{logger::Logger, ResponderId};
 use mc_connection::{ConnectionManager, ThickClient};
 use mc_consensus_scp::QuorumSet;
+use mc_crypto_keys::Ed25519Public;
+use mc_ledger_sync::BlockSignaturePolicy;
+use mc_transaction_core::{BlockVersionSchedule, NetworkParameters};
+use mc_util_parse::{
+    parse_block_version_schedule_from_json, parse_ed25519_public_from_hex,
+    parse_network_parameters_from_json,
+};
 use mc_util_uri::{ConnectionUri, ConsensusClientUri};
 use std::{path::PathBuf, str::FromStr, sync::Arc, time::Duration};
 use structopt::StructOpt;
@@ -21,6 +29,14 @@ pub struct Config {
     #[structopt(long)]
     pub ledger_db_bootstrap: Option<String>,
 
+    /// Path to a ledger snapshot, used when initializing new ledger dbs.
+    ///
+    /// The snapshot is checked against its chain of block IDs, and its last block must be signed
+    /// by enough trusted block signers (see `--trusted-block-signer`). Blocks after it are then
+    /// synced as usual.
+    #[structopt(long, parse(from_os_str), conflicts_with = "ledger-db-bootstrap")]
+    pub ledger_snapshot: Option<PathBuf>,
+
     #[structopt(flatten)]
     pub peers_config: PeersConfig,
 
@@ -31,9 +47,32 @@ pub struct Config {
     #[structopt(long, parse(try_from_str=parse_quorum_set_from_json))]
     quorum_set: Option<QuorumSet<ResponderId>>,
 
+    /// Transaction validation limits of the network. Omitted values take their defaults.
+    ///
+    /// The parameters are represented in JSON. For example:
+    /// {"ring_size":11,"base_fee":10000000000}
+    #[structopt(long, parse(try_from_str=parse_network_parameters_from_json))]
+    network_parameters: Option<NetworkParameters>,
+
+    /// Block version schedule of the network, which must match the one committed to by the
+    /// origin block.
+    ///
+    /// The schedule is represented in JSON. For example:
+    /// {"activations":[{"version":1,"block_index":1000}]}
+    #[structopt(long, parse(try_from_str=parse_block_version_schedule_from_json))]
+    block_version_schedule: Option<BlockVersionSchedule>,
+
+    /// Maximum number of TxOut membership proofs cached for building transactions.
+    ///
+    /// Defaults to `DEFAULT_PROOF_CACHE_SIZE`.
+    #[structopt(long)]
+    proof_cache_size: Option<usize>,
+
     /// URLs to use for transaction data.
     ///
     /// For example: https://s3-us-west-1.amazonaws.com/mobilecoin.chain/node1.test.mobilecoin.com/
+    ///
+    /// `s3://` and `file://` URLs are also accepted (see `mc_ledger_block_storage::StorageUri`).
     #[structopt(long = "tx-source-url", required = true, min_values = 1)]
     pub tx_source_urls: Vec<String>,
 
@@ -41,6 +80,24 @@ pub struct Config {
     #[structopt(long, default_value = "5", parse(try_from_str=parse_duration_in_seconds))]
     pub poll_interval: Duration,
 
+    /// Learn about new blocks from block subscriptions to the peers, instead of polling them
+    /// every `poll_interval`.
+    #[structopt(long)]
+    pub subscribe_blocks: bool,
+
+    /// Public key of a trusted block signer, hex-encoded. May be given multiple times.
+    ///
+    /// When set, blocks are only appended to the ledger once they are signed by
+    /// `min_trusted_block_signatures` distinct trusted signers, and the trusted signatures are
+    /// stored in the ledger. Signatures are fetched from the `tx_source_url`s of the peers, until
+    /// every trusted signer is covered.
+    #[structopt(long = "trusted-block-signer", parse(try_from_str=parse_ed25519_public_from_hex))]
+    trusted_block_signers: Vec<Ed25519Public>,
+
+    /// Number of distinct trusted signers that must have signed a block.
+    #[structopt(long, default_value = "1")]
+    min_trusted_block_signatures: usize,
+
     // Mobilecoind specific arguments
     /// Path to mobilecoind database used to store transactions and accounts.
     #[structopt(long, parse(from_os_str))]
@@ -66,6 +123,29 @@ fn parse_quorum_set_from_json(src: &str) -> Result<QuorumSet<ResponderId>, Strin
 }
 
 impl Config {
+    pub fn network_parameters(&self) -> NetworkParameters {
+        self.network_parameters.unwrap_or_default()
+    }
+
+    pub fn block_version_schedule(&self) -> BlockVersionSchedule {
+        self.block_version_schedule.clone().unwrap_or_default()
+    }
+
+    pub fn proof_cache_size(&self) -> usize {
+        self.proof_cache_size.unwrap_or(DEFAULT_PROOF_CACHE_SIZE)
+    }
+
+    pub fn block_signature_policy(&self) -> BlockSignaturePolicy {
+        if self.trusted_block_signers.is_empty() {
+            return BlockSignaturePolicy::default();
+        }
+        BlockSignaturePolicy::new(
+            self.trusted_block_signers.iter().cloned(),
+            self.min_trusted_block_signatures,
+        )
+        .unwrap_or_else(|err| panic!("Invalid block signature policy: {}", err))
+    }
+
     pub fn quorum_set(&self) -> QuorumSet<ResponderId> {
         // If we have an explicit quorum set, use that.
         if let Some(quorum_set) = &self.quorum_set {
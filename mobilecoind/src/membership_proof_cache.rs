@@ -0,0 +1,191 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A cache of TxOut membership proofs.
+//!
+//! Building a transaction requires a membership proof for every input and every ring member.
+//! Proofs of TxOuts that were used recently are cached by TxOut index, and brought up to date with
+//! `Ledger::update_tx_out_proof_of_memberships`, which only reads the parts of the Merkle tree
+//! that changed since the proof was cached.
+
+use crate::error::Error;
+use lru::LruCache;
+use mc_ledger_db::{Ledger, LedgerDB};
+use mc_transaction_core::tx::TxOutMembershipProof;
+use std::sync::{Arc, Mutex};
+
+/// Default number of proofs held by a `MembershipProofCache`.
+pub const DEFAULT_PROOF_CACHE_SIZE: usize = 10_000;
+
+#[derive(Clone)]
+pub struct MembershipProofCache {
+    /// Ledger database.
+    ledger_db: LedgerDB,
+
+    /// The most recently used proofs, by TxOut index.
+    proofs: Arc<Mutex<LruCache<u64, TxOutMembershipProof>>>,
+}
+
+impl MembershipProofCache {
+    /// Creates a cache holding at most `capacity` proofs.
+    ///
+    /// # Arguments
+    /// * `ledger_db` - The ledger that proofs are read from.
+    /// * `capacity` - Maximum number of cached proofs.
+    pub fn new(ledger_db: LedgerDB, capacity: usize) -> Self {
+        Self {
+            ledger_db,
+            proofs: Arc::new(Mutex::new(LruCache::new(capacity))),
+        }
+    }
+
+    /// Number of proofs currently cached.
+    pub fn len(&self) -> usize {
+        self.proofs.lock().expect("mutex poisoned").len()
+    }
+
+    /// Returns true if no proofs are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets proofs of membership for TxOuts with indexes `indexes`, at the current state of the
+    /// ledger.
+    ///
+    /// All returned proofs share the same `highest_index`, so they can be used together in a
+    /// single transaction.
+    ///
+    /// # Arguments
+    /// * `indexes` - TxOut indexes.
+    pub fn get_tx_out_proof_of_memberships(
+        &self,
+        indexes: &[u64],
+    ) -> Result<Vec<TxOutMembershipProof>, Error> {
+        let (cached_proofs, missing_indexes): (Vec<_>, Vec<_>) = {
+            let mut proofs = self.proofs.lock().expect("mutex poisoned");
+            let mut cached_proofs = Vec::new();
+            let mut missing_indexes = Vec::new();
+            for index in indexes {
+                match proofs.get(index) {
+                    Some(proof) => cached_proofs.push(proof.clone()),
+                    None => missing_indexes.push(*index),
+                }
+            }
+            (cached_proofs, missing_indexes)
+        };
+
+        let mut updated_proofs = self
+            .ledger_db
+            .update_tx_out_proof_of_memberships(&cached_proofs)?
+            .into_iter()
+            .chain(
+                self.ledger_db
+                    .get_tx_out_proof_of_memberships(&missing_indexes)?
+                    .into_iter(),
+            )
+            .map(|proof| (proof.index, proof))
+            .collect::<Vec<_>>();
+
+        // The ledger may have grown between the two reads above. If so, read every proof in a
+        // single database transaction instead.
+        let is_consistent = updated_proofs
+            .windows(2)
+            .all(|pair| pair[0].1.highest_index == pair[1].1.highest_index);
+        if !is_consistent {
+            updated_proofs = self
+                .ledger_db
+                .get_tx_out_proof_of_memberships(indexes)?
+                .into_iter()
+                .map(|proof| (proof.index, proof))
+                .collect();
+        }
+
+        let mut proofs = self.proofs.lock().expect("mutex poisoned");
+        for (index, proof) in &updated_proofs {
+            proofs.put(*index, proof.clone());
+        }
+
+        // Return proofs in the order they were requested.
+        indexes
+            .iter()
+            .map(|index| {
+                updated_proofs
+                    .iter()
+                    .find(|(proof_index, _)| proof_index == index)
+                    .map(|(_, proof)| proof.clone())
+                    .ok_or(Error::LedgerDB(mc_ledger_db::Error::NotFound))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{add_block_to_ledger_db, get_test_databases};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_transaction_core::account_keys::AccountKey;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_cached_proofs_are_updated(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let (mut ledger_db, _mobilecoind_db) = get_test_databases(
+            5,
+            &vec![account_key.default_subaddress()],
+            10,
+            logger,
+            &mut rng,
+        );
+
+        let cache = MembershipProofCache::new(ledger_db.clone(), 100);
+        assert!(cache.is_empty());
+
+        let indexes = vec![3, 0, 7];
+        let proofs = cache.get_tx_out_proof_of_memberships(&indexes).unwrap();
+        assert_eq!(
+            proofs,
+            ledger_db.get_tx_out_proof_of_memberships(&indexes).unwrap()
+        );
+        assert_eq!(cache.len(), 3);
+
+        // After the ledger grows, cached proofs are brought up to date.
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![account_key.default_subaddress()],
+            &[],
+            &mut rng,
+        );
+        let indexes = vec![7, 1, 3];
+        let proofs = cache.get_tx_out_proof_of_memberships(&indexes).unwrap();
+        assert_eq!(
+            proofs,
+            ledger_db.get_tx_out_proof_of_memberships(&indexes).unwrap()
+        );
+        assert_eq!(cache.len(), 4);
+
+        let highest_index = ledger_db.num_txos().unwrap() - 1;
+        assert!(proofs
+            .iter()
+            .all(|proof| proof.highest_index == highest_index));
+    }
+
+    #[test_with_logger]
+    fn test_cache_is_bounded(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let (ledger_db, _mobilecoind_db) = get_test_databases(
+            5,
+            &vec![account_key.default_subaddress()],
+            10,
+            logger,
+            &mut rng,
+        );
+
+        let cache = MembershipProofCache::new(ledger_db, 2);
+        cache
+            .get_tx_out_proof_of_memberships(&[0, 1, 2, 3])
+            .unwrap();
+        assert_eq!(cache.len(), 2);
+    }
+}
@@ -2,7 +2,10 @@
 
 //! Construct and submit transactions to the validator network.
 
-use crate::{database::Database, error::Error, monitor_store::MonitorId, utxo_store::UnspentTxOut};
+use crate::{
+    database::Database, error::Error, membership_proof_cache::MembershipProofCache,
+    monitor_store::MonitorId, utxo_store::UnspentTxOut,
+};
 
 use mc_common::{
     logger::{log, o, Logger},
@@ -14,11 +17,11 @@ use mc_crypto_rand::{CryptoRng, RngCore};
 use mc_ledger_db::{Error as LedgerError, Ledger, LedgerDB};
 use mc_transaction_core::{
     account_keys::{AccountKey, PublicAddress},
-    constants::{BASE_FEE, MAX_INPUTS, RING_SIZE},
+    constants::RING_SIZE,
     onetime_keys::recover_onetime_private_key,
     ring_signature::KeyImage,
     tx::{Tx, TxOut, TxOutMembershipProof},
-    BlockIndex,
+    BlockIndex, BlockVersionSchedule, NetworkParameters,
 };
 use mc_transaction_std::{InputCredentials, TransactionBuilder};
 use rand::Rng;
@@ -82,6 +85,15 @@ pub struct TransactionsManager<T: UserTxConnection + 'static> {
     /// Peer manager, for communicating with validator nodes.
     peer_manager: ConnectionManager<T>,
 
+    /// Limits of the network transactions are submitted to.
+    network_parameters: NetworkParameters,
+
+    /// Block versions of the network transactions are submitted to.
+    block_version_schedule: BlockVersionSchedule,
+
+    /// Recently used membership proofs.
+    membership_proof_cache: MembershipProofCache,
+
     /// Logger.
     logger: Logger,
 
@@ -95,6 +107,9 @@ impl<T: UserTxConnection + 'static> Clone for TransactionsManager<T> {
             ledger_db: self.ledger_db.clone(),
             mobilecoind_db: self.mobilecoind_db.clone(),
             peer_manager: self.peer_manager.clone(),
+            network_parameters: self.network_parameters,
+            block_version_schedule: self.block_version_schedule.clone(),
+            membership_proof_cache: self.membership_proof_cache.clone(),
             logger: self.logger.clone(),
             submit_node_offset: self.submit_node_offset.clone(),
         }
@@ -106,18 +121,35 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         ledger_db: LedgerDB,
         mobilecoind_db: Database,
         peer_manager: ConnectionManager<T>,
+        network_parameters: NetworkParameters,
+        block_version_schedule: BlockVersionSchedule,
+        proof_cache_size: usize,
         logger: Logger,
     ) -> Self {
         let mut rng = rand::thread_rng();
+        let membership_proof_cache = MembershipProofCache::new(ledger_db.clone(), proof_cache_size);
         Self {
             ledger_db,
             mobilecoind_db,
             peer_manager,
+            network_parameters,
+            block_version_schedule,
+            membership_proof_cache,
             logger,
             submit_node_offset: Arc::new(AtomicUsize::new(rng.next_u64() as usize)),
         }
     }
 
+    /// Limits of the network transactions are built for.
+    pub fn network_parameters(&self) -> &NetworkParameters {
+        &self.network_parameters
+    }
+
+    /// Block versions of the network transactions are built for.
+    pub fn block_version_schedule(&self) -> &BlockVersionSchedule {
+        &self.block_version_schedule
+    }
+
     pub fn build_transaction(
         &self,
         sender_monitor_id: &MonitorId,
@@ -154,11 +186,18 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         );
 
         // Figure out the fee.
-        let fee = if opt_fee > 0 { opt_fee } else { BASE_FEE };
+        let fee = if opt_fee > 0 {
+            opt_fee
+        } else {
+            self.network_parameters.base_fee
+        };
 
         // Select the UTXOs to be used for this transaction.
-        let selected_utxos =
-            Self::select_utxos_for_value(inputs, total_value + fee, MAX_INPUTS as usize)?;
+        let selected_utxos = Self::select_utxos_for_value(
+            inputs,
+            total_value + fee,
+            self.network_parameters.max_inputs as usize,
+        )?;
         log::trace!(
             logger,
             "Selected {} utxos ({:?})",
@@ -171,7 +210,6 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         log::trace!(logger, "Got membership proofs");
 
         // Get rings.
-        // TODO configurable ring size
         let excluded_tx_out_indices: Vec<u64> = selected_utxos
             .iter()
             .map(|utxo| {
@@ -182,7 +220,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
             .collect::<Result<Vec<u64>, Error>>()?;
 
         let rings = self.get_rings(
-            DEFAULT_RING_SIZE,
+            self.network_parameters.ring_size,
             selected_utxos_with_proofs.len(),
             &excluded_tx_out_indices,
         )?;
@@ -237,7 +275,8 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         let (selected_utxos, fee) = Self::select_utxos_for_optimization(
             num_blocks_in_ledger,
             &inputs,
-            MAX_INPUTS as usize,
+            self.network_parameters.max_inputs as usize,
+            self.network_parameters.base_fee,
         )?;
 
         log::trace!(
@@ -260,7 +299,6 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         log::trace!(logger, "Got membership proofs");
 
         // Get rings.
-        // TODO configurable ring size
         let excluded_tx_out_indices: Vec<u64> = selected_utxos
             .iter()
             .map(|utxo| {
@@ -271,7 +309,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
             .collect::<Result<Vec<u64>, Error>>()?;
 
         let rings = self.get_rings(
-            DEFAULT_RING_SIZE,
+            self.network_parameters.ring_size,
             selected_utxos_with_proofs.len(),
             &excluded_tx_out_indices,
         )?;
@@ -404,6 +442,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         num_blocks_in_ledger: u64,
         inputs: &[UnspentTxOut],
         max_inputs: usize,
+        fee: u64,
     ) -> Result<(Vec<UnspentTxOut>, u64), Error> {
         if max_inputs < 2 {
             return Err(Error::InvalidArgument(
@@ -442,9 +481,6 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
                 total += utxo.value;
             }
 
-            // Calculate the fee - right now this is constant.
-            let fee = BASE_FEE;
-
             // See if the total amount we are trying to merge into our biggest UTXO is bigger than the fee.
             // If it's smaller, the merge would just lose us money.
             if total > fee {
@@ -473,7 +509,9 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
             .iter()
             .map(|utxo| self.ledger_db.get_tx_out_index_by_hash(&utxo.tx_out.hash()))
             .collect::<Result<Vec<u64>, LedgerError>>()?;
-        let proofs = self.ledger_db.get_tx_out_proof_of_memberships(&indexes)?;
+        let proofs = self
+            .membership_proof_cache
+            .get_tx_out_proof_of_memberships(&indexes)?;
 
         Ok(utxos.into_iter().zip(proofs.into_iter()).collect())
     }
@@ -514,7 +552,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
 
         // Get proofs for all of those indexes.
         let proofs = self
-            .ledger_db
+            .membership_proof_cache
             .get_tx_out_proof_of_memberships(&sampled_indices_vec)?;
 
         // Create an iterator that returns (index, proof) elements.
@@ -743,6 +781,7 @@ mod test {
     use super::*;
     use mc_connection::ThickClient;
     use mc_crypto_keys::RistrettoPrivate;
+    use mc_transaction_core::constants::BASE_FEE;
     use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
 
@@ -845,8 +884,10 @@ mod test {
             utxos[5].value = 1000;
 
             let (selected_utxos, fee) =
-                TransactionsManager::<ThickClient>::select_utxos_for_optimization(1000, &utxos, 2)
-                    .unwrap();
+                TransactionsManager::<ThickClient>::select_utxos_for_optimization(
+                    1000, &utxos, 2, BASE_FEE,
+                )
+                .unwrap();
 
             assert_eq!(selected_utxos, vec![utxos[0].clone(), utxos[4].clone()]);
             assert_eq!(fee, BASE_FEE);
@@ -864,8 +905,10 @@ mod test {
             utxos[5].value = 1000;
 
             let (selected_utxos, fee) =
-                TransactionsManager::<ThickClient>::select_utxos_for_optimization(1000, &utxos, 3)
-                    .unwrap();
+                TransactionsManager::<ThickClient>::select_utxos_for_optimization(
+                    1000, &utxos, 3, BASE_FEE,
+                )
+                .unwrap();
 
             assert_eq!(
                 selected_utxos,
@@ -895,7 +938,7 @@ mod test {
             );
 
             let result = TransactionsManager::<ThickClient>::select_utxos_for_optimization(
-                1000, &utxos, 100,
+                1000, &utxos, 100, BASE_FEE,
             );
             assert!(result.is_err());
         }
@@ -908,7 +951,7 @@ mod test {
             utxos[1].value = 2000;
 
             let result = TransactionsManager::<ThickClient>::select_utxos_for_optimization(
-                1000, &utxos, 100,
+                1000, &utxos, 100, BASE_FEE,
             );
             assert!(result.is_err());
         }
@@ -923,8 +966,10 @@ mod test {
             utxos[3].value = 2;
 
             let (selected_utxos, fee) =
-                TransactionsManager::<ThickClient>::select_utxos_for_optimization(1000, &utxos, 3)
-                    .unwrap();
+                TransactionsManager::<ThickClient>::select_utxos_for_optimization(
+                    1000, &utxos, 3, BASE_FEE,
+                )
+                .unwrap();
             // Since we're limited to 3 inputs, the lowest input (of value 1) is going to get excluded.
             assert_eq!(
                 selected_utxos,
@@ -942,14 +987,19 @@ mod test {
         utxos[0].value = 2000;
         utxos[1].value = 2000;
 
-        let result =
-            TransactionsManager::<ThickClient>::select_utxos_for_optimization(1000, &[], 100);
+        let result = TransactionsManager::<ThickClient>::select_utxos_for_optimization(
+            1000,
+            &[],
+            100,
+            BASE_FEE,
+        );
         assert!(result.is_err());
 
         let result = TransactionsManager::<ThickClient>::select_utxos_for_optimization(
             1000,
             &utxos[0..1],
             100,
+            BASE_FEE,
         );
         assert!(result.is_err());
 
@@ -958,6 +1008,7 @@ mod test {
             1000,
             &utxos[0..2],
             2,
+            BASE_FEE,
         );
         assert!(result.is_ok());
 
@@ -965,6 +1016,7 @@ mod test {
             1000,
             &utxos[0..2],
             3,
+            BASE_FEE,
         );
         assert!(result.is_err());
     }
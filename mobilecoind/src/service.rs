@@ -23,6 +23,7 @@ use mc_common::{
 use mc_connection::{BlockchainConnection, UserTxConnection};
 use mc_crypto_keys::RistrettoPublic;
 use mc_ledger_db::{Ledger, LedgerDB};
+use mc_ledger_query::LedgerQueryService;
 use mc_ledger_sync::{NetworkState, PollingNetworkState};
 use mc_mobilecoind_api::mobilecoind_api_grpc::{create_mobilecoind_api, MobilecoindApi};
 use mc_transaction_core::{
@@ -71,6 +72,10 @@ impl Service {
             logger.clone(),
         );
 
+        // Ledger query API service.
+        let ledger_query_service =
+            LedgerQueryService::new(ledger_db.clone(), logger.clone()).into_service();
+
         let api = ServiceApi::new(
             transactions_manager,
             ledger_db,
@@ -92,7 +97,12 @@ impl Service {
         log::info!(logger, "Starting mobilecoind API Service on port {}", port);
         let server = mc_util_grpc::run_server(
             env,
-            vec![mobilecoind_service, health_service, build_info_service],
+            vec![
+                mobilecoind_service,
+                ledger_query_service,
+                health_service,
+                build_info_service,
+            ],
             port,
             &logger,
         );
@@ -744,6 +754,41 @@ impl<T: BlockchainConnection + UserTxConnection + 'static> ServiceApi<T> {
         Ok(response)
     }
 
+    fn validate_tx_impl(
+        &mut self,
+        request: mc_mobilecoind_api::ValidateTxRequest,
+    ) -> Result<mc_mobilecoind_api::ValidateTxResponse, RpcStatus> {
+        // Get TxProposal from request.
+        let tx_proposal = TxProposal::try_from(request.get_tx_proposal())
+            .map_err(|err| rpc_internal_error("tx_proposal.try_from", err, &self.logger))?;
+
+        let num_blocks = self
+            .ledger_db
+            .num_blocks()
+            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+
+        // Run every check a consensus node would perform, against our local copy of the ledger.
+        let mut rng = rand::thread_rng();
+        let errors = mc_ledger_db::validate_tx(
+            &self.ledger_db,
+            &tx_proposal.tx,
+            self.transactions_manager.network_parameters(),
+            self.transactions_manager.block_version_schedule(),
+            &mut rng,
+        )
+        .map_err(|err| rpc_internal_error("mc_ledger_db.validate_tx", err, &self.logger))?;
+
+        let mut response = mc_mobilecoind_api::ValidateTxResponse::new();
+        response.set_error_list(RepeatedField::from_vec(
+            errors
+                .iter()
+                .map(mc_mobilecoind_api::TxValidationError::from)
+                .collect(),
+        ));
+        response.set_num_blocks(num_blocks);
+        Ok(response)
+    }
+
     fn get_ledger_info_impl(
         &mut self,
         _request: mc_mobilecoind_api::Empty,
@@ -1078,6 +1123,7 @@ build_api! {
     generate_optimization_tx GenerateOptimizationTxRequest GenerateOptimizationTxResponse generate_optimization_tx_impl,
     generate_transfer_code_tx GenerateTransferCodeTxRequest GenerateTransferCodeTxResponse generate_transfer_code_tx_impl,
     submit_tx SubmitTxRequest SubmitTxResponse submit_tx_impl,
+    validate_tx ValidateTxRequest ValidateTxResponse validate_tx_impl,
     get_ledger_info Empty GetLedgerInfoResponse get_ledger_info_impl,
     get_block_info GetBlockInfoRequest GetBlockInfoResponse get_block_info_impl,
     get_tx_status_as_sender GetTxStatusAsSenderRequest GetTxStatusAsSenderResponse get_tx_status_as_sender_impl,
@@ -2305,6 +2351,106 @@ mod test {
         }
     }
 
+    #[test_with_logger]
+    fn test_validate_tx(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![sender.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Get list of unspent tx outs
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        assert!(!utxos.is_empty());
+
+        let receiver = AccountKey::random(&mut rng);
+        let outlays = vec![Outlay {
+            value: 123,
+            receiver: receiver.default_subaddress(),
+        }];
+
+        // Call generate tx.
+        let mut request = mc_mobilecoind_api::GenerateTxRequest::new();
+        request.set_sender_monitor_id(monitor_id.to_vec());
+        request.set_change_subaddress(0);
+        request.set_input_list(RepeatedField::from_vec(
+            utxos
+                .iter()
+                .map(mc_mobilecoind_api::UnspentTxOut::from)
+                .collect(),
+        ));
+        request.set_outlay_list(RepeatedField::from_vec(
+            outlays
+                .iter()
+                .map(mc_mobilecoind_api::Outlay::from)
+                .collect(),
+        ));
+
+        let response = client.generate_tx(&request).unwrap();
+        let mut tx_proposal = TxProposal::try_from(response.get_tx_proposal()).unwrap();
+
+        // A freshly generated transaction should pass validation.
+        {
+            let mut request = mc_mobilecoind_api::ValidateTxRequest::new();
+            request.set_tx_proposal(mc_mobilecoind_api::TxProposal::from(&tx_proposal));
+
+            let response = client.validate_tx(&request).unwrap();
+            assert_eq!(response.get_error_list(), &[]);
+            assert_eq!(response.num_blocks, ledger_db.num_blocks().unwrap());
+        }
+
+        // Lowering the fee should report both the fee and the signature failures.
+        {
+            tx_proposal.tx.prefix.fee = 0;
+
+            let mut request = mc_mobilecoind_api::ValidateTxRequest::new();
+            request.set_tx_proposal(mc_mobilecoind_api::TxProposal::from(&tx_proposal));
+
+            let response = client.validate_tx(&request).unwrap();
+            let codes: Vec<_> = response
+                .get_error_list()
+                .iter()
+                .map(|error| error.get_code())
+                .collect();
+            assert_eq!(
+                codes,
+                vec![
+                    mc_mobilecoind_api::TxValidationError_Code::InvalidTransactionSignature,
+                    mc_mobilecoind_api::TxValidationError_Code::TxFeeError,
+                ]
+            );
+        }
+
+        // Validation must not submit anything to the network.
+        for mock_peer in server_conn_manager.conns() {
+            assert!(mock_peer.read().proposed_txs.is_empty());
+        }
+    }
+
     #[test_with_logger]
     fn test_get_balance_impl(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
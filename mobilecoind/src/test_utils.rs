@@ -7,6 +7,7 @@
 
 use crate::{
     database::Database,
+    membership_proof_cache::DEFAULT_PROOF_CACHE_SIZE,
     monitor_store::{MonitorData, MonitorId},
     payments::TransactionsManager,
     service::Service,
@@ -25,7 +26,7 @@ use mc_transaction_core::{
     account_keys::{AccountKey, PublicAddress, DEFAULT_SUBADDRESS_INDEX},
     ring_signature::KeyImage,
     tx::TxOut,
-    Block, BlockContents, BLOCK_VERSION,
+    Block, BlockContents, BlockVersionSchedule, NetworkParameters, BLOCK_VERSION,
 };
 use mc_util_from_random::FromRandom;
 use mc_util_uri::ConnectionUri;
@@ -220,6 +221,9 @@ fn setup_server(
         ledger_db.clone(),
         mobilecoind_db.clone(),
         conn_manager.clone(),
+        NetworkParameters::default(),
+        BlockVersionSchedule::default(),
+        DEFAULT_PROOF_CACHE_SIZE,
         logger.clone(),
     );
 
@@ -8,7 +8,7 @@ use mc_common::{NodeID, ResponderId};
 use mc_consensus_scp::Msg;
 use mc_crypto_keys::{Ed25519Pair, Ed25519Signature, KeyError, Signer, Verifier};
 use mc_ledger_db::Ledger;
-use mc_transaction_core::{tx::TxHash, BlockID};
+use mc_transaction_core::{BlockID, ConsensusValue};
 use serde::{Deserialize, Serialize};
 use sha2::{digest::Digest, Sha256};
 use std::{convert::TryFrom, result::Result as StdResult};
@@ -18,7 +18,7 @@ use std::{convert::TryFrom, result::Result as StdResult};
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct ConsensusMsg {
     /// An SCP message, used to reach agreement on the set of values the next block will contain.
-    pub scp_msg: Msg<TxHash>,
+    pub scp_msg: Msg<ConsensusValue>,
 
     /// The block ID of the block the message is trying to append values to.
     pub prev_block_id: BlockID,
@@ -34,7 +34,7 @@ pub struct VerifiedConsensusMsg {
 }
 
 impl VerifiedConsensusMsg {
-    pub fn scp_msg(&self) -> &Msg<TxHash> {
+    pub fn scp_msg(&self) -> &Msg<ConsensusValue> {
         &self.inner.scp_msg
     }
 
@@ -117,7 +117,7 @@ impl From<SignatureError> for ConsensusMsgError {
 impl ConsensusMsg {
     pub fn from_scp_msg(
         ledger: &impl Ledger,
-        scp_msg: Msg<TxHash>,
+        scp_msg: Msg<ConsensusValue>,
         signer_key: &Ed25519Pair,
     ) -> StdResult<Self, ConsensusMsgError> {
         if scp_msg.slot_index == 0 {
@@ -175,6 +175,7 @@ mod tests {
     use mc_consensus_scp::{core_types::Ballot, msg::*, QuorumSet, SlotIndex};
     use mc_ledger_db::test_utils::get_mock_ledger;
     use mc_peers_test_utils::test_node_id_and_signer;
+    use mc_transaction_core::tx::TxHash;
     use std::convert::TryFrom;
 
     // Create a minimal ConsensusMsg for testing
@@ -182,7 +183,7 @@ mod tests {
         let (local_node_id, local_signer_key) = test_node_id_and_signer(22);
         let local_quorum_set = QuorumSet::empty();
 
-        let hash_tx = TxHash::default();
+        let hash_tx = ConsensusValue::TxHash(TxHash::default());
 
         let num_blocks = 10;
         let ledger = get_mock_ledger(num_blocks);
@@ -243,11 +244,11 @@ mod tests {
         assert_eq!(msg.scp_msg.quorum_set, m);
 
         let ser = mc_util_serial::serialize(&msg.scp_msg.topic).unwrap();
-        let m: Topic<TxHash> = mc_util_serial::deserialize(&ser).unwrap();
+        let m: Topic<ConsensusValue> = mc_util_serial::deserialize(&ser).unwrap();
         assert_eq!(msg.scp_msg.topic, m);
 
         let ser = mc_util_serial::serialize(&msg.scp_msg).unwrap();
-        let m: Msg<TxHash> = mc_util_serial::deserialize(&ser).unwrap();
+        let m: Msg<ConsensusValue> = mc_util_serial::deserialize(&ser).unwrap();
         assert_eq!(msg.scp_msg, m);
 
         let ser = mc_util_serial::serialize(&msg.prev_block_id).unwrap();
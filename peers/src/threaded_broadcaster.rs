@@ -67,22 +67,8 @@ impl<RP: RetryPolicy> ThreadedBroadcaster<RP> {
         let peer_threads: Vec<PeerThread> = manager
             .conns()
             .into_iter()
-            .filter(|conn| {
-                conn.uri()
-                    .get_param("broadcast-consensus-msgs")
-                    .unwrap_or_else(|| "1".to_string())
-                    == "1"
-            })
-            .map(|conn| {
-                let peer_name = conn.to_string();
-                PeerThread::new(
-                    conn,
-                    retry_policy,
-                    logger.new(o!(
-                        "mc.peers.peer_name" => peer_name,
-                    )),
-                )
-            })
+            .filter(Self::should_broadcast_to)
+            .map(|conn| Self::start_peer_thread(conn, retry_policy, &logger))
             .collect();
         Self {
             peer_threads,
@@ -99,6 +85,83 @@ impl<RP: RetryPolicy> ThreadedBroadcaster<RP> {
         }
     }
 
+    /// Reconcile the set of peers we broadcast to with the connections held by `manager`.
+    ///
+    /// Peer threads whose connection is unchanged keep running (along with any messages queued
+    /// for them). Threads of peers that were removed, or whose URI changed, are stopped.
+    pub fn update_peers<CC: ConsensusConnection + 'static>(
+        &mut self,
+        manager: &ConnectionManager<CC>,
+    ) {
+        let conns: Vec<SyncConnection<CC>> = manager
+            .conns()
+            .into_iter()
+            .filter(Self::should_broadcast_to)
+            .collect();
+
+        let (mut kept, mut stopped): (Vec<PeerThread>, Vec<PeerThread>) =
+            self.peer_threads.drain(..).partition(|peer_thread| {
+                conns.iter().any(|conn| {
+                    conn.remote_responder_id() == peer_thread.responder_id
+                        && conn.uri().to_string() == peer_thread.uri
+                })
+            });
+        for peer_thread in stopped.iter_mut() {
+            log::info!(
+                self.logger,
+                "No longer broadcasting to {}",
+                peer_thread.responder_id()
+            );
+            peer_thread.stop();
+        }
+
+        for conn in conns {
+            let is_running = kept
+                .iter()
+                .any(|peer_thread| peer_thread.responder_id == conn.remote_responder_id());
+            if !is_running {
+                log::info!(self.logger, "Broadcasting to {}", conn);
+                kept.push(Self::start_peer_thread(
+                    conn,
+                    &self.retry_policy,
+                    &self.logger,
+                ));
+            }
+        }
+
+        self.peer_threads = kept;
+    }
+
+    /// The responder IDs of the peers we broadcast to.
+    pub fn peer_responder_ids(&self) -> Vec<ResponderId> {
+        self.peer_threads
+            .iter()
+            .map(|peer_thread| peer_thread.responder_id().clone())
+            .collect()
+    }
+
+    fn should_broadcast_to<CC: ConsensusConnection>(conn: &SyncConnection<CC>) -> bool {
+        conn.uri()
+            .get_param("broadcast-consensus-msgs")
+            .unwrap_or_else(|| "1".to_string())
+            == "1"
+    }
+
+    fn start_peer_thread<CC: ConsensusConnection + 'static>(
+        conn: SyncConnection<CC>,
+        retry_policy: &RP,
+        logger: &Logger,
+    ) -> PeerThread {
+        let peer_name = conn.to_string();
+        PeerThread::new(
+            conn,
+            retry_policy,
+            logger.new(o!(
+                "mc.peers.peer_name" => peer_name,
+            )),
+        )
+    }
+
     /// Broadcasts a consensus message.
     ///
     /// # Arguments
@@ -250,6 +313,7 @@ enum ThreadMsg {
 /// A single peer thread.
 struct PeerThread {
     responder_id: ResponderId,
+    uri: String,
     sender: crossbeam_channel::Sender<ThreadMsg>,
     join_handle: Option<thread::JoinHandle<()>>,
 }
@@ -263,6 +327,7 @@ impl PeerThread {
         let (sender, receiver) = crossbeam_channel::unbounded();
 
         let responder_id = conn.remote_responder_id();
+        let uri = conn.uri().to_string();
 
         let retry_policy = retry_policy.clone();
 
@@ -276,6 +341,7 @@ impl PeerThread {
         );
         Self {
             responder_id,
+            uri,
             sender,
             join_handle,
         }
@@ -17,7 +17,7 @@ use mc_consensus_scp::{
 use mc_crypto_keys::Ed25519Pair;
 use mc_ledger_db::{test_utils::mock_ledger::MockLedger, Ledger};
 use mc_peers::{ConsensusConnection, ConsensusMsg, Error as PeerError, Result as PeerResult};
-use mc_transaction_core::{tx::TxHash, Block, BlockID, BlockIndex};
+use mc_transaction_core::{tx::TxHash, Block, BlockID, BlockIndex, ConsensusValue};
 use mc_util_from_random::FromRandom;
 use mc_util_uri::{ConnectionUri, ConsensusPeerUri as PeerUri};
 use rand::SeedableRng;
@@ -232,7 +232,7 @@ pub fn create_consensus_msg(
         Y: BTreeSet::default(),
     };
 
-    payload.X.insert(msg_hash);
+    payload.X.insert(ConsensusValue::TxHash(msg_hash));
     let topic = Topic::Nominate(payload);
     let scp_msg = Msg::new(sender_id, quorum_set, slot_index, topic);
     ConsensusMsg::from_scp_msg(ledger, scp_msg, signer_key)
@@ -387,6 +387,48 @@ mod peer_manager_tests {
         assert!(ret.is_ok());
         assert_eq!(peer.state().send_consensus_msg_call_count, 5);
     }
+
+    #[test_with_logger]
+    // Updating the connection manager keeps unchanged connections and reports what changed.
+    fn update_connections(logger: Logger) {
+        let (local_node_id, _) = test_node_id_and_signer(100);
+        let ledger = get_mock_ledger(1);
+        let peer =
+            |uri: PeerUri| MockPeerConnection::new(uri, local_node_id.clone(), ledger.clone(), 0);
+
+        let peer1 = peer(test_peer_uri(1));
+        let peer2 = peer(test_peer_uri(2));
+        let peer_manager = ConnectionManager::new(vec![peer1, peer2], logger);
+
+        // Peer 1 moves to a new URI, peer 2 is removed and peer 3 is added.
+        let peer1_moved =
+            peer(PeerUri::from_str(&format!("{}&new-param=1", test_peer_uri(1))).unwrap());
+        let peer3 = peer(test_peer_uri(3));
+        let changes = peer_manager.update(vec![peer1_moved.clone(), peer3.clone()]);
+
+        let responder_id = |uri: PeerUri| uri.responder_id().unwrap();
+        assert_eq!(changes.added, vec![responder_id(test_peer_uri(3))]);
+        assert_eq!(changes.removed, vec![responder_id(test_peer_uri(2))]);
+        assert_eq!(changes.replaced, vec![responder_id(test_peer_uri(1))]);
+        assert_eq!(
+            peer_manager.responder_ids(),
+            vec![
+                responder_id(test_peer_uri(1)),
+                responder_id(test_peer_uri(3))
+            ]
+        );
+        assert_eq!(
+            peer_manager
+                .conn(&responder_id(test_peer_uri(1)))
+                .unwrap()
+                .uri(),
+            peer1_moved.uri
+        );
+
+        // Updating with the same connections changes nothing.
+        let changes = peer_manager.update(vec![peer1_moved, peer3]);
+        assert!(changes.is_empty());
+    }
 }
 
 #[cfg(test)]
@@ -670,4 +712,65 @@ mod threaded_broadcaster_tests {
             assert_eq!(peer3.state().send_consensus_msg_call_count, 2);
         }
     }
+
+    #[test_with_logger]
+    // Peers added to the connection manager receive broadcasts once the broadcaster is updated,
+    // and removed peers no longer do.
+    fn test_update_peers(logger: Logger) {
+        let (local_node_id, _) = test_node_id_and_signer(1);
+        let node2_uri = test_peer_uri(2);
+        let node2 = NodeID::from(&node2_uri);
+        let node3_uri = test_peer_uri(3);
+        let node3 = NodeID::from(&node3_uri);
+
+        let quorum_set = QuorumSet::new_with_node_ids(2, vec![node2, node3]);
+        let ledger = get_mock_ledger(1);
+        let peer2 = MockPeerConnection::new(node2_uri, local_node_id.clone(), ledger.clone(), 0);
+        let peer3 = MockPeerConnection::new(node3_uri, local_node_id.clone(), ledger.clone(), 0);
+
+        let peer_manager = ConnectionManager::new(vec![peer2.clone()], logger.clone());
+        let mut broadcaster = ThreadedBroadcaster::new(
+            &peer_manager,
+            &FibonacciRetryPolicy::default(),
+            logger.clone(),
+        );
+
+        let mut seeded_rng: FixedRng = SeedableRng::from_seed([1u8; 32]);
+        let local_signer_key = Ed25519Pair::from_random(&mut seeded_rng);
+        let msg = |name: &str| {
+            create_consensus_msg(
+                &ledger,
+                local_node_id.clone(),
+                quorum_set.clone(),
+                1,
+                name,
+                &local_signer_key,
+            )
+        };
+
+        // Add peer 3.
+        peer_manager.update(vec![peer2.clone(), peer3.clone()]);
+        broadcaster.update_peers(&peer_manager);
+        assert_eq!(broadcaster.peer_responder_ids().len(), 2);
+
+        let msg1 = msg("msg1");
+        broadcaster.broadcast_consensus_msg(&msg1.issuer_responder_id(), &msg1);
+        broadcaster.barrier();
+        assert_eq!(peer2.msgs(), vec![msg1.clone()]);
+        assert_eq!(peer3.msgs(), vec![msg1.clone()]);
+
+        // Remove peer 2.
+        peer_manager.update(vec![peer3.clone()]);
+        broadcaster.update_peers(&peer_manager);
+        assert_eq!(
+            broadcaster.peer_responder_ids(),
+            vec![peer3.uri.responder_id().unwrap()]
+        );
+
+        let msg2 = msg("msg2");
+        broadcaster.broadcast_consensus_msg(&msg2.issuer_responder_id(), &msg2);
+        broadcaster.barrier();
+        assert_eq!(peer2.msgs(), vec![msg1.clone()]);
+        assert_eq!(peer3.msgs(), vec![msg1, msg2]);
+    }
 }
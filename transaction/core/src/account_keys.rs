@@ -278,17 +278,8 @@ impl AccountKey {
 
     /// The private spend key for the i^th subaddress.
     pub fn subaddress_spend_private(&self, index: u64) -> RistrettoPrivate {
-        let a: &Scalar = self.view_private_key.as_ref();
-
         // `Hs(a || n)`
-        let Hs: Scalar = {
-            let n = Scalar::from(index);
-            let mut digest = Blake2b::new();
-            digest.input(SUBADDRESS_DOMAIN_TAG);
-            digest.input(a.as_bytes());
-            digest.input(n.as_bytes());
-            Scalar::from_hash::<Blake2b>(digest)
-        };
+        let Hs: Scalar = subaddress_offset(&self.view_private_key, index);
 
         let b: &Scalar = self.spend_private_key.as_ref();
         RistrettoPrivate::from(Hs + b)
@@ -304,14 +295,7 @@ impl AccountKey {
         let a: &Scalar = self.view_private_key.as_ref();
 
         // `Hs(a || n)`
-        let Hs: Scalar = {
-            let n = Scalar::from(index);
-            let mut digest = Blake2b::new();
-            digest.input(SUBADDRESS_DOMAIN_TAG);
-            digest.input(a.as_bytes());
-            digest.input(n.as_bytes());
-            Scalar::from_hash::<Blake2b>(digest)
-        };
+        let Hs: Scalar = subaddress_offset(&self.view_private_key, index);
 
         let b: &Scalar = self.spend_private_key.as_ref();
         let c = a * (Hs + b);
@@ -319,6 +303,21 @@ impl AccountKey {
     }
 }
 
+/// Computes `Hs(a || n)`, the offset from the spend key `b` to the i^th subaddress spend key.
+///
+/// # Arguments
+/// * `view_private_key` - The account's private view key `a`.
+/// * `index` - The subaddress index `n`.
+pub(crate) fn subaddress_offset(view_private_key: &RistrettoPrivate, index: u64) -> Scalar {
+    let a: &Scalar = view_private_key.as_ref();
+    let n = Scalar::from(index);
+    let mut digest = Blake2b::new();
+    digest.input(SUBADDRESS_DOMAIN_TAG);
+    digest.input(a.as_bytes());
+    digest.input(n.as_bytes());
+    Scalar::from_hash::<Blake2b>(digest)
+}
+
 #[cfg(test)]
 mod account_key_tests {
     use super::*;
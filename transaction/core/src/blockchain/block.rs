@@ -2,7 +2,7 @@
 
 use crate::{
     tx::{TxOut, TxOutMembershipElement},
-    BlockContents, BlockContentsHash, BlockID,
+    BlockContents, BlockContentsHash, BlockID, BlockVersionSchedule, Feature, NetworkParameters,
 };
 use alloc::vec::Vec;
 use mc_crypto_digestible::{Digest, Digestible};
@@ -15,7 +15,7 @@ pub const BLOCK_VERSION: u32 = 0;
 /// The index of a block in the blockchain.
 pub type BlockIndex = u64;
 
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Digestible, Message)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Message)]
 /// A block of transactions in the blockchain.
 pub struct Block {
     /// Block ID.
@@ -46,23 +46,67 @@ pub struct Block {
     /// Hash of the block's contents.
     #[prost(message, required, tag = "7")]
     pub contents_hash: BlockContentsHash,
+
+    /// The close time agreed on by consensus, in seconds since the Unix epoch. Always zero in
+    /// blocks whose version does not enable `Feature::BlockTimestamp`.
+    #[prost(uint64, tag = "8")]
+    #[serde(default)]
+    pub timestamp: u64,
 }
 
 impl Block {
-    /// Creates the origin block.
+    /// Creates the origin block of a network that uses the default `NetworkParameters`.
     ///
     /// # Arguments
     /// * `outputs` - Outputs "minted" by the origin block.
     pub fn new_origin_block(outputs: &[TxOut]) -> Self {
+        Self::new_origin_block_with_parameters(outputs, &NetworkParameters::default())
+    }
+
+    /// Creates the origin block, committing to the given network parameters.
+    ///
+    /// # Arguments
+    /// * `outputs` - Outputs "minted" by the origin block.
+    /// * `parameters` - Parameters all nodes of the network must agree on.
+    pub fn new_origin_block_with_parameters(
+        outputs: &[TxOut],
+        parameters: &NetworkParameters,
+    ) -> Self {
+        Self::new_origin_block_with_schedule(outputs, parameters, &BlockVersionSchedule::default())
+    }
+
+    /// Creates the origin block, committing to the given network parameters and block version
+    /// schedule.
+    ///
+    /// The origin block has no membership proofs to validate against, so its root element
+    /// instead holds `parameters.origin_commitment(block_version_schedule)`. A network that uses
+    /// the defaults keeps the default root element, so that its origin block ID is unchanged.
+    ///
+    /// # Arguments
+    /// * `outputs` - Outputs "minted" by the origin block.
+    /// * `parameters` - Parameters all nodes of the network must agree on.
+    /// * `block_version_schedule` - Block version schedule all nodes of the network must agree on.
+    pub fn new_origin_block_with_schedule(
+        outputs: &[TxOut],
+        parameters: &NetworkParameters,
+        block_version_schedule: &BlockVersionSchedule,
+    ) -> Self {
         let version = BLOCK_VERSION;
         let parent_id = BlockID::default();
         let index: BlockIndex = 0;
         let cumulative_txo_count = outputs.len() as u64;
-        let root_element = TxOutMembershipElement::default();
+        let root_element = match parameters.origin_commitment(block_version_schedule) {
+            None => TxOutMembershipElement::default(),
+            Some(hash) => TxOutMembershipElement {
+                range: Default::default(),
+                hash,
+            },
+        };
         // The origin block does not contain any key images.
         let key_images = Vec::new();
         let block_contents = BlockContents::new(key_images, outputs.to_vec());
         let contents_hash = block_contents.hash();
+        let timestamp = 0;
         let id = compute_block_id(
             version,
             &parent_id,
@@ -70,6 +114,7 @@ impl Block {
             cumulative_txo_count,
             &root_element,
             &contents_hash,
+            timestamp,
         );
         Self {
             id,
@@ -79,6 +124,7 @@ impl Block {
             cumulative_txo_count,
             root_element,
             contents_hash,
+            timestamp,
         }
     }
 
@@ -97,14 +143,55 @@ impl Block {
         root_element: &TxOutMembershipElement,
         block_contents: &BlockContents,
     ) -> Self {
-        Block::new(
+        Block::new_with_parent_and_timestamp(version, parent, root_element, block_contents, 0)
+    }
+
+    /// Creates a new `Block` intermediate in the block chain, from a parent block, with the close
+    /// time agreed on by consensus.
+    ///
+    /// # Arguments
+    /// * `version` - The block format version
+    /// * `parent` - The parent block
+    /// * `root_element` - The root element for membership proofs
+    /// * `block_contents - The Contents of the block.
+    /// * `timestamp` - The close time, in seconds since the Unix epoch. Ignored if `version` does
+    ///   not enable `Feature::BlockTimestamp`.
+    pub fn new_with_parent_and_timestamp(
+        version: u32,
+        parent: &Block,
+        root_element: &TxOutMembershipElement,
+        block_contents: &BlockContents,
+        timestamp: u64,
+    ) -> Self {
+        let timestamp = if Feature::BlockTimestamp.is_enabled(version) {
+            timestamp
+        } else {
+            0
+        };
+        let contents_hash = block_contents.hash();
+        let index = parent.index + 1;
+        let cumulative_txo_count =
+            parent.cumulative_txo_count + block_contents.outputs.len() as u64;
+        let id = compute_block_id(
             version,
             &parent.id,
-            parent.index + 1,
-            parent.cumulative_txo_count + block_contents.outputs.len() as u64,
-            root_element,
-            block_contents,
-        )
+            index,
+            cumulative_txo_count,
+            &root_element,
+            &contents_hash,
+            timestamp,
+        );
+
+        Self {
+            id,
+            version,
+            parent_id: parent.id.clone(),
+            index,
+            cumulative_txo_count,
+            root_element: root_element.clone(),
+            contents_hash,
+            timestamp,
+        }
     }
 
     /// Creates a new `Block`.
@@ -127,6 +214,7 @@ impl Block {
         block_contents: &BlockContents,
     ) -> Self {
         let contents_hash = block_contents.hash();
+        let timestamp = 0;
         let id = compute_block_id(
             version,
             &parent_id,
@@ -134,6 +222,7 @@ impl Block {
             cumulative_txo_count,
             &root_element,
             &contents_hash,
+            timestamp,
         );
 
         Self {
@@ -144,6 +233,7 @@ impl Block {
             cumulative_txo_count,
             root_element: root_element.clone(),
             contents_hash,
+            timestamp,
         }
     }
 
@@ -159,16 +249,44 @@ impl Block {
             self.cumulative_txo_count,
             &self.root_element,
             &self.contents_hash,
+            self.timestamp,
         );
 
         self.id == expected_id
     }
 }
 
+// The timestamp is only digested in blocks whose version enables `Feature::BlockTimestamp`, so that
+// the digest of earlier blocks, and the signatures over it, are unchanged. Otherwise, this matches
+// `#[derive(Digestible)]`.
+impl Digestible for Block {
+    fn digest<D: Digest>(&self, hasher: &mut D) {
+        hasher.input(b"Block");
+        hasher.input(b"id");
+        self.id.digest(hasher);
+        hasher.input(b"version");
+        self.version.digest(hasher);
+        hasher.input(b"parent_id");
+        self.parent_id.digest(hasher);
+        hasher.input(b"index");
+        self.index.digest(hasher);
+        hasher.input(b"cumulative_txo_count");
+        self.cumulative_txo_count.digest(hasher);
+        hasher.input(b"root_element");
+        self.root_element.digest(hasher);
+        hasher.input(b"contents_hash");
+        self.contents_hash.digest(hasher);
+        if Feature::BlockTimestamp.is_enabled(self.version) {
+            hasher.input(b"timestamp");
+            self.timestamp.digest(hasher);
+        }
+    }
+}
+
 /// Computes the BlockID by hashing the contents of a block.
 ///
 /// The identifier of a block is the result of hashing everything inside a block except the `id`
-/// field.
+/// field. The timestamp is only hashed if `version` enables `Feature::BlockTimestamp`.
 pub fn compute_block_id<D: Digest>(
     version: u32,
     parent_id: &BlockID<D>,
@@ -176,6 +294,7 @@ pub fn compute_block_id<D: Digest>(
     cumulative_txo_count: u64,
     root_element: &TxOutMembershipElement,
     contents_hash: &BlockContentsHash<D>,
+    timestamp: u64,
 ) -> BlockID<D> {
     let mut hasher = D::new();
 
@@ -185,6 +304,9 @@ pub fn compute_block_id<D: Digest>(
     cumulative_txo_count.digest(&mut hasher);
     root_element.digest(&mut hasher);
     contents_hash.digest(&mut hasher);
+    if Feature::BlockTimestamp.is_enabled(version) {
+        timestamp.digest(&mut hasher);
+    }
 
     BlockID(hasher.result())
 }
@@ -200,9 +322,11 @@ mod block_tests {
     use alloc::vec::Vec;
     use core::convert::TryFrom;
     use generic_array::GenericArray;
+    use mc_crypto_digestible::{Digest, Digestible};
     use mc_crypto_keys::RistrettoPrivate;
     use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, CryptoRng, RngCore, SeedableRng};
+    use sha2::Sha256;
 
     fn get_block<RNG: CryptoRng + RngCore>(rng: &mut RNG) -> Block {
         let bytes = [14u8; 32];
@@ -308,6 +432,65 @@ mod block_tests {
         assert!(!block.is_block_id_valid());
     }
 
+    #[test]
+    /// The block ID should depend on the timestamp, from the version that enables it.
+    fn test_block_id_includes_timestamp() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let block = get_block(&mut rng);
+        let block_contents = BlockContents::new(Vec::new(), Vec::new());
+
+        let mut v2_block = Block::new_with_parent_and_timestamp(
+            2,
+            &block,
+            &block.root_element,
+            &block_contents,
+            1_590_000_000,
+        );
+        assert!(v2_block.is_block_id_valid());
+        v2_block.timestamp += 1;
+        assert!(!v2_block.is_block_id_valid());
+    }
+
+    mod previous {
+        use crate::{tx::TxOutMembershipElement, BlockContentsHash, BlockID, BlockIndex};
+        use mc_crypto_digestible::Digestible;
+
+        /// `Block` before timestamps were added.
+        #[derive(Digestible)]
+        pub struct Block {
+            pub id: BlockID,
+            pub version: u32,
+            pub parent_id: BlockID,
+            pub index: BlockIndex,
+            pub cumulative_txo_count: u64,
+            pub root_element: TxOutMembershipElement,
+            pub contents_hash: BlockContentsHash,
+        }
+    }
+
+    #[test]
+    /// The digest of blocks without a timestamp should be the digest they had before timestamps
+    /// were added.
+    fn test_digest_without_timestamp_is_unchanged() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let block = get_block(&mut rng);
+        let previous_block = previous::Block {
+            id: block.id.clone(),
+            version: block.version,
+            parent_id: block.parent_id.clone(),
+            index: block.index,
+            cumulative_txo_count: block.cumulative_txo_count,
+            root_element: block.root_element.clone(),
+            contents_hash: block.contents_hash.clone(),
+        };
+
+        let mut hasher = Sha256::default();
+        block.digest(&mut hasher);
+        let mut previous_hasher = Sha256::default();
+        previous_block.digest(&mut previous_hasher);
+        assert_eq!(hasher.result(), previous_hasher.result());
+    }
+
     #[test]
     #[ignore]
     // TODO: Block::new should return an error if `tx_hashes` contains duplicates.
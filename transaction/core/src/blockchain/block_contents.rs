@@ -32,6 +32,24 @@ impl BlockContents {
         }
     }
 
+    /// Sorts the key images, and the outputs by public key.
+    ///
+    /// This removes ordering information which could be used to infer the per-transaction
+    /// relationships among outputs and/or key images.
+    pub fn sort(&mut self) {
+        self.key_images.sort();
+        self.outputs.sort_by(|a, b| a.public_key.cmp(&b.public_key));
+    }
+
+    /// Returns true if the key images, and the outputs by public key, are sorted.
+    pub fn is_sorted(&self) -> bool {
+        self.key_images.windows(2).all(|pair| pair[0] <= pair[1])
+            && self
+                .outputs
+                .windows(2)
+                .all(|pair| pair[0].public_key <= pair[1].public_key)
+    }
+
     /// The Blake2B256 digest of `self`.
     pub fn hash(&self) -> BlockContentsHash {
         BlockContentsHash(self.digest_with::<Blake2b256>())
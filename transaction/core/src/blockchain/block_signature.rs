@@ -21,6 +21,12 @@ pub struct BlockSignature {
     /// The public key of the keypair used to generate the signature.
     #[prost(message, required, tag = "2")]
     signer: Ed25519Public,
+
+    /// Seconds since the Unix epoch at which the signer signed the block, according to its own
+    /// clock, or zero if unknown. It is covered by the signature when it is not zero.
+    #[prost(uint64, tag = "3")]
+    #[serde(default)]
+    signed_at: u64,
 }
 
 impl BlockSignature {
@@ -29,23 +35,43 @@ impl BlockSignature {
     /// # Arguments
     /// * `signature` - A block signature.
     /// * `signer` - The signer of the signature.
-    pub fn new(signature: Ed25519Signature, signer: Ed25519Public) -> Self {
-        Self { signature, signer }
+    /// * `signed_at` - Seconds since the Unix epoch at which the block was signed, or zero.
+    pub fn new(signature: Ed25519Signature, signer: Ed25519Public, signed_at: u64) -> Self {
+        Self {
+            signature,
+            signer,
+            signed_at,
+        }
     }
 
-    /// Create a new BlockSignature by signing a block.
+    /// Create a new BlockSignature by signing a block, without a signing time.
     pub fn from_block_and_keypair(
         block: &Block,
         keypair: &Ed25519Pair,
     ) -> Result<Self, Ed25519SignatureError> {
-        // SHA512 is used for compatibility with Ed25519ph.
-        let mut hasher = Sha512::default();
-        block.digest(&mut hasher);
-        let signature = keypair.try_sign_digest(hasher)?;
+        Self::from_block_keypair_and_time(block, keypair, 0)
+    }
+
+    /// Create a new BlockSignature by signing a block and the time at which it is signed.
+    ///
+    /// # Arguments
+    /// * `block` - The block to sign.
+    /// * `keypair` - The signer's keypair.
+    /// * `signed_at` - Seconds since the Unix epoch, or zero if unknown.
+    pub fn from_block_keypair_and_time(
+        block: &Block,
+        keypair: &Ed25519Pair,
+        signed_at: u64,
+    ) -> Result<Self, Ed25519SignatureError> {
+        let signature = keypair.try_sign_digest(signed_digest(block, signed_at))?;
 
         let signer = keypair.public_key();
 
-        Ok(Self { signature, signer })
+        Ok(Self {
+            signature,
+            signer,
+            signed_at,
+        })
     }
 
     /// Get the signature.
@@ -58,13 +84,44 @@ impl BlockSignature {
         &self.signer
     }
 
-    /// Verify that this signature is over a given block.
+    /// Get the time at which the block was signed, in seconds since the Unix epoch, or zero if
+    /// unknown.
+    pub fn signed_at(&self) -> u64 {
+        self.signed_at
+    }
+
+    /// Verify that this signature is over a given block, and over its signing time.
     pub fn verify(&self, block: &Block) -> Result<(), Ed25519SignatureError> {
-        let mut hasher = Sha512::default();
-        block.digest(&mut hasher);
+        self.signer
+            .verify_digest(signed_digest(block, self.signed_at), &self.signature)
+    }
+}
+
+/// A block and the time at which it was signed, as covered by a block signature that records its
+/// signing time.
+#[derive(Digestible)]
+struct SignedBlock {
+    block: Block,
+    signed_at: u64,
+}
 
-        self.signer.verify_digest(hasher, &self.signature)
+/// The digest signed by a block signature.
+///
+/// Signatures without a signing time are over the block's digest alone, so that signatures made
+/// before signing times were recorded remain valid.
+fn signed_digest(block: &Block, signed_at: u64) -> Sha512 {
+    // SHA512 is used for compatibility with Ed25519ph.
+    let mut hasher = Sha512::default();
+    if signed_at == 0 {
+        block.digest(&mut hasher);
+    } else {
+        SignedBlock {
+            block: block.clone(),
+            signed_at,
+        }
+        .digest(&mut hasher);
     }
+    hasher
 }
 
 impl Display for BlockSignature {
@@ -77,3 +134,62 @@ impl Display for BlockSignature {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tx::TxOutMembershipElement, BlockContents, BlockID, BLOCK_VERSION};
+    use alloc::vec::Vec;
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn get_block() -> Block {
+        let block_contents = BlockContents::new(Vec::new(), Vec::new());
+        Block::new(
+            BLOCK_VERSION,
+            &BlockID::default(),
+            1,
+            2,
+            &TxOutMembershipElement::default(),
+            &block_contents,
+        )
+    }
+
+    #[test]
+    // The signing time should be covered by the signature.
+    fn test_signed_at_is_signed() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let keypair = Ed25519Pair::from_random(&mut rng);
+        let block = get_block();
+
+        let signature =
+            BlockSignature::from_block_keypair_and_time(&block, &keypair, 1_590_000_000).unwrap();
+        assert_eq!(signature.signed_at(), 1_590_000_000);
+        assert!(signature.verify(&block).is_ok());
+
+        let tampered =
+            BlockSignature::new(*signature.signature(), *signature.signer(), 1_590_000_001);
+        assert!(tampered.verify(&block).is_err());
+    }
+
+    #[test]
+    // Signatures without a signing time should be over the block alone.
+    fn test_without_signed_at() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let keypair = Ed25519Pair::from_random(&mut rng);
+        let block = get_block();
+
+        let signature = BlockSignature::from_block_and_keypair(&block, &keypair).unwrap();
+        assert_eq!(signature.signed_at(), 0);
+        assert!(signature.verify(&block).is_ok());
+
+        let mut hasher = Sha512::default();
+        block.digest(&mut hasher);
+        let legacy = BlockSignature::new(
+            keypair.try_sign_digest(hasher).unwrap(),
+            keypair.public_key(),
+            0,
+        );
+        assert_eq!(legacy, signature);
+    }
+}
@@ -0,0 +1,437 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Block versions, and the protocol features each of them enables.
+//!
+//! Every block records the version of the rules it was formed under. A network moves to a new
+//! version at a block index that all of its nodes agree on (see `BlockVersionSchedule`). Blocks are
+//! formed deterministically from the transactions externalized by consensus, so a node whose
+//! schedule differs from the rest of the network forms blocks with different IDs and cannot
+//! silently diverge from it.
+
+use crate::{Block, BlockContents, BlockIndex, BLOCK_VERSION};
+use alloc::vec::Vec;
+use failure::Fail;
+use mc_crypto_digestible::Digestible;
+use serde::{Deserialize, Serialize};
+
+/// The highest block version this software is able to form and validate.
+pub const MAX_BLOCK_VERSION: u32 = 2;
+
+/// A protocol rule that is enabled starting at a particular block version.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Feature {
+    /// The outputs of each transaction must be sorted by public key, so that their order does not
+    /// reveal which output is the change output.
+    SortedTxOutputs,
+
+    /// The outputs and key images of each block must be sorted, so that their order does not
+    /// reveal which of them belong to the same transaction.
+    SortedBlockContents,
+
+    /// Each block records the close time agreed on by consensus, which its ID and digest cover.
+    /// Close times never decrease.
+    BlockTimestamp,
+}
+
+/// Every feature, in order of activation.
+pub const FEATURES: &[Feature] = &[
+    Feature::SortedTxOutputs,
+    Feature::SortedBlockContents,
+    Feature::BlockTimestamp,
+];
+
+impl Feature {
+    /// The first block version in which this feature is enabled.
+    pub fn activation_version(self) -> u32 {
+        match self {
+            Feature::SortedTxOutputs => 1,
+            Feature::SortedBlockContents => 1,
+            Feature::BlockTimestamp => 2,
+        }
+    }
+
+    /// Returns true if this feature is enabled in blocks of the given version.
+    ///
+    /// # Arguments
+    /// * `block_version` - The version of the block.
+    pub fn is_enabled(self, block_version: u32) -> bool {
+        block_version >= self.activation_version()
+    }
+}
+
+/// The features enabled in blocks of the given version.
+///
+/// # Arguments
+/// * `block_version` - The version of the block.
+pub fn enabled_features(block_version: u32) -> Vec<Feature> {
+    FEATURES
+        .iter()
+        .cloned()
+        .filter(|feature| feature.is_enabled(block_version))
+        .collect()
+}
+
+/// Reasons why a block version, or a schedule of block versions, is invalid.
+#[derive(Clone, Debug, Eq, Fail, PartialEq)]
+pub enum BlockVersionError {
+    /// The rules of this version are unknown to this software.
+    #[fail(display = "Unsupported block version: {}", _0)]
+    Unsupported(u32),
+
+    /// A block's version is lower than the version of its parent.
+    #[fail(
+        display = "Block version {} is lower than its parent's version {}",
+        _0, _1
+    )]
+    Decreased(u32, u32),
+
+    /// A block's contents do not follow the rules of its version.
+    #[fail(display = "Block contents violate the rules of block version {}", _0)]
+    InvalidContents(u32),
+
+    /// A block's timestamp is not allowed by its version, or is earlier than its parent's.
+    #[fail(display = "Invalid timestamp {} for block version {}", _0, _1)]
+    InvalidTimestamp(u64, u32),
+
+    /// The versions and block indices of a schedule's activations must be strictly increasing.
+    #[fail(display = "Block version activations are not strictly increasing")]
+    UnorderedActivations,
+}
+
+/// Activation of a new block version.
+#[derive(Clone, Debug, Deserialize, Digestible, Eq, Hash, PartialEq, Serialize)]
+pub struct BlockVersionActivation {
+    /// The block version being activated.
+    pub version: u32,
+
+    /// Index of the first block that uses `version`.
+    pub block_index: BlockIndex,
+}
+
+/// The block indices at which a network activates new block versions.
+///
+/// Blocks before the first activation use `BLOCK_VERSION`. Every node of a network must be
+/// configured with the same schedule, which the origin block commits to (see
+/// `Block::new_origin_block_with_schedule`).
+///
+/// Since the origin block commits to it, the schedule is fixed when the network is created. A
+/// running network can not agree to change it: new block versions can only be activated on a new
+/// network, by scheduling them ahead of time in its origin block.
+#[derive(Clone, Debug, Default, Deserialize, Digestible, Eq, Hash, PartialEq, Serialize)]
+pub struct BlockVersionSchedule {
+    /// Activations, in increasing order of version and block index.
+    pub activations: Vec<BlockVersionActivation>,
+}
+
+impl BlockVersionSchedule {
+    /// Checks that this software supports every scheduled version, and that versions only increase.
+    pub fn check(&self) -> Result<(), BlockVersionError> {
+        let mut previous: Option<&BlockVersionActivation> = None;
+        for activation in &self.activations {
+            if activation.version > MAX_BLOCK_VERSION {
+                return Err(BlockVersionError::Unsupported(activation.version));
+            }
+
+            let is_increasing = match previous {
+                None => activation.version > BLOCK_VERSION,
+                Some(previous) => {
+                    activation.version > previous.version
+                        && activation.block_index > previous.block_index
+                }
+            };
+            if !is_increasing {
+                return Err(BlockVersionError::UnorderedActivations);
+            }
+
+            previous = Some(activation);
+        }
+        Ok(())
+    }
+
+    /// The version of the block at `block_index`.
+    ///
+    /// # Arguments
+    /// * `block_index` - Index of the block.
+    pub fn version_at(&self, block_index: BlockIndex) -> u32 {
+        self.activations
+            .iter()
+            .take_while(|activation| activation.block_index <= block_index)
+            .last()
+            .map_or(BLOCK_VERSION, |activation| activation.version)
+    }
+}
+
+/// Checks that a block follows the rules of its version.
+///
+/// # Arguments
+/// * `block` - The block.
+/// * `block_contents` - Contents of `block`.
+/// * `parent` - The parent of `block`, or None for the origin block.
+pub fn validate_block_version(
+    block: &Block,
+    block_contents: &BlockContents,
+    parent: Option<&Block>,
+) -> Result<(), BlockVersionError> {
+    if block.version > MAX_BLOCK_VERSION {
+        return Err(BlockVersionError::Unsupported(block.version));
+    }
+
+    // A network can not return to the rules of an earlier version.
+    if let Some(parent) = parent {
+        if block.version < parent.version {
+            return Err(BlockVersionError::Decreased(block.version, parent.version));
+        }
+    }
+
+    if Feature::SortedBlockContents.is_enabled(block.version) && !block_contents.is_sorted() {
+        return Err(BlockVersionError::InvalidContents(block.version));
+    }
+
+    // Blocks only have a timestamp from `BlockTimestamp` onwards, and it never decreases.
+    let is_timestamp_valid = if Feature::BlockTimestamp.is_enabled(block.version) {
+        parent.map_or(true, |parent| block.timestamp >= parent.timestamp)
+    } else {
+        block.timestamp == 0
+    };
+    if !is_timestamp_valid {
+        return Err(BlockVersionError::InvalidTimestamp(
+            block.timestamp,
+            block.version,
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod block_version_tests {
+    use super::*;
+    use crate::{account_keys::AccountKey, ring_signature::KeyImage, tx::TxOut};
+    use mc_crypto_keys::RistrettoPrivate;
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn get_block_contents(num_outputs: usize) -> BlockContents {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let recipient = AccountKey::random(&mut rng);
+        let outputs: Vec<TxOut> = (0..num_outputs)
+            .map(|_i| {
+                TxOut::new(
+                    10,
+                    &recipient.default_subaddress(),
+                    &RistrettoPrivate::from_random(&mut rng),
+                    Default::default(),
+                    &mut rng,
+                )
+                .unwrap()
+            })
+            .collect();
+        let key_images: Vec<KeyImage> = (0..num_outputs as u64).map(KeyImage::from).collect();
+        BlockContents::new(key_images, outputs)
+    }
+
+    #[test]
+    /// Features should be enabled from their activation version onwards.
+    fn test_enabled_features() {
+        assert_eq!(enabled_features(BLOCK_VERSION), vec![]);
+        assert_eq!(
+            enabled_features(1),
+            vec![Feature::SortedTxOutputs, Feature::SortedBlockContents]
+        );
+        assert_eq!(
+            enabled_features(2),
+            vec![
+                Feature::SortedTxOutputs,
+                Feature::SortedBlockContents,
+                Feature::BlockTimestamp
+            ]
+        );
+        assert_eq!(enabled_features(MAX_BLOCK_VERSION), FEATURES.to_vec());
+    }
+
+    #[test]
+    /// `version_at` should return the most recently activated version.
+    fn test_version_at() {
+        assert_eq!(BlockVersionSchedule::default().version_at(0), BLOCK_VERSION);
+        assert_eq!(
+            BlockVersionSchedule::default().version_at(100),
+            BLOCK_VERSION
+        );
+
+        let schedule = BlockVersionSchedule {
+            activations: vec![BlockVersionActivation {
+                version: 1,
+                block_index: 10,
+            }],
+        };
+        assert_eq!(schedule.check(), Ok(()));
+        assert_eq!(schedule.version_at(0), BLOCK_VERSION);
+        assert_eq!(schedule.version_at(9), BLOCK_VERSION);
+        assert_eq!(schedule.version_at(10), 1);
+        assert_eq!(schedule.version_at(11), 1);
+    }
+
+    #[test]
+    /// `check` should reject unknown versions and activations that are out of order.
+    fn test_check_rejects_invalid_schedules() {
+        let schedule = BlockVersionSchedule {
+            activations: vec![BlockVersionActivation {
+                version: MAX_BLOCK_VERSION + 1,
+                block_index: 10,
+            }],
+        };
+        assert_eq!(
+            schedule.check(),
+            Err(BlockVersionError::Unsupported(MAX_BLOCK_VERSION + 1))
+        );
+
+        let schedule = BlockVersionSchedule {
+            activations: vec![BlockVersionActivation {
+                version: BLOCK_VERSION,
+                block_index: 10,
+            }],
+        };
+        assert_eq!(
+            schedule.check(),
+            Err(BlockVersionError::UnorderedActivations)
+        );
+
+        let schedule = BlockVersionSchedule {
+            activations: vec![
+                BlockVersionActivation {
+                    version: 1,
+                    block_index: 10,
+                },
+                BlockVersionActivation {
+                    version: 1,
+                    block_index: 20,
+                },
+            ],
+        };
+        assert_eq!(
+            schedule.check(),
+            Err(BlockVersionError::UnorderedActivations)
+        );
+    }
+
+    #[test]
+    /// `validate_block_version` should enforce the version bounds and the rules of each version.
+    fn test_validate_block_version() {
+        let mut block_contents = get_block_contents(4);
+        block_contents
+            .outputs
+            .sort_by(|a, b| b.public_key.cmp(&a.public_key));
+        assert!(!block_contents.is_sorted());
+
+        let origin = Block::new_origin_block(&[]);
+        let v0_block =
+            Block::new_with_parent(BLOCK_VERSION, &origin, &Default::default(), &block_contents);
+        let v1_block = Block::new_with_parent(1, &origin, &Default::default(), &block_contents);
+
+        // Unsorted contents are only allowed before `SortedBlockContents` is activated.
+        assert_eq!(
+            validate_block_version(&v0_block, &block_contents, Some(&origin)),
+            Ok(())
+        );
+        assert_eq!(
+            validate_block_version(&v1_block, &block_contents, Some(&origin)),
+            Err(BlockVersionError::InvalidContents(1))
+        );
+
+        let mut sorted_block_contents = block_contents.clone();
+        sorted_block_contents.sort();
+        assert!(sorted_block_contents.is_sorted());
+        let v1_block =
+            Block::new_with_parent(1, &origin, &Default::default(), &sorted_block_contents);
+        assert_eq!(
+            validate_block_version(&v1_block, &sorted_block_contents, Some(&origin)),
+            Ok(())
+        );
+
+        // A block can not have a lower version than its parent.
+        let block = Block::new_with_parent(
+            BLOCK_VERSION,
+            &v1_block,
+            &Default::default(),
+            &sorted_block_contents,
+        );
+        assert_eq!(
+            validate_block_version(&block, &sorted_block_contents, Some(&v1_block)),
+            Err(BlockVersionError::Decreased(BLOCK_VERSION, 1))
+        );
+
+        // Unknown versions are rejected.
+        let block = Block::new_with_parent(
+            MAX_BLOCK_VERSION + 1,
+            &v1_block,
+            &Default::default(),
+            &sorted_block_contents,
+        );
+        assert_eq!(
+            validate_block_version(&block, &sorted_block_contents, Some(&v1_block)),
+            Err(BlockVersionError::Unsupported(MAX_BLOCK_VERSION + 1))
+        );
+    }
+
+    #[test]
+    /// `validate_block_version` should only allow timestamps from `BlockTimestamp` onwards, and
+    /// should reject timestamps earlier than the parent's.
+    fn test_validate_block_timestamp() {
+        let mut block_contents = get_block_contents(4);
+        block_contents.sort();
+        let origin = Block::new_origin_block(&[]);
+
+        // Earlier versions ignore the timestamp, and a block with one is invalid.
+        let v1_block = Block::new_with_parent_and_timestamp(
+            1,
+            &origin,
+            &Default::default(),
+            &block_contents,
+            1_590_000_000,
+        );
+        assert_eq!(v1_block.timestamp, 0);
+        let mut invalid_block = v1_block.clone();
+        invalid_block.timestamp = 1_590_000_000;
+        assert_eq!(
+            validate_block_version(&invalid_block, &block_contents, Some(&origin)),
+            Err(BlockVersionError::InvalidTimestamp(1_590_000_000, 1))
+        );
+
+        let v2_block = Block::new_with_parent_and_timestamp(
+            2,
+            &v1_block,
+            &Default::default(),
+            &block_contents,
+            1_590_000_000,
+        );
+        assert_eq!(v2_block.timestamp, 1_590_000_000);
+        assert_eq!(
+            validate_block_version(&v2_block, &block_contents, Some(&v1_block)),
+            Ok(())
+        );
+
+        // Timestamps never decrease.
+        let block = Block::new_with_parent_and_timestamp(
+            2,
+            &v2_block,
+            &Default::default(),
+            &block_contents,
+            1_590_000_000,
+        );
+        assert_eq!(
+            validate_block_version(&block, &block_contents, Some(&v2_block)),
+            Ok(())
+        );
+        let block = Block::new_with_parent_and_timestamp(
+            2,
+            &v2_block,
+            &Default::default(),
+            &block_contents,
+            1_589_999_999,
+        );
+        assert_eq!(
+            validate_block_version(&block, &block_contents, Some(&v2_block)),
+            Err(BlockVersionError::InvalidTimestamp(1_589_999_999, 2))
+        );
+    }
+}
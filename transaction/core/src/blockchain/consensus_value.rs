@@ -0,0 +1,149 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+use crate::tx::{TxHash, TX_HASH_LEN};
+use core::fmt;
+use mc_crypto_digestible::{Digest, Digestible};
+use serde::{
+    de::{Error as DeserializeError, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// A value that consensus nodes agree on to form the next block.
+///
+/// Each node nominates the transactions it would like to include in the block along with its
+/// current time. Combining nominated values keeps the latest close time, so that the externalized
+/// values contain the transactions of the block and the close time it records. Close times are
+/// only nominated for blocks whose version enables `Feature::BlockTimestamp`.
+///
+/// A transaction is serialized and digested exactly like a plain `TxHash`, so that until close
+/// times are enabled, consensus messages are the same as those of nodes that only agree on
+/// transactions.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ConsensusValue {
+    /// A transaction to include in the block.
+    TxHash(TxHash),
+
+    /// The close time of the block, in seconds since the Unix epoch.
+    CloseTime(u64),
+}
+
+impl ConsensusValue {
+    /// The transaction hash, if this value is a transaction.
+    pub fn tx_hash(&self) -> Option<&TxHash> {
+        match self {
+            ConsensusValue::TxHash(tx_hash) => Some(tx_hash),
+            ConsensusValue::CloseTime(_) => None,
+        }
+    }
+
+    /// The close time, if this value is a close time.
+    pub fn close_time(&self) -> Option<u64> {
+        match self {
+            ConsensusValue::TxHash(_) => None,
+            ConsensusValue::CloseTime(close_time) => Some(*close_time),
+        }
+    }
+}
+
+impl From<TxHash> for ConsensusValue {
+    fn from(tx_hash: TxHash) -> Self {
+        ConsensusValue::TxHash(tx_hash)
+    }
+}
+
+impl Digestible for ConsensusValue {
+    fn digest<D: Digest>(&self, hasher: &mut D) {
+        match self {
+            ConsensusValue::TxHash(tx_hash) => tx_hash.digest(hasher),
+            ConsensusValue::CloseTime(close_time) => {
+                hasher.input(b"ConsensusValue");
+                hasher.input(b"CloseTime");
+                close_time.digest(hasher);
+            }
+        }
+    }
+}
+
+// A transaction is serialized as its `TxHash`, i.e. a sequence of bytes, and a close time as an
+// integer.
+impl Serialize for ConsensusValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ConsensusValue::TxHash(tx_hash) => tx_hash.serialize(serializer),
+            ConsensusValue::CloseTime(close_time) => close_time.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ConsensusValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ConsensusValueVisitor;
+
+        impl<'de> Visitor<'de> for ConsensusValueVisitor {
+            type Value = ConsensusValue;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(
+                    formatter,
+                    "a transaction hash of {} bytes, or a close time",
+                    TX_HASH_LEN
+                )
+            }
+
+            fn visit_u64<E: DeserializeError>(self, close_time: u64) -> Result<Self::Value, E> {
+                Ok(ConsensusValue::CloseTime(close_time))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut bytes = [0u8; TX_HASH_LEN];
+                for (index, byte) in bytes.iter_mut().enumerate() {
+                    *byte = seq
+                        .next_element()?
+                        .ok_or_else(|| A::Error::invalid_length(index, &self))?;
+                }
+                if seq.next_element::<u8>()?.is_some() {
+                    return Err(A::Error::invalid_length(TX_HASH_LEN + 1, &self));
+                }
+                Ok(ConsensusValue::TxHash(TxHash(bytes)))
+            }
+        }
+
+        deserializer.deserialize_any(ConsensusValueVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blake2b_256::Blake2b256;
+
+    #[test]
+    // A transaction should be serialized and digested like its TxHash, and a close time should
+    // round-trip.
+    fn test_tx_hash_encoding_is_unchanged() {
+        let tx_hash = TxHash([7u8; TX_HASH_LEN]);
+        let value = ConsensusValue::TxHash(tx_hash);
+
+        let bytes = mc_util_serial::serialize(&value).unwrap();
+        assert_eq!(bytes, mc_util_serial::serialize(&tx_hash).unwrap());
+        assert_eq!(
+            mc_util_serial::deserialize::<ConsensusValue>(&bytes).unwrap(),
+            value
+        );
+        assert_eq!(
+            value.digest_with::<Blake2b256>(),
+            tx_hash.digest_with::<Blake2b256>()
+        );
+
+        let close_time = ConsensusValue::CloseTime(1_590_000_000);
+        let bytes = mc_util_serial::serialize(&close_time).unwrap();
+        assert_eq!(
+            mc_util_serial::deserialize::<ConsensusValue>(&bytes).unwrap(),
+            close_time
+        );
+        assert_ne!(
+            close_time.digest_with::<Blake2b256>(),
+            ConsensusValue::CloseTime(1_590_000_001).digest_with::<Blake2b256>()
+        );
+    }
+}
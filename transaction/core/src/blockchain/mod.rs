@@ -8,11 +8,15 @@ mod block;
 mod block_contents;
 mod block_id;
 mod block_signature;
+mod block_version;
+mod consensus_value;
 
 pub use block::*;
 pub use block_contents::*;
 pub use block_id::*;
 pub use block_signature::*;
+pub use block_version::*;
+pub use consensus_value::*;
 
 #[derive(Debug, Fail)]
 /// Array conversion errors.
@@ -38,3 +38,9 @@ pub const TXOUT_MERKLE_NIL_DOMAIN_TAG: &str = "mc_tx_out_merkle_nil";
 
 /// Domain separator for RingMLSAG's challenges.
 pub const RING_MLSAG_CHALLENGE_DOMAIN_TAG: &str = "mc_ring_mlsag_challenge";
+
+/// Domain separator for a multisig signer's commitment to their nonces.
+pub const MULTISIG_NONCE_COMMITMENT_DOMAIN_TAG: &str = "mc_multisig_nonce_commitment";
+
+/// Domain separator for the challenge of a multisig partial key image's proof.
+pub const MULTISIG_PARTIAL_KEY_IMAGE_DOMAIN_TAG: &str = "mc_multisig_partial_key_image";
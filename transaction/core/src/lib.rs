@@ -26,6 +26,8 @@ mod domain_separators;
 pub mod encrypted_fog_hint;
 pub mod fog_hint;
 pub mod membership_proofs;
+pub mod multisig_keys;
+pub mod network_parameters;
 pub mod onetime_keys;
 pub mod range;
 pub mod range_proofs;
@@ -40,6 +42,7 @@ pub mod proptest_fixtures;
 pub use blockchain::*;
 pub use commitment::*;
 pub use compressed_commitment::*;
+pub use network_parameters::NetworkParameters;
 
 /// Get the shared secret for a transaction output.
 ///
@@ -0,0 +1,571 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Threshold (M-of-N) multisig account keys.
+//!
+//! A multisig account has an ordinary private view key `a`, known to every participant, and a
+//! spend private key `b` that is never assembled in one place. Instead, `b` is Shamir-shared
+//! among N participants with a threshold of M, using a Pedersen-style distributed key
+//! generation in which every participant deals a random polynomial of degree M - 1:
+//!
+//! 1. Participant `i` publishes commitments `a_{i,k} * G` to the coefficients of their
+//!    polynomial `f_i`, and privately sends `f_i(j)` to each participant `j`.
+//! 2. Participant `j` checks each received share against the dealer's commitments and sums them
+//!    into their secret share `s_j = sum_i f_i(j)`.
+//!
+//! The shared spend public key is `B = sum_i a_{i,0} * G`, and any M participants can jointly
+//! sign for outputs sent to the account's subaddresses (see `ring_signature::multisig`).
+//!
+//! Secret shares must be sent to their recipients over an authenticated, encrypted channel.
+//! That transport is outside the scope of this module.
+
+#![allow(non_snake_case)]
+
+use crate::{
+    account_keys::{subaddress_offset, PublicAddress},
+    onetime_keys::recover_onetime_private_key,
+    ring_signature::{CurveScalar, Error},
+};
+use alloc::{vec, vec::Vec};
+use core::convert::TryFrom;
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT, ristretto::RistrettoPoint, scalar::Scalar,
+    traits::Identity,
+};
+use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPrivate, RistrettoPublic};
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+const G: RistrettoPoint = RISTRETTO_BASEPOINT_POINT;
+
+/// Identifies a participant in a multisig account. Participant indices start at 1.
+pub type ParticipantIndex = u32;
+
+/// A participant's public commitments to the coefficients of their key generation polynomial.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct KeygenCommitment {
+    /// The dealer of this polynomial.
+    pub participant: ParticipantIndex,
+
+    /// `a_{i,0} * G, ..., a_{i,M-1} * G`.
+    pub coefficient_commitments: Vec<CompressedRistrettoPublic>,
+}
+
+/// A secret share `f_i(j)` sent from dealer `i` to participant `j`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct KeygenSecretShare {
+    /// The dealer `i`.
+    pub from: ParticipantIndex,
+
+    /// The recipient `j`.
+    pub to: ParticipantIndex,
+
+    /// The share `f_i(j)`.
+    pub value: CurveScalar,
+}
+
+/// One participant's state during distributed key generation.
+pub struct KeygenDealer {
+    participant: ParticipantIndex,
+    threshold: u32,
+    num_participants: u32,
+
+    /// Secret coefficients `a_{i,0}, ..., a_{i,M-1}` of this dealer's polynomial.
+    coefficients: Vec<Scalar>,
+}
+
+impl KeygenDealer {
+    /// Samples a random polynomial of degree `threshold - 1`.
+    ///
+    /// # Arguments
+    /// * `participant` - This participant's index, in `[1, num_participants]`.
+    /// * `threshold` - Number of participants required to sign (M).
+    /// * `num_participants` - Total number of participants (N).
+    /// * `rng` - Randomness.
+    pub fn new<CSPRNG: RngCore + CryptoRng>(
+        participant: ParticipantIndex,
+        threshold: u32,
+        num_participants: u32,
+        rng: &mut CSPRNG,
+    ) -> Result<Self, Error> {
+        check_parameters(threshold, num_participants)?;
+        check_participant(participant, num_participants)?;
+
+        let coefficients: Vec<Scalar> = (0..threshold).map(|_| Scalar::random(rng)).collect();
+
+        Ok(Self {
+            participant,
+            threshold,
+            num_participants,
+            coefficients,
+        })
+    }
+
+    /// Public commitments to this dealer's coefficients, broadcast to every participant.
+    pub fn commitment(&self) -> KeygenCommitment {
+        KeygenCommitment {
+            participant: self.participant,
+            coefficient_commitments: self
+                .coefficients
+                .iter()
+                .map(|a| CompressedRistrettoPublic::from(a * G))
+                .collect(),
+        }
+    }
+
+    /// The share `f_i(to)` for participant `to`.
+    pub fn share_for(&self, to: ParticipantIndex) -> Result<KeygenSecretShare, Error> {
+        check_participant(to, self.num_participants)?;
+        Ok(KeygenSecretShare {
+            from: self.participant,
+            to,
+            value: CurveScalar::from(evaluate_polynomial(&self.coefficients, to)),
+        })
+    }
+
+    /// This dealer's participant index.
+    pub fn participant(&self) -> ParticipantIndex {
+        self.participant
+    }
+
+    /// Number of participants required to sign.
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+}
+
+/// A participant's share of a multisig account's spend private key.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultisigKeyShare {
+    /// The owner of this share.
+    participant: ParticipantIndex,
+
+    /// Number of participants required to sign (M).
+    threshold: u32,
+
+    /// This participant's secret share `s_j` of the spend private key `b`.
+    secret_share: RistrettoPrivate,
+
+    /// The shared spend public key `B`.
+    spend_public_key: RistrettoPublic,
+
+    /// Verification share `s_k * G` of every participant `k`, ordered by participant index.
+    verification_shares: Vec<RistrettoPublic>,
+}
+
+impl MultisigKeyShare {
+    /// Completes distributed key generation for `participant`.
+    ///
+    /// # Arguments
+    /// * `participant` - The participant receiving `shares`.
+    /// * `threshold` - Number of participants required to sign (M).
+    /// * `commitments` - One commitment from every participant.
+    /// * `shares` - One secret share addressed to `participant` from every participant.
+    pub fn from_keygen(
+        participant: ParticipantIndex,
+        threshold: u32,
+        commitments: &[KeygenCommitment],
+        shares: &[KeygenSecretShare],
+    ) -> Result<Self, Error> {
+        let num_participants = commitments.len() as u32;
+        check_parameters(threshold, num_participants)?;
+        check_participant(participant, num_participants)?;
+
+        // Decompress every dealer's commitments, indexed by dealer.
+        let mut decompressed: Vec<Option<Vec<RistrettoPoint>>> =
+            vec![None; num_participants as usize];
+        for commitment in commitments {
+            check_participant(commitment.participant, num_participants)?;
+            if commitment.coefficient_commitments.len() != threshold as usize {
+                return Err(Error::LengthMismatch(
+                    threshold as usize,
+                    commitment.coefficient_commitments.len(),
+                ));
+            }
+            let points = commitment
+                .coefficient_commitments
+                .iter()
+                .map(|point| RistrettoPublic::try_from(point).map(|p| *p.as_ref()))
+                .collect::<Result<Vec<RistrettoPoint>, _>>()?;
+            let slot = &mut decompressed[commitment.participant as usize - 1];
+            if slot.is_some() {
+                return Err(Error::DuplicateParticipant(commitment.participant));
+            }
+            *slot = Some(points);
+        }
+        let dealer_commitments: Vec<Vec<RistrettoPoint>> = decompressed
+            .into_iter()
+            .enumerate()
+            .map(|(i, points)| points.ok_or(Error::MissingParticipant(i as u32 + 1)))
+            .collect::<Result<_, _>>()?;
+
+        // Verify each received share against its dealer's commitments.
+        let mut received = vec![false; num_participants as usize];
+        let mut secret_share = Scalar::zero();
+        for share in shares {
+            check_participant(share.from, num_participants)?;
+            if share.to != participant {
+                return Err(Error::InvalidKeyShare(share.from));
+            }
+            if received[share.from as usize - 1] {
+                return Err(Error::DuplicateParticipant(share.from));
+            }
+            received[share.from as usize - 1] = true;
+
+            let expected =
+                evaluate_commitments(&dealer_commitments[share.from as usize - 1], participant);
+            if share.value.scalar * G != expected {
+                return Err(Error::InvalidKeyShare(share.from));
+            }
+            secret_share += share.value.scalar;
+        }
+        if let Some(missing) = received.iter().position(|r| !r) {
+            return Err(Error::MissingParticipant(missing as u32 + 1));
+        }
+
+        let spend_public_key: RistrettoPoint = dealer_commitments.iter().map(|c| c[0]).sum();
+
+        let verification_shares: Vec<RistrettoPublic> = (1..=num_participants)
+            .map(|k| {
+                let point: RistrettoPoint = dealer_commitments
+                    .iter()
+                    .map(|c| evaluate_commitments(c, k))
+                    .sum();
+                RistrettoPublic::from(point)
+            })
+            .collect();
+
+        Ok(Self {
+            participant,
+            threshold,
+            secret_share: RistrettoPrivate::from(secret_share),
+            spend_public_key: RistrettoPublic::from(spend_public_key),
+            verification_shares,
+        })
+    }
+
+    /// The owner of this share.
+    pub fn participant(&self) -> ParticipantIndex {
+        self.participant
+    }
+
+    /// Number of participants required to sign.
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    /// Total number of participants.
+    pub fn num_participants(&self) -> u32 {
+        self.verification_shares.len() as u32
+    }
+
+    /// This participant's secret share `s_j` of the spend private key.
+    pub fn secret_share(&self) -> &RistrettoPrivate {
+        &self.secret_share
+    }
+
+    /// The shared spend public key `B`.
+    pub fn spend_public_key(&self) -> &RistrettoPublic {
+        &self.spend_public_key
+    }
+
+    /// Verification share `s_k * G` of participant `k`.
+    pub fn verification_share(&self, k: ParticipantIndex) -> Result<&RistrettoPublic, Error> {
+        check_participant(k, self.num_participants())?;
+        Ok(&self.verification_shares[k as usize - 1])
+    }
+
+    /// Verification shares of every participant, ordered by participant index.
+    pub fn verification_shares(&self) -> &[RistrettoPublic] {
+        &self.verification_shares
+    }
+}
+
+/// The public half of a multisig account, held by whoever builds transactions for it.
+///
+/// This is the analogue of `AccountKey` for an account whose spend private key is shared.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultisigAccount {
+    /// Private key `a` used for view-key matching, known to every participant.
+    view_private_key: RistrettoPrivate,
+
+    /// The shared spend public key `B`.
+    spend_public_key: RistrettoPublic,
+
+    /// Number of participants required to sign (M).
+    threshold: u32,
+
+    /// Verification share `s_k * G` of every participant `k`, ordered by participant index.
+    verification_shares: Vec<RistrettoPublic>,
+}
+
+impl MultisigAccount {
+    /// A multisig account.
+    ///
+    /// # Arguments
+    /// * `view_private_key` - The account's private view key `a`.
+    /// * `key_share` - Any participant's key share, used for the account's public values.
+    pub fn new(view_private_key: &RistrettoPrivate, key_share: &MultisigKeyShare) -> Self {
+        Self {
+            view_private_key: *view_private_key,
+            spend_public_key: key_share.spend_public_key,
+            threshold: key_share.threshold,
+            verification_shares: key_share.verification_shares.clone(),
+        }
+    }
+
+    /// Get the view private key.
+    pub fn view_private_key(&self) -> &RistrettoPrivate {
+        &self.view_private_key
+    }
+
+    /// The shared spend public key `B`.
+    pub fn spend_public_key(&self) -> &RistrettoPublic {
+        &self.spend_public_key
+    }
+
+    /// Number of participants required to sign.
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    /// Total number of participants.
+    pub fn num_participants(&self) -> u32 {
+        self.verification_shares.len() as u32
+    }
+
+    /// Verification share `s_k * G` of participant `k`.
+    pub fn verification_share(&self, k: ParticipantIndex) -> Result<&RistrettoPublic, Error> {
+        check_participant(k, self.num_participants())?;
+        Ok(&self.verification_shares[k as usize - 1])
+    }
+
+    /// Verification shares of every participant, ordered by participant index.
+    pub fn verification_shares(&self) -> &[RistrettoPublic] {
+        &self.verification_shares
+    }
+
+    /// Get the account's default subaddress.
+    pub fn default_subaddress(&self) -> PublicAddress {
+        self.subaddress(crate::account_keys::DEFAULT_SUBADDRESS_INDEX)
+    }
+
+    /// Get the account's i^th subaddress `(C, D) = (a * D, Hs(a || i) * G + B)`.
+    pub fn subaddress(&self, index: u64) -> PublicAddress {
+        let a: &Scalar = self.view_private_key.as_ref();
+        let Hs = subaddress_offset(&self.view_private_key, index);
+        let D = Hs * G + self.spend_public_key.as_ref();
+        let C = a * D;
+        PublicAddress::new(&RistrettoPublic::from(D), &RistrettoPublic::from(C))
+    }
+
+    /// The publicly computable part `t = Hs(a * R) + Hs(a || i)` of an output's onetime private
+    /// key `x = t + b`.
+    ///
+    /// # Arguments
+    /// * `tx_public_key` - The output's transaction public key `R`.
+    /// * `subaddress_index` - The subaddress the output was sent to.
+    pub fn onetime_private_key_offset(
+        &self,
+        tx_public_key: &RistrettoPublic,
+        subaddress_index: u64,
+    ) -> Scalar {
+        let offset =
+            RistrettoPrivate::from(subaddress_offset(&self.view_private_key, subaddress_index));
+        let t = recover_onetime_private_key(tx_public_key, &self.view_private_key, &offset);
+        let t: &Scalar = t.as_ref();
+        *t
+    }
+}
+
+/// Runs every participant's side of distributed key generation in one process.
+///
+/// Whoever runs this sees every secret share, so it is only suitable for tests and tools.
+///
+/// # Arguments
+/// * `threshold` - Number of participants required to sign (M).
+/// * `num_participants` - Total number of participants (N).
+/// * `rng` - Randomness.
+pub fn keygen_in_process<CSPRNG: RngCore + CryptoRng>(
+    threshold: u32,
+    num_participants: u32,
+    rng: &mut CSPRNG,
+) -> Result<Vec<MultisigKeyShare>, Error> {
+    let dealers: Vec<KeygenDealer> = (1..=num_participants)
+        .map(|i| KeygenDealer::new(i, threshold, num_participants, rng))
+        .collect::<Result<_, _>>()?;
+    let commitments: Vec<KeygenCommitment> = dealers.iter().map(|d| d.commitment()).collect();
+    (1..=num_participants)
+        .map(|j| {
+            let shares: Vec<KeygenSecretShare> = dealers
+                .iter()
+                .map(|d| d.share_for(j))
+                .collect::<Result<_, _>>()?;
+            MultisigKeyShare::from_keygen(j, threshold, &commitments, &shares)
+        })
+        .collect()
+}
+
+/// The Lagrange coefficient `lambda_i` of `participant` for interpolating at zero over `signers`.
+///
+/// # Arguments
+/// * `participant` - A member of `signers`.
+/// * `signers` - Distinct participant indices.
+pub fn lagrange_coefficient(
+    participant: ParticipantIndex,
+    signers: &[ParticipantIndex],
+) -> Result<Scalar, Error> {
+    if participant == 0 {
+        return Err(Error::InvalidParticipant(participant));
+    }
+    if !signers.contains(&participant) {
+        return Err(Error::MissingParticipant(participant));
+    }
+    let x_i = Scalar::from(participant);
+    let mut numerator = Scalar::one();
+    let mut denominator = Scalar::one();
+    for (n, &j) in signers.iter().enumerate() {
+        if j == 0 {
+            return Err(Error::InvalidParticipant(j));
+        }
+        if signers[..n].contains(&j) {
+            return Err(Error::DuplicateParticipant(j));
+        }
+        if j == participant {
+            continue;
+        }
+        let x_j = Scalar::from(j);
+        numerator *= x_j;
+        denominator *= x_j - x_i;
+    }
+    Ok(numerator * denominator.invert())
+}
+
+/// Evaluates `f(x) = sum_k coefficients[k] * x^k`.
+fn evaluate_polynomial(coefficients: &[Scalar], x: ParticipantIndex) -> Scalar {
+    let x = Scalar::from(x);
+    coefficients
+        .iter()
+        .rev()
+        .fold(Scalar::zero(), |acc, coefficient| acc * x + coefficient)
+}
+
+/// Evaluates `f(x) * G` from commitments `coefficients[k] * G`.
+fn evaluate_commitments(commitments: &[RistrettoPoint], x: ParticipantIndex) -> RistrettoPoint {
+    let x = Scalar::from(x);
+    let mut power = Scalar::one();
+    let mut sum = RistrettoPoint::identity();
+    for commitment in commitments {
+        sum += power * commitment;
+        power *= x;
+    }
+    sum
+}
+
+fn check_parameters(threshold: u32, num_participants: u32) -> Result<(), Error> {
+    if threshold == 0 || threshold > num_participants {
+        return Err(Error::InvalidThreshold(threshold, num_participants));
+    }
+    Ok(())
+}
+
+fn check_participant(participant: ParticipantIndex, num_participants: u32) -> Result<(), Error> {
+    if participant == 0 || participant > num_participants {
+        return Err(Error::InvalidParticipant(participant));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod multisig_keys_tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    // Every participant should agree on the spend public key and verification shares.
+    fn test_keygen_participants_agree() {
+        let mut rng: StdRng = SeedableRng::from_seed([7u8; 32]);
+        let key_shares = keygen_in_process(2, 3, &mut rng).unwrap();
+        for key_share in &key_shares {
+            assert_eq!(
+                key_share.spend_public_key(),
+                key_shares[0].spend_public_key()
+            );
+            assert_eq!(
+                key_share.verification_shares(),
+                key_shares[0].verification_shares()
+            );
+            assert_eq!(
+                RistrettoPublic::from(key_share.secret_share()),
+                *key_share
+                    .verification_share(key_share.participant())
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    // Any `threshold` shares should interpolate to the spend private key.
+    fn test_any_threshold_subset_recovers_spend_key() {
+        let mut rng: StdRng = SeedableRng::from_seed([8u8; 32]);
+        let key_shares = keygen_in_process(3, 5, &mut rng).unwrap();
+        for signers in &[[1u32, 2, 3], [1, 3, 5], [2, 4, 5], [5, 4, 1]] {
+            let b: Scalar = signers
+                .iter()
+                .map(|&j| {
+                    let lambda = lagrange_coefficient(j, signers).unwrap();
+                    let s_j: &Scalar = key_shares[j as usize - 1].secret_share().as_ref();
+                    lambda * s_j
+                })
+                .sum();
+            assert_eq!(
+                RistrettoPublic::from(b * G),
+                *key_shares[0].spend_public_key()
+            );
+        }
+    }
+
+    #[test]
+    // A share that does not match its dealer's commitments should be rejected.
+    fn test_from_keygen_rejects_invalid_share() {
+        let mut rng: StdRng = SeedableRng::from_seed([9u8; 32]);
+        let dealers: Vec<KeygenDealer> = (1..=3)
+            .map(|i| KeygenDealer::new(i, 2, 3, &mut rng).unwrap())
+            .collect();
+        let commitments: Vec<KeygenCommitment> = dealers.iter().map(|d| d.commitment()).collect();
+        let mut shares: Vec<KeygenSecretShare> =
+            dealers.iter().map(|d| d.share_for(1).unwrap()).collect();
+        shares[2].value = CurveScalar::from(Scalar::random(&mut rng));
+
+        match MultisigKeyShare::from_keygen(1, 2, &commitments, &shares) {
+            Err(Error::InvalidKeyShare(3)) => {} // Expected.
+            other => panic!("Unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    // Multisig subaddresses should match those of an AccountKey with the same keys.
+    fn test_subaddress_matches_account_key() {
+        use crate::account_keys::AccountKey;
+        use mc_util_from_random::FromRandom;
+
+        let mut rng: StdRng = SeedableRng::from_seed([10u8; 32]);
+        let key_shares = keygen_in_process(2, 2, &mut rng).unwrap();
+        let view_private_key = RistrettoPrivate::from_random(&mut rng);
+        let account = MultisigAccount::new(&view_private_key, &key_shares[0]);
+
+        let signers = [1u32, 2];
+        let b: Scalar = signers
+            .iter()
+            .map(|&j| {
+                let lambda = lagrange_coefficient(j, &signers).unwrap();
+                let s_j: &Scalar = key_shares[j as usize - 1].secret_share().as_ref();
+                lambda * s_j
+            })
+            .sum();
+        let account_key = AccountKey::new(&RistrettoPrivate::from(b), &view_private_key);
+
+        for index in &[0u64, 1, 17] {
+            assert_eq!(account.subaddress(*index), account_key.subaddress(*index));
+        }
+    }
+}
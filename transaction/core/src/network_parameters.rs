@@ -0,0 +1,282 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Parameters that every node in a MobileCoin network must agree on.
+//!
+//! The defaults are the values in `constants`. A network that uses different parameters, or a
+//! block version schedule, commits to them in its origin block (see
+//! `Block::new_origin_block_with_schedule`), so that nodes configured with mismatching values can
+//! detect it before participating.
+
+use crate::{
+    blake2b_256::Blake2b256,
+    constants::{
+        BASE_FEE, MAX_INPUTS, MAX_OUTPUTS, MAX_TOMBSTONE_BLOCKS, MAX_TRANSACTIONS_PER_BLOCK,
+        RING_SIZE,
+    },
+    ring_signature::BP_GENERATORS,
+    tx::{TxOutMembershipElement, TxOutMembershipHash},
+    Block, BlockVersionSchedule,
+};
+use core::convert::TryInto;
+use failure::Fail;
+use mc_common::Hash;
+use mc_crypto_digestible::Digestible;
+use serde::{Deserialize, Serialize};
+
+/// Limits enforced when validating transactions and forming blocks.
+#[derive(Clone, Copy, Debug, Deserialize, Digestible, Eq, Hash, PartialEq, Serialize)]
+#[serde(default)]
+pub struct NetworkParameters {
+    /// Each input ring must contain this many elements.
+    pub ring_size: usize,
+
+    /// Each transaction must contain no more than this many inputs (rings).
+    pub max_inputs: u64,
+
+    /// Each transaction must contain no more than this many outputs.
+    pub max_outputs: u64,
+
+    /// Maximum number of blocks in the future a transaction's tombstone block can be set to.
+    pub max_tombstone_blocks: u64,
+
+    /// Minimum allowed fee, denominated in picoMOB.
+    pub base_fee: u64,
+
+    /// Maximum number of transactions that may be included in a Block.
+    pub max_transactions_per_block: usize,
+}
+
+impl Default for NetworkParameters {
+    fn default() -> Self {
+        Self {
+            ring_size: RING_SIZE,
+            max_inputs: MAX_INPUTS,
+            max_outputs: MAX_OUTPUTS,
+            max_tombstone_blocks: MAX_TOMBSTONE_BLOCKS,
+            base_fee: BASE_FEE,
+            max_transactions_per_block: MAX_TRANSACTIONS_PER_BLOCK,
+        }
+    }
+}
+
+/// Reasons why a set of network parameters is unusable.
+#[derive(Clone, Debug, Eq, Fail, PartialEq)]
+pub enum NetworkParametersError {
+    /// A limit that must be positive is zero.
+    #[fail(display = "{} must be greater than zero", _0)]
+    Zero(&'static str),
+
+    /// A transaction with the maximum number of inputs and outputs could not be range-proved.
+    #[fail(
+        display = "max_inputs + max_outputs must not exceed the range proof capacity of {}",
+        _0
+    )]
+    ExceedsRangeProofCapacity(usize),
+}
+
+impl NetworkParameters {
+    /// Checks that transactions can be built and validated under these parameters.
+    pub fn check(&self) -> Result<(), NetworkParametersError> {
+        if self.ring_size == 0 {
+            return Err(NetworkParametersError::Zero("ring_size"));
+        }
+        if self.max_inputs == 0 {
+            return Err(NetworkParametersError::Zero("max_inputs"));
+        }
+        if self.max_outputs == 0 {
+            return Err(NetworkParametersError::Zero("max_outputs"));
+        }
+        if self.max_tombstone_blocks == 0 {
+            return Err(NetworkParametersError::Zero("max_tombstone_blocks"));
+        }
+        if self.max_transactions_per_block == 0 {
+            return Err(NetworkParametersError::Zero("max_transactions_per_block"));
+        }
+
+        // The range proof covers one pseudo-output per input, and every output.
+        let capacity = BP_GENERATORS.party_capacity;
+        if self.max_inputs + self.max_outputs > capacity as u64 {
+            return Err(NetworkParametersError::ExceedsRangeProofCapacity(capacity));
+        }
+
+        Ok(())
+    }
+
+    /// The Blake2B256 digest of `self`.
+    pub fn hash(&self) -> Hash {
+        self.digest_with::<Blake2b256>().try_into().unwrap()
+    }
+
+    /// The hash held by the root element of an origin block that commits to these parameters and
+    /// `block_version_schedule`, or None if both are the defaults.
+    ///
+    /// With the default schedule, this is the hash of the parameters alone, so that origin blocks
+    /// created before schedules were committed remain valid.
+    ///
+    /// # Arguments
+    /// * `block_version_schedule` - The network's block version schedule.
+    pub fn origin_commitment(
+        &self,
+        block_version_schedule: &BlockVersionSchedule,
+    ) -> Option<TxOutMembershipHash> {
+        if *block_version_schedule == BlockVersionSchedule::default() {
+            if *self == Self::default() {
+                return None;
+            }
+            return Some(TxOutMembershipHash::from(self.hash()));
+        }
+
+        let commitment = NetworkCommitment {
+            network_parameters: *self,
+            block_version_schedule: block_version_schedule.clone(),
+        };
+        let hash: Hash = commitment.digest_with::<Blake2b256>().try_into().unwrap();
+        Some(TxOutMembershipHash::from(hash))
+    }
+
+    /// Returns true if `origin_block` commits to these parameters and `block_version_schedule`.
+    ///
+    /// Origin blocks created before parameters were committed have a default root element, and
+    /// are treated as committing to the default parameters and schedule.
+    ///
+    /// # Arguments
+    /// * `block_version_schedule` - The network's block version schedule.
+    /// * `origin_block` - The first block in the ledger.
+    pub fn is_committed_by(
+        &self,
+        block_version_schedule: &BlockVersionSchedule,
+        origin_block: &Block,
+    ) -> bool {
+        match self.origin_commitment(block_version_schedule) {
+            None => origin_block.root_element == TxOutMembershipElement::default(),
+            Some(hash) => origin_block.root_element.hash == hash,
+        }
+    }
+}
+
+/// Everything an origin block commits to, when the block version schedule is not the default.
+#[derive(Digestible)]
+struct NetworkCommitment {
+    network_parameters: NetworkParameters,
+    block_version_schedule: BlockVersionSchedule,
+}
+
+#[cfg(test)]
+mod network_parameters_tests {
+    use super::*;
+    use crate::{BlockVersionActivation, BLOCK_VERSION};
+
+    #[test]
+    /// The default parameters should be usable.
+    fn test_default_parameters_are_valid() {
+        assert_eq!(NetworkParameters::default().check(), Ok(()));
+    }
+
+    #[test]
+    /// `check` should reject zero limits and limits the range proof cannot support.
+    fn test_check_rejects_invalid_parameters() {
+        let parameters = NetworkParameters {
+            ring_size: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            parameters.check(),
+            Err(NetworkParametersError::Zero("ring_size"))
+        );
+
+        let parameters = NetworkParameters {
+            max_inputs: 48,
+            max_outputs: 48,
+            ..Default::default()
+        };
+        assert_eq!(
+            parameters.check(),
+            Err(NetworkParametersError::ExceedsRangeProofCapacity(
+                BP_GENERATORS.party_capacity
+            ))
+        );
+    }
+
+    #[test]
+    /// An origin block should commit to exactly the parameters it was created with.
+    fn test_origin_block_commits_to_parameters() {
+        let parameters = NetworkParameters {
+            ring_size: 5,
+            base_fee: 1,
+            ..Default::default()
+        };
+
+        let origin_block = Block::new_origin_block_with_parameters(&[], &parameters);
+        assert!(origin_block.is_block_id_valid());
+        assert!(parameters.is_committed_by(&Default::default(), &origin_block));
+        assert!(!NetworkParameters::default().is_committed_by(&Default::default(), &origin_block));
+
+        // The default parameters leave the origin block as it was before parameters existed.
+        let default_origin_block = Block::new_origin_block(&[]);
+        assert_eq!(
+            default_origin_block.root_element,
+            TxOutMembershipElement::default()
+        );
+        assert!(NetworkParameters::default()
+            .is_committed_by(&Default::default(), &default_origin_block));
+        assert!(!parameters.is_committed_by(&Default::default(), &default_origin_block));
+        assert_ne!(origin_block.id, default_origin_block.id);
+    }
+
+    #[test]
+    /// Origin blocks without committed parameters imply the default parameters.
+    fn test_legacy_origin_block_implies_default_parameters() {
+        let legacy_origin_block = Block::new(
+            BLOCK_VERSION,
+            &Default::default(),
+            0,
+            0,
+            &TxOutMembershipElement::default(),
+            &Default::default(),
+        );
+
+        assert!(
+            NetworkParameters::default().is_committed_by(&Default::default(), &legacy_origin_block)
+        );
+        let parameters = NetworkParameters {
+            max_tombstone_blocks: 10,
+            ..Default::default()
+        };
+        assert!(!parameters.is_committed_by(&Default::default(), &legacy_origin_block));
+    }
+
+    #[test]
+    /// An origin block should commit to exactly the block version schedule it was created with.
+    fn test_origin_block_commits_to_block_version_schedule() {
+        let schedule = BlockVersionSchedule {
+            activations: vec![BlockVersionActivation {
+                version: 1,
+                block_index: 100,
+            }],
+        };
+        let other_schedule = BlockVersionSchedule {
+            activations: vec![BlockVersionActivation {
+                version: 1,
+                block_index: 101,
+            }],
+        };
+        let parameters = NetworkParameters {
+            ring_size: 5,
+            ..Default::default()
+        };
+
+        for parameters in &[NetworkParameters::default(), parameters] {
+            let origin_block = Block::new_origin_block_with_schedule(&[], parameters, &schedule);
+            assert!(origin_block.is_block_id_valid());
+            assert!(parameters.is_committed_by(&schedule, &origin_block));
+            assert!(!parameters.is_committed_by(&Default::default(), &origin_block));
+            assert!(!parameters.is_committed_by(&other_schedule, &origin_block));
+        }
+
+        // Without a schedule, an origin block commits to the parameters alone.
+        assert_eq!(
+            Block::new_origin_block_with_schedule(&[], &parameters, &Default::default()),
+            Block::new_origin_block_with_parameters(&[], &parameters)
+        );
+    }
+}
@@ -55,6 +55,37 @@ pub enum Error {
 
     #[fail(display = "Serialization failed")]
     SerializationFailed,
+
+    /// Multisig threshold must be in [1, num_participants].
+    #[fail(display = "Invalid multisig threshold {} of {}", _0, _1)]
+    InvalidThreshold(u32, u32),
+
+    #[fail(display = "Invalid multisig participant: {}", _0)]
+    InvalidParticipant(u32),
+
+    #[fail(display = "Duplicate multisig participant: {}", _0)]
+    DuplicateParticipant(u32),
+
+    #[fail(display = "Missing multisig participant: {}", _0)]
+    MissingParticipant(u32),
+
+    /// Fewer participants than the multisig threshold are signing.
+    #[fail(display = "Insufficient multisig signers: {} of {}", _0, _1)]
+    InsufficientSigners(u32, u32),
+
+    /// A key generation share did not match its dealer's commitments.
+    #[fail(display = "Invalid key generation share from participant {}", _0)]
+    InvalidKeyShare(u32),
+
+    #[fail(display = "Invalid partial key image from participant {}", _0)]
+    InvalidPartialKeyImage(u32),
+
+    /// Revealed nonces did not match the participant's earlier commitment.
+    #[fail(display = "Invalid nonce commitment from participant {}", _0)]
+    InvalidNonceCommitment(u32),
+
+    #[fail(display = "Invalid partial response from participant {}", _0)]
+    InvalidPartialResponse(u32),
 }
 
 impl From<mc_util_serial::LengthMismatch32> for Error {
@@ -21,7 +21,7 @@ use crate::{
 };
 
 // This needs to be the same "Hp" function used by the onetime keys.
-fn hash_to_point(ristretto_public: &RistrettoPublic) -> RistrettoPoint {
+pub(crate) fn hash_to_point(ristretto_public: &RistrettoPublic) -> RistrettoPoint {
     let mut hasher = Blake2b::new();
     hasher.input(&ristretto_public.to_bytes());
     RistrettoPoint::from_hash(hasher)
@@ -119,9 +119,6 @@ impl RingMLSAG {
 
         let key_image = KeyImage::from(onetime_private_key);
 
-        // The uncompressed key_image.
-        let I: RistrettoPoint = key_image.point.decompress().ok_or(Error::InvalidKeyImage)?;
-
         // Uncompressed output commitment.
         // This ensures that each address and commitment encodes a valid Ristretto point.
         let output_commitment: Commitment = Commitment::new(value, *output_blinding);
@@ -129,67 +126,23 @@ impl RingMLSAG {
         // Ring must decompress.
         let decompressed_ring = decompress_ring(ring)?;
 
-        // Challenges `c_0, ... c_{ring_size - 1}`.
-        let mut c: Vec<Scalar> = vec![Scalar::zero(); ring_size];
-
-        // Responses `r_{0,0}, r_{0,1}, ... , r_{ring_size-1,0}, r_{ring_size-1,1}`.
-        let mut r: Vec<Scalar> = vec![Scalar::zero(); 2 * ring_size];
-        for i in 0..ring_size {
-            if i == real_index {
-                continue;
-            }
-            r[2 * i] = Scalar::random(rng);
-            r[2 * i + 1] = Scalar::random(rng);
-        }
-
         let alpha_0 = Scalar::random(rng);
         let alpha_1 = Scalar::random(rng);
 
-        for n in 0..ring_size {
-            // Iterate around the ring, starting at real_index.
-            let i = (real_index + n) % ring_size;
-            let (P_i, input_commitment) = &decompressed_ring[i];
-
-            let (L0, R0, L1) = if i == real_index {
-                // c_{i+1} = Hn( m | key_image | alpha_0 * G | alpha_0 * Hp(P_i) | alpha_1 * H )
-                //         = Hn( m | key_image |      L0     |         R0        |      L1     )
-                //
-                // where P_i is the i^th onetime public key.
-                // There is no R1 term because no key image is needed for the commitment to zero.
-
-                let L0 = alpha_0 * G;
-                let R0 = alpha_0 * hash_to_point(&P_i);
-                let L1 = alpha_1 * H;
-                (L0, R0, L1)
-            } else {
-                // c_{i+1} = Hn( m | key_image | r_{i,0} * G + c_i * P_i | r_{i,0} * Hp(P_i) + c_i * I | r_{i,1} * G + c_i * Z_i )
-                //         = Hn( m | key_image |           L0            |               R0            |             L1          )
-                //
-                // where:
-                // * P_i is the i^th onetime public key.
-                // * I is the key image of the real input's private key,
-                // * Z_i is the i^th "commitment to zero" = output_commitment - input_commitment.
-                //
-                // There is no R1 term because no key image is needed for the commitment to zero.
-
-                let L0 = r[2 * i] * G + c[i] * P_i.as_ref();
-                let R0 = r[2 * i] * hash_to_point(&P_i) + c[i] * I;
-                let L1 =
-                    r[2 * i + 1] * H + c[i] * (output_commitment.point - input_commitment.point);
-                (L0, R0, L1)
-            };
+        // The real input's P_i is used to compute R0.
+        let (P_real, _) = &decompressed_ring[real_index];
 
-            c[(i + 1) % ring_size] = {
-                let mut hasher = Blake2b::new();
-                hasher.input(&RING_MLSAG_CHALLENGE_DOMAIN_TAG);
-                hasher.input(message);
-                hasher.input(&key_image);
-                hasher.input(L0.compress().as_bytes());
-                hasher.input(R0.compress().as_bytes());
-                hasher.input(L1.compress().as_bytes());
-                Scalar::from_hash::<Blake2b>(hasher)
-            };
-        }
+        let (c, mut r) = compute_challenges(
+            message,
+            &decompressed_ring,
+            real_index,
+            &key_image,
+            &output_commitment,
+            &(alpha_0 * G),
+            &(alpha_0 * hash_to_point(P_real)),
+            &(alpha_1 * H),
+            rng,
+        )?;
 
         // "Close the loop" by computing responses for the real index.
 
@@ -313,7 +266,7 @@ impl RingMLSAG {
     }
 }
 
-fn decompress_ring(
+pub(crate) fn decompress_ring(
     ring: &[(CompressedRistrettoPublic, CompressedCommitment)],
 ) -> Result<Vec<(RistrettoPublic, Commitment)>, Error> {
     // Ring must decompress.
@@ -326,6 +279,145 @@ fn decompress_ring(
     Ok(decompressed_ring)
 }
 
+/// Computes the challenges `c_0, ..., c_{ring_size - 1}` for a ring whose real-input terms are known.
+///
+/// Random responses are chosen for every ring member except the real input, whose responses are
+/// left as zero for the caller to "close the loop".
+///
+/// # Arguments
+/// * `message` - Message to be signed.
+/// * `decompressed_ring` - A ring of input onetime addresses and amount commitments.
+/// * `real_index` - The index in the ring of the real input.
+/// * `key_image` - Key image of the real input.
+/// * `output_commitment` - Output amount commitment.
+/// * `real_L0` - `alpha_0 * G` for the real input.
+/// * `real_R0` - `alpha_0 * Hp(P)` for the real input.
+/// * `real_L1` - `alpha_1 * H` for the real input.
+/// * `rng` - Randomness.
+///
+/// Returns `(c, r)`.
+pub(crate) fn compute_challenges<CSPRNG: RngCore + CryptoRng>(
+    message: &[u8],
+    decompressed_ring: &[(RistrettoPublic, Commitment)],
+    real_index: usize,
+    key_image: &KeyImage,
+    output_commitment: &Commitment,
+    real_L0: &RistrettoPoint,
+    real_R0: &RistrettoPoint,
+    real_L1: &RistrettoPoint,
+    rng: &mut CSPRNG,
+) -> Result<(Vec<Scalar>, Vec<Scalar>), Error> {
+    let ring_size = decompressed_ring.len();
+
+    // Responses `r_{0,0}, r_{0,1}, ... , r_{ring_size-1,0}, r_{ring_size-1,1}`.
+    let mut r: Vec<Scalar> = vec![Scalar::zero(); 2 * ring_size];
+    for i in 0..ring_size {
+        if i == real_index {
+            continue;
+        }
+        r[2 * i] = Scalar::random(rng);
+        r[2 * i + 1] = Scalar::random(rng);
+    }
+
+    let c = compute_challenges_with_responses(
+        message,
+        decompressed_ring,
+        real_index,
+        key_image,
+        output_commitment,
+        real_L0,
+        real_R0,
+        real_L1,
+        &r,
+    )?;
+    Ok((c, r))
+}
+
+/// Computes the challenges `c_0, ..., c_{ring_size - 1}` from the real input's terms and the
+/// responses of every other ring member.
+///
+/// # Arguments
+/// * `message` - Message to be signed.
+/// * `decompressed_ring` - A ring of input onetime addresses and amount commitments.
+/// * `real_index` - The index in the ring of the real input.
+/// * `key_image` - Key image of the real input.
+/// * `output_commitment` - Output amount commitment.
+/// * `real_L0` - `alpha_0 * G` for the real input.
+/// * `real_R0` - `alpha_0 * Hp(P)` for the real input.
+/// * `real_L1` - `alpha_1 * H` for the real input.
+/// * `r` - Responses `r_{0,0}, r_{0,1}, ... , r_{ring_size-1,1}`. Those of the real input are ignored.
+pub(crate) fn compute_challenges_with_responses(
+    message: &[u8],
+    decompressed_ring: &[(RistrettoPublic, Commitment)],
+    real_index: usize,
+    key_image: &KeyImage,
+    output_commitment: &Commitment,
+    real_L0: &RistrettoPoint,
+    real_R0: &RistrettoPoint,
+    real_L1: &RistrettoPoint,
+    r: &[Scalar],
+) -> Result<Vec<Scalar>, Error> {
+    let ring_size = decompressed_ring.len();
+    if real_index >= ring_size {
+        return Err(Error::IndexOutOfBounds);
+    }
+    if r.len() != 2 * ring_size {
+        return Err(Error::LengthMismatch(2 * ring_size, r.len()));
+    }
+
+    let G = GENERATORS.B;
+    let H = GENERATORS.B_blinding;
+
+    // The uncompressed key_image.
+    let I: RistrettoPoint = key_image.point.decompress().ok_or(Error::InvalidKeyImage)?;
+
+    // Challenges `c_0, ... c_{ring_size - 1}`.
+    let mut c: Vec<Scalar> = vec![Scalar::zero(); ring_size];
+
+    for n in 0..ring_size {
+        // Iterate around the ring, starting at real_index.
+        let i = (real_index + n) % ring_size;
+        let (P_i, input_commitment) = &decompressed_ring[i];
+
+        let (L0, R0, L1) = if i == real_index {
+            // c_{i+1} = Hn( m | key_image | alpha_0 * G | alpha_0 * Hp(P_i) | alpha_1 * H )
+            //         = Hn( m | key_image |      L0     |         R0        |      L1     )
+            //
+            // where P_i is the i^th onetime public key.
+            // There is no R1 term because no key image is needed for the commitment to zero.
+            (*real_L0, *real_R0, *real_L1)
+        } else {
+            // c_{i+1} = Hn( m | key_image | r_{i,0} * G + c_i * P_i | r_{i,0} * Hp(P_i) + c_i * I | r_{i,1} * G + c_i * Z_i )
+            //         = Hn( m | key_image |           L0            |               R0            |             L1          )
+            //
+            // where:
+            // * P_i is the i^th onetime public key.
+            // * I is the key image of the real input's private key,
+            // * Z_i is the i^th "commitment to zero" = output_commitment - input_commitment.
+            //
+            // There is no R1 term because no key image is needed for the commitment to zero.
+
+            let L0 = r[2 * i] * G + c[i] * P_i.as_ref();
+            let R0 = r[2 * i] * hash_to_point(&P_i) + c[i] * I;
+            let L1 = r[2 * i + 1] * H + c[i] * (output_commitment.point - input_commitment.point);
+            (L0, R0, L1)
+        };
+
+        c[(i + 1) % ring_size] = {
+            let mut hasher = Blake2b::new();
+            hasher.input(&RING_MLSAG_CHALLENGE_DOMAIN_TAG);
+            hasher.input(message);
+            hasher.input(key_image);
+            hasher.input(L0.compress().as_bytes());
+            hasher.input(R0.compress().as_bytes());
+            hasher.input(L1.compress().as_bytes());
+            Scalar::from_hash::<Blake2b>(hasher)
+        };
+    }
+
+    Ok(c)
+}
+
 #[cfg(test)]
 mod mlsag_tests {
     use crate::{
@@ -10,12 +10,14 @@ pub use curve_scalar::*;
 pub use error::Error;
 pub use key_image::*;
 pub use mlsag::*;
+pub use multisig::*;
 pub use rct_bulletproofs::*;
 
 mod curve_scalar;
 mod error;
 mod key_image;
 mod mlsag;
+mod multisig;
 mod rct_bulletproofs;
 
 lazy_static! {
@@ -0,0 +1,894 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Threshold (M-of-N) signing of a RingMLSAG.
+//!
+//! The onetime private key of an output owned by a multisig account is `x = t + b`, where the
+//! offset `t = Hs(a * R) + Hs(a || i)` is known to anyone holding the view private key `a`, and
+//! the spend private key `b` is Shamir-shared among the participants (see `multisig_keys`).
+//! A coordinator holding `a` and a set of M signers jointly produce an ordinary `RingMLSAG`:
+//!
+//! 1. Each signer publishes a `PartialKeyImage` `s_j * Hp(P)`, with a proof that it was derived
+//!    from the signer's verification share, and a `NonceCommitment` to fresh nonces.
+//! 2. Once every commitment has been collected, each signer reveals their `NoncePoints`
+//!    `alpha_j * G` and `alpha_j * Hp(P)`.
+//! 3. The coordinator computes the ring's challenge at the real index and sends it to each signer
+//!    with a `ChallengeTranscript`. Each signer rebuilds the challenge from the transcript and the
+//!    message, and only then returns a `PartialResponse` `r_j = alpha_j - c * lambda_j * s_j`,
+//!    which the coordinator checks and combines into the real input's response.
+//!
+//! The resulting signature is indistinguishable from one produced by `RingMLSAG::sign`.
+
+#![allow(non_snake_case)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use blake2::{Blake2b, Digest};
+use core::convert::TryFrom;
+use curve25519_dalek::{ristretto::RistrettoPoint, traits::Identity};
+use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPrivate, RistrettoPublic};
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    blake2b_256::Blake2b256,
+    commitment::Commitment,
+    compressed_commitment::CompressedCommitment,
+    domain_separators::{
+        MULTISIG_NONCE_COMMITMENT_DOMAIN_TAG, MULTISIG_PARTIAL_KEY_IMAGE_DOMAIN_TAG,
+    },
+    multisig_keys::{lagrange_coefficient, ParticipantIndex},
+    ring_signature::{
+        mlsag::{
+            compute_challenges, compute_challenges_with_responses, decompress_ring, hash_to_point,
+        },
+        CurveScalar, Error, KeyImage, RingMLSAG, Scalar, GENERATORS,
+    },
+};
+
+/// Proof that `log_G(S) == log_Hp(P)(K)`, for a verification share `S` and partial key image `K`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DleqProof {
+    /// Challenge `c`.
+    pub challenge: CurveScalar,
+
+    /// Response `z = k - c * s`.
+    pub response: CurveScalar,
+}
+
+/// A signer's share `K_j = s_j * Hp(P)` of the key image of an input with onetime public key `P`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PartialKeyImage {
+    /// The signer.
+    pub participant: ParticipantIndex,
+
+    /// `s_j * Hp(P)`.
+    pub point: CompressedRistrettoPublic,
+
+    /// Proof that `point` was computed with the signer's secret share.
+    pub proof: DleqProof,
+}
+
+impl PartialKeyImage {
+    /// Computes a partial key image and its proof.
+    ///
+    /// # Arguments
+    /// * `participant` - The signer.
+    /// * `secret_share` - The signer's secret share `s_j` of the spend private key.
+    /// * `onetime_public_key` - The onetime public key `P` of the output being spent.
+    /// * `rng` - Randomness.
+    pub fn new<CSPRNG: RngCore + CryptoRng>(
+        participant: ParticipantIndex,
+        secret_share: &RistrettoPrivate,
+        onetime_public_key: &RistrettoPublic,
+        rng: &mut CSPRNG,
+    ) -> Self {
+        let G = GENERATORS.B;
+        let Hp = hash_to_point(onetime_public_key);
+        let s: &Scalar = secret_share.as_ref();
+
+        let S = s * G;
+        let K = s * Hp;
+
+        let k = Scalar::random(rng);
+        let challenge = dleq_challenge(participant, &S, &K, &(k * G), &(k * Hp));
+        let response = k - challenge * s;
+
+        Self {
+            participant,
+            point: CompressedRistrettoPublic::from(K),
+            proof: DleqProof {
+                challenge: CurveScalar::from(challenge),
+                response: CurveScalar::from(response),
+            },
+        }
+    }
+
+    /// Checks that this partial key image was computed with the signer's secret share.
+    ///
+    /// # Arguments
+    /// * `verification_share` - The signer's verification share `S_j = s_j * G`.
+    /// * `onetime_public_key` - The onetime public key `P` of the output being spent.
+    pub fn verify(
+        &self,
+        verification_share: &RistrettoPublic,
+        onetime_public_key: &RistrettoPublic,
+    ) -> Result<(), Error> {
+        let invalid = Error::InvalidPartialKeyImage(self.participant);
+        let G = GENERATORS.B;
+        let Hp = hash_to_point(onetime_public_key);
+        let K = *RistrettoPublic::try_from(&self.point)
+            .map_err(|_| invalid)?
+            .as_ref();
+        let S = verification_share.as_ref();
+
+        let c = self.proof.challenge.scalar;
+        let z = self.proof.response.scalar;
+        if !c.is_canonical() || !z.is_canonical() {
+            return Err(invalid);
+        }
+
+        let A = z * G + c * S;
+        let B = z * Hp + c * K;
+        if dleq_challenge(self.participant, S, &K, &A, &B) == c {
+            Ok(())
+        } else {
+            Err(invalid)
+        }
+    }
+}
+
+/// Combines partial key images into the key image `I = t * Hp(P) + sum_j lambda_j * K_j`.
+///
+/// Each partial key image should already have been checked with `PartialKeyImage::verify`.
+///
+/// # Arguments
+/// * `partial_key_images` - One partial key image from each signer.
+/// * `onetime_private_key_offset` - The offset `t` of the onetime private key `x = t + b`.
+/// * `onetime_public_key` - The onetime public key `P` of the output being spent.
+pub fn combine_partial_key_images(
+    partial_key_images: &[PartialKeyImage],
+    onetime_private_key_offset: &Scalar,
+    onetime_public_key: &RistrettoPublic,
+) -> Result<KeyImage, Error> {
+    let signers: Vec<ParticipantIndex> = partial_key_images
+        .iter()
+        .map(|partial| partial.participant)
+        .collect();
+
+    let mut I: RistrettoPoint = onetime_private_key_offset * hash_to_point(onetime_public_key);
+    for partial in partial_key_images {
+        let lambda = lagrange_coefficient(partial.participant, &signers)?;
+        let K = RistrettoPublic::try_from(&partial.point)
+            .map_err(|_| Error::InvalidPartialKeyImage(partial.participant))?;
+        I += lambda * K.as_ref();
+    }
+
+    Ok(KeyImage {
+        point: I.compress(),
+    })
+}
+
+/// A signer's nonce points `alpha_j * G` and `alpha_j * Hp(P)` for one input.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NoncePoints {
+    /// The signer.
+    pub participant: ParticipantIndex,
+
+    /// `alpha_j * G`.
+    pub L: CompressedRistrettoPublic,
+
+    /// `alpha_j * Hp(P)`.
+    pub R: CompressedRistrettoPublic,
+}
+
+impl NoncePoints {
+    /// The commitment a signer publishes before revealing these points.
+    pub fn commitment(&self) -> NonceCommitment {
+        let mut hasher = Blake2b256::new();
+        hasher.input(&MULTISIG_NONCE_COMMITMENT_DOMAIN_TAG);
+        hasher.input(&self.participant.to_le_bytes());
+        hasher.input(self.L.as_bytes());
+        hasher.input(self.R.as_bytes());
+
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&hasher.result());
+        NonceCommitment {
+            participant: self.participant,
+            hash,
+        }
+    }
+}
+
+/// A binding commitment to a signer's `NoncePoints`.
+///
+/// Every signer's commitment must be collected before any nonces are revealed, so that no signer
+/// can choose their nonces as a function of the others'.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NonceCommitment {
+    /// The signer.
+    pub participant: ParticipantIndex,
+
+    /// Hash of the signer's `NoncePoints`.
+    pub hash: [u8; 32],
+}
+
+/// A signer's secret nonce for one input.
+///
+/// `respond` consumes the nonce, so that it can never be used to answer two different challenges
+/// (which would reveal the signer's secret share).
+pub struct MultisigNonce {
+    alpha: Scalar,
+    points: NoncePoints,
+}
+
+impl MultisigNonce {
+    /// Samples a fresh nonce.
+    ///
+    /// # Arguments
+    /// * `participant` - The signer.
+    /// * `onetime_public_key` - The onetime public key `P` of the output being spent.
+    /// * `rng` - Randomness.
+    pub fn new<CSPRNG: RngCore + CryptoRng>(
+        participant: ParticipantIndex,
+        onetime_public_key: &RistrettoPublic,
+        rng: &mut CSPRNG,
+    ) -> Self {
+        let alpha = Scalar::random(rng);
+        let points = NoncePoints {
+            participant,
+            L: CompressedRistrettoPublic::from(alpha * GENERATORS.B),
+            R: CompressedRistrettoPublic::from(alpha * hash_to_point(onetime_public_key)),
+        };
+        Self { alpha, points }
+    }
+
+    /// The nonce points, to be revealed once every signer has committed.
+    pub fn points(&self) -> &NoncePoints {
+        &self.points
+    }
+
+    /// The commitment to publish in the first round.
+    pub fn commitment(&self) -> NonceCommitment {
+        self.points.commitment()
+    }
+
+    /// Answers the coordinator's challenge with `r_j = alpha_j - c * lambda_j * s_j`.
+    ///
+    /// # Arguments
+    /// * `challenge` - The ring's challenge `c` at the real index.
+    /// * `secret_share` - The signer's secret share `s_j` of the spend private key.
+    /// * `signers` - Every participant taking part in this signature.
+    pub fn respond(
+        self,
+        challenge: &CurveScalar,
+        secret_share: &RistrettoPrivate,
+        signers: &[ParticipantIndex],
+    ) -> Result<PartialResponse, Error> {
+        let lambda = lagrange_coefficient(self.points.participant, signers)?;
+        let s: &Scalar = secret_share.as_ref();
+        let response = self.alpha - challenge.scalar * lambda * s;
+        Ok(PartialResponse {
+            participant: self.points.participant,
+            response: CurveScalar::from(response),
+        })
+    }
+}
+
+/// A signer's share `r_j` of the real input's response.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PartialResponse {
+    /// The signer.
+    pub participant: ParticipantIndex,
+
+    /// `alpha_j - c * lambda_j * s_j`.
+    pub response: CurveScalar,
+}
+
+/// The public values from which a signer can rebuild a ring's challenge at the real index.
+///
+/// A signer must never answer a challenge it has not rebuilt itself: otherwise a coordinator could
+/// obtain a response to a challenge for a message the signer never agreed to sign.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChallengeTranscript {
+    /// The index in the ring of the real input.
+    pub real_index: u64,
+
+    /// Key image of the real input.
+    pub key_image: KeyImage,
+
+    /// `alpha_1 * H`, the real input's commitment-to-zero term.
+    pub L1: CompressedRistrettoPublic,
+
+    /// Responses of every other ring member. Those of the real input are zero.
+    pub responses: Vec<CurveScalar>,
+
+    /// Every signer's revealed nonce points.
+    pub nonce_points: Vec<NoncePoints>,
+}
+
+impl ChallengeTranscript {
+    /// Rebuilds the challenge at the real index.
+    ///
+    /// # Arguments
+    /// * `message` - Message to be signed.
+    /// * `ring` - A ring of input onetime addresses and amount commitments.
+    /// * `output_commitment` - Output amount commitment.
+    pub fn challenge(
+        &self,
+        message: &[u8],
+        ring: &[(CompressedRistrettoPublic, CompressedCommitment)],
+        output_commitment: &CompressedCommitment,
+    ) -> Result<CurveScalar, Error> {
+        let real_index = self.real_index as usize;
+        let decompressed_ring = decompress_ring(ring)?;
+        let output_commitment = Commitment::try_from(output_commitment)?;
+
+        let decompress = |point: &CompressedRistrettoPublic| {
+            RistrettoPublic::try_from(point)
+                .map(|p| *p.as_ref())
+                .map_err(|_| Error::InvalidCurvePoint)
+        };
+        let mut real_L0 = RistrettoPoint::identity();
+        let mut real_R0 = RistrettoPoint::identity();
+        for points in &self.nonce_points {
+            real_L0 += decompress(&points.L)?;
+            real_R0 += decompress(&points.R)?;
+        }
+        let r: Vec<Scalar> = self.responses.iter().map(|r| r.scalar).collect();
+
+        let c = compute_challenges_with_responses(
+            message,
+            &decompressed_ring,
+            real_index,
+            &self.key_image,
+            &output_commitment,
+            &real_L0,
+            &real_R0,
+            &decompress(&self.L1)?,
+            &r,
+        )?;
+        Ok(CurveScalar::from(c[real_index]))
+    }
+}
+
+/// A signer's public values for one ring, decompressed.
+struct SignerPoints {
+    participant: ParticipantIndex,
+
+    /// Verification share `S_j = s_j * G`.
+    S: RistrettoPoint,
+
+    /// Partial key image `K_j = s_j * Hp(P)`.
+    K: RistrettoPoint,
+
+    /// `alpha_j * G`.
+    L: RistrettoPoint,
+
+    /// `alpha_j * Hp(P)`.
+    R: RistrettoPoint,
+}
+
+/// The coordinator's state while jointly signing one ring.
+pub struct MultisigRingSigner {
+    real_index: usize,
+    key_image: KeyImage,
+    onetime_private_key_offset: Scalar,
+    onetime_public_key: RistrettoPublic,
+
+    /// Each signer's checked public values.
+    signers: Vec<SignerPoints>,
+
+    /// `alpha_1 * H`, the real input's commitment-to-zero term.
+    real_L1: RistrettoPoint,
+
+    /// Challenges `c_0, ... c_{ring_size - 1}`.
+    c: Vec<Scalar>,
+
+    /// Responses, complete except for `r_{real_index, 0}`.
+    r: Vec<Scalar>,
+}
+
+impl MultisigRingSigner {
+    /// Verifies the signers' first two rounds and computes the ring's challenges.
+    ///
+    /// # Arguments
+    /// * `message` - Message to be signed.
+    /// * `ring` - A ring of input onetime addresses and amount commitments.
+    /// * `real_index` - The index in the ring of the real input.
+    /// * `onetime_private_key_offset` - The offset `t` of the real input's onetime private key.
+    /// * `verification_shares` - Verification share of every participant, ordered by index.
+    /// * `threshold` - Number of participants required to sign.
+    /// * `partial_key_images` - One partial key image from each signer.
+    /// * `nonce_commitments` - One nonce commitment from each signer.
+    /// * `nonce_points` - One set of revealed nonce points from each signer.
+    /// * `value` - Value of the real input.
+    /// * `blinding` - Blinding of the real input.
+    /// * `output_blinding` - The output amount's blinding factor.
+    /// * `rng` - Randomness.
+    pub fn new<CSPRNG: RngCore + CryptoRng>(
+        message: &[u8],
+        ring: &[(CompressedRistrettoPublic, CompressedCommitment)],
+        real_index: usize,
+        onetime_private_key_offset: &Scalar,
+        verification_shares: &[RistrettoPublic],
+        threshold: u32,
+        partial_key_images: &[PartialKeyImage],
+        nonce_commitments: &[NonceCommitment],
+        nonce_points: &[NoncePoints],
+        value: u64,
+        blinding: &Scalar,
+        output_blinding: &Scalar,
+        rng: &mut CSPRNG,
+    ) -> Result<Self, Error> {
+        let ring_size = ring.len();
+        if real_index >= ring_size {
+            return Err(Error::IndexOutOfBounds);
+        }
+
+        // Fewer than `threshold` signers cannot produce a valid signature.
+        if (partial_key_images.len() as u32) < threshold {
+            return Err(Error::InsufficientSigners(
+                partial_key_images.len() as u32,
+                threshold,
+            ));
+        }
+
+        let H = GENERATORS.B_blinding;
+
+        // Ring must decompress.
+        let decompressed_ring = decompress_ring(ring)?;
+        let (onetime_public_key, input_commitment) = decompressed_ring[real_index];
+
+        // Each signer must supply a valid partial key image, and nonces matching their commitment.
+        let mut signers = Vec::with_capacity(partial_key_images.len());
+        for partial in partial_key_images {
+            let participant = partial.participant;
+            let verification_share = verification_shares
+                .get((participant as usize).wrapping_sub(1))
+                .ok_or(Error::InvalidParticipant(participant))?;
+            partial.verify(verification_share, &onetime_public_key)?;
+
+            let commitment = nonce_commitments
+                .iter()
+                .find(|commitment| commitment.participant == participant)
+                .ok_or(Error::MissingParticipant(participant))?;
+            let points = nonce_points
+                .iter()
+                .find(|points| points.participant == participant)
+                .ok_or(Error::MissingParticipant(participant))?;
+            if points.commitment() != *commitment {
+                return Err(Error::InvalidNonceCommitment(participant));
+            }
+
+            let decompress = |point: &CompressedRistrettoPublic| {
+                RistrettoPublic::try_from(point)
+                    .map(|p| *p.as_ref())
+                    .map_err(|_| Error::InvalidNonceCommitment(participant))
+            };
+            signers.push(SignerPoints {
+                participant,
+                S: *verification_share.as_ref(),
+                K: decompress(&partial.point)?,
+                L: decompress(&points.L)?,
+                R: decompress(&points.R)?,
+            });
+        }
+        if signers.len() != nonce_commitments.len() || signers.len() != nonce_points.len() {
+            return Err(Error::LengthMismatch(signers.len(), nonce_points.len()));
+        }
+
+        let key_image = combine_partial_key_images(
+            partial_key_images,
+            onetime_private_key_offset,
+            &onetime_public_key,
+        )?;
+
+        // The real input's commitment to zero is proven by the coordinator alone.
+        let output_commitment = Commitment::new(value, *output_blinding);
+        let z: Scalar = output_blinding - blinding;
+        if output_commitment.point - input_commitment.point != z * H {
+            return Err(Error::ValueNotConserved);
+        }
+        let alpha_1 = Scalar::random(rng);
+        let real_L1 = alpha_1 * H;
+
+        // The real input's L0 and R0 terms use the sum of the signers' nonces.
+        let real_L0: RistrettoPoint = signers.iter().map(|signer| signer.L).sum();
+        let real_R0: RistrettoPoint = signers.iter().map(|signer| signer.R).sum();
+
+        let (c, mut r) = compute_challenges(
+            message,
+            &decompressed_ring,
+            real_index,
+            &key_image,
+            &output_commitment,
+            &real_L0,
+            &real_R0,
+            &real_L1,
+            rng,
+        )?;
+        r[2 * real_index + 1] = alpha_1 - c[real_index] * z;
+
+        Ok(Self {
+            real_index,
+            key_image,
+            onetime_private_key_offset: *onetime_private_key_offset,
+            onetime_public_key,
+            signers,
+            real_L1,
+            c,
+            r,
+        })
+    }
+
+    /// The key image of the real input.
+    pub fn key_image(&self) -> KeyImage {
+        self.key_image
+    }
+
+    /// The challenge at the real index, sent to every signer in the third round.
+    pub fn challenge(&self) -> CurveScalar {
+        CurveScalar::from(self.c[self.real_index])
+    }
+
+    /// The public values from which each signer rebuilds the challenge, sent with it in the third
+    /// round.
+    pub fn transcript(&self) -> ChallengeTranscript {
+        let mut responses: Vec<CurveScalar> =
+            self.r.iter().cloned().map(CurveScalar::from).collect();
+        // The real input's responses are not yet known to the signers.
+        responses[2 * self.real_index] = CurveScalar::from(Scalar::zero());
+        responses[2 * self.real_index + 1] = CurveScalar::from(Scalar::zero());
+
+        ChallengeTranscript {
+            real_index: self.real_index as u64,
+            key_image: self.key_image,
+            L1: CompressedRistrettoPublic::from(self.real_L1),
+            responses,
+            nonce_points: self
+                .signers
+                .iter()
+                .map(|signer| NoncePoints {
+                    participant: signer.participant,
+                    L: CompressedRistrettoPublic::from(signer.L),
+                    R: CompressedRistrettoPublic::from(signer.R),
+                })
+                .collect(),
+        }
+    }
+
+    /// Every participant taking part in this signature.
+    pub fn signers(&self) -> Vec<ParticipantIndex> {
+        self.signers
+            .iter()
+            .map(|signer| signer.participant)
+            .collect()
+    }
+
+    /// Checks each signer's partial response and completes the signature.
+    ///
+    /// # Arguments
+    /// * `partial_responses` - One partial response from each signer.
+    pub fn finalize(self, partial_responses: &[PartialResponse]) -> Result<RingMLSAG, Error> {
+        let G = GENERATORS.B;
+        let Hp = hash_to_point(&self.onetime_public_key);
+        let c_real = self.c[self.real_index];
+        let participants = self.signers();
+
+        if partial_responses.len() != self.signers.len() {
+            return Err(Error::LengthMismatch(
+                self.signers.len(),
+                partial_responses.len(),
+            ));
+        }
+
+        let mut sum_of_responses = Scalar::zero();
+        for signer in &self.signers {
+            let partial = partial_responses
+                .iter()
+                .find(|partial| partial.participant == signer.participant)
+                .ok_or(Error::MissingParticipant(signer.participant))?;
+            let r_j = partial.response.scalar;
+            let c_lambda = c_real * lagrange_coefficient(signer.participant, &participants)?;
+
+            // r_j * G + c * lambda_j * S_j == alpha_j * G, and likewise for Hp(P) and K_j.
+            if r_j * G + c_lambda * signer.S != signer.L
+                || r_j * Hp + c_lambda * signer.K != signer.R
+            {
+                return Err(Error::InvalidPartialResponse(signer.participant));
+            }
+            sum_of_responses += r_j;
+        }
+
+        // "Close the loop": r = sum_j r_j - c * t = alpha - c * x.
+        let mut r = self.r;
+        r[2 * self.real_index] = sum_of_responses - c_real * self.onetime_private_key_offset;
+
+        Ok(RingMLSAG {
+            c_zero: CurveScalar::from(self.c[0]),
+            responses: r.into_iter().map(CurveScalar::from).collect(),
+            key_image: self.key_image,
+        })
+    }
+}
+
+/// Hn( participant | S | K | A | B ) for a partial key image's proof.
+fn dleq_challenge(
+    participant: ParticipantIndex,
+    S: &RistrettoPoint,
+    K: &RistrettoPoint,
+    A: &RistrettoPoint,
+    B: &RistrettoPoint,
+) -> Scalar {
+    let mut hasher = Blake2b::new();
+    hasher.input(&MULTISIG_PARTIAL_KEY_IMAGE_DOMAIN_TAG);
+    hasher.input(&participant.to_le_bytes());
+    hasher.input(S.compress().as_bytes());
+    hasher.input(K.compress().as_bytes());
+    hasher.input(A.compress().as_bytes());
+    hasher.input(B.compress().as_bytes());
+    Scalar::from_hash::<Blake2b>(hasher)
+}
+
+#[cfg(test)]
+mod multisig_tests {
+    use super::*;
+    use crate::multisig_keys::{keygen_in_process, MultisigKeyShare};
+    use alloc::vec;
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// A ring whose real input has onetime private key `t + b`, where `b` is the shared key.
+    fn ring_for(
+        spend_public_key: &RistrettoPublic,
+        t: &Scalar,
+        value: u64,
+        blinding: &Scalar,
+        num_mixins: usize,
+        rng: &mut StdRng,
+    ) -> (
+        Vec<(CompressedRistrettoPublic, CompressedCommitment)>,
+        usize,
+    ) {
+        let mut ring = Vec::new();
+        for _ in 0..num_mixins {
+            let address = CompressedRistrettoPublic::from(RistrettoPublic::from_random(rng));
+            let commitment = CompressedCommitment::new(rng.next_u64(), Scalar::random(rng));
+            ring.push((address, commitment));
+        }
+        let P = t * GENERATORS.B + spend_public_key.as_ref();
+        let real_index = rng.next_u64() as usize % (num_mixins + 1);
+        ring.insert(
+            real_index,
+            (
+                CompressedRistrettoPublic::from(P),
+                CompressedCommitment::new(value, *blinding),
+            ),
+        );
+        (ring, real_index)
+    }
+
+    /// Runs all three rounds with the given signers.
+    fn sign(
+        key_shares: &[MultisigKeyShare],
+        signers: &[ParticipantIndex],
+        rng: &mut StdRng,
+    ) -> Result<
+        (
+            RingMLSAG,
+            KeyImage,
+            Vec<(CompressedRistrettoPublic, CompressedCommitment)>,
+            CompressedCommitment,
+            [u8; 32],
+        ),
+        Error,
+    > {
+        let mut message = [0u8; 32];
+        rng.fill_bytes(&mut message);
+        let t = Scalar::random(rng);
+        let value = rng.next_u64();
+        let blinding = Scalar::random(rng);
+        let output_blinding = Scalar::random(rng);
+        let spend_public_key = key_shares[0].spend_public_key();
+        let (ring, real_index) = ring_for(spend_public_key, &t, value, &blinding, 10, rng);
+        let P = RistrettoPublic::try_from(&ring[real_index].0).unwrap();
+
+        // Round 1.
+        let mut nonces = Vec::new();
+        let mut partial_key_images = Vec::new();
+        let mut nonce_commitments = Vec::new();
+        for &j in signers {
+            let key_share = &key_shares[j as usize - 1];
+            partial_key_images.push(PartialKeyImage::new(j, key_share.secret_share(), &P, rng));
+            let nonce = MultisigNonce::new(j, &P, rng);
+            nonce_commitments.push(nonce.commitment());
+            nonces.push(nonce);
+        }
+
+        // Round 2.
+        let nonce_points: Vec<NoncePoints> = nonces.iter().map(|n| *n.points()).collect();
+        let ring_signer = MultisigRingSigner::new(
+            &message,
+            &ring,
+            real_index,
+            &t,
+            key_shares[0].verification_shares(),
+            key_shares[0].threshold(),
+            &partial_key_images,
+            &nonce_commitments,
+            &nonce_points,
+            value,
+            &blinding,
+            &output_blinding,
+            rng,
+        )?;
+
+        // Round 3. Each signer rebuilds the challenge before responding.
+        let challenge = ring_signer.challenge();
+        let output_commitment = CompressedCommitment::new(value, output_blinding);
+        assert_eq!(
+            ring_signer
+                .transcript()
+                .challenge(&message, &ring, &output_commitment)?,
+            challenge
+        );
+        let partial_responses: Vec<PartialResponse> = nonces
+            .into_iter()
+            .zip(signers)
+            .map(|(nonce, &j)| {
+                nonce
+                    .respond(
+                        &challenge,
+                        key_shares[j as usize - 1].secret_share(),
+                        signers,
+                    )
+                    .unwrap()
+            })
+            .collect();
+        let signature = ring_signer.finalize(&partial_responses)?;
+
+        let b: Scalar = signers
+            .iter()
+            .map(|&j| {
+                let lambda = lagrange_coefficient(j, signers).unwrap();
+                let s_j: &Scalar = key_shares[j as usize - 1].secret_share().as_ref();
+                lambda * s_j
+            })
+            .sum();
+        let expected_key_image = KeyImage::from(&RistrettoPrivate::from(t + b));
+
+        Ok((
+            signature,
+            expected_key_image,
+            ring,
+            output_commitment,
+            message,
+        ))
+    }
+
+    #[test]
+    // Any threshold of signers should produce a valid signature with the correct key image.
+    fn test_threshold_signature_verifies() {
+        let mut rng: StdRng = SeedableRng::from_seed([11u8; 32]);
+        let key_shares = keygen_in_process(2, 3, &mut rng).unwrap();
+        for signers in &[vec![1u32, 2], vec![2, 3], vec![3, 1], vec![1, 2, 3]] {
+            let (signature, expected_key_image, ring, output_commitment, message) =
+                sign(&key_shares, signers, &mut rng).unwrap();
+            assert_eq!(signature.key_image, expected_key_image);
+            assert!(signature
+                .verify(&message, &ring, &output_commitment)
+                .is_ok());
+        }
+    }
+
+    #[test]
+    // Fewer than threshold signers should be rejected before any signing round.
+    fn test_below_threshold_signers_are_rejected() {
+        let mut rng: StdRng = SeedableRng::from_seed([12u8; 32]);
+        let key_shares = keygen_in_process(3, 4, &mut rng).unwrap();
+        match sign(&key_shares, &[1, 4], &mut rng) {
+            Err(Error::InsufficientSigners(2, 3)) => {} // Expected.
+            Err(e) => panic!("Unexpected error {:?}", e),
+            Ok(_) => panic!("Signing with fewer than threshold signers should fail"),
+        }
+    }
+
+    #[test]
+    // A partial key image computed with the wrong secret share should be rejected.
+    fn test_partial_key_image_rejects_wrong_share() {
+        let mut rng: StdRng = SeedableRng::from_seed([13u8; 32]);
+        let key_shares = keygen_in_process(2, 3, &mut rng).unwrap();
+        let P = RistrettoPublic::from_random(&mut rng);
+
+        let partial = PartialKeyImage::new(1, key_shares[0].secret_share(), &P, &mut rng);
+        assert!(partial
+            .verify(key_shares[0].verification_share(1).unwrap(), &P)
+            .is_ok());
+
+        let forged = PartialKeyImage::new(1, key_shares[1].secret_share(), &P, &mut rng);
+        assert_eq!(
+            forged.verify(key_shares[0].verification_share(1).unwrap(), &P),
+            Err(Error::InvalidPartialKeyImage(1))
+        );
+    }
+
+    #[test]
+    // Revealed nonces that do not match the signer's commitment should be rejected.
+    fn test_nonce_commitment_mismatch() {
+        let mut rng: StdRng = SeedableRng::from_seed([14u8; 32]);
+        let P = RistrettoPublic::from_random(&mut rng);
+        let nonce = MultisigNonce::new(2, &P, &mut rng);
+        let other = MultisigNonce::new(2, &P, &mut rng);
+        assert_ne!(nonce.commitment(), other.points().commitment());
+        assert_eq!(nonce.commitment(), nonce.points().commitment());
+    }
+
+    #[test]
+    // A transcript should not reproduce the challenge for a different message.
+    fn test_transcript_is_bound_to_message() {
+        let mut rng: StdRng = SeedableRng::from_seed([15u8; 32]);
+        let key_shares = keygen_in_process(2, 2, &mut rng).unwrap();
+        let t = Scalar::random(&mut rng);
+        let value = 1234;
+        let blinding = Scalar::random(&mut rng);
+        let output_blinding = Scalar::random(&mut rng);
+        let (ring, real_index) = ring_for(
+            key_shares[0].spend_public_key(),
+            &t,
+            value,
+            &blinding,
+            4,
+            &mut rng,
+        );
+        let P = RistrettoPublic::try_from(&ring[real_index].0).unwrap();
+
+        let mut partial_key_images = Vec::new();
+        let mut nonce_points = Vec::new();
+        for (i, key_share) in key_shares.iter().enumerate() {
+            let j = i as u32 + 1;
+            partial_key_images.push(PartialKeyImage::new(
+                j,
+                key_share.secret_share(),
+                &P,
+                &mut rng,
+            ));
+            nonce_points.push(*MultisigNonce::new(j, &P, &mut rng).points());
+        }
+        let nonce_commitments: Vec<NonceCommitment> =
+            nonce_points.iter().map(NoncePoints::commitment).collect();
+
+        let message = [1u8; 32];
+        let ring_signer = MultisigRingSigner::new(
+            &message,
+            &ring,
+            real_index,
+            &t,
+            key_shares[0].verification_shares(),
+            key_shares[0].threshold(),
+            &partial_key_images,
+            &nonce_commitments,
+            &nonce_points,
+            value,
+            &blinding,
+            &output_blinding,
+            &mut rng,
+        )
+        .unwrap();
+
+        let transcript = ring_signer.transcript();
+        let output_commitment = CompressedCommitment::new(value, output_blinding);
+        assert_eq!(
+            transcript
+                .challenge(&message, &ring, &output_commitment)
+                .unwrap(),
+            ring_signer.challenge()
+        );
+        assert_ne!(
+            transcript
+                .challenge(&[2u8; 32], &ring, &output_commitment)
+                .unwrap(),
+            ring_signer.challenge()
+        );
+    }
+}
@@ -248,74 +248,15 @@ fn sign_with_balance_check<CSPRNG: RngCore + CryptoRng>(
         }
     }
 
-    // Blindings for pseudo_outputs. All but the last are random.
-    // Constructing blindings in this way ensures that sum_of_outputs - sum_of_pseudo_outputs = 0
-    // if the sum of outputs and the sum of pseudo_outputs have equal value.
-    let mut pseudo_output_blindings: Vec<Scalar> = Vec::new();
-    for _i in 0..num_inputs - 1 {
-        pseudo_output_blindings.push(Scalar::random(rng));
-    }
-    // The implicit fee output is ommitted because its blinding is zero.
-    let sum_of_output_blindings: Scalar = output_values_and_blindings
-        .iter()
-        .map(|(_, blinding)| blinding)
-        .sum();
-
-    let sum_of_pseudo_output_blindings: Scalar = pseudo_output_blindings.iter().sum();
-    let last_blinding: Scalar = sum_of_output_blindings - sum_of_pseudo_output_blindings;
-    pseudo_output_blindings.push(last_blinding);
-
-    // Create Range proofs for outputs and pseudo-outputs.
-    let pseudo_output_values_and_blindings: Vec<(u64, Scalar)> = input_secrets
-        .iter()
-        .zip(pseudo_output_blindings.iter())
-        .map(|((_, value, _), blinding)| (*value, *blinding))
-        .collect();
-
-    let (range_proof, commitments) = {
-        let values_and_blindings: Vec<(u64, Scalar)> = pseudo_output_values_and_blindings
-            .iter()
-            .chain(output_values_and_blindings.iter())
-            .map(|(value, blinding)| (*value, *blinding))
-            .collect();
-
-        // The implicit fee output is omitted from the range proof because it is known.
-
-        let (values, blindings): (Vec<_>, Vec<_>) = values_and_blindings.into_iter().unzip();
-        generate_range_proofs(&values, &blindings, rng).map_err(|_e| Error::RangeProofError)?
-    };
-
-    if check_value_is_preserved {
-        let sum_of_output_commitments: RistrettoPoint = output_values_and_blindings
-            .iter()
-            .map(|(value, blinding)| GENERATORS.commit(Scalar::from(*value), *blinding))
-            .sum();
-
-        let sum_of_pseudo_output_commitments: RistrettoPoint = pseudo_output_values_and_blindings
-            .iter()
-            .map(|(value, blinding)| GENERATORS.commit(Scalar::from(*value), *blinding))
-            .sum();
-
-        // The implicit fee output.
-        let fee_commitment = GENERATORS.commit(Scalar::from(fee), *FEE_BLINDING);
-
-        let difference =
-            sum_of_output_commitments + fee_commitment - sum_of_pseudo_output_commitments;
-        if difference != GENERATORS.commit(Scalar::zero(), Scalar::zero()) {
-            return Err(Error::ValueNotConserved);
-        }
-    }
-
-    let pseudo_output_commitments: Vec<CompressedCommitment> = commitments
-        .iter()
-        .take(num_inputs)
-        .map(CompressedCommitment::from)
-        .collect();
-
-    // Extend the message with the range proof and pseudo_output_commitments.
-    // This ensures that they are signed.
-    let range_proof_bytes = range_proof.to_bytes();
-    let extended_message = extend_message(message, &pseudo_output_commitments, &range_proof_bytes)?;
+    let input_values: Vec<u64> = input_secrets.iter().map(|(_, value, _)| *value).collect();
+    let unsigned = UnsignedRctBulletproofs::new_with_balance_check(
+        message,
+        &input_values,
+        output_values_and_blindings,
+        fee,
+        check_value_is_preserved,
+        rng,
+    )?;
 
     // Prove that the signer is allowed to spend a public key in each ring, and that
     // the input's value equals the value of the pseudo_output.
@@ -324,27 +265,174 @@ fn sign_with_balance_check<CSPRNG: RngCore + CryptoRng>(
         let real_index = real_input_indices[i];
         let (onetime_private_key, value, blinding) = input_secrets[i];
         let ring_signature = RingMLSAG::sign(
-            &extended_message,
+            &unsigned.extended_message,
             &rings[i],
             real_index,
             &onetime_private_key,
             value,
             &blinding,
-            &pseudo_output_blindings[i],
+            &unsigned.pseudo_output_blindings[i],
             rng,
         )?;
         ring_signatures.push(ring_signature);
     }
 
-    Ok(SignatureRctBulletproofs {
-        ring_signatures,
-        pseudo_output_commitments,
-        range_proof_bytes,
-    })
+    Ok(unsigned.finalize(ring_signatures))
+}
+
+/// Everything in a `SignatureRctBulletproofs` except its ring signatures.
+///
+/// This allows each ring to be signed separately, e.g. by multiple parties of a multisig account
+/// (see `MultisigRingSigner`), before the signature is assembled with `finalize`.
+pub struct UnsignedRctBulletproofs {
+    /// Blinding of each pseudo-output. The i^th ring signature must use the i^th blinding as its
+    /// output blinding.
+    pub pseudo_output_blindings: Vec<Scalar>,
+
+    /// Commitments of value equal to each real input.
+    pub pseudo_output_commitments: Vec<CompressedCommitment>,
+
+    /// Proof that all pseudo_outputs and transaction outputs are in [0, 2^64).
+    pub range_proof_bytes: Vec<u8>,
+
+    /// The message signed by each ring signature:
+    /// [message || pseudo_output_commitments || range_proof].
+    pub extended_message: Vec<u8>,
+}
+
+impl UnsignedRctBulletproofs {
+    /// Creates pseudo-outputs and a range proof.
+    ///
+    /// # Arguments
+    /// * `message` - The messages to be signed, e.g. Hash(TxPrefix).
+    /// * `input_values` - Value of each real input.
+    /// * `output_values_and_blindings` - Value and blinding for each output amount commitment.
+    /// * `fee` - Value of the implicit fee output.
+    /// * `rng` - Randomness.
+    pub fn new<CSPRNG: RngCore + CryptoRng>(
+        message: &[u8; 32],
+        input_values: &[u64],
+        output_values_and_blindings: &[(u64, Scalar)],
+        fee: u64,
+        rng: &mut CSPRNG,
+    ) -> Result<Self, Error> {
+        Self::new_with_balance_check(
+            message,
+            input_values,
+            output_values_and_blindings,
+            fee,
+            true,
+            rng,
+        )
+    }
+
+    fn new_with_balance_check<CSPRNG: RngCore + CryptoRng>(
+        message: &[u8; 32],
+        input_values: &[u64],
+        output_values_and_blindings: &[(u64, Scalar)],
+        fee: u64,
+        check_value_is_preserved: bool,
+        rng: &mut CSPRNG,
+    ) -> Result<Self, Error> {
+        if input_values.is_empty() {
+            return Err(Error::NoInputs);
+        }
+        let num_inputs = input_values.len();
+
+        // Blindings for pseudo_outputs. All but the last are random.
+        // Constructing blindings in this way ensures that sum_of_outputs - sum_of_pseudo_outputs = 0
+        // if the sum of outputs and the sum of pseudo_outputs have equal value.
+        let mut pseudo_output_blindings: Vec<Scalar> = Vec::new();
+        for _i in 0..num_inputs - 1 {
+            pseudo_output_blindings.push(Scalar::random(rng));
+        }
+        // The implicit fee output is ommitted because its blinding is zero.
+        let sum_of_output_blindings: Scalar = output_values_and_blindings
+            .iter()
+            .map(|(_, blinding)| blinding)
+            .sum();
+
+        let sum_of_pseudo_output_blindings: Scalar = pseudo_output_blindings.iter().sum();
+        let last_blinding: Scalar = sum_of_output_blindings - sum_of_pseudo_output_blindings;
+        pseudo_output_blindings.push(last_blinding);
+
+        // Create Range proofs for outputs and pseudo-outputs.
+        let pseudo_output_values_and_blindings: Vec<(u64, Scalar)> = input_values
+            .iter()
+            .zip(pseudo_output_blindings.iter())
+            .map(|(value, blinding)| (*value, *blinding))
+            .collect();
+
+        let (range_proof, commitments) = {
+            let values_and_blindings: Vec<(u64, Scalar)> = pseudo_output_values_and_blindings
+                .iter()
+                .chain(output_values_and_blindings.iter())
+                .map(|(value, blinding)| (*value, *blinding))
+                .collect();
+
+            // The implicit fee output is omitted from the range proof because it is known.
+
+            let (values, blindings): (Vec<_>, Vec<_>) = values_and_blindings.into_iter().unzip();
+            generate_range_proofs(&values, &blindings, rng).map_err(|_e| Error::RangeProofError)?
+        };
+
+        if check_value_is_preserved {
+            let sum_of_output_commitments: RistrettoPoint = output_values_and_blindings
+                .iter()
+                .map(|(value, blinding)| GENERATORS.commit(Scalar::from(*value), *blinding))
+                .sum();
+
+            let sum_of_pseudo_output_commitments: RistrettoPoint =
+                pseudo_output_values_and_blindings
+                    .iter()
+                    .map(|(value, blinding)| GENERATORS.commit(Scalar::from(*value), *blinding))
+                    .sum();
+
+            // The implicit fee output.
+            let fee_commitment = GENERATORS.commit(Scalar::from(fee), *FEE_BLINDING);
+
+            let difference =
+                sum_of_output_commitments + fee_commitment - sum_of_pseudo_output_commitments;
+            if difference != GENERATORS.commit(Scalar::zero(), Scalar::zero()) {
+                return Err(Error::ValueNotConserved);
+            }
+        }
+
+        let pseudo_output_commitments: Vec<CompressedCommitment> = commitments
+            .iter()
+            .take(num_inputs)
+            .map(CompressedCommitment::from)
+            .collect();
+
+        // Extend the message with the range proof and pseudo_output_commitments.
+        // This ensures that they are signed.
+        let range_proof_bytes = range_proof.to_bytes();
+        let extended_message =
+            extend_message(message, &pseudo_output_commitments, &range_proof_bytes)?;
+
+        Ok(Self {
+            pseudo_output_blindings,
+            pseudo_output_commitments,
+            range_proof_bytes,
+            extended_message,
+        })
+    }
+
+    /// Assembles the signature from one ring signature per input.
+    ///
+    /// # Arguments
+    /// * `ring_signatures` - Signature for each input ring, signing `extended_message`.
+    pub fn finalize(self, ring_signatures: Vec<RingMLSAG>) -> SignatureRctBulletproofs {
+        SignatureRctBulletproofs {
+            ring_signatures,
+            pseudo_output_commitments: self.pseudo_output_commitments,
+            range_proof_bytes: self.range_proof_bytes,
+        }
+    }
 }
 
 /// Concatenates [message || pseudo_output_commitments || range_proof].
-fn extend_message(
+pub fn extend_message(
     message: &[u8],
     pseudo_output_commitments: &[CompressedCommitment],
     range_proof_bytes: &[u8],
@@ -118,6 +118,10 @@ pub enum TransactionValidationError {
     /// Public keys must be valid Ristretto points.
     #[fail(display = "KeyError")]
     KeyError,
+
+    /// Outputs must be sorted by public key.
+    #[fail(display = "UnsortedOutputs")]
+    UnsortedOutputs,
 }
 
 impl From<mc_crypto_keys::KeyError> for TransactionValidationError {
@@ -4,4 +4,6 @@ mod error;
 mod validate;
 
 pub use error::{TransactionValidationError, TransactionValidationResult};
-pub use validate::{validate, validate_signature, validate_tombstone};
+pub use validate::{
+    validate, validate_all, validate_features, validate_signature, validate_tombstone,
+};
@@ -9,9 +9,9 @@ use alloc::vec::Vec;
 use super::error::{TransactionValidationError, TransactionValidationResult};
 use crate::{
     compressed_commitment::CompressedCommitment,
-    constants::*,
     membership_proofs::{derive_proof_at_index, is_membership_proof_valid},
     tx::{Tx, TxOut, TxOutMembershipProof, TxPrefix},
+    Feature, NetworkParameters,
 };
 use mc_common::HashSet;
 use mc_crypto_keys::CompressedRistrettoPublic;
@@ -23,33 +23,114 @@ use rand_core::{CryptoRng, RngCore};
 /// * `tx` - A pending transaction.
 /// * `current_block_index` - The index of the current block that is being built.
 /// * `root_proofs` - Membership proofs for each input ring element contained in `tx`.
+/// * `parameters` - Limits of the network the transaction is being validated for.
 /// * `csprng` - Cryptographically secure random number generator.
 pub fn validate<R: RngCore + CryptoRng>(
     tx: &Tx,
     current_block_index: u64,
     root_proofs: &[TxOutMembershipProof],
+    parameters: &NetworkParameters,
     csprng: &mut R,
 ) -> TransactionValidationResult<()> {
-    validate_number_of_inputs(&tx.prefix, MAX_INPUTS)?;
+    let context = ValidationContext {
+        tx,
+        current_block_index,
+        root_proofs,
+        parameters,
+    };
+    for check in checks::<R>().iter() {
+        check(&context, csprng)?;
+    }
 
-    validate_number_of_outputs(&tx.prefix, MAX_OUTPUTS)?;
+    // Note: The transaction must not contain a Key Image that has previously been spent.
+    // This must be checked outside the enclave.
 
-    validate_ring_sizes(&tx.prefix, RING_SIZE)?;
+    Ok(())
+}
 
-    validate_ring_elements_are_unique(&tx.prefix)?;
+/// Runs every check performed by `validate`, collecting all failures instead of stopping at the
+/// first one. An empty result means the transaction is valid with respect to the provided context.
+///
+/// # Arguments
+/// * `tx` - A pending transaction.
+/// * `current_block_index` - The index of the current block that is being built.
+/// * `root_proofs` - Membership proofs for each input ring element contained in `tx`.
+/// * `parameters` - Limits of the network the transaction is being validated for.
+/// * `csprng` - Cryptographically secure random number generator.
+pub fn validate_all<R: RngCore + CryptoRng>(
+    tx: &Tx,
+    current_block_index: u64,
+    root_proofs: &[TxOutMembershipProof],
+    parameters: &NetworkParameters,
+    csprng: &mut R,
+) -> Vec<TransactionValidationError> {
+    let context = ValidationContext {
+        tx,
+        current_block_index,
+        root_proofs,
+        parameters,
+    };
+    checks::<R>()
+        .iter()
+        .filter_map(|check| check(&context, csprng).err())
+        .collect()
+}
 
-    validate_membership_proofs(&tx.prefix, &root_proofs)?;
+/// The context a transaction is validated in.
+struct ValidationContext<'a> {
+    tx: &'a Tx,
+    current_block_index: u64,
+    root_proofs: &'a [TxOutMembershipProof],
+    parameters: &'a NetworkParameters,
+}
 
-    validate_signature(&tx, csprng)?;
+/// A single check performed by `validate` and `validate_all`.
+type Check<R> = fn(&ValidationContext<'_>, &mut R) -> TransactionValidationResult<()>;
+
+/// The checks performed by `validate` and `validate_all`, in order.
+fn checks<R: RngCore + CryptoRng>() -> [Check<R>; 9] {
+    [
+        |context, _| validate_number_of_inputs(&context.tx.prefix, context.parameters.max_inputs),
+        |context, _| validate_number_of_outputs(&context.tx.prefix, context.parameters.max_outputs),
+        |context, _| validate_ring_sizes(&context.tx.prefix, context.parameters.ring_size),
+        |context, _| validate_ring_elements_are_unique(&context.tx.prefix),
+        |context, _| validate_membership_proofs(&context.tx.prefix, context.root_proofs),
+        |context, csprng| validate_signature(context.tx, csprng),
+        |context, _| validate_transaction_fee(context.tx, context.parameters.base_fee),
+        |context, _| validate_key_images_are_unique(context.tx),
+        |context, _| {
+            validate_tombstone(
+                context.current_block_index,
+                context.tx.prefix.tombstone_block,
+                context.parameters.max_tombstone_blocks,
+            )
+        },
+    ]
+}
 
-    validate_transaction_fee(&tx)?;
+/// Checks that the transaction follows the rules enabled in blocks of the given version.
+///
+/// # Arguments
+/// * `tx` - A pending transaction.
+/// * `block_version` - Version of the block the transaction would be included in.
+pub fn validate_features(tx: &Tx, block_version: u32) -> TransactionValidationResult<()> {
+    if Feature::SortedTxOutputs.is_enabled(block_version) {
+        validate_outputs_are_sorted(&tx.prefix)?;
+    }
 
-    validate_key_images_are_unique(&tx)?;
+    Ok(())
+}
 
-    validate_tombstone(current_block_index, tx.prefix.tombstone_block)?;
+/// The outputs of the transaction must be sorted by public key.
+fn validate_outputs_are_sorted(tx_prefix: &TxPrefix) -> TransactionValidationResult<()> {
+    let is_sorted = tx_prefix
+        .outputs
+        .windows(2)
+        .all(|pair| pair[0].public_key <= pair[1].public_key);
 
-    // Note: The transaction must not contain a Key Image that has previously been spent.
-    // This must be checked outside the enclave.
+    if !is_sorted {
+        return Err(TransactionValidationError::UnsortedOutputs);
+    }
 
     Ok(())
 }
@@ -173,9 +254,9 @@ pub fn validate_signature<R: RngCore + CryptoRng>(
         .map_err(TransactionValidationError::InvalidTransactionSignature)
 }
 
-/// The fee amount must be greater than or equal to `BASE_FEE`.
-fn validate_transaction_fee(tx: &Tx) -> TransactionValidationResult<()> {
-    if tx.prefix.fee < BASE_FEE {
+/// The fee amount must be greater than or equal to `base_fee`.
+fn validate_transaction_fee(tx: &Tx, base_fee: u64) -> TransactionValidationResult<()> {
+    if tx.prefix.fee < base_fee {
         Err(TransactionValidationError::TxFeeError)
     } else {
         Ok(())
@@ -284,16 +365,18 @@ fn validate_membership_proofs(
 /// # Arguments
 /// * `current_block_index` - The index of the block currently being built.
 /// * `tombstone_block_index` - The block index at which this transaction is no longer considered valid.
+/// * `max_tombstone_blocks` - How far in the future the tombstone block may be.
 ///
 pub fn validate_tombstone(
     current_block_index: u64,
     tombstone_block_index: u64,
+    max_tombstone_blocks: u64,
 ) -> TransactionValidationResult<()> {
     if current_block_index >= tombstone_block_index {
         return Err(TransactionValidationError::TombstoneBlockExceeded);
     }
 
-    let limit = current_block_index + MAX_TOMBSTONE_BLOCKS;
+    let limit = current_block_index + max_tombstone_blocks;
     if tombstone_block_index > limit {
         return Err(TransactionValidationError::TombstoneBlockTooFar);
     }
@@ -308,17 +391,18 @@ mod tests {
     use alloc::vec::Vec;
 
     use crate::{
-        constants::{BASE_FEE, RING_SIZE},
-        tx::{Tx, TxOutMembershipHash, TxOutMembershipProof},
+        constants::{BASE_FEE, MAX_TOMBSTONE_BLOCKS, RING_SIZE},
+        tx::{Tx, TxOut, TxOutMembershipHash, TxOutMembershipProof},
         validation::{
             error::TransactionValidationError,
             validate::{
-                validate_key_images_are_unique, validate_membership_proofs,
-                validate_number_of_inputs, validate_number_of_outputs,
+                validate, validate_all, validate_features, validate_key_images_are_unique,
+                validate_membership_proofs, validate_number_of_inputs, validate_number_of_outputs,
                 validate_ring_elements_are_unique, validate_ring_sizes, validate_signature,
-                validate_tombstone, validate_transaction_fee, MAX_TOMBSTONE_BLOCKS,
+                validate_tombstone, validate_transaction_fee,
             },
         },
+        NetworkParameters, BLOCK_VERSION, MAX_BLOCK_VERSION,
     };
 
     use mc_crypto_keys::CompressedRistrettoPublic;
@@ -601,6 +685,30 @@ mod tests {
         unimplemented!()
     }
 
+    #[test]
+    /// validate_features should only require sorted outputs once `SortedTxOutputs` is enabled.
+    fn test_validate_features() {
+        let (mut tx, ledger) = create_test_tx();
+
+        let block_contents = ledger.get_block_contents(0).unwrap();
+        let mut outputs: Vec<TxOut> = adapt_hack(&block_contents.outputs[0..3].to_vec());
+
+        // Outputs in descending order.
+        outputs.sort_by(|a, b| b.public_key.cmp(&a.public_key));
+        tx.prefix.outputs = outputs.clone();
+        assert_eq!(validate_features(&tx, BLOCK_VERSION), Ok(()));
+        assert_eq!(
+            validate_features(&tx, 1),
+            Err(TransactionValidationError::UnsortedOutputs)
+        );
+
+        // Outputs in ascending order.
+        outputs.reverse();
+        tx.prefix.outputs = outputs;
+        assert_eq!(validate_features(&tx, BLOCK_VERSION), Ok(()));
+        assert_eq!(validate_features(&tx, MAX_BLOCK_VERSION), Ok(()));
+    }
+
     #[test]
     /// validate_key_images_are_unique rejects duplicate key image.
     fn test_validate_key_images_are_unique_rejects_duplicate() {
@@ -691,7 +799,7 @@ mod tests {
             // Zero fees gets rejected
             let (tx, _ledger) = create_test_tx_with_amount(INITIALIZE_LEDGER_AMOUNT, 0);
             assert_eq!(
-                validate_transaction_fee(&tx),
+                validate_transaction_fee(&tx, BASE_FEE),
                 Err(TransactionValidationError::TxFeeError)
             );
         }
@@ -701,7 +809,7 @@ mod tests {
             let fee = BASE_FEE - 1;
             let (tx, _ledger) = create_test_tx_with_amount(INITIALIZE_LEDGER_AMOUNT - fee, fee);
             assert_eq!(
-                validate_transaction_fee(&tx),
+                validate_transaction_fee(&tx, BASE_FEE),
                 Err(TransactionValidationError::TxFeeError)
             );
         }
@@ -710,14 +818,14 @@ mod tests {
             // Exact fee amount is okay
             let (tx, _ledger) =
                 create_test_tx_with_amount(INITIALIZE_LEDGER_AMOUNT - BASE_FEE, BASE_FEE);
-            assert_eq!(validate_transaction_fee(&tx), Ok(()));
+            assert_eq!(validate_transaction_fee(&tx, BASE_FEE), Ok(()));
         }
 
         {
             // Overpaying fees is okay
             let fee = BASE_FEE + 1;
             let (tx, _ledger) = create_test_tx_with_amount(INITIALIZE_LEDGER_AMOUNT - fee, fee);
-            assert_eq!(validate_transaction_fee(&tx), Ok(()));
+            assert_eq!(validate_transaction_fee(&tx, BASE_FEE), Ok(()));
         }
     }
 
@@ -729,7 +837,11 @@ mod tests {
             let current_block_index = 888;
             let tombstone_block_index = 889;
             assert_eq!(
-                validate_tombstone(current_block_index, tombstone_block_index),
+                validate_tombstone(
+                    current_block_index,
+                    tombstone_block_index,
+                    MAX_TOMBSTONE_BLOCKS
+                ),
                 Ok(())
             );
         }
@@ -739,7 +851,11 @@ mod tests {
             let current_block_index = 7;
             let tombstone_block_index = 7;
             assert_eq!(
-                validate_tombstone(current_block_index, tombstone_block_index),
+                validate_tombstone(
+                    current_block_index,
+                    tombstone_block_index,
+                    MAX_TOMBSTONE_BLOCKS
+                ),
                 Err(TransactionValidationError::TombstoneBlockExceeded)
             );
         }
@@ -749,7 +865,11 @@ mod tests {
             let current_block_index = 888;
             let tombstone_block_index = 7;
             assert_eq!(
-                validate_tombstone(current_block_index, tombstone_block_index),
+                validate_tombstone(
+                    current_block_index,
+                    tombstone_block_index,
+                    MAX_TOMBSTONE_BLOCKS
+                ),
                 Err(TransactionValidationError::TombstoneBlockExceeded)
             );
         }
@@ -763,7 +883,11 @@ mod tests {
             let current_block_index = 7;
             let tombstone_block_index = current_block_index + 1;
             assert_eq!(
-                validate_tombstone(current_block_index, tombstone_block_index),
+                validate_tombstone(
+                    current_block_index,
+                    tombstone_block_index,
+                    MAX_TOMBSTONE_BLOCKS
+                ),
                 Ok(())
             );
         }
@@ -773,7 +897,11 @@ mod tests {
             let current_block_index = 7;
             let tombstone_block_index = current_block_index + MAX_TOMBSTONE_BLOCKS;
             assert_eq!(
-                validate_tombstone(current_block_index, tombstone_block_index),
+                validate_tombstone(
+                    current_block_index,
+                    tombstone_block_index,
+                    MAX_TOMBSTONE_BLOCKS
+                ),
                 Ok(())
             );
         }
@@ -783,9 +911,110 @@ mod tests {
             let current_block_index = 7;
             let tombstone_block_index = current_block_index + MAX_TOMBSTONE_BLOCKS + 1;
             assert_eq!(
-                validate_tombstone(current_block_index, tombstone_block_index),
+                validate_tombstone(
+                    current_block_index,
+                    tombstone_block_index,
+                    MAX_TOMBSTONE_BLOCKS
+                ),
                 Err(TransactionValidationError::TombstoneBlockTooFar)
             );
         }
     }
+
+    #[test]
+    /// `validate_all` should report every failing check, not just the first one.
+    fn test_validate_all_collects_all_errors() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let (mut tx, ledger) = create_test_tx();
+        let parameters = NetworkParameters::default();
+
+        let highest_indices = tx.get_membership_proof_highest_indices();
+        let root_proofs: Vec<TxOutMembershipProof> = adapt_hack(
+            &ledger
+                .get_tx_out_proof_of_memberships(&highest_indices)
+                .expect("failed getting proofs"),
+        );
+
+        // A valid transaction produces no errors.
+        let current_block_index = ledger.num_blocks().unwrap();
+        assert_eq!(
+            validate_all(
+                &tx,
+                current_block_index,
+                &root_proofs,
+                &parameters,
+                &mut rng
+            ),
+            Vec::new()
+        );
+
+        // Lowering the fee breaks both the fee check and the signature, and a later block index
+        // exceeds the tombstone block.
+        tx.prefix.fee = 0;
+        let errors = validate_all(
+            &tx,
+            tx.prefix.tombstone_block,
+            &root_proofs,
+            &parameters,
+            &mut rng,
+        );
+        assert_eq!(errors.len(), 3);
+        match errors[0] {
+            TransactionValidationError::InvalidTransactionSignature(_) => {} // Expected.
+            ref e => panic!("Unexpected error {}", e),
+        }
+        assert_eq!(errors[1], TransactionValidationError::TxFeeError);
+        assert_eq!(
+            errors[2],
+            TransactionValidationError::TombstoneBlockExceeded
+        );
+
+        // `validate` stops at the first of those errors.
+        assert_eq!(
+            validate(
+                &tx,
+                tx.prefix.tombstone_block,
+                &root_proofs,
+                &parameters,
+                &mut rng,
+            ),
+            Err(errors[0].clone())
+        );
+    }
+
+    #[test]
+    /// `validate_all` should enforce the provided network parameters rather than the defaults.
+    fn test_validate_all_uses_network_parameters() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let (tx, ledger) = create_test_tx();
+
+        let highest_indices = tx.get_membership_proof_highest_indices();
+        let root_proofs: Vec<TxOutMembershipProof> = adapt_hack(
+            &ledger
+                .get_tx_out_proof_of_memberships(&highest_indices)
+                .expect("failed getting proofs"),
+        );
+        let current_block_index = ledger.num_blocks().unwrap();
+
+        let parameters = NetworkParameters {
+            ring_size: RING_SIZE - 1,
+            base_fee: tx.prefix.fee + 1,
+            max_tombstone_blocks: tx.prefix.tombstone_block - current_block_index - 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            validate_all(
+                &tx,
+                current_block_index,
+                &root_proofs,
+                &parameters,
+                &mut rng
+            ),
+            vec![
+                TransactionValidationError::ExcessiveRingSize,
+                TransactionValidationError::TxFeeError,
+                TransactionValidationError::TombstoneBlockTooFar,
+            ]
+        );
+    }
 }
@@ -41,6 +41,24 @@ pub enum TxBuilderError {
 
     #[fail(display = "Key error: {}", _0)]
     KeyError(mc_crypto_keys::KeyError),
+
+    #[fail(display = "Multisig: {}", _0)]
+    Multisig(ring_signature::Error),
+
+    #[fail(display = "Multisig signing round is incomplete")]
+    MultisigRoundIncomplete,
+
+    #[fail(display = "Unexpected multisig participant: {}", _0)]
+    UnexpectedMultisigParticipant(u32),
+
+    #[fail(display = "Insufficient multisig signers: {} of {}", _0, _1)]
+    InsufficientMultisigSigners(u32, u32),
+
+    #[fail(
+        display = "Multisig challenge for input {} does not match the transaction",
+        _0
+    )]
+    InvalidMultisigChallenge(usize),
 }
 
 impl From<mc_util_serial::encode::Error> for TxBuilderError {
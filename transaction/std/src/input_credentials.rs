@@ -55,24 +55,8 @@ impl InputCredentials {
             .ok_or(TxBuilderError::NoInputs)?;
         let real_output_public_key = RistrettoPublic::try_from(&real_tx_out.public_key)?;
 
-        // Randomly shuffle the ring and the corresponding proofs. This ensures that the ordering
-        // of mixins in the transaction will not depend on the user's implementation for obtaining
-        // mixins.
-        let (shuffled_ring, shuffled_membership_proofs): (Vec<TxOut>, Vec<TxOutMembershipProof>) = {
-            use rand::seq::SliceRandom;
-            let mut zipped: Vec<_> = ring
-                .into_iter()
-                .zip(membership_proofs.into_iter())
-                .collect();
-            let zipped_as_slice = zipped.as_mut_slice();
-            zipped_as_slice.shuffle(rng);
-            zipped.into_iter().unzip()
-        };
-
-        let shuffled_real_index = shuffled_ring
-            .iter()
-            .position(|tx_out| *tx_out == real_tx_out)
-            .expect("The real tx_out must still exist after shuffling.");
+        let (shuffled_ring, shuffled_membership_proofs, shuffled_real_index) =
+            shuffle_ring(ring, membership_proofs, &real_tx_out, rng);
 
         Ok(InputCredentials {
             ring: shuffled_ring,
@@ -84,3 +68,36 @@ impl InputCredentials {
         })
     }
 }
+
+/// Randomly shuffles a ring and its membership proofs, and returns the new index of `real_tx_out`.
+///
+/// This ensures that the ordering of mixins in the transaction will not depend on the user's
+/// implementation for obtaining mixins.
+pub(crate) fn shuffle_ring<R: Rng + CryptoRng>(
+    ring: Vec<TxOut>,
+    membership_proofs: Vec<TxOutMembershipProof>,
+    real_tx_out: &TxOut,
+    rng: &mut R,
+) -> (Vec<TxOut>, Vec<TxOutMembershipProof>, usize) {
+    let (shuffled_ring, shuffled_membership_proofs): (Vec<TxOut>, Vec<TxOutMembershipProof>) = {
+        use rand::seq::SliceRandom;
+        let mut zipped: Vec<_> = ring
+            .into_iter()
+            .zip(membership_proofs.into_iter())
+            .collect();
+        let zipped_as_slice = zipped.as_mut_slice();
+        zipped_as_slice.shuffle(rng);
+        zipped.into_iter().unzip()
+    };
+
+    let shuffled_real_index = shuffled_ring
+        .iter()
+        .position(|tx_out| tx_out == real_tx_out)
+        .expect("The real tx_out must still exist after shuffling.");
+
+    (
+        shuffled_ring,
+        shuffled_membership_proofs,
+        shuffled_real_index,
+    )
+}
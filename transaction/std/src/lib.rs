@@ -3,8 +3,14 @@
 mod error;
 pub mod identity;
 mod input_credentials;
+mod multisig;
 mod transaction_builder;
 
 pub use error::TxBuilderError;
 pub use input_credentials::InputCredentials;
+pub use multisig::{
+    MultisigChallenges, MultisigCommitments, MultisigCoordinator, MultisigInputCredentials,
+    MultisigNonceReveal, MultisigResponses, MultisigSigner, MultisigSigningRequest,
+    MultisigTransactionBuilder,
+};
 pub use transaction_builder::TransactionBuilder;
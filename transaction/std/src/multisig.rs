@@ -0,0 +1,982 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Building and signing transactions that spend outputs owned by a threshold multisig account.
+//!
+//! A `MultisigTransactionBuilder` is used like a `TransactionBuilder`, except that inputs are
+//! identified by subaddress index rather than by onetime private key. Calling `begin_signing`
+//! returns a `MultisigCoordinator`, which drives the signing rounds with a `MultisigSigner` held
+//! by each participant:
+//!
+//! 1. `MultisigCoordinator::signing_request` -> `MultisigSigner::new`, which returns the signer's
+//!    partial key images and nonce commitments.
+//! 2. Once every signer's commitments are added, `MultisigSigner::reveal_nonces`.
+//! 3. Once every signer's nonces are added, `MultisigCoordinator::challenges` ->
+//!    `MultisigSigner::respond`, which rebuilds each challenge from the transaction prefix before
+//!    signing it.
+//!
+//! Finally, `MultisigCoordinator::build` checks every response and assembles the `Tx`.
+
+use crate::{
+    input_credentials::shuffle_ring,
+    transaction_builder::{build_tx_prefix, create_output},
+    TxBuilderError,
+};
+use curve25519_dalek::scalar::Scalar;
+use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPublic};
+use mc_transaction_core::{
+    account_keys::PublicAddress,
+    constants::BASE_FEE,
+    multisig_keys::{MultisigAccount, MultisigKeyShare, ParticipantIndex},
+    onetime_keys::compute_shared_secret,
+    ring_signature::{
+        extend_message, ChallengeTranscript, CurveScalar, MultisigNonce, MultisigRingSigner,
+        NonceCommitment, NoncePoints, PartialKeyImage, PartialResponse, RingMLSAG,
+        UnsignedRctBulletproofs,
+    },
+    tx::{Tx, TxOut, TxOutMembershipProof, TxPrefix},
+    CompressedCommitment,
+};
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// An input owned by a multisig account.
+#[derive(Clone, Debug)]
+pub struct MultisigInputCredentials {
+    /// A "ring" containing "mixins" and the one "real" TxOut to be spent.
+    pub ring: Vec<TxOut>,
+
+    /// Proof that each TxOut in `ring` is in the ledger.
+    pub membership_proofs: Vec<TxOutMembershipProof>,
+
+    /// Index in `ring` of the "real" output being spent.
+    pub real_index: usize,
+
+    /// Public key of the transaction that created the "real" output being spent.
+    pub real_output_public_key: RistrettoPublic,
+
+    /// The account subaddress the "real" output was sent to.
+    pub subaddress_index: u64,
+}
+
+impl MultisigInputCredentials {
+    /// Creates credentials for spending an output owned by a multisig account.
+    ///
+    /// # Arguments
+    /// * `ring` - A "ring" of transaction outputs.
+    /// * `membership_proofs` - Proof that each TxOut in `ring` is in the ledger.
+    /// * `real_index` - Index in `ring` of the output being spent.
+    /// * `subaddress_index` - The account subaddress the output being spent was sent to.
+    /// * `rng` - Randomness.
+    pub fn new<R: RngCore + CryptoRng>(
+        ring: Vec<TxOut>,
+        membership_proofs: Vec<TxOutMembershipProof>,
+        real_index: usize,
+        subaddress_index: u64,
+        rng: &mut R,
+    ) -> Result<Self, TxBuilderError> {
+        debug_assert_eq!(ring.len(), membership_proofs.len());
+
+        let real_tx_out: TxOut = ring
+            .get(real_index)
+            .cloned()
+            .ok_or(TxBuilderError::NoInputs)?;
+        let real_output_public_key = RistrettoPublic::try_from(&real_tx_out.public_key)?;
+
+        let (ring, membership_proofs, real_index) =
+            shuffle_ring(ring, membership_proofs, &real_tx_out, rng);
+
+        Ok(Self {
+            ring,
+            membership_proofs,
+            real_index,
+            real_output_public_key,
+            subaddress_index,
+        })
+    }
+
+    /// The onetime public key of the "real" output being spent.
+    fn onetime_public_key(&self) -> Result<RistrettoPublic, TxBuilderError> {
+        Ok(RistrettoPublic::try_from(
+            &self.ring[self.real_index].target_key,
+        )?)
+    }
+}
+
+/// Sent by the coordinator to each signer to begin signing.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultisigSigningRequest {
+    /// Every participant taking part in this signature.
+    pub signers: Vec<ParticipantIndex>,
+
+    /// Onetime public key of the real output spent by each input.
+    pub onetime_public_keys: Vec<RistrettoPublic>,
+}
+
+/// A signer's first-round message: partial key images and nonce commitments for each input.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultisigCommitments {
+    /// The signer.
+    pub participant: ParticipantIndex,
+
+    /// A partial key image for each input.
+    pub partial_key_images: Vec<PartialKeyImage>,
+
+    /// A nonce commitment for each input.
+    pub nonce_commitments: Vec<NonceCommitment>,
+}
+
+/// A signer's second-round message: revealed nonce points for each input.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultisigNonceReveal {
+    /// The signer.
+    pub participant: ParticipantIndex,
+
+    /// Nonce points for each input.
+    pub nonce_points: Vec<NoncePoints>,
+}
+
+/// Sent by the coordinator to each signer once every signer's nonces are known.
+///
+/// Signers should inspect `tx_prefix` (outputs, fee, tombstone block) before responding.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultisigChallenges {
+    /// The transaction being signed.
+    pub tx_prefix: TxPrefix,
+
+    /// Commitments of value equal to each real input.
+    pub pseudo_output_commitments: Vec<CompressedCommitment>,
+
+    /// Range proof of every pseudo-output and output.
+    pub range_proof_bytes: Vec<u8>,
+
+    /// The values from which each input's challenge is rebuilt.
+    pub transcripts: Vec<ChallengeTranscript>,
+
+    /// The challenge at the real index of each input's ring.
+    pub challenges: Vec<CurveScalar>,
+}
+
+/// A signer's third-round message: partial responses for each input.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultisigResponses {
+    /// The signer.
+    pub participant: ParticipantIndex,
+
+    /// A partial response for each input.
+    pub responses: Vec<PartialResponse>,
+}
+
+/// A participant's side of the signing protocol.
+pub struct MultisigSigner {
+    key_share: MultisigKeyShare,
+    signers: Vec<ParticipantIndex>,
+
+    /// Onetime public key of the real output spent by each input.
+    onetime_public_keys: Vec<RistrettoPublic>,
+
+    /// A fresh nonce for each input, consumed by `respond`.
+    nonces: Vec<MultisigNonce>,
+}
+
+impl MultisigSigner {
+    /// Joins a signing session, returning this signer's first-round message.
+    ///
+    /// # Arguments
+    /// * `key_share` - This participant's share of the spend private key.
+    /// * `request` - The coordinator's signing request.
+    /// * `rng` - Randomness.
+    pub fn new<R: RngCore + CryptoRng>(
+        key_share: MultisigKeyShare,
+        request: &MultisigSigningRequest,
+        rng: &mut R,
+    ) -> Result<(Self, MultisigCommitments), TxBuilderError> {
+        let participant = key_share.participant();
+        check_signers(&request.signers, key_share.threshold())?;
+        if !request.signers.contains(&participant) {
+            return Err(TxBuilderError::UnexpectedMultisigParticipant(participant));
+        }
+
+        let mut partial_key_images = Vec::new();
+        let mut nonces = Vec::new();
+        for onetime_public_key in &request.onetime_public_keys {
+            partial_key_images.push(PartialKeyImage::new(
+                participant,
+                key_share.secret_share(),
+                onetime_public_key,
+                rng,
+            ));
+            nonces.push(MultisigNonce::new(participant, onetime_public_key, rng));
+        }
+
+        let commitments = MultisigCommitments {
+            participant,
+            partial_key_images,
+            nonce_commitments: nonces.iter().map(MultisigNonce::commitment).collect(),
+        };
+
+        Ok((
+            Self {
+                key_share,
+                signers: request.signers.clone(),
+                onetime_public_keys: request.onetime_public_keys.clone(),
+                nonces,
+            },
+            commitments,
+        ))
+    }
+
+    /// This signer's second-round message, to be sent once every signer has committed.
+    pub fn reveal_nonces(&self) -> MultisigNonceReveal {
+        MultisigNonceReveal {
+            participant: self.key_share.participant(),
+            nonce_points: self.nonces.iter().map(|nonce| *nonce.points()).collect(),
+        }
+    }
+
+    /// This signer's third-round message.
+    ///
+    /// Each challenge is rebuilt from `challenges.tx_prefix` and its transcript, and nothing is
+    /// signed unless every challenge matches.
+    ///
+    /// # Arguments
+    /// * `challenges` - The coordinator's challenge for each input.
+    pub fn respond(
+        self,
+        challenges: &MultisigChallenges,
+    ) -> Result<MultisigResponses, TxBuilderError> {
+        let num_inputs = self.nonces.len();
+        if challenges.challenges.len() != num_inputs
+            || challenges.transcripts.len() != num_inputs
+            || challenges.pseudo_output_commitments.len() != num_inputs
+            || challenges.tx_prefix.inputs.len() != num_inputs
+        {
+            return Err(TxBuilderError::MultisigRoundIncomplete);
+        }
+
+        let message = challenges.tx_prefix.hash().0;
+        let extended_message = extend_message(
+            &message,
+            &challenges.pseudo_output_commitments,
+            &challenges.range_proof_bytes,
+        )
+        .map_err(TxBuilderError::Multisig)?;
+        for (i, transcript) in challenges.transcripts.iter().enumerate() {
+            let ring: Vec<(CompressedRistrettoPublic, CompressedCommitment)> =
+                challenges.tx_prefix.inputs[i]
+                    .ring
+                    .iter()
+                    .map(|tx_out| (tx_out.target_key, tx_out.amount.commitment))
+                    .collect();
+
+            // The real input must be the output this signer agreed to spend, and the nonces must
+            // be exactly those of this session's signers, including this signer's own.
+            let onetime_public_key = CompressedRistrettoPublic::from(&self.onetime_public_keys[i]);
+            let spends_agreed_output = ring
+                .get(transcript.real_index as usize)
+                .map_or(false, |(target_key, _)| *target_key == onetime_public_key);
+            let nonces_match = transcript.nonce_points.len() == self.signers.len()
+                && self.signers.iter().all(|signer| {
+                    transcript
+                        .nonce_points
+                        .iter()
+                        .filter(|points| points.participant == *signer)
+                        .count()
+                        == 1
+                })
+                && transcript.nonce_points.contains(self.nonces[i].points());
+            if !spends_agreed_output || !nonces_match {
+                return Err(TxBuilderError::InvalidMultisigChallenge(i));
+            }
+
+            let challenge = transcript
+                .challenge(
+                    &extended_message,
+                    &ring,
+                    &challenges.pseudo_output_commitments[i],
+                )
+                .map_err(TxBuilderError::Multisig)?;
+            if challenge != challenges.challenges[i] {
+                return Err(TxBuilderError::InvalidMultisigChallenge(i));
+            }
+        }
+
+        let key_share = &self.key_share;
+        let signers = &self.signers;
+        let responses = self
+            .nonces
+            .into_iter()
+            .zip(challenges.challenges.iter())
+            .map(|(nonce, challenge)| {
+                nonce
+                    .respond(challenge, key_share.secret_share(), signers)
+                    .map_err(TxBuilderError::Multisig)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(MultisigResponses {
+            participant: key_share.participant(),
+            responses,
+        })
+    }
+}
+
+/// Helper utility for building a transaction that spends outputs owned by a multisig account.
+pub struct MultisigTransactionBuilder {
+    account: MultisigAccount,
+    input_credentials: Vec<MultisigInputCredentials>,
+    outputs_and_shared_secrets: Vec<(TxOut, RistrettoPublic)>,
+    tombstone_block: u64,
+    pub fee: u64,
+}
+
+impl MultisigTransactionBuilder {
+    /// Initializes a new MultisigTransactionBuilder.
+    ///
+    /// # Arguments
+    /// * `account` - The multisig account that owns every input.
+    pub fn new(account: MultisigAccount) -> Self {
+        Self {
+            account,
+            input_credentials: Vec::new(),
+            outputs_and_shared_secrets: Vec::new(),
+            tombstone_block: u64::max_value(),
+            fee: BASE_FEE,
+        }
+    }
+
+    /// Add an Input to the transaction.
+    ///
+    /// # Arguments
+    /// * `input_credentials` - Credentials required to construct a ring signature for an input.
+    pub fn add_input(&mut self, input_credentials: MultisigInputCredentials) {
+        self.input_credentials.push(input_credentials);
+    }
+
+    /// Add an output to the transaction.
+    ///
+    /// # Arguments
+    /// * `value` - The value of this output, in picoMOB.
+    /// * `recipient` - The recipient's public address
+    /// * `recipient_fog_ingest_key` - The recipient's fog server's public key
+    /// * `rng` - RNG used to generate blinding for commitment
+    ///
+    pub fn add_output<RNG: CryptoRng + RngCore>(
+        &mut self,
+        value: u64,
+        recipient: &PublicAddress,
+        recipient_fog_ingest_key: Option<&RistrettoPublic>,
+        rng: &mut RNG,
+    ) -> Result<TxOut, TxBuilderError> {
+        let (tx_out, shared_secret) =
+            create_output(value, recipient, recipient_fog_ingest_key, rng)?;
+
+        self.outputs_and_shared_secrets
+            .push((tx_out.clone(), shared_secret));
+        Ok(tx_out)
+    }
+
+    /// Sets the tombstone block.
+    ///
+    /// # Arguments
+    /// * `tombstone_block` - Tombstone block number.
+    pub fn set_tombstone_block(&mut self, tombstone_block: u64) {
+        self.tombstone_block = tombstone_block;
+    }
+
+    /// Sets the transaction fee.
+    ///
+    /// # Arguments
+    /// * `fee` - Transaction fee, in picoMOB.
+    pub fn set_fee(&mut self, fee: u64) {
+        self.fee = fee;
+    }
+
+    /// Consume the builder and start a signing session with the given signers.
+    ///
+    /// # Arguments
+    /// * `signers` - At least `threshold` distinct participants.
+    pub fn begin_signing(
+        self,
+        signers: &[ParticipantIndex],
+    ) -> Result<MultisigCoordinator, TxBuilderError> {
+        if self.input_credentials.is_empty() {
+            return Err(TxBuilderError::NoInputs);
+        }
+        check_signers(signers, self.account.threshold())?;
+        for signer in signers {
+            self.account
+                .verification_share(*signer)
+                .map_err(TxBuilderError::Multisig)?;
+        }
+
+        let (tx_prefix, output_values_and_blindings) = build_tx_prefix(
+            self.input_credentials
+                .iter()
+                .map(|input| (input.ring.clone(), input.membership_proofs.clone()))
+                .collect(),
+            self.outputs_and_shared_secrets,
+            self.fee,
+            self.tombstone_block,
+        )?;
+
+        Ok(MultisigCoordinator {
+            account: self.account,
+            input_credentials: self.input_credentials,
+            tx_prefix,
+            output_values_and_blindings,
+            signers: signers.to_vec(),
+            commitments: Vec::new(),
+            nonce_reveals: Vec::new(),
+            session: None,
+            responses: Vec::new(),
+        })
+    }
+}
+
+/// The coordinator's side of the signing protocol.
+pub struct MultisigCoordinator {
+    account: MultisigAccount,
+    input_credentials: Vec<MultisigInputCredentials>,
+    tx_prefix: TxPrefix,
+    output_values_and_blindings: Vec<(u64, Scalar)>,
+    signers: Vec<ParticipantIndex>,
+    commitments: Vec<MultisigCommitments>,
+    nonce_reveals: Vec<MultisigNonceReveal>,
+
+    /// Pseudo-outputs, range proof, and per-ring state, once challenges have been issued.
+    session: Option<(UnsignedRctBulletproofs, Vec<MultisigRingSigner>)>,
+    responses: Vec<MultisigResponses>,
+}
+
+impl MultisigCoordinator {
+    /// The request to send to every signer.
+    pub fn signing_request(&self) -> Result<MultisigSigningRequest, TxBuilderError> {
+        Ok(MultisigSigningRequest {
+            signers: self.signers.clone(),
+            onetime_public_keys: self
+                .input_credentials
+                .iter()
+                .map(MultisigInputCredentials::onetime_public_key)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Adds a signer's first-round message.
+    pub fn add_commitments(
+        &mut self,
+        commitments: MultisigCommitments,
+    ) -> Result<(), TxBuilderError> {
+        let participant = commitments.participant;
+        self.check_new_participant(participant, self.commitments.iter().map(|c| c.participant))?;
+        if commitments.partial_key_images.len() != self.input_credentials.len()
+            || commitments.nonce_commitments.len() != self.input_credentials.len()
+        {
+            return Err(TxBuilderError::MultisigRoundIncomplete);
+        }
+        check_sender(
+            participant,
+            commitments
+                .partial_key_images
+                .iter()
+                .map(|partial| partial.participant)
+                .chain(commitments.nonce_commitments.iter().map(|c| c.participant)),
+        )?;
+        self.commitments.push(commitments);
+        Ok(())
+    }
+
+    /// Adds a signer's second-round message. Every signer's commitments must be added first.
+    pub fn add_nonces(&mut self, nonce_reveal: MultisigNonceReveal) -> Result<(), TxBuilderError> {
+        if self.commitments.len() != self.signers.len() {
+            return Err(TxBuilderError::MultisigRoundIncomplete);
+        }
+        let participant = nonce_reveal.participant;
+        self.check_new_participant(
+            participant,
+            self.nonce_reveals.iter().map(|n| n.participant),
+        )?;
+        if nonce_reveal.nonce_points.len() != self.input_credentials.len() {
+            return Err(TxBuilderError::MultisigRoundIncomplete);
+        }
+        check_sender(
+            participant,
+            nonce_reveal
+                .nonce_points
+                .iter()
+                .map(|points| points.participant),
+        )?;
+        self.nonce_reveals.push(nonce_reveal);
+        Ok(())
+    }
+
+    /// Checks every signer's partial key images and nonces, and computes the challenges to send
+    /// to every signer. Every signer's nonces must be added first.
+    pub fn challenges<RNG: CryptoRng + RngCore>(
+        &mut self,
+        rng: &mut RNG,
+    ) -> Result<MultisigChallenges, TxBuilderError> {
+        if self.session.is_some() || self.nonce_reveals.len() != self.signers.len() {
+            return Err(TxBuilderError::MultisigRoundIncomplete);
+        }
+
+        // Value and blinding of each real input.
+        let mut input_values_and_blindings: Vec<(u64, Scalar)> = Vec::new();
+        for input in &self.input_credentials {
+            let amount = &input.ring[input.real_index].amount;
+            let shared_secret = compute_shared_secret(
+                &input.real_output_public_key,
+                self.account.view_private_key(),
+            );
+            input_values_and_blindings.push(amount.get_value(&shared_secret)?);
+        }
+
+        let message = self.tx_prefix.hash().0;
+        let input_values: Vec<u64> = input_values_and_blindings
+            .iter()
+            .map(|(value, _)| *value)
+            .collect();
+        let unsigned = UnsignedRctBulletproofs::new(
+            &message,
+            &input_values,
+            &self.output_values_and_blindings,
+            self.fee(),
+            rng,
+        )?;
+
+        let mut ring_signers = Vec::new();
+        for (i, input) in self.input_credentials.iter().enumerate() {
+            let ring: Vec<(CompressedRistrettoPublic, CompressedCommitment)> = input
+                .ring
+                .iter()
+                .map(|tx_out| (tx_out.target_key, tx_out.amount.commitment))
+                .collect();
+            let onetime_private_key_offset = self
+                .account
+                .onetime_private_key_offset(&input.real_output_public_key, input.subaddress_index);
+            let partial_key_images: Vec<PartialKeyImage> = self
+                .commitments
+                .iter()
+                .map(|c| c.partial_key_images[i])
+                .collect();
+            let nonce_commitments: Vec<NonceCommitment> = self
+                .commitments
+                .iter()
+                .map(|c| c.nonce_commitments[i])
+                .collect();
+            let nonce_points: Vec<NoncePoints> = self
+                .nonce_reveals
+                .iter()
+                .map(|n| n.nonce_points[i])
+                .collect();
+            let (value, blinding) = input_values_and_blindings[i];
+
+            let ring_signer = MultisigRingSigner::new(
+                &unsigned.extended_message,
+                &ring,
+                input.real_index,
+                &onetime_private_key_offset,
+                self.account.verification_shares(),
+                self.account.threshold(),
+                &partial_key_images,
+                &nonce_commitments,
+                &nonce_points,
+                value,
+                &blinding,
+                &unsigned.pseudo_output_blindings[i],
+                rng,
+            )
+            .map_err(TxBuilderError::Multisig)?;
+            ring_signers.push(ring_signer);
+        }
+
+        let challenges = MultisigChallenges {
+            tx_prefix: self.tx_prefix.clone(),
+            pseudo_output_commitments: unsigned.pseudo_output_commitments.clone(),
+            range_proof_bytes: unsigned.range_proof_bytes.clone(),
+            transcripts: ring_signers
+                .iter()
+                .map(MultisigRingSigner::transcript)
+                .collect(),
+            challenges: ring_signers
+                .iter()
+                .map(MultisigRingSigner::challenge)
+                .collect(),
+        };
+        self.session = Some((unsigned, ring_signers));
+        Ok(challenges)
+    }
+
+    /// Adds a signer's third-round message.
+    pub fn add_responses(&mut self, responses: MultisigResponses) -> Result<(), TxBuilderError> {
+        if self.session.is_none() {
+            return Err(TxBuilderError::MultisigRoundIncomplete);
+        }
+        let participant = responses.participant;
+        self.check_new_participant(participant, self.responses.iter().map(|r| r.participant))?;
+        if responses.responses.len() != self.input_credentials.len() {
+            return Err(TxBuilderError::MultisigRoundIncomplete);
+        }
+        check_sender(
+            participant,
+            responses
+                .responses
+                .iter()
+                .map(|response| response.participant),
+        )?;
+        self.responses.push(responses);
+        Ok(())
+    }
+
+    /// Checks every signer's responses and returns the signed transaction.
+    pub fn build(self) -> Result<Tx, TxBuilderError> {
+        let (unsigned, ring_signers) = self
+            .session
+            .ok_or(TxBuilderError::MultisigRoundIncomplete)?;
+        if self.responses.len() != self.signers.len() {
+            return Err(TxBuilderError::MultisigRoundIncomplete);
+        }
+
+        let responses = &self.responses;
+        let ring_signatures = ring_signers
+            .into_iter()
+            .enumerate()
+            .map(|(i, ring_signer)| {
+                let partial_responses: Vec<PartialResponse> =
+                    responses.iter().map(|r| r.responses[i]).collect();
+                ring_signer
+                    .finalize(&partial_responses)
+                    .map_err(TxBuilderError::Multisig)
+            })
+            .collect::<Result<Vec<RingMLSAG>, _>>()?;
+
+        Ok(Tx {
+            prefix: self.tx_prefix,
+            signature: unsigned.finalize(ring_signatures),
+        })
+    }
+
+    /// The transaction fee, in picoMOB.
+    pub fn fee(&self) -> u64 {
+        self.tx_prefix.fee
+    }
+
+    /// Checks that `participant` is a signer who has not already sent a message this round.
+    fn check_new_participant(
+        &self,
+        participant: ParticipantIndex,
+        mut received: impl Iterator<Item = ParticipantIndex>,
+    ) -> Result<(), TxBuilderError> {
+        if !self.signers.contains(&participant) || received.any(|p| p == participant) {
+            return Err(TxBuilderError::UnexpectedMultisigParticipant(participant));
+        }
+        Ok(())
+    }
+}
+
+/// Every value in a signer's message must be attributed to the signer who sent it.
+fn check_sender(
+    sender: ParticipantIndex,
+    participants: impl Iterator<Item = ParticipantIndex>,
+) -> Result<(), TxBuilderError> {
+    for participant in participants {
+        if participant != sender {
+            return Err(TxBuilderError::UnexpectedMultisigParticipant(participant));
+        }
+    }
+    Ok(())
+}
+
+/// Signers must be distinct, and there must be at least `threshold` of them.
+fn check_signers(signers: &[ParticipantIndex], threshold: u32) -> Result<(), TxBuilderError> {
+    for (i, signer) in signers.iter().enumerate() {
+        if signers[..i].contains(signer) {
+            return Err(TxBuilderError::UnexpectedMultisigParticipant(*signer));
+        }
+    }
+    if (signers.len() as u32) < threshold {
+        return Err(TxBuilderError::InsufficientMultisigSigners(
+            signers.len() as u32,
+            threshold,
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod multisig_tests {
+    use super::*;
+    use mc_crypto_keys::RistrettoPrivate;
+    use mc_transaction_core::{
+        account_keys::AccountKey, get_tx_out_shared_secret, multisig_keys::keygen_in_process,
+        onetime_keys::view_key_matches_output, ring_signature::KeyImage,
+        validation::validate_signature,
+    };
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Creates a ring containing an output of `value` sent to `recipient`.
+    fn get_ring(
+        ring_size: usize,
+        recipient: &PublicAddress,
+        value: u64,
+        rng: &mut StdRng,
+    ) -> (Vec<TxOut>, usize) {
+        let mut ring: Vec<TxOut> = Vec::new();
+        for _i in 0..ring_size - 1 {
+            let address = AccountKey::random(rng).default_subaddress();
+            let (tx_out, _) = create_output(value, &address, None, rng).unwrap();
+            ring.push(tx_out);
+        }
+        let real_index = (rng.next_u64() % ring_size as u64) as usize;
+        let (tx_out, _) = create_output(value, recipient, None, rng).unwrap();
+        ring.insert(real_index, tx_out);
+        (ring, real_index)
+    }
+
+    /// Runs the first two signing rounds in-process, returning the coordinator's challenges.
+    fn challenge(
+        builder: MultisigTransactionBuilder,
+        key_shares: &[MultisigKeyShare],
+        signers: &[ParticipantIndex],
+        rng: &mut StdRng,
+    ) -> Result<(MultisigCoordinator, Vec<MultisigSigner>, MultisigChallenges), TxBuilderError>
+    {
+        let mut coordinator = builder.begin_signing(signers)?;
+        let request = coordinator.signing_request()?;
+
+        let mut multisig_signers = Vec::new();
+        for signer in signers {
+            let (multisig_signer, commitments) =
+                MultisigSigner::new(key_shares[*signer as usize - 1].clone(), &request, rng)?;
+            coordinator.add_commitments(commitments)?;
+            multisig_signers.push(multisig_signer);
+        }
+        for multisig_signer in &multisig_signers {
+            coordinator.add_nonces(multisig_signer.reveal_nonces())?;
+        }
+        let challenges = coordinator.challenges(rng)?;
+        Ok((coordinator, multisig_signers, challenges))
+    }
+
+    /// Runs every signing round in-process.
+    fn sign(
+        builder: MultisigTransactionBuilder,
+        key_shares: &[MultisigKeyShare],
+        signers: &[ParticipantIndex],
+        rng: &mut StdRng,
+    ) -> Result<Tx, TxBuilderError> {
+        let (mut coordinator, multisig_signers, challenges) =
+            challenge(builder, key_shares, signers, rng)?;
+        for multisig_signer in multisig_signers {
+            coordinator.add_responses(multisig_signer.respond(&challenges)?)?;
+        }
+        coordinator.build()
+    }
+
+    /// A builder spending one output of `value` owned by `account`.
+    fn builder_for(
+        account: &MultisigAccount,
+        value: u64,
+        rng: &mut StdRng,
+    ) -> MultisigTransactionBuilder {
+        let (ring, real_index) = get_ring(3, &account.default_subaddress(), value, rng);
+        let membership_proofs = vec![TxOutMembershipProof::default(); ring.len()];
+        let mut builder = MultisigTransactionBuilder::new(account.clone());
+        builder.add_input(
+            MultisigInputCredentials::new(ring, membership_proofs, real_index, 0, rng).unwrap(),
+        );
+        builder
+            .add_output(
+                value - BASE_FEE,
+                &AccountKey::random(rng).default_subaddress(),
+                None,
+                rng,
+            )
+            .unwrap();
+        builder
+    }
+
+    #[test]
+    // A 2-of-3 account should be able to spend two outputs with any two signers.
+    fn test_multisig_transaction() {
+        let mut rng: StdRng = SeedableRng::from_seed([21u8; 32]);
+        let key_shares = keygen_in_process(2, 3, &mut rng).unwrap();
+        let view_private_key = RistrettoPrivate::from_random(&mut rng);
+        let account = MultisigAccount::new(&view_private_key, &key_shares[0]);
+        let recipient = AccountKey::random(&mut rng);
+        let value = 1475;
+
+        for signers in &[[1u32, 2], [3, 1]] {
+            let mut builder = MultisigTransactionBuilder::new(account.clone());
+            let mut key_images = Vec::new();
+            for subaddress_index in &[0u64, 5] {
+                let (ring, real_index) =
+                    get_ring(3, &account.subaddress(*subaddress_index), value, &mut rng);
+                let membership_proofs = vec![TxOutMembershipProof::default(); ring.len()];
+
+                // The key image an unshared spend key would produce.
+                let b: Scalar = signers
+                    .iter()
+                    .map(|&j| {
+                        let lambda =
+                            mc_transaction_core::multisig_keys::lagrange_coefficient(j, signers)
+                                .unwrap();
+                        let s_j: &Scalar = key_shares[j as usize - 1].secret_share().as_ref();
+                        lambda * s_j
+                    })
+                    .sum();
+                let account_key = AccountKey::new(&RistrettoPrivate::from(b), &view_private_key);
+                let onetime_private_key =
+                    mc_transaction_core::onetime_keys::recover_onetime_private_key(
+                        &RistrettoPublic::try_from(&ring[real_index].public_key).unwrap(),
+                        &view_private_key,
+                        &account_key.subaddress_spend_private(*subaddress_index),
+                    );
+                key_images.push(KeyImage::from(&onetime_private_key));
+
+                builder.add_input(
+                    MultisigInputCredentials::new(
+                        ring,
+                        membership_proofs,
+                        real_index,
+                        *subaddress_index,
+                        &mut rng,
+                    )
+                    .unwrap(),
+                );
+            }
+            builder
+                .add_output(
+                    2 * value - BASE_FEE,
+                    &recipient.default_subaddress(),
+                    None,
+                    &mut rng,
+                )
+                .unwrap();
+
+            let tx = sign(builder, &key_shares, signers, &mut rng).unwrap();
+
+            assert_eq!(tx.key_images(), key_images);
+            assert!(validate_signature(&tx, &mut rng).is_ok());
+
+            let output = &tx.prefix.outputs[0];
+            assert!(view_key_matches_output(
+                &recipient.view_key(),
+                &RistrettoPublic::try_from(&output.target_key).unwrap(),
+                &RistrettoPublic::try_from(&output.public_key).unwrap()
+            ));
+            let public_key = RistrettoPublic::try_from(&output.public_key).unwrap();
+            let shared_secret = get_tx_out_shared_secret(recipient.view_private_key(), &public_key);
+            let (output_value, _blinding) = output.amount.get_value(&shared_secret).unwrap();
+            assert_eq!(output_value, 2 * value - BASE_FEE);
+        }
+    }
+
+    #[test]
+    // Signing should fail with fewer than `threshold` signers.
+    fn test_insufficient_signers() {
+        let mut rng: StdRng = SeedableRng::from_seed([22u8; 32]);
+        let key_shares = keygen_in_process(3, 3, &mut rng).unwrap();
+        let view_private_key = RistrettoPrivate::from_random(&mut rng);
+        let account = MultisigAccount::new(&view_private_key, &key_shares[0]);
+
+        let (ring, real_index) = get_ring(3, &account.default_subaddress(), 100, &mut rng);
+        let membership_proofs = vec![TxOutMembershipProof::default(); ring.len()];
+        let mut builder = MultisigTransactionBuilder::new(account);
+        builder.add_input(
+            MultisigInputCredentials::new(ring, membership_proofs, real_index, 0, &mut rng)
+                .unwrap(),
+        );
+
+        match builder.begin_signing(&[1, 2]) {
+            Err(TxBuilderError::InsufficientMultisigSigners(2, 3)) => {} // Expected.
+            Err(e) => panic!("Unexpected error {}", e),
+            Ok(_) => panic!("Unexpected success"),
+        }
+    }
+
+    #[test]
+    // A signer using the wrong key share should be identified.
+    fn test_invalid_key_share_is_identified() {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let key_shares = keygen_in_process(2, 2, &mut rng).unwrap();
+        let other_shares = keygen_in_process(2, 2, &mut rng).unwrap();
+        let view_private_key = RistrettoPrivate::from_random(&mut rng);
+        let account = MultisigAccount::new(&view_private_key, &key_shares[0]);
+        let builder = builder_for(&account, 100, &mut rng);
+
+        // Participant 2 uses a share from a different account.
+        let mixed_shares = vec![key_shares[0].clone(), other_shares[1].clone()];
+        match sign(builder, &mixed_shares, &[1, 2], &mut rng) {
+            Err(TxBuilderError::Multisig(
+                mc_transaction_core::ring_signature::Error::InvalidPartialKeyImage(2),
+            )) => {} // Expected.
+            Err(e) => panic!("Unexpected error {}", e),
+            Ok(_) => panic!("Unexpected success"),
+        }
+    }
+
+    #[test]
+    // A signer should refuse a challenge that does not match the transaction it is shown.
+    fn test_signer_rejects_mismatched_challenge() {
+        let mut rng: StdRng = SeedableRng::from_seed([24u8; 32]);
+        let key_shares = keygen_in_process(2, 2, &mut rng).unwrap();
+        let view_private_key = RistrettoPrivate::from_random(&mut rng);
+        let account = MultisigAccount::new(&view_private_key, &key_shares[0]);
+
+        let (_coordinator, multisig_signers, challenges) = challenge(
+            builder_for(&account, 100, &mut rng),
+            &key_shares,
+            &[1, 2],
+            &mut rng,
+        )
+        .unwrap();
+        let mut multisig_signers = multisig_signers.into_iter();
+
+        // The transaction shown to the signer differs from the one the challenge signs.
+        let mut tampered = challenges.clone();
+        tampered.tx_prefix.tombstone_block = 17;
+        match multisig_signers.next().unwrap().respond(&tampered) {
+            Err(TxBuilderError::InvalidMultisigChallenge(0)) => {} // Expected.
+            Err(e) => panic!("Unexpected error {}", e),
+            Ok(_) => panic!("Unexpected success"),
+        }
+
+        // The challenge differs from the one the transcript produces.
+        let mut tampered = challenges;
+        tampered.challenges[0] = CurveScalar::from(Scalar::one());
+        match multisig_signers.next().unwrap().respond(&tampered) {
+            Err(TxBuilderError::InvalidMultisigChallenge(0)) => {} // Expected.
+            Err(e) => panic!("Unexpected error {}", e),
+            Ok(_) => panic!("Unexpected success"),
+        }
+    }
+
+    #[test]
+    // A signer's message containing values attributed to another participant should be rejected.
+    fn test_coordinator_rejects_misattributed_values() {
+        let mut rng: StdRng = SeedableRng::from_seed([25u8; 32]);
+        let key_shares = keygen_in_process(2, 2, &mut rng).unwrap();
+        let view_private_key = RistrettoPrivate::from_random(&mut rng);
+        let account = MultisigAccount::new(&view_private_key, &key_shares[0]);
+
+        let mut coordinator = builder_for(&account, 100, &mut rng)
+            .begin_signing(&[1, 2])
+            .unwrap();
+        let request = coordinator.signing_request().unwrap();
+
+        // Participant 2 forwards participant 1's partial key images as its own.
+        let (_signer_1, commitments_1) =
+            MultisigSigner::new(key_shares[0].clone(), &request, &mut rng).unwrap();
+        let (_signer_2, mut commitments_2) =
+            MultisigSigner::new(key_shares[1].clone(), &request, &mut rng).unwrap();
+        commitments_2.partial_key_images = commitments_1.partial_key_images;
+
+        match coordinator.add_commitments(commitments_2) {
+            Err(TxBuilderError::UnexpectedMultisigParticipant(1)) => {} // Expected.
+            Err(e) => panic!("Unexpected error {}", e),
+            Ok(_) => panic!("Unexpected success"),
+        }
+    }
+}
@@ -9,13 +9,12 @@ use curve25519_dalek::scalar::Scalar;
 use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPrivate, RistrettoPublic};
 use mc_transaction_core::{
     account_keys::PublicAddress,
-    constants::BASE_FEE,
     encrypted_fog_hint::EncryptedFogHint,
     fog_hint::FogHint,
     onetime_keys::compute_shared_secret,
     ring_signature::SignatureRctBulletproofs,
-    tx::{Tx, TxIn, TxOut, TxPrefix},
-    CompressedCommitment,
+    tx::{Tx, TxIn, TxOut, TxOutMembershipProof, TxPrefix},
+    CompressedCommitment, NetworkParameters,
 };
 use mc_util_from_random::FromRandom;
 use rand_core::{CryptoRng, RngCore};
@@ -31,13 +30,21 @@ pub struct TransactionBuilder {
 }
 
 impl TransactionBuilder {
-    /// Initializes a new TransactionBuilder.
+    /// Initializes a new TransactionBuilder for a network that uses the default parameters.
     pub fn new() -> Self {
+        Self::new_with_parameters(&NetworkParameters::default())
+    }
+
+    /// Initializes a new TransactionBuilder whose fee defaults to the network's base fee.
+    ///
+    /// # Arguments
+    /// * `parameters` - Limits of the network the transaction is built for.
+    pub fn new_with_parameters(parameters: &NetworkParameters) -> Self {
         TransactionBuilder {
             input_credentials: Vec::new(),
             outputs_and_shared_secrets: Vec::new(),
             tombstone_block: u64::max_value(),
-            fee: BASE_FEE,
+            fee: parameters.base_fee,
         }
     }
 
@@ -89,52 +96,25 @@ impl TransactionBuilder {
     }
 
     /// Consume the builder and return the transaction.
-    pub fn build<RNG: CryptoRng + RngCore>(mut self, rng: &mut RNG) -> Result<Tx, TxBuilderError> {
+    pub fn build<RNG: CryptoRng + RngCore>(self, rng: &mut RNG) -> Result<Tx, TxBuilderError> {
         if self.input_credentials.is_empty() {
             return Err(TxBuilderError::NoInputs);
         }
 
-        // All inputs must have rings of the same size.
-        {
-            let ring_sizes: HashSet<usize> = self
-                .input_credentials
+        let (tx_prefix, output_values_and_blindings) = build_tx_prefix(
+            self.input_credentials
                 .iter()
-                .map(|input| input.ring.len())
-                .collect();
-            if ring_sizes.len() > 1 {
-                return Err(TxBuilderError::InvalidRingSize);
-            }
-        }
-
-        let inputs: Vec<TxIn> = self
-            .input_credentials
-            .iter()
-            .map(|input_credential| TxIn {
-                ring: input_credential.ring.clone(),
-                proofs: input_credential.membership_proofs.clone(),
-            })
-            .collect();
-
-        // Sort outputs by public key.
-        self.outputs_and_shared_secrets
-            .sort_by(|(a, _), (b, _)| a.public_key.cmp(&b.public_key));
-
-        let output_values_and_blindings: Vec<(u64, Scalar)> = self
-            .outputs_and_shared_secrets
-            .iter()
-            .map(|(tx_out, shared_secret)| {
-                let amount = &tx_out.amount;
-                let (value, blinding) = amount
-                    .get_value(shared_secret)
-                    .expect("TransactionBuilder created an invalid Amount");
-                (value, blinding)
-            })
-            .collect();
-
-        let (outputs, _shared_serets): (Vec<TxOut>, Vec<_>) =
-            self.outputs_and_shared_secrets.into_iter().unzip();
-
-        let tx_prefix = TxPrefix::new(inputs, outputs, self.fee, self.tombstone_block);
+                .map(|input_credential| {
+                    (
+                        input_credential.ring.clone(),
+                        input_credential.membership_proofs.clone(),
+                    )
+                })
+                .collect(),
+            self.outputs_and_shared_secrets,
+            self.fee,
+            self.tombstone_block,
+        )?;
 
         let mut rings: Vec<Vec<(CompressedRistrettoPublic, CompressedCommitment)>> = Vec::new();
         for input in &tx_prefix.inputs {
@@ -190,6 +170,58 @@ impl Default for TransactionBuilder {
     }
 }
 
+/// Creates the TxPrefix for a transaction, sorting its outputs by public key.
+///
+/// # Arguments
+/// * `rings_and_proofs` - The ring and membership proofs of each input.
+/// * `outputs_and_shared_secrets` - Each output and the shared secret used to create it.
+/// * `fee` - Transaction fee, in picoMOB.
+/// * `tombstone_block` - Tombstone block number.
+///
+/// Returns the prefix and the value and blinding of each output, in the prefix's order.
+pub(crate) fn build_tx_prefix(
+    rings_and_proofs: Vec<(Vec<TxOut>, Vec<TxOutMembershipProof>)>,
+    mut outputs_and_shared_secrets: Vec<(TxOut, RistrettoPublic)>,
+    fee: u64,
+    tombstone_block: u64,
+) -> Result<(TxPrefix, Vec<(u64, Scalar)>), TxBuilderError> {
+    // All inputs must have rings of the same size.
+    {
+        let ring_sizes: HashSet<usize> = rings_and_proofs
+            .iter()
+            .map(|(ring, _)| ring.len())
+            .collect();
+        if ring_sizes.len() > 1 {
+            return Err(TxBuilderError::InvalidRingSize);
+        }
+    }
+
+    let inputs: Vec<TxIn> = rings_and_proofs
+        .into_iter()
+        .map(|(ring, proofs)| TxIn { ring, proofs })
+        .collect();
+
+    // Sort outputs by public key.
+    outputs_and_shared_secrets.sort_by(|(a, _), (b, _)| a.public_key.cmp(&b.public_key));
+
+    let output_values_and_blindings: Vec<(u64, Scalar)> = outputs_and_shared_secrets
+        .iter()
+        .map(|(tx_out, shared_secret)| {
+            let amount = &tx_out.amount;
+            let (value, blinding) = amount
+                .get_value(shared_secret)
+                .expect("TransactionBuilder created an invalid Amount");
+            (value, blinding)
+        })
+        .collect();
+
+    let (outputs, _shared_serets): (Vec<TxOut>, Vec<_>) =
+        outputs_and_shared_secrets.into_iter().unzip();
+
+    let tx_prefix = TxPrefix::new(inputs, outputs, fee, tombstone_block);
+    Ok((tx_prefix, output_values_and_blindings))
+}
+
 /// Creates a TxOut that sends `value` to `recipient`.
 ///
 /// # Arguments
@@ -197,7 +229,7 @@ impl Default for TransactionBuilder {
 /// * `recipient` - Recipient's address.
 /// * `ingest_pubkey` - The public key for the recipients fog server, if any
 /// * `rng` -
-fn create_output<RNG: CryptoRng + RngCore>(
+pub(crate) fn create_output<RNG: CryptoRng + RngCore>(
     value: u64,
     recipient: &PublicAddress,
     ingest_pubkey: Option<&RistrettoPublic>,
@@ -237,7 +269,7 @@ pub mod transaction_builder_tests {
     use super::*;
     use mc_transaction_core::{
         account_keys::{AccountKey, DEFAULT_SUBADDRESS_INDEX},
-        constants::{MAX_INPUTS, MAX_OUTPUTS},
+        constants::{BASE_FEE, MAX_INPUTS, MAX_OUTPUTS},
         get_tx_out_shared_secret,
         onetime_keys::*,
         ring_signature::KeyImage,
@@ -1,5 +1,7 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
+use mc_transaction_core::{BlockVersionSchedule, NetworkParameters};
+use mc_util_parse::{parse_block_version_schedule_from_json, parse_network_parameters_from_json};
 use std::path::PathBuf;
 use structopt::StructOpt;
 
@@ -16,6 +18,15 @@ struct Config {
     /// Key images per transaction
     #[structopt(long = "key-images", short = "k", default_value = "0")]
     pub num_key_images: usize,
+
+    /// Network parameters to commit to in the origin block, represented in JSON.
+    /// Omitted values take their defaults.
+    #[structopt(long, parse(try_from_str=parse_network_parameters_from_json))]
+    pub network_parameters: Option<NetworkParameters>,
+
+    /// Block version schedule to commit to in the origin block, represented in JSON.
+    #[structopt(long, parse(try_from_str=parse_block_version_schedule_from_json))]
+    pub block_version_schedule: Option<BlockVersionSchedule>,
 }
 
 fn main() {
@@ -33,5 +44,7 @@ fn main() {
         config.num_txs,
         config.num_blocks,
         config.num_key_images,
+        &config.network_parameters.unwrap_or_default(),
+        &config.block_version_schedule.unwrap_or_default(),
     );
 }
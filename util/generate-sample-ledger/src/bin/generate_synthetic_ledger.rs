@@ -0,0 +1,114 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Generates a ledger of valid transactions between the accounts in a keys directory, and writes
+//! the ground truth of each transaction to a JSON file.
+
+use mc_ledger_db::LedgerDB;
+use mc_transaction_core::{account_keys::AccountKey, NetworkParameters};
+use mc_util_generate_sample_ledger::{populate_synthetic_ledger, SyntheticLedgerConfig};
+use mc_util_parse::parse_network_parameters_from_json;
+use rand::SeedableRng;
+use rand_hc::Hc128Rng as FixedRng;
+use std::{fs, path::PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+struct Config {
+    /// Directory containing the root entropies of the accounts, as written by `sample-keys`.
+    #[structopt(long, default_value = "keys", parse(from_os_str))]
+    pub keys: PathBuf,
+
+    /// Directory to create the ledger in.
+    #[structopt(long, default_value = "ledger", parse(from_os_str))]
+    pub ledger: PathBuf,
+
+    /// File to write the ground truth to, as JSON.
+    #[structopt(long, default_value = "ground_truth.json", parse(from_os_str))]
+    pub ground_truth: PathBuf,
+
+    /// Number of outputs each account receives in the origin block.
+    #[structopt(long, default_value = "16")]
+    pub origin_outputs: usize,
+
+    /// Number of blocks of transactions after the origin block.
+    #[structopt(long, default_value = "10")]
+    pub blocks: usize,
+
+    /// Number of transactions per block.
+    #[structopt(long, default_value = "10")]
+    pub txs_per_block: usize,
+
+    /// Maximal number of inputs per transaction.
+    #[structopt(long, default_value = "4")]
+    pub max_inputs: usize,
+
+    /// Maximal number of recipients per transaction, not counting change.
+    #[structopt(long, default_value = "3")]
+    pub max_recipients: usize,
+
+    /// Fees are the base fee times a random multiplier of at most this.
+    #[structopt(long, default_value = "4")]
+    pub max_fee_multiplier: u64,
+
+    /// Seed of the generator. The same seed and keys produce the same ledger.
+    #[structopt(long, default_value = "0")]
+    pub seed: u64,
+
+    /// Network parameters to commit to in the origin block, represented in JSON.
+    /// Omitted values take their defaults.
+    #[structopt(long, parse(try_from_str=parse_network_parameters_from_json))]
+    pub network_parameters: Option<NetworkParameters>,
+}
+
+fn main() {
+    let config = Config::from_args();
+
+    let accounts: Vec<AccountKey> =
+        mc_util_keyfile::keygen::read_default_root_entropies(&config.keys)
+            .expect("Could not read root entropies")
+            .iter()
+            .map(AccountKey::from)
+            .collect();
+    assert!(
+        accounts.len() >= 2,
+        "At least two accounts are needed to transact"
+    );
+
+    fs::create_dir_all(&config.ledger).expect("Could not create ledger dir");
+    LedgerDB::create(config.ledger.clone()).expect("Could not create ledger_db");
+    let mut ledger = LedgerDB::open(config.ledger.clone()).expect("Could not open ledger_db");
+
+    let mut seed = [0u8; 32];
+    seed[..8].copy_from_slice(&config.seed.to_le_bytes());
+    let mut rng: FixedRng = SeedableRng::from_seed(seed);
+
+    let synthetic_config = SyntheticLedgerConfig {
+        origin_outputs_per_account: config.origin_outputs,
+        num_blocks: config.blocks,
+        txs_per_block: config.txs_per_block,
+        max_inputs: config.max_inputs,
+        max_recipients: config.max_recipients,
+        max_fee_multiplier: config.max_fee_multiplier,
+    };
+    let summaries = populate_synthetic_ledger(
+        &mut ledger,
+        &accounts,
+        &synthetic_config,
+        &config.network_parameters.unwrap_or_default(),
+        &mut rng,
+    );
+
+    let num_txs: usize = summaries
+        .iter()
+        .map(|summary| summary.transactions.len())
+        .sum();
+    println!(
+        "Wrote {} blocks of {} transactions to {:?}.",
+        summaries.len(),
+        num_txs,
+        config.ledger
+    );
+
+    let json = serde_json::to_string_pretty(&summaries).expect("Could not serialize ground truth");
+    fs::write(&config.ground_truth, json).expect("Could not write ground truth");
+}
@@ -1,10 +1,18 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
+mod synthetic;
+
+pub use synthetic::{
+    populate_synthetic_ledger, BlockSummary, InputSummary, OutputSummary, SyntheticLedgerConfig,
+    TxSummary,
+};
+
 use mc_crypto_keys::RistrettoPrivate;
 use mc_ledger_db::{Ledger, LedgerDB};
 use mc_transaction_core::{
     account_keys::PublicAddress, constants::TOTAL_MOB, encrypted_fog_hint::EncryptedFogHint,
-    ring_signature::KeyImage, tx::TxOut, Block, BlockContents, BLOCK_VERSION,
+    ring_signature::KeyImage, tx::TxOut, Block, BlockContents, BlockVersionSchedule, Feature,
+    NetworkParameters,
 };
 use mc_util_from_random::FromRandom;
 use rand::{RngCore, SeedableRng};
@@ -21,6 +29,8 @@ use std::{path::PathBuf, vec::Vec};
 /// * `num_outputs_per_recipient` - Number of equal-valued outputs that each recipient receives, per block.
 /// * `num_blocks` - Number of blocks that will be created.
 /// * `key_images_per_block` - Number of randomly generated key images per block.
+/// * `network_parameters` - Parameters committed to by the origin block.
+/// * `block_version_schedule` - Block version schedule committed to by the origin block.
 ///
 /// This will panic if it attempts to distribute the total value of mobilecoin into fewer than 16 outputs.
 pub fn bootstrap_ledger(
@@ -29,6 +39,8 @@ pub fn bootstrap_ledger(
     outputs_per_recipient_per_block: usize,
     num_blocks: usize,
     key_images_per_block: usize,
+    network_parameters: &NetworkParameters,
+    block_version_schedule: &BlockVersionSchedule,
 ) {
     // Create the DB
     std::fs::create_dir_all(path.clone()).expect("Could not create ledger dir");
@@ -63,13 +75,21 @@ pub fn bootstrap_ledger(
             .map(|_i| KeyImage::from(rng.next_u64()))
             .collect();
 
-        let block_contents = BlockContents::new(key_images, outputs.clone());
+        let mut block_contents = BlockContents::new(key_images, outputs.clone());
 
         let block = match previous_block {
             Some(parent) => {
-                Block::new_with_parent(BLOCK_VERSION, &parent, &Default::default(), &block_contents)
+                let version = block_version_schedule.version_at(block_index);
+                if Feature::SortedBlockContents.is_enabled(version) {
+                    block_contents.sort();
+                }
+                Block::new_with_parent(version, &parent, &Default::default(), &block_contents)
             }
-            None => Block::new_origin_block(&outputs),
+            None => Block::new_origin_block_with_schedule(
+                &outputs,
+                network_parameters,
+                block_version_schedule,
+            ),
         };
         previous_block = Some(block.clone());
         blocks_and_contents.push((block, block_contents));
@@ -0,0 +1,655 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Generates ledgers of fully valid transactions between a population of accounts, for benchmarks
+//! and privacy analysis.
+//!
+//! Blocks are formed the way consensus forms them: the outputs and key images of their
+//! transactions are merged and sorted, and an output collecting the fees is added. Alongside the
+//! ledger, the ground truth of every transaction is recorded: its sender, its real inputs, and
+//! the recipient and value of each of its outputs.
+
+use core::convert::TryFrom;
+use mc_crypto_keys::{RistrettoPrivate, RistrettoPublic};
+use mc_ledger_db::{validate_tx, Ledger};
+use mc_transaction_core::{
+    account_keys::{AccountKey, PublicAddress, DEFAULT_SUBADDRESS_INDEX},
+    constants::{FEE_SPEND_PUBLIC_KEY, FEE_VIEW_PUBLIC_KEY, TOTAL_MOB},
+    encrypted_fog_hint::EncryptedFogHint,
+    onetime_keys::recover_onetime_private_key,
+    tx::{Tx, TxOut},
+    Block, BlockContents, BlockIndex, BlockVersionSchedule, NetworkParameters, BLOCK_VERSION,
+};
+use mc_transaction_std::{InputCredentials, TransactionBuilder};
+use mc_util_from_random::FromRandom;
+use rand::{seq::SliceRandom, CryptoRng, Rng, RngCore};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Shape of a synthetic ledger.
+#[derive(Clone, Debug)]
+pub struct SyntheticLedgerConfig {
+    /// Number of outputs each account receives in the origin block.
+    pub origin_outputs_per_account: usize,
+
+    /// Number of blocks of transactions after the origin block.
+    pub num_blocks: usize,
+
+    /// Number of transactions in each block.
+    pub txs_per_block: usize,
+
+    /// Maximal number of inputs of a transaction. Each transaction has between one and this many.
+    pub max_inputs: usize,
+
+    /// Maximal number of accounts a transaction pays, not counting change to the sender. Each
+    /// transaction pays between one and this many.
+    pub max_recipients: usize,
+
+    /// Each fee is the base fee times a random multiplier between one and this.
+    pub max_fee_multiplier: u64,
+}
+
+impl Default for SyntheticLedgerConfig {
+    fn default() -> Self {
+        Self {
+            origin_outputs_per_account: 16,
+            num_blocks: 10,
+            txs_per_block: 10,
+            max_inputs: 4,
+            max_recipients: 3,
+            max_fee_multiplier: 4,
+        }
+    }
+}
+
+/// Ground truth about a generated block.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct BlockSummary {
+    pub block_index: BlockIndex,
+
+    /// Hex-encoded block ID.
+    pub block_id: String,
+
+    pub transactions: Vec<TxSummary>,
+
+    /// Sum of the fees of the transactions.
+    pub total_fee: u64,
+
+    /// Index in the ledger of the output collecting the fees.
+    pub fee_output_index: u64,
+}
+
+/// Ground truth about a generated transaction.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct TxSummary {
+    /// Hex-encoded transaction hash.
+    pub tx_hash: String,
+
+    /// Index of the sending account.
+    pub sender: usize,
+
+    pub fee: u64,
+
+    pub tombstone_block: BlockIndex,
+
+    /// Inputs, in the order of the transaction's key images.
+    pub inputs: Vec<InputSummary>,
+
+    pub outputs: Vec<OutputSummary>,
+}
+
+/// Ground truth about an input of a generated transaction.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct InputSummary {
+    /// Hex-encoded key image.
+    pub key_image: String,
+
+    /// Index in the ledger of the output actually spent.
+    pub real_index: u64,
+
+    /// Indices in the ledger of the members of the ring, in the order of the transaction.
+    pub ring: Vec<u64>,
+
+    pub value: u64,
+}
+
+/// Ground truth about an output of a generated transaction.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct OutputSummary {
+    /// Index of the output in the ledger.
+    pub index: u64,
+
+    /// Index of the receiving account.
+    pub recipient: usize,
+
+    pub value: u64,
+
+    /// True if the output returns change to the sender.
+    pub is_change: bool,
+}
+
+/// An unspent output owned by one of the accounts.
+#[derive(Clone, Debug)]
+struct OwnedTxOut {
+    tx_out: TxOut,
+    index: u64,
+    value: u64,
+}
+
+/// An output created by a transaction that is not in the ledger yet.
+struct PendingOutput {
+    tx_out: TxOut,
+    recipient: usize,
+    value: u64,
+    is_change: bool,
+}
+
+/// A transaction of the block being formed, and what is needed to complete its ground truth once
+/// the block is in the ledger.
+struct PendingTx {
+    tx: Tx,
+    sender: usize,
+    inputs: Vec<OwnedTxOut>,
+    outputs: Vec<PendingOutput>,
+}
+
+/// Writes an origin block and blocks of transactions to an empty ledger, and returns the ground
+/// truth of each block after the origin block.
+///
+/// The origin block distributes the total value of MobileCoin equally between the outputs it
+/// gives each account, or as much of it as keeps the sum of all values in a u64. Generation stops
+/// early if no account can afford a transaction anymore.
+///
+/// The result only depends on the arguments, so seeding `rng` identically reproduces a ledger.
+///
+/// # Arguments
+/// * `ledger` - An empty ledger.
+/// * `accounts` - The population of accounts that transact with each other.
+/// * `config` - Shape of the ledger.
+/// * `network_parameters` - Parameters committed to by the origin block, that every transaction
+///   satisfies.
+/// * `rng` - Randomness.
+pub fn populate_synthetic_ledger<L: Ledger, R: RngCore + CryptoRng>(
+    ledger: &mut L,
+    accounts: &[AccountKey],
+    config: &SyntheticLedgerConfig,
+    network_parameters: &NetworkParameters,
+    rng: &mut R,
+) -> Vec<BlockSummary> {
+    assert_eq!(ledger.num_blocks().expect("Failed reading ledger"), 0);
+    let num_origin_outputs = accounts.len() * config.origin_outputs_per_account;
+    assert!(
+        num_origin_outputs >= network_parameters.ring_size,
+        "The origin block must contain at least one ring of outputs"
+    );
+
+    let mut unspent = write_origin_block(
+        ledger,
+        accounts,
+        config.origin_outputs_per_account,
+        network_parameters,
+        rng,
+    );
+
+    let max_inputs = config
+        .max_inputs
+        .min(network_parameters.max_inputs as usize)
+        .max(1);
+    // One output is reserved for change.
+    let max_recipients = config
+        .max_recipients
+        .min(network_parameters.max_outputs as usize - 1)
+        .max(1);
+    let txs_per_block = config
+        .txs_per_block
+        .min(network_parameters.max_transactions_per_block);
+
+    let mut summaries = Vec::new();
+    for _ in 0..config.num_blocks {
+        let block_index = ledger.num_blocks().expect("Failed reading ledger");
+        let num_txos = ledger.num_txos().expect("Failed reading ledger");
+        // Rings of a transaction must not share members.
+        let max_inputs = max_inputs.min((num_txos as usize / network_parameters.ring_size).max(1));
+
+        let mut pending_txs = Vec::new();
+        for _ in 0..txs_per_block {
+            match create_transaction(
+                ledger,
+                accounts,
+                &mut unspent,
+                max_inputs,
+                max_recipients,
+                config.max_fee_multiplier.max(1),
+                block_index,
+                network_parameters,
+                rng,
+            ) {
+                Some(pending_tx) => pending_txs.push(pending_tx),
+                None => break,
+            }
+        }
+        if pending_txs.is_empty() {
+            break;
+        }
+
+        summaries.push(write_block(ledger, pending_txs, &mut unspent, rng));
+    }
+    summaries
+}
+
+/// Writes the origin block, and returns the outputs it gives each account.
+fn write_origin_block<L: Ledger, R: RngCore + CryptoRng>(
+    ledger: &mut L,
+    accounts: &[AccountKey],
+    outputs_per_account: usize,
+    network_parameters: &NetworkParameters,
+    rng: &mut R,
+) -> Vec<Vec<OwnedTxOut>> {
+    let num_outputs = (accounts.len() * outputs_per_account) as u64;
+    // Any set of outputs can be spent together without overflowing their sum.
+    let picomob = u128::from(TOTAL_MOB) * 1_000_000_000_000;
+    let value = (picomob.min(u128::from(u64::MAX)) / u128::from(num_outputs)) as u64;
+
+    let mut outputs_and_owners = Vec::new();
+    for (owner, account) in accounts.iter().enumerate() {
+        for _ in 0..outputs_per_account {
+            let tx_out = TxOut::new(
+                value,
+                &account.default_subaddress(),
+                &RistrettoPrivate::from_random(rng),
+                EncryptedFogHint::fake_onetime_hint(rng),
+                rng,
+            )
+            .expect("Failed creating output");
+            outputs_and_owners.push((tx_out, owner));
+        }
+    }
+    outputs_and_owners.sort_by(|(a, _), (b, _)| a.public_key.cmp(&b.public_key));
+
+    let outputs: Vec<TxOut> = outputs_and_owners
+        .iter()
+        .map(|(tx_out, _owner)| tx_out.clone())
+        .collect();
+    let block = Block::new_origin_block_with_parameters(&outputs, network_parameters);
+    let block_contents = BlockContents::new(Vec::new(), outputs);
+    ledger
+        .append_block(&block, &block_contents, None)
+        .expect("Failed appending origin block");
+
+    let mut unspent = vec![Vec::new(); accounts.len()];
+    for (index, (tx_out, owner)) in outputs_and_owners.into_iter().enumerate() {
+        unspent[owner].push(OwnedTxOut {
+            tx_out,
+            index: index as u64,
+            value,
+        });
+    }
+    unspent
+}
+
+/// Creates a transaction from a random account to other random accounts. The outputs it spends
+/// are removed from `unspent`.
+///
+/// Returns None if no account can afford a transaction.
+#[allow(clippy::too_many_arguments)]
+fn create_transaction<L: Ledger, R: RngCore + CryptoRng>(
+    ledger: &L,
+    accounts: &[AccountKey],
+    unspent: &mut [Vec<OwnedTxOut>],
+    max_inputs: usize,
+    max_recipients: usize,
+    max_fee_multiplier: u64,
+    block_index: BlockIndex,
+    network_parameters: &NetworkParameters,
+    rng: &mut R,
+) -> Option<PendingTx> {
+    let fee = network_parameters.base_fee * rng.gen_range(1, max_fee_multiplier + 1);
+
+    // Senders whose outputs, up to the maximal number of inputs, cover the fee and a payment.
+    let can_afford = |outputs: &Vec<OwnedTxOut>| {
+        let mut values: Vec<u64> = outputs.iter().map(|owned| owned.value).collect();
+        values.sort_unstable_by(|a, b| b.cmp(a));
+        values.iter().take(max_inputs).sum::<u64>() > fee
+    };
+    let senders: Vec<usize> = (0..accounts.len())
+        .filter(|sender| can_afford(&unspent[*sender]))
+        .collect();
+    let sender = *senders.choose(rng)?;
+
+    // Spend random outputs, adding the largest remaining ones if they do not cover the fee.
+    let num_inputs = rng.gen_range(1, max_inputs.min(unspent[sender].len()) + 1);
+    unspent[sender].shuffle(rng);
+    let mut inputs: Vec<OwnedTxOut> = unspent[sender].drain(..num_inputs).collect();
+    unspent[sender].sort_by_key(|owned| owned.value);
+    while inputs.iter().map(|owned| owned.value).sum::<u64>() <= fee {
+        if inputs.len() == max_inputs {
+            // Swap the smallest input for the largest remaining output.
+            inputs.sort_by_key(|owned| owned.value);
+            let smallest = inputs.remove(0);
+            unspent[sender].push(smallest);
+            unspent[sender].sort_by_key(|owned| owned.value);
+        }
+        let largest = unspent[sender]
+            .pop()
+            .expect("sender can afford a transaction");
+        inputs.push(largest);
+    }
+    let total_input_value: u64 = inputs.iter().map(|owned| owned.value).sum();
+
+    // Pay a random part of the available value to other accounts, and return the rest as change.
+    let available = total_input_value - fee;
+    let payment = rng.gen_range(1, available + 1);
+    let change = available - payment;
+    let mut others: Vec<usize> = (0..accounts.len())
+        .filter(|account| *account != sender)
+        .collect();
+    if others.is_empty() {
+        others.push(sender);
+    }
+    let num_recipients = rng
+        .gen_range(1, max_recipients.min(others.len()) + 1)
+        .min(payment as usize);
+    others.shuffle(rng);
+
+    let mut builder = TransactionBuilder::new_with_parameters(network_parameters);
+    builder.set_fee(fee);
+    builder.set_tombstone_block(block_index + network_parameters.max_tombstone_blocks);
+
+    let mut ring_members: BTreeSet<u64> = inputs.iter().map(|owned| owned.index).collect();
+    let num_txos = ledger.num_txos().expect("Failed reading ledger");
+    let sender_account = &accounts[sender];
+    for input in &inputs {
+        let mut ring_indexes = vec![input.index];
+        while ring_indexes.len() < network_parameters.ring_size {
+            let index = rng.gen_range(0, num_txos);
+            if ring_members.insert(index) {
+                ring_indexes.push(index);
+            }
+        }
+        let ring: Vec<TxOut> = ring_indexes
+            .iter()
+            .map(|index| {
+                ledger
+                    .get_tx_out_by_index(*index)
+                    .expect("Failed reading ledger")
+            })
+            .collect();
+        let membership_proofs = ledger
+            .get_tx_out_proof_of_memberships(&ring_indexes)
+            .expect("Failed reading ledger");
+
+        let public_key =
+            RistrettoPublic::try_from(&input.tx_out.public_key).expect("Invalid public key");
+        let onetime_private_key = recover_onetime_private_key(
+            &public_key,
+            sender_account.view_private_key(),
+            &sender_account.subaddress_spend_private(DEFAULT_SUBADDRESS_INDEX),
+        );
+        let input_credentials = InputCredentials::new(
+            ring,
+            membership_proofs,
+            0,
+            onetime_private_key,
+            *sender_account.view_private_key(),
+            rng,
+        )
+        .expect("Failed creating input credentials");
+        builder.add_input(input_credentials);
+    }
+
+    let mut outputs = Vec::new();
+    let mut add_output = |builder: &mut TransactionBuilder,
+                          recipient: usize,
+                          value: u64,
+                          is_change: bool,
+                          rng: &mut R| {
+        let tx_out = builder
+            .add_output(value, &accounts[recipient].default_subaddress(), None, rng)
+            .expect("Failed adding output");
+        outputs.push(PendingOutput {
+            tx_out,
+            recipient,
+            value,
+            is_change,
+        });
+    };
+    for (i, recipient) in others.iter().take(num_recipients).enumerate() {
+        // The first recipient also receives the remainder of the division.
+        let mut value = payment / num_recipients as u64;
+        if i == 0 {
+            value += payment % num_recipients as u64;
+        }
+        add_output(&mut builder, *recipient, value, false, rng);
+    }
+    if change > 0 {
+        add_output(&mut builder, sender, change, true, rng);
+    }
+
+    let tx = builder.build(rng).expect("Failed building transaction");
+    let errors = validate_tx(
+        ledger,
+        &tx,
+        network_parameters,
+        &BlockVersionSchedule::default(),
+        rng,
+    )
+    .expect("Failed reading ledger");
+    assert!(errors.is_empty(), "Invalid transaction: {:?}", errors);
+
+    Some(PendingTx {
+        tx,
+        sender,
+        inputs,
+        outputs,
+    })
+}
+
+/// Forms a block from transactions and appends it, then records the outputs it creates as
+/// unspent.
+fn write_block<L: Ledger, R: RngCore + CryptoRng>(
+    ledger: &mut L,
+    pending_txs: Vec<PendingTx>,
+    unspent: &mut [Vec<OwnedTxOut>],
+    rng: &mut R,
+) -> BlockSummary {
+    let num_blocks = ledger.num_blocks().expect("Failed reading ledger");
+    let parent = ledger
+        .get_block(num_blocks - 1)
+        .expect("Failed reading ledger");
+    let root_element = ledger
+        .get_tx_out_proof_of_memberships(&[0])
+        .expect("Failed reading ledger")[0]
+        .elements
+        .last()
+        .cloned()
+        .expect("Membership proofs end with the root element");
+
+    let total_fee: u64 = pending_txs
+        .iter()
+        .map(|pending| pending.tx.prefix.fee)
+        .sum();
+    let fee_recipient = PublicAddress::new(
+        &RistrettoPublic::try_from(&FEE_SPEND_PUBLIC_KEY).expect("Invalid fee key"),
+        &RistrettoPublic::try_from(&FEE_VIEW_PUBLIC_KEY).expect("Invalid fee key"),
+    );
+    let fee_output = TxOut::new(
+        total_fee,
+        &fee_recipient,
+        &RistrettoPrivate::from_random(rng),
+        Default::default(),
+        rng,
+    )
+    .expect("Failed creating fee output");
+
+    let mut key_images = Vec::new();
+    let mut outputs = Vec::new();
+    for pending in &pending_txs {
+        key_images.extend(pending.tx.key_images());
+        outputs.extend(pending.tx.prefix.outputs.iter().cloned());
+    }
+    outputs.push(fee_output.clone());
+    let mut block_contents = BlockContents::new(key_images, outputs);
+    block_contents.sort();
+
+    let block = Block::new_with_parent(BLOCK_VERSION, &parent, &root_element, &block_contents);
+    ledger
+        .append_block(&block, &block_contents, None)
+        .expect("Failed appending block");
+
+    let index_of = |tx_out: &TxOut| {
+        ledger
+            .get_tx_out_index_by_hash(&tx_out.hash())
+            .expect("Failed reading ledger")
+    };
+
+    let mut transactions = Vec::new();
+    for pending in pending_txs {
+        let inputs = pending
+            .tx
+            .key_images()
+            .iter()
+            .zip(&pending.tx.prefix.inputs)
+            .map(|(key_image, tx_in)| {
+                let ring: Vec<u64> = tx_in.ring.iter().map(index_of).collect();
+                let real = pending
+                    .inputs
+                    .iter()
+                    .find(|owned| ring.contains(&owned.index))
+                    .expect("Each ring contains a real input");
+                InputSummary {
+                    key_image: hex::encode(key_image.as_bytes()),
+                    real_index: real.index,
+                    ring,
+                    value: real.value,
+                }
+            })
+            .collect();
+
+        let mut outputs = Vec::new();
+        for output in pending.outputs {
+            let index = index_of(&output.tx_out);
+            outputs.push(OutputSummary {
+                index,
+                recipient: output.recipient,
+                value: output.value,
+                is_change: output.is_change,
+            });
+            unspent[output.recipient].push(OwnedTxOut {
+                tx_out: output.tx_out,
+                index,
+                value: output.value,
+            });
+        }
+
+        transactions.push(TxSummary {
+            tx_hash: hex::encode(pending.tx.tx_hash().as_bytes()),
+            sender: pending.sender,
+            fee: pending.tx.prefix.fee,
+            tombstone_block: pending.tx.prefix.tombstone_block,
+            inputs,
+            outputs,
+        });
+    }
+
+    BlockSummary {
+        block_index: block.index,
+        block_id: hex::encode(block.id.0.as_slice()),
+        transactions,
+        total_fee,
+        fee_output_index: index_of(&fee_output),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_ledger_db::LedgerDB;
+    use mc_transaction_core::get_tx_out_shared_secret;
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+
+    fn generate(
+        accounts: &[AccountKey],
+        config: &SyntheticLedgerConfig,
+    ) -> (TempDir, LedgerDB, Vec<BlockSummary>) {
+        let dir = TempDir::new("synthetic_ledger").unwrap();
+        let path = dir.path().to_path_buf();
+        LedgerDB::create(path.clone()).unwrap();
+        let mut ledger = LedgerDB::open(path).unwrap();
+
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let summaries = populate_synthetic_ledger(
+            &mut ledger,
+            accounts,
+            config,
+            &NetworkParameters::default(),
+            &mut rng,
+        );
+        (dir, ledger, summaries)
+    }
+
+    #[test]
+    // The ground truth should match the ledger, and be reproducible from the seed.
+    fn test_populate_synthetic_ledger() {
+        let mut rng: StdRng = SeedableRng::from_seed([2u8; 32]);
+        let accounts: Vec<AccountKey> = (0..3).map(|_i| AccountKey::random(&mut rng)).collect();
+        let config = SyntheticLedgerConfig {
+            origin_outputs_per_account: 6,
+            num_blocks: 2,
+            txs_per_block: 2,
+            max_inputs: 2,
+            max_recipients: 2,
+            max_fee_multiplier: 3,
+        };
+
+        let (_dir, ledger, summaries) = generate(&accounts, &config);
+        assert_eq!(ledger.num_blocks().unwrap(), 3);
+        assert_eq!(summaries.len(), 2);
+
+        for summary in &summaries {
+            assert_eq!(summary.transactions.len(), 2);
+            let block_contents = ledger.get_block_contents(summary.block_index).unwrap();
+
+            for tx in &summary.transactions {
+                // Value is conserved.
+                let input_value: u64 = tx.inputs.iter().map(|input| input.value).sum();
+                let output_value: u64 = tx.outputs.iter().map(|output| output.value).sum();
+                assert_eq!(input_value, output_value + tx.fee);
+
+                for input in &tx.inputs {
+                    assert!(input.ring.contains(&input.real_index));
+                    let key_image = block_contents
+                        .key_images
+                        .iter()
+                        .find(|key_image| hex::encode(key_image.as_bytes()) == input.key_image)
+                        .expect("Key image should be in the block");
+                    assert_eq!(
+                        ledger.check_key_image(key_image).unwrap(),
+                        Some(summary.block_index)
+                    );
+                }
+
+                // Each output is in the ledger, and decrypts to its value with the recipient's key.
+                for output in &tx.outputs {
+                    let tx_out = ledger.get_tx_out_by_index(output.index).unwrap();
+                    assert!(block_contents.outputs.contains(&tx_out));
+                    let shared_secret = get_tx_out_shared_secret(
+                        accounts[output.recipient].view_private_key(),
+                        &RistrettoPublic::try_from(&tx_out.public_key).unwrap(),
+                    );
+                    let (value, _blinding) = tx_out.amount.get_value(&shared_secret).unwrap();
+                    assert_eq!(value, output.value);
+                    assert_eq!(output.is_change, output.recipient == tx.sender);
+                }
+            }
+
+            let total_fee: u64 = summary.transactions.iter().map(|tx| tx.fee).sum();
+            assert_eq!(summary.total_fee, total_fee);
+            // The block holds the outputs of its transactions, and the fee output.
+            let num_outputs: usize = summary.transactions.iter().map(|tx| tx.outputs.len()).sum();
+            assert_eq!(block_contents.outputs.len(), num_outputs + 1);
+        }
+
+        let (_dir, _ledger, summaries_again) = generate(&accounts, &config);
+        assert_eq!(summaries_again, summaries);
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Parsers for command line arguments that several binaries accept.
+
+use mc_crypto_keys::Ed25519Public;
+use mc_transaction_core::{BlockVersionSchedule, NetworkParameters};
+use std::convert::TryFrom;
+
+/// Parses network parameters represented in JSON, e.g. `{"ring_size":11,"base_fee":10000000000}`.
+///
+/// Omitted values take their defaults. Parameters that fail `NetworkParameters::check` are
+/// rejected.
+pub fn parse_network_parameters_from_json(src: &str) -> Result<NetworkParameters, String> {
+    let network_parameters: NetworkParameters = serde_json::from_str(src)
+        .map_err(|err| format!("Error parsing network parameters {}: {:?}", src, err))?;
+    network_parameters
+        .check()
+        .map_err(|err| format!("Invalid network parameters {}: {}", src, err))?;
+    Ok(network_parameters)
+}
+
+/// Parses a block version schedule represented in JSON, e.g.
+/// `{"activations":[{"version":1,"block_index":1000}]}`.
+///
+/// Schedules that fail `BlockVersionSchedule::check` are rejected.
+pub fn parse_block_version_schedule_from_json(src: &str) -> Result<BlockVersionSchedule, String> {
+    let block_version_schedule: BlockVersionSchedule = serde_json::from_str(src)
+        .map_err(|err| format!("Error parsing block version schedule {}: {:?}", src, err))?;
+    block_version_schedule
+        .check()
+        .map_err(|err| format!("Invalid block version schedule {}: {}", src, err))?;
+    Ok(block_version_schedule)
+}
+
+/// Parses a hex-encoded Ed25519 public key, e.g. a trusted block signer.
+pub fn parse_ed25519_public_from_hex(src: &str) -> Result<Ed25519Public, String> {
+    let bytes =
+        hex::decode(src).map_err(|err| format!("Error decoding signer {}: {:?}", src, err))?;
+    Ed25519Public::try_from(&bytes[..])
+        .map_err(|err| format!("Invalid signer public key {}: {:?}", src, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_crypto_keys::Ed25519Pair;
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    // Omitted values should take their defaults, and unusable parameters should be rejected.
+    fn test_parse_network_parameters_from_json() {
+        let network_parameters = parse_network_parameters_from_json(r#"{"base_fee":1}"#).unwrap();
+        assert_eq!(
+            network_parameters,
+            NetworkParameters {
+                base_fee: 1,
+                ..Default::default()
+            }
+        );
+
+        assert!(parse_network_parameters_from_json(r#"{"ring_size":0}"#).is_err());
+        assert!(parse_network_parameters_from_json("not json").is_err());
+    }
+
+    #[test]
+    // Valid schedules should parse, and unusable schedules should be rejected.
+    fn test_parse_block_version_schedule_from_json() {
+        let block_version_schedule = parse_block_version_schedule_from_json(
+            r#"{"activations":[{"version":1,"block_index":10}]}"#,
+        )
+        .unwrap();
+        assert_eq!(block_version_schedule.version_at(9), 0);
+        assert_eq!(block_version_schedule.version_at(10), 1);
+
+        assert!(parse_block_version_schedule_from_json(
+            r#"{"activations":[{"version":1000,"block_index":10}]}"#
+        )
+        .is_err());
+        assert!(parse_block_version_schedule_from_json("not json").is_err());
+    }
+
+    #[test]
+    // Hex-encoded public keys should parse, and anything else should be rejected.
+    fn test_parse_ed25519_public_from_hex() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let public_key = Ed25519Pair::from_random(&mut rng).public_key();
+        let src = hex::encode(AsRef::<[u8]>::as_ref(&public_key));
+        assert_eq!(parse_ed25519_public_from_hex(&src).unwrap(), public_key);
+
+        assert!(parse_ed25519_public_from_hex("not hex").is_err());
+        assert!(parse_ed25519_public_from_hex(&src[2..]).is_err());
+    }
+}